@@ -0,0 +1,128 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Part of a day in minutes, e.g. 570-600 for 09:30 to 10:00
+ */
+export type DayPeriod = { 
+/**
+ * First minute of the period (0-1439)
+ */
+from_minute: number, 
+/**
+ * End of the period (1-1440, exclusive)
+ */
+till_minute: number, };
+
+/**
+ * Represents different types of electricity rate structures
+ */
+export type ElectricityRate = { "Fixed": { 
+/**
+ * The rate per unit of electricity
+ */
+rate: number, } } | { "Tiered": { 
+/**
+ * List of rate tiers
+ */
+tiers: Array<RateTier>, } } | { "Hourly": { 
+/**
+ * Rate per unit of electricity for each hour, starting on January 1st 0h
+ */
+rates: Array<number>, } };
+
+/**
+ * Represents a time range when a rate tier applies, with minute resolution for tariffs that
+ * switch on the half or quarter hour
+ */
+export type HourRange = { 
+/**
+ * Starting hour (0-23)
+ */
+from: number, 
+/**
+ * Starting minute within the starting hour (0-59)
+ */
+from_minute: number, 
+/**
+ * Ending hour (0-24, exclusive)
+ */
+till: number, 
+/**
+ * Ending minute within the ending hour (0-59, exclusive)
+ */
+till_minute: number, 
+/**
+ * Type of day this range applies to
+ */
+weekday_type: WeekdayType, };
+
+/**
+ * Represents a single tier in a tiered rate structure
+ */
+export type RateTier = { 
+/**
+ * Name of the tier (e.g., "Peak", "Off-Peak", "Super Off-Peak")
+ */
+name: string, 
+/**
+ * Rate per unit of electricity for this tier
+ */
+rate: number, 
+/**
+ * List of hour ranges when this tier applies
+ */
+hour_ranges: Array<HourRange>, };
+
+/**
+ * Problem in a rate structure found by `ElectricityRate::validate`
+ */
+export type RateValidationError = { "UncoveredPeriods": { 
+/**
+ * Day type with the gap
+ */
+weekday_type: WeekdayType, 
+/**
+ * Uncovered periods
+ */
+periods: Array<DayPeriod>, } } | { "OverlappingPeriods": { 
+/**
+ * Day type with the overlap
+ */
+weekday_type: WeekdayType, 
+/**
+ * Periods covered more than once
+ */
+periods: Array<DayPeriod>, 
+/**
+ * Names of the tiers covering these periods
+ */
+tiers: Array<string>, } } | { "InvalidHourRange": { 
+/**
+ * Name of the tier with the range
+ */
+tier: string, 
+/**
+ * The invalid range
+ */
+range: HourRange, } } | { "NonFiniteRate": { 
+/**
+ * Name of the tier, None for a fixed rate
+ */
+tier: string | null, } } | { "WrongHourCount": { 
+/**
+ * Number of hours in a year
+ */
+expected: number, 
+/**
+ * Number of rates given
+ */
+actual: number, } } | { "NonFiniteHourlyRates": { 
+/**
+ * Hours of the year (0-8759)
+ */
+hours: Array<number>, } };
+
+/**
+ * Represents the type of day for rate application
+ */
+export type WeekdayType = "Weekday" | "Weekend";
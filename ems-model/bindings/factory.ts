@@ -1,4 +1,7 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Line } from "./line";
+import type { Step } from "./machine";
+import type { Worker } from "./worker";
 
 export type Factory = { 
 /**
@@ -17,3 +20,24 @@ location: string,
  * The lines of the factory.
  */
 lines: Array<string>, };
+
+/**
+ * A factory together with all lines, steps and workers it references.
+ */
+export type FactoryData = { 
+/**
+ * The factory.
+ */
+factory: Factory, 
+/**
+ * The lines of the factory.
+ */
+lines: Array<Line>, 
+/**
+ * The steps referenced by the nodes of the lines.
+ */
+steps: Array<Step>, 
+/**
+ * The workers of the factory.
+ */
+workers: Array<Worker>, };
@@ -30,7 +30,7 @@ id: string,
  */
 name: string, 
 /**
- * The step ID.
+ * The id of the `Step` run by this node, resolved with `FactoryRepository::step`.
  */
 step_id: string, 
 /**
@@ -41,3 +41,20 @@ dependencies: Array<string>,
  * The steps that depend on this step.
  */
 dependents: Array<string>, };
+
+/**
+ * Timing of a node of the line for a single unit, in minutes from the start of the unit.
+ */
+export type StepTiming = { 
+/**
+ * Earliest start once all prerequisites are done.
+ */
+earliest_start: number, 
+/**
+ * Latest start that doesn't delay the unit.
+ */
+latest_start: number, 
+/**
+ * How far the step can be shifted without delaying the unit; 0 on the critical path.
+ */
+slack: number, };
@@ -1,5 +1,7 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 import type { Specialization } from "./worker";
+import type { Time } from "./worker";
+import type { WeekDay } from "./worker";
 
 /**
  * How can a machine be controlled?
@@ -7,13 +9,22 @@ import type { Specialization } from "./worker";
  */
 export type MachineControl = "Human" | "Computer";
 
+/**
+ * Planned downtime in which a machine can't run
+ */
+export type MaintenanceWindow = { "Weekly": { day: WeekDay, start: Time, end: Time, } } | { "Dated": { from: string, until: string, } };
+
 /**
  * A machine in the factory.
  *
  * for the ems it is important how flexible the machine is in terms of how it can be turned on and off,
  * for how long a run is, and how much electricity it consumes.
  */
-export type Step = { id: string, 
+export type Step = { 
+/**
+ * The id of the step, referenced by `LineNode::step_id`.
+ */
+id: string, 
 /**
  * The type of the step.
  */
@@ -37,6 +48,27 @@ control: MachineControl,
 /**
  * required specialization of the worker
  */
-required_specialization: Specialization | null, };
+required_specialization: Specialization | null, 
+/**
+ * Power consumption while the machine is switched on but idle, in W.
+ */
+standby_power: number, 
+/**
+ * Energy to switch the machine on (heating up, pressurizing, ...), in Wh.
+ */
+startup_energy: number, 
+/**
+ * Time from switching on until the machine is ready for a run, in minutes.
+ */
+startup_minutes: number, 
+/**
+ * Shortest time the machine may be switched off, in minutes. Shorter idle times are
+ * spent in standby.
+ */
+min_off_minutes: number, 
+/**
+ * Planned downtime in which the machine can't run
+ */
+maintenance: Array<MaintenanceWindow>, };
 
 export type StepType = "Machine" | "Task";
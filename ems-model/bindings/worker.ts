@@ -1,9 +1,36 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
 /**
- * Simple weekly schedule
+ * Days on which the weekly shifts of a worker don't apply
  */
-export type Schedule = { weekly_shifts: { [key in WeekDay]?: WorkShift }, };
+export type Absence = { 
+/**
+ * First day of the absence (YYYY-MM-DD)
+ */
+from: string, 
+/**
+ * Last day of the absence (YYYY-MM-DD), inclusive
+ */
+until: string, kind: AbsenceKind, 
+/**
+ * Shifts worked on every day of the absence instead of the weekly shifts; empty if the
+ * worker is away all day
+ */
+shifts: Array<WorkShift>, };
+
+/**
+ * Why a worker is absent
+ */
+export type AbsenceKind = "Vacation" | "Sick" | "Other";
+
+/**
+ * Simple weekly schedule, a day can have several shifts (e.g. a split shift)
+ */
+export type Schedule = { 
+/**
+ * Shifts of every day, sorted by start
+ */
+weekly_shifts: { [key in WeekDay]?: Array<WorkShift> }, };
 
 /**
  * What can a worker do?
@@ -23,6 +50,14 @@ export type WeekDay = "monday" | "tuesday" | "wednesday" | "thursday" | "friday"
 /**
  * Represents a work shift with start and end times
  */
-export type WorkShift = { start: Time, end: Time, };
+export type WorkShift = { start: Time, end: Time, 
+/**
+ * The shift ends at `end` on the next day, e.g. a night shift from 22:00 to 06:00.
+ */
+overnight: boolean, };
 
-export type Worker = { id: string, name: string, specialization: Array<Specialization>, schedule: Schedule, };
+export type Worker = { id: string, name: string, specialization: Array<Specialization>, schedule: Schedule, 
+/**
+ * Vacation, sick days and other days that override the weekly schedule
+ */
+absences: Array<Absence>, };
@@ -1,5 +1,13 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use utoipa::ToSchema;
+
+const MINUTES_PER_DAY: u16 = 24 * 60;
+
 /// Represents different types of electricity rate structures
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export, export_to = "./electricity.ts")]
+#[schema(example = json!({"Fixed": {"rate": 0.32}}))]
 pub enum ElectricityRate {
     /// Fixed rate for all hours
     Fixed {
@@ -11,10 +19,21 @@ pub enum ElectricityRate {
         /// List of rate tiers
         tiers: Vec<RateTier>,
     },
+    /// Dynamic rate with its own price for every hour, prices may be negative
+    Hourly {
+        /// Rate per unit of electricity for each hour, starting on January 1st 0h
+        rates: Vec<f64>,
+    },
 }
 
 /// Represents a single tier in a tiered rate structure
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export, export_to = "./electricity.ts")]
+#[schema(example = json!({
+    "name": "Peak",
+    "rate": 0.38,
+    "hour_ranges": [{"from": 8, "till": 20, "weekday_type": "Weekday"}]
+}))]
 pub struct RateTier {
     /// Name of the tier (e.g., "Peak", "Off-Peak", "Super Off-Peak")
     pub name: String,
@@ -24,19 +43,29 @@ pub struct RateTier {
     pub hour_ranges: Vec<HourRange>,
 }
 
-/// Represents a time range when a rate tier applies
-#[derive(Debug, Clone, PartialEq)]
+/// Represents a time range when a rate tier applies, with minute resolution for tariffs that
+/// switch on the half or quarter hour
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export, export_to = "./electricity.ts")]
+#[schema(example = json!({"from": 22, "from_minute": 30, "till": 6, "weekday_type": "Weekday"}))]
 pub struct HourRange {
     /// Starting hour (0-23)
     pub from: u8,
-    /// Ending hour (0-23, exclusive)
+    /// Starting minute within the starting hour (0-59)
+    #[serde(default)]
+    pub from_minute: u8,
+    /// Ending hour (0-24, exclusive)
     pub till: u8,
+    /// Ending minute within the ending hour (0-59, exclusive)
+    #[serde(default)]
+    pub till_minute: u8,
     /// Type of day this range applies to
     pub weekday_type: WeekdayType,
 }
 
 /// Represents the type of day for rate application
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export, export_to = "./electricity.ts")]
 pub enum WeekdayType {
     /// Monday through Friday
     Weekday,
@@ -55,56 +84,126 @@ impl ElectricityRate {
         Self::Tiered { tiers }
     }
 
+    /// Creates a new dynamic electricity rate from hourly prices
+    pub fn hourly(rates: Vec<f64>) -> Self {
+        Self::Hourly { rates }
+    }
+
     /// Converts the electricity rate to a vector of hourly rates for a single week
     /// Returns a Vec<f64> with 168 elements (24 hours × 7 days)
     /// The vector is organized as: [Mon 0h, Mon 1h, ..., Mon 23h, Tue 0h, ..., Sun 23h]
+    /// Hours split between tiers get the time-weighted average rate
     pub fn to_weekly_hourly_rates(&self) -> Vec<f64> {
-        let mut weekly_rates = Vec::with_capacity(168);
+        if let ElectricityRate::Hourly { rates } = self {
+            return Self::repeat_rates(rates, 168);
+        }
+        let weekday_rates = self.get_day_rates(60, WeekdayType::Weekday);
+        let weekend_rates = self.get_day_rates(60, WeekdayType::Weekend);
 
         // Days of the week: 0=Monday, 1=Tuesday, ..., 6=Sunday
+        let mut weekly_rates = Vec::with_capacity(168);
         for day in 0..7 {
-            let weekday_type = if day < 5 {
-                WeekdayType::Weekday
+            if day < 5 {
+                weekly_rates.extend_from_slice(&weekday_rates);
             } else {
-                WeekdayType::Weekend
-            };
-
-            for hour in 0..24 {
-                let rate = self.get_rate_for_hour(hour, weekday_type);
-                weekly_rates.push(rate);
+                weekly_rates.extend_from_slice(&weekend_rates);
             }
         }
 
         weekly_rates
     }
 
+    /// Converts the electricity rate to a vector of rates for a single week in steps of
+    /// `step_minutes`, e.g. 672 quarter-hour rates for a step of 15 minutes
+    /// The step has to divide an hour evenly. Steps split between tiers get the time-weighted
+    /// average rate, hourly rates are repeated for every step of their hour.
+    pub fn to_weekly_rates(&self, step_minutes: u16) -> Result<Vec<f64>, String> {
+        if step_minutes == 0 || 60 % step_minutes != 0 {
+            return Err(format!(
+                "Invalid step of {} minutes. Must divide an hour evenly",
+                step_minutes
+            ));
+        }
+        let steps_per_hour = (60 / step_minutes) as usize;
+        if let ElectricityRate::Hourly { rates } = self {
+            return Ok(Self::repeat_rates(rates, 168)
+                .into_iter()
+                .flat_map(|rate| std::iter::repeat_n(rate, steps_per_hour))
+                .collect());
+        }
+        let weekday_rates = self.get_day_rates(step_minutes, WeekdayType::Weekday);
+        let weekend_rates = self.get_day_rates(step_minutes, WeekdayType::Weekend);
+        let mut weekly_rates = Vec::with_capacity(168 * steps_per_hour);
+        for day in 0..7 {
+            if day < 5 {
+                weekly_rates.extend_from_slice(&weekday_rates);
+            } else {
+                weekly_rates.extend_from_slice(&weekend_rates);
+            }
+        }
+        Ok(weekly_rates)
+    }
+
     /// Converts the electricity rate to a vector of hourly rates for the whole year
     /// Returns a Vec<f64> with 8760 elements (24 hours × 365 days)
     /// The vector is organized as: [Jan 1 0h, Jan 1 1h, ..., Dec 31 23h]
     pub fn to_yearly_hourly_rates(&self) -> Vec<f64> {
+        if let ElectricityRate::Hourly { rates } = self {
+            return Self::repeat_rates(rates, 8760);
+        }
+        let weekday_rates = self.get_day_rates(60, WeekdayType::Weekday);
+        let weekend_rates = self.get_day_rates(60, WeekdayType::Weekend);
         let mut yearly_rates = Vec::with_capacity(8760);
 
         // Generate rates for each day of the year
         for day_of_year in 0..365 {
-            let weekday_type = self.get_weekday_type_for_day_of_year(day_of_year);
-
-            for hour in 0..24 {
-                let rate = self.get_rate_for_hour(hour, weekday_type);
-                yearly_rates.push(rate);
+            match self.get_weekday_type_for_day_of_year(day_of_year) {
+                WeekdayType::Weekday => yearly_rates.extend_from_slice(&weekday_rates),
+                WeekdayType::Weekend => yearly_rates.extend_from_slice(&weekend_rates),
             }
         }
 
         yearly_rates
     }
 
-    /// Gets the rate for a specific hour and day type
-    fn get_rate_for_hour(&self, hour: u8, weekday_type: WeekdayType) -> f64 {
+    /// Repeats hourly rates to `num_hours` values, an empty series gives 0.0
+    fn repeat_rates(rates: &[f64], num_hours: usize) -> Vec<f64> {
+        if rates.is_empty() {
+            return vec![0.0; num_hours];
+        }
+        rates.iter().copied().cycle().take(num_hours).collect()
+    }
+
+    /// Average rates of one day of the given type in steps of `step_minutes`
+    fn get_day_rates(&self, step_minutes: u16, weekday_type: WeekdayType) -> Vec<f64> {
+        (0..MINUTES_PER_DAY)
+            .step_by(step_minutes as usize)
+            .map(|start| {
+                let rates: Vec<f64> = (start..start + step_minutes)
+                    .map(|minute| self.get_rate_for_minute(minute, weekday_type))
+                    .collect();
+                // Keep the exact rate if the step is not split between tiers
+                if rates.iter().all(|&rate| rate == rates[0]) {
+                    rates[0]
+                } else {
+                    rates.iter().sum::<f64>() / rates.len() as f64
+                }
+            })
+            .collect()
+    }
+
+    /// Gets the rate for a specific minute of the day (0-1439) and day type
+    fn get_rate_for_minute(&self, minute: u16, weekday_type: WeekdayType) -> f64 {
         match self {
             ElectricityRate::Fixed { rate } => *rate,
+            // Rate of that hour on the first day
+            ElectricityRate::Hourly { rates } => {
+                rates.get((minute / 60) as usize).copied().unwrap_or(0.0)
+            }
             ElectricityRate::Tiered { tiers } => {
-                // Find the first tier that matches this hour and day type
+                // Find the first tier that matches this minute and day type
                 for tier in tiers {
-                    if tier.matches_hour(hour, weekday_type) {
+                    if tier.matches_minute(minute, weekday_type) {
                         return tier.rate;
                     }
                 }
@@ -130,83 +229,253 @@ impl ElectricityRate {
     /// Validates that all weekend and weekday hours are covered exactly once
     /// Returns true if the rate structure is valid, false otherwise
     pub fn is_valid(&self) -> bool {
+        self.validate().is_ok()
+    }
+
+    /// Validates the rate structure and reports every problem found, e.g. the hours a tiered
+    /// rate leaves uncovered or covers twice per day type
+    pub fn validate(&self) -> Result<(), Vec<RateValidationError>> {
+        let mut errors = Vec::new();
         match self {
-            ElectricityRate::Fixed { .. } => {
-                // Fixed rates are always valid as they cover all hours
-                true
+            ElectricityRate::Fixed { rate } => {
+                if !rate.is_finite() {
+                    errors.push(RateValidationError::NonFiniteRate { tier: None });
+                }
             }
             ElectricityRate::Tiered { tiers } => {
-                // Check if all hours (0-23) are covered exactly once for both weekday types
-                self.validate_weekday_coverage(tiers) && self.validate_weekend_coverage(tiers)
+                for tier in tiers {
+                    if !tier.rate.is_finite() {
+                        errors.push(RateValidationError::NonFiniteRate {
+                            tier: Some(tier.name.clone()),
+                        });
+                    }
+                    for range in &tier.hour_ranges {
+                        if !range.is_within_day() {
+                            errors.push(RateValidationError::InvalidHourRange {
+                                tier: tier.name.clone(),
+                                range: range.clone(),
+                            });
+                        }
+                    }
+                }
+                for weekday_type in [WeekdayType::Weekday, WeekdayType::Weekend] {
+                    Self::validate_coverage(tiers, weekday_type, &mut errors);
+                }
             }
+            ElectricityRate::Hourly { rates } => {
+                // One finite rate for every hour of the year
+                if rates.len() != 8760 {
+                    errors.push(RateValidationError::WrongHourCount {
+                        expected: 8760,
+                        actual: rates.len(),
+                    });
+                }
+                let hours: Vec<usize> = (0..rates.len())
+                    .filter(|&hour| !rates[hour].is_finite())
+                    .collect();
+                if !hours.is_empty() {
+                    errors.push(RateValidationError::NonFiniteHourlyRates { hours });
+                }
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
         }
     }
 
-    /// Validates that all weekday hours (0-23) are covered exactly once
-    fn validate_weekday_coverage(&self, tiers: &[RateTier]) -> bool {
-        let mut covered_hours = [false; 24];
+    /// Checks that every minute of a day type is covered by exactly one tier
+    fn validate_coverage(
+        tiers: &[RateTier],
+        weekday_type: WeekdayType,
+        errors: &mut Vec<RateValidationError>,
+    ) {
+        let covering_tiers: Vec<Vec<&str>> = (0..MINUTES_PER_DAY)
+            .map(|minute| {
+                tiers
+                    .iter()
+                    .flat_map(|tier| {
+                        tier.hour_ranges
+                            .iter()
+                            .filter(move |range| range.matches_minute(minute, weekday_type))
+                            .map(|_| tier.name.as_str())
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let uncovered = DayPeriod::collect(|minute| covering_tiers[minute].is_empty());
+        if !uncovered.is_empty() {
+            errors.push(RateValidationError::UncoveredPeriods {
+                weekday_type,
+                periods: uncovered,
+            });
+        }
 
-        for tier in tiers {
-            for hour_range in &tier.hour_ranges {
-                if hour_range.weekday_type == WeekdayType::Weekday
-                    && !self.mark_hours_covered(&mut covered_hours, hour_range)
-                {
-                    return false; // Overlapping hours detected
+        let overlapping = DayPeriod::collect(|minute| covering_tiers[minute].len() > 1);
+        if !overlapping.is_empty() {
+            let mut tier_names: Vec<String> = Vec::new();
+            for names in covering_tiers.iter().filter(|names| names.len() > 1) {
+                for name in names {
+                    if !tier_names.iter().any(|known| known == name) {
+                        tier_names.push(name.to_string());
+                    }
                 }
             }
+            errors.push(RateValidationError::OverlappingPeriods {
+                weekday_type,
+                periods: overlapping,
+                tiers: tier_names,
+            });
         }
-
-        // Check if all hours are covered
-        covered_hours.iter().all(|&covered| covered)
     }
+}
 
-    /// Validates that all weekend hours (0-23) are covered exactly once
-    fn validate_weekend_coverage(&self, tiers: &[RateTier]) -> bool {
-        let mut covered_hours = [false; 24];
+/// Part of a day in minutes, e.g. 570-600 for 09:30 to 10:00
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export, export_to = "./electricity.ts")]
+pub struct DayPeriod {
+    /// First minute of the period (0-1439)
+    pub from_minute: u16,
+    /// End of the period (1-1440, exclusive)
+    pub till_minute: u16,
+}
 
-        for tier in tiers {
-            for hour_range in &tier.hour_ranges {
-                if hour_range.weekday_type == WeekdayType::Weekend
-                    && !self.mark_hours_covered(&mut covered_hours, hour_range)
-                {
-                    return false; // Overlapping hours detected
-                }
+impl DayPeriod {
+    /// Contiguous periods of the minutes of a day for which `is_included` is true
+    fn collect(is_included: impl Fn(usize) -> bool) -> Vec<Self> {
+        let mut periods: Vec<Self> = Vec::new();
+        for minute in (0..MINUTES_PER_DAY).filter(|&minute| is_included(minute as usize)) {
+            match periods.last_mut() {
+                Some(period) if period.till_minute == minute => period.till_minute += 1,
+                _ => periods.push(Self {
+                    from_minute: minute,
+                    till_minute: minute + 1,
+                }),
             }
         }
+        periods
+    }
+}
 
-        // Check if all hours are covered
-        covered_hours.iter().all(|&covered| covered)
+impl std::fmt::Display for DayPeriod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:02}:{:02}-{:02}:{:02}",
+            self.from_minute / 60,
+            self.from_minute % 60,
+            self.till_minute / 60,
+            self.till_minute % 60
+        )
     }
+}
 
-    /// Marks hours as covered in the given array and returns false if any overlap is detected
-    fn mark_hours_covered(&self, covered_hours: &mut [bool; 24], hour_range: &HourRange) -> bool {
-        if hour_range.from > hour_range.till {
-            // Wrapping range (e.g., 22:00 to 06:00)
-            for hour in hour_range.from..24 {
-                if covered_hours[hour as usize] {
-                    return false; // Overlap detected
-                }
-                covered_hours[hour as usize] = true;
-            }
-            for hour in 0..hour_range.till {
-                if covered_hours[hour as usize] {
-                    return false; // Overlap detected
-                }
-                covered_hours[hour as usize] = true;
+/// Problem in a rate structure found by `ElectricityRate::validate`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export, export_to = "./electricity.ts")]
+pub enum RateValidationError {
+    /// Times of a day type no tier applies to
+    UncoveredPeriods {
+        /// Day type with the gap
+        weekday_type: WeekdayType,
+        /// Uncovered periods
+        periods: Vec<DayPeriod>,
+    },
+    /// Times of a day type more than one tier applies to
+    OverlappingPeriods {
+        /// Day type with the overlap
+        weekday_type: WeekdayType,
+        /// Periods covered more than once
+        periods: Vec<DayPeriod>,
+        /// Names of the tiers covering these periods
+        tiers: Vec<String>,
+    },
+    /// Range outside of 00:00-24:00
+    InvalidHourRange {
+        /// Name of the tier with the range
+        tier: String,
+        /// The invalid range
+        range: HourRange,
+    },
+    /// Rate that is NaN or infinite
+    NonFiniteRate {
+        /// Name of the tier, None for a fixed rate
+        tier: Option<String>,
+    },
+    /// Hourly rate series of the wrong length
+    WrongHourCount {
+        /// Number of hours in a year
+        expected: usize,
+        /// Number of rates given
+        actual: usize,
+    },
+    /// Hours of an hourly rate series that are NaN or infinite
+    NonFiniteHourlyRates {
+        /// Hours of the year (0-8759)
+        hours: Vec<usize>,
+    },
+}
+
+impl std::fmt::Display for RateValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let period_list = |periods: &[DayPeriod]| {
+            periods
+                .iter()
+                .map(|period| period.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match self {
+            RateValidationError::UncoveredPeriods {
+                weekday_type,
+                periods,
+            } => write!(
+                f,
+                "{:?} times {} are not covered by any tier",
+                weekday_type,
+                period_list(periods)
+            ),
+            RateValidationError::OverlappingPeriods {
+                weekday_type,
+                periods,
+                tiers,
+            } => write!(
+                f,
+                "{:?} times {} are covered more than once by tiers {}",
+                weekday_type,
+                period_list(periods),
+                tiers.join(", ")
+            ),
+            RateValidationError::InvalidHourRange { tier, range } => write!(
+                f,
+                "Tier {} has the invalid range {:02}:{:02}-{:02}:{:02}, times must be within 00:00-24:00",
+                tier, range.from, range.from_minute, range.till, range.till_minute
+            ),
+            RateValidationError::NonFiniteRate { tier: Some(tier) } => {
+                write!(f, "Tier {} has a rate that is not a finite number", tier)
             }
-        } else {
-            // Normal range (e.g., 09:00 to 17:00)
-            for hour in hour_range.from..hour_range.till {
-                if covered_hours[hour as usize] {
-                    return false; // Overlap detected
-                }
-                covered_hours[hour as usize] = true;
+            RateValidationError::NonFiniteRate { tier: None } => {
+                write!(f, "The rate is not a finite number")
             }
+            RateValidationError::WrongHourCount { expected, actual } => write!(
+                f,
+                "Hourly rates have {} values, expected {}",
+                actual, expected
+            ),
+            RateValidationError::NonFiniteHourlyRates { hours } => write!(
+                f,
+                "{} hourly rates are not finite numbers, first at hour {}",
+                hours.len(),
+                hours[0]
+            ),
         }
-        true
     }
 }
 
+impl std::error::Error for RateValidationError {}
+
 impl RateTier {
     /// Creates a new rate tier
     pub fn new(name: String, rate: f64, hour_ranges: Vec<HourRange>) -> Self {
@@ -223,32 +492,76 @@ impl RateTier {
             .iter()
             .any(|range| range.matches_hour(hour, weekday_type))
     }
+
+    /// Checks if this tier applies to the given minute of the day (0-1439) and day type
+    pub fn matches_minute(&self, minute: u16, weekday_type: WeekdayType) -> bool {
+        self.hour_ranges
+            .iter()
+            .any(|range| range.matches_minute(minute, weekday_type))
+    }
 }
 
 impl HourRange {
     /// Creates a new hour range
     pub fn new(from: u8, till: u8, weekday_type: WeekdayType) -> Self {
+        Self::with_minutes(from, 0, till, 0, weekday_type)
+    }
+
+    /// Creates a new range starting at `from:from_minute` and ending at `till:till_minute`
+    pub fn with_minutes(
+        from: u8,
+        from_minute: u8,
+        till: u8,
+        till_minute: u8,
+        weekday_type: WeekdayType,
+    ) -> Self {
         Self {
             from,
+            from_minute,
             till,
+            till_minute,
             weekday_type,
         }
     }
 
-    /// Checks if this hour range matches the given hour and day type
+    /// First minute of the day in the range
+    pub fn start_minute(&self) -> u16 {
+        self.from as u16 * 60 + self.from_minute as u16
+    }
+
+    /// Minute of the day the range ends at (exclusive)
+    pub fn end_minute(&self) -> u16 {
+        self.till as u16 * 60 + self.till_minute as u16
+    }
+
+    /// Whether start and end are within 00:00 and 24:00
+    pub fn is_within_day(&self) -> bool {
+        self.from < 24
+            && self.from_minute < 60
+            && self.till_minute < 60
+            && self.end_minute() <= MINUTES_PER_DAY
+    }
+
+    /// Checks if this hour range matches the start of the given hour and day type
     pub fn matches_hour(&self, hour: u8, weekday_type: WeekdayType) -> bool {
+        self.matches_minute(hour as u16 * 60, weekday_type)
+    }
+
+    /// Checks if this range matches the given minute of the day (0-1439) and day type
+    pub fn matches_minute(&self, minute: u16, weekday_type: WeekdayType) -> bool {
         // First check if the weekday type matches
         if self.weekday_type != weekday_type {
             return false;
         }
 
+        let (start, end) = (self.start_minute(), self.end_minute());
         // Handle the case where the range wraps around midnight (e.g., 22:00 to 06:00)
-        if self.from > self.till {
+        if start > end {
             // Wrapping range: from > till (e.g., 22:00 to 06:00)
-            hour >= self.from || hour < self.till
+            minute >= start || minute < end
         } else {
             // Normal range: from <= till (e.g., 09:00 to 17:00)
-            hour >= self.from && hour < self.till
+            minute >= start && minute < end
         }
     }
 }
@@ -348,7 +661,7 @@ mod tests {
         for day in 0..5 {
             for hour in 0..24 {
                 let index = day * 24 + hour;
-                if hour >= 9 && hour < 17 {
+                if (9..17).contains(&hour) {
                     // Peak hours
                     assert_eq!(weekly_rates[index], 0.25);
                 } else {
@@ -576,4 +889,206 @@ mod tests {
         let rate = ElectricityRate::tiered(vec![weekday_peak, weekday_off_peak, weekend_rate]);
         assert!(rate.is_valid());
     }
+
+    #[test]
+    fn test_hourly_rate() {
+        let rates: Vec<f64> = (0..8760)
+            .map(|hour| (hour % 24) as f64 * 0.01 - 0.05)
+            .collect();
+        let rate = ElectricityRate::hourly(rates.clone());
+        assert!(rate.is_valid());
+        assert_eq!(rate.to_yearly_hourly_rates(), rates);
+        assert_eq!(rate.to_weekly_hourly_rates(), rates[..168].to_vec());
+        assert!(rate.to_yearly_hourly_rates()[2] < 0.0); // negative prices are kept
+
+        // shorter series repeat
+        let rate = ElectricityRate::hourly(vec![0.1, 0.2]);
+        assert!(!rate.is_valid());
+        assert_eq!(rate.to_yearly_hourly_rates().len(), 8760);
+        assert_eq!(rate.to_yearly_hourly_rates()[3], 0.2);
+        assert!(!ElectricityRate::hourly(vec![f64::NAN; 8760]).is_valid());
+    }
+
+    #[test]
+    fn test_serde_representation() {
+        let rate = ElectricityRate::tiered(vec![RateTier::new(
+            "Night".to_string(),
+            0.2,
+            vec![HourRange::new(22, 6, WeekdayType::Weekend)],
+        )]);
+        let json = serde_json::to_value(&rate).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"Tiered": {"tiers": [{
+                "name": "Night",
+                "rate": 0.2,
+                "hour_ranges": [{
+                    "from": 22,
+                    "from_minute": 0,
+                    "till": 6,
+                    "till_minute": 0,
+                    "weekday_type": "Weekend"
+                }]
+            }]}})
+        );
+        let parsed: ElectricityRate = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, rate);
+
+        let fixed: ElectricityRate = serde_json::from_str(r#"{"Fixed": {"rate": 0.32}}"#).unwrap();
+        assert_eq!(fixed, ElectricityRate::fixed(0.32));
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let peak = RateTier::new(
+            "Peak".to_string(),
+            0.25,
+            vec![HourRange::new(9, 17, WeekdayType::Weekday)],
+        );
+        let shoulder = RateTier::new(
+            "Shoulder".to_string(),
+            0.15,
+            vec![
+                HourRange::new(15, 20, WeekdayType::Weekday),
+                HourRange::new(0, 24, WeekdayType::Weekend),
+            ],
+        );
+        let off_peak = RateTier::new(
+            "Off-Peak".to_string(),
+            0.08,
+            vec![HourRange::new(21, 9, WeekdayType::Weekday)],
+        );
+        let errors = ElectricityRate::tiered(vec![peak, shoulder, off_peak])
+            .validate()
+            .unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                RateValidationError::UncoveredPeriods {
+                    weekday_type: WeekdayType::Weekday,
+                    periods: vec![DayPeriod {
+                        from_minute: 20 * 60,
+                        till_minute: 21 * 60,
+                    }],
+                },
+                RateValidationError::OverlappingPeriods {
+                    weekday_type: WeekdayType::Weekday,
+                    periods: vec![DayPeriod {
+                        from_minute: 15 * 60,
+                        till_minute: 17 * 60,
+                    }],
+                    tiers: vec!["Peak".to_string(), "Shoulder".to_string()],
+                },
+            ]
+        );
+        assert_eq!(
+            errors[1].to_string(),
+            "Weekday times 15:00-17:00 are covered more than once by tiers Peak, Shoulder"
+        );
+
+        let invalid = RateTier::new(
+            "Broken".to_string(),
+            f64::NAN,
+            vec![
+                HourRange::new(0, 25, WeekdayType::Weekday),
+                HourRange::new(0, 24, WeekdayType::Weekend),
+            ],
+        );
+        let errors = ElectricityRate::tiered(vec![invalid])
+            .validate()
+            .unwrap_err();
+        assert!(errors.contains(&RateValidationError::NonFiniteRate {
+            tier: Some("Broken".to_string())
+        }));
+        assert!(errors.contains(&RateValidationError::InvalidHourRange {
+            tier: "Broken".to_string(),
+            range: HourRange::new(0, 25, WeekdayType::Weekday),
+        }));
+
+        let mut rates = vec![0.1; 100];
+        rates[42] = f64::INFINITY;
+        assert_eq!(
+            ElectricityRate::hourly(rates).validate().unwrap_err(),
+            vec![
+                RateValidationError::WrongHourCount {
+                    expected: 8760,
+                    actual: 100,
+                },
+                RateValidationError::NonFiniteHourlyRates { hours: vec![42] },
+            ]
+        );
+        assert!(ElectricityRate::fixed(0.3).validate().is_ok());
+    }
+
+    #[test]
+    fn test_half_hour_tiers() {
+        // Peak from 08:30 to 18:15 on weekdays, off-peak otherwise
+        let peak = RateTier::new(
+            "Peak".to_string(),
+            0.30,
+            vec![HourRange::with_minutes(8, 30, 18, 15, WeekdayType::Weekday)],
+        );
+        let off_peak = RateTier::new(
+            "Off-Peak".to_string(),
+            0.10,
+            vec![
+                HourRange::with_minutes(18, 15, 8, 30, WeekdayType::Weekday),
+                HourRange::new(0, 24, WeekdayType::Weekend),
+            ],
+        );
+        let rate = ElectricityRate::tiered(vec![peak, off_peak]);
+        assert!(rate.is_valid());
+
+        // Hours split between tiers get the time-weighted average
+        let hourly = rate.to_weekly_hourly_rates();
+        assert!((hourly[8] - 0.20).abs() < 1e-12);
+        assert!((hourly[9] - 0.30).abs() < 1e-12);
+        assert!((hourly[18] - 0.15).abs() < 1e-12);
+        assert_eq!(rate.to_yearly_hourly_rates()[..168], hourly[..]);
+
+        let quarter_hourly = rate.to_weekly_rates(15).unwrap();
+        assert_eq!(quarter_hourly.len(), 672);
+        assert_eq!(quarter_hourly[8 * 4 + 1], 0.10); // 08:15
+        assert_eq!(quarter_hourly[8 * 4 + 2], 0.30); // 08:30
+        assert_eq!(quarter_hourly[18 * 4 + 1], 0.10); // 18:15
+        assert_eq!(rate.to_weekly_rates(60).unwrap(), hourly);
+        assert!(rate.to_weekly_rates(7).is_err());
+        assert_eq!(
+            ElectricityRate::hourly(vec![0.1, 0.2])
+                .to_weekly_rates(30)
+                .unwrap()[..4],
+            [0.1, 0.1, 0.2, 0.2]
+        );
+
+        // A quarter hour gap is reported exactly
+        let gap = ElectricityRate::tiered(vec![
+            RateTier::new(
+                "Day".to_string(),
+                0.3,
+                vec![HourRange::with_minutes(6, 0, 22, 0, WeekdayType::Weekday)],
+            ),
+            RateTier::new(
+                "Night".to_string(),
+                0.1,
+                vec![
+                    HourRange::with_minutes(22, 15, 6, 0, WeekdayType::Weekday),
+                    HourRange::new(0, 24, WeekdayType::Weekend),
+                ],
+            ),
+        ]);
+        let errors = gap.validate().unwrap_err();
+        assert_eq!(
+            errors[0].to_string(),
+            "Weekday times 22:00-22:15 are not covered by any tier"
+        );
+
+        let invalid = HourRange::with_minutes(24, 0, 24, 30, WeekdayType::Weekday);
+        assert!(!invalid.is_within_day());
+        assert!(HourRange::with_minutes(23, 45, 24, 0, WeekdayType::Weekday).is_within_day());
+
+        // Whole-hour ranges keep their JSON form
+        let range: HourRange =
+            serde_json::from_str(r#"{"from": 22, "till": 6, "weekday_type": "Weekday"}"#).unwrap();
+        assert_eq!(range, HourRange::new(22, 6, WeekdayType::Weekday));
+    }
 }
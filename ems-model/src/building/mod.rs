@@ -1,2 +1,3 @@
 pub mod electricity;
 pub mod insulation;
+pub mod tariff;
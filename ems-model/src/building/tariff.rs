@@ -0,0 +1,483 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use super::electricity::{ElectricityRate, HourRange, RateTier, WeekdayType};
+
+/// Electricity tariff in the exchange format maintained outside of the code, e.g. scraped from
+/// comparison sites
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tariff {
+    /// Name of the tariff
+    pub name: String,
+    /// Supplier offering the tariff
+    #[serde(default)]
+    pub provider: Option<String>,
+    /// First day the tariff applies (YYYY-MM-DD)
+    #[serde(default)]
+    pub valid_from: Option<String>,
+    /// Last day the tariff applies (YYYY-MM-DD)
+    #[serde(default)]
+    pub valid_until: Option<String>,
+    /// Energy price before fees and taxes
+    pub energy: TariffEnergyPrice,
+    /// Fees on top of the energy price
+    #[serde(default)]
+    pub fees: Vec<TariffFee>,
+    /// Taxes applied to the energy price and all fees
+    #[serde(default)]
+    pub taxes: Vec<TariffTax>,
+}
+
+/// Energy price of a tariff per kWh
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TariffEnergyPrice {
+    /// Same price in every hour
+    Fixed {
+        /// Price per kWh
+        rate: f64,
+    },
+    /// Time-of-use tiers, every hour of weekdays and weekends covered exactly once
+    TimeOfUse {
+        /// Price tiers
+        tiers: Vec<TariffTier>,
+    },
+}
+
+/// Time-of-use tier of a tariff
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TariffTier {
+    /// Name of the tier (e.g., "Peak", "Off-Peak")
+    pub name: String,
+    /// Price per kWh
+    pub rate: f64,
+    /// Hours the tier applies
+    pub periods: Vec<TariffPeriod>,
+}
+
+/// Hours of a tier, `till` is exclusive and may be smaller than `from` to wrap around midnight
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TariffPeriod {
+    /// Starting hour (0-23)
+    pub from: u8,
+    /// Starting minute within the starting hour (0-59), e.g. 30 for a half-hour boundary
+    #[serde(default)]
+    pub from_minute: u8,
+    /// Ending hour (0-24, exclusive)
+    pub till: u8,
+    /// Ending minute within the ending hour (0-59)
+    #[serde(default)]
+    pub till_minute: u8,
+    /// Days the period applies
+    #[serde(default)]
+    pub days: TariffDays,
+}
+
+/// Days a tariff period applies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TariffDays {
+    /// Every day of the week
+    #[default]
+    All,
+    /// Monday through Friday
+    Weekday,
+    /// Saturday and Sunday
+    Weekend,
+}
+
+/// Fee of a tariff
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TariffFee {
+    /// Name of the fee (e.g., "Network fee", "Base fee")
+    pub name: String,
+    /// Amount per unit
+    pub amount: f64,
+    /// What the amount is charged for
+    pub unit: FeeUnit,
+}
+
+/// Billing unit of a tariff fee
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeUnit {
+    /// Per kWh imported, added to the energy price
+    PerKwh,
+    /// Fixed amount per month
+    PerMonth,
+    /// Fixed amount per year
+    PerYear,
+}
+
+/// Tax of a tariff
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TariffTax {
+    /// Name of the tax (e.g., "VAT")
+    pub name: String,
+    /// Tax rate in percent
+    pub percent: f64,
+}
+
+impl Tariff {
+    /// Parses a tariff from JSON and validates it
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let tariff: Self =
+            serde_json::from_str(json).map_err(|e| format!("Invalid tariff JSON: {}", e))?;
+        tariff.validate()?;
+        Ok(tariff)
+    }
+
+    /// Reads a tariff from a JSON file and validates it
+    pub fn from_json_file(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read tariff file {}: {}", path.display(), e))?;
+        Self::from_json(&json).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Checks the dates, amounts and the rate, see `ElectricityRate::validate`
+    pub fn validate(&self) -> Result<(), String> {
+        for date in [&self.valid_from, &self.valid_until].into_iter().flatten() {
+            if !is_iso_date(date) {
+                return Err(format!("Invalid date: {}. Expected YYYY-MM-DD", date));
+            }
+        }
+        if let (Some(from), Some(until)) = (&self.valid_from, &self.valid_until) {
+            if from > until {
+                return Err(format!(
+                    "valid_from {} is after valid_until {}",
+                    from, until
+                ));
+            }
+        }
+        let amounts = self
+            .fees
+            .iter()
+            .map(|fee| fee.amount)
+            .chain(self.taxes.iter().map(|tax| tax.percent));
+        if let Some(amount) = amounts.into_iter().find(|amount| !amount.is_finite()) {
+            return Err(format!("Invalid fee or tax amount: {}", amount));
+        }
+        self.to_electricity_rate().validate().map_err(|errors| {
+            errors
+                .iter()
+                .map(|error| error.to_string())
+                .collect::<Vec<_>>()
+                .join("; ")
+        })
+    }
+
+    /// Factor applied to prices for all taxes, e.g. 1.19 for 19 % VAT
+    pub fn tax_factor(&self) -> f64 {
+        1.0 + self.taxes.iter().map(|tax| tax.percent).sum::<f64>() / 100.0
+    }
+
+    /// Per-kWh fees before taxes
+    pub fn energy_fees_per_kwh(&self) -> f64 {
+        self.fees_of(FeeUnit::PerKwh)
+    }
+
+    /// Monthly and yearly fixed fees of one year, including taxes
+    pub fn annual_fixed_cost(&self) -> f64 {
+        (self.fees_of(FeeUnit::PerMonth) * 12.0 + self.fees_of(FeeUnit::PerYear))
+            * self.tax_factor()
+    }
+
+    /// Import price per kWh including per-kWh fees and taxes, the fixed fees are left out
+    pub fn to_electricity_rate(&self) -> ElectricityRate {
+        let price = |rate: f64| (rate + self.energy_fees_per_kwh()) * self.tax_factor();
+        match &self.energy {
+            TariffEnergyPrice::Fixed { rate } => ElectricityRate::fixed(price(*rate)),
+            TariffEnergyPrice::TimeOfUse { tiers } => ElectricityRate::tiered(
+                tiers
+                    .iter()
+                    .map(|tier| {
+                        let hour_ranges = tier
+                            .periods
+                            .iter()
+                            .flat_map(|period| {
+                                period.days.weekday_types().iter().map(|&weekday_type| {
+                                    HourRange::with_minutes(
+                                        period.from,
+                                        period.from_minute,
+                                        period.till,
+                                        period.till_minute,
+                                        weekday_type,
+                                    )
+                                })
+                            })
+                            .collect();
+                        RateTier::new(tier.name.clone(), price(tier.rate), hour_ranges)
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Whether the tariff applies on `date` (YYYY-MM-DD); open ends apply to every date
+    pub fn is_valid_on(&self, date: &str) -> bool {
+        self.valid_from.as_deref().is_none_or(|from| from <= date)
+            && self
+                .valid_until
+                .as_deref()
+                .is_none_or(|until| date <= until)
+    }
+
+    fn fees_of(&self, unit: FeeUnit) -> f64 {
+        self.fees
+            .iter()
+            .filter(|fee| fee.unit == unit)
+            .map(|fee| fee.amount)
+            .sum()
+    }
+}
+
+impl TariffDays {
+    fn weekday_types(self) -> &'static [WeekdayType] {
+        match self {
+            TariffDays::All => &[WeekdayType::Weekday, WeekdayType::Weekend],
+            TariffDays::Weekday => &[WeekdayType::Weekday],
+            TariffDays::Weekend => &[WeekdayType::Weekend],
+        }
+    }
+}
+
+impl ElectricityRate {
+    /// Reads a tariff JSON file (see `Tariff`) and returns its import price including per-kWh
+    /// fees and taxes
+    pub fn from_json_file(path: impl AsRef<Path>) -> Result<Self, String> {
+        Ok(Tariff::from_json_file(path)?.to_electricity_rate())
+    }
+}
+
+/// Import price built from components, e.g. a dynamic spot price plus fixed fees plus VAT
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TariffStack {
+    /// Components in the order they are applied
+    pub components: Vec<TariffComponent>,
+}
+
+/// Component of a `TariffStack`
+#[derive(Debug, Clone, PartialEq)]
+pub enum TariffComponent {
+    /// Price per kWh added to the total, fixed or time-dependent (e.g. spot price, network fee)
+    Price {
+        /// Name of the component
+        name: String,
+        /// Price per kWh of the component
+        rate: ElectricityRate,
+    },
+    /// Percentage on the sum of all components before it (e.g. VAT)
+    Percentage {
+        /// Name of the component
+        name: String,
+        /// Percentage added to the total
+        percent: f64,
+    },
+}
+
+impl TariffStack {
+    /// Creates an empty stack
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a price per kWh, e.g. `ElectricityRate::fixed(0.08)` for a network fee
+    pub fn with_price(mut self, name: &str, rate: ElectricityRate) -> Self {
+        self.components.push(TariffComponent::Price {
+            name: name.to_string(),
+            rate,
+        });
+        self
+    }
+
+    /// Adds a percentage on the components added so far, e.g. 21.0 for VAT
+    pub fn with_percentage(mut self, name: &str, percent: f64) -> Self {
+        self.components.push(TariffComponent::Percentage {
+            name: name.to_string(),
+            percent,
+        });
+        self
+    }
+
+    /// Flattens the stack to 8760 hourly prices starting on January 1st 0h
+    pub fn to_yearly_hourly_rates(&self) -> Vec<f64> {
+        let mut total = vec![0.0; 8760];
+        for component in &self.components {
+            match component {
+                TariffComponent::Price { rate, .. } => {
+                    for (value, price) in total.iter_mut().zip(rate.to_yearly_hourly_rates()) {
+                        *value += price;
+                    }
+                }
+                TariffComponent::Percentage { percent, .. } => {
+                    for value in total.iter_mut() {
+                        *value *= 1.0 + percent / 100.0;
+                    }
+                }
+            }
+        }
+        total
+    }
+
+    /// Hourly price added by each component, in the order of the stack. The rows sum up to
+    /// `to_yearly_hourly_rates`.
+    pub fn yearly_breakdown(&self) -> Vec<(String, Vec<f64>)> {
+        let mut total = vec![0.0; 8760];
+        let mut breakdown = Vec::with_capacity(self.components.len());
+        for component in &self.components {
+            let (name, added): (&str, Vec<f64>) = match component {
+                TariffComponent::Price { name, rate } => (name, rate.to_yearly_hourly_rates()),
+                TariffComponent::Percentage { name, percent } => (
+                    name,
+                    total.iter().map(|value| value * percent / 100.0).collect(),
+                ),
+            };
+            for (value, added) in total.iter_mut().zip(&added) {
+                *value += added;
+            }
+            breakdown.push((name.to_string(), added));
+        }
+        breakdown
+    }
+
+    /// The stack as an hourly rate for the optimization
+    pub fn to_electricity_rate(&self) -> ElectricityRate {
+        ElectricityRate::hourly(self.to_yearly_hourly_rates())
+    }
+}
+
+/// YYYY-MM-DD with a month of 1-12 and a day of 1-31
+fn is_iso_date(date: &str) -> bool {
+    let parts: Vec<&str> = date.split('-').collect();
+    let [year, month, day] = parts.as_slice() else {
+        return false;
+    };
+    let number = |part: &str, len: usize| {
+        (part.len() == len && part.chars().all(|c| c.is_ascii_digit()))
+            .then(|| part.parse::<u32>().ok())
+            .flatten()
+    };
+    number(year, 4).is_some()
+        && number(month, 2).is_some_and(|month| (1..=12).contains(&month))
+        && number(day, 2).is_some_and(|day| (1..=31).contains(&day))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOU_TARIFF: &str = r#"{
+        "name": "Night Saver",
+        "provider": "Example Energy",
+        "valid_from": "2025-01-01",
+        "valid_until": "2025-12-31",
+        "energy": {
+            "type": "time_of_use",
+            "tiers": [
+                {"name": "Peak", "rate": 0.30, "periods": [{"from": 7, "till": 22, "days": "weekday"}]},
+                {"name": "Off-Peak", "rate": 0.20, "periods": [
+                    {"from": 22, "till": 7, "days": "weekday"},
+                    {"from": 0, "till": 24, "days": "weekend"}
+                ]}
+            ]
+        },
+        "fees": [
+            {"name": "Network fee", "amount": 0.10, "unit": "per_kwh"},
+            {"name": "Base fee", "amount": 10.0, "unit": "per_month"},
+            {"name": "Meter", "amount": 30.0, "unit": "per_year"}
+        ],
+        "taxes": [{"name": "VAT", "percent": 20.0}]
+    }"#;
+
+    #[test]
+    fn test_time_of_use_tariff() {
+        let tariff = Tariff::from_json(TOU_TARIFF).unwrap();
+        assert_eq!(tariff.provider.as_deref(), Some("Example Energy"));
+        assert!((tariff.annual_fixed_cost() - 150.0 * 1.2).abs() < 1e-9);
+
+        let rates = tariff.to_electricity_rate().to_weekly_hourly_rates();
+        assert!((rates[8] - 0.48).abs() < 1e-9); // Monday 8h: (0.30 + 0.10) * 1.2
+        assert!((rates[3] - 0.36).abs() < 1e-9); // Monday 3h: off-peak
+        assert!((rates[5 * 24 + 12] - 0.36).abs() < 1e-9); // Saturday noon: off-peak
+
+        assert!(tariff.is_valid_on("2025-06-01"));
+        assert!(!tariff.is_valid_on("2026-01-01"));
+    }
+
+    #[test]
+    fn test_fixed_tariff_defaults() {
+        let tariff =
+            Tariff::from_json(r#"{"name": "Basic", "energy": {"type": "fixed", "rate": 0.25}}"#)
+                .unwrap();
+        assert_eq!(tariff.to_electricity_rate(), ElectricityRate::fixed(0.25));
+        assert_eq!(tariff.annual_fixed_cost(), 0.0);
+        assert!(tariff.is_valid_on("1999-01-01"));
+    }
+
+    #[test]
+    fn test_invalid_tariffs() {
+        // Gap in the weekend hours
+        let gap = TOU_TARIFF.replace(
+            r#""from": 0, "till": 24, "days": "weekend""#,
+            r#""from": 0, "till": 20, "days": "weekend""#,
+        );
+        assert!(Tariff::from_json(&gap)
+            .unwrap_err()
+            .contains("Weekend times 20:00-24:00 are not covered"));
+
+        let dates = TOU_TARIFF.replace("2025-12-31", "2024-12-31");
+        assert!(Tariff::from_json(&dates).is_err());
+        let format = TOU_TARIFF.replace("2025-12-31", "31.12.2025");
+        assert!(Tariff::from_json(&format).is_err());
+        assert!(Tariff::from_json(r#"{"name": "No price"}"#).is_err());
+    }
+
+    #[test]
+    fn test_rate_from_json_file() {
+        let path = std::env::temp_dir().join(format!("tariff_{}.json", std::process::id()));
+        std::fs::write(&path, TOU_TARIFF).unwrap();
+        let rate = ElectricityRate::from_json_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(rate.is_valid());
+        assert_eq!(rate.to_yearly_hourly_rates().len(), 8760);
+        assert!(ElectricityRate::from_json_file(&path).is_err());
+    }
+
+    #[test]
+    fn test_tariff_stack() {
+        let spot: Vec<f64> = (0..8760)
+            .map(|hour| (hour % 24) as f64 * 0.01 - 0.05)
+            .collect();
+        let stack = TariffStack::new()
+            .with_price("Spot", ElectricityRate::hourly(spot.clone()))
+            .with_price("Network fee", ElectricityRate::fixed(0.08))
+            .with_price("Levies", ElectricityRate::fixed(0.02))
+            .with_percentage("VAT", 21.0);
+
+        let rates = stack.to_yearly_hourly_rates();
+        assert_eq!(rates.len(), 8760);
+        assert!((rates[0] - 0.05 * 1.21).abs() < 1e-12);
+        assert!((rates[30] - (0.01 + 0.1) * 1.21).abs() < 1e-12); // 6h spot price 0.01
+        assert_eq!(stack.to_electricity_rate().to_yearly_hourly_rates(), rates);
+
+        let breakdown = stack.yearly_breakdown();
+        let names: Vec<&str> = breakdown.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["Spot", "Network fee", "Levies", "VAT"]);
+        for hour in [0, 30, 8759] {
+            let sum: f64 = breakdown.iter().map(|(_, values)| values[hour]).sum();
+            assert!((sum - rates[hour]).abs() < 1e-12);
+        }
+
+        // A percentage only applies to the components before it
+        let stack = TariffStack::new()
+            .with_price("Energy", ElectricityRate::fixed(0.2))
+            .with_percentage("Tax", 10.0)
+            .with_price("Untaxed fee", ElectricityRate::fixed(0.1));
+        assert!((stack.to_yearly_hourly_rates()[100] - 0.32).abs() < 1e-12);
+        assert!(TariffStack::new()
+            .to_yearly_hourly_rates()
+            .iter()
+            .all(|&v| v == 0.0));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::factory::machine::Step;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet, VecDeque};
 use ts_rs::TS;
@@ -11,7 +12,7 @@ pub struct LineNode {
     pub id: String,
     /// The name of the node.
     pub name: String,
-    /// The step ID.
+    /// The id of the `Step` run by this node, resolved with `FactoryRepository::step`.
     pub step_id: String,
     /// The steps that this step depends on (prerequisites).
     pub dependencies: Vec<String>,
@@ -43,6 +44,18 @@ impl LineNode {
     }
 }
 
+/// Timing of a node of the line for a single unit, in minutes from the start of the unit.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export, export_to = "./line.ts")]
+pub struct StepTiming {
+    /// Earliest start once all prerequisites are done.
+    pub earliest_start: f64,
+    /// Latest start that doesn't delay the unit.
+    pub latest_start: f64,
+    /// How far the step can be shifted without delaying the unit; 0 on the critical path.
+    pub slack: f64,
+}
+
 /// A production line that models dependencies between steps as a directed acyclic graph (DAG).
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema, TS)]
 #[ts(export, export_to = "./line.ts")]
@@ -110,6 +123,131 @@ impl Line {
         Ok(())
     }
 
+    /// Removes a dependency between two steps. Returns false if there was none.
+    pub fn remove_dependency(&mut self, prerequisite_id: &str, dependent_id: &str) -> bool {
+        let mut removed = false;
+        if let Some(node) = self.nodes.get_mut(prerequisite_id) {
+            let before = node.dependents.len();
+            node.dependents.retain(|id| id != dependent_id);
+            removed = node.dependents.len() < before;
+        }
+        if let Some(node) = self.nodes.get_mut(dependent_id) {
+            node.dependencies.retain(|id| id != prerequisite_id);
+        }
+        removed
+    }
+
+    /// Removes a step and all dependencies from and to it. Its prerequisites and dependents
+    /// are not reconnected. Returns the removed node, if it existed.
+    pub fn remove_step(&mut self, id: &str) -> Option<LineNode> {
+        let node = self.nodes.remove(id)?;
+        for other in self.nodes.values_mut() {
+            other.dependencies.retain(|dependency| dependency != id);
+            other.dependents.retain(|dependent| dependent != id);
+        }
+        Some(node)
+    }
+
+    /// Adds the steps and dependencies of `other`. Steps with the same id are the same step and
+    /// must refer to the same `step_id`. The line is left unchanged if merging fails, e.g.
+    /// because the dependencies would form a cycle.
+    pub fn merge(&mut self, other: &Line) -> Result<(), String> {
+        let mut merged = self.clone();
+        for node in other.nodes.values() {
+            match merged.nodes.get(&node.id) {
+                Some(existing) if existing.step_id != node.step_id => {
+                    return Err(format!(
+                        "Step '{}' refers to '{}' in line '{}' and to '{}' in line '{}'",
+                        node.id, existing.step_id, self.name, node.step_id, other.name
+                    ));
+                }
+                Some(_) => {}
+                None => merged.add_step(node.id.clone(), node.name.clone(), node.step_id.clone()),
+            }
+        }
+        for node in other.nodes.values() {
+            for dependent in &node.dependents {
+                if !merged.nodes[&node.id].dependents.contains(dependent) {
+                    merged.add_dependency(node.id.clone(), dependent.clone())?;
+                }
+            }
+        }
+        *self = merged;
+        Ok(())
+    }
+
+    /// Ids of a step and all steps reachable from it, following dependents (downstream) or
+    /// dependencies (upstream).
+    fn reachable(&self, id: &str, downstream: bool) -> HashSet<String> {
+        let mut reached = HashSet::new();
+        let mut queue = VecDeque::from([id.to_string()]);
+        while let Some(current) = queue.pop_front() {
+            if !reached.insert(current.clone()) {
+                continue;
+            }
+            let node = &self.nodes[&current];
+            let next = if downstream {
+                &node.dependents
+            } else {
+                &node.dependencies
+            };
+            queue.extend(next.iter().cloned());
+        }
+        reached
+    }
+
+    /// Line of the given steps with the dependencies among them.
+    fn subgraph(&self, ids: &HashSet<String>) -> Line {
+        let mut line = Line::new(self.name.clone(), self.id.clone());
+        for id in ids {
+            let mut node = self.nodes[id].clone();
+            node.dependencies
+                .retain(|dependency| ids.contains(dependency));
+            node.dependents.retain(|dependent| ids.contains(dependent));
+            line.nodes.insert(id.clone(), node);
+        }
+        line
+    }
+
+    /// Returns the step with all its direct and indirect prerequisites.
+    pub fn upstream(&self, id: &str) -> Option<Line> {
+        self.nodes
+            .contains_key(id)
+            .then(|| self.subgraph(&self.reachable(id, false)))
+    }
+
+    /// Returns the step with all steps that directly or indirectly depend on it.
+    pub fn downstream(&self, id: &str) -> Option<Line> {
+        self.nodes
+            .contains_key(id)
+            .then(|| self.subgraph(&self.reachable(id, true)))
+    }
+
+    /// Lists all dependency paths from one step to another, including both ends, in sorted
+    /// order. Empty if there is no path or a step doesn't exist.
+    pub fn paths_between(&self, from: &str, to: &str) -> Vec<Vec<String>> {
+        let mut paths = Vec::new();
+        if self.nodes.contains_key(from) && self.nodes.contains_key(to) {
+            let mut path = vec![from.to_string()];
+            self.collect_paths(to, &mut path, &mut paths);
+        }
+        paths.sort();
+        paths
+    }
+
+    fn collect_paths(&self, to: &str, path: &mut Vec<String>, paths: &mut Vec<Vec<String>>) {
+        let current = path.last().unwrap().clone();
+        if current == to {
+            paths.push(path.clone());
+            return;
+        }
+        for dependent in &self.nodes[&current].dependents {
+            path.push(dependent.clone());
+            self.collect_paths(to, path, paths);
+            path.pop();
+        }
+    }
+
     /// Checks if adding a dependency would create a cycle in the graph.
     fn would_create_cycle(&self, from: &str, to: &str) -> bool {
         // Use DFS to check if there's already a path from 'to' to 'from'
@@ -239,6 +377,208 @@ impl Line {
         }
     }
 
+    /// Runtime in minutes of every node, looked up from the step of the node.
+    fn node_runtimes(&self, steps: &[Step]) -> Result<HashMap<&str, f64>, String> {
+        self.nodes
+            .values()
+            .map(|node| {
+                let step = steps
+                    .iter()
+                    .find(|step| step.id == node.step_id)
+                    .ok_or_else(|| {
+                        format!("Step '{}' of node '{}' is missing", node.step_id, node.id)
+                    })?;
+                step.validate()?;
+                Ok((node.id.as_str(), step.runtime_minutes))
+            })
+            .collect()
+    }
+
+    /// Computes the earliest and latest start and the slack of every node for one unit, using
+    /// the runtimes of `steps`.
+    pub fn step_timings(&self, steps: &[Step]) -> Result<HashMap<String, StepTiming>, String> {
+        let order = self
+            .topological_sort()
+            .ok_or_else(|| "The production line contains cycles".to_string())?;
+        let runtimes = self.node_runtimes(steps)?;
+
+        let mut earliest_finish: HashMap<&str, f64> = HashMap::new();
+        for id in &order {
+            let start = self.nodes[id]
+                .dependencies
+                .iter()
+                .map(|prerequisite| earliest_finish[prerequisite.as_str()])
+                .fold(0.0, f64::max);
+            earliest_finish.insert(id, start + runtimes[id.as_str()]);
+        }
+        let duration = earliest_finish.values().copied().fold(0.0, f64::max);
+
+        let mut timings = HashMap::new();
+        let mut latest_start: HashMap<&str, f64> = HashMap::new();
+        for id in order.iter().rev() {
+            let latest_finish = self.nodes[id]
+                .dependents
+                .iter()
+                .map(|dependent| latest_start[dependent.as_str()])
+                .fold(duration, f64::min);
+            let runtime = runtimes[id.as_str()];
+            let earliest_start = earliest_finish[id.as_str()] - runtime;
+            latest_start.insert(id, latest_finish - runtime);
+            timings.insert(
+                id.clone(),
+                StepTiming {
+                    earliest_start,
+                    latest_start: latest_finish - runtime,
+                    slack: latest_finish - runtime - earliest_start,
+                },
+            );
+        }
+        Ok(timings)
+    }
+
+    /// Duration of one unit through the line in minutes, the length of the critical path.
+    pub fn critical_path_minutes(&self, steps: &[Step]) -> Result<f64, String> {
+        let runtimes = self.node_runtimes(steps)?;
+        Ok(self
+            .step_timings(steps)?
+            .iter()
+            .map(|(id, timing)| timing.earliest_start + runtimes[id.as_str()])
+            .fold(0.0, f64::max))
+    }
+
+    /// Returns the nodes on the critical path (no slack) in dependency order.
+    pub fn critical_path(&self, steps: &[Step]) -> Result<Vec<String>, String> {
+        let timings = self.step_timings(steps)?;
+        let mut path: Vec<String> = timings
+            .iter()
+            .filter(|(_, timing)| timing.slack < 1e-9)
+            .map(|(id, _)| id.clone())
+            .collect();
+        path.sort_by(|a, b| {
+            timings[a]
+                .earliest_start
+                .total_cmp(&timings[b].earliest_start)
+                .then_with(|| a.cmp(b))
+        });
+        Ok(path)
+    }
+
+    /// Takt time in minutes: the time between two units leaving the line, set by the bottleneck
+    /// node. `parallel` gives the number of parallel stations of a node (default 1).
+    pub fn takt_time_minutes(
+        &self,
+        steps: &[Step],
+        parallel: &HashMap<String, usize>,
+    ) -> Result<f64, String> {
+        let runtimes = self.node_runtimes(steps)?;
+        let mut takt: f64 = 0.0;
+        for (id, runtime) in runtimes {
+            let stations = parallel.get(id).copied().unwrap_or(1);
+            if stations == 0 {
+                return Err(format!("Node '{}' needs at least one station", id));
+            }
+            takt = takt.max(runtime / stations as f64);
+        }
+        Ok(takt)
+    }
+
+    /// Units per hour the line can produce in steady state, see `takt_time_minutes`.
+    pub fn throughput_per_hour(
+        &self,
+        steps: &[Step],
+        parallel: &HashMap<String, usize>,
+    ) -> Result<f64, String> {
+        let takt = self.takt_time_minutes(steps, parallel)?;
+        if takt > 0.0 {
+            Ok(60.0 / takt)
+        } else {
+            Err("The production line has no steps".to_string())
+        }
+    }
+
+    /// Parses a line from an edge list: one `prerequisite -> dependent` per row, or a single
+    /// id for a step without dependencies. Blank rows and rows starting with `#` are skipped.
+    /// Node ids are used as names and step ids.
+    pub fn from_edge_list(name: String, id: String, edges: &str) -> Result<Self, String> {
+        let mut line = Self::new(name, id);
+        for (row, text) in edges.lines().enumerate() {
+            let text = text.trim();
+            if text.is_empty() || text.starts_with('#') {
+                continue;
+            }
+            let ids: Vec<&str> = text.split("->").map(str::trim).collect();
+            if ids.len() > 2 || ids.iter().any(|id| id.is_empty()) {
+                return Err(format!("Invalid edge in row {}: '{}'", row + 1, text));
+            }
+            for id in &ids {
+                if !line.nodes.contains_key(*id) {
+                    line.add_step(id.to_string(), id.to_string(), id.to_string());
+                }
+            }
+            if let [prerequisite, dependent] = ids[..] {
+                line.add_dependency(prerequisite.to_string(), dependent.to_string())
+                    .map_err(|e| format!("Row {}: {}", row + 1, e))?;
+            }
+        }
+        Ok(line)
+    }
+
+    /// Node ids in sorted order, for a stable output.
+    fn sorted_ids(&self) -> Vec<&String> {
+        let mut ids: Vec<&String> = self.nodes.keys().collect();
+        ids.sort();
+        ids
+    }
+
+    /// Renders the dependency graph in Graphviz DOT, labelled with the node names.
+    pub fn to_dot(&self) -> String {
+        let quote = |text: &str| format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""));
+        let mut dot = format!("digraph {} {{\n    rankdir=LR;\n", quote(&self.name));
+        for id in self.sorted_ids() {
+            dot.push_str(&format!(
+                "    {} [label={}];\n",
+                quote(id),
+                quote(&self.nodes[id].name)
+            ));
+        }
+        for id in self.sorted_ids() {
+            for dependent in &self.nodes[id].dependents {
+                dot.push_str(&format!("    {} -> {};\n", quote(id), quote(dependent)));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Renders the dependency graph as a Mermaid flowchart, labelled with the node names.
+    /// Nodes get the ids `n0`, `n1`, ... in the order of their ids, as Mermaid ids can't hold
+    /// arbitrary characters.
+    pub fn to_mermaid(&self) -> String {
+        let ids = self.sorted_ids();
+        let mermaid_ids: HashMap<&String, String> = ids
+            .iter()
+            .enumerate()
+            .map(|(index, id)| (*id, format!("n{}", index)))
+            .collect();
+        let mut mermaid = "flowchart LR\n".to_string();
+        for id in &ids {
+            mermaid.push_str(&format!(
+                "    {}[\"{}\"]\n",
+                mermaid_ids[id],
+                self.nodes[*id].name.replace('"', "#quot;")
+            ));
+        }
+        for id in &ids {
+            for dependent in &self.nodes[*id].dependents {
+                mermaid.push_str(&format!(
+                    "    {} --> {}\n",
+                    mermaid_ids[id], mermaid_ids[dependent]
+                ));
+            }
+        }
+        mermaid
+    }
+
     /// Returns the line name.
     pub fn name(&self) -> &str {
         &self.name
@@ -258,17 +598,12 @@ impl Line {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::factory::machine::{MachineControl, Step, StepType};
+    use crate::factory::machine::Step;
 
     fn create_test_step(id: &str, name: &str) -> Step {
         Step {
-            id: id.to_string(),
-            step_type: StepType::Machine,
-            name: name.to_string(),
             power_consumption: 100.0,
-            runtime_minutes: 60.0,
-            control: MachineControl::Computer,
-            required_specialization: None,
+            ..Step::new(id.to_string(), name.to_string(), 60.0)
         }
     }
 
@@ -369,4 +704,137 @@ mod tests {
         let ready = line.get_ready_steps(&completed);
         assert_eq!(ready, vec!["step3"]);
     }
+
+    #[test]
+    fn test_step_timings() {
+        // cut (60) -> weld (30) -> paint (60), drill (30) in parallel to weld
+        let mut line = Line::new("Test Line".to_string(), "line1".to_string());
+        for id in ["cut", "weld", "drill", "paint"] {
+            line.add_step(id.to_string(), id.to_string(), id.to_string());
+        }
+        for (from, to) in [
+            ("cut", "weld"),
+            ("cut", "drill"),
+            ("weld", "paint"),
+            ("drill", "paint"),
+        ] {
+            line.add_dependency(from.to_string(), to.to_string())
+                .unwrap();
+        }
+        let mut steps: Vec<Step> = ["cut", "weld", "drill", "paint"]
+            .iter()
+            .map(|id| create_test_step(id, id))
+            .collect();
+        steps[1].runtime_minutes = 30.0;
+        steps[2].runtime_minutes = 10.0;
+
+        assert_eq!(line.critical_path_minutes(&steps).unwrap(), 150.0);
+        assert_eq!(
+            line.critical_path(&steps).unwrap(),
+            vec!["cut", "weld", "paint"]
+        );
+        let timings = line.step_timings(&steps).unwrap();
+        assert_eq!(
+            timings["drill"],
+            StepTiming {
+                earliest_start: 60.0,
+                latest_start: 80.0,
+                slack: 20.0,
+            }
+        );
+        assert_eq!(timings["paint"].earliest_start, 90.0);
+
+        // cut and paint are the bottlenecks, two paint stations leave cut
+        let mut parallel = HashMap::new();
+        assert_eq!(line.takt_time_minutes(&steps, &parallel).unwrap(), 60.0);
+        parallel.insert("paint".to_string(), 2);
+        steps[0].runtime_minutes = 40.0;
+        assert_eq!(line.takt_time_minutes(&steps, &parallel).unwrap(), 40.0);
+        assert_eq!(line.throughput_per_hour(&steps, &parallel).unwrap(), 1.5);
+
+        parallel.insert("cut".to_string(), 0);
+        assert!(line.takt_time_minutes(&steps, &parallel).is_err());
+        assert!(line.step_timings(&steps[..3]).is_err());
+    }
+
+    #[test]
+    fn test_edge_list_and_export() {
+        let line = Line::from_edge_list(
+            "Test Line".to_string(),
+            "line1".to_string(),
+            "# cutting first\ncut -> weld\ncut -> drill\n\nweld -> paint\ndrill -> paint\npack\n",
+        )
+        .unwrap();
+        assert_eq!(line.nodes.len(), 5);
+        assert_eq!(line.get_prerequisites("paint").unwrap(), ["weld", "drill"]);
+        assert!(line.get_prerequisites("pack").unwrap().is_empty());
+
+        let dot = line.to_dot();
+        assert!(dot.starts_with("digraph \"Test Line\" {"));
+        assert!(dot.contains("    \"cut\" [label=\"cut\"];\n"));
+        assert!(dot.contains("    \"weld\" -> \"paint\";\n"));
+        assert_eq!(dot.matches("->").count(), 4);
+
+        let mermaid = line.to_mermaid();
+        assert!(mermaid.starts_with("flowchart LR\n"));
+        // cut, drill, pack, paint, weld
+        assert!(mermaid.contains("    n0[\"cut\"]\n"));
+        assert!(mermaid.contains("    n0 --> n4\n"));
+        assert!(mermaid.contains("    n4 --> n3\n"));
+
+        assert!(Line::from_edge_list("L".to_string(), "l".to_string(), "a -> b -> c").is_err());
+        assert!(Line::from_edge_list("L".to_string(), "l".to_string(), "a ->").is_err());
+        assert!(Line::from_edge_list("L".to_string(), "l".to_string(), "a -> b\nb -> a").is_err());
+    }
+
+    #[test]
+    fn test_editing_and_subgraphs() {
+        let mut line = Line::from_edge_list(
+            "Test Line".to_string(),
+            "line1".to_string(),
+            "cut -> weld\ncut -> drill\nweld -> paint\ndrill -> paint\npaint -> pack",
+        )
+        .unwrap();
+
+        assert_eq!(
+            line.paths_between("cut", "paint"),
+            vec![vec!["cut", "drill", "paint"], vec!["cut", "weld", "paint"]]
+        );
+        assert!(line.paths_between("paint", "cut").is_empty());
+
+        let upstream = line.upstream("weld").unwrap();
+        let mut ids: Vec<&String> = upstream.nodes.keys().collect();
+        ids.sort();
+        assert_eq!(ids, ["cut", "weld"]);
+        assert_eq!(upstream.get_dependents("cut").unwrap(), ["weld"]);
+        let downstream = line.downstream("drill").unwrap();
+        assert_eq!(
+            downstream.topological_sort().unwrap(),
+            ["drill", "paint", "pack"]
+        );
+        assert!(line.upstream("missing").is_none());
+
+        let removed = line.remove_step("drill").unwrap();
+        assert_eq!(removed.dependencies, ["cut"]);
+        assert_eq!(line.get_dependents("cut").unwrap(), ["weld"]);
+        assert_eq!(line.get_prerequisites("paint").unwrap(), ["weld"]);
+        assert!(line.remove_step("drill").is_none());
+        assert!(line.remove_dependency("paint", "pack"));
+        assert!(!line.remove_dependency("paint", "pack"));
+
+        // merging adds the new steps and dependencies and rejects cycles
+        let other = Line::from_edge_list(
+            "Other".to_string(),
+            "line2".to_string(),
+            "paint -> pack\npack -> ship",
+        )
+        .unwrap();
+        line.merge(&other).unwrap();
+        assert_eq!(line.nodes.len(), 5);
+        assert_eq!(line.paths_between("cut", "ship").len(), 1);
+        let cyclic =
+            Line::from_edge_list("Cyclic".to_string(), "line3".to_string(), "ship -> cut").unwrap();
+        assert!(line.merge(&cyclic).is_err());
+        assert!(line.get_prerequisites("cut").unwrap().is_empty());
+    }
 }
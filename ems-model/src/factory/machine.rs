@@ -1,4 +1,4 @@
-use crate::factory::worker::Specialization;
+use crate::factory::worker::{Specialization, Time, WeekDay};
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 use utoipa::ToSchema;
@@ -23,13 +23,91 @@ pub enum StepType {
     Task,
 }
 
+/// Planned downtime in which a machine can't run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export, export_to = "./machine.ts")]
+#[schema(example = json!({"Weekly": {
+    "day": "saturday",
+    "start": {"hour": 6, "minute": 0},
+    "end": {"hour": 10, "minute": 0}
+}}))]
+pub enum MaintenanceWindow {
+    /// Every week on `day` from `start` until `end` (exclusive)
+    Weekly {
+        day: WeekDay,
+        start: Time,
+        end: Time,
+    },
+    /// Whole days from `from` until `until` (YYYY-MM-DD, inclusive)
+    Dated { from: String, until: String },
+}
+
+impl MaintenanceWindow {
+    /// Checks the times, respectively the dates, and that the window ends after it starts
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            MaintenanceWindow::Weekly { day, start, end } => {
+                if !start.is_valid() || !end.is_valid() || end <= start {
+                    return Err(format!(
+                        "Maintenance on {:?} from {}:{} to {}:{} must end after it starts on the same day",
+                        day, start.hour, start.minute, end.hour, end.minute
+                    ));
+                }
+            }
+            MaintenanceWindow::Dated { from, until } => {
+                for date in [from, until] {
+                    if WeekDay::of_date(date).is_none() {
+                        return Err(format!(
+                            "Invalid maintenance date: {}. Expected YYYY-MM-DD",
+                            date
+                        ));
+                    }
+                }
+                if from > until {
+                    return Err(format!(
+                        "Maintenance from {} is after its end {}",
+                        from, until
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the window includes `time` on `date` (YYYY-MM-DD)
+    pub fn is_active(&self, date: &str, time: &Time) -> bool {
+        match self {
+            MaintenanceWindow::Weekly { day, start, end } => {
+                WeekDay::of_date(date) == Some(*day) && start <= time && time < end
+            }
+            MaintenanceWindow::Dated { from, until } => {
+                from.as_str() <= date && date <= until.as_str()
+            }
+        }
+    }
+}
+
 /// A machine in the factory.
 ///
 /// for the ems it is important how flexible the machine is in terms of how it can be turned on and off,
 /// for how long a run is, and how much electricity it consumes.
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema, TS)]
 #[ts(export, export_to = "./machine.ts")]
+#[schema(example = json!({
+    "id": "weld",
+    "step_type": "Machine",
+    "name": "Welding robot",
+    "power_consumption": 12000.0,
+    "runtime_minutes": 45.0,
+    "control": "Human",
+    "required_specialization": {"Custom": "Welder"},
+    "standby_power": 800.0,
+    "startup_energy": 500.0,
+    "startup_minutes": 10.0,
+    "min_off_minutes": 30.0
+}))]
 pub struct Step {
+    /// The id of the step, referenced by `LineNode::step_id`.
     pub id: String,
     /// The type of the step.
     pub step_type: StepType,
@@ -43,4 +121,124 @@ pub struct Step {
     pub control: MachineControl,
     /// required specialization of the worker
     pub required_specialization: Option<Specialization>,
+    /// Power consumption while the machine is switched on but idle, in W.
+    #[serde(default)]
+    pub standby_power: f64,
+    /// Energy to switch the machine on (heating up, pressurizing, ...), in Wh.
+    #[serde(default)]
+    pub startup_energy: f64,
+    /// Time from switching on until the machine is ready for a run, in minutes.
+    #[serde(default)]
+    pub startup_minutes: f64,
+    /// Shortest time the machine may be switched off, in minutes. Shorter idle times are
+    /// spent in standby.
+    #[serde(default)]
+    pub min_off_minutes: f64,
+    /// Planned downtime in which the machine can't run
+    #[serde(default)]
+    pub maintenance: Vec<MaintenanceWindow>,
+}
+
+impl Step {
+    /// A computer-controlled machine step without power consumption, standby, startup or
+    /// maintenance; set the other fields with struct update syntax.
+    pub fn new(id: String, name: String, runtime_minutes: f64) -> Self {
+        Self {
+            id,
+            step_type: StepType::Machine,
+            name,
+            power_consumption: 0.0,
+            runtime_minutes,
+            control: MachineControl::Computer,
+            required_specialization: None,
+            standby_power: 0.0,
+            startup_energy: 0.0,
+            startup_minutes: 0.0,
+            min_off_minutes: 0.0,
+            maintenance: Vec::new(),
+        }
+    }
+
+    /// Checks that the power and energy values are non-negative, the runtime is positive and the
+    /// maintenance windows are valid.
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.runtime_minutes > 0.0 && self.runtime_minutes.is_finite()) {
+            return Err(format!(
+                "Step {} needs a positive runtime, got {} minutes",
+                self.name, self.runtime_minutes
+            ));
+        }
+        for (name, value) in [
+            ("power consumption", self.power_consumption),
+            ("standby power", self.standby_power),
+            ("startup energy", self.startup_energy),
+            ("startup time", self.startup_minutes),
+            ("minimum off time", self.min_off_minutes),
+        ] {
+            if !(value >= 0.0 && value.is_finite()) {
+                return Err(format!(
+                    "Step {} needs a non-negative {}, got {}",
+                    self.name, name, value
+                ));
+            }
+        }
+        for window in &self.maintenance {
+            window
+                .validate()
+                .map_err(|e| format!("Step {}: {}", self.name, e))?;
+        }
+        Ok(())
+    }
+
+    /// Whether a maintenance window of the machine includes `time` on `date` (YYYY-MM-DD)
+    pub fn is_under_maintenance(&self, date: &str, time: &Time) -> bool {
+        self.maintenance
+            .iter()
+            .any(|window| window.is_active(date, time))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_maintenance_windows() {
+        let mut step = Step {
+            power_consumption: 12000.0,
+            maintenance: vec![
+                MaintenanceWindow::Weekly {
+                    day: WeekDay::Saturday,
+                    start: Time::new(6, 0),
+                    end: Time::new(10, 0),
+                },
+                MaintenanceWindow::Dated {
+                    from: "2025-12-24".to_string(),
+                    until: "2025-12-26".to_string(),
+                },
+            ],
+            ..Step::new("cnc".to_string(), "CNC".to_string(), 60.0)
+        };
+        assert!(step.validate().is_ok());
+        // 2025-03-08 is a Saturday
+        assert!(step.is_under_maintenance("2025-03-08", &Time::new(6, 0)));
+        assert!(!step.is_under_maintenance("2025-03-08", &Time::new(10, 0)));
+        assert!(!step.is_under_maintenance("2025-03-07", &Time::new(8, 0)));
+        assert!(step.is_under_maintenance("2025-12-26", &Time::new(23, 0)));
+
+        step.maintenance.push(MaintenanceWindow::Weekly {
+            day: WeekDay::Sunday,
+            start: Time::new(10, 0),
+            end: Time::new(8, 0),
+        });
+        assert!(step.validate().is_err());
+        step.maintenance[2] = MaintenanceWindow::Dated {
+            from: "2025-12-27".to_string(),
+            until: "2025-12-01".to_string(),
+        };
+        assert_eq!(
+            step.validate().unwrap_err(),
+            "Step CNC: Maintenance from 2025-12-27 is after its end 2025-12-01"
+        );
+    }
 }
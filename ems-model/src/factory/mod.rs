@@ -3,9 +3,14 @@ pub mod machine;
 pub mod worker;
 
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use ts_rs::TS;
 use utoipa::ToSchema;
 
+use crate::factory::line::Line;
+use crate::factory::machine::Step;
+use crate::factory::worker::Worker;
+
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema, TS)]
 #[ts(export, export_to = "./factory.ts")]
 pub struct Factory {
@@ -18,3 +23,195 @@ pub struct Factory {
     /// The lines of the factory.
     pub lines: Vec<String>,
 }
+
+impl Factory {
+    /// Checks the references of the factory before a simulation: every line id must resolve in
+    /// `repository`, appear once and have an acyclic graph whose nodes reference existing and
+    /// valid steps. Returns all problems found.
+    pub fn validate<R: FactoryRepository + ?Sized>(
+        &self,
+        repository: &R,
+    ) -> Result<(), Vec<String>> {
+        let mut errors = duplicate_ids("line", self.lines.iter().map(String::as_str));
+        let mut checked = HashSet::new();
+        for line_id in &self.lines {
+            if !checked.insert(line_id) {
+                continue;
+            }
+            let Some(line) = repository.line(line_id) else {
+                errors.push(format!(
+                    "Line '{}' of factory '{}' is missing",
+                    line_id, self.id
+                ));
+                continue;
+            };
+            if let Err(line_errors) = line.validate() {
+                errors.extend(
+                    line_errors
+                        .into_iter()
+                        .map(|error| format!("Line '{}': {}", line_id, error)),
+                );
+            }
+            let mut nodes: Vec<_> = line.nodes().values().collect();
+            nodes.sort_by(|a, b| a.id.cmp(&b.id));
+            for node in nodes {
+                match repository.step(node.step_id()) {
+                    Some(step) => {
+                        if let Err(error) = step.validate() {
+                            errors.push(format!("Line '{}': {}", line_id, error));
+                        }
+                    }
+                    None => errors.push(format!(
+                        "Line '{}': step '{}' of node '{}' is missing",
+                        line_id,
+                        node.step_id(),
+                        node.id
+                    )),
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Resolves the ids referenced by a factory, e.g. from a database or an API payload.
+pub trait FactoryRepository {
+    /// The line with the given id
+    fn line(&self, id: &str) -> Option<&Line>;
+    /// The step with the given id
+    fn step(&self, id: &str) -> Option<&Step>;
+    /// The worker with the given id
+    fn worker(&self, id: &str) -> Option<&Worker>;
+}
+
+/// A factory together with all lines, steps and workers it references.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export, export_to = "./factory.ts")]
+pub struct FactoryData {
+    /// The factory.
+    pub factory: Factory,
+    /// The lines of the factory.
+    pub lines: Vec<Line>,
+    /// The steps referenced by the nodes of the lines.
+    pub steps: Vec<Step>,
+    /// The workers of the factory.
+    pub workers: Vec<Worker>,
+}
+
+impl FactoryData {
+    /// Checks that the ids of lines, steps and workers are unique and that the factory
+    /// references resolve, see `Factory::validate`.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = duplicate_ids("line", self.lines.iter().map(|line| line.id()));
+        errors.extend(duplicate_ids(
+            "step",
+            self.steps.iter().map(|step| step.id.as_str()),
+        ));
+        errors.extend(duplicate_ids(
+            "worker",
+            self.workers.iter().map(|worker| worker.id.as_str()),
+        ));
+        if let Err(factory_errors) = self.factory.validate(self) {
+            errors.extend(factory_errors);
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl FactoryRepository for FactoryData {
+    fn line(&self, id: &str) -> Option<&Line> {
+        self.lines.iter().find(|line| line.id() == id)
+    }
+
+    fn step(&self, id: &str) -> Option<&Step> {
+        self.steps.iter().find(|step| step.id == id)
+    }
+
+    fn worker(&self, id: &str) -> Option<&Worker> {
+        self.workers.iter().find(|worker| worker.id == id)
+    }
+}
+
+/// One error for every id that occurs more than once.
+fn duplicate_ids<'a>(kind: &str, ids: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut reported = HashSet::new();
+    ids.filter(|id| !seen.insert(*id) && reported.insert(*id))
+        .map(|id| format!("Duplicate {} id '{}'", kind, id))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::factory::worker::Schedule;
+
+    fn step(id: &str) -> Step {
+        Step {
+            power_consumption: 100.0,
+            ..Step::new(id.to_string(), id.to_string(), 30.0)
+        }
+    }
+
+    fn factory_data() -> FactoryData {
+        let mut line = Line::new("Assembly".to_string(), "line1".to_string());
+        line.add_step("a".to_string(), "Cut".to_string(), "cut".to_string());
+        line.add_step("b".to_string(), "Weld".to_string(), "weld".to_string());
+        line.add_dependency("a".to_string(), "b".to_string())
+            .unwrap();
+        FactoryData {
+            factory: Factory {
+                name: "Plant".to_string(),
+                id: "plant".to_string(),
+                location: "Berlin".to_string(),
+                lines: vec!["line1".to_string()],
+            },
+            lines: vec![line],
+            steps: vec![step("cut"), step("weld")],
+            workers: vec![Worker::new(
+                "anna".to_string(),
+                "Anna".to_string(),
+                Vec::new(),
+                Schedule::default(),
+            )],
+        }
+    }
+
+    #[test]
+    fn test_factory_validation() {
+        let data = factory_data();
+        assert!(data.validate().is_ok());
+        assert_eq!(data.step("weld").unwrap().name, "weld");
+        assert_eq!(data.worker("anna").unwrap().name, "Anna");
+        assert!(data.line("line2").is_none());
+
+        let mut data = factory_data();
+        data.factory.lines.push("line2".to_string());
+        data.factory.lines.push("line1".to_string());
+        data.steps[1] = step("cut");
+        data.steps[0].runtime_minutes = 0.0;
+        data.workers.push(data.workers[0].clone());
+        let errors = data.validate().unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                "Duplicate step id 'cut'",
+                "Duplicate worker id 'anna'",
+                "Duplicate line id 'line1'",
+                "Line 'line1': Step cut needs a positive runtime, got 0 minutes",
+                "Line 'line1': step 'weld' of node 'b' is missing",
+                "Line 'line2' of factory 'plant' is missing",
+            ]
+        );
+    }
+}
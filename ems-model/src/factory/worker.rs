@@ -1,8 +1,12 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
 use ts_rs::TS;
 use utoipa::ToSchema;
 
+pub use crate::general::calendar::add_days;
+use crate::general::calendar::days_since_epoch;
+
 /// Represents a time of day in 24-hour format
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema, TS)]
 #[ts(export, export_to = "./worker.ts")]
@@ -15,6 +19,22 @@ impl Time {
     pub fn new(hour: u8, minute: u8) -> Self {
         Time { hour, minute }
     }
+
+    /// Minutes since midnight
+    pub fn minutes(&self) -> u16 {
+        self.hour as u16 * 60 + self.minute as u16
+    }
+
+    /// Whether hour and minute are within 0-23 and 0-59
+    pub fn is_valid(&self) -> bool {
+        self.hour < 24 && self.minute < 60
+    }
+}
+
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}", self.hour, self.minute)
+    }
 }
 
 /// Represents a work shift with start and end times
@@ -23,11 +43,60 @@ impl Time {
 pub struct WorkShift {
     pub start: Time,
     pub end: Time,
+    /// The shift ends at `end` on the next day, e.g. a night shift from 22:00 to 06:00.
+    #[serde(default)]
+    pub overnight: bool,
 }
 
 impl WorkShift {
     pub fn new(start: Time, end: Time) -> Self {
-        WorkShift { start, end }
+        WorkShift {
+            start,
+            end,
+            overnight: false,
+        }
+    }
+
+    /// A shift that ends at `end` on the day after it started
+    pub fn overnight(start: Time, end: Time) -> Self {
+        WorkShift {
+            start,
+            end,
+            overnight: true,
+        }
+    }
+
+    /// Start and end in minutes since midnight of the day the shift starts, the end of an
+    /// overnight shift lies after 24:00.
+    pub fn minutes(&self) -> (u16, u16) {
+        let end = self.end.minutes() + if self.overnight { 24 * 60 } else { 0 };
+        (self.start.minutes(), end)
+    }
+
+    /// Checks that the times are valid and that the shift ends after it starts, or is marked as
+    /// overnight and lasts less than a day. The error continues a sentence like "Shift on
+    /// Monday ...".
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.start.is_valid() || !self.end.is_valid() {
+            return Err(format!(
+                "has an invalid time: {}:{} to {}:{}",
+                self.start.hour, self.start.minute, self.end.hour, self.end.minute
+            ));
+        }
+        let (start, end) = self.minutes();
+        if end <= start {
+            Err(format!(
+                "ends at {}, not after its start at {}; mark it as overnight if it ends on the next day",
+                self.end, self.start
+            ))
+        } else if end - start >= 24 * 60 {
+            Err(format!(
+                "from {} to {} lasts a day or longer",
+                self.start, self.end
+            ))
+        } else {
+            Ok(())
+        }
     }
 }
 
@@ -58,35 +127,222 @@ pub enum WeekDay {
     Sunday,
 }
 
-/// Simple weekly schedule
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema, TS, Default)]
+impl WeekDay {
+    /// All days from Monday to Sunday
+    pub const ALL: [WeekDay; 7] = [
+        WeekDay::Monday,
+        WeekDay::Tuesday,
+        WeekDay::Wednesday,
+        WeekDay::Thursday,
+        WeekDay::Friday,
+        WeekDay::Saturday,
+        WeekDay::Sunday,
+    ];
+
+    /// Position in the week, 0 for Monday
+    pub fn index(self) -> usize {
+        WeekDay::ALL.iter().position(|day| *day == self).unwrap()
+    }
+
+    /// The following day, Monday after Sunday
+    pub fn next(self) -> WeekDay {
+        WeekDay::ALL[(self.index() + 1) % 7]
+    }
+
+    /// The day before, Sunday before Monday
+    pub fn previous(self) -> WeekDay {
+        WeekDay::ALL[(self.index() + 6) % 7]
+    }
+
+    /// Day of the week of a date (YYYY-MM-DD), None if the date is invalid
+    pub fn of_date(date: &str) -> Option<WeekDay> {
+        // 1970-01-01 was a Thursday
+        days_since_epoch(date).map(|days| WeekDay::ALL[(days + 3).rem_euclid(7) as usize])
+    }
+}
+
+/// Simple weekly schedule, a day can have several shifts (e.g. a split shift)
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema, TS, Default)]
 #[ts(export, export_to = "./worker.ts")]
 pub struct Schedule {
-    pub weekly_shifts: HashMap<WeekDay, WorkShift>,
+    /// Shifts of every day, sorted by start. A single shift object per day (the format before
+    /// split shifts) is still accepted.
+    pub weekly_shifts: HashMap<WeekDay, Vec<WorkShift>>,
+}
+
+/// Serialized form of `Schedule`, accepting one shift per day (the old format) or a list
+#[derive(Deserialize)]
+struct ScheduleRepr {
+    weekly_shifts: HashMap<WeekDay, DayShifts>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DayShifts {
+    One(WorkShift),
+    Many(Vec<WorkShift>),
+}
+
+impl<'de> Deserialize<'de> for Schedule {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = ScheduleRepr::deserialize(deserializer)?;
+        let mut schedule = Schedule::default();
+        for (day, shifts) in repr.weekly_shifts {
+            let shifts = match shifts {
+                DayShifts::One(shift) => vec![shift],
+                DayShifts::Many(shifts) => shifts,
+            };
+            for shift in shifts {
+                schedule.add_shift(day, shift);
+            }
+        }
+        Ok(schedule)
+    }
 }
 
 impl Schedule {
+    /// Adds a shift on `day`, keeping the shifts of the day sorted by start. Existing shifts of
+    /// the day are kept; call `remove_shift` first to replace them.
     pub fn add_shift(&mut self, day: WeekDay, shift: WorkShift) {
-        self.weekly_shifts.insert(day, shift);
+        let shifts = self.weekly_shifts.entry(day).or_default();
+        shifts.push(shift);
+        shifts.sort_by(|a, b| a.start.cmp(&b.start));
     }
 
+    /// Removes all shifts on `day`
     pub fn remove_shift(&mut self, day: WeekDay) {
         self.weekly_shifts.remove(&day);
     }
 
+    /// The first shift on `day`
+    #[deprecated(note = "a day can have several shifts, use `get_shifts`")]
     pub fn get_shift(&self, day: WeekDay) -> Option<&WorkShift> {
-        self.weekly_shifts.get(&day)
+        self.get_shifts(day).first()
+    }
+
+    /// The shifts on `day`, sorted by start
+    pub fn get_shifts(&self, day: WeekDay) -> &[WorkShift] {
+        self.weekly_shifts.get(&day).map_or(&[], Vec::as_slice)
+    }
+
+    /// Checks that all times are valid, that shifts end after they start (or are marked as
+    /// overnight and last less than a day) and that no two shifts overlap, including overnight
+    /// shifts running into the next day.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        for day in WeekDay::ALL {
+            let mut valid: Vec<&WorkShift> = Vec::new();
+            for shift in self.get_shifts(day) {
+                match shift.validate() {
+                    Ok(()) => valid.push(shift),
+                    Err(error) => errors.push(format!("Shift on {:?} {}", day, error)),
+                }
+            }
+            valid.sort_by(|a, b| a.start.cmp(&b.start));
+
+            for pair in valid.windows(2) {
+                if pair[0].minutes().1 > pair[1].start.minutes() {
+                    errors.push(format!(
+                        "Shifts on {:?} from {} to {} and from {} to {} overlap",
+                        day, pair[0].start, pair[0].end, pair[1].start, pair[1].end
+                    ));
+                }
+            }
+            let Some(last) = valid.last().filter(|shift| shift.overnight) else {
+                continue;
+            };
+            if let Some(next) = self
+                .get_shifts(day.next())
+                .iter()
+                .find(|next| next.start.minutes() < last.end.minutes())
+            {
+                errors.push(format!(
+                    "Overnight shift on {:?} until {} overlaps the shift on {:?} starting at {}",
+                    day,
+                    last.end,
+                    day.next(),
+                    next.start
+                ));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Whether the schedule has a shift at `time` on `day`, including overnight shifts that
+    /// started the day before. Shifts include their start and exclude their end.
+    pub fn is_on_shift(&self, day: WeekDay, time: &Time) -> bool {
+        let minute = time.minutes();
+        let today = self.get_shifts(day).iter().any(|shift| {
+            let (start, end) = shift.minutes();
+            start <= minute && minute < end
+        });
+        let from_yesterday = self
+            .get_shifts(day.previous())
+            .iter()
+            .any(|shift| shift.overnight && minute < shift.end.minutes());
+        today || from_yesterday
     }
 }
 
 /// What can a worker do?
-#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, TS)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema, TS)]
 #[ts(export, export_to = "./worker.ts")]
+#[schema(example = json!({"Custom": "Welder"}))]
 pub enum Specialization {
+    /// A specialization without its own variant, e.g. `{"Custom": "Welder"}`.
     Custom(String),
+    /// Serialized as `"CncMachineOperator"`.
     CncMachineOperator,
 }
 
+/// Why a worker is absent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export, export_to = "./worker.ts")]
+pub enum AbsenceKind {
+    Vacation,
+    Sick,
+    /// Other changes of the weekly shifts, e.g. training or reduced hours
+    Other,
+}
+
+/// Days on which the weekly shifts of a worker don't apply
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export, export_to = "./worker.ts")]
+#[schema(example = json!({"from": "2025-08-04", "until": "2025-08-15", "kind": "Vacation"}))]
+pub struct Absence {
+    /// First day of the absence (YYYY-MM-DD)
+    pub from: String,
+    /// Last day of the absence (YYYY-MM-DD), inclusive
+    pub until: String,
+    pub kind: AbsenceKind,
+    /// Shifts worked on every day of the absence instead of the weekly shifts; empty if the
+    /// worker is away all day
+    #[serde(default)]
+    pub shifts: Vec<WorkShift>,
+}
+
+impl Absence {
+    /// Absence without any shifts from `from` until `until` (YYYY-MM-DD, inclusive)
+    pub fn new(from: &str, until: &str, kind: AbsenceKind) -> Self {
+        Absence {
+            from: from.to_string(),
+            until: until.to_string(),
+            kind,
+            shifts: Vec::new(),
+        }
+    }
+
+    /// Whether the absence includes `date` (YYYY-MM-DD)
+    pub fn covers(&self, date: &str) -> bool {
+        self.from.as_str() <= date && date <= self.until.as_str()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema, TS)]
 #[ts(export, export_to = "./worker.ts")]
 pub struct Worker {
@@ -94,6 +350,9 @@ pub struct Worker {
     pub name: String,
     pub specialization: Vec<Specialization>,
     pub schedule: Schedule,
+    /// Vacation, sick days and other days that override the weekly schedule
+    #[serde(default)]
+    pub absences: Vec<Absence>,
 }
 
 impl Worker {
@@ -108,6 +367,7 @@ impl Worker {
             name,
             specialization,
             schedule,
+            absences: Vec::new(),
         }
     }
 
@@ -131,4 +391,374 @@ impl Worker {
     pub fn remove_work_shift(&mut self, day: WeekDay) {
         self.schedule.remove_shift(day);
     }
+
+    /// Whether the worker has the specialization
+    pub fn has_specialization(&self, specialization: &Specialization) -> bool {
+        self.specialization.contains(specialization)
+    }
+
+    /// Record an absence, later absences take precedence where they overlap
+    pub fn add_absence(&mut self, absence: Absence) {
+        self.absences.push(absence);
+    }
+
+    /// Checks the weekly schedule and the dates and shifts of the absences
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = self.schedule.validate().err().unwrap_or_default();
+        for absence in &self.absences {
+            for date in [&absence.from, &absence.until] {
+                if WeekDay::of_date(date).is_none() {
+                    errors.push(format!(
+                        "Invalid absence date: {}. Expected YYYY-MM-DD",
+                        date
+                    ));
+                }
+            }
+            if absence.from > absence.until {
+                errors.push(format!(
+                    "Absence from {} is after its end {}",
+                    absence.from, absence.until
+                ));
+            }
+            for shift in &absence.shifts {
+                if let Err(error) = shift.validate() {
+                    errors.push(format!(
+                        "Shift of the absence from {} {}",
+                        absence.from, error
+                    ));
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Shifts of the worker on `date` (YYYY-MM-DD): those of the latest absence covering the
+    /// date, or the weekly shifts of its day
+    pub fn shifts_on(&self, date: &str) -> Result<&[WorkShift], String> {
+        let day = WeekDay::of_date(date)
+            .ok_or_else(|| format!("Invalid date: {}. Expected YYYY-MM-DD", date))?;
+        Ok(
+            match self
+                .absences
+                .iter()
+                .rev()
+                .find(|absence| absence.covers(date))
+            {
+                Some(absence) => &absence.shifts,
+                None => self.schedule.get_shifts(day),
+            },
+        )
+    }
+
+    /// The schedule of the week starting on `monday` (YYYY-MM-DD) with the absences applied
+    pub fn schedule_for_week(&self, monday: &str) -> Result<Schedule, String> {
+        if WeekDay::of_date(monday) != Some(WeekDay::Monday) {
+            return Err(format!("{} is not a Monday", monday));
+        }
+        let mut schedule = Schedule::default();
+        for (offset, day) in WeekDay::ALL.into_iter().enumerate() {
+            let date = add_days(monday, offset as i64).unwrap();
+            for shift in self.shifts_on(&date)? {
+                schedule.add_shift(day, shift.clone());
+            }
+        }
+        Ok(schedule)
+    }
+
+    /// Whether the worker is on shift at `time` on `date` (YYYY-MM-DD), including overnight
+    /// shifts of the day before, with the absences applied
+    pub fn is_available_on(&self, date: &str, time: &Time) -> Result<bool, String> {
+        let day = WeekDay::of_date(date)
+            .ok_or_else(|| format!("Invalid date: {}. Expected YYYY-MM-DD", date))?;
+        let yesterday = add_days(date, -1).unwrap();
+        let mut schedule = Schedule::default();
+        for (day, date) in [(day.previous(), yesterday.as_str()), (day, date)] {
+            for shift in self.shifts_on(date)? {
+                schedule.add_shift(day, shift.clone());
+            }
+        }
+        Ok(schedule.is_on_shift(day, time))
+    }
+}
+
+/// Workers on shift at `time` on `day` who have the `specialization`, or all workers on shift
+/// if it is None.
+pub fn available_workers<'a>(
+    workers: &'a [Worker],
+    specialization: Option<&Specialization>,
+    day: WeekDay,
+    time: &Time,
+) -> Vec<&'a Worker> {
+    workers
+        .iter()
+        .filter(|worker| specialization.is_none_or(|s| worker.has_specialization(s)))
+        .filter(|worker| worker.schedule.is_on_shift(day, time))
+        .collect()
+}
+
+/// Whether a worker with the `specialization` is on shift at `time` on `day`
+pub fn is_covered(
+    workers: &[Worker],
+    specialization: Option<&Specialization>,
+    day: WeekDay,
+    time: &Time,
+) -> bool {
+    !available_workers(workers, specialization, day, time).is_empty()
+}
+
+/// Whether a worker with the `specialization` is on shift at `time` on `date` (YYYY-MM-DD),
+/// taking absences into account
+pub fn is_covered_on(
+    workers: &[Worker],
+    specialization: Option<&Specialization>,
+    date: &str,
+    time: &Time,
+) -> Result<bool, String> {
+    for worker in workers {
+        if specialization.is_none_or(|s| worker.has_specialization(s))
+            && worker.is_available_on(date, time)?
+        {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schedule_validation() {
+        let mut schedule = Schedule::default();
+        schedule.add_shift(
+            WeekDay::Monday,
+            WorkShift::new(Time::new(8, 0), Time::new(16, 0)),
+        );
+        schedule.add_shift(
+            WeekDay::Tuesday,
+            WorkShift::overnight(Time::new(22, 0), Time::new(6, 0)),
+        );
+        schedule.add_shift(
+            WeekDay::Wednesday,
+            WorkShift::new(Time::new(14, 0), Time::new(22, 0)),
+        );
+        // a split shift on Thursday
+        schedule.add_shift(
+            WeekDay::Thursday,
+            WorkShift::new(Time::new(14, 0), Time::new(18, 0)),
+        );
+        schedule.add_shift(
+            WeekDay::Thursday,
+            WorkShift::new(Time::new(6, 0), Time::new(10, 0)),
+        );
+        assert!(schedule.validate().is_ok());
+        assert_eq!(
+            schedule.get_shifts(WeekDay::Thursday)[0].start,
+            Time::new(6, 0)
+        );
+        assert!(schedule.is_on_shift(WeekDay::Thursday, &Time::new(15, 0)));
+        assert!(!schedule.is_on_shift(WeekDay::Thursday, &Time::new(12, 0)));
+        assert!(schedule.get_shifts(WeekDay::Sunday).is_empty());
+
+        schedule.add_shift(
+            WeekDay::Monday,
+            WorkShift::new(Time::new(12, 0), Time::new(18, 0)),
+        );
+        schedule.add_shift(
+            WeekDay::Wednesday,
+            WorkShift::new(Time::new(5, 0), Time::new(13, 0)),
+        );
+        schedule.add_shift(
+            WeekDay::Friday,
+            WorkShift::new(Time::new(22, 0), Time::new(6, 0)),
+        );
+        schedule.add_shift(
+            WeekDay::Saturday,
+            WorkShift::overnight(Time::new(6, 0), Time::new(6, 0)),
+        );
+        schedule.add_shift(
+            WeekDay::Sunday,
+            WorkShift::new(Time::new(8, 0), Time::new(24, 0)),
+        );
+        assert_eq!(
+            schedule.validate().unwrap_err(),
+            vec![
+                "Shifts on Monday from 08:00 to 16:00 and from 12:00 to 18:00 overlap",
+                "Overnight shift on Tuesday until 06:00 overlaps the shift on Wednesday starting at 05:00",
+                "Shift on Friday ends at 06:00, not after its start at 22:00; mark it as overnight if it ends on the next day",
+                "Shift on Saturday from 06:00 to 06:00 lasts a day or longer",
+                "Shift on Sunday has an invalid time: 8:0 to 24:0",
+            ]
+        );
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_schedule_deserialization() {
+        // one shift object per day, as written before split shifts
+        let old: Schedule = serde_json::from_str(
+            r#"{"weekly_shifts": {"monday": {"start": {"hour": 8, "minute": 0}, "end": {"hour": 16, "minute": 0}}}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            old.get_shifts(WeekDay::Monday),
+            [WorkShift::new(Time::new(8, 0), Time::new(16, 0))]
+        );
+        assert_eq!(
+            old.get_shift(WeekDay::Monday),
+            Some(&WorkShift::new(Time::new(8, 0), Time::new(16, 0)))
+        );
+
+        let mut split = Schedule::default();
+        split.add_shift(
+            WeekDay::Friday,
+            WorkShift::new(Time::new(14, 0), Time::new(18, 0)),
+        );
+        split.add_shift(
+            WeekDay::Friday,
+            WorkShift::new(Time::new(7, 0), Time::new(11, 0)),
+        );
+        let json = serde_json::to_string(&split).unwrap();
+        assert_eq!(serde_json::from_str::<Schedule>(&json).unwrap(), split);
+        assert_eq!(
+            split.get_shift(WeekDay::Friday).unwrap().start,
+            Time::new(7, 0)
+        );
+
+        assert!(serde_json::from_str::<Schedule>(r#"{"weekly_shifts": {"monday": 8}}"#).is_err());
+    }
+
+    #[test]
+    fn test_coverage() {
+        let mut welder = Worker::new(
+            "anna".to_string(),
+            "Anna".to_string(),
+            vec![Specialization::Custom("Welder".to_string())],
+            Schedule::default(),
+        );
+        welder.schedule.add_shift(
+            WeekDay::Sunday,
+            WorkShift::overnight(Time::new(22, 0), Time::new(6, 0)),
+        );
+        let mut operator = Worker::new(
+            "ben".to_string(),
+            "Ben".to_string(),
+            vec![Specialization::CncMachineOperator],
+            Schedule::default(),
+        );
+        operator.add_work_shift(WeekDay::Monday, Time::new(8, 0), Time::new(16, 0));
+        let workers = [welder, operator];
+        let welding = Specialization::Custom("Welder".to_string());
+
+        // the night shift from Sunday covers Monday morning
+        assert!(is_covered(
+            &workers,
+            Some(&welding),
+            WeekDay::Monday,
+            &Time::new(5, 59)
+        ));
+        assert!(!is_covered(
+            &workers,
+            Some(&welding),
+            WeekDay::Monday,
+            &Time::new(6, 0)
+        ));
+        assert!(is_covered(
+            &workers,
+            Some(&welding),
+            WeekDay::Sunday,
+            &Time::new(23, 0)
+        ));
+        assert!(!is_covered(
+            &workers,
+            Some(&welding),
+            WeekDay::Sunday,
+            &Time::new(5, 0)
+        ));
+        assert!(!is_covered(
+            &workers,
+            Some(&Specialization::CncMachineOperator),
+            WeekDay::Monday,
+            &Time::new(7, 0)
+        ));
+        let available = available_workers(&workers, None, WeekDay::Monday, &Time::new(8, 0));
+        assert_eq!(available.len(), 1);
+        assert_eq!(available[0].id, "ben");
+        assert_eq!(WeekDay::Sunday.next(), WeekDay::Monday);
+        assert_eq!(WeekDay::Monday.previous(), WeekDay::Sunday);
+    }
+
+    #[test]
+    fn test_dates() {
+        assert_eq!(WeekDay::of_date("2025-01-01"), Some(WeekDay::Wednesday));
+        assert_eq!(WeekDay::of_date("2024-02-29"), Some(WeekDay::Thursday));
+        assert_eq!(WeekDay::of_date("2025-02-29"), None);
+        assert_eq!(WeekDay::of_date("2025-1-01"), None);
+        assert_eq!(add_days("2024-12-31", 1).unwrap(), "2025-01-01");
+        assert_eq!(add_days("2024-03-01", -1).unwrap(), "2024-02-29");
+        assert_eq!(add_days("2025-08-04", 14).unwrap(), "2025-08-18");
+    }
+
+    #[test]
+    fn test_absences() {
+        let mut worker = Worker::new(
+            "anna".to_string(),
+            "Anna".to_string(),
+            vec![Specialization::CncMachineOperator],
+            Schedule::default(),
+        );
+        for day in &WeekDay::ALL[..5] {
+            worker.add_work_shift(*day, Time::new(8, 0), Time::new(16, 0));
+        }
+        worker.add_absence(Absence::new(
+            "2025-08-04",
+            "2025-08-15",
+            AbsenceKind::Vacation,
+        ));
+        // back from vacation with a short day
+        worker.add_absence(Absence {
+            shifts: vec![WorkShift::new(Time::new(8, 0), Time::new(12, 0))],
+            ..Absence::new("2025-08-18", "2025-08-18", AbsenceKind::Other)
+        });
+        assert!(worker.validate().is_ok());
+
+        let nine = Time::new(9, 0);
+        assert!(!worker.is_available_on("2025-08-05", &nine).unwrap());
+        assert!(worker.is_available_on("2025-08-18", &nine).unwrap());
+        assert!(!worker
+            .is_available_on("2025-08-18", &Time::new(13, 0))
+            .unwrap());
+        assert!(worker
+            .is_available_on("2025-08-19", &Time::new(13, 0))
+            .unwrap());
+        assert!(worker.is_available_on("2025-13-01", &nine).is_err());
+        assert!(worker.shifts_on("2025-08-08").unwrap().is_empty());
+
+        let workers = [worker.clone()];
+        let cnc = Specialization::CncMachineOperator;
+        assert!(!is_covered_on(&workers, Some(&cnc), "2025-08-11", &nine).unwrap());
+        assert!(is_covered_on(&workers, Some(&cnc), "2025-08-25", &nine).unwrap());
+
+        let week = worker.schedule_for_week("2025-08-18").unwrap();
+        assert_eq!(week.get_shifts(WeekDay::Monday)[0].end, Time::new(12, 0));
+        assert_eq!(week.get_shifts(WeekDay::Tuesday)[0].end, Time::new(16, 0));
+        assert!(worker
+            .schedule_for_week("2025-08-11")
+            .unwrap()
+            .weekly_shifts
+            .is_empty());
+        assert!(worker.schedule_for_week("2025-08-12").is_err());
+
+        worker.add_absence(Absence::new("2025-09-10", "2025-09-01", AbsenceKind::Sick));
+        assert_eq!(
+            worker.validate().unwrap_err(),
+            vec!["Absence from 2025-09-10 is after its end 2025-09-01"]
+        );
+    }
 }
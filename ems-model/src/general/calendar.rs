@@ -0,0 +1,107 @@
+/// Days of the months of a non-leap year
+pub const DAYS_IN_MONTH: [usize; 12] = [31, 28, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+
+/// Whether `year` is a leap year of the Gregorian calendar
+pub fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+/// Days of `month` (1-12) in `year`
+pub fn days_in_month(year: i64, month: usize) -> usize {
+    if month == 2 && is_leap_year(year) {
+        29
+    } else {
+        DAYS_IN_MONTH[month - 1]
+    }
+}
+
+/// Days from 1970-01-01 to a valid date (YYYY-MM-DD) of the Gregorian calendar
+pub fn days_since_epoch(date: &str) -> Option<i64> {
+    let parts: Vec<&str> = date.split('-').collect();
+    let [year, month, day] = parts.as_slice() else {
+        return None;
+    };
+    if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+        return None;
+    }
+    let (year, month, day): (i64, usize, usize) =
+        (year.parse().ok()?, month.parse().ok()?, day.parse().ok()?);
+    if !(1..=12).contains(&month) || !(1..=days_in_month(year, month)).contains(&day) {
+        return None;
+    }
+    let (month, day) = (month as i64, day as i64);
+    // days from civil, with years starting in March
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    Some(era * 146097 + day_of_era - 719468)
+}
+
+/// Date (YYYY-MM-DD) `days` after 1970-01-01
+pub fn date_from_days(days: i64) -> String {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Date (YYYY-MM-DD) `days` after `date`, None if `date` is invalid
+pub fn add_days(date: &str, days: i64) -> Option<String> {
+    Some(date_from_days(days_since_epoch(date)? + days))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calendar() {
+        assert!(is_leap_year(2024) && is_leap_year(2000));
+        assert!(!is_leap_year(2025) && !is_leap_year(1900));
+        assert_eq!(days_in_month(2024, 2), 29);
+        assert_eq!(days_in_month(2025, 2), 28);
+        assert_eq!(DAYS_IN_MONTH.iter().sum::<usize>(), 365);
+
+        assert_eq!(days_since_epoch("1970-01-01"), Some(0));
+        assert_eq!(days_since_epoch("1969-12-31"), Some(-1));
+        assert_eq!(days_since_epoch("2025-13-01"), None);
+        assert_eq!(
+            date_from_days(days_since_epoch("2024-02-29").unwrap()),
+            "2024-02-29"
+        );
+        assert_eq!(add_days("2024-12-31", 1).unwrap(), "2025-01-01");
+        assert_eq!(add_days("2024-03-01", -1).unwrap(), "2024-02-29");
+    }
+
+    #[test]
+    fn test_calendar_rejects_invalid_dates() {
+        for date in [
+            "",
+            "2025-02-29",
+            "1900-02-29",
+            "2025-04-31",
+            "2025-01-00",
+            "2025-00-10",
+            "2025-1-01",
+            "25-01-01",
+            "2025/01/01",
+            "2025-01-01-01",
+            "abcd-01-01",
+        ] {
+            assert_eq!(days_since_epoch(date), None, "{}", date);
+            assert_eq!(add_days(date, 1), None);
+        }
+        assert_eq!(days_since_epoch("2000-02-29"), Some(11016));
+        assert_eq!(add_days("2025-06-15", 0).unwrap(), "2025-06-15");
+        assert_eq!(add_days("1970-01-01", -719468).unwrap(), "0000-03-01");
+    }
+}
@@ -2,37 +2,118 @@ use serde::{Deserialize, Serialize};
 
 /// Supported countries for the EMS system
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "CountryRepr")]
 pub enum Country {
     Germany,
     Spain,
     Portugal,
+    France,
+    Italy,
+    Netherlands,
+    Austria,
+    Switzerland,
+    /// Any other country by its ISO 3166-1 alpha-2 code, created with `Country::from_code`
+    Other(String),
+}
+
+/// Serialized form of `Country`, checked by `Country::from_code` when deserializing
+#[derive(Deserialize)]
+enum CountryRepr {
+    Germany,
+    Spain,
+    Portugal,
+    France,
+    Italy,
+    Netherlands,
+    Austria,
+    Switzerland,
+    Other(String),
+}
+
+impl TryFrom<CountryRepr> for Country {
+    type Error = String;
+
+    fn try_from(repr: CountryRepr) -> Result<Self, Self::Error> {
+        Ok(match repr {
+            CountryRepr::Germany => Country::Germany,
+            CountryRepr::Spain => Country::Spain,
+            CountryRepr::Portugal => Country::Portugal,
+            CountryRepr::France => Country::France,
+            CountryRepr::Italy => Country::Italy,
+            CountryRepr::Netherlands => Country::Netherlands,
+            CountryRepr::Austria => Country::Austria,
+            CountryRepr::Switzerland => Country::Switzerland,
+            CountryRepr::Other(code) => Country::from_code(&code)?,
+        })
+    }
 }
 
 impl Country {
+    /// Country of an ISO 3166-1 alpha-2 code (case insensitive). Codes without a variant
+    /// become `Country::Other`.
+    pub fn from_code(code: &str) -> Result<Self, String> {
+        let code = code.trim().to_ascii_uppercase();
+        if code.len() != 2 || !code.chars().all(|c| c.is_ascii_uppercase()) {
+            return Err(format!(
+                "Invalid country code: {:?}. Must be two letters (ISO 3166-1 alpha-2)",
+                code
+            ));
+        }
+        Ok(match code.as_str() {
+            "DE" => Country::Germany,
+            "ES" => Country::Spain,
+            "PT" => Country::Portugal,
+            "FR" => Country::France,
+            "IT" => Country::Italy,
+            "NL" => Country::Netherlands,
+            "AT" => Country::Austria,
+            "CH" => Country::Switzerland,
+            _ => Country::Other(code),
+        })
+    }
+
     /// Get the ISO 3166-1 alpha-2 country code
-    pub fn code(&self) -> &'static str {
+    pub fn code(&self) -> &str {
         match self {
             Country::Germany => "DE",
             Country::Spain => "ES",
             Country::Portugal => "PT",
+            Country::France => "FR",
+            Country::Italy => "IT",
+            Country::Netherlands => "NL",
+            Country::Austria => "AT",
+            Country::Switzerland => "CH",
+            Country::Other(code) => code,
         }
     }
 
-    /// Get the country name in English
-    pub fn name(&self) -> &'static str {
+    /// Get the country name in English, the ISO code for other countries
+    pub fn name(&self) -> &str {
         match self {
             Country::Germany => "Germany",
             Country::Spain => "Spain",
             Country::Portugal => "Portugal",
+            Country::France => "France",
+            Country::Italy => "Italy",
+            Country::Netherlands => "Netherlands",
+            Country::Austria => "Austria",
+            Country::Switzerland => "Switzerland",
+            Country::Other(code) => code,
         }
     }
 
-    /// Get the local name of the country
-    pub fn local_name(&self) -> &'static str {
+    /// Get the local name of the country, the ISO code for other countries
+    pub fn local_name(&self) -> &str {
         match self {
             Country::Germany => "Deutschland",
             Country::Spain => "España",
             Country::Portugal => "Portugal",
+            Country::France => "France",
+            Country::Italy => "Italia",
+            Country::Netherlands => "Nederland",
+            Country::Austria => "Österreich",
+            Country::Switzerland => "Schweiz",
+            Country::Other(code) => code,
         }
     }
 }
@@ -67,6 +148,68 @@ impl Coordinates {
             longitude,
         })
     }
+
+    /// Great-circle distance to other coordinates in km (haversine formula)
+    pub fn distance_to(&self, other: &Coordinates) -> f64 {
+        let (lat1, lat2) = (self.latitude.to_radians(), other.latitude.to_radians());
+        let d_lat = lat2 - lat1;
+        let d_lon = (other.longitude - self.longitude).to_radians();
+        let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+    }
+
+    /// Solar elevation above the horizon in degrees on `day_of_year` (0 for January 1st) at
+    /// `hour_utc` (fractional hours), negative at night
+    pub fn solar_elevation(&self, day_of_year: u16, hour_utc: f64) -> f64 {
+        let (declination, equation_of_time) = solar_declination_and_eot(day_of_year);
+        let solar_time = hour_utc + self.longitude / 15.0 + equation_of_time / 60.0;
+        let hour_angle = (15.0 * (solar_time - 12.0)).to_radians();
+        let latitude = self.latitude.to_radians();
+        (latitude.sin() * declination.sin() + latitude.cos() * declination.cos() * hour_angle.cos())
+            .clamp(-1.0, 1.0)
+            .asin()
+            .to_degrees()
+    }
+
+    /// Sunrise and sunset in fractional UTC hours on `day_of_year` (0 for January 1st), None
+    /// during polar day or polar night
+    pub fn sunrise_sunset(&self, day_of_year: u16) -> Option<(f64, f64)> {
+        let (declination, equation_of_time) = solar_declination_and_eot(day_of_year);
+        let cos_hour_angle = -self.latitude.to_radians().tan() * declination.tan();
+        if !(-1.0..=1.0).contains(&cos_hour_angle) {
+            return None;
+        }
+        let half_day = cos_hour_angle.acos().to_degrees() / 15.0;
+        let noon_utc = 12.0 - self.longitude / 15.0 - equation_of_time / 60.0;
+        Some((noon_utc - half_day, noon_utc + half_day))
+    }
+
+    /// Solar elevation in degrees at the middle of each of the 8760 hours of a year, in UTC
+    pub fn hourly_solar_elevation(&self) -> Vec<f64> {
+        (0..8760)
+            .map(|hour| self.solar_elevation((hour / 24) as u16, (hour % 24) as f64 + 0.5))
+            .collect()
+    }
+}
+
+/// Mean radius of the earth in km
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// Solar declination in radians and equation of time in minutes on `day_of_year` (Spencer,
+/// 1971)
+fn solar_declination_and_eot(day_of_year: u16) -> (f64, f64) {
+    let b = 2.0 * std::f64::consts::PI * day_of_year as f64 / 365.0;
+    let declination = 0.006918 - 0.399912 * b.cos() + 0.070257 * b.sin()
+        - 0.006758 * (2.0 * b).cos()
+        + 0.000907 * (2.0 * b).sin()
+        - 0.002697 * (3.0 * b).cos()
+        + 0.00148 * (3.0 * b).sin();
+    let equation_of_time = 229.18
+        * (0.000075 + 0.001868 * b.cos()
+            - 0.032077 * b.sin()
+            - 0.014615 * (2.0 * b).cos()
+            - 0.040849 * (2.0 * b).sin());
+    (declination, equation_of_time)
 }
 
 /// Address information for a location
@@ -177,3 +320,72 @@ impl Location {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_country_codes() {
+        assert_eq!(Country::from_code("at"), Ok(Country::Austria));
+        assert_eq!(Country::from_code(" CH "), Ok(Country::Switzerland));
+        let belgium = Country::from_code("be").unwrap();
+        assert_eq!(belgium, Country::Other("BE".to_string()));
+        assert_eq!((belgium.code(), belgium.name()), ("BE", "BE"));
+        assert!(Country::from_code("BEL").is_err());
+        assert!(Country::from_code("B1").is_err());
+        for country in [Country::Germany, Country::Italy, Country::Netherlands] {
+            assert_eq!(Country::from_code(country.code()), Ok(country));
+        }
+    }
+
+    #[test]
+    fn test_country_deserialization() {
+        let parse = |json| serde_json::from_value::<Country>(json);
+        assert_eq!(
+            parse(serde_json::json!("Switzerland")).unwrap(),
+            Country::Switzerland
+        );
+        assert_eq!(
+            parse(serde_json::json!({"Other": "be"})).unwrap(),
+            Country::Other("BE".to_string())
+        );
+        assert!(parse(serde_json::json!({"Other": "not a code"})).is_err());
+        let belgium = Country::Other("BE".to_string());
+        assert_eq!(
+            parse(serde_json::to_value(&belgium).unwrap()).unwrap(),
+            belgium
+        );
+    }
+
+    #[test]
+    fn test_distance() {
+        let berlin = Coordinates::new(52.52, 13.405).unwrap();
+        let madrid = Coordinates::new(40.4168, -3.7038).unwrap();
+        assert!((berlin.distance_to(&madrid) - 1869.0).abs() < 5.0);
+        assert!((madrid.distance_to(&berlin) - berlin.distance_to(&madrid)).abs() < 1e-9);
+        assert_eq!(berlin.distance_to(&berlin), 0.0);
+    }
+
+    #[test]
+    fn test_solar_position() {
+        let berlin = Coordinates::new(52.52, 13.405).unwrap();
+        // summer solstice: sunrise 04:43 and sunset 21:33 CEST
+        let (sunrise, sunset) = berlin.sunrise_sunset(171).unwrap();
+        assert!((sunrise - (2.0 + 43.0 / 60.0)).abs() < 0.15);
+        assert!((sunset - (19.0 + 33.0 / 60.0)).abs() < 0.15);
+
+        // at solar noon of the equinox the sun stands 90° minus the latitude high
+        let noon_utc = 12.0 - 13.405 / 15.0;
+        assert!((berlin.solar_elevation(79, noon_utc) - (90.0 - 52.52)).abs() < 1.0);
+        assert!(berlin.solar_elevation(79, noon_utc + 12.0) < 0.0);
+        let elevation = berlin.hourly_solar_elevation();
+        assert_eq!(elevation.len(), 8760);
+        assert!(elevation[171 * 24 + 11] > elevation[355 * 24 + 11]);
+
+        // polar day and night in Tromsø
+        let tromso = Coordinates::new(69.65, 18.96).unwrap();
+        assert!(tromso.sunrise_sunset(171).is_none());
+        assert!(tromso.sunrise_sunset(355).is_none());
+    }
+}
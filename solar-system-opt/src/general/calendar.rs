@@ -0,0 +1,59 @@
+use anyhow::{Result, bail};
+
+pub(crate) use ems_model::general::calendar::DAYS_IN_MONTH;
+
+/// Hours of the modelled (non-leap) year
+pub(crate) const NUM_HOURS: usize = 8760;
+
+pub(crate) const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Day of the modelled (non-leap) year, 0 for Jan 1
+pub fn day_of_year(month: usize, day: usize) -> Result<usize> {
+    if !(1..=12).contains(&month) || day == 0 || day > DAYS_IN_MONTH[month - 1] {
+        bail!("Invalid date {:02}-{:02} in a non-leap year", month, day);
+    }
+    Ok(DAYS_IN_MONTH[..month - 1].iter().sum::<usize>() + day - 1)
+}
+
+/// Month and day (both starting at 1) of a day of the year
+fn month_and_day(day_of_year: usize) -> (usize, usize) {
+    let mut remaining_days = day_of_year % 365;
+    for (month, &days) in DAYS_IN_MONTH.iter().enumerate() {
+        if remaining_days < days {
+            return (month + 1, remaining_days + 1);
+        }
+        remaining_days -= days;
+    }
+    unreachable!("the months cover 365 days")
+}
+
+/// Readable date of a day of the year, e.g. "Jun 21"
+pub fn date_label(day_of_year: usize) -> String {
+    let (month, day) = month_and_day(day_of_year);
+    format!("{} {}", MONTHS[month - 1], day)
+}
+
+/// Date of a day of the year for file names, e.g. "06-21"
+pub fn date_file_label(day_of_year: usize) -> String {
+    let (month, day) = month_and_day(day_of_year);
+    format!("{:02}-{:02}", month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_day_of_year_and_labels() {
+        assert_eq!(day_of_year(1, 1).unwrap(), 0);
+        assert_eq!(day_of_year(6, 21).unwrap(), 171);
+        assert_eq!(day_of_year(12, 31).unwrap(), 364);
+        assert!(day_of_year(2, 29).is_err());
+        assert!(day_of_year(13, 1).is_err());
+        assert_eq!(date_label(171), "Jun 21");
+        assert_eq!(date_file_label(364), "12-31");
+        assert_eq!(DAYS_IN_MONTH.iter().sum::<usize>() * 24, NUM_HOURS);
+    }
+}
@@ -0,0 +1,104 @@
+/// Currency used for all cost inputs and money outputs.
+///
+/// Without a conversion rate the configured costs are taken to already be in `code`, so only the
+/// labels change. With a conversion rate the costs are treated as euros (the defaults are euro
+/// prices) and money outputs are converted into `code`: the costs and the objective of the
+/// optimization results as well as the ROI figures.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Currency {
+    pub code: String,                 // ISO 4217 code, e.g. "EUR", "CHF", "GBP", "USD"
+    pub conversion_rate: Option<f64>, // Units of this currency per euro; None if costs are already in this currency
+}
+
+impl Default for Currency {
+    fn default() -> Self {
+        Self::eur()
+    }
+}
+
+impl Currency {
+    pub fn eur() -> Self {
+        Self::new("EUR")
+    }
+
+    /// Currency whose costs are already given in `code`
+    pub fn new(code: &str) -> Self {
+        Self {
+            code: code.to_uppercase(),
+            conversion_rate: None,
+        }
+    }
+
+    /// Currency whose money outputs are converted from euros with `rate` units per euro
+    pub fn with_conversion_rate(code: &str, rate: f64) -> Result<Self, Box<dyn std::error::Error>> {
+        if !(rate.is_finite() && rate > 0.0) {
+            return Err(format!(
+                "Conversion rate for {} must be positive, got {}",
+                code, rate
+            )
+            .into());
+        }
+        Ok(Self {
+            conversion_rate: Some(rate),
+            ..Self::new(code)
+        })
+    }
+
+    /// Get the symbol used when labeling amounts, falls back to the ISO code
+    pub fn symbol(&self) -> &str {
+        match self.code.as_str() {
+            "EUR" => "€",
+            "GBP" => "£",
+            "USD" => "$",
+            code => code,
+        }
+    }
+
+    /// Converts an amount from the configured costs into this currency
+    pub fn convert(&self, amount: f64) -> f64 {
+        amount * self.conversion_rate.unwrap_or(1.0)
+    }
+
+    /// Formats an already converted amount with two decimals and the currency symbol
+    pub fn format(&self, amount: f64) -> String {
+        format!("{:.2} {}", amount, self.symbol())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_currency_conversion() {
+        let eur = Currency::default();
+        assert_eq!(eur.code, "EUR");
+        assert_eq!(eur.symbol(), "€");
+        assert_eq!(eur.convert(100.0), 100.0);
+
+        let chf = Currency::new("chf");
+        assert_eq!(chf.code, "CHF");
+        assert_eq!(chf.symbol(), "CHF");
+        assert_eq!(chf.convert(100.0), 100.0);
+
+        let gbp = Currency::with_conversion_rate("GBP", 0.85).unwrap();
+        assert_eq!(gbp.symbol(), "£");
+        assert!((gbp.convert(100.0) - 85.0).abs() < 1e-12);
+        assert_eq!(gbp.format(gbp.convert(100.0)), "85.00 £");
+    }
+
+    #[test]
+    fn test_currency_rejects_invalid_rates() {
+        for rate in [0.0, -1.1, f64::NAN, f64::INFINITY] {
+            let err = Currency::with_conversion_rate("USD", rate).unwrap_err();
+            assert!(err.to_string().contains("USD"));
+        }
+
+        // revenues and savings below zero keep their sign
+        let usd = Currency::with_conversion_rate("usd", 1.1).unwrap();
+        assert_eq!(usd.code, "USD");
+        assert!((usd.convert(-50.0) + 55.0).abs() < 1e-12);
+        assert_eq!(usd.format(-55.0), "-55.00 $");
+        assert_eq!(usd.convert(0.0), 0.0);
+    }
+}
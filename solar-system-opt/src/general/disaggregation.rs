@@ -0,0 +1,220 @@
+use anyhow::{Result, bail};
+
+/// Settings of the demand disaggregation
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisaggregationConfig {
+    pub heating_base_temperature: f64, // Daily mean temperature (°C) above which nothing is heated
+    pub ev_start_hour: usize,          // Hour of day (0-23) night charging may start
+    pub ev_end_hour: usize, // Hour of day night charging ends, before `ev_start_hour` for windows crossing midnight
+    pub ev_min_power_w: f64, // Load step above the usual load of the hour that counts as charging
+    pub ev_min_hours: usize, // Shorter steps are left in the base load
+}
+
+impl Default for DisaggregationConfig {
+    fn default() -> Self {
+        Self {
+            heating_base_temperature: 15.0,
+            ev_start_hour: 18,
+            ev_end_hour: 7,
+            ev_min_power_w: 2000.0,
+            ev_min_hours: 2,
+        }
+    }
+}
+
+/// Measured household demand split into its components, each in Wh per hour
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisaggregatedDemand {
+    pub base_load: Vec<f64>,
+    pub heating: Vec<f64>,
+    pub ev_charging: Vec<f64>,
+}
+
+impl DisaggregationConfig {
+    fn is_night(&self, hour_of_day: usize) -> bool {
+        if self.ev_start_hour < self.ev_end_hour {
+            (self.ev_start_hour..self.ev_end_hour).contains(&hour_of_day)
+        } else {
+            hour_of_day >= self.ev_start_hour || hour_of_day < self.ev_end_hour
+        }
+    }
+}
+
+/// Estimates the heating and EV charging contained in a measured demand profile, so that an
+/// existing heat pump or car can be modelled explicitly without counting it twice.
+///
+/// EV charging is found by its night charging signature: a load step of at least
+/// `ev_min_power_w` above the usual load of the hour (25th percentile over all days), lasting
+/// `ev_min_hours` or longer. Its energy is the load above the hours just before and after the
+/// step. Heating is the part of the remaining daily energy that grows with the heating degree
+/// days (least squares fit against the daily mean temperature), spread over the day like the
+/// remaining load.
+pub fn disaggregate_demand(
+    demand: &[f64],
+    temperature: &[f64],
+    config: &DisaggregationConfig,
+) -> Result<DisaggregatedDemand> {
+    if demand.is_empty() || !demand.len().is_multiple_of(24) || demand.len() != temperature.len() {
+        bail!(
+            "Demand and temperature need the same number of whole days, got {} and {} hours",
+            demand.len(),
+            temperature.len()
+        );
+    }
+    let num_days = demand.len() / 24;
+
+    // Usual load of every hour of the day
+    let baseline: Vec<f64> = (0..24)
+        .map(|hour_of_day| {
+            let mut loads: Vec<f64> = (0..num_days)
+                .map(|day| demand[day * 24 + hour_of_day])
+                .collect();
+            loads.sort_by(f64::total_cmp);
+            loads[loads.len() / 4]
+        })
+        .collect();
+
+    // Night charging steps
+    let mut ev_charging = vec![0.0; demand.len()];
+    let is_step =
+        |t: usize| config.is_night(t % 24) && demand[t] - baseline[t % 24] >= config.ev_min_power_w;
+    let mut t = 0;
+    while t < demand.len() {
+        if !is_step(t) {
+            t += 1;
+            continue;
+        }
+        let start = t;
+        while t < demand.len() && is_step(t) {
+            t += 1;
+        }
+        if t - start < config.ev_min_hours {
+            continue;
+        }
+        let before = start.checked_sub(1).map(|hour| demand[hour]);
+        let after = demand.get(t).copied();
+        let reference = |hour: usize| match (before, after) {
+            (Some(before), Some(after)) => (before + after) / 2.0,
+            (Some(level), None) | (None, Some(level)) => level,
+            (None, None) => baseline[hour % 24],
+        };
+        for hour in start..t {
+            ev_charging[hour] = (demand[hour] - reference(hour)).max(0.0);
+        }
+    }
+    let remainder: Vec<f64> = demand
+        .iter()
+        .zip(&ev_charging)
+        .map(|(demand, ev)| demand - ev)
+        .collect();
+
+    // Daily energy = a + b * heating degree days
+    let daily_energy: Vec<f64> = remainder.chunks(24).map(|day| day.iter().sum()).collect();
+    let degree_days: Vec<f64> = temperature
+        .chunks(24)
+        .map(|day| (config.heating_base_temperature - day.iter().sum::<f64>() / 24.0).max(0.0))
+        .collect();
+    let mean_degree_days = degree_days.iter().sum::<f64>() / num_days as f64;
+    let mean_energy = daily_energy.iter().sum::<f64>() / num_days as f64;
+    let (covariance, variance) = degree_days.iter().zip(&daily_energy).fold(
+        (0.0, 0.0),
+        |(covariance, variance), (degree_days, energy)| {
+            let deviation = degree_days - mean_degree_days;
+            (
+                covariance + deviation * (energy - mean_energy),
+                variance + deviation * deviation,
+            )
+        },
+    );
+    let slope = if variance > 0.0 {
+        (covariance / variance).max(0.0)
+    } else {
+        0.0
+    };
+
+    let mut heating = vec![0.0; demand.len()];
+    for day in 0..num_days {
+        let heating_energy = (slope * degree_days[day]).min(daily_energy[day]);
+        if heating_energy <= 0.0 {
+            continue;
+        }
+        for hour in day * 24..(day + 1) * 24 {
+            heating[hour] = heating_energy * remainder[hour] / daily_energy[day];
+        }
+    }
+
+    Ok(DisaggregatedDemand {
+        base_load: remainder
+            .iter()
+            .zip(&heating)
+            .map(|(remainder, heating)| remainder - heating)
+            .collect(),
+        heating,
+        ev_charging,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disaggregate_demand() {
+        let num_days = 365;
+        // mean temperature between -5 and 25 °C over the year
+        let daily_temperature: Vec<f64> = (0..num_days)
+            .map(|day| 10.0 - 15.0 * (2.0 * std::f64::consts::PI * day as f64 / 365.0).cos())
+            .collect();
+        let temperature: Vec<f64> = (0..num_days * 24)
+            .map(|hour| daily_temperature[hour / 24])
+            .collect();
+        // 400 W base load, 80 W heating per degree day, 7 kW charging from 1:00 to 3:00
+        // every third night
+        let true_heating: Vec<f64> = (0..num_days * 24)
+            .map(|hour| 80.0 * (15.0 - daily_temperature[hour / 24]).max(0.0))
+            .collect();
+        let true_ev: Vec<f64> = (0..num_days * 24)
+            .map(|hour| {
+                if (hour / 24).is_multiple_of(3) && (1..3).contains(&(hour % 24)) {
+                    7000.0
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        let demand: Vec<f64> = (0..num_days * 24)
+            .map(|hour| 400.0 + true_heating[hour] + true_ev[hour])
+            .collect();
+
+        let result =
+            disaggregate_demand(&demand, &temperature, &DisaggregationConfig::default()).unwrap();
+
+        for hour in 0..demand.len() {
+            assert!((result.ev_charging[hour] - true_ev[hour]).abs() < 1e-6);
+            assert!((result.heating[hour] - true_heating[hour]).abs() < 1e-6);
+            assert!((result.base_load[hour] - 400.0).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_disaggregate_demand_without_heating_or_ev() {
+        let demand = vec![500.0; 48];
+        let temperature = vec![20.0; 48];
+        let config = DisaggregationConfig::default();
+        let result = disaggregate_demand(&demand, &temperature, &config).unwrap();
+        assert_eq!(result.base_load, demand);
+        assert!(result.heating.iter().all(|&heating| heating == 0.0));
+        assert!(result.ev_charging.iter().all(|&ev| ev == 0.0));
+
+        // a short step or one outside the night window stays in the base load
+        let mut demand = vec![500.0; 48];
+        demand[2] = 4000.0;
+        demand[12] = 4000.0;
+        demand[13] = 4000.0;
+        let result = disaggregate_demand(&demand, &temperature, &config).unwrap();
+        assert!(result.ev_charging.iter().all(|&ev| ev == 0.0));
+
+        assert!(disaggregate_demand(&demand[..47], &temperature[..47], &config).is_err());
+        assert!(disaggregate_demand(&demand, &temperature[..24], &config).is_err());
+    }
+}
@@ -1,6 +1,9 @@
 use anyhow::{Context, Result};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use tracing::debug;
+
+use crate::general::resampling::{Quantity, StandardDayProfile, to_hourly};
 
 /// Represents monthly energy demand in kWh
 #[derive(Debug, Clone)]
@@ -62,7 +65,7 @@ pub fn load_hourly_demand(file_path: &str) -> Result<Vec<f64>> {
             .filter(|c| c.is_ascii_digit() || *c == '.' || *c == '-' || *c == ',')
             .collect();
         if line_num < 5 {
-            println!(
+            debug!(
                 "Line {}: '{}' (cleaned: '{}', length: {})",
                 line_num + 1,
                 trimmed,
@@ -92,7 +95,12 @@ pub fn load_hourly_demand(file_path: &str) -> Result<Vec<f64>> {
         hourly_demand.push(value / 1000.0);
     }
 
-    Ok(hourly_demand)
+    // 15-minute meter data is summed up, daily readings follow a household profile
+    Ok(to_hourly(
+        hourly_demand,
+        Quantity::Energy,
+        &StandardDayProfile::Household,
+    ))
 }
 
 /// Generates a scaled hourly load curve based on monthly demand totals
@@ -185,6 +193,18 @@ mod tests {
         assert_eq!(hourly_demand, vec![1.0, 2.0, 1.5]); // Converted from Wh to kWh
     }
 
+    #[test]
+    fn test_load_quarter_hourly_demand() {
+        // 15-minute meter data of a year with 250 Wh per quarter
+        let test_data = "250\n".repeat(35040);
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(&temp_file, test_data).unwrap();
+
+        let hourly_demand = load_hourly_demand(temp_file.path().to_str().unwrap()).unwrap();
+        assert_eq!(hourly_demand.len(), 8760);
+        assert!((hourly_demand[0] - 1.0).abs() < 1e-9);
+    }
+
     #[test]
     fn test_generate_scaled_load_curve() {
         // Create test monthly demand
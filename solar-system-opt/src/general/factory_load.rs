@@ -0,0 +1,768 @@
+use std::collections::HashMap;
+
+use anyhow::{Result, anyhow, bail};
+use ems_model::factory::Factory;
+use ems_model::factory::line::Line;
+use ems_model::factory::machine::{MachineControl, MaintenanceWindow, Step, StepType};
+use ems_model::factory::worker::{Schedule, WeekDay, Worker, add_days};
+
+const HOURS_PER_YEAR: usize = 8760;
+const MINUTES_PER_WEEK: usize = 7 * 24 * 60;
+
+/// Production of a line: its steps, the workers staffing it and the weekly target
+#[derive(Debug, Clone)]
+pub struct LineProduction {
+    pub line: Line,
+    pub steps: Vec<Step>, // Steps referenced by the `step_id` of the line nodes
+    pub workers: Vec<Worker>, // Staff of this line; human-controlled machines and tasks need one of them on shift
+    pub units_per_week: usize,
+}
+
+/// Why a step of a unit couldn't be scheduled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnscheduledReason {
+    NoQualifiedWorker, // No worker of the line has the required specialization
+    NoShift,           // No qualified worker is on shift and free for the whole runtime this week
+    EndOfWeek,         // The unattended machine would run past the end of the week
+}
+
+/// Step that couldn't be scheduled; the unit and the remaining units of the week are not produced
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnscheduledStep {
+    pub line: String,
+    pub unit: usize, // 1-based unit of the week
+    pub node: String,
+    pub step: String,
+    pub reason: UnscheduledReason,
+}
+
+/// Scheduled weekly load of a line
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeeklyLoad {
+    pub hourly_load: Vec<f64>, // 168 hours from Monday 00:00 in Wh
+    pub units_produced: usize,
+    pub startups: usize,                      // Machine switch-ons a week
+    pub unscheduled: Option<UnscheduledStep>, // Set if the target wasn't reached
+}
+
+/// Yearly load of a factory
+#[derive(Debug, Clone, PartialEq)]
+pub struct FactoryLoad {
+    pub hourly_demand: Vec<f64>,    // 8760 hours in Wh, lines and base loads
+    pub hourly_base_load: Vec<f64>, // Part of `hourly_demand` from the base loads
+    pub units_produced: usize,      // Units a week over all lines
+    pub unscheduled: Vec<UnscheduledStep>, // First unscheduled step of every line missing its target
+}
+
+impl LineProduction {
+    /// Schedules the weekly production and returns the electricity demand of the line for the
+    /// 168 hours of a week starting Monday 00:00, in Wh per hour.
+    ///
+    /// Every unit passes all steps in dependency order, each node of the line is a station that
+    /// works on one unit at a time. A step starts as soon as its prerequisites for the unit are
+    /// done and its station is free. Human-controlled machines and tasks need a worker with the
+    /// `required_specialization` who is on shift and not busy with another step for the whole
+    /// runtime, computer-controlled machines run unattended. Production stops at the first step
+    /// that can't be scheduled, which is reported in `unscheduled`.
+    ///
+    /// Machines draw their `power_consumption` in W for the whole runtime. Between runs they
+    /// stay in standby if the idle time is shorter than `startup_minutes` plus
+    /// `min_off_minutes` or the standby costs less energy than `startup_energy`; otherwise they
+    /// are switched off and draw the startup energy over `startup_minutes` before the next run.
+    /// The week repeats, so the idle time after the last run ends with the first run.
+    ///
+    /// Machines don't run during their weekly `maintenance` windows and are switched off over
+    /// them. Dated windows only apply to a calendar year, see `factory_load_profile_in_year`.
+    pub fn weekly_load_profile(&self) -> Result<WeeklyLoad> {
+        self.schedule_week(None)
+    }
+
+    /// Schedules the week starting on `monday` (YYYY-MM-DD), or a generic week if None
+    fn schedule_week(&self, monday: Option<&str>) -> Result<WeeklyLoad> {
+        if let Err(errors) = self.line.validate() {
+            bail!("Invalid line {}: {}", self.line.name, errors.join(", "));
+        }
+        let order = self
+            .line
+            .topological_sort()
+            .ok_or_else(|| anyhow!("Line {} contains cycles", self.line.name))?;
+        let steps: HashMap<&str, &Step> = self
+            .steps
+            .iter()
+            .map(|step| (step.id.as_str(), step))
+            .collect();
+        let mut stations = Vec::with_capacity(order.len());
+        for node_id in &order {
+            let node = &self.line.nodes[node_id];
+            let step = *steps.get(node.step_id()).ok_or_else(|| {
+                anyhow!("Step {} of node {} is missing", node.step_id(), node.name)
+            })?;
+            step.validate().map_err(|e| anyhow!(e))?;
+            stations.push((node_id, step));
+        }
+        for worker in &self.workers {
+            if let Err(errors) = worker.validate() {
+                bail!(
+                    "Invalid schedule of worker {}: {}",
+                    worker.name,
+                    errors.join(", ")
+                );
+            }
+        }
+
+        // Minutes every worker is on shift and not busy with another step
+        let mut available: Vec<Vec<bool>> = self
+            .workers
+            .iter()
+            .map(|worker| shift_minutes(worker.get_schedule()))
+            .collect();
+        let downtime: Vec<Vec<bool>> = stations
+            .iter()
+            .map(|(_, step)| downtime_minutes(step, monday))
+            .collect();
+        let always_on = vec![true; MINUTES_PER_WEEK];
+        let mut power_w = vec![0.0; MINUTES_PER_WEEK];
+        let mut station_free = vec![0; stations.len()];
+        let mut runs: Vec<Vec<(usize, usize)>> = vec![Vec::new(); stations.len()];
+        let mut units_produced = 0;
+        let mut unscheduled = None;
+        'units: for unit in 0..self.units_per_week {
+            let mut finished: HashMap<&str, usize> = HashMap::new();
+            for (index, (node_id, step)) in stations.iter().enumerate() {
+                let ready = self.line.nodes[*node_id]
+                    .dependencies()
+                    .iter()
+                    .map(|prerequisite| finished[prerequisite.as_str()])
+                    .max()
+                    .unwrap_or(0)
+                    .max(station_free[index]);
+                let runtime = step.runtime_minutes.ceil() as usize;
+                let attended = matches!(step.control, MachineControl::Human)
+                    || matches!(step.step_type, StepType::Task);
+                let start = if attended {
+                    self.staff_step(step, &mut available, &downtime[index], ready, runtime)
+                } else {
+                    next_window(&always_on, &downtime[index], ready, runtime)
+                        .ok_or(UnscheduledReason::EndOfWeek)
+                };
+                let start = match start {
+                    Ok(start) => start,
+                    Err(reason) => {
+                        unscheduled = Some(UnscheduledStep {
+                            line: self.line.name.clone(),
+                            unit: unit + 1,
+                            node: self.line.nodes[*node_id].name.clone(),
+                            step: step.name.clone(),
+                            reason,
+                        });
+                        break 'units;
+                    }
+                };
+
+                if matches!(step.step_type, StepType::Machine) {
+                    for power in &mut power_w[start..start + runtime] {
+                        *power += step.power_consumption;
+                    }
+                }
+                station_free[index] = start + runtime;
+                runs[index].push((start, start + runtime));
+                finished.insert(node_id.as_str(), start + runtime);
+            }
+            units_produced += 1;
+        }
+
+        let mut startups = 0;
+        for (((_, step), runs), downtime) in stations.iter().zip(&runs).zip(&downtime) {
+            if matches!(step.step_type, StepType::Machine) {
+                startups += add_idle_load(step, runs, downtime, &mut power_w);
+            }
+        }
+
+        Ok(WeeklyLoad {
+            hourly_load: power_w
+                .chunks(60)
+                .map(|hour| hour.iter().sum::<f64>() / 60.0)
+                .collect(),
+            units_produced,
+            startups,
+            unscheduled,
+        })
+    }
+
+    /// The production with the schedules of the workers in the week starting on `monday`
+    /// (YYYY-MM-DD), absences applied
+    fn staffed_in_week(&self, monday: &str) -> Result<LineProduction> {
+        let mut staffed = self.clone();
+        for worker in &mut staffed.workers {
+            if let Err(errors) = worker.validate() {
+                bail!(
+                    "Invalid schedule of worker {}: {}",
+                    worker.name,
+                    errors.join(", ")
+                );
+            }
+            worker.schedule = worker.schedule_for_week(monday).map_err(|e| anyhow!(e))?;
+            worker.absences.clear();
+        }
+        Ok(staffed)
+    }
+
+    /// Assigns the qualified worker who can start the step first, from `ready` on and outside
+    /// the `downtime` of the machine, and marks them busy for the runtime. Returns the start
+    /// minute.
+    fn staff_step(
+        &self,
+        step: &Step,
+        available: &mut [Vec<bool>],
+        downtime: &[bool],
+        ready: usize,
+        runtime: usize,
+    ) -> Result<usize, UnscheduledReason> {
+        let qualified: Vec<usize> = (0..self.workers.len())
+            .filter(|&worker| {
+                step.required_specialization
+                    .as_ref()
+                    .is_none_or(|specialization| {
+                        self.workers[worker].has_specialization(specialization)
+                    })
+            })
+            .collect();
+        if qualified.is_empty() {
+            return Err(UnscheduledReason::NoQualifiedWorker);
+        }
+        let (worker, start) = qualified
+            .into_iter()
+            .filter_map(|worker| {
+                next_window(&available[worker], downtime, ready, runtime)
+                    .map(|start| (worker, start))
+            })
+            .min_by_key(|&(_, start)| start)
+            .ok_or(UnscheduledReason::NoShift)?;
+        available[worker][start..start + runtime].fill(false);
+        Ok(start)
+    }
+}
+
+/// Adds the standby and startup load of a machine between its `runs` (start and end minute, in
+/// order) and returns the number of startups. The machine is switched off over its `downtime`.
+fn add_idle_load(
+    step: &Step,
+    runs: &[(usize, usize)],
+    downtime: &[bool],
+    power_w: &mut [f64],
+) -> usize {
+    let startup_minutes = step.startup_minutes.ceil() as usize;
+    let min_off_minutes = step.min_off_minutes.ceil() as usize;
+    let mut startups = 0;
+    for (index, &(_, end)) in runs.iter().enumerate() {
+        let next_start = match runs.get(index + 1) {
+            Some(&(start, _)) => start,
+            None => runs[0].0 + MINUTES_PER_WEEK,
+        };
+        let idle = next_start - end;
+        if idle == 0 {
+            continue;
+        }
+        let maintained = (end..next_start).any(|minute| downtime[minute % MINUTES_PER_WEEK]);
+        if !maintained
+            && (idle < startup_minutes + min_off_minutes
+                || step.standby_power * idle as f64 / 60.0 <= step.startup_energy)
+        {
+            for minute in end..next_start {
+                power_w[minute % MINUTES_PER_WEEK] += step.standby_power;
+            }
+        } else {
+            startups += 1;
+            let ramp = startup_minutes.max(1);
+            for minute in next_start - ramp..next_start {
+                power_w[minute % MINUTES_PER_WEEK] += step.startup_energy * 60.0 / ramp as f64;
+            }
+        }
+    }
+    startups
+}
+
+/// Minutes of the week (from Monday 00:00) in which the schedule has a shift. Overnight shifts
+/// run past midnight, Sunday night shifts wrap to Monday morning.
+fn shift_minutes(schedule: &Schedule) -> Vec<bool> {
+    let mut on_shift = vec![false; MINUTES_PER_WEEK];
+    for (day, weekday) in WeekDay::ALL.iter().enumerate() {
+        for shift in schedule.get_shifts(*weekday) {
+            let (start, end) = shift.minutes();
+            for minute in start as usize..end as usize {
+                on_shift[(day * 24 * 60 + minute) % MINUTES_PER_WEEK] = true;
+            }
+        }
+    }
+    on_shift
+}
+
+/// Minutes of the week (from Monday 00:00) in which the machine is down for maintenance. Dated
+/// windows only count if the week starting on `monday` (YYYY-MM-DD) is known.
+fn downtime_minutes(step: &Step, monday: Option<&str>) -> Vec<bool> {
+    let mut down = vec![false; MINUTES_PER_WEEK];
+    for window in &step.maintenance {
+        match window {
+            MaintenanceWindow::Weekly { day, start, end } => {
+                let offset = day.index() * 24 * 60;
+                down[offset + start.minutes() as usize..offset + end.minutes() as usize].fill(true);
+            }
+            MaintenanceWindow::Dated { from, until } => {
+                let Some(monday) = monday else {
+                    continue;
+                };
+                for day in 0..7 {
+                    let date = add_days(monday, day as i64).unwrap();
+                    if from.as_str() <= date.as_str() && date.as_str() <= until.as_str() {
+                        down[day * 24 * 60..(day + 1) * 24 * 60].fill(true);
+                    }
+                }
+            }
+        }
+    }
+    down
+}
+
+/// First minute from `earliest` at which `runtime` minutes fit into a shift outside the
+/// `downtime` of the machine, if any this week
+fn next_window(
+    on_shift: &[bool],
+    downtime: &[bool],
+    earliest: usize,
+    runtime: usize,
+) -> Option<usize> {
+    let mut start = earliest;
+    while start + runtime <= on_shift.len() {
+        let window = start..start + runtime;
+        match on_shift[window.clone()]
+            .iter()
+            .zip(&downtime[window])
+            .rposition(|(on, down)| !on || *down)
+        {
+            Some(gap) => start += gap + 1,
+            None => return Some(start),
+        }
+    }
+    None
+}
+
+/// Lookup of the production of a line by its id, e.g. a map from line id or a slice of lines
+pub trait LineRepository {
+    fn line_production(&self, line_id: &str) -> Option<&LineProduction>;
+}
+
+impl LineRepository for HashMap<String, LineProduction> {
+    fn line_production(&self, line_id: &str) -> Option<&LineProduction> {
+        self.get(line_id)
+    }
+}
+
+impl LineRepository for [LineProduction] {
+    fn line_production(&self, line_id: &str) -> Option<&LineProduction> {
+        self.iter().find(|production| production.line.id == line_id)
+    }
+}
+
+/// Load of the whole factory that doesn't belong to a line, e.g. lighting or HVAC
+#[derive(Debug, Clone)]
+pub struct BaseLoad {
+    pub name: String,
+    pub power_w: f64,
+    pub schedule: Option<Schedule>, // Hours the load is on; None for around the clock
+}
+
+impl BaseLoad {
+    /// Base load for the 168 hours of a week starting Monday 00:00, in Wh per hour
+    fn weekly_load(&self) -> Result<Vec<f64>> {
+        if !(self.power_w >= 0.0 && self.power_w.is_finite()) {
+            bail!(
+                "Base load {} needs a non-negative power, got {} W",
+                self.name,
+                self.power_w
+            );
+        }
+        Ok(match &self.schedule {
+            Some(schedule) => shift_minutes(schedule)
+                .chunks(60)
+                .map(|hour| hour.iter().filter(|on| **on).count() as f64 / 60.0 * self.power_w)
+                .collect(),
+            None => vec![self.power_w; 7 * 24],
+        })
+    }
+}
+
+/// Hourly electricity demand in Wh of `factory` for one year: the weekly production of every
+/// line of the factory, resolved from `lines`, plus the `base_loads`. Weeks repeat and the
+/// modelled year starts on a Monday. `hourly_demand` can be passed as `electricity_demand` to
+/// `run_simple_opt`; lines that couldn't be staffed for their target are listed in
+/// `unscheduled`. Absences of the workers are ignored, see `factory_load_profile_in_year`.
+pub fn factory_load_profile<R: LineRepository + ?Sized>(
+    factory: &Factory,
+    lines: &R,
+    base_loads: &[BaseLoad],
+) -> Result<FactoryLoad> {
+    let mut weekly = vec![0.0; 7 * 24];
+    let mut units_produced = 0;
+    let mut unscheduled = Vec::new();
+    for line_id in &factory.lines {
+        let load = line_production(factory, lines, line_id)?.weekly_load_profile()?;
+        for (total, line) in weekly.iter_mut().zip(&load.hourly_load) {
+            *total += line;
+        }
+        units_produced += load.units_produced;
+        unscheduled.extend(load.unscheduled);
+    }
+    with_base_loads(&weekly, base_loads, units_produced, unscheduled)
+}
+
+/// Hourly electricity demand in Wh of `factory` in the calendar `year`, like
+/// `factory_load_profile` but scheduled week by week with the absences of the workers and the
+/// dated maintenance windows of the machines applied.
+///
+/// The profile still starts on a Monday: week k holds the production of the week starting k
+/// weeks after the Monday on or before January 1st. `units_produced` is the lowest weekly
+/// production of the year and `unscheduled` holds, for every line, the first unscheduled step
+/// of its first week missing the target.
+pub fn factory_load_profile_in_year<R: LineRepository + ?Sized>(
+    factory: &Factory,
+    lines: &R,
+    base_loads: &[BaseLoad],
+    year: i32,
+) -> Result<FactoryLoad> {
+    let new_year = format!("{:04}-01-01", year);
+    let first_monday = WeekDay::of_date(&new_year)
+        .and_then(|day| add_days(&new_year, -(day.index() as i64)))
+        .ok_or_else(|| anyhow!("Invalid year {}", year))?;
+    let weeks = HOURS_PER_YEAR.div_ceil(7 * 24);
+    let mut hourly = vec![0.0; weeks * 7 * 24];
+    let mut weekly_units = vec![0; weeks];
+    let mut unscheduled = Vec::new();
+    for line_id in &factory.lines {
+        let production = line_production(factory, lines, line_id)?;
+        let mut first_unscheduled = None;
+        for week in 0..weeks {
+            let monday = add_days(&first_monday, 7 * week as i64).unwrap();
+            let load = production
+                .staffed_in_week(&monday)?
+                .schedule_week(Some(&monday))?;
+            for (total, line) in hourly[week * 7 * 24..].iter_mut().zip(&load.hourly_load) {
+                *total += line;
+            }
+            weekly_units[week] += load.units_produced;
+            first_unscheduled = first_unscheduled.or(load.unscheduled);
+        }
+        unscheduled.extend(first_unscheduled);
+    }
+    let units_produced = weekly_units.into_iter().min().unwrap_or(0);
+    with_base_loads(&hourly, base_loads, units_produced, unscheduled)
+}
+
+fn line_production<'a, R: LineRepository + ?Sized>(
+    factory: &Factory,
+    lines: &'a R,
+    line_id: &str,
+) -> Result<&'a LineProduction> {
+    lines
+        .line_production(line_id)
+        .ok_or_else(|| anyhow!("Line {} of factory {} is missing", line_id, factory.name))
+}
+
+/// Repeats the line demand (starting Monday 00:00) over the year and adds the base loads
+fn with_base_loads(
+    line_demand: &[f64],
+    base_loads: &[BaseLoad],
+    units_produced: usize,
+    unscheduled: Vec<UnscheduledStep>,
+) -> Result<FactoryLoad> {
+    let mut weekly_base_load = vec![0.0; 7 * 24];
+    for base_load in base_loads {
+        for (total, load) in weekly_base_load.iter_mut().zip(base_load.weekly_load()?) {
+            *total += load;
+        }
+    }
+    let yearly = |profile: &[f64]| -> Vec<f64> {
+        (0..HOURS_PER_YEAR)
+            .map(|hour| profile[hour % profile.len()])
+            .collect()
+    };
+    let hourly_base_load = yearly(&weekly_base_load);
+    Ok(FactoryLoad {
+        hourly_demand: yearly(line_demand)
+            .iter()
+            .zip(&hourly_base_load)
+            .map(|(line, base)| line + base)
+            .collect(),
+        hourly_base_load,
+        units_produced,
+        unscheduled,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ems_model::factory::worker::{Absence, AbsenceKind, Specialization, Time, WorkShift};
+
+    fn step(id: &str, control: MachineControl, power_w: f64, runtime_minutes: f64) -> Step {
+        Step {
+            power_consumption: power_w,
+            control,
+            ..Step::new(id.to_string(), id.to_string(), runtime_minutes)
+        }
+    }
+
+    fn worker(name: &str, specialization: Vec<Specialization>) -> Worker {
+        let mut worker = Worker::new(
+            name.to_string(),
+            name.to_string(),
+            specialization,
+            Schedule::default(),
+        );
+        for day in &WeekDay::ALL[..5] {
+            worker.add_work_shift(*day, Time::new(8, 0), Time::new(16, 0));
+        }
+        worker
+    }
+
+    fn production(units_per_week: usize) -> LineProduction {
+        let mut line = Line::new("Milling".to_string(), "line1".to_string());
+        line.add_step("cut".to_string(), "Cut".to_string(), "saw".to_string());
+        line.add_step("mill".to_string(), "Mill".to_string(), "cnc".to_string());
+        line.add_dependency("cut".to_string(), "mill".to_string())
+            .unwrap();
+        LineProduction {
+            line,
+            steps: vec![
+                step("saw", MachineControl::Human, 6000.0, 60.0),
+                step("cnc", MachineControl::Computer, 12000.0, 120.0),
+            ],
+            workers: vec![worker("Anna", Vec::new())],
+            units_per_week,
+        }
+    }
+
+    #[test]
+    fn test_factory_load_profile() {
+        let factory = Factory {
+            name: "Plant".to_string(),
+            id: "plant".to_string(),
+            location: "Berlin".to_string(),
+            lines: vec!["line1".to_string()],
+        };
+
+        // 8 units fill the Monday shift of the saw, the CNC runs unattended into the night
+        let load = factory_load_profile(&factory, &[production(8)][..], &[]).unwrap();
+        let profile = &load.hourly_demand;
+        assert_eq!(profile.len(), HOURS_PER_YEAR);
+        assert!(load.unscheduled.is_empty());
+        assert_eq!(load.units_produced, 8);
+        assert_eq!(profile[7], 0.0);
+        assert_eq!(profile[8], 6000.0);
+        assert_eq!(profile[9], 6000.0 + 12000.0);
+        assert_eq!(profile[15], 6000.0 + 12000.0);
+        assert_eq!(profile[24], 12000.0);
+        assert_eq!(profile[25], 0.0);
+        let weekly: f64 = profile[..168].iter().sum();
+        assert!((weekly - 8.0 * (6000.0 + 2.0 * 12000.0)).abs() < 1e-6);
+        // the week repeats
+        assert_eq!(profile[168 + 9], profile[9]);
+
+        // the 9th unit waits for Tuesday's shift
+        let load = factory_load_profile(&factory, &[production(9)][..], &[]).unwrap();
+        assert_eq!(load.hourly_demand[24 + 8], 6000.0);
+
+        // the saw can't cut more than 40 units in five shifts
+        let load = production(41).weekly_load_profile().unwrap();
+        assert_eq!(load.units_produced, 40);
+        let unscheduled = load.unscheduled.unwrap();
+        assert_eq!((unscheduled.unit, unscheduled.node.as_str()), (41, "Cut"));
+        assert_eq!(unscheduled.reason, UnscheduledReason::NoShift);
+        let mut other = production(1);
+        other.line.id = "line2".to_string();
+        assert!(factory_load_profile(&factory, &[other][..], &[]).is_err());
+
+        // lighting during the shifts and HVAC around the clock
+        let lines = HashMap::from([("line1".to_string(), production(8))]);
+        let mut lighting = Schedule::default();
+        lighting.add_shift(
+            WeekDay::Monday,
+            WorkShift::new(Time::new(7, 30), Time::new(17, 0)),
+        );
+        let base_loads = [
+            BaseLoad {
+                name: "Lighting".to_string(),
+                power_w: 2000.0,
+                schedule: Some(lighting),
+            },
+            BaseLoad {
+                name: "HVAC".to_string(),
+                power_w: 1000.0,
+                schedule: None,
+            },
+        ];
+        let load = factory_load_profile(&factory, &lines, &base_loads).unwrap();
+        assert_eq!(load.hourly_base_load[7], 1000.0 + 1000.0);
+        assert_eq!(load.hourly_base_load[8], 1000.0 + 2000.0);
+        assert_eq!(load.hourly_base_load[17], 1000.0);
+        assert_eq!(load.hourly_demand[8], 6000.0 + 3000.0);
+        assert_eq!(load.hourly_demand[168 + 8], load.hourly_demand[8]);
+    }
+
+    #[test]
+    fn test_worker_availability() {
+        // the CNC needs an operator
+        let mut production = production(2);
+        production.steps[1].control = MachineControl::Human;
+        production.steps[1].required_specialization = Some(Specialization::CncMachineOperator);
+        let load = production.weekly_load_profile().unwrap();
+        assert_eq!(load.units_produced, 0);
+        assert_eq!(
+            load.unscheduled.unwrap().reason,
+            UnscheduledReason::NoQualifiedWorker
+        );
+
+        // a single operator can't saw and mill at the same time
+        production.workers = vec![worker("Ben", vec![Specialization::CncMachineOperator])];
+        let load = production.weekly_load_profile().unwrap();
+        assert_eq!(load.units_produced, 2);
+        assert_eq!(load.hourly_load[8], 6000.0); // saw unit 1
+        assert_eq!(load.hourly_load[9], 12000.0); // mill unit 1
+        assert_eq!(load.hourly_load[11], 6000.0); // saw unit 2
+        assert_eq!(load.hourly_load[12], 12000.0); // mill unit 2
+
+        // with a second worker the saw runs alongside the mill
+        production.workers.push(worker("Anna", Vec::new()));
+        let load = production.weekly_load_profile().unwrap();
+        assert_eq!(load.hourly_load[9], 6000.0 + 12000.0);
+    }
+
+    #[test]
+    fn test_standby_and_startup() {
+        // one unit on Monday and Tuesday morning, the CNC is off in between and starts up twice
+        let mut production = production(2);
+        production.workers[0].remove_work_shift(WeekDay::Monday);
+        production.workers[0].add_work_shift(WeekDay::Monday, Time::new(8, 0), Time::new(9, 0));
+        let cnc = &mut production.steps[1];
+        cnc.standby_power = 500.0;
+        cnc.startup_energy = 3000.0;
+        cnc.startup_minutes = 60.0;
+        cnc.min_off_minutes = 120.0;
+        let load = production.weekly_load_profile().unwrap();
+        assert_eq!(load.units_produced, 2);
+        assert_eq!(load.startups, 2);
+        assert_eq!(load.hourly_load[8], 6000.0 + 3000.0); // saw and CNC startup
+        assert_eq!(load.hourly_load[11], 0.0);
+        let weekly: f64 = load.hourly_load.iter().sum();
+        assert!((weekly - 2.0 * (6000.0 + 2.0 * 12000.0 + 3000.0)).abs() < 1e-6);
+
+        // the hour between the two CNC runs is bridged in standby
+        production.workers = vec![worker("Anna", Vec::new())];
+        production.steps[0].runtime_minutes = 180.0;
+        let load = production.weekly_load_profile().unwrap();
+        assert_eq!(load.startups, 1);
+        assert_eq!(load.hourly_load[13], 6000.0 + 500.0);
+
+        production.steps[1].runtime_minutes = -1.0;
+        assert!(production.weekly_load_profile().is_err());
+    }
+
+    #[test]
+    fn test_factory_load_profile_in_year() {
+        let factory = Factory {
+            name: "Plant".to_string(),
+            id: "plant".to_string(),
+            location: "Berlin".to_string(),
+            lines: vec!["line1".to_string()],
+        };
+        let mut production = production(8);
+        let lines = std::slice::from_ref(&production);
+        let repeated = factory_load_profile(&factory, lines, &[]).unwrap();
+        let load = factory_load_profile_in_year(&factory, lines, &[], 2025).unwrap();
+        assert_eq!(load, repeated);
+
+        // the profile of 2025 starts on Monday 2024-12-30, the vacation is week 9
+        production.workers[0].add_absence(Absence::new(
+            "2025-03-03",
+            "2025-03-07",
+            AbsenceKind::Vacation,
+        ));
+        let lines = std::slice::from_ref(&production);
+        let load = factory_load_profile_in_year(&factory, lines, &[], 2025).unwrap();
+        assert_eq!(load.hourly_demand[8 * 168 + 8], 6000.0);
+        assert_eq!(load.hourly_demand[9 * 168 + 8], 0.0);
+        assert_eq!(load.hourly_demand[10 * 168 + 8], 6000.0);
+        assert_eq!(load.units_produced, 0);
+        assert_eq!(load.unscheduled[0].reason, UnscheduledReason::NoShift);
+        assert!(factory_load_profile_in_year(&factory, lines, &[], 99999).is_err());
+    }
+
+    #[test]
+    fn test_maintenance_windows() {
+        // the saw is serviced every Monday from 10:00 to 12:00, 6 units are cut on Monday
+        let mut production = production(8);
+        production.steps[0]
+            .maintenance
+            .push(MaintenanceWindow::Weekly {
+                day: WeekDay::Monday,
+                start: Time::new(10, 0),
+                end: Time::new(12, 0),
+            });
+        let load = production.weekly_load_profile().unwrap();
+        assert_eq!(load.units_produced, 8);
+        assert_eq!(load.hourly_load[10], 12000.0);
+        assert_eq!(load.hourly_load[12], 6000.0 + 12000.0);
+        assert_eq!(load.hourly_load[24 + 9], 6000.0 + 12000.0);
+
+        // the CNC is down on Tuesday 2025-03-04 and mills the 7th unit on Wednesday
+        production.steps[1]
+            .maintenance
+            .push(MaintenanceWindow::Dated {
+                from: "2025-03-04".to_string(),
+                until: "2025-03-04".to_string(),
+            });
+        assert_eq!(production.weekly_load_profile().unwrap(), load);
+        let factory = Factory {
+            name: "Plant".to_string(),
+            id: "plant".to_string(),
+            location: "Berlin".to_string(),
+            lines: vec!["line1".to_string()],
+        };
+        let lines = std::slice::from_ref(&production);
+        let year = factory_load_profile_in_year(&factory, lines, &[], 2025).unwrap();
+        assert_eq!(year.units_produced, 8);
+        assert_eq!(year.hourly_demand[8 * 168 + 24 + 9], 6000.0 + 12000.0);
+        assert_eq!(year.hourly_demand[9 * 168 + 24 + 9], 6000.0);
+        assert_eq!(year.hourly_demand[9 * 168 + 48], 12000.0);
+    }
+
+    #[test]
+    fn test_overnight_shift() {
+        // a night shift from Monday 22:00 to Tuesday 06:00 cuts 8 units
+        let mut production = production(8);
+        production.workers[0].schedule = Schedule::default();
+        production.workers[0].schedule.add_shift(
+            WeekDay::Monday,
+            WorkShift::overnight(Time::new(22, 0), Time::new(6, 0)),
+        );
+        let load = production.weekly_load_profile().unwrap();
+        assert_eq!(load.units_produced, 8);
+        assert_eq!(load.hourly_load[21], 0.0);
+        assert_eq!(load.hourly_load[22], 6000.0);
+        assert_eq!(load.hourly_load[24 + 5], 6000.0 + 12000.0);
+
+        // split shift: an extra hour on Monday morning cuts the 9th unit
+        production.units_per_week = 10;
+        production.workers[0].add_work_shift(WeekDay::Monday, Time::new(8, 0), Time::new(9, 0));
+        let load = production.weekly_load_profile().unwrap();
+        assert_eq!(load.units_produced, 9);
+        assert_eq!(load.hourly_load[8], 6000.0);
+
+        // without the overnight flag the shift ends before it starts
+        production.workers[0].remove_work_shift(WeekDay::Monday);
+        production.workers[0].schedule.add_shift(
+            WeekDay::Monday,
+            WorkShift::new(Time::new(22, 0), Time::new(6, 0)),
+        );
+        assert!(production.weekly_load_profile().is_err());
+    }
+}
@@ -5,15 +5,165 @@ pub struct FinancialRentabilityResult {
     pub yearly_roi: f64,
 }
 
+/// Returns of an investment. The nominal figures use the savings at the escalated electricity
+/// prices of each year, the real figures the same savings deflated by the inflation rate to
+/// prices of the first year.
 #[derive(Debug)]
 pub struct OptimizedROIResult {
-    pub roi: f64,
-    pub net_present_value: f64,
-    pub payback_period: Option<f64>,
+    pub roi: f64,                             // Nominal
+    pub net_present_value: f64,               // Nominal, in `currency`
+    pub payback_period: Option<f64>,          // Nominal
+    pub real_roi: f64,                        // Real (inflation-adjusted)
+    pub real_net_present_value: f64,          // Real, in `currency` of the first year
+    pub real_payback_period: Option<f64>,     // Real
+    pub residual_value: f64, // Nominal value of the assets at the end, in `currency`
+    pub after_tax: Option<AfterTaxCashFlows>, // Only with a business tax in the config
+    pub currency: Currency,
 }
 
+use crate::general::currency::Currency;
 use crate::simple::solar_system_utils::SimpleOptimizationResults;
 
+/// Value of the assets left at the end of the calculation period, counted as income in its
+/// last year. Without it a shorter period undervalues long-lived PV.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ResidualValue {
+    #[default]
+    Zero, // Assets are worth nothing at the end
+    StraightLine {
+        lifetime_years: f64,
+    }, // Linear depreciation of the investment over the lifetime
+    ShareOfInvestment(f64), // Fixed share (0-1) of the investment
+}
+
+impl ResidualValue {
+    /// Residual value of `investment` after `age_years` years
+    pub fn value(&self, investment: f64, age_years: f64) -> f64 {
+        match *self {
+            ResidualValue::Zero => 0.0,
+            ResidualValue::StraightLine { lifetime_years } => {
+                if lifetime_years <= 0.0 {
+                    0.0
+                } else {
+                    investment * (1.0 - age_years / lifetime_years).max(0.0)
+                }
+            }
+            ResidualValue::ShareOfInvestment(share) => investment * share,
+        }
+    }
+}
+
+/// How a business writes off an investment, as shares of the investment per year starting
+/// with the year of the investment
+#[derive(Debug, Clone, PartialEq)]
+pub enum DepreciationSchedule {
+    StraightLine { years: usize }, // Equal shares over the useful life
+    Shares(Vec<f64>),              // Own table, e.g. declining balance or special depreciation
+}
+
+impl DepreciationSchedule {
+    /// Share of the investment written off in each year
+    pub fn shares(&self) -> Vec<f64> {
+        match self {
+            DepreciationSchedule::StraightLine { years } => {
+                vec![1.0 / (*years).max(1) as f64; (*years).max(1)]
+            }
+            DepreciationSchedule::Shares(shares) => shares.clone(),
+        }
+    }
+}
+
+/// Income tax of a commercial operator. Savings and feed-in revenue are taxable, the
+/// depreciation of the investments is deductible.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BusinessTax {
+    pub corporate_tax_rate: f64, // Tax on profits (0-1)
+    pub depreciation: DepreciationSchedule,
+}
+
+/// After-tax cash flows of an investment
+#[derive(Debug, Clone, PartialEq)]
+pub struct AfterTaxCashFlows {
+    pub yearly_depreciation: Vec<f64>,
+    pub yearly_taxable_income: Vec<f64>, // Savings minus depreciation, the last year with the gain on the residual value
+    pub yearly_tax: Vec<f64>,            // Negative if the loss reduces the tax on other profits
+    pub yearly_cash_flow: Vec<f64>,      // Savings minus investment minus tax
+    pub net_present_value: f64,          // At the discount rate passed in
+    pub internal_rate_of_return: Option<f64>,
+}
+
+impl AfterTaxCashFlows {
+    /// The money figures converted into `currency`; the IRR does not depend on it
+    pub fn converted(self, currency: &Currency) -> Self {
+        let convert = |values: Vec<f64>| values.into_iter().map(|v| currency.convert(v)).collect();
+        Self {
+            yearly_depreciation: convert(self.yearly_depreciation),
+            yearly_taxable_income: convert(self.yearly_taxable_income),
+            yearly_tax: convert(self.yearly_tax),
+            yearly_cash_flow: convert(self.yearly_cash_flow),
+            net_present_value: currency.convert(self.net_present_value),
+            internal_rate_of_return: self.internal_rate_of_return,
+        }
+    }
+}
+
+/// After-tax cash flows of yearly savings (including feed-in revenue) and investments. Each
+/// investment is depreciated from its year on; depreciation beyond the last year is lost except
+/// for the book value, which is deducted from the residual value received in the last year.
+/// Losses are assumed to offset other profits of the business.
+pub fn calculate_after_tax_cash_flows(
+    yearly_savings: &[f64],
+    yearly_investment: &[f64],
+    residual_value: f64,
+    tax: &BusinessTax,
+    discount_rate: f64,
+) -> AfterTaxCashFlows {
+    let num_years = yearly_savings.len();
+    let shares = tax.depreciation.shares();
+    let mut yearly_depreciation = vec![0.0; num_years];
+    for (year, investment) in yearly_investment.iter().enumerate().take(num_years) {
+        for (offset, share) in shares.iter().enumerate() {
+            if let Some(depreciation) = yearly_depreciation.get_mut(year + offset) {
+                *depreciation += investment * share;
+            }
+        }
+    }
+    let book_value = yearly_investment.iter().take(num_years).sum::<f64>()
+        - yearly_depreciation.iter().sum::<f64>();
+
+    let mut yearly_taxable_income: Vec<f64> = yearly_savings
+        .iter()
+        .zip(&yearly_depreciation)
+        .map(|(savings, depreciation)| savings - depreciation)
+        .collect();
+    if let Some(last) = yearly_taxable_income.last_mut() {
+        *last += residual_value - book_value.max(0.0);
+    }
+    let yearly_tax: Vec<f64> = yearly_taxable_income
+        .iter()
+        .map(|income| income * tax.corporate_tax_rate)
+        .collect();
+    let mut yearly_cash_flow: Vec<f64> = (0..num_years)
+        .map(|year| {
+            yearly_savings[year]
+                - yearly_investment.get(year).copied().unwrap_or(0.0)
+                - yearly_tax[year]
+        })
+        .collect();
+    if let Some(last) = yearly_cash_flow.last_mut() {
+        *last += residual_value;
+    }
+
+    AfterTaxCashFlows {
+        net_present_value: net_present_value(&yearly_cash_flow, discount_rate),
+        internal_rate_of_return: internal_rate_of_return(&yearly_cash_flow),
+        yearly_depreciation,
+        yearly_taxable_income,
+        yearly_tax,
+        yearly_cash_flow,
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ROICalculationConfig {
     pub inv_pv: f64,
@@ -21,7 +171,11 @@ pub struct ROICalculationConfig {
     pub inv_bat: f64,
     pub fc_grid: f64,
     pub electricity_usage_wh: f64,
-    pub electricity_price_increase: f64,
+    pub electricity_price_increase: f64, // Nominal electricity price increase per year
+    pub inflation_rate: f64,             // General inflation per year, deflates the real figures
+    pub residual_value: ResidualValue,   // Value of the investment at the end of the period
+    pub business_tax: Option<BusinessTax>, // Corporate tax and depreciation; None for households
+    pub currency: Currency,
 }
 
 #[derive(Debug, Clone)]
@@ -45,35 +199,41 @@ impl From<SimpleOptimizationResults> for ROICalculationInput {
                 inv_grid: results.config.inv_grid,
                 inv_bat: results.config.inv_bat,
                 fc_grid: results.config.fc_grid,
-                electricity_usage_wh: results.config.electricity_usage,
+                electricity_usage_wh: results.config.electricity_usage.wh(),
                 electricity_price_increase: results.config.electricity_price_increase,
+                inflation_rate: results.config.inflation_rate,
+                residual_value: results.config.residual_value,
+                business_tax: results.config.business_tax.clone(),
+                currency: results.config.currency.clone(),
             },
         }
     }
 }
 
-/// Calculate ROI using root-finding to solve the equation:
-/// 0 = (sum_{i=0}^{N-1} (1+ROI)^i * s_i / I_0)^{1/N} - 1 - ROI
-/// where s_i is the annual savings in year i, N is num_years, and I_0 is initial_investment
-pub fn calculate_optimized_roi(
-    input: ROICalculationInput,
-    num_years: usize,
-    other_yearly_cost: f64,
-) -> Result<OptimizedROIResult, Box<dyn std::error::Error>> {
-    // Calculate initial investment (same as in calculate_financial_rentability)
-    let initial_investment = input.pv_capacity_kw * input.config.inv_pv
-        + input.grid_capacity_kw * input.config.inv_grid
-        + input.battery_capacity_kwh * input.config.inv_bat;
-
-    if initial_investment <= 0.0 {
-        return Ok(OptimizedROIResult {
-            roi: 0.0,
-            net_present_value: 0.0,
-            payback_period: None,
-        });
+impl ROICalculationInput {
+    /// Investment for the installed capacities
+    pub fn initial_investment(&self) -> f64 {
+        self.pv_capacity_kw * self.config.inv_pv
+            + self.grid_capacity_kw * self.config.inv_grid
+            + self.battery_capacity_kwh * self.config.inv_bat
     }
+}
 
-    // Calculate annual savings for each year
+/// Deflates nominal yearly values by `inflation_rate` to prices of the first year
+pub fn real_values(nominal: &[f64], inflation_rate: f64) -> Vec<f64> {
+    nominal
+        .iter()
+        .enumerate()
+        .map(|(year, value)| value / (1.0 + inflation_rate).powi(year as i32))
+        .collect()
+}
+
+/// Savings compared to buying all electricity from the grid for each year of the calculation period
+pub fn calculate_annual_savings(
+    input: &ROICalculationInput,
+    num_years: usize,
+    other_yearly_cost: f64,
+) -> Vec<f64> {
     let annual_costs_no_solar = (0..num_years)
         .map(|index| {
             input.config.fc_grid * input.config.electricity_usage_wh / 1000.0
@@ -90,11 +250,93 @@ pub fn calculate_optimized_roi(
         })
         .collect::<Vec<f64>>();
 
-    let annual_savings: Vec<f64> = annual_costs_no_solar
+    annual_costs_no_solar
         .iter()
         .zip(annual_grid_costs_solar.iter())
         .map(|(cost_no_solar, cost_with_solar)| cost_no_solar - cost_with_solar)
-        .collect();
+        .collect()
+}
+
+/// Calculate ROI using root-finding to solve the equation:
+/// 0 = (sum_{i=0}^{N-1} (1+ROI)^i * s_i / I_0)^{1/N} - 1 - ROI
+/// where s_i is the annual savings in year i, N is num_years, and I_0 is initial_investment.
+/// The real figures solve the same equation with the savings deflated by the inflation rate.
+/// The residual value adds to the savings of the last year for ROI and NPV, not for the payback.
+pub fn calculate_optimized_roi(
+    input: ROICalculationInput,
+    num_years: usize,
+    other_yearly_cost: f64,
+) -> Result<OptimizedROIResult, Box<dyn std::error::Error>> {
+    // Calculate initial investment (same as in calculate_financial_rentability)
+    let initial_investment = input.initial_investment();
+
+    if initial_investment <= 0.0 {
+        return Ok(OptimizedROIResult {
+            roi: 0.0,
+            net_present_value: 0.0,
+            payback_period: None,
+            real_roi: 0.0,
+            real_net_present_value: 0.0,
+            real_payback_period: None,
+            residual_value: 0.0,
+            after_tax: None,
+            currency: input.config.currency,
+        });
+    }
+
+    // Calculate annual savings for each year
+    let annual_savings = calculate_annual_savings(&input, num_years, other_yearly_cost);
+    let residual_value = input
+        .config
+        .residual_value
+        .value(initial_investment, num_years as f64);
+    let (roi, npv, payback_period) = solve_roi(&annual_savings, initial_investment, residual_value);
+    let real_savings = real_values(&annual_savings, input.config.inflation_rate);
+    let real_residual_value = residual_value
+        / (1.0 + input.config.inflation_rate).powi(num_years.saturating_sub(1) as i32);
+    let (real_roi, real_npv, real_payback_period) =
+        solve_roi(&real_savings, initial_investment, real_residual_value);
+    // after-tax figures discounted at the pre-tax ROI like the NPV above
+    let after_tax = input.config.business_tax.as_ref().map(|tax| {
+        let mut yearly_investment = vec![0.0; num_years];
+        if let Some(first) = yearly_investment.first_mut() {
+            *first = initial_investment;
+        }
+        calculate_after_tax_cash_flows(
+            &annual_savings,
+            &yearly_investment,
+            residual_value,
+            tax,
+            roi,
+        )
+        .converted(&input.config.currency)
+    });
+
+    Ok(OptimizedROIResult {
+        roi,
+        net_present_value: input.config.currency.convert(npv),
+        payback_period,
+        real_roi,
+        real_net_present_value: input.config.currency.convert(real_npv),
+        real_payback_period,
+        residual_value: input.config.currency.convert(residual_value),
+        after_tax,
+        currency: input.config.currency,
+    })
+}
+
+/// ROI, NPV and payback period of yearly savings against an initial investment, with the
+/// residual value received in the last year
+fn solve_roi(
+    savings: &[f64],
+    initial_investment: f64,
+    residual_value: f64,
+) -> (f64, f64, Option<f64>) {
+    let num_years = savings.len();
+    let mut annual_savings = savings.to_vec();
+    if let Some(last) = annual_savings.last_mut() {
+        *last += residual_value;
+    }
 
     // Define the function to find the root of: f(ROI) = (sum / I_0)^{1/N} - 1 - ROI
     let equation_function = |roi: f64| -> f64 {
@@ -156,7 +398,7 @@ pub fn calculate_optimized_roi(
     // Calculate payback period
     let mut cumulative_savings = 0.0;
     let mut payback_period = None;
-    for (i, annual_saving) in annual_savings.iter().enumerate() {
+    for (i, annual_saving) in savings.iter().enumerate() {
         cumulative_savings += annual_saving;
         if cumulative_savings >= initial_investment && payback_period.is_none() {
             payback_period = Some(
@@ -167,11 +409,41 @@ pub fn calculate_optimized_roi(
         }
     }
 
-    Ok(OptimizedROIResult {
-        roi: roi_value,
-        net_present_value: npv,
-        payback_period,
-    })
+    (roi_value, npv, payback_period)
+}
+
+/// Net present value of yearly cash flows, the first one undiscounted
+pub fn net_present_value(cash_flows: &[f64], discount_rate: f64) -> f64 {
+    cash_flows
+        .iter()
+        .enumerate()
+        .map(|(year, cash_flow)| cash_flow / (1.0 + discount_rate).powi(year as i32))
+        .sum()
+}
+
+/// Internal rate of return of yearly cash flows: the discount rate with a net present value of
+/// zero, searched between -99 % and 1000 %. None if the net present value doesn't change its
+/// sign in that range, e.g. for cash flows without an investment.
+pub fn internal_rate_of_return(cash_flows: &[f64]) -> Option<f64> {
+    let (mut low, mut high) = (-0.99, 10.0);
+    let npv_low = net_present_value(cash_flows, low);
+    if cash_flows.iter().all(|&cash_flow| cash_flow == 0.0)
+        || npv_low * net_present_value(cash_flows, high) > 0.0
+    {
+        return None;
+    }
+    for _ in 0..200 {
+        let mid = (low + high) / 2.0;
+        if (net_present_value(cash_flows, mid) > 0.0) == (npv_low > 0.0) {
+            low = mid;
+        } else {
+            high = mid;
+        }
+        if high - low < 1e-9 {
+            break;
+        }
+    }
+    Some((low + high) / 2.0)
 }
 
 /// Newton's method for root finding
@@ -225,6 +497,10 @@ mod tests {
             fc_grid: 0.16,
             electricity_usage_wh: electricity_usage,
             electricity_price_increase: 0.01,
+            inflation_rate: 0.0,
+            residual_value: ResidualValue::Zero,
+            business_tax: None,
+            currency: Currency::default(),
         };
 
         let input = ROICalculationInput {
@@ -244,6 +520,148 @@ mod tests {
         assert!((optimized_roi.payback_period.unwrap() - 3.5).abs() < 0.02);
     }
 
+    #[test]
+    fn test_calculate_optimized_roi_real_figures() {
+        let input = |inflation_rate: f64| ROICalculationInput {
+            pv_capacity_kw: 2.45,
+            grid_capacity_kw: 0.0,
+            battery_capacity_kwh: 0.0,
+            annual_grid_energy_kwh: 9000000.0 * 0.57 / 1000.0,
+            config: ROICalculationConfig {
+                inv_pv: 900.0,
+                inv_grid: 0.0,
+                inv_bat: 0.0,
+                fc_grid: 0.16,
+                electricity_usage_wh: 9000000.0,
+                electricity_price_increase: 0.03,
+                inflation_rate,
+                residual_value: ResidualValue::Zero,
+                business_tax: None,
+                currency: Currency::default(),
+            },
+        };
+
+        // without inflation real and nominal figures are the same
+        let no_inflation = calculate_optimized_roi(input(0.0), 25, 0.0).unwrap();
+        assert_eq!(no_inflation.real_roi, no_inflation.roi);
+        assert_eq!(
+            no_inflation.real_payback_period,
+            no_inflation.payback_period
+        );
+
+        let inflation = calculate_optimized_roi(input(0.03), 25, 0.0).unwrap();
+        assert_eq!(inflation.roi, no_inflation.roi);
+        assert!(inflation.real_roi < inflation.roi);
+        assert!(inflation.real_payback_period.unwrap() > inflation.payback_period.unwrap());
+
+        let savings = [100.0, 103.0, 106.09];
+        let real = real_values(&savings, 0.03);
+        assert!(real.iter().all(|value| (value - 100.0).abs() < 1e-9));
+    }
+
+    #[test]
+    fn test_calculate_optimized_roi_residual_value() {
+        let input = |num_years: usize, residual_value: ResidualValue| {
+            let input = ROICalculationInput {
+                pv_capacity_kw: 4.0 * 0.61,
+                grid_capacity_kw: 0.0,
+                battery_capacity_kwh: 0.0,
+                annual_grid_energy_kwh: 3726.29,
+                config: ROICalculationConfig {
+                    inv_pv: 900.0,
+                    inv_grid: 0.0,
+                    inv_bat: 0.0,
+                    fc_grid: 0.16,
+                    electricity_usage_wh: 5500000.0,
+                    electricity_price_increase: 0.01,
+                    inflation_rate: 0.0,
+                    residual_value,
+                    business_tax: None,
+                    currency: Currency::default(),
+                },
+            };
+            calculate_optimized_roi(input, num_years, 120.0).unwrap()
+        };
+
+        let investment = 4.0 * 0.61 * 900.0;
+        let lifetime = ResidualValue::StraightLine {
+            lifetime_years: 30.0,
+        };
+        assert!((lifetime.value(investment, 20.0) - investment / 3.0).abs() < 1e-9);
+        assert_eq!(lifetime.value(investment, 40.0), 0.0);
+
+        let without = input(20, ResidualValue::Zero);
+        let with = input(20, lifetime);
+        assert!((with.residual_value - investment / 3.0).abs() < 1e-9);
+        assert!(with.roi > without.roi);
+        assert_eq!(with.payback_period, without.payback_period);
+        assert_eq!(without.residual_value, 0.0);
+
+        let share = input(25, ResidualValue::ShareOfInvestment(0.1));
+        assert!((share.residual_value - investment * 0.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_calculate_after_tax_cash_flows() {
+        let tax = BusinessTax {
+            corporate_tax_rate: 0.3,
+            depreciation: DepreciationSchedule::StraightLine { years: 4 },
+        };
+        let savings = [300.0; 5];
+        let investment = [1000.0, 0.0, 0.0, 0.0, 0.0];
+        let after_tax = calculate_after_tax_cash_flows(&savings, &investment, 0.0, &tax, 0.05);
+        assert_eq!(
+            after_tax.yearly_depreciation,
+            vec![250.0, 250.0, 250.0, 250.0, 0.0]
+        );
+        assert!((after_tax.yearly_tax[0] - 15.0).abs() < 1e-9);
+        assert!((after_tax.yearly_tax[4] - 90.0).abs() < 1e-9);
+        assert!((after_tax.yearly_cash_flow[0] - (300.0 - 1000.0 - 15.0)).abs() < 1e-9);
+        let pre_tax_irr = internal_rate_of_return(&[-700.0, 300.0, 300.0, 300.0, 300.0]).unwrap();
+        assert!(after_tax.internal_rate_of_return.unwrap() < pre_tax_irr);
+
+        // depreciation cut off by the horizon leaves a book value against the residual value
+        let short = calculate_after_tax_cash_flows(&savings[..2], &investment, 500.0, &tax, 0.05);
+        assert!((short.yearly_taxable_income[1] - 50.0).abs() < 1e-9);
+        assert!((short.yearly_cash_flow[1] - (300.0 + 500.0 - 0.3 * 50.0)).abs() < 1e-9);
+
+        let input = ROICalculationInput {
+            pv_capacity_kw: 2.45,
+            grid_capacity_kw: 0.0,
+            battery_capacity_kwh: 0.0,
+            annual_grid_energy_kwh: 9000000.0 * 0.57 / 1000.0,
+            config: ROICalculationConfig {
+                inv_pv: 900.0,
+                inv_grid: 0.0,
+                inv_bat: 0.0,
+                fc_grid: 0.16,
+                electricity_usage_wh: 9000000.0,
+                electricity_price_increase: 0.01,
+                inflation_rate: 0.0,
+                residual_value: ResidualValue::Zero,
+                business_tax: Some(BusinessTax {
+                    corporate_tax_rate: 0.3,
+                    depreciation: DepreciationSchedule::StraightLine { years: 20 },
+                }),
+                currency: Currency::default(),
+            },
+        };
+        let roi = calculate_optimized_roi(input, 25, 0.0).unwrap();
+        let after_tax = roi.after_tax.unwrap();
+        assert_eq!(after_tax.yearly_cash_flow.len(), 25);
+        assert!(after_tax.internal_rate_of_return.unwrap() > 0.0);
+    }
+
+    #[test]
+    fn test_net_present_value_and_internal_rate_of_return() {
+        let cash_flows = [-1000.0, 0.0, 1210.0];
+        assert!((net_present_value(&cash_flows, 0.0) - 210.0).abs() < 1e-9);
+        assert!(net_present_value(&cash_flows, 0.1).abs() < 1e-9);
+        assert!((internal_rate_of_return(&cash_flows).unwrap() - 0.1).abs() < 1e-6);
+        assert_eq!(internal_rate_of_return(&[100.0, 100.0]), None);
+        assert_eq!(internal_rate_of_return(&[0.0, 0.0, 0.0]), None);
+    }
+
     #[test]
     fn test_calculate_financial_rentability_2() {
         let num_years = 25;
@@ -257,6 +675,10 @@ mod tests {
             fc_grid: 0.16,
             electricity_usage_wh: electricity_usage,
             electricity_price_increase: 0.01,
+            inflation_rate: 0.0,
+            residual_value: ResidualValue::Zero,
+            business_tax: None,
+            currency: Currency::default(),
         };
 
         let input = ROICalculationInput {
@@ -289,6 +711,10 @@ mod tests {
             fc_grid: 0.16,
             electricity_usage_wh: electricity_usage,
             electricity_price_increase: 0.0,
+            inflation_rate: 0.0,
+            residual_value: ResidualValue::Zero,
+            business_tax: None,
+            currency: Currency::default(),
         };
 
         let input = ROICalculationInput {
@@ -322,6 +748,10 @@ mod tests {
             fc_grid: 0.15,
             electricity_usage_wh: electricity_usage,
             electricity_price_increase: 0.0,
+            inflation_rate: 0.0,
+            residual_value: ResidualValue::Zero,
+            business_tax: None,
+            currency: Currency::default(),
         };
 
         let input = ROICalculationInput {
@@ -341,4 +771,51 @@ mod tests {
         println!("Payback period: {:?}", optimized_roi.payback_period);
         assert!((optimized_roi.payback_period.unwrap() - 4.2).abs() < 0.02);
     }
+
+    #[test]
+    fn test_calculate_optimized_roi_currency_conversion() {
+        let input = |currency: Currency| ROICalculationInput {
+            pv_capacity_kw: 2.45,
+            grid_capacity_kw: 0.0,
+            battery_capacity_kwh: 0.0,
+            annual_grid_energy_kwh: 9000000.0 * 0.57 / 1000.0,
+            config: ROICalculationConfig {
+                inv_pv: 900.0,
+                inv_grid: 0.0,
+                inv_bat: 0.0,
+                fc_grid: 0.16,
+                electricity_usage_wh: 9000000.0,
+                electricity_price_increase: 0.01,
+                inflation_rate: 0.0,
+                residual_value: ResidualValue::Zero,
+                business_tax: Some(BusinessTax {
+                    corporate_tax_rate: 0.3,
+                    depreciation: DepreciationSchedule::StraightLine { years: 20 },
+                }),
+                currency,
+            },
+        };
+
+        let eur = calculate_optimized_roi(input(Currency::default()), 25, 0.0).unwrap();
+        let gbp = calculate_optimized_roi(
+            input(Currency::with_conversion_rate("GBP", 0.85).unwrap()),
+            25,
+            0.0,
+        )
+        .unwrap();
+
+        assert_eq!(gbp.currency.code, "GBP");
+        // ROI and payback period do not depend on the currency, the NPV is converted
+        assert_eq!(gbp.roi, eur.roi);
+        assert_eq!(gbp.payback_period, eur.payback_period);
+        assert!((gbp.net_present_value - eur.net_present_value * 0.85).abs() < 1e-9);
+        let (eur_tax, gbp_tax) = (eur.after_tax.unwrap(), gbp.after_tax.unwrap());
+        assert!((gbp_tax.net_present_value - eur_tax.net_present_value * 0.85).abs() < 1e-9);
+        assert!((gbp_tax.yearly_cash_flow[0] - eur_tax.yearly_cash_flow[0] * 0.85).abs() < 1e-9);
+        assert!((gbp_tax.yearly_tax[1] - eur_tax.yearly_tax[1] * 0.85).abs() < 1e-9);
+        assert_eq!(
+            gbp_tax.internal_rate_of_return,
+            eur_tax.internal_rate_of_return
+        );
+    }
 }
@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+
+use anyhow::{Result, anyhow};
+use ems_model::general::location::{Address, Coordinates, Country, Location};
+
+/// Looks up the coordinates of a street address
+pub trait Geocoder {
+    fn geocode(&self, address: &Address, country: &Country) -> Result<Coordinates>;
+}
+
+/// Fixed coordinates by formatted address (`Address::formatted`), e.g. for tests or a cache
+impl Geocoder for HashMap<String, Coordinates> {
+    fn geocode(&self, address: &Address, _country: &Country) -> Result<Coordinates> {
+        let key = address.formatted();
+        self.get(&key)
+            .cloned()
+            .ok_or_else(|| anyhow!("No coordinates for address {}", key))
+    }
+}
+
+/// Location of an address in `country`, with the coordinates looked up by `geocoder`
+pub fn locate<G: Geocoder + ?Sized>(
+    geocoder: &G,
+    name: &str,
+    country: Country,
+    address: Address,
+) -> Result<Location> {
+    let coordinates = geocoder.geocode(&address, &country)?;
+    Ok(Location::new(
+        name.to_string(),
+        country,
+        address,
+        coordinates,
+    ))
+}
+
+#[cfg(feature = "geocoding")]
+pub use nominatim::NominatimGeocoder;
+
+#[cfg(feature = "geocoding")]
+mod nominatim {
+    use anyhow::{Context, Result, anyhow};
+    use ems_model::general::location::{Address, Coordinates, Country};
+
+    use super::Geocoder;
+
+    /// Geocoder querying the structured search of a Nominatim (OpenStreetMap) server
+    ///
+    /// The public server allows one request per second and requires an identifying user agent.
+    #[derive(Debug, Clone)]
+    pub struct NominatimGeocoder {
+        pub base_url: String,   // e.g. https://nominatim.openstreetmap.org
+        pub user_agent: String, // Identifies the application to the server
+    }
+
+    impl NominatimGeocoder {
+        /// Geocoder using the public OpenStreetMap server
+        pub fn new(user_agent: &str) -> Self {
+            Self {
+                base_url: "https://nominatim.openstreetmap.org".to_string(),
+                user_agent: user_agent.to_string(),
+            }
+        }
+
+        /// Query parameters of the structured search for an address
+        pub(crate) fn query(address: &Address, country: &Country) -> Vec<(&'static str, String)> {
+            let mut query = vec![
+                ("format", "jsonv2".to_string()),
+                ("limit", "1".to_string()),
+                ("countrycodes", country.code().to_ascii_lowercase()),
+            ];
+            let fields = [
+                ("street", Some(&address.street)),
+                ("city", Some(&address.city)),
+                ("state", address.region.as_ref()),
+                ("postalcode", Some(&address.postal_code)),
+            ];
+            for (param, value) in fields {
+                if let Some(value) = value.filter(|value| !value.trim().is_empty()) {
+                    query.push((param, value.clone()));
+                }
+            }
+            query
+        }
+
+        /// Coordinates of the first result of a search response
+        pub(crate) fn parse_response(body: &str) -> Result<Coordinates> {
+            let json: serde_json::Value =
+                serde_json::from_str(body).context("Invalid Nominatim response")?;
+            let first = json
+                .as_array()
+                .and_then(|results| results.first())
+                .ok_or_else(|| anyhow!("Address not found"))?;
+            let number = |field: &str| -> Result<f64> {
+                first
+                    .get(field)
+                    .and_then(|value| value.as_str())
+                    .ok_or_else(|| anyhow!("No {} in Nominatim result", field))?
+                    .parse::<f64>()
+                    .with_context(|| format!("Invalid {} in Nominatim result", field))
+            };
+            Coordinates::new(number("lat")?, number("lon")?).map_err(|e| anyhow!(e))
+        }
+    }
+
+    impl Geocoder for NominatimGeocoder {
+        fn geocode(&self, address: &Address, country: &Country) -> Result<Coordinates> {
+            let mut request = ureq::get(&format!("{}/search", self.base_url.trim_end_matches('/')))
+                .set("User-Agent", &self.user_agent);
+            for (param, value) in Self::query(address, country) {
+                request = request.query(param, &value);
+            }
+            let body = request
+                .call()
+                .with_context(|| format!("Geocoding {} failed", address.formatted()))?
+                .into_string()?;
+            Self::parse_response(&body)
+                .with_context(|| format!("Geocoding {} failed", address.formatted()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address() -> Address {
+        Address::new(
+            "Unter den Linden 1".to_string(),
+            "Berlin".to_string(),
+            None,
+            "10117".to_string(),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_locate() {
+        let mut geocoder = HashMap::new();
+        geocoder.insert(
+            address().formatted(),
+            Coordinates::new(52.517, 13.397).unwrap(),
+        );
+        let location = locate(&geocoder, "Office", Country::Germany, address()).unwrap();
+        assert_eq!(location.coordinates.latitude, 52.517);
+        assert_eq!(location.address, address());
+
+        let mut unknown = address();
+        unknown.city = "Potsdam".to_string();
+        assert!(locate(&geocoder, "Office", Country::Germany, unknown).is_err());
+    }
+
+    #[cfg(feature = "geocoding")]
+    #[test]
+    fn test_nominatim_request_and_response() {
+        let query = NominatimGeocoder::query(&address(), &Country::Germany);
+        assert!(query.contains(&("countrycodes", "de".to_string())));
+        assert!(query.contains(&("postalcode", "10117".to_string())));
+        assert!(query.iter().all(|(param, _)| *param != "state"));
+
+        let body = r#"[{"place_id": 1, "lat": "52.5170365", "lon": "13.3888599"}]"#;
+        let coordinates = NominatimGeocoder::parse_response(body).unwrap();
+        assert!((coordinates.longitude - 13.3888599).abs() < 1e-9);
+        assert!(NominatimGeocoder::parse_response("[]").is_err());
+        assert!(NominatimGeocoder::parse_response(r#"[{"lat": "95", "lon": "0"}]"#).is_err());
+    }
+}
@@ -1,4 +1,16 @@
+pub mod calendar;
+pub mod currency;
+pub mod disaggregation;
 pub mod electricity_demand;
+pub mod factory_load;
 pub mod finance;
+pub mod geocoding;
+pub mod resampling;
+pub mod shift_plan;
+#[cfg(feature = "telemetry")]
+pub mod telemetry;
+pub mod time_series;
+pub mod units;
 
 pub use finance::{FinancialRentabilityResult, OptimizedROIResult, calculate_optimized_roi};
+pub use units::{Energy, Power, Ratio};
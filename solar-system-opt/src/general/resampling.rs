@@ -0,0 +1,262 @@
+use anyhow::{Result, bail};
+
+/// Time resolution of an input series
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    QuarterHourly,
+    Hourly,
+    Daily,
+}
+
+impl Resolution {
+    /// Resolution of a series covering one year (365 or 366 days), None for other lengths
+    pub fn detect(len: usize) -> Option<Self> {
+        match len {
+            35040 | 35136 => Some(Resolution::QuarterHourly),
+            8760 | 8784 => Some(Resolution::Hourly),
+            365 | 366 => Some(Resolution::Daily),
+            _ => None,
+        }
+    }
+
+    /// Length of a step
+    pub fn minutes(&self) -> usize {
+        match self {
+            Resolution::QuarterHourly => 15,
+            Resolution::Hourly => 60,
+            Resolution::Daily => 1440,
+        }
+    }
+}
+
+/// How values of a series combine when the resolution changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quantity {
+    Energy, // Energy per step (Wh, kWh), summed when aggregating
+    Rate,   // Power, irradiance or temperature, averaged when aggregating
+}
+
+/// Shape of a day used to split daily values into hours
+#[derive(Debug, Clone, PartialEq)]
+pub enum StandardDayProfile {
+    Household,         // Resembles the BDEW H0 profile: morning and evening peaks
+    Business,          // Resembles the BDEW G0 profile: office hours on weekdays
+    Daylight,          // Half sine between 6:00 and 18:00, for irradiance
+    Flat,              // The same value every hour
+    Custom([f64; 24]), // Relative weights of the hours, the same every day
+}
+
+impl StandardDayProfile {
+    /// Relative weights of the 24 hours of a day. `weekday` 0 is Monday, the modelled year
+    /// starts on a Monday.
+    pub fn shape(&self, weekday: usize) -> [f64; 24] {
+        let weekend = weekday % 7 >= 5;
+        match self {
+            StandardDayProfile::Household if weekend => [
+                0.70, 0.55, 0.45, 0.40, 0.38, 0.38, 0.42, 0.55, 0.80, 1.05, 1.20, 1.30, 1.40, 1.25,
+                1.05, 0.95, 0.95, 1.05, 1.25, 1.40, 1.40, 1.30, 1.15, 0.90,
+            ],
+            StandardDayProfile::Household => [
+                0.60, 0.45, 0.40, 0.38, 0.38, 0.42, 0.60, 0.90, 1.00, 0.95, 0.95, 1.00, 1.15, 1.05,
+                0.95, 0.90, 0.95, 1.10, 1.35, 1.50, 1.45, 1.30, 1.10, 0.80,
+            ],
+            StandardDayProfile::Business if weekend => [
+                0.40, 0.38, 0.38, 0.38, 0.38, 0.40, 0.45, 0.50, 0.55, 0.60, 0.62, 0.62, 0.60, 0.58,
+                0.55, 0.52, 0.50, 0.48, 0.46, 0.45, 0.43, 0.42, 0.41, 0.40,
+            ],
+            StandardDayProfile::Business => [
+                0.35, 0.33, 0.33, 0.33, 0.35, 0.45, 0.80, 1.30, 1.60, 1.70, 1.75, 1.70, 1.55, 1.60,
+                1.65, 1.60, 1.45, 1.10, 0.75, 0.55, 0.45, 0.40, 0.38, 0.36,
+            ],
+            StandardDayProfile::Daylight => std::array::from_fn(|hour| {
+                let position = (hour as f64 + 0.5 - 6.0) / 12.0;
+                if (0.0..=1.0).contains(&position) {
+                    (position * std::f64::consts::PI).sin()
+                } else {
+                    0.0
+                }
+            }),
+            StandardDayProfile::Flat => [1.0; 24],
+            StandardDayProfile::Custom(shape) => *shape,
+        }
+    }
+}
+
+/// Scales `shape` so that it sums to `total`. A shape without weight is split evenly.
+fn scale_to_total(shape: &[f64], total: f64) -> impl Iterator<Item = f64> + '_ {
+    let sum: f64 = shape.iter().sum();
+    let even = sum.abs() < 1e-12;
+    shape.iter().map(move |&weight| {
+        if even {
+            total / shape.len() as f64
+        } else {
+            total * weight / sum
+        }
+    })
+}
+
+/// Splits every total into `shape.len() / totals.len()` steps following `shape`, e.g. a
+/// measured quarter-hourly profile to split hourly totals. Each chunk of the shape is scaled
+/// to its total.
+pub fn distribute(totals: &[f64], shape: &[f64]) -> Result<Vec<f64>> {
+    if totals.is_empty() || !shape.len().is_multiple_of(totals.len()) {
+        bail!(
+            "A shape of {} values can't be split into {} totals",
+            shape.len(),
+            totals.len()
+        );
+    }
+    let steps = shape.len() / totals.len();
+    Ok(totals
+        .iter()
+        .zip(shape.chunks(steps))
+        .flat_map(|(&total, chunk)| scale_to_total(chunk, total))
+        .collect())
+}
+
+/// Aggregates 15-minute values to hours: energy is summed, rates are averaged
+pub fn quarter_hourly_to_hourly(values: &[f64], quantity: Quantity) -> Result<Vec<f64>> {
+    if !values.len().is_multiple_of(4) {
+        bail!(
+            "{} quarter-hourly values don't make up whole hours",
+            values.len()
+        );
+    }
+    Ok(aggregate_quarters(values, quantity))
+}
+
+fn aggregate_quarters(values: &[f64], quantity: Quantity) -> Vec<f64> {
+    values
+        .chunks(4)
+        .map(|quarters| match quantity {
+            Quantity::Energy => quarters.iter().sum(),
+            Quantity::Rate => quarters.iter().sum::<f64>() / 4.0,
+        })
+        .collect()
+}
+
+/// Splits hourly values into 15-minute values. The shape within an hour is interpolated
+/// linearly between the neighbouring hours and scaled so that the quarters sum up to the hour
+/// (energy) or average to it (rate).
+pub fn hourly_to_quarter_hourly(values: &[f64], quantity: Quantity) -> Vec<f64> {
+    let last = values.len().saturating_sub(1);
+    let shape: Vec<f64> = (0..values.len() * 4)
+        .map(|quarter| {
+            // position relative to the hour centers
+            let position = ((quarter as f64 + 0.5) / 4.0 - 0.5).clamp(0.0, last as f64);
+            let hour = (position.floor() as usize).min(last);
+            let next = (hour + 1).min(last);
+            let fraction = position - hour as f64;
+            values[hour] * (1.0 - fraction) + values[next] * fraction
+        })
+        .collect();
+    let totals: Vec<f64> = match quantity {
+        Quantity::Energy => values.to_vec(),
+        Quantity::Rate => values.iter().map(|value| value * 4.0).collect(),
+    };
+    totals
+        .iter()
+        .zip(shape.chunks(4))
+        .flat_map(|(&total, chunk)| scale_to_total(chunk, total))
+        .collect()
+}
+
+/// Splits daily values into hours following `profile`, so that the hours sum up to the day
+/// (energy) or average to it (rate)
+pub fn daily_to_hourly(
+    values: &[f64],
+    quantity: Quantity,
+    profile: &StandardDayProfile,
+) -> Vec<f64> {
+    values
+        .iter()
+        .enumerate()
+        .flat_map(|(day, &value)| {
+            let total = match quantity {
+                Quantity::Energy => value,
+                Quantity::Rate => value * 24.0,
+            };
+            scale_to_total(&profile.shape(day), total).collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Brings a loaded series to hourly resolution, detected from its length (see
+/// `Resolution::detect`). `profile` is only used for daily values. Hourly series and series of
+/// other lengths are returned unchanged.
+pub fn to_hourly(values: Vec<f64>, quantity: Quantity, profile: &StandardDayProfile) -> Vec<f64> {
+    match Resolution::detect(values.len()) {
+        Some(Resolution::QuarterHourly) => aggregate_quarters(&values, quantity),
+        Some(Resolution::Daily) => daily_to_hourly(&values, quantity, profile),
+        Some(Resolution::Hourly) | None => values,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quarter_hourly_round_trip() {
+        let hourly = vec![100.0, 200.0, 400.0, 0.0, 300.0];
+        let quarters = hourly_to_quarter_hourly(&hourly, Quantity::Energy);
+        assert_eq!(quarters.len(), 20);
+        // the shape follows the neighbouring hours
+        assert!(quarters[4] < quarters[7]);
+        assert!(quarters[12..16].iter().all(|&quarter| quarter == 0.0));
+        let back = quarter_hourly_to_hourly(&quarters, Quantity::Energy).unwrap();
+        for (a, b) in back.iter().zip(&hourly) {
+            assert!((a - b).abs() < 1e-9);
+        }
+
+        let temperatures = vec![10.0, 12.0, 14.0];
+        let quarters = hourly_to_quarter_hourly(&temperatures, Quantity::Rate);
+        let back = quarter_hourly_to_hourly(&quarters, Quantity::Rate).unwrap();
+        for (a, b) in back.iter().zip(&temperatures) {
+            assert!((a - b).abs() < 1e-9);
+        }
+        assert!(quarter_hourly_to_hourly(&[1.0; 5], Quantity::Energy).is_err());
+    }
+
+    #[test]
+    fn test_daily_to_hourly() {
+        let daily = vec![12.0; 7];
+        let hourly = daily_to_hourly(&daily, Quantity::Energy, &StandardDayProfile::Household);
+        assert_eq!(hourly.len(), 168);
+        for day in hourly.chunks(24) {
+            assert!((day.iter().sum::<f64>() - 12.0).abs() < 1e-9);
+        }
+        // evening peak on weekdays, later morning on the weekend
+        assert!(hourly[19] > hourly[3]);
+        assert!(hourly[5 * 24 + 7] < hourly[7]);
+
+        let irradiance = daily_to_hourly(&[0.2], Quantity::Rate, &StandardDayProfile::Daylight);
+        assert_eq!(irradiance[2], 0.0);
+        assert!((irradiance.iter().sum::<f64>() / 24.0 - 0.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_to_hourly() {
+        let quarters = vec![250.0; 35040];
+        let hourly = to_hourly(quarters, Quantity::Energy, &StandardDayProfile::Flat);
+        assert_eq!(hourly.len(), 8760);
+        assert_eq!(hourly[0], 1000.0);
+
+        let daily = to_hourly(vec![24.0; 365], Quantity::Energy, &StandardDayProfile::Flat);
+        assert_eq!(daily.len(), 8760);
+        assert!((daily[100] - 1.0).abs() < 1e-9);
+
+        assert_eq!(
+            to_hourly(vec![1.0, 2.0], Quantity::Rate, &StandardDayProfile::Flat),
+            vec![1.0, 2.0]
+        );
+        assert_eq!(Resolution::detect(8784), Some(Resolution::Hourly));
+
+        let measured = [1.0, 3.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0];
+        assert_eq!(
+            distribute(&[8.0, 4.0], &measured).unwrap(),
+            vec![2.0, 6.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0]
+        );
+        assert!(distribute(&[1.0, 2.0, 3.0], &measured).is_err());
+    }
+}
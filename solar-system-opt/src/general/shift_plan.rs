@@ -0,0 +1,337 @@
+use anyhow::{Result, bail};
+use ems_model::building::electricity::ElectricityRate;
+use ems_model::factory::Factory;
+use ems_model::factory::worker::{Time, WeekDay};
+
+use crate::general::factory_load::{
+    BaseLoad, LineProduction, UnscheduledStep, factory_load_profile,
+};
+
+/// Production of all lines of a factory under one set of worker schedules
+#[derive(Debug, Clone)]
+pub struct ShiftPlan {
+    pub name: String,
+    pub lines: Vec<LineProduction>,
+}
+
+/// Annual energy and cost of a shift plan
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShiftPlanCost {
+    pub name: String,
+    pub annual_energy_kwh: f64,
+    pub annual_cost: f64,
+    pub units_per_week: usize,
+    pub unscheduled: Vec<UnscheduledStep>,
+}
+
+/// Annual energy cost of the factory under `plan` and `rate`, including the base loads
+pub fn shift_plan_cost(
+    factory: &Factory,
+    plan: &ShiftPlan,
+    base_loads: &[BaseLoad],
+    rate: &ElectricityRate,
+) -> Result<ShiftPlanCost> {
+    let load = factory_load_profile(factory, &plan.lines[..], base_loads)?;
+    let rates = rate.to_yearly_hourly_rates();
+    Ok(ShiftPlanCost {
+        name: plan.name.clone(),
+        annual_energy_kwh: load.hourly_demand.iter().sum::<f64>() / 1000.0,
+        annual_cost: load
+            .hourly_demand
+            .iter()
+            .zip(&rates)
+            .map(|(demand, rate)| demand / 1000.0 * rate)
+            .sum(),
+        units_per_week: load.units_produced,
+        unscheduled: load.unscheduled,
+    })
+}
+
+/// Costs of several shift plans of the factory, in the order of `plans`
+pub fn compare_shift_plans(
+    factory: &Factory,
+    plans: &[ShiftPlan],
+    base_loads: &[BaseLoad],
+    rate: &ElectricityRate,
+) -> Result<Vec<ShiftPlanCost>> {
+    plans
+        .iter()
+        .map(|plan| shift_plan_cost(factory, plan, base_loads, rate))
+        .collect()
+}
+
+/// Move of the shifts of all workers of a line on one day
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShiftMove {
+    pub line: String, // Line id
+    pub day: WeekDay,
+    pub offset_hours: i32, // Positive for later shifts
+    pub annual_saving: f64,
+}
+
+/// Shift plan proposed by `optimize_shift_plan`, with the moves leading to it
+#[derive(Debug, Clone)]
+pub struct ShiftPlanProposal {
+    pub plan: ShiftPlan,
+    pub moves: Vec<ShiftMove>,
+    pub original: ShiftPlanCost,
+    pub proposed: ShiftPlanCost,
+}
+
+impl ShiftPlanProposal {
+    /// Renders the moves and the saving as plain text
+    pub fn render(&self) -> String {
+        let mut lines = vec![format!(
+            "Annual cost {:.2} -> {:.2} (saving {:.2})",
+            self.original.annual_cost,
+            self.proposed.annual_cost,
+            self.original.annual_cost - self.proposed.annual_cost
+        )];
+        for change in &self.moves {
+            lines.push(format!(
+                "{:<20} {:<10} {:>+3} h {:>12.2}",
+                change.line,
+                format!("{:?}", change.day),
+                change.offset_hours,
+                change.annual_saving
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Moves the shifts of a line on `day` by `offset_hours`, keeping their length. Returns None if
+/// a shift would start on another day or run into the shift of the next day.
+fn move_shifts(
+    production: &LineProduction,
+    day: WeekDay,
+    offset_hours: i32,
+) -> Option<LineProduction> {
+    let mut moved = production.clone();
+    let mut any = false;
+    for worker in &mut moved.workers {
+        for shift in worker
+            .schedule
+            .weekly_shifts
+            .get_mut(&day)
+            .into_iter()
+            .flatten()
+        {
+            let start = shift.start.hour as i32 + offset_hours;
+            if !(0..24).contains(&start) {
+                return None;
+            }
+            shift.start = Time::new(start as u8, shift.start.minute);
+            shift.end = Time::new(
+                (shift.end.hour as i32 + offset_hours).rem_euclid(24) as u8,
+                shift.end.minute,
+            );
+            shift.overnight = shift.end <= shift.start;
+            any = true;
+        }
+        if worker.schedule.validate().is_err() {
+            return None;
+        }
+    }
+    any.then_some(moved)
+}
+
+/// Proposes a cheaper shift plan under time-of-use rates by moving the shifts of a line on a
+/// day by up to `max_offset_hours` (all workers of the line together).
+///
+/// Each round applies the move with the largest saving, until no move saves more. Moves that
+/// would reduce the weekly production or the annual energy (a shift running past the end of
+/// the modelled year) are rejected. Base loads keep their schedules.
+pub fn optimize_shift_plan(
+    factory: &Factory,
+    plan: &ShiftPlan,
+    base_loads: &[BaseLoad],
+    rate: &ElectricityRate,
+    max_offset_hours: u8,
+) -> Result<ShiftPlanProposal> {
+    if max_offset_hours == 0 || max_offset_hours > 23 {
+        bail!(
+            "Shifts can be moved by 1 to 23 hours, got {}",
+            max_offset_hours
+        );
+    }
+    let original = shift_plan_cost(factory, plan, base_loads, rate)?;
+    let mut current = plan.clone();
+    let mut current_cost = original.clone();
+    let mut moves = Vec::new();
+    loop {
+        let mut best: Option<(ShiftPlan, ShiftPlanCost, ShiftMove)> = None;
+        for (index, production) in current.lines.iter().enumerate() {
+            for day in WeekDay::ALL {
+                for offset_hours in -(max_offset_hours as i32)..=max_offset_hours as i32 {
+                    if offset_hours == 0 {
+                        continue;
+                    }
+                    let Some(moved) = move_shifts(production, day, offset_hours) else {
+                        continue;
+                    };
+                    let mut candidate = current.clone();
+                    candidate.lines[index] = moved;
+                    let cost = shift_plan_cost(factory, &candidate, base_loads, rate)?;
+                    let saving = current_cost.annual_cost - cost.annual_cost;
+                    let best_saving = best
+                        .as_ref()
+                        .map_or(1e-6, |(_, _, best)| best.annual_saving);
+                    // a shift moved past midnight of the last day drops out of the year
+                    let keeps_energy =
+                        cost.annual_energy_kwh >= current_cost.annual_energy_kwh - 1e-6;
+                    if cost.units_per_week >= current_cost.units_per_week
+                        && keeps_energy
+                        && saving > best_saving
+                    {
+                        let change = ShiftMove {
+                            line: production.line.id.clone(),
+                            day,
+                            offset_hours,
+                            annual_saving: saving,
+                        };
+                        best = Some((candidate, cost, change));
+                    }
+                }
+            }
+        }
+        match best {
+            Some((plan, cost, change)) => {
+                current = plan;
+                current_cost = cost;
+                moves.push(change);
+            }
+            None => break,
+        }
+    }
+
+    current.name = format!("{} (optimized)", plan.name);
+    current_cost.name = current.name.clone();
+    Ok(ShiftPlanProposal {
+        plan: current,
+        moves,
+        original,
+        proposed: current_cost,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ems_model::building::electricity::{HourRange, RateTier, WeekdayType};
+    use ems_model::factory::line::Line;
+    use ems_model::factory::machine::{MachineControl, Step};
+    use ems_model::factory::worker::{Schedule, Worker};
+
+    fn plan() -> ShiftPlan {
+        let mut line = Line::new("Pressing".to_string(), "line1".to_string());
+        line.add_step(
+            "press".to_string(),
+            "Press".to_string(),
+            "press".to_string(),
+        );
+        let mut worker = Worker::new(
+            "anna".to_string(),
+            "Anna".to_string(),
+            Vec::new(),
+            Schedule::default(),
+        );
+        for day in &WeekDay::ALL[..5] {
+            worker.add_work_shift(*day, Time::new(8, 0), Time::new(16, 0));
+        }
+        ShiftPlan {
+            name: "Day shift".to_string(),
+            lines: vec![LineProduction {
+                line,
+                steps: vec![Step {
+                    power_consumption: 10000.0,
+                    control: MachineControl::Human,
+                    ..Step::new("press".to_string(), "Press".to_string(), 60.0)
+                }],
+                workers: vec![worker],
+                units_per_week: 40,
+            }],
+        }
+    }
+
+    fn factory() -> Factory {
+        Factory {
+            name: "Plant".to_string(),
+            id: "plant".to_string(),
+            location: "Berlin".to_string(),
+            lines: vec!["line1".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_optimize_shift_plan() {
+        let factory = factory();
+        let hours = |from, till| {
+            vec![
+                HourRange::new(from, till, WeekdayType::Weekday),
+                HourRange::new(from, till, WeekdayType::Weekend),
+            ]
+        };
+        let rate = ElectricityRate::tiered(vec![
+            RateTier::new("Night".to_string(), 0.15, hours(22, 6)),
+            RateTier::new("Day".to_string(), 0.35, hours(6, 22)),
+        ]);
+
+        // 8 pressing hours a weekday, 52 weeks plus a Monday
+        let plan = plan();
+        let costs = compare_shift_plans(&factory, std::slice::from_ref(&plan), &[], &rate).unwrap();
+        assert_eq!(costs[0].units_per_week, 40);
+        assert!((costs[0].annual_energy_kwh - (52.0 * 400.0 + 80.0)).abs() < 1e-6);
+        let day_cost = 8.0 * 10.0 * 0.35;
+        assert!((costs[0].annual_cost - 261.0 * day_cost).abs() < 1e-6);
+
+        // the earliest shift (0:00 to 8:00) has six night hours
+        let proposal = optimize_shift_plan(&factory, &plan, &[], &rate, 8).unwrap();
+        assert_eq!(proposal.moves.len(), 5);
+        assert!(
+            proposal
+                .moves
+                .iter()
+                .all(|change| change.offset_hours == -8)
+        );
+        let night_cost = (6.0 * 0.15 + 2.0 * 0.35) * 10.0;
+        assert!((proposal.proposed.annual_cost - 261.0 * night_cost).abs() < 1e-6);
+        assert_eq!(proposal.proposed.units_per_week, 40);
+        assert_eq!(proposal.original, costs[0]);
+        let shift = &proposal.plan.lines[0].workers[0]
+            .schedule
+            .get_shifts(WeekDay::Friday)[0];
+        assert_eq!((shift.start.hour, shift.end.hour), (0, 8));
+        assert!(proposal.render().contains("Monday"));
+    }
+
+    #[test]
+    fn test_optimize_shift_plan_edge_cases() {
+        let factory = factory();
+        let plan = plan();
+        let rate = ElectricityRate::fixed(0.3);
+        for max_offset_hours in [0, 24] {
+            assert!(optimize_shift_plan(&factory, &plan, &[], &rate, max_offset_hours).is_err());
+        }
+        assert!(
+            compare_shift_plans(&factory, &[], &[], &rate)
+                .unwrap()
+                .is_empty()
+        );
+
+        // under a flat rate no move saves anything, not even a Monday shift moved past
+        // midnight on December 31st
+        let proposal = optimize_shift_plan(&factory, &plan, &[], &rate, 23).unwrap();
+        assert!(proposal.moves.is_empty());
+        assert_eq!(proposal.proposed.annual_cost, proposal.original.annual_cost);
+        assert_eq!(proposal.plan.name, "Day shift (optimized)");
+
+        // a line without workers produces nothing and has no shifts to move
+        let mut idle = plan.clone();
+        idle.lines[0].workers.clear();
+        let proposal = optimize_shift_plan(&factory, &idle, &[], &rate, 8).unwrap();
+        assert_eq!(proposal.original.units_per_week, 0);
+        assert_eq!(proposal.original.annual_energy_kwh, 0.0);
+        assert!(proposal.moves.is_empty());
+    }
+}
@@ -0,0 +1,378 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::{DateTime, Datelike, NaiveDate};
+
+const HOURS_PER_YEAR: usize = 8760;
+
+/// Telemetry topic that contributes to the household demand
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopicMapping {
+    pub topic: String,
+    pub scale: f64, // Converts a reading into W supplied to the household loads, e.g. 1000 for kW or -1 for battery charging power
+    pub json_field: Option<String>, // Field of a JSON payload holding the reading; None for plain numbers
+}
+
+impl TopicMapping {
+    pub fn new(topic: &str, scale: f64) -> Self {
+        Self {
+            topic: topic.to_string(),
+            scale,
+            json_field: None,
+        }
+    }
+
+    pub fn with_json_field(mut self, field: &str) -> Self {
+        self.json_field = Some(field.to_string());
+        self
+    }
+
+    /// Reads the power in W from an MQTT payload
+    fn power_w(&self, payload: &str) -> Result<f64> {
+        let value = match &self.json_field {
+            Some(field) => {
+                let json: serde_json::Value = serde_json::from_str(payload)
+                    .with_context(|| format!("Invalid JSON payload on {}", self.topic))?;
+                json.get(field)
+                    .and_then(|value| value.as_f64())
+                    .ok_or_else(|| anyhow!("No number in field {} on {}", field, self.topic))?
+            }
+            None => payload
+                .trim()
+                .parse::<f64>()
+                .with_context(|| format!("Invalid payload on {}: '{}'", self.topic, payload))?,
+        };
+        Ok(value * self.scale)
+    }
+}
+
+/// Aggregates power readings of home EMS telemetry (MQTT topics or a CSV dump of them) into the
+/// hourly household demand of one year.
+///
+/// Every reading holds until the next reading of the same topic, but at most for
+/// `max_gap_seconds`. The demand of an hour is the sum of all mapped topics, e.g. grid import +
+/// PV production - battery charging, or a single consumption meter. Readings outside the year
+/// are ignored.
+#[derive(Debug, Clone)]
+pub struct TelemetryAggregator {
+    pub max_gap_seconds: f64,
+    year: i32,
+    year_start: f64, // Unix timestamp of January 1st 00:00 UTC
+    mappings: Vec<TopicMapping>,
+    last_readings: Vec<Option<(f64, f64)>>, // Per topic: timestamp and power in W
+    energy_wh: Vec<Vec<f64>>,               // Per topic and hour
+    covered_seconds: Vec<Vec<f64>>,         // Per topic and hour
+}
+
+/// Hourly demand built from telemetry
+#[derive(Debug, Clone, PartialEq)]
+pub struct HourlyTelemetry {
+    pub year: i32,
+    pub demand_wh: Vec<f64>, // Demand for 8760 hours, scaled up to the full hour if partly covered
+    pub coverage: Vec<f64>,  // Share of each hour (0-1) covered by readings of every topic
+}
+
+impl TelemetryAggregator {
+    /// Default hold time of a reading: 15 minutes
+    pub const DEFAULT_MAX_GAP_SECONDS: f64 = 900.0;
+
+    pub fn new(year: i32, mappings: Vec<TopicMapping>) -> Result<Self> {
+        if mappings.is_empty() {
+            bail!("At least one telemetry topic is needed");
+        }
+        let year_start = NaiveDate::from_ymd_opt(year, 1, 1)
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .ok_or_else(|| anyhow!("Invalid year: {}", year))?
+            .and_utc()
+            .timestamp() as f64;
+        let num_topics = mappings.len();
+        Ok(Self {
+            max_gap_seconds: Self::DEFAULT_MAX_GAP_SECONDS,
+            year,
+            year_start,
+            mappings,
+            last_readings: vec![None; num_topics],
+            energy_wh: vec![vec![0.0; HOURS_PER_YEAR]; num_topics],
+            covered_seconds: vec![vec![0.0; HOURS_PER_YEAR]; num_topics],
+        })
+    }
+
+    pub fn with_max_gap(mut self, seconds: f64) -> Self {
+        self.max_gap_seconds = seconds;
+        self
+    }
+
+    /// Adds one message, e.g. from an MQTT subscription. Messages of unmapped topics are
+    /// ignored, readings of a topic have to arrive in chronological order.
+    pub fn push(&mut self, topic: &str, timestamp: f64, payload: &str) -> Result<()> {
+        let Some(index) = self
+            .mappings
+            .iter()
+            .position(|mapping| mapping.topic == topic)
+        else {
+            return Ok(());
+        };
+        let power_w = self.mappings[index].power_w(payload)?;
+        if let Some((last_timestamp, last_power_w)) = self.last_readings[index] {
+            if timestamp < last_timestamp {
+                bail!(
+                    "Reading of {} at {} is older than the previous one at {}",
+                    topic,
+                    timestamp,
+                    last_timestamp
+                );
+            }
+            let end = timestamp.min(last_timestamp + self.max_gap_seconds);
+            self.integrate(index, last_timestamp, end, last_power_w);
+        }
+        self.last_readings[index] = Some((timestamp, power_w));
+        Ok(())
+    }
+
+    /// Adds a CSV dump with the columns `timestamp,topic,payload`. Timestamps are Unix seconds
+    /// or RFC 3339, the payload may contain commas (JSON). A header line is skipped.
+    pub fn read_csv_dump(&mut self, file_path: &str) -> Result<()> {
+        let file =
+            File::open(file_path).with_context(|| format!("Failed to open file: {}", file_path))?;
+        for (line_num, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.with_context(|| format!("Failed to read line {}", line_num + 1))?;
+            let parts: Vec<&str> = line.splitn(3, ',').collect();
+            if line.trim().is_empty() || (line_num == 0 && parse_timestamp(parts[0]).is_err()) {
+                continue;
+            }
+            if parts.len() < 3 {
+                bail!("Invalid CSV format on line {}: '{}'", line_num + 1, line);
+            }
+            let timestamp = parse_timestamp(parts[0])
+                .with_context(|| format!("Invalid timestamp on line {}", line_num + 1))?;
+            self.push(parts[1].trim(), timestamp, parts[2])
+                .with_context(|| format!("Invalid reading on line {}", line_num + 1))?;
+        }
+        Ok(())
+    }
+
+    /// Hourly demand of all readings pushed so far
+    pub fn finish(&self) -> HourlyTelemetry {
+        let mut demand_wh = vec![0.0; HOURS_PER_YEAR];
+        let mut coverage = vec![1.0f64; HOURS_PER_YEAR];
+        for (energy, covered) in self.energy_wh.iter().zip(&self.covered_seconds) {
+            for hour in 0..HOURS_PER_YEAR {
+                let share = covered[hour] / 3600.0;
+                coverage[hour] = coverage[hour].min(share);
+                if share > 0.0 {
+                    demand_wh[hour] += energy[hour] / share;
+                }
+            }
+        }
+        HourlyTelemetry {
+            year: self.year,
+            demand_wh,
+            coverage,
+        }
+    }
+
+    /// Adds the energy of a constant power between two timestamps to the hours it spans
+    fn integrate(&mut self, index: usize, start: f64, end: f64, power_w: f64) {
+        let mut from = (start - self.year_start).max(0.0);
+        let to = (end - self.year_start).min(HOURS_PER_YEAR as f64 * 3600.0);
+        while from < to {
+            let hour = (from / 3600.0) as usize;
+            let until = to.min((hour + 1) as f64 * 3600.0);
+            self.energy_wh[index][hour] += power_w * (until - from) / 3600.0;
+            self.covered_seconds[index][hour] += until - from;
+            from = until;
+        }
+    }
+}
+
+impl HourlyTelemetry {
+    /// Demand vector for the optimizer. Hours covered less than `min_coverage` are filled with
+    /// the mean of the same hour of day in the same month, or of the whole data if that month
+    /// has no data. Negative demand (e.g. unmetered export) is cut at zero.
+    pub fn demand_vector(&self, min_coverage: f64) -> Result<Vec<f64>> {
+        let is_covered = |hour: usize| self.coverage[hour] >= min_coverage;
+        let month = |hour: usize| {
+            NaiveDate::from_yo_opt(self.year, (hour / 24) as u32 + 1)
+                .map(|date| date.month0() as usize)
+                .unwrap_or(11)
+        };
+        let mut sums = [[(0.0, 0usize); 24]; 13]; // Per month and for the whole data (index 12)
+        for hour in (0..HOURS_PER_YEAR).filter(|&hour| is_covered(hour)) {
+            for group in [month(hour), 12] {
+                let (sum, count) = &mut sums[group][hour % 24];
+                *sum += self.demand_wh[hour];
+                *count += 1;
+            }
+        }
+        if sums[12].iter().all(|&(_, count)| count == 0) {
+            bail!("No hour is covered by the telemetry");
+        }
+
+        (0..HOURS_PER_YEAR)
+            .map(|hour| {
+                if is_covered(hour) {
+                    return Ok(self.demand_wh[hour].max(0.0));
+                }
+                [month(hour), 12]
+                    .iter()
+                    .map(|&group| sums[group][hour % 24])
+                    .find(|&(_, count)| count > 0)
+                    .map(|(sum, count)| (sum / count as f64).max(0.0))
+                    .ok_or_else(|| anyhow!("No telemetry for hour {} of the day", hour % 24))
+            })
+            .collect()
+    }
+
+    /// Number of hours covered at least `min_coverage`
+    pub fn covered_hours(&self, min_coverage: f64) -> usize {
+        self.coverage
+            .iter()
+            .filter(|&&coverage| coverage >= min_coverage)
+            .count()
+    }
+}
+
+/// Parses Unix seconds or an RFC 3339 timestamp
+fn parse_timestamp(value: &str) -> Result<f64> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<f64>() {
+        return Ok(seconds);
+    }
+    let timestamp = DateTime::parse_from_rfc3339(value)
+        .with_context(|| format!("Invalid timestamp: '{}'", value))?;
+    Ok(timestamp.timestamp_millis() as f64 / 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2023-01-01 00:00 UTC
+    const YEAR_START: f64 = 1672531200.0;
+
+    fn household() -> TelemetryAggregator {
+        TelemetryAggregator::new(
+            2023,
+            vec![
+                TopicMapping::new("ems/grid/power", 1.0),
+                TopicMapping::new("ems/pv/power", 1000.0), // kW
+                TopicMapping::new("ems/battery", -1.0).with_json_field("charge_w"),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_aggregate_readings() {
+        let mut aggregator = household();
+        for minute in (0..=120).step_by(10) {
+            let timestamp = YEAR_START + minute as f64 * 60.0;
+            aggregator.push("ems/grid/power", timestamp, "800").unwrap();
+            aggregator.push("ems/pv/power", timestamp, "0.5").unwrap();
+            aggregator
+                .push("ems/battery", timestamp, r#"{"charge_w": 300, "soc": 40}"#)
+                .unwrap();
+            aggregator.push("ems/other", timestamp, "junk").unwrap();
+        }
+        assert!(aggregator.push("ems/grid/power", YEAR_START, "1").is_err());
+        assert!(
+            aggregator
+                .push("ems/pv/power", YEAR_START + 7300.0, "a")
+                .is_err()
+        );
+
+        let hourly = aggregator.finish();
+        assert!((hourly.demand_wh[0] - 1000.0).abs() < 1e-9);
+        assert!((hourly.demand_wh[1] - 1000.0).abs() < 1e-9);
+        assert_eq!(hourly.coverage[0], 1.0);
+        assert_eq!(hourly.coverage[2], 0.0);
+        assert_eq!(hourly.covered_hours(0.9), 2);
+    }
+
+    #[test]
+    fn test_partly_covered_hour() {
+        let mut aggregator = TelemetryAggregator::new(2023, vec![TopicMapping::new("load", 1.0)])
+            .unwrap()
+            .with_max_gap(3600.0);
+        // 2 kW from 0:30 to 1:30, 1 kW until the meter goes silent for longer than the max gap
+        aggregator
+            .push("load", YEAR_START + 1800.0, "2000")
+            .unwrap();
+        aggregator
+            .push("load", YEAR_START + 5400.0, "1000")
+            .unwrap();
+        aggregator
+            .push("load", YEAR_START + 36000.0, "1000")
+            .unwrap();
+
+        let hourly = aggregator.finish();
+        assert_eq!(hourly.coverage[0], 0.5);
+        assert!((hourly.demand_wh[0] - 2000.0).abs() < 1e-9);
+        assert_eq!(hourly.coverage[1], 1.0);
+        assert!((hourly.demand_wh[1] - 1500.0).abs() < 1e-9);
+        assert_eq!(hourly.coverage[2], 0.5);
+        assert!((hourly.demand_wh[2] - 1000.0).abs() < 1e-9);
+        assert_eq!(hourly.coverage[3], 0.0);
+    }
+
+    #[test]
+    fn test_demand_vector_fills_gaps() {
+        let mut coverage = vec![0.0; HOURS_PER_YEAR];
+        let mut demand_wh = vec![0.0; HOURS_PER_YEAR];
+        // two covered days in January
+        for hour in 0..48 {
+            coverage[hour] = 1.0;
+            demand_wh[hour] = if hour % 24 == 12 { 600.0 } else { 200.0 };
+        }
+        demand_wh[36] = 1000.0;
+        let hourly = HourlyTelemetry {
+            year: 2023,
+            demand_wh,
+            coverage,
+        };
+
+        let demand = hourly.demand_vector(0.9).unwrap();
+        assert_eq!(demand.len(), HOURS_PER_YEAR);
+        assert_eq!(demand[36], 1000.0);
+        assert_eq!(demand[24 * 10 + 12], 800.0);
+        assert_eq!(demand[24 * 200 + 5], 200.0);
+
+        let empty = HourlyTelemetry {
+            year: 2023,
+            demand_wh: vec![0.0; HOURS_PER_YEAR],
+            coverage: vec![0.0; HOURS_PER_YEAR],
+        };
+        assert!(empty.demand_vector(0.9).is_err());
+    }
+
+    #[test]
+    fn test_read_csv_dump() {
+        let dump = "\
+timestamp,topic,payload
+2023-01-01T00:00:00Z,ems/grid/power,400
+2023-01-01T00:00:00Z,ems/pv/power,0.2
+2023-01-01T00:00:00Z,ems/battery,{\"charge_w\": 0, \"soc\": 50}
+1672534800,ems/grid/power,400
+1672534800,ems/pv/power,0.2
+2023-01-01T02:00:00+01:00,ems/battery,{\"charge_w\": 0, \"soc\": 50}
+";
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(&file, dump).unwrap();
+
+        let mut aggregator = household().with_max_gap(3600.0);
+        aggregator
+            .read_csv_dump(file.path().to_str().unwrap())
+            .unwrap();
+        let hourly = aggregator.finish();
+        assert!((hourly.demand_wh[0] - 600.0).abs() < 1e-9);
+        assert_eq!(hourly.coverage[0], 1.0);
+
+        std::fs::write(&file, "2023-01-01T00:00:00Z,ems/grid/power\n").unwrap();
+        assert!(
+            household()
+                .read_csv_dump(file.path().to_str().unwrap())
+                .is_err()
+        );
+    }
+}
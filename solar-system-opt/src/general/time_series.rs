@@ -0,0 +1,331 @@
+use std::fmt;
+
+use anyhow::{Result, bail};
+
+use crate::general::calendar::{NUM_HOURS, date_label, day_of_year};
+use crate::general::resampling::{
+    Quantity, Resolution, StandardDayProfile, daily_to_hourly, quarter_hourly_to_hourly,
+};
+
+/// Point in the modelled (non-leap) year in minutes since January 1st 00:00
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp(usize);
+
+impl Timestamp {
+    pub const YEAR_START: Timestamp = Timestamp(0);
+
+    /// `month` and `day` start at 1
+    pub fn new(month: usize, day: usize, hour: usize, minute: usize) -> Result<Self> {
+        let day = day_of_year(month, day)?;
+        if hour >= 24 || minute >= 60 {
+            bail!("Invalid time {:02}:{:02}", hour, minute);
+        }
+        Ok(Self((day * 24 + hour) * 60 + minute))
+    }
+
+    /// Start of an hour of the year, 0 for January 1st 00:00
+    pub fn from_hour(hour_of_year: usize) -> Self {
+        Self(hour_of_year * 60)
+    }
+
+    pub fn minutes(&self) -> usize {
+        self.0
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let minute_of_day = self.0 % 1440;
+        write!(
+            f,
+            "{} {:02}:{:02}",
+            date_label(self.0 / 1440),
+            minute_of_day / 60,
+            minute_of_day % 60
+        )
+    }
+}
+
+/// Unit of the values of a time series
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeriesUnit {
+    Wh,          // Energy per step
+    KWh,         // Energy per step
+    W,           // Mean power of a step
+    KW,          // Mean power of a step
+    Irradiance,  // Normalized irradiance as in ts_res.csv
+    Celsius,     // Temperature
+    PricePerKwh, // Electricity price in the currency of the cost inputs
+    Factor,      // Dimensionless, e.g. a COP
+}
+
+impl SeriesUnit {
+    /// How values combine when the resolution changes
+    pub fn quantity(&self) -> Quantity {
+        match self {
+            SeriesUnit::Wh | SeriesUnit::KWh => Quantity::Energy,
+            _ => Quantity::Rate,
+        }
+    }
+}
+
+/// Series of equally spaced values with the time of the first value, the resolution and the
+/// unit, so series of different sources can't be combined off by some steps
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeSeries {
+    pub start: Timestamp,
+    pub resolution: Resolution,
+    pub unit: SeriesUnit,
+    pub values: Vec<f64>,
+}
+
+impl TimeSeries {
+    pub fn new(
+        start: Timestamp,
+        resolution: Resolution,
+        unit: SeriesUnit,
+        values: Vec<f64>,
+    ) -> Self {
+        Self {
+            start,
+            resolution,
+            unit,
+            values,
+        }
+    }
+
+    /// Hourly series of a whole year, the input the optimizer takes
+    pub fn hourly_year(unit: SeriesUnit, values: Vec<f64>) -> Result<Self> {
+        if values.len() != NUM_HOURS {
+            bail!(
+                "A year needs {} hourly values, got {}",
+                NUM_HOURS,
+                values.len()
+            );
+        }
+        Ok(Self::new(
+            Timestamp::YEAR_START,
+            Resolution::Hourly,
+            unit,
+            values,
+        ))
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Start of the step at `index`
+    pub fn timestamp(&self, index: usize) -> Timestamp {
+        Timestamp(self.start.0 + index * self.resolution.minutes())
+    }
+
+    /// End of the last step
+    pub fn end(&self) -> Timestamp {
+        self.timestamp(self.len())
+    }
+
+    /// Index of the step starting at `timestamp`, None if no step starts there
+    pub fn index_of(&self, timestamp: Timestamp) -> Option<usize> {
+        let offset = timestamp.0.checked_sub(self.start.0)?;
+        let step = self.resolution.minutes();
+        (offset % step == 0 && offset / step < self.len()).then_some(offset / step)
+    }
+
+    /// Steps from `from` up to (not including) `to`, both on step boundaries within the series
+    pub fn slice(&self, from: Timestamp, to: Timestamp) -> Result<TimeSeries> {
+        let boundary = |timestamp: Timestamp| {
+            if timestamp == self.end() {
+                Some(self.len())
+            } else {
+                self.index_of(timestamp)
+            }
+        };
+        let (Some(first), Some(last)) = (boundary(from), boundary(to)) else {
+            bail!(
+                "{} to {} is not on the steps of the series from {} to {}",
+                from,
+                to,
+                self.start,
+                self.end()
+            );
+        };
+        if first > last {
+            bail!("{} is after {}", from, to);
+        }
+        Ok(Self::new(
+            from,
+            self.resolution,
+            self.unit,
+            self.values[first..last].to_vec(),
+        ))
+    }
+
+    /// Errors unless both series start at the same time and have the same resolution and length
+    pub fn check_aligned(&self, other: &TimeSeries) -> Result<()> {
+        if self.resolution != other.resolution {
+            bail!(
+                "Series resolutions differ: {:?} and {:?}",
+                self.resolution,
+                other.resolution
+            );
+        }
+        if self.start != other.start || self.len() != other.len() {
+            bail!(
+                "Series are not aligned: {} to {} and {} to {}",
+                self.start,
+                self.end(),
+                other.start,
+                other.end()
+            );
+        }
+        Ok(())
+    }
+
+    /// Combines two aligned series value by value, e.g. demand minus PV production
+    pub fn combine(
+        &self,
+        other: &TimeSeries,
+        unit: SeriesUnit,
+        f: impl Fn(f64, f64) -> f64,
+    ) -> Result<TimeSeries> {
+        self.check_aligned(other)?;
+        Ok(Self::new(
+            self.start,
+            self.resolution,
+            unit,
+            self.values
+                .iter()
+                .zip(&other.values)
+                .map(|(&a, &b)| f(a, b))
+                .collect(),
+        ))
+    }
+
+    /// The series at hourly resolution, see `resampling::to_hourly`. `profile` is only used for
+    /// daily values.
+    pub fn to_hourly(&self, profile: &StandardDayProfile) -> Result<TimeSeries> {
+        let values = match self.resolution {
+            Resolution::Hourly => self.values.clone(),
+            Resolution::QuarterHourly => {
+                quarter_hourly_to_hourly(&self.values, self.unit.quantity())?
+            }
+            // the weekday of each day counts from January 1st, not from the start of the series
+            Resolution::Daily => {
+                let first_day = self.start.0 / 1440;
+                self.values
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(day, &value)| {
+                        let shape = StandardDayProfile::Custom(profile.shape(first_day + day));
+                        daily_to_hourly(&[value], self.unit.quantity(), &shape)
+                    })
+                    .collect()
+            }
+        };
+        Ok(Self::new(self.start, Resolution::Hourly, self.unit, values))
+    }
+
+    /// Energy per step in Wh, for energy and power series
+    pub fn wh_values(&self) -> Result<Vec<f64>> {
+        let hours = self.resolution.minutes() as f64 / 60.0;
+        let factor = match self.unit {
+            SeriesUnit::Wh => 1.0,
+            SeriesUnit::KWh => 1000.0,
+            SeriesUnit::W => hours,
+            SeriesUnit::KW => 1000.0 * hours,
+            unit => bail!("{:?} is not an energy or power unit", unit),
+        };
+        Ok(self.values.iter().map(|value| value * factor).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamp() {
+        let timestamp = Timestamp::new(6, 21, 12, 15).unwrap();
+        assert_eq!(timestamp.minutes(), ((171 * 24) + 12) * 60 + 15);
+        assert_eq!(timestamp.to_string(), "Jun 21 12:15");
+        assert_eq!(Timestamp::from_hour(24).to_string(), "Jan 2 00:00");
+        assert!(Timestamp::new(2, 29, 0, 0).is_err());
+        assert!(Timestamp::new(1, 1, 24, 0).is_err());
+    }
+
+    #[test]
+    fn test_slice_and_alignment() {
+        let demand =
+            TimeSeries::hourly_year(SeriesUnit::Wh, (0..NUM_HOURS).map(|t| t as f64).collect())
+                .unwrap();
+        let june = demand
+            .slice(
+                Timestamp::new(6, 1, 0, 0).unwrap(),
+                Timestamp::new(7, 1, 0, 0).unwrap(),
+            )
+            .unwrap();
+        assert_eq!(june.len(), 30 * 24);
+        assert_eq!(june.values[0], (151 * 24) as f64);
+        assert_eq!(june.start.to_string(), "Jun 1 00:00");
+        assert_eq!(
+            demand.slice(june.start, demand.end()).unwrap().end(),
+            demand.end()
+        );
+        assert!(demand.slice(Timestamp(30), june.end()).is_err());
+        assert!(demand.slice(june.end(), june.start).is_err());
+
+        // a PV series starting one hour late can't be combined with the demand
+        let pv = TimeSeries::new(
+            Timestamp::from_hour(1),
+            Resolution::Hourly,
+            SeriesUnit::Wh,
+            vec![100.0; NUM_HOURS],
+        );
+        assert!(demand.combine(&pv, SeriesUnit::Wh, |d, p| d - p).is_err());
+        let pv = TimeSeries {
+            start: Timestamp::YEAR_START,
+            ..pv
+        };
+        let residual = demand.combine(&pv, SeriesUnit::Wh, |d, p| d - p).unwrap();
+        assert_eq!(residual.values[200], 100.0);
+        assert!(TimeSeries::hourly_year(SeriesUnit::Wh, vec![0.0; 10]).is_err());
+    }
+
+    #[test]
+    fn test_resolutions() {
+        let meter = TimeSeries::new(
+            Timestamp::YEAR_START,
+            Resolution::QuarterHourly,
+            SeriesUnit::KW,
+            vec![2.0; 8],
+        );
+        assert_eq!(meter.index_of(Timestamp(45)), Some(3));
+        assert_eq!(meter.index_of(Timestamp(50)), None);
+        assert_eq!(meter.wh_values().unwrap()[0], 500.0);
+        let hourly = meter.to_hourly(&StandardDayProfile::Flat).unwrap();
+        assert_eq!(hourly.values, vec![2.0, 2.0]);
+        assert_eq!(hourly.wh_values().unwrap()[0], 2000.0);
+
+        // Saturday January 6th follows the weekend shape
+        let daily = TimeSeries::new(
+            Timestamp::new(1, 6, 0, 0).unwrap(),
+            Resolution::Daily,
+            SeriesUnit::KWh,
+            vec![10.0],
+        );
+        let hourly = daily.to_hourly(&StandardDayProfile::Household).unwrap();
+        assert_eq!(hourly.len(), 24);
+        assert!((hourly.values.iter().sum::<f64>() - 10.0).abs() < 1e-9);
+        let weekend = StandardDayProfile::Household.shape(5);
+        assert!((hourly.values[7] / hourly.values[0] - weekend[7] / weekend[0]).abs() < 1e-9);
+
+        let prices =
+            TimeSeries::hourly_year(SeriesUnit::PricePerKwh, vec![0.3; NUM_HOURS]).unwrap();
+        assert!(prices.wh_values().is_err());
+    }
+}
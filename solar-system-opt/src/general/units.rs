@@ -0,0 +1,206 @@
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, Mul, Sub};
+
+/// Amount of energy, stored in Wh.
+///
+/// Use the constructors and accessors instead of `/ 1000.0` conversions, so a value can't be
+/// read in the wrong unit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+pub struct Energy(f64);
+
+/// Power, stored in W
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+pub struct Power(f64);
+
+/// Share of a whole such as the autarky, stored as a fraction (0-1).
+///
+/// Use `from_percent`/`percent` and `from_fraction`/`fraction` instead of `* 100.0`, so a value
+/// can't be read in the wrong convention.
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+pub struct Ratio(f64);
+
+impl Energy {
+    pub const ZERO: Self = Self(0.0);
+
+    pub const fn from_wh(wh: f64) -> Self {
+        Self(wh)
+    }
+
+    pub const fn from_kwh(kwh: f64) -> Self {
+        Self(kwh * 1000.0)
+    }
+
+    pub const fn wh(self) -> f64 {
+        self.0
+    }
+
+    pub const fn kwh(self) -> f64 {
+        self.0 / 1000.0
+    }
+}
+
+impl Power {
+    pub const ZERO: Self = Self(0.0);
+
+    pub const fn from_w(w: f64) -> Self {
+        Self(w)
+    }
+
+    pub const fn from_kw(kw: f64) -> Self {
+        Self(kw * 1000.0)
+    }
+
+    pub const fn w(self) -> f64 {
+        self.0
+    }
+
+    pub const fn kw(self) -> f64 {
+        self.0 / 1000.0
+    }
+
+    /// Energy delivered at this power over `hours`
+    pub fn over_hours(self, hours: f64) -> Energy {
+        Energy(self.0 * hours)
+    }
+}
+
+impl Ratio {
+    pub const ZERO: Self = Self(0.0);
+    pub const ONE: Self = Self(1.0);
+
+    pub const fn from_fraction(fraction: f64) -> Self {
+        Self(fraction)
+    }
+
+    pub const fn from_percent(percent: f64) -> Self {
+        Self(percent / 100.0)
+    }
+
+    /// Share of `part` in `whole`, zero for an empty whole
+    pub fn of(part: f64, whole: f64) -> Self {
+        if whole > 0.0 {
+            Self(part / whole)
+        } else {
+            Self::ZERO
+        }
+    }
+
+    pub const fn fraction(self) -> f64 {
+        self.0
+    }
+
+    pub const fn percent(self) -> f64 {
+        self.0 * 100.0
+    }
+}
+
+macro_rules! quantity_ops {
+    ($quantity:ident) => {
+        impl Add for $quantity {
+            type Output = Self;
+            fn add(self, other: Self) -> Self {
+                Self(self.0 + other.0)
+            }
+        }
+
+        impl AddAssign for $quantity {
+            fn add_assign(&mut self, other: Self) {
+                self.0 += other.0;
+            }
+        }
+
+        impl Sub for $quantity {
+            type Output = Self;
+            fn sub(self, other: Self) -> Self {
+                Self(self.0 - other.0)
+            }
+        }
+
+        impl Mul<f64> for $quantity {
+            type Output = Self;
+            fn mul(self, factor: f64) -> Self {
+                Self(self.0 * factor)
+            }
+        }
+
+        impl Div<f64> for $quantity {
+            type Output = Self;
+            fn div(self, divisor: f64) -> Self {
+                Self(self.0 / divisor)
+            }
+        }
+
+        // Ratio of two quantities
+        impl Div for $quantity {
+            type Output = f64;
+            fn div(self, other: Self) -> f64 {
+                self.0 / other.0
+            }
+        }
+
+        impl Sum for $quantity {
+            fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+                Self(iter.map(|quantity| quantity.0).sum())
+            }
+        }
+    };
+}
+
+quantity_ops!(Energy);
+quantity_ops!(Power);
+quantity_ops!(Ratio);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_units() {
+        let usage = Energy::from_kwh(4173.44);
+        assert!((usage.wh() - 4173440.0).abs() < 1e-6);
+        assert_eq!(Energy::from_wh(2500.0).kwh(), 2.5);
+        assert_eq!(Power::from_kw(11.0).w(), 11000.0);
+        assert_eq!(Power::from_w(3700.0).over_hours(2.0), Energy::from_kwh(7.4));
+
+        let total: Energy = [Energy::from_wh(500.0), Energy::from_kwh(1.5)]
+            .into_iter()
+            .sum();
+        assert_eq!(total, Energy::from_kwh(2.0));
+        assert_eq!(total * 2.0 - Energy::from_wh(1000.0), Energy::from_kwh(3.0));
+        assert_eq!(total / Energy::from_kwh(4.0), 0.5);
+        assert!(Power::from_kw(1.0) > Power::from_w(999.0));
+
+        let autarky = Ratio::from_percent(62.5);
+        assert_eq!(autarky.fraction(), 0.625);
+        assert_eq!(Ratio::from_fraction(0.25).percent(), 25.0);
+        assert_eq!(Ratio::of(1.0, 4.0), Ratio::from_fraction(0.25));
+        assert_eq!(Ratio::of(1.0, 0.0), Ratio::ZERO);
+        assert_eq!(
+            autarky - Ratio::from_percent(50.0),
+            Ratio::from_percent(12.5)
+        );
+    }
+
+    #[test]
+    fn test_units_edge_cases() {
+        let none: Vec<Energy> = Vec::new();
+        assert_eq!(none.into_iter().sum::<Energy>(), Energy::ZERO);
+        assert_eq!(std::iter::empty::<Power>().sum::<Power>(), Power::ZERO);
+        assert_eq!(Power::from_kw(5.0).over_hours(0.0), Energy::ZERO);
+
+        // an empty or invalid whole has no share
+        assert_eq!(Ratio::of(0.0, 0.0), Ratio::ZERO);
+        assert_eq!(Ratio::of(1.0, -4.0), Ratio::ZERO);
+        assert_eq!(Ratio::of(1.0, f64::NAN), Ratio::ZERO);
+        // shares are not clamped, e.g. a feed-in above the demand
+        assert_eq!(Ratio::of(6.0, 4.0).percent(), 150.0);
+        assert_eq!(Ratio::of(-1.0, 4.0), Ratio::from_fraction(-0.25));
+
+        let mut total = Energy::ZERO;
+        total += Energy::from_kwh(-1.0);
+        assert!(total < Energy::ZERO);
+        assert_eq!(total.wh(), -1000.0);
+        assert!((Energy::from_kwh(1.0) / Energy::ZERO).is_infinite());
+        assert!((Energy::ZERO / Energy::ZERO).is_nan());
+    }
+}
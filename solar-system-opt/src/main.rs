@@ -1 +1,44 @@
-fn main() {}
+use solar_system_opt::simple::day_selection::{DaySelection, parse_day_selections};
+use solar_system_opt::simple::simple_opt_re::{example_scenario, run_simple_opt_with_day_plots};
+use tracing::{Level, error};
+
+/// Log level from the command line: `--quiet`/`-q` only shows warnings, `--verbose`/`-v` adds debug output
+fn log_level(args: impl Iterator<Item = String>) -> Level {
+    let mut level = Level::INFO;
+    for arg in args {
+        match arg.as_str() {
+            "-q" | "--quiet" => level = Level::WARN,
+            "-v" | "--verbose" => level = Level::DEBUG,
+            _ => {}
+        }
+    }
+    level
+}
+
+/// Days given with `--plot-days`, e.g. `--plot-days 2024-06-21,2024-12-21` or `--plot-days solstices`
+fn plot_days(
+    mut args: impl Iterator<Item = String>,
+) -> Result<Option<Vec<DaySelection>>, Box<dyn std::error::Error>> {
+    while let Some(arg) = args.next() {
+        if arg == "--plot-days" {
+            let value = args.next().ok_or("--plot-days needs a list of days")?;
+            return Ok(Some(parse_day_selections(&value)?));
+        }
+    }
+    Ok(None)
+}
+
+fn main() {
+    tracing_subscriber::fmt()
+        .with_max_level(log_level(std::env::args().skip(1)))
+        .init();
+
+    let result = plot_days(std::env::args().skip(1)).and_then(|days| match days {
+        Some(days) => run_simple_opt_with_day_plots(example_scenario(), 10000.0, &days),
+        None => Ok(()),
+    });
+    if let Err(e) = result {
+        error!("{}", e);
+        std::process::exit(1);
+    }
+}
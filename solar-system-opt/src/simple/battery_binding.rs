@@ -0,0 +1,275 @@
+use crate::simple::solar_system_utils::SimpleOptimizationResults;
+
+/// Bound the battery sits at in an hour of the optimized schedule
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatteryBound {
+    Free,          // Neither full, empty nor at its power limit
+    Full,          // At the usable capacity (new plus existing)
+    Empty,         // At zero state of charge
+    ChargeRate,    // Charging at the c-rate limit
+    DischargeRate, // Discharging at the c-rate limit
+}
+
+/// How often the battery of an optimized system is constrained, and what a bigger battery
+/// would be worth
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatteryBindingAnalysis {
+    pub hourly_bound: Vec<BatteryBound>, // Capacity bounds take precedence over the c-rate
+    pub full_hours: usize,
+    pub empty_hours: usize,
+    pub charge_rate_hours: usize,
+    pub discharge_rate_hours: usize,
+    pub surplus_while_full_kwh: f64, // Exported or curtailed while the battery is full
+    pub import_while_empty_kwh: f64, // Grid import while the battery is empty
+    pub capacity_limited_cycles: usize, // Full with surplus, then empty with import before it is full again
+    pub marginal_value_per_kwh: f64,    // Annual saving of one more kWh of capacity
+}
+
+impl BatteryBindingAnalysis {
+    /// Hours the battery sits at any bound
+    pub fn binding_hours(&self) -> usize {
+        self.full_hours + self.empty_hours + self.charge_rate_hours + self.discharge_rate_hours
+    }
+
+    /// Explains in plain text why a bigger battery does or doesn't help
+    pub fn render(&self) -> String {
+        let mut lines = vec![
+            format!(
+                "Battery full {} h ({:.0} kWh surplus), empty {} h ({:.0} kWh import)",
+                self.full_hours,
+                self.surplus_while_full_kwh,
+                self.empty_hours,
+                self.import_while_empty_kwh
+            ),
+            format!(
+                "Power limit binding {} h charging, {} h discharging",
+                self.charge_rate_hours, self.discharge_rate_hours
+            ),
+        ];
+        lines.push(if self.capacity_limited_cycles > 0 {
+            format!(
+                "{} cycles limited by the capacity: one more kWh saves about {:.2} per year",
+                self.capacity_limited_cycles, self.marginal_value_per_kwh
+            )
+        } else {
+            "No cycle is limited by the capacity: a bigger battery wouldn't be used".to_string()
+        });
+        if self.charge_rate_hours + self.discharge_rate_hours > self.capacity_limited_cycles {
+            lines.push(
+                "The power limit binds more often than the capacity: a higher c-rate helps more"
+                    .to_string(),
+            );
+        }
+        lines.join("\n")
+    }
+}
+
+/// Classifies every hour of the optimized schedule by the battery bound it sits at.
+///
+/// A cycle is limited by the capacity if the battery is full while surplus is exported or
+/// curtailed and runs empty while importing before it is full again: one more kWh would have
+/// been stored and used. Each such cycle is worth the import price times the round trip
+/// efficiency minus the feed-in tariff, from the marginal prices of the run if available and
+/// from `fc_grid` and `feed_in_tariff` otherwise. None without a battery.
+pub fn analyze_battery_binding(
+    results: &SimpleOptimizationResults,
+) -> Option<BatteryBindingAnalysis> {
+    let capacity_wh =
+        (results.battery_capacity_kwh + results.existing_battery_capacity_kwh) * 1000.0;
+    if capacity_wh <= 0.0 || results.hourly_battery_storage.is_empty() {
+        return None;
+    }
+    let config = &results.config;
+    let tolerance = 1.0 + capacity_wh * 1e-4;
+    let max_power = config.c_rate_limit * capacity_wh;
+    let hourly = |series: &Vec<f64>, t: usize| series.get(t).copied().unwrap_or(0.0);
+    let price = |t: usize, fallback: f64| {
+        results
+            .hourly_marginal_price
+            .get(t)
+            .copied()
+            .unwrap_or_else(|| config.currency.convert(fallback))
+    };
+    let round_trip = config.eta_in_bat * config.eta_out_bat;
+
+    let mut analysis = BatteryBindingAnalysis {
+        hourly_bound: Vec::with_capacity(results.hourly_battery_storage.len()),
+        full_hours: 0,
+        empty_hours: 0,
+        charge_rate_hours: 0,
+        discharge_rate_hours: 0,
+        surplus_while_full_kwh: 0.0,
+        import_while_empty_kwh: 0.0,
+        capacity_limited_cycles: 0,
+        marginal_value_per_kwh: 0.0,
+    };
+    // export price of the surplus the last full hour couldn't store
+    let mut stored_surplus_price: Option<f64> = None;
+    for (t, &storage) in results.hourly_battery_storage.iter().enumerate() {
+        let surplus =
+            hourly(&results.hourly_overproduction, t) + hourly(&results.hourly_curtailment, t);
+        let import = hourly(&results.hourly_grid_consumption, t);
+        let bound = if storage >= capacity_wh - tolerance {
+            BatteryBound::Full
+        } else if storage <= tolerance {
+            BatteryBound::Empty
+        } else if hourly(&results.hourly_battery_in, t) >= max_power - tolerance {
+            BatteryBound::ChargeRate
+        } else if hourly(&results.hourly_battery_out, t) >= max_power - tolerance {
+            BatteryBound::DischargeRate
+        } else {
+            BatteryBound::Free
+        };
+        match bound {
+            BatteryBound::Full => {
+                analysis.full_hours += 1;
+                analysis.surplus_while_full_kwh += surplus / 1000.0;
+                if surplus > tolerance {
+                    stored_surplus_price = Some(price(t, config.feed_in_tariff).max(0.0));
+                }
+            }
+            BatteryBound::Empty => {
+                analysis.empty_hours += 1;
+                analysis.import_while_empty_kwh += import / 1000.0;
+                if import > tolerance
+                    && let Some(export_price) = stored_surplus_price.take()
+                {
+                    analysis.capacity_limited_cycles += 1;
+                    analysis.marginal_value_per_kwh +=
+                        (price(t, config.fc_grid) * round_trip - export_price).max(0.0);
+                }
+            }
+            BatteryBound::ChargeRate => analysis.charge_rate_hours += 1,
+            BatteryBound::DischargeRate => analysis.discharge_rate_hours += 1,
+            BatteryBound::Free => {}
+        }
+        analysis.hourly_bound.push(bound);
+    }
+    Some(analysis)
+}
+
+impl SimpleOptimizationResults {
+    /// Battery bounds of the optimized schedule, see `analyze_battery_binding`
+    pub fn battery_binding(&self) -> Option<BatteryBindingAnalysis> {
+        analyze_battery_binding(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::general::currency::Currency;
+    use crate::simple::solar_system_utils::OptimizationConfig;
+
+    #[test]
+    fn test_analyze_battery_binding() {
+        // 5 kWh battery: charged at noon until full with surplus left, emptied in the evening
+        // with import left, for two days
+        let day_storage = [
+            0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 2500.0, 5000.0, 5000.0, 5000.0,
+            5000.0, 5000.0, 5000.0, 4000.0, 2000.0, 1000.0, 0.0, 0.0, 0.0, 0.0,
+        ];
+        let storage: Vec<f64> = day_storage.iter().chain(&day_storage).copied().collect();
+        let mut overproduction = vec![0.0; 48];
+        let mut grid_consumption = vec![0.0; 48];
+        let mut battery_in = vec![0.0; 48];
+        for day in 0..2 {
+            battery_in[day * 24 + 10] = 2500.0;
+            battery_in[day * 24 + 11] = 2500.0;
+            overproduction[day * 24 + 12] = 1500.0;
+            grid_consumption[day * 24 + 21] = 400.0;
+        }
+        let results = SimpleOptimizationResults {
+            battery_capacity_kwh: 5.0,
+            hourly_battery_storage: storage,
+            hourly_battery_in: battery_in,
+            hourly_battery_out: vec![0.0; 48],
+            hourly_overproduction: overproduction,
+            hourly_grid_consumption: grid_consumption,
+            config: OptimizationConfig {
+                c_rate_limit: 0.5,
+                fc_grid: 0.3,
+                feed_in_tariff: 0.08,
+                eta_in_bat: 0.95,
+                eta_out_bat: 0.95,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let analysis = results.battery_binding().unwrap();
+        assert_eq!(analysis.hourly_bound.len(), 48);
+        assert_eq!(analysis.hourly_bound[10], BatteryBound::ChargeRate);
+        assert_eq!(analysis.hourly_bound[12], BatteryBound::Full);
+        assert_eq!(analysis.full_hours, 12);
+        assert_eq!(analysis.charge_rate_hours, 2);
+        assert_eq!(analysis.discharge_rate_hours, 0);
+        assert!((analysis.surplus_while_full_kwh - 3.0).abs() < 1e-9);
+        assert!((analysis.import_while_empty_kwh - 0.8).abs() < 1e-9);
+        assert_eq!(analysis.capacity_limited_cycles, 2);
+        let cycle_value = 0.3 * 0.95 * 0.95 - 0.08;
+        assert!((analysis.marginal_value_per_kwh - 2.0 * cycle_value).abs() < 1e-9);
+        assert_eq!(
+            analysis.binding_hours(),
+            analysis
+                .hourly_bound
+                .iter()
+                .filter(|bound| **bound != BatteryBound::Free)
+                .count()
+        );
+        assert!(
+            analysis
+                .render()
+                .contains("2 cycles limited by the capacity")
+        );
+
+        // without surplus while full a bigger battery has nothing to store
+        let no_surplus = SimpleOptimizationResults {
+            hourly_overproduction: vec![0.0; 48],
+            ..results.clone()
+        };
+        let analysis = no_surplus.battery_binding().unwrap();
+        assert_eq!(analysis.capacity_limited_cycles, 0);
+        assert_eq!(analysis.marginal_value_per_kwh, 0.0);
+
+        assert!(
+            SimpleOptimizationResults::default()
+                .battery_binding()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_analyze_battery_binding_edge_cases() {
+        // a battery without a schedule has nothing to analyze
+        let unscheduled = SimpleOptimizationResults {
+            battery_capacity_kwh: 5.0,
+            ..Default::default()
+        };
+        assert!(unscheduled.battery_binding().is_none());
+
+        // missing hourly series count as zero, fallback prices are converted like the results
+        let results = SimpleOptimizationResults {
+            existing_battery_capacity_kwh: 1.0,
+            hourly_battery_storage: vec![1000.0, 0.0],
+            hourly_overproduction: vec![500.0],
+            hourly_grid_consumption: vec![0.0, 400.0],
+            config: OptimizationConfig {
+                fc_grid: 0.3,
+                feed_in_tariff: 0.1,
+                eta_in_bat: 1.0,
+                eta_out_bat: 1.0,
+                currency: Currency::with_conversion_rate("GBP", 0.5).unwrap(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let analysis = results.battery_binding().unwrap();
+        assert_eq!(
+            analysis.hourly_bound,
+            vec![BatteryBound::Full, BatteryBound::Empty]
+        );
+        assert_eq!(analysis.capacity_limited_cycles, 1);
+        assert!((analysis.marginal_value_per_kwh - (0.3 - 0.1) * 0.5).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,315 @@
+use ems_model::building::electricity::ElectricityRate;
+use ems_model::building::tariff::{FeeUnit, Tariff, TariffEnergyPrice};
+
+use crate::general::calendar::NUM_HOURS;
+use crate::general::calendar::{DAYS_IN_MONTH, MONTHS};
+use crate::simple::solar_system_utils::SimpleOptimizationResults;
+
+/// Line of a reconstructed invoice, `amount` is `quantity * unit_price`
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvoiceLine {
+    pub description: String,
+    pub quantity: f64,
+    pub unit: &'static str, // "kWh", "month" or "year"; "net" for taxes on the net amount
+    pub unit_price: f64,
+    pub amount: f64, // Negative for credits
+}
+
+impl InvoiceLine {
+    fn new(description: &str, quantity: f64, unit: &'static str, unit_price: f64) -> Self {
+        Self {
+            description: description.to_string(),
+            quantity,
+            unit,
+            unit_price,
+            amount: quantity * unit_price,
+        }
+    }
+}
+
+/// Invoice of one calendar month of the modelled year
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonthlyInvoice {
+    pub month: usize, // 1-12
+    pub lines: Vec<InvoiceLine>,
+}
+
+impl MonthlyInvoice {
+    /// Amount due, after taxes and feed-in credits
+    pub fn total(&self) -> f64 {
+        self.lines.iter().map(|line| line.amount).sum()
+    }
+
+    /// Get a line by its description, e.g. "Energy Peak" or "VAT"
+    pub fn line(&self, description: &str) -> Option<&InvoiceLine> {
+        self.lines
+            .iter()
+            .find(|line| line.description == description)
+    }
+
+    /// Renders the invoice as a plain text table
+    pub fn render(&self) -> String {
+        let mut lines = vec![format!(
+            "{:<28} {:>12} {:<6} {:>10} {:>10}",
+            MONTHS[self.month - 1],
+            "Quantity",
+            "",
+            "Price",
+            "Amount"
+        )];
+        for line in &self.lines {
+            lines.push(format!(
+                "{:<28} {:>12.2} {:<6} {:>10.4} {:>10.2}",
+                line.description, line.quantity, line.unit, line.unit_price, line.amount
+            ));
+        }
+        lines.push(format!("{:<28} {:>41.2}", "Total", self.total()));
+        lines.join("\n")
+    }
+}
+
+/// Reconstructs the monthly invoices of a year from hourly grid import and feed-in in Wh.
+///
+/// Energy is billed per time-of-use tier (hours split between tiers are split by time),
+/// followed by the per-kWh fees, the monthly fees and the share of the yearly fees of the
+/// month. Taxes apply to all of them, the feed-in is credited untaxed at `feed_in_prices`.
+/// With `monthly_credit_cap` the credit of a month is limited to its energy charges, as in the
+/// Spanish simplified compensation.
+pub fn monthly_invoices(
+    tariff: &Tariff,
+    grid_import_wh: &[f64],
+    feed_in_wh: &[f64],
+    feed_in_prices: &[f64],
+    monthly_credit_cap: bool,
+) -> Result<Vec<MonthlyInvoice>, Box<dyn std::error::Error>> {
+    tariff.validate()?;
+    for (name, series) in [
+        ("grid import", grid_import_wh),
+        ("feed-in", feed_in_wh),
+        ("feed-in prices", feed_in_prices),
+    ] {
+        if series.len() != NUM_HOURS {
+            return Err(format!(
+                "Billing needs {} hourly {} values, got {}",
+                NUM_HOURS,
+                name,
+                series.len()
+            )
+            .into());
+        }
+    }
+
+    // Share of every hour billed in each energy tier
+    let tiers: Vec<(String, f64, Vec<f64>)> = match &tariff.energy {
+        TariffEnergyPrice::Fixed { rate } => {
+            vec![("Energy".to_string(), *rate, vec![1.0; NUM_HOURS])]
+        }
+        TariffEnergyPrice::TimeOfUse { tiers } => {
+            let ElectricityRate::Tiered { tiers: rate_tiers } = tariff.to_electricity_rate() else {
+                unreachable!("time-of-use tariffs convert to tiered rates");
+            };
+            (0..tiers.len())
+                .map(|index| {
+                    // Rate of 1.0 in the tier and 0.0 elsewhere gives its time share per hour
+                    let indicator = ElectricityRate::tiered(
+                        rate_tiers
+                            .iter()
+                            .enumerate()
+                            .map(|(i, tier)| {
+                                let mut tier = tier.clone();
+                                tier.rate = if i == index { 1.0 } else { 0.0 };
+                                tier
+                            })
+                            .collect(),
+                    );
+                    (
+                        format!("Energy {}", tiers[index].name),
+                        tiers[index].rate,
+                        indicator.to_yearly_hourly_rates(),
+                    )
+                })
+                .collect()
+        }
+    };
+
+    let mut invoices = Vec::with_capacity(12);
+    let mut start = 0;
+    for (month, days) in DAYS_IN_MONTH.iter().enumerate() {
+        let hours = start..start + days * 24;
+        start = hours.end;
+        let import_kwh = grid_import_wh[hours.clone()].iter().sum::<f64>() / 1000.0;
+
+        let mut lines: Vec<InvoiceLine> = tiers
+            .iter()
+            .map(|(description, rate, shares)| {
+                let tier_kwh = hours
+                    .clone()
+                    .map(|t| grid_import_wh[t] * shares[t])
+                    .sum::<f64>()
+                    / 1000.0;
+                InvoiceLine::new(description, tier_kwh, "kWh", *rate)
+            })
+            .collect();
+        let energy_charges: f64 = lines.iter().map(|line| line.amount).sum();
+        for fee in &tariff.fees {
+            lines.push(match fee.unit {
+                FeeUnit::PerKwh => InvoiceLine::new(&fee.name, import_kwh, "kWh", fee.amount),
+                FeeUnit::PerMonth => InvoiceLine::new(&fee.name, 1.0, "month", fee.amount),
+                FeeUnit::PerYear => {
+                    InvoiceLine::new(&fee.name, *days as f64 / 365.0, "year", fee.amount)
+                }
+            });
+        }
+        let net: f64 = lines.iter().map(|line| line.amount).sum();
+        for tax in &tariff.taxes {
+            lines.push(InvoiceLine::new(&tax.name, net, "net", tax.percent / 100.0));
+        }
+
+        let feed_in_kwh = feed_in_wh[hours.clone()].iter().sum::<f64>() / 1000.0;
+        let mut credit: f64 = hours
+            .map(|t| feed_in_wh[t] * feed_in_prices[t])
+            .sum::<f64>()
+            / 1000.0;
+        if monthly_credit_cap {
+            credit = credit.min(energy_charges);
+        }
+        let average_price = if feed_in_kwh > 0.0 {
+            credit / feed_in_kwh
+        } else {
+            0.0
+        };
+        lines.push(InvoiceLine::new(
+            "Feed-in credit",
+            feed_in_kwh,
+            "kWh",
+            -average_price,
+        ));
+
+        invoices.push(MonthlyInvoice {
+            month: month + 1,
+            lines,
+        });
+    }
+    Ok(invoices)
+}
+
+impl SimpleOptimizationResults {
+    /// Monthly invoices of the optimized grid import and feed-in under `tariff`, crediting the
+    /// feed-in at the feed-in prices of the config and applying its monthly credit cap. See
+    /// `monthly_invoices`.
+    pub fn monthly_invoices(
+        &self,
+        tariff: &Tariff,
+    ) -> Result<Vec<MonthlyInvoice>, Box<dyn std::error::Error>> {
+        monthly_invoices(
+            tariff,
+            &self.hourly_grid_consumption,
+            &self.hourly_overproduction,
+            &self.config.hourly_feed_in_prices(),
+            self.config.monthly_feed_in_credit_cap,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_monthly_invoices() {
+        let tariff = Tariff::from_json(
+            r#"{
+                "name": "Night Saver",
+                "energy": {
+                    "type": "time_of_use",
+                    "tiers": [
+                        {"name": "Peak", "rate": 0.30, "periods": [
+                            {"from": 7, "from_minute": 30, "till": 22}
+                        ]},
+                        {"name": "Off-Peak", "rate": 0.20, "periods": [
+                            {"from": 22, "till": 7, "till_minute": 30}
+                        ]}
+                    ]
+                },
+                "fees": [
+                    {"name": "Network fee", "amount": 0.10, "unit": "per_kwh"},
+                    {"name": "Base fee", "amount": 10.0, "unit": "per_month"},
+                    {"name": "Meter", "amount": 36.5, "unit": "per_year"}
+                ],
+                "taxes": [{"name": "VAT", "percent": 20.0}]
+            }"#,
+        )
+        .unwrap();
+        // 1 kWh import every hour, 2 kWh feed-in at noon
+        let grid_import = vec![1000.0; NUM_HOURS];
+        let feed_in: Vec<f64> = (0..NUM_HOURS)
+            .map(|t| if t % 24 == 12 { 2000.0 } else { 0.0 })
+            .collect();
+        let feed_in_prices = vec![0.08; NUM_HOURS];
+
+        let invoices =
+            monthly_invoices(&tariff, &grid_import, &feed_in, &feed_in_prices, false).unwrap();
+        assert_eq!(invoices.len(), 12);
+        let january = &invoices[0];
+        assert_eq!(january.month, 1);
+        // 14.5 peak and 9.5 off-peak hours a day
+        assert!((january.line("Energy Peak").unwrap().quantity - 14.5 * 31.0).abs() < 1e-9);
+        assert!((january.line("Energy Off-Peak").unwrap().quantity - 9.5 * 31.0).abs() < 1e-9);
+        assert!((january.line("Network fee").unwrap().amount - 0.1 * 24.0 * 31.0).abs() < 1e-9);
+        assert!((january.line("Meter").unwrap().amount - 3.1).abs() < 1e-9);
+
+        let net = (14.5 * 0.3 + 9.5 * 0.2 + 2.4) * 31.0 + 10.0 + 3.1;
+        assert!((january.line("VAT").unwrap().amount - net * 0.2).abs() < 1e-9);
+        let credit = 2.0 * 0.08 * 31.0;
+        assert!((january.line("Feed-in credit").unwrap().amount + credit).abs() < 1e-9);
+        assert!((january.total() - (net * 1.2 - credit)).abs() < 1e-9);
+
+        // the year matches the hourly rate and the fixed costs of the tariff
+        let annual: f64 = invoices.iter().map(MonthlyInvoice::total).sum();
+        let energy_cost: f64 = tariff
+            .to_electricity_rate()
+            .to_yearly_hourly_rates()
+            .iter()
+            .sum();
+        let expected = energy_cost + tariff.annual_fixed_cost() - 2.0 * 0.08 * 365.0;
+        assert!((annual - expected).abs() < 1e-6);
+        assert!(january.render().contains("Feed-in credit"));
+
+        // the simplified compensation credits at most the energy charges
+        let feed_in = vec![5000.0; NUM_HOURS];
+        let capped =
+            monthly_invoices(&tariff, &grid_import, &feed_in, &feed_in_prices, true).unwrap();
+        let energy_charges = (14.5 * 0.3 + 9.5 * 0.2) * 31.0;
+        assert!((capped[0].line("Feed-in credit").unwrap().amount + energy_charges).abs() < 1e-9);
+        assert!((capped[0].line("VAT").unwrap().amount - net * 0.2).abs() < 1e-9);
+
+        assert!(
+            monthly_invoices(&tariff, &grid_import[1..], &feed_in, &feed_in_prices, false).is_err()
+        );
+    }
+
+    #[test]
+    fn test_monthly_invoices_rejects_invalid_input() {
+        let tariff =
+            Tariff::from_json(r#"{"name": "Basic", "energy": {"type": "fixed", "rate": 0.25}}"#)
+                .unwrap();
+        let hourly = vec![1000.0; NUM_HOURS];
+        let prices = vec![0.08; NUM_HOURS];
+        assert!(monthly_invoices(&tariff, &hourly, &hourly, &prices[..24], false).is_err());
+        assert!(monthly_invoices(&tariff, &hourly, &[], &prices, false).is_err());
+
+        let invalid = Tariff {
+            valid_from: Some("2025-13-01".to_string()),
+            ..tariff.clone()
+        };
+        assert!(monthly_invoices(&invalid, &hourly, &hourly, &prices, false).is_err());
+
+        // without any feed-in the credit line is zero instead of dividing by zero
+        let invoices =
+            monthly_invoices(&tariff, &hourly, &vec![0.0; NUM_HOURS], &prices, true).unwrap();
+        let credit = invoices[1].line("Feed-in credit").unwrap();
+        assert_eq!(credit.unit_price, 0.0);
+        assert_eq!(credit.amount, 0.0);
+        assert!((invoices[1].total() - 0.25 * 24.0 * 28.0).abs() < 1e-9);
+    }
+}
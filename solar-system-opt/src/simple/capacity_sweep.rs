@@ -0,0 +1,793 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use ems_model::building::electricity::ElectricityRate;
+use good_lp::Solver;
+
+use crate::general::units::{Energy, Ratio};
+use crate::simple::comparison::ComparisonReport;
+use crate::simple::simple_opt_re::run_simple_opt;
+use crate::simple::solar_system_utils::{OptimizationConfig, SimpleOptimizationResults};
+
+/// PV and battery capacities the sweep builds and evaluates, every combination is one run
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapacityGrid {
+    pub pv_capacities_kw: Vec<f64>,
+    pub battery_capacities_kwh: Vec<f64>,
+}
+
+impl CapacityGrid {
+    /// Sweeps the PV range (`pv_capacity_min`, `pv_capacity_max`, `pv_capacity_step`) and the
+    /// battery range (`bat_capacity_min`, `bat_capacity_max`, `bat_capacity_step`) of the config
+    pub fn from_config(config: &OptimizationConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self {
+            pv_capacities_kw: capacity_steps(
+                config.pv_capacity_min,
+                config.pv_capacity_max,
+                config.pv_capacity_step,
+            )?,
+            battery_capacities_kwh: battery_capacity_steps(config)?,
+        })
+    }
+}
+
+/// Battery capacities of the config range (`bat_capacity_min`, `bat_capacity_max`,
+/// `bat_capacity_step`) in kWh
+pub fn battery_capacity_steps(
+    config: &OptimizationConfig,
+) -> Result<Vec<f64>, Box<dyn std::error::Error>> {
+    capacity_steps(
+        config.bat_capacity_min,
+        config.bat_capacity_max,
+        config.bat_capacity_step,
+    )
+}
+
+/// Capacities from `min` to `max` (inclusive) in steps of `step`
+pub fn capacity_steps(
+    min: f64,
+    max: f64,
+    step: f64,
+) -> Result<Vec<f64>, Box<dyn std::error::Error>> {
+    let valid = step > 0.0 && min >= 0.0 && max >= min;
+    if !valid {
+        return Err(format!(
+            "Invalid capacity range: min {}, max {}, step {} (need 0 <= min <= max and step > 0)",
+            min, max, step
+        )
+        .into());
+    }
+    // Tolerance keeps `max` in the range despite rounding of the step
+    let num_steps = ((max - min) / step + 1e-9).floor() as usize;
+    Ok((0..=num_steps).map(|i| min + i as f64 * step).collect())
+}
+
+/// KPIs of every PV and battery combination, indexed `[battery][pv]` like
+/// `CapacityGrid::battery_capacities_kwh` and `CapacityGrid::pv_capacities_kw`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapacityMap {
+    pub pv_capacities_kw: Vec<f64>,
+    pub battery_capacities_kwh: Vec<f64>,
+    pub autarky: Vec<Vec<f64>>,          // in %
+    pub self_consumption: Vec<Vec<f64>>, // Share of own production used on site in %
+    pub annual_cost: Vec<Vec<f64>>, // Objective of the run: annuity of the investment plus grid cost minus feed-in revenue
+}
+
+/// Share of the own production (PV and wind) that is used on site instead of being exported or
+/// curtailed
+pub fn self_consumption(results: &SimpleOptimizationResults) -> Ratio {
+    let production = results.annual_pv_production_kwh + results.annual_wind_production_kwh;
+    if production <= 0.0 {
+        return Ratio::ZERO;
+    }
+    let surplus = results.annual_overproduction_kwh + results.annual_curtailed_kwh;
+    Ratio::from_fraction((1.0 - surplus / production).clamp(0.0, 1.0))
+}
+
+/// Builds the "autarky map" of a household: runs the optimization with every PV and battery
+/// capacity of `grid` fixed, so only the operation is optimized, and collects autarky,
+/// self-consumption and annual cost per combination. The runs are solved in parallel.
+///
+/// Use `plot::plot_capacity_map` to draw the result as a contour plot.
+pub fn run_capacity_map<S: Solver + Clone + Send>(
+    config: OptimizationConfig,
+    grid: &CapacityGrid,
+    solar_irradiance: &[f64],
+    electricity_demand: &[f64],
+    electricity_rate: &ElectricityRate,
+    solver: S,
+) -> Result<CapacityMap, Box<dyn std::error::Error>> {
+    if grid.pv_capacities_kw.is_empty() || grid.battery_capacities_kwh.is_empty() {
+        return Err("The capacity grid needs at least one PV and one battery capacity".into());
+    }
+
+    let runs: Vec<(OptimizationConfig, f64)> = grid
+        .battery_capacities_kwh
+        .iter()
+        .flat_map(|&battery_capacity_kwh| {
+            let run_config = OptimizationConfig {
+                pv_fixed: true,
+                bat_fixed: true,
+                bat_value: Energy::from_kwh(battery_capacity_kwh),
+                ..config.clone()
+            };
+            grid.pv_capacities_kw
+                .iter()
+                .map(move |&pv_capacity_kw| (run_config.clone(), pv_capacity_kw * 1000.0))
+        })
+        .collect();
+    let results = solve_runs(
+        runs,
+        solar_irradiance,
+        electricity_demand,
+        electricity_rate,
+        solver,
+    )?;
+
+    let matrix = |value: fn(&SimpleOptimizationResults) -> f64| -> Vec<Vec<f64>> {
+        results
+            .chunks(grid.pv_capacities_kw.len())
+            .map(|row| row.iter().map(value).collect())
+            .collect()
+    };
+    Ok(CapacityMap {
+        pv_capacities_kw: grid.pv_capacities_kw.clone(),
+        battery_capacities_kwh: grid.battery_capacities_kwh.clone(),
+        autarky: matrix(|results| results.autarky.percent()),
+        self_consumption: matrix(|results| self_consumption(results).percent()),
+        annual_cost: matrix(|results| results.objective_value),
+    })
+}
+
+/// One battery capacity of a battery sweep
+#[derive(Debug, Clone)]
+pub struct BatterySweepPoint {
+    pub battery_capacity_kwh: f64,
+    pub pv_capacity_kw: f64, // Optimal PV capacity for this battery, unless `pv_fixed` is set
+    pub autarky: Ratio,
+    pub self_consumption: Ratio,
+    pub annual_cost: f64, // Objective of the run
+    pub results: SimpleOptimizationResults,
+}
+
+/// Sweeps the battery capacity like `CapacityGrid` sweeps the PV capacity: every capacity of
+/// `battery_capacities_kwh` is built exactly, while the PV capacity (up to `pv_cap_w_max`) is
+/// still optimized unless `pv_fixed` is set. The runs are solved in parallel.
+///
+/// `battery_capacity_steps` builds the capacities from the config range and `knee_point` finds
+/// the capacity after which more battery adds little autarky.
+pub fn run_battery_sweep<S: Solver + Clone + Send>(
+    config: OptimizationConfig,
+    pv_cap_w_max: f64,
+    battery_capacities_kwh: &[f64],
+    solar_irradiance: &[f64],
+    electricity_demand: &[f64],
+    electricity_rate: &ElectricityRate,
+    solver: S,
+) -> Result<Vec<BatterySweepPoint>, Box<dyn std::error::Error>> {
+    let runs = battery_capacities_kwh
+        .iter()
+        .map(|&battery_capacity_kwh| {
+            let run_config = OptimizationConfig {
+                bat_fixed: true,
+                bat_value: Energy::from_kwh(battery_capacity_kwh),
+                ..config.clone()
+            };
+            (run_config, pv_cap_w_max)
+        })
+        .collect();
+    let results = solve_runs(
+        runs,
+        solar_irradiance,
+        electricity_demand,
+        electricity_rate,
+        solver,
+    )?;
+
+    Ok(battery_capacities_kwh
+        .iter()
+        .zip(results)
+        .map(|(&battery_capacity_kwh, results)| BatterySweepPoint {
+            battery_capacity_kwh,
+            pv_capacity_kw: results.pv_capacity_kw,
+            autarky: results.autarky,
+            self_consumption: self_consumption(&results),
+            annual_cost: results.objective_value,
+            results,
+        })
+        .collect())
+}
+
+/// Index of the knee of the autarky curve of a battery sweep: the point farthest above the
+/// straight line from the first to the last point, with both axes scaled to their range.
+/// None if the sweep has fewer than three points or the curve bends the other way.
+pub fn knee_point(points: &[BatterySweepPoint]) -> Option<usize> {
+    let (first, last) = (points.first()?, points.last()?);
+    let capacity_range = last.battery_capacity_kwh - first.battery_capacity_kwh;
+    let autarky_range = (last.autarky - first.autarky).fraction();
+    if points.len() < 3 || capacity_range <= 0.0 || autarky_range <= 0.0 {
+        return None;
+    }
+    points
+        .iter()
+        .enumerate()
+        .map(|(index, point)| {
+            let capacity =
+                (point.battery_capacity_kwh - first.battery_capacity_kwh) / capacity_range;
+            let autarky = (point.autarky - first.autarky).fraction() / autarky_range;
+            (index, autarky - capacity)
+        })
+        .filter(|&(_, distance)| distance > 1e-9)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(index, _)| index)
+}
+
+/// Capacity increments of the incremental value report
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IncrementalValueConfig {
+    pub pv_step_kw: f64,
+    pub battery_step_kwh: f64,
+}
+
+impl Default for IncrementalValueConfig {
+    fn default() -> Self {
+        Self {
+            pv_step_kw: 1.0,
+            battery_step_kwh: 1.0,
+        }
+    }
+}
+
+/// Effect of building more or less than the optimal system
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarginalValue {
+    pub label: String, // e.g. "+1 kWh battery"
+    pub pv_capacity_kw: f64,
+    pub battery_capacity_kwh: f64,
+    pub annual_cost_change: f64, // Change of the objective, negative if the change pays off
+    pub autarky_change: Ratio,
+}
+
+/// Marginal value of the next (and the last) kWp of PV and kWh of battery around an optimal
+/// system
+#[derive(Debug, Clone, PartialEq)]
+pub struct IncrementalValueReport {
+    pub pv_capacity_kw: f64,
+    pub battery_capacity_kwh: f64,
+    pub annual_cost: f64,
+    pub autarky: Ratio,
+    pub changes: Vec<MarginalValue>,
+}
+
+impl IncrementalValueReport {
+    /// Renders the report as a plain text table
+    pub fn render(&self) -> String {
+        let mut lines = vec![
+            format!(
+                "Optimal system: {:.2} kWp PV, {:.2} kWh battery, annual cost {:.2}, autarky {:.1} %",
+                self.pv_capacity_kw,
+                self.battery_capacity_kwh,
+                self.annual_cost,
+                self.autarky.percent()
+            ),
+            format!(
+                "{:<20} {:>18} {:>18}",
+                "Change", "Annual cost delta", "Autarky delta (pp)"
+            ),
+        ];
+        for change in &self.changes {
+            lines.push(format!(
+                "{:<20} {:>+18.2} {:>+18.2}",
+                change.label,
+                change.annual_cost_change,
+                change.autarky_change.percent()
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Re-runs the optimization around `optimum` (the results of `run_simple_opt`) with the PV and
+/// the battery capacity changed by one increment up and down, and reports how annual cost and
+/// autarky change. Decreases below zero are skipped. The runs fix both capacities and are
+/// solved in parallel.
+pub fn run_incremental_value<S: Solver + Clone + Send>(
+    optimum: &SimpleOptimizationResults,
+    increments: &IncrementalValueConfig,
+    solar_irradiance: &[f64],
+    electricity_demand: &[f64],
+    electricity_rate: &ElectricityRate,
+    solver: S,
+) -> Result<IncrementalValueReport, Box<dyn std::error::Error>> {
+    if !(increments.pv_step_kw > 0.0 && increments.battery_step_kwh > 0.0) {
+        return Err("The capacity increments must be positive".into());
+    }
+    let pv = optimum.pv_capacity_kw;
+    let battery = optimum.battery_capacity_kwh;
+    let (pv_step, battery_step) = (increments.pv_step_kw, increments.battery_step_kwh);
+    let candidates = [
+        (format!("+{} kWp PV", pv_step), pv + pv_step, battery),
+        (format!("-{} kWp PV", pv_step), pv - pv_step, battery),
+        (
+            format!("+{} kWh battery", battery_step),
+            pv,
+            battery + battery_step,
+        ),
+        (
+            format!("-{} kWh battery", battery_step),
+            pv,
+            battery - battery_step,
+        ),
+    ];
+    // Small negative values are solver noise around a capacity of zero
+    let candidates: Vec<(String, f64, f64)> = candidates
+        .into_iter()
+        .filter(|&(_, pv, battery)| pv >= -1e-6 && battery >= -1e-6)
+        .map(|(label, pv, battery)| (label, pv.max(0.0), battery.max(0.0)))
+        .collect();
+
+    let runs = candidates
+        .iter()
+        .map(|&(_, pv_capacity_kw, battery_capacity_kwh)| {
+            let run_config = OptimizationConfig {
+                pv_fixed: true,
+                bat_fixed: true,
+                bat_value: Energy::from_kwh(battery_capacity_kwh),
+                ..optimum.config.clone()
+            };
+            (run_config, pv_capacity_kw * 1000.0)
+        })
+        .collect();
+    let results = solve_runs(
+        runs,
+        solar_irradiance,
+        electricity_demand,
+        electricity_rate,
+        solver,
+    )?;
+
+    Ok(IncrementalValueReport {
+        pv_capacity_kw: pv,
+        battery_capacity_kwh: battery,
+        annual_cost: optimum.objective_value,
+        autarky: optimum.autarky,
+        changes: candidates
+            .into_iter()
+            .zip(results)
+            .map(
+                |((label, pv_capacity_kw, battery_capacity_kwh), results)| MarginalValue {
+                    label,
+                    pv_capacity_kw,
+                    battery_capacity_kwh,
+                    annual_cost_change: results.objective_value - optimum.objective_value,
+                    autarky_change: results.autarky - optimum.autarky,
+                },
+            )
+            .collect(),
+    })
+}
+
+/// Discrete sizes the PV array and the battery are bought in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProductSizes {
+    pub module_power_w: f64,        // Peak power of one PV module
+    pub battery_module_kwh: f64,    // Capacity of one battery module
+    pub min_battery_modules: usize, // Smallest stack the battery is sold as
+}
+
+impl Default for ProductSizes {
+    fn default() -> Self {
+        Self {
+            module_power_w: 430.0,
+            battery_module_kwh: 2.5,
+            min_battery_modules: 2,
+        }
+    }
+}
+
+impl ProductSizes {
+    pub fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if !(self.module_power_w > 0.0 && self.battery_module_kwh > 0.0) {
+            return Err(format!(
+                "Module power and battery module capacity must be positive, got {} W and {} kWh",
+                self.module_power_w, self.battery_module_kwh
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Number of PV modules closest to a capacity in kW
+    pub fn pv_modules(&self, pv_capacity_kw: f64) -> usize {
+        (pv_capacity_kw * 1000.0 / self.module_power_w)
+            .round()
+            .max(0.0) as usize
+    }
+
+    /// Number of battery modules closest to a capacity in kWh, either none or at least the
+    /// smallest stack
+    pub fn battery_modules(&self, battery_capacity_kwh: f64) -> usize {
+        let modules = (battery_capacity_kwh / self.battery_module_kwh)
+            .round()
+            .max(0.0) as usize;
+        if modules >= self.min_battery_modules {
+            modules
+        } else if battery_capacity_kwh
+            >= self.min_battery_modules as f64 * self.battery_module_kwh / 2.0
+        {
+            self.min_battery_modules
+        } else {
+            0
+        }
+    }
+}
+
+/// Continuous optimum snapped to purchasable sizes and re-simulated
+#[derive(Debug, Clone)]
+pub struct SnappedSizing {
+    pub pv_modules: usize,
+    pub battery_modules: usize,
+    pub pv_capacity_kw: f64,
+    pub battery_capacity_kwh: f64,
+    pub annual_cost_change: f64, // Objective of the snapped system minus the continuous optimum
+    pub comparison: ComparisonReport, // Continuous optimum (A) against the snapped system (B)
+    pub results: SimpleOptimizationResults,
+}
+
+impl SnappedSizing {
+    /// Renders the snapped sizes and the KPI deltas as a plain text table
+    pub fn render(&self) -> String {
+        format!(
+            "Snapped system: {} modules ({:.2} kWp PV), {} battery modules ({:.2} kWh), annual cost {:+.2}\n{}",
+            self.pv_modules,
+            self.pv_capacity_kw,
+            self.battery_modules,
+            self.battery_capacity_kwh,
+            self.annual_cost_change,
+            self.comparison.render()
+        )
+    }
+}
+
+/// Snaps the new PV and battery capacity of `optimum` (the results of `run_simple_opt`) to the
+/// closest number of modules, re-runs the optimization with both capacities fixed and compares
+/// the KPIs with the continuous optimum. The PV is rounded down instead if the closest module
+/// count doesn't fit on the roof of the config.
+pub fn run_snapped_sizes<S: Solver>(
+    optimum: &SimpleOptimizationResults,
+    sizes: &ProductSizes,
+    solar_irradiance: &[f64],
+    electricity_demand: &[f64],
+    electricity_rate: &ElectricityRate,
+    solver: S,
+) -> Result<SnappedSizing, Box<dyn std::error::Error>> {
+    sizes.validate()?;
+    let mut pv_modules = sizes.pv_modules(optimum.pv_capacity_kw);
+    let roof_limit_w = optimum.config.pv_limit_w(f64::INFINITY);
+    if pv_modules as f64 * sizes.module_power_w > roof_limit_w + 1e-6 {
+        pv_modules = (roof_limit_w / sizes.module_power_w).floor() as usize;
+    }
+    let battery_modules = sizes.battery_modules(optimum.battery_capacity_kwh);
+    let pv_capacity_kw = pv_modules as f64 * sizes.module_power_w / 1000.0;
+    let battery_capacity_kwh = battery_modules as f64 * sizes.battery_module_kwh;
+
+    let config = OptimizationConfig {
+        pv_fixed: true,
+        bat_fixed: true,
+        bat_value: Energy::from_kwh(battery_capacity_kwh),
+        ..optimum.config.clone()
+    };
+    let snapped = run_simple_opt(
+        config,
+        pv_capacity_kw * 1000.0,
+        solar_irradiance.to_vec(),
+        electricity_demand.to_vec(),
+        electricity_rate.clone(),
+        solver,
+    )?;
+
+    Ok(SnappedSizing {
+        pv_modules,
+        battery_modules,
+        pv_capacity_kw,
+        battery_capacity_kwh,
+        annual_cost_change: snapped.objective_value - optimum.objective_value,
+        comparison: optimum
+            .compare(&snapped)
+            .with_labels("Continuous", "Snapped"),
+        results: snapped,
+    })
+}
+
+/// Solves the runs (config and `pv_cap_w_max`) on all available cores and returns the results
+/// in the order of the runs
+fn solve_runs<S: Solver + Clone + Send>(
+    runs: Vec<(OptimizationConfig, f64)>,
+    solar_irradiance: &[f64],
+    electricity_demand: &[f64],
+    electricity_rate: &ElectricityRate,
+    solver: S,
+) -> Result<Vec<SimpleOptimizationResults>, Box<dyn std::error::Error>> {
+    let num_threads = std::thread::available_parallelism()
+        .map(|threads| threads.get())
+        .unwrap_or(1)
+        .min(runs.len())
+        .max(1);
+    let next_run = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<Result<SimpleOptimizationResults, String>>>> =
+        Mutex::new(vec![None; runs.len()]);
+
+    std::thread::scope(|scope| {
+        let (runs, next_run, results) = (&runs, &next_run, &results);
+        for _ in 0..num_threads {
+            let solver = solver.clone();
+            scope.spawn(move || {
+                loop {
+                    let index = next_run.fetch_add(1, Ordering::Relaxed);
+                    let Some((config, pv_cap_w_max)) = runs.get(index) else {
+                        break;
+                    };
+                    let result = run_simple_opt(
+                        config.clone(),
+                        *pv_cap_w_max,
+                        solar_irradiance.to_vec(),
+                        electricity_demand.to_vec(),
+                        electricity_rate.clone(),
+                        solver.clone(),
+                    )
+                    .map_err(|e| {
+                        format!(
+                            "Optimization with {} kWp PV and {} kWh battery failed: {}",
+                            pv_cap_w_max / 1000.0,
+                            config.bat_value.kwh(),
+                            e
+                        )
+                    });
+                    results.lock().unwrap()[index] = Some(result);
+                }
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|result| result.expect("every run is solved").map_err(Into::into))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simple::solar_system_utils::{load_demand_from_csv, load_solar_radiance_from_csv};
+
+    #[test]
+    fn test_capacity_steps() {
+        assert_eq!(
+            capacity_steps(0.0, 2.0, 0.5).unwrap(),
+            vec![0.0, 0.5, 1.0, 1.5, 2.0]
+        );
+        assert_eq!(capacity_steps(3.0, 3.0, 1.0).unwrap(), vec![3.0]);
+        // 0.1 steps don't add up exactly to 0.3
+        assert_eq!(capacity_steps(0.0, 0.3, 0.1).unwrap().len(), 4);
+        assert!(capacity_steps(0.0, 1.0, 0.0).is_err());
+        assert!(capacity_steps(2.0, 1.0, 0.5).is_err());
+        assert!(capacity_steps(0.0, f64::NAN, 0.5).is_err());
+    }
+
+    #[test]
+    fn test_capacity_grid_from_config() {
+        let config = OptimizationConfig {
+            pv_capacity_min: 4.0,
+            pv_capacity_max: 8.0,
+            pv_capacity_step: 2.0,
+            bat_capacity_min: 0.0,
+            bat_capacity_max: 10.0,
+            bat_capacity_step: 5.0,
+            ..Default::default()
+        };
+        let grid = CapacityGrid::from_config(&config).unwrap();
+        assert_eq!(grid.pv_capacities_kw, vec![4.0, 6.0, 8.0]);
+        assert_eq!(grid.battery_capacities_kwh, vec![0.0, 5.0, 10.0]);
+    }
+
+    #[test]
+    fn test_knee_point() {
+        let point = |battery_capacity_kwh, autarky| BatterySweepPoint {
+            battery_capacity_kwh,
+            pv_capacity_kw: 5.0,
+            autarky: Ratio::from_percent(autarky),
+            self_consumption: Ratio::ZERO,
+            annual_cost: 0.0,
+            results: SimpleOptimizationResults::default(),
+        };
+        let points = vec![
+            point(0.0, 30.0),
+            point(5.0, 55.0),
+            point(10.0, 62.0),
+            point(15.0, 65.0),
+        ];
+        assert_eq!(knee_point(&points), Some(1));
+        // a straight line has no knee
+        let linear = vec![point(0.0, 30.0), point(5.0, 40.0), point(10.0, 50.0)];
+        assert_eq!(knee_point(&linear), None);
+        assert_eq!(knee_point(&points[..2]), None);
+    }
+
+    #[test]
+    fn test_product_sizes() {
+        let sizes = ProductSizes::default();
+        // 7.43 kWp are 17.3 modules of 430 W
+        assert_eq!(sizes.pv_modules(7.43), 17);
+        assert_eq!(sizes.battery_modules(9.2), 4);
+        // below the smallest stack of 5 kWh the battery is dropped or rounded up to the stack
+        assert_eq!(sizes.battery_modules(2.4), 0);
+        assert_eq!(sizes.battery_modules(2.6), 2);
+        assert_eq!(sizes.battery_modules(0.0), 0);
+        assert!(
+            ProductSizes {
+                module_power_w: 0.0,
+                ..sizes
+            }
+            .validate()
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_run_capacity_map() {
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+        let grid = CapacityGrid {
+            pv_capacities_kw: vec![2.0, 6.0],
+            battery_capacities_kwh: vec![0.0],
+        };
+
+        let map = run_capacity_map(
+            OptimizationConfig::default(),
+            &grid,
+            &solar_irradiance,
+            &electricity_demand,
+            &ElectricityRate::fixed(0.3),
+            good_lp::clarabel,
+        )
+        .unwrap();
+
+        assert_eq!(map.autarky.len(), 1);
+        assert_eq!(map.autarky[0].len(), 2);
+        // more PV covers more demand, but less of the production is used on site
+        assert!(map.autarky[0][1] > map.autarky[0][0]);
+        assert!(map.self_consumption[0][1] < map.self_consumption[0][0]);
+        assert!(map.annual_cost[0].iter().all(|cost| cost.is_finite()));
+
+        let empty_grid = CapacityGrid {
+            pv_capacities_kw: vec![],
+            battery_capacities_kwh: vec![0.0],
+        };
+        assert!(
+            run_capacity_map(
+                OptimizationConfig::default(),
+                &empty_grid,
+                &solar_irradiance,
+                &electricity_demand,
+                &ElectricityRate::fixed(0.3),
+                good_lp::clarabel,
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_run_battery_sweep() {
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+
+        let points = run_battery_sweep(
+            OptimizationConfig::default(),
+            10000.0,
+            &[0.0, 5.0],
+            &solar_irradiance,
+            &electricity_demand,
+            &ElectricityRate::fixed(0.3),
+            good_lp::clarabel,
+        )
+        .unwrap();
+
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[0].battery_capacity_kwh, 0.0);
+        assert!(points[0].results.battery_capacity_kwh.abs() < 1e-3);
+        assert!((points[1].results.battery_capacity_kwh - 5.0).abs() < 1e-3);
+        assert!(points[1].autarky > points[0].autarky);
+        assert!(
+            points
+                .iter()
+                .all(|point| point.pv_capacity_kw <= 10.0 + 1e-3)
+        );
+    }
+
+    #[test]
+    fn test_run_incremental_value() {
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+        let config = OptimizationConfig {
+            bat_value: Energy::ZERO,
+            ..Default::default()
+        };
+        let rate = ElectricityRate::fixed(0.3);
+        let optimum = run_simple_opt(
+            config,
+            20000.0,
+            solar_irradiance.clone(),
+            electricity_demand.clone(),
+            rate.clone(),
+            good_lp::clarabel,
+        )
+        .unwrap();
+
+        let report = run_incremental_value(
+            &optimum,
+            &IncrementalValueConfig::default(),
+            &solar_irradiance,
+            &electricity_demand,
+            &rate,
+            good_lp::clarabel,
+        )
+        .unwrap();
+
+        // without a battery there is no kWh to remove
+        let labels: Vec<&str> = report.changes.iter().map(|c| c.label.as_str()).collect();
+        assert_eq!(labels, vec!["+1 kWp PV", "-1 kWp PV", "+1 kWh battery"]);
+        // the optimum sits at the PV limit and has no battery because `bat_value` caps it at
+        // zero, so more of both still pays off
+        assert!(report.changes[0].annual_cost_change < 0.0);
+        assert!(report.changes[1].annual_cost_change > 0.0);
+        assert!(report.changes[2].annual_cost_change < 0.0);
+        assert!(report.changes[0].autarky_change > Ratio::ZERO);
+        assert!(report.changes[1].autarky_change < Ratio::ZERO);
+        assert!(report.changes[2].autarky_change > Ratio::ZERO);
+        assert!(report.render().contains("+1 kWh battery"));
+    }
+
+    #[test]
+    fn test_run_snapped_sizes() {
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+        let config = OptimizationConfig {
+            bat_value: Energy::from_kwh(9.2),
+            ..Default::default()
+        };
+        let rate = ElectricityRate::fixed(0.3);
+        let optimum = run_simple_opt(
+            config,
+            7430.0,
+            solar_irradiance.clone(),
+            electricity_demand.clone(),
+            rate.clone(),
+            good_lp::clarabel,
+        )
+        .unwrap();
+
+        let sizes = ProductSizes::default();
+        let sizing = run_snapped_sizes(
+            &optimum,
+            &sizes,
+            &solar_irradiance,
+            &electricity_demand,
+            &rate,
+            good_lp::clarabel,
+        )
+        .unwrap();
+
+        // the snapped system is built exactly in whole modules
+        assert_eq!(sizing.pv_modules, sizes.pv_modules(optimum.pv_capacity_kw));
+        assert!((sizing.results.pv_capacity_kw - sizing.pv_capacity_kw).abs() < 1e-3);
+        assert!((sizing.results.battery_capacity_kwh - sizing.battery_capacity_kwh).abs() < 1e-3);
+        assert!((sizing.battery_capacity_kwh / sizes.battery_module_kwh).fract() < 1e-9);
+        // the continuous optimum can't be beaten
+        assert!(sizing.annual_cost_change >= -1e-3);
+        let pv = sizing.comparison.get("PV capacity").unwrap();
+        assert!((pv.b - sizing.pv_capacity_kw).abs() < 1e-3);
+        assert!(sizing.render().contains("Snapped"));
+    }
+}
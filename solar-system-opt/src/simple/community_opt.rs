@@ -0,0 +1,429 @@
+use ems_model::building::electricity::ElectricityRate;
+use good_lp::{Expression, ProblemVariables, Solution, Solver, SolverModel, constraint, variable};
+
+use crate::general::calendar::NUM_HOURS;
+use crate::general::units::{Energy, Ratio};
+use crate::simple::simple_opt_re::get_scaled_electricity_demand;
+use crate::simple::solar_system_utils::OptimizationConfig;
+
+/// A single household (unit) taking part in an energy community
+#[derive(Debug, Clone)]
+pub struct CommunityHousehold {
+    /// Name of the household, used to label the results
+    pub name: String,
+    /// Household configuration; only the demand related fields are used
+    /// (`electricity_usage`, `monthly_demand`)
+    pub config: OptimizationConfig,
+    /// Hourly base electricity demand profile of the household
+    pub electricity_demand: Vec<f64>,
+    /// Grid tariff the household pays for imported electricity
+    pub electricity_rate: ElectricityRate,
+}
+
+/// Configuration of the assets shared by all households of the community
+#[derive(Debug, Clone)]
+pub struct CommunityConfig {
+    /// Parameters of the shared PV plant and battery (investment costs, annuity,
+    /// feed-in tariff, battery efficiencies, `bat_value`, `pv_fixed`, `bat_fixed`)
+    pub shared_assets: OptimizationConfig,
+    /// Maximum capacity of the shared PV plant in W
+    pub pv_cap_w_max: f64,
+    /// Internal price per kWh households pay the community for shared energy
+    pub sharing_price: f64,
+}
+
+impl Default for CommunityConfig {
+    fn default() -> Self {
+        Self {
+            shared_assets: OptimizationConfig::default(),
+            pv_cap_w_max: 10000.0,
+            sharing_price: 0.1,
+        }
+    }
+}
+
+/// Per-household results of a community optimization
+#[derive(Debug, Clone, Default)]
+pub struct HouseholdResults {
+    pub name: String,
+    pub annual_electricity_demand_kwh: f64,
+    pub annual_grid_energy_kwh: f64,
+    pub annual_shared_energy_kwh: f64,
+    pub autarky: Ratio, // share of demand covered by the community assets
+
+    // Annual costs
+    pub annual_grid_cost: f64,
+    pub annual_sharing_cost: f64,
+    pub annual_total_cost: f64,
+
+    // Hourly data
+    pub hourly_electricity_demand: Vec<f64>,
+    pub hourly_grid_consumption: Vec<f64>,
+    pub hourly_shared_energy: Vec<f64>,
+}
+
+/// Results of a community (multi-household) optimization
+#[derive(Debug, Clone, Default)]
+pub struct CommunityOptimizationResults {
+    // Shared capacities
+    pub pv_capacity_kw: f64,
+    pub battery_capacity_kwh: f64,
+
+    // Annual totals of the shared assets
+    pub annual_pv_production_kwh: f64,
+    pub annual_overproduction_kwh: f64,
+    pub annual_battery_in_kwh: f64,
+    pub annual_battery_out_kwh: f64,
+    pub annual_electricity_demand_kwh: f64,
+    pub community_autarky: Ratio,
+
+    // Community finances
+    pub annualized_investment: f64,
+    pub annual_sharing_revenue: f64,
+    pub annual_feed_in_revenue: f64,
+    pub objective_value: f64, // Annualized investment plus grid cost minus feed-in revenue of the solved model
+
+    pub households: Vec<HouseholdResults>,
+
+    // Hourly data of the shared assets
+    pub hourly_total_pv_production: Vec<f64>,
+    pub hourly_overproduction: Vec<f64>,
+    pub hourly_battery_storage: Vec<f64>,
+
+    pub optimization_duration_ms: u128,
+}
+
+/// Optimizes a PV plant and/or battery shared by several households (e.g. an apartment
+/// building or an energy community under Spanish "autoconsumo colectivo").
+///
+/// The LP sizes the shared assets and allocates the shared production hour by hour across
+/// the households so that the total cost of the community is minimal. The internal
+/// `sharing_price` is a transfer between households and the community: it doesn't change the
+/// optimal dispatch but determines how costs are split in the per-household results.
+///
+/// # Arguments
+///
+/// * `community` - Shared asset parameters and sharing price
+/// * `households` - Households with their demand profiles and grid tariffs
+/// * `solar_irradiance` - Hourly normalized solar irradiance for 8760 hours
+/// * `solver` - LP solver to use
+pub fn run_community_opt<S: Solver>(
+    community: CommunityConfig,
+    households: Vec<CommunityHousehold>,
+    solar_irradiance: Vec<f64>,
+    solver: S,
+) -> Result<CommunityOptimizationResults, Box<dyn std::error::Error>> {
+    if households.is_empty() {
+        return Err("Community optimization requires at least one household".into());
+    }
+    if solar_irradiance.len() != NUM_HOURS {
+        return Err(format!(
+            "Expected {} hours of solar irradiance, got {}",
+            NUM_HOURS,
+            solar_irradiance.len()
+        )
+        .into());
+    }
+
+    let shared = &community.shared_assets;
+    let battery_enabled = shared.bat_value > Energy::ZERO;
+    let storage_retention_bat = 1.0 - shared.storage_loss_bat;
+    let eta_out_bat_inv = 1.0 / shared.eta_out_bat;
+
+    let mut demands = Vec::with_capacity(households.len());
+    let mut rates = Vec::with_capacity(households.len());
+    for household in &households {
+        demands.push(get_scaled_electricity_demand(
+            household.config.monthly_demand.clone(),
+            household.config.electricity_usage,
+            household.electricity_demand.clone(),
+        )?);
+        rates.push(household.electricity_rate.to_yearly_hourly_rates());
+    }
+
+    let mut vars = ProblemVariables::new();
+    let cap_pv = vars.add(variable().min(0.0));
+    let cst_battery = battery_enabled.then(|| vars.add(variable().min(0.0)));
+
+    let mut e_o = Vec::with_capacity(NUM_HOURS);
+    let mut est_battery = Vec::new();
+    let mut est_in_battery = Vec::new();
+    let mut est_out_battery = Vec::new();
+    for _t in 0..NUM_HOURS {
+        e_o.push(vars.add(variable().min(0.0)));
+        if battery_enabled {
+            est_battery.push(vars.add(variable().min(0.0)));
+            est_in_battery.push(vars.add(variable().min(0.0)));
+            est_out_battery.push(vars.add(variable().min(0.0)));
+        }
+    }
+
+    // shared energy and grid import per household
+    let mut e_shared: Vec<Vec<good_lp::Variable>> = Vec::with_capacity(households.len());
+    let mut e_grid: Vec<Vec<good_lp::Variable>> = Vec::with_capacity(households.len());
+    for _ in &households {
+        e_shared.push(
+            (0..NUM_HOURS)
+                .map(|_| vars.add(variable().min(0.0)))
+                .collect(),
+        );
+        e_grid.push(
+            (0..NUM_HOURS)
+                .map(|_| vars.add(variable().min(0.0)))
+                .collect(),
+        );
+    }
+
+    // Objective: investment of the shared assets + grid costs of all households - feed-in
+    let mut objective = Expression::default();
+    objective += cap_pv / 1000.0 * shared.inv_pv * shared.annuity;
+    if let Some(cst_battery) = cst_battery {
+        objective += cst_battery / 1000.0 * shared.inv_bat * shared.annuity;
+    }
+    for (household_grid, household_rates) in e_grid.iter().zip(rates.iter()) {
+        for t in 0..NUM_HOURS {
+            objective += household_grid[t] / 1000.0 * household_rates[t];
+        }
+    }
+    for e_o_t in &e_o {
+        objective -= *e_o_t / 1000.0 * shared.feed_in_tariff;
+    }
+
+    let mut model = vars.minimise(objective.clone()).using(solver);
+
+    // Fixed capacity constraints
+    if shared.pv_fixed {
+        model = model.with(constraint!(cap_pv == community.pv_cap_w_max));
+    } else {
+        model = model.with(constraint!(cap_pv <= community.pv_cap_w_max));
+    }
+    if let Some(cst_battery) = cst_battery {
+        if shared.bat_fixed {
+            model = model.with(constraint!(cst_battery == shared.bat_value.wh()));
+        } else {
+            model = model.with(constraint!(cst_battery <= shared.bat_value.wh()));
+        }
+        model = model.with(constraint!(est_battery[0] == 0.0));
+    }
+
+    for t in 0..NUM_HOURS {
+        let solar_t = solar_irradiance[t];
+
+        // Community balance: PV + Battery Out = Shared + Battery In + Overproduction
+        let shared_sum: Expression = e_shared
+            .iter()
+            .map(|shares| Expression::from(shares[t]))
+            .sum();
+        if battery_enabled {
+            model = model.with(constraint!(
+                cap_pv * solar_t + est_out_battery[t] - est_in_battery[t] - e_o[t] - shared_sum
+                    == 0.0
+            ));
+        } else {
+            model = model.with(constraint!(cap_pv * solar_t - e_o[t] - shared_sum == 0.0));
+        }
+
+        // Household balance: Shared + Grid = Demand
+        for (h, demand) in demands.iter().enumerate() {
+            model = model.with(constraint!(e_shared[h][t] + e_grid[h][t] == demand[t]));
+        }
+
+        if let Some(cst_battery) = cst_battery {
+            model = model.with(constraint!(cst_battery - est_battery[t] >= 0.0));
+            model = model.with(constraint!(
+                shared.c_rate_limit * cst_battery - est_in_battery[t] >= 0.0
+            ));
+            model = model.with(constraint!(
+                shared.c_rate_limit * cst_battery - est_out_battery[t] >= 0.0
+            ));
+            if t > 0 {
+                model = model.with(constraint!(
+                    est_battery[t]
+                        - est_battery[t - 1] * storage_retention_bat
+                        - shared.eta_in_bat * est_in_battery[t]
+                        + est_out_battery[t] * eta_out_bat_inv
+                        == 0.0
+                ));
+            }
+        }
+    }
+
+    let start_time = std::time::Instant::now();
+    let solution = model
+        .solve()
+        .map_err(|e| format!("Community optimization failed: {:?}", e))?;
+    let optimization_duration = start_time.elapsed();
+
+    let values = |vars: &[good_lp::Variable]| -> Vec<f64> {
+        vars.iter().map(|&var| solution.value(var)).collect()
+    };
+
+    let pv_capacity = solution.value(cap_pv);
+    let hourly_total_pv_production: Vec<f64> = solar_irradiance
+        .iter()
+        .take(NUM_HOURS)
+        .map(|&s| s * pv_capacity)
+        .collect();
+    let hourly_overproduction = values(&e_o);
+    let hourly_battery_storage = if battery_enabled {
+        values(&est_battery)
+    } else {
+        vec![0.0; NUM_HOURS]
+    };
+    let battery_capacity = cst_battery.map(|var| solution.value(var)).unwrap_or(0.0);
+
+    let mut household_results = Vec::with_capacity(households.len());
+    for (h, household) in households.iter().enumerate() {
+        let hourly_shared_energy = values(&e_shared[h]);
+        let hourly_grid_consumption = values(&e_grid[h]);
+        let annual_demand: f64 = demands[h].iter().sum();
+        let annual_shared: f64 = hourly_shared_energy.iter().sum();
+        let annual_grid_cost: f64 = hourly_grid_consumption
+            .iter()
+            .zip(rates[h].iter())
+            .map(|(&grid, &rate)| grid / 1000.0 * rate)
+            .sum();
+        let annual_sharing_cost = annual_shared / 1000.0 * community.sharing_price;
+
+        household_results.push(HouseholdResults {
+            name: household.name.clone(),
+            annual_electricity_demand_kwh: annual_demand / 1000.0,
+            annual_grid_energy_kwh: hourly_grid_consumption.iter().sum::<f64>() / 1000.0,
+            annual_shared_energy_kwh: annual_shared / 1000.0,
+            autarky: Ratio::of(annual_shared, annual_demand),
+            annual_grid_cost,
+            annual_sharing_cost,
+            annual_total_cost: annual_grid_cost + annual_sharing_cost,
+            hourly_electricity_demand: demands[h].clone(),
+            hourly_grid_consumption,
+            hourly_shared_energy,
+        });
+    }
+
+    let annual_demand_kwh: f64 = household_results
+        .iter()
+        .map(|household| household.annual_electricity_demand_kwh)
+        .sum();
+    let annual_grid_kwh: f64 = household_results
+        .iter()
+        .map(|household| household.annual_grid_energy_kwh)
+        .sum();
+    let annual_overproduction_kwh = hourly_overproduction.iter().sum::<f64>() / 1000.0;
+
+    Ok(CommunityOptimizationResults {
+        pv_capacity_kw: pv_capacity / 1000.0,
+        battery_capacity_kwh: battery_capacity / 1000.0,
+        annual_pv_production_kwh: hourly_total_pv_production.iter().sum::<f64>() / 1000.0,
+        annual_overproduction_kwh,
+        annual_battery_in_kwh: values(&est_in_battery).iter().sum::<f64>() / 1000.0,
+        annual_battery_out_kwh: values(&est_out_battery).iter().sum::<f64>() / 1000.0,
+        annual_electricity_demand_kwh: annual_demand_kwh,
+        community_autarky: if annual_demand_kwh > 0.0 {
+            Ratio::from_fraction(1.0 - annual_grid_kwh / annual_demand_kwh)
+        } else {
+            Ratio::ZERO
+        },
+        annualized_investment: pv_capacity / 1000.0 * shared.inv_pv * shared.annuity
+            + battery_capacity / 1000.0 * shared.inv_bat * shared.annuity,
+        annual_sharing_revenue: household_results
+            .iter()
+            .map(|household| household.annual_sharing_cost)
+            .sum(),
+        annual_feed_in_revenue: annual_overproduction_kwh * shared.feed_in_tariff,
+        objective_value: solution.eval(&objective),
+        households: household_results,
+        hourly_total_pv_production,
+        hourly_overproduction,
+        hourly_battery_storage,
+        optimization_duration_ms: optimization_duration.as_millis(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simple::solar_system_utils::{load_demand_from_csv, load_solar_radiance_from_csv};
+
+    #[test]
+    fn test_run_community_opt() {
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+
+        let household = |name: &str, electricity_usage_kwh: f64| CommunityHousehold {
+            name: name.to_string(),
+            config: OptimizationConfig {
+                electricity_usage: Energy::from_kwh(electricity_usage_kwh),
+                ..Default::default()
+            },
+            electricity_demand: electricity_demand.clone(),
+            electricity_rate: ElectricityRate::fixed(0.3),
+        };
+        let community = CommunityConfig {
+            shared_assets: OptimizationConfig {
+                bat_value: Energy::ZERO,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let results = run_community_opt(
+            community,
+            vec![household("A", 3000.0), household("B", 5000.0)],
+            solar_irradiance,
+            good_lp::clarabel,
+        )
+        .unwrap();
+
+        assert_eq!(results.households.len(), 2);
+        assert!((results.annual_electricity_demand_kwh - 8000.0).abs() < 1.0);
+        assert!(results.pv_capacity_kw > 0.0);
+        for household in &results.households {
+            // shared energy + grid import cover the demand of each household
+            assert!(
+                (household.annual_shared_energy_kwh + household.annual_grid_energy_kwh
+                    - household.annual_electricity_demand_kwh)
+                    .abs()
+                    < 1.0
+            );
+            assert!(household.autarky > Ratio::ZERO && household.autarky <= Ratio::ONE);
+        }
+        // the shared plant can't hand out more energy than it produces
+        let shared_sum: f64 = results
+            .households
+            .iter()
+            .map(|household| household.annual_shared_energy_kwh)
+            .sum();
+        assert!(
+            shared_sum + results.annual_overproduction_kwh
+                <= results.annual_pv_production_kwh + 1.0
+        );
+        // the objective is the community's cost without the internal sharing transfers
+        let grid_cost: f64 = results
+            .households
+            .iter()
+            .map(|household| household.annual_grid_cost)
+            .sum();
+        let expected_objective =
+            results.annualized_investment + grid_cost - results.annual_feed_in_revenue;
+        assert!((results.objective_value - expected_objective).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_run_community_opt_rejects_short_irradiance() {
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+        let household = CommunityHousehold {
+            name: "A".to_string(),
+            config: OptimizationConfig::default(),
+            electricity_demand,
+            electricity_rate: ElectricityRate::fixed(0.3),
+        };
+
+        let results = run_community_opt(
+            CommunityConfig::default(),
+            vec![household],
+            vec![0.5; 24],
+            good_lp::clarabel,
+        );
+        assert!(results.is_err());
+    }
+}
@@ -0,0 +1,225 @@
+use crate::simple::solar_system_utils::SimpleOptimizationResults;
+
+/// Group of a KPI, used to plot comparable values together
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KpiKind {
+    Capacity,
+    AnnualEnergy,
+    Share,
+}
+
+/// Difference of one scalar KPI between two optimization results
+#[derive(Debug, Clone, PartialEq)]
+pub struct KpiDelta {
+    pub name: &'static str,
+    pub unit: &'static str,
+    pub kind: KpiKind,
+    pub a: f64,
+    pub b: f64,
+    pub delta: f64,                   // b - a
+    pub relative_change: Option<f64>, // delta / |a|; None if a is zero
+}
+
+impl KpiDelta {
+    fn new(name: &'static str, unit: &'static str, kind: KpiKind, a: f64, b: f64) -> Self {
+        let delta = b - a;
+        Self {
+            name,
+            unit,
+            kind,
+            a,
+            b,
+            delta,
+            relative_change: (a.abs() > f64::EPSILON).then(|| delta / a.abs()),
+        }
+    }
+}
+
+/// Side-by-side comparison of two optimization results, e.g. a scenario with and without EV
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComparisonReport {
+    pub label_a: String,
+    pub label_b: String,
+    pub kpis: Vec<KpiDelta>,
+}
+
+impl ComparisonReport {
+    /// Sets the scenario names used in the rendered table and the comparison plot
+    pub fn with_labels(mut self, label_a: &str, label_b: &str) -> Self {
+        self.label_a = label_a.to_string();
+        self.label_b = label_b.to_string();
+        self
+    }
+
+    /// Get a KPI by its name, e.g. "Autarky"
+    pub fn get(&self, name: &str) -> Option<&KpiDelta> {
+        self.kpis.iter().find(|kpi| kpi.name == name)
+    }
+
+    /// KPIs of one kind, e.g. all annual energies
+    pub fn kpis_of(&self, kind: KpiKind) -> impl Iterator<Item = &KpiDelta> {
+        self.kpis.iter().filter(move |kpi| kpi.kind == kind)
+    }
+
+    /// Renders the comparison as a plain text table
+    pub fn render(&self) -> String {
+        let mut lines = vec![format!(
+            "{:<28} {:>12} {:>12} {:>12} {:>9}",
+            "KPI", self.label_a, self.label_b, "Delta", "Change"
+        )];
+        for kpi in &self.kpis {
+            let change = match kpi.relative_change {
+                Some(change) => format!("{:+.1} %", change * 100.0),
+                None => "-".to_string(),
+            };
+            lines.push(format!(
+                "{:<28} {:>12.2} {:>12.2} {:>+12.2} {:>9}",
+                format!("{} ({})", kpi.name, kpi.unit),
+                kpi.a,
+                kpi.b,
+                kpi.delta,
+                change
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+impl SimpleOptimizationResults {
+    /// Compares all scalar KPIs of `self` (scenario A) with `other` (scenario B)
+    pub fn compare(&self, other: &SimpleOptimizationResults) -> ComparisonReport {
+        use KpiKind::*;
+        let kpi = |name, unit, kind, value: fn(&SimpleOptimizationResults) -> f64| {
+            KpiDelta::new(name, unit, kind, value(self), value(other))
+        };
+        ComparisonReport {
+            label_a: "A".to_string(),
+            label_b: "B".to_string(),
+            kpis: vec![
+                kpi("PV capacity", "kWp", Capacity, |r| r.pv_capacity_kw),
+                kpi("Battery capacity", "kWh", Capacity, |r| {
+                    r.battery_capacity_kwh
+                }),
+                kpi("Wind capacity", "kW", Capacity, |r| r.wind_capacity_kw),
+                kpi("Grid capacity", "kW", Capacity, |r| r.grid_capacity_kw),
+                kpi("Peak grid import", "kW", Capacity, |r| {
+                    r.peak_grid_import_kw
+                }),
+                kpi("Electricity demand", "kWh", AnnualEnergy, |r| {
+                    r.annual_electricity_demand_kwh
+                }),
+                kpi("PV production", "kWh", AnnualEnergy, |r| {
+                    r.annual_pv_production_kwh
+                }),
+                kpi("Wind production", "kWh", AnnualEnergy, |r| {
+                    r.annual_wind_production_kwh
+                }),
+                kpi("Grid consumption", "kWh", AnnualEnergy, |r| {
+                    r.annual_grid_energy_kwh
+                }),
+                kpi("Feed-in", "kWh", AnnualEnergy, |r| {
+                    r.annual_overproduction_kwh
+                }),
+                kpi("Battery charge", "kWh", AnnualEnergy, |r| {
+                    r.annual_battery_in_kwh
+                }),
+                kpi("Battery discharge", "kWh", AnnualEnergy, |r| {
+                    r.annual_battery_out_kwh
+                }),
+                kpi("EV charging", "kWh", AnnualEnergy, |r| {
+                    r.annual_car_charging_kwh
+                }),
+                kpi("Public charging", "kWh", AnnualEnergy, |r| {
+                    r.annual_public_charging_kwh
+                }),
+                kpi("Heat pump", "kWh", AnnualEnergy, |r| r.annual_heat_pump_kwh),
+                kpi("PV coverage", "%", Share, |r| r.pv_coverage_percent),
+                kpi("Autarky", "%", Share, |r| r.autarky.percent()),
+                kpi("Autarky without battery", "%", Share, |r| {
+                    r.autarky_without_battery.percent()
+                }),
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::general::units::Ratio;
+
+    #[test]
+    fn test_compare_results() {
+        let without_ev = SimpleOptimizationResults {
+            pv_capacity_kw: 5.0,
+            annual_electricity_demand_kwh: 4000.0,
+            autarky: Ratio::from_percent(50.0),
+            ..Default::default()
+        };
+        let with_ev = SimpleOptimizationResults {
+            pv_capacity_kw: 8.0,
+            annual_electricity_demand_kwh: 6000.0,
+            annual_car_charging_kwh: 2000.0,
+            autarky: Ratio::from_percent(45.0),
+            ..Default::default()
+        };
+
+        let report = without_ev
+            .compare(&with_ev)
+            .with_labels("Without EV", "With EV");
+        let pv = report.get("PV capacity").unwrap();
+        assert_eq!(pv.delta, 3.0);
+        assert_eq!(pv.relative_change, Some(0.6));
+        let autarky = report.get("Autarky").unwrap();
+        assert_eq!(autarky.delta, -5.0);
+        assert_eq!(autarky.relative_change, Some(-0.1));
+        assert_eq!(report.get("EV charging").unwrap().relative_change, None);
+        assert_eq!(report.kpis_of(KpiKind::Share).count(), 3);
+
+        let table = report.render();
+        assert!(table.contains("Without EV"));
+        assert!(table.contains("+60.0 %"));
+    }
+
+    #[test]
+    fn test_compare_results_with_zero_baseline() {
+        let empty = SimpleOptimizationResults::default();
+        let report = empty.compare(&empty);
+        assert_eq!(report.label_a, "A");
+        assert!(report.get("Unknown KPI").is_none());
+        for kpi in &report.kpis {
+            assert_eq!(kpi.delta, 0.0);
+            assert_eq!(kpi.relative_change, None);
+        }
+
+        let with_pv = SimpleOptimizationResults {
+            pv_capacity_kw: 5.0,
+            ..Default::default()
+        };
+        let report = empty.compare(&with_pv);
+        let pv = report.get("PV capacity").unwrap();
+        assert_eq!(pv.delta, 5.0);
+        assert_eq!(pv.relative_change, None);
+        let row = report
+            .render()
+            .lines()
+            .find(|line| line.starts_with("PV capacity"))
+            .unwrap()
+            .to_string();
+        assert!(row.trim_end().ends_with('-'));
+
+        // a negative baseline still reports the change relative to its magnitude
+        let a = SimpleOptimizationResults {
+            pv_coverage_percent: -20.0,
+            ..Default::default()
+        };
+        let b = SimpleOptimizationResults {
+            pv_coverage_percent: -10.0,
+            ..Default::default()
+        };
+        assert_eq!(
+            a.compare(&b).get("PV coverage").unwrap().relative_change,
+            Some(0.5)
+        );
+    }
+}
@@ -0,0 +1,417 @@
+use crate::general::calendar::DAYS_IN_MONTH;
+use crate::simple::solar_system_utils::SimpleOptimizationResults;
+
+/// Minimum contracted power in one period of a 3.0TD contract
+const TD30_MIN_KW: f64 = 15.0;
+
+/// Spanish access tariff with its power periods
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessTariff {
+    Td20, // 2.0TD up to 15 kW: P1 on weekdays from 8:00, P2 at night and on weekends
+    Td30, // 3.0TD above 15 kW: P1-P5 by season and hour on weekdays, P6 at night and on weekends
+}
+
+impl AccessTariff {
+    pub fn num_periods(&self) -> usize {
+        match self {
+            AccessTariff::Td20 => 2,
+            AccessTariff::Td30 => 6,
+        }
+    }
+
+    /// Power period (0 for P1) of an hour of the year (peninsula). The year starts on a
+    /// Monday; national holidays are not modelled.
+    pub fn period(&self, hour: usize) -> usize {
+        let day = hour / 24;
+        let hour_of_day = hour % 24;
+        let off_peak = day % 7 >= 5 || hour_of_day < 8;
+        match self {
+            AccessTariff::Td20 => usize::from(off_peak),
+            AccessTariff::Td30 if off_peak => 5,
+            AccessTariff::Td30 => {
+                // high season P1/P2, medium-high P2/P3, medium P3/P4, low P4/P5
+                let season = match month_of_day(day % 365) {
+                    0 | 1 | 6 | 11 => 0,
+                    2 | 10 => 1,
+                    5 | 7 | 8 => 2,
+                    _ => 3,
+                };
+                let peak = (9..14).contains(&hour_of_day) || (18..22).contains(&hour_of_day);
+                if peak { season } else { season + 1 }
+            }
+        }
+    }
+}
+
+/// Month (0-11) of a day of the year
+fn month_of_day(day: usize) -> usize {
+    let mut end = 0;
+    DAYS_IN_MONTH
+        .iter()
+        .position(|days| {
+            end += days;
+            day < end
+        })
+        .unwrap_or(11)
+}
+
+/// What happens if the grid import exceeds the contracted power of a period
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExcessRule {
+    NotAllowed, // Power control switch (ICP): the contracted power must cover every peak
+    MonthlyExcess { price_per_kw_month: Vec<f64> }, // Maximeter: penalty per kW of the monthly peak above the contracted power, per period (e.g. 2 × tep)
+}
+
+/// Power term of a Spanish electricity contract
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContractedPowerTariff {
+    pub access_tariff: AccessTariff,
+    pub price_per_kw_year: Vec<f64>, // Power price of P1, P2, ... per kW and year
+    pub excess: ExcessRule,
+    pub step_kw: f64, // Contracted powers are multiples of this step, e.g. 0.1 kW
+}
+
+impl ContractedPowerTariff {
+    pub fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let num_periods = self.access_tariff.num_periods();
+        let mut prices = vec![("power price", &self.price_per_kw_year)];
+        if let ExcessRule::MonthlyExcess { price_per_kw_month } = &self.excess {
+            prices.push(("excess price", price_per_kw_month));
+        }
+        for (name, values) in prices {
+            if values.len() != num_periods {
+                return Err(format!(
+                    "The {} needs {} periods, got {}",
+                    name,
+                    num_periods,
+                    values.len()
+                )
+                .into());
+            }
+            if let Some(value) = values.iter().find(|value| value.is_nan() || **value < 0.0) {
+                return Err(format!("The {} must not be negative, got {}", name, value).into());
+            }
+        }
+        if self.step_kw.is_nan() || self.step_kw <= 0.0 {
+            return Err(format!("The power step must be positive, got {}", self.step_kw).into());
+        }
+        Ok(())
+    }
+
+    /// Import peak in kW of every period (rows) and month (columns) of an hourly grid import
+    /// series in W
+    pub fn monthly_period_peaks_kw(&self, grid_import: &[f64]) -> Vec<[f64; 12]> {
+        let mut peaks = vec![[0.0; 12]; self.access_tariff.num_periods()];
+        for (hour, &import) in grid_import.iter().enumerate() {
+            let peak = &mut peaks[self.access_tariff.period(hour)][month_of_day((hour / 24) % 365)];
+            *peak = f64::max(*peak, import / 1000.0);
+        }
+        peaks
+    }
+
+    /// Annual power cost and excess penalty of `contracted_kw` under the monthly period peaks.
+    /// Returns None if a peak exceeds the contracted power without a maximeter.
+    fn period_cost(
+        &self,
+        period: usize,
+        contracted_kw: f64,
+        peaks: &[f64; 12],
+    ) -> Option<(f64, f64)> {
+        let power_cost = contracted_kw * self.price_per_kw_year[period];
+        let excess_kw = peaks.iter().map(|peak| (peak - contracted_kw).max(0.0));
+        match &self.excess {
+            ExcessRule::NotAllowed if excess_kw.clone().any(|excess| excess > 1e-9) => None,
+            ExcessRule::NotAllowed => Some((power_cost, 0.0)),
+            ExcessRule::MonthlyExcess { price_per_kw_month } => Some((
+                power_cost,
+                excess_kw.sum::<f64>() * price_per_kw_month[period],
+            )),
+        }
+    }
+
+    /// Annual power cost and excess penalty of contracted powers for all periods
+    pub fn annual_cost(
+        &self,
+        contracted_kw: &[f64],
+        grid_import: &[f64],
+    ) -> Result<(f64, f64), Box<dyn std::error::Error>> {
+        self.validate()?;
+        if contracted_kw.len() != self.access_tariff.num_periods() {
+            return Err(format!(
+                "Expected {} contracted powers, got {}",
+                self.access_tariff.num_periods(),
+                contracted_kw.len()
+            )
+            .into());
+        }
+        let peaks = self.monthly_period_peaks_kw(grid_import);
+        let mut total = (0.0, 0.0);
+        for (period, &kw) in contracted_kw.iter().enumerate() {
+            let (power_cost, penalty) =
+                self.period_cost(period, kw, &peaks[period])
+                    .ok_or_else(|| {
+                        format!("The import exceeds the contracted power in P{}", period + 1)
+                    })?;
+            total = (total.0 + power_cost, total.1 + penalty);
+        }
+        Ok(total)
+    }
+}
+
+/// Recommended contracted power compared with the current contract
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContractedPowerRecommendation {
+    pub period_peaks_kw: Vec<f64>, // Annual import peak of P1, P2, ...
+    pub contracted_kw: Vec<f64>,   // Recommended power of P1, P2, ...
+    pub annual_power_cost: f64,
+    pub annual_excess_penalty: f64,
+    pub current_kw: Vec<f64>,
+    pub current_annual_cost: f64, // Power cost and penalties of the current contract
+    pub annual_saving: f64,
+}
+
+impl ContractedPowerRecommendation {
+    /// Renders the periods as a plain text table
+    pub fn render(&self) -> String {
+        let mut lines = vec![
+            format!(
+                "Contracted power: annual cost {:.2} -> {:.2} (saving {:.2})",
+                self.current_annual_cost,
+                self.annual_power_cost + self.annual_excess_penalty,
+                self.annual_saving
+            ),
+            format!(
+                "{:<8} {:>10} {:>14} {:>14}",
+                "Period", "Peak (kW)", "Current (kW)", "Proposed (kW)"
+            ),
+        ];
+        for (period, peak) in self.period_peaks_kw.iter().enumerate() {
+            lines.push(format!(
+                "{:<8} {:>10.2} {:>14.2} {:>14.2}",
+                format!("P{}", period + 1),
+                peak,
+                self.current_kw[period],
+                self.contracted_kw[period]
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Cheapest contracted power of every period for an hourly grid import series in W, e.g. the
+/// import after PV and battery from `SimpleOptimizationResults::hourly_grid_consumption`.
+///
+/// The candidates are the monthly period peaks rounded up to `step_kw`. 3.0TD contracts need
+/// non-decreasing powers from P1 to P6 and at least 15 kW in P6; the periods are then chosen
+/// together by dynamic programming over the candidates.
+pub fn optimize_contracted_power(
+    tariff: &ContractedPowerTariff,
+    current_kw: &[f64],
+    grid_import: &[f64],
+) -> Result<ContractedPowerRecommendation, Box<dyn std::error::Error>> {
+    let (current_power_cost, current_penalty) = tariff.annual_cost(current_kw, grid_import)?;
+    let peaks = tariff.monthly_period_peaks_kw(grid_import);
+    let num_periods = peaks.len();
+    let ordered = tariff.access_tariff == AccessTariff::Td30;
+    let round_up = |kw: f64| (kw / tariff.step_kw - 1e-9).ceil().max(0.0) * tariff.step_kw;
+
+    let mut candidates: Vec<f64> = peaks.iter().flatten().map(|&peak| round_up(peak)).collect();
+    if ordered {
+        candidates.push(round_up(TD30_MIN_KW));
+    }
+    candidates.sort_by(f64::total_cmp);
+    candidates.dedup_by(|a, b| (*a - *b).abs() < 1e-9);
+
+    // cheapest cost of P1..=p with P(p) at each candidate, and the index of the previous period
+    let mut best: Vec<Vec<Option<(f64, usize)>>> = Vec::with_capacity(num_periods);
+    for (period, period_peaks) in peaks.iter().enumerate() {
+        let mut row = Vec::with_capacity(candidates.len());
+        let cheapest_previous = best.last().and_then(|previous| cheapest(previous));
+        let mut cheapest_before: Option<(f64, usize)> = None;
+        for (index, &kw) in candidates.iter().enumerate() {
+            if let Some((cost, _)) = best.last().and_then(|previous| previous[index])
+                && cheapest_before.is_none_or(|(cheapest, _)| cost < cheapest)
+            {
+                cheapest_before = Some((cost, index));
+            }
+            let allowed = !ordered || period + 1 < num_periods || kw >= TD30_MIN_KW - 1e-9;
+            let own = tariff
+                .period_cost(period, kw, period_peaks)
+                .filter(|_| allowed)
+                .map(|(power_cost, penalty)| power_cost + penalty);
+            let previous = match (period, ordered) {
+                (0, _) => Some((0.0, 0)),
+                (_, true) => cheapest_before,
+                (_, false) => cheapest_previous,
+            };
+            row.push(
+                own.zip(previous)
+                    .map(|(own, (cost, index))| (own + cost, index)),
+            );
+        }
+        best.push(row);
+    }
+
+    let (_, mut index) =
+        cheapest(&best[num_periods - 1]).ok_or("No contracted power covers the grid import")?;
+    let mut contracted_kw = vec![0.0; num_periods];
+    for period in (0..num_periods).rev() {
+        contracted_kw[period] = candidates[index];
+        index = best[period][index].map_or(0, |(_, previous)| previous);
+    }
+
+    let (annual_power_cost, annual_excess_penalty) =
+        tariff.annual_cost(&contracted_kw, grid_import)?;
+    let current_annual_cost = current_power_cost + current_penalty;
+    Ok(ContractedPowerRecommendation {
+        period_peaks_kw: peaks
+            .iter()
+            .map(|months| months.iter().copied().fold(0.0, f64::max))
+            .collect(),
+        contracted_kw,
+        annual_power_cost,
+        annual_excess_penalty,
+        current_kw: current_kw.to_vec(),
+        current_annual_cost,
+        annual_saving: current_annual_cost - annual_power_cost - annual_excess_penalty,
+    })
+}
+
+/// Lowest cost of a dynamic programming row and its candidate index
+fn cheapest(row: &[Option<(f64, usize)>]) -> Option<(f64, usize)> {
+    row.iter()
+        .enumerate()
+        .filter_map(|(index, cost)| cost.map(|(cost, _)| (cost, index)))
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+}
+
+impl SimpleOptimizationResults {
+    /// Cheapest contracted power for the grid import of the optimized system
+    pub fn recommend_contracted_power(
+        &self,
+        tariff: &ContractedPowerTariff,
+        current_kw: &[f64],
+    ) -> Result<ContractedPowerRecommendation, Box<dyn std::error::Error>> {
+        optimize_contracted_power(tariff, current_kw, &self.hourly_grid_consumption)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::general::calendar::NUM_HOURS;
+
+    /// 2 kW base import, 4 kW on weekdays at noon and a 6 kW spike on January 8th at noon
+    fn grid_import() -> Vec<f64> {
+        let mut import: Vec<f64> = (0..NUM_HOURS)
+            .map(|hour| {
+                if hour % 24 == 12 && (hour / 24) % 7 < 5 {
+                    4000.0
+                } else {
+                    2000.0
+                }
+            })
+            .collect();
+        import[7 * 24 + 12] = 6000.0;
+        import
+    }
+
+    #[test]
+    fn test_periods() {
+        // Monday 9:00 in January, Monday 3:00, Saturday noon
+        assert_eq!(AccessTariff::Td20.period(9), 0);
+        assert_eq!(AccessTariff::Td20.period(3), 1);
+        assert_eq!(AccessTariff::Td20.period(5 * 24 + 12), 1);
+        assert_eq!(AccessTariff::Td30.period(10), 0);
+        assert_eq!(AccessTariff::Td30.period(15), 1);
+        assert_eq!(AccessTariff::Td30.period(5 * 24 + 12), 5);
+        // Monday, April 2nd, 10:00 and 15:00
+        let april = (31 + 28 + 31 + 1) * 24;
+        assert_eq!(AccessTariff::Td30.period(april + 10), 3);
+        assert_eq!(AccessTariff::Td30.period(april + 15), 4);
+    }
+
+    #[test]
+    fn test_optimize_contracted_power() {
+        let import = grid_import();
+        let tariff = ContractedPowerTariff {
+            access_tariff: AccessTariff::Td20,
+            price_per_kw_year: vec![26.93, 0.7],
+            excess: ExcessRule::NotAllowed,
+            step_kw: 0.1,
+        };
+        let recommendation = optimize_contracted_power(&tariff, &[6.9, 6.9], &import).unwrap();
+        assert_eq!(recommendation.period_peaks_kw, vec![6.0, 2.0]);
+        assert!((recommendation.contracted_kw[0] - 6.0).abs() < 1e-9);
+        assert!((recommendation.contracted_kw[1] - 2.0).abs() < 1e-9);
+        let saving = 0.9 * 26.93 + 4.9 * 0.7;
+        assert!((recommendation.annual_saving - saving).abs() < 1e-6);
+        assert!(recommendation.render().contains("P2"));
+        assert!(optimize_contracted_power(&tariff, &[5.0, 6.9], &import).is_err());
+
+        // with a maximeter the January spike is cheaper as a penalty
+        let tariff = ContractedPowerTariff {
+            excess: ExcessRule::MonthlyExcess {
+                price_per_kw_month: vec![3.0, 0.1],
+            },
+            ..tariff
+        };
+        let recommendation = optimize_contracted_power(&tariff, &[6.9, 6.9], &import).unwrap();
+        assert!((recommendation.contracted_kw[0] - 4.0).abs() < 1e-9);
+        assert!((recommendation.annual_excess_penalty - 2.0 * 3.0).abs() < 1e-9);
+
+        // 3.0TD: non-decreasing powers and at least 15 kW in P6
+        let tariff = ContractedPowerTariff {
+            access_tariff: AccessTariff::Td30,
+            price_per_kw_year: vec![20.0, 10.0, 5.0, 4.0, 3.0, 2.0],
+            excess: ExcessRule::NotAllowed,
+            step_kw: 0.1,
+        };
+        let recommendation = optimize_contracted_power(&tariff, &[20.0; 6], &import).unwrap();
+        let kw = &recommendation.contracted_kw;
+        assert!(kw.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!((kw[0] - 6.0).abs() < 1e-9);
+        assert!((kw[5] - 15.0).abs() < 1e-9);
+        assert!(optimize_contracted_power(&tariff, &[20.0; 2], &import).is_err());
+    }
+
+    #[test]
+    fn test_contracted_power_rejects_invalid_tariffs() {
+        let tariff = ContractedPowerTariff {
+            access_tariff: AccessTariff::Td20,
+            price_per_kw_year: vec![26.93, 0.7],
+            excess: ExcessRule::NotAllowed,
+            step_kw: 0.1,
+        };
+        assert!(tariff.validate().is_ok());
+        for broken in [
+            ContractedPowerTariff {
+                price_per_kw_year: vec![26.93],
+                ..tariff.clone()
+            },
+            ContractedPowerTariff {
+                price_per_kw_year: vec![26.93, f64::NAN],
+                ..tariff.clone()
+            },
+            ContractedPowerTariff {
+                excess: ExcessRule::MonthlyExcess {
+                    price_per_kw_month: vec![-3.0, 0.1],
+                },
+                ..tariff.clone()
+            },
+            ContractedPowerTariff {
+                step_kw: 0.0,
+                ..tariff.clone()
+            },
+        ] {
+            assert!(broken.validate().is_err());
+            assert!(optimize_contracted_power(&broken, &[6.9, 6.9], &grid_import()).is_err());
+        }
+
+        // without any import nothing has to be contracted
+        let recommendation =
+            optimize_contracted_power(&tariff, &[6.9, 6.9], &vec![0.0; 8760]).unwrap();
+        assert_eq!(recommendation.period_peaks_kw, vec![0.0, 0.0]);
+        assert_eq!(recommendation.contracted_kw, vec![0.0, 0.0]);
+    }
+}
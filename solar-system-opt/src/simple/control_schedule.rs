@@ -0,0 +1,228 @@
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::simple::solar_system_utils::SimpleOptimizationResults;
+
+// Setpoints below this are sent as zero, the LP leaves tiny values in idle hours
+const MIN_SETPOINT_W: f64 = 1.0;
+
+/// Battery setpoints of one hour
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BatterySetpoint {
+    pub hour: usize,      // Hour of the year (0-8759)
+    pub charge_w: f64,    // Charging power
+    pub discharge_w: f64, // Discharging power
+}
+
+/// Contiguous hours in which a car is charged
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EvChargingWindow {
+    pub start_hour: usize, // Hour of the year charging starts
+    pub end_hour: usize,   // Hour of the year charging stops (exclusive)
+    pub energy_wh: f64,    // Energy charged in the window
+    pub max_power_w: f64,  // Highest hourly charging power in the window
+}
+
+/// Charging windows of one car
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VehicleSchedule {
+    pub vehicle: usize, // Index in `OptimizationConfig::vehicles`
+    pub windows: Vec<EvChargingWindow>,
+}
+
+/// Optimized dispatch as setpoints for a home energy management controller.
+///
+/// Hours are hours of the modelled year, the controller maps them to the current date. Powers
+/// are hourly averages in W.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ControlSchedule {
+    pub resolution_minutes: usize,
+    pub start_hour: usize, // First hour of the year covered by the schedule
+    pub num_hours: usize,
+    pub battery: Vec<BatterySetpoint>, // One setpoint per hour, empty without a battery
+    pub vehicles: Vec<VehicleSchedule>,
+    pub heat_pump_boost_hours: Vec<usize>, // Hours to send the SG-Ready boost signal
+}
+
+impl ControlSchedule {
+    /// Schedule for the hours `start_hour..start_hour + num_hours` of an optimization result
+    pub fn from_results(
+        results: &SimpleOptimizationResults,
+        start_hour: usize,
+        num_hours: usize,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let end_hour = start_hour + num_hours;
+        let available = results.hourly_car_charging.len();
+        if num_hours == 0 || end_hour > available {
+            return Err(format!(
+                "Schedule hours {}..{} are outside the {} hours of the results",
+                start_hour, end_hour, available
+            )
+            .into());
+        }
+        let setpoint = |power: f64| {
+            if power < MIN_SETPOINT_W {
+                0.0
+            } else {
+                power.round()
+            }
+        };
+
+        let has_battery =
+            results.battery_capacity_kwh > 0.0 || results.existing_battery_capacity_kwh > 0.0;
+        let battery = if has_battery {
+            (start_hour..end_hour)
+                .map(|hour| BatterySetpoint {
+                    hour,
+                    charge_w: setpoint(results.hourly_battery_in[hour]),
+                    discharge_w: setpoint(results.hourly_battery_out[hour]),
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let vehicles = results
+            .hourly_vehicle_charging
+            .iter()
+            .enumerate()
+            .map(|(vehicle, charging)| {
+                let mut windows: Vec<EvChargingWindow> = Vec::new();
+                for (hour, &charging) in charging.iter().enumerate().take(end_hour).skip(start_hour)
+                {
+                    let power = setpoint(charging);
+                    if power == 0.0 {
+                        continue;
+                    }
+                    match windows.last_mut() {
+                        Some(window) if window.end_hour == hour => {
+                            window.end_hour += 1;
+                            window.energy_wh += power;
+                            window.max_power_w = window.max_power_w.max(power);
+                        }
+                        _ => windows.push(EvChargingWindow {
+                            start_hour: hour,
+                            end_hour: hour + 1,
+                            energy_wh: power,
+                            max_power_w: power,
+                        }),
+                    }
+                }
+                VehicleSchedule { vehicle, windows }
+            })
+            .collect();
+
+        // The heat pump heats into the boost band above the regular buffer capacity
+        let heat_pump_boost_hours = match &results.config.heat_pump_dispatch {
+            Some(heat_pump) if heat_pump.sg_ready_boost_wh.is_some() => (start_hour..end_hour)
+                .filter(|&hour| {
+                    setpoint(results.hourly_heat_pump[hour]) > 0.0
+                        && results.hourly_heat_storage[hour]
+                            > heat_pump.storage_capacity_wh + MIN_SETPOINT_W
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        Ok(Self {
+            resolution_minutes: 60,
+            start_hour,
+            num_hours,
+            battery,
+            vehicles,
+            heat_pump_boost_hours,
+        })
+    }
+
+    /// Serializes the schedule as pretty-printed JSON
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Writes the schedule as JSON file
+    pub fn write_json(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simple::solar_system_utils::{HeatPumpDispatch, OptimizationConfig};
+
+    fn example_results() -> SimpleOptimizationResults {
+        let mut car_charging = vec![0.0; 48];
+        car_charging[1] = 3700.0;
+        car_charging[2] = 2000.4;
+        car_charging[5] = 0.3; // LP noise
+        car_charging[30] = 11000.0;
+        let mut heat_pump = vec![0.0; 48];
+        heat_pump[12] = 2000.0;
+        heat_pump[13] = 2000.0;
+        let mut heat_storage = vec![5000.0; 48];
+        heat_storage[13] = 12000.0;
+        SimpleOptimizationResults {
+            battery_capacity_kwh: 5.0,
+            hourly_battery_in: (0..48).map(|hour| hour as f64 * 10.0).collect(),
+            hourly_battery_out: vec![0.0; 48],
+            hourly_car_charging: car_charging.clone(),
+            hourly_vehicle_charging: vec![car_charging],
+            hourly_heat_pump: heat_pump,
+            hourly_heat_storage: heat_storage,
+            config: OptimizationConfig {
+                heat_pump_dispatch: Some(HeatPumpDispatch {
+                    storage_capacity_wh: 10000.0,
+                    sg_ready_boost_wh: Some(5000.0),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_control_schedule() {
+        let results = example_results();
+        let schedule = ControlSchedule::from_results(&results, 0, 24).unwrap();
+
+        assert_eq!(schedule.battery.len(), 24);
+        assert_eq!(schedule.battery[0].charge_w, 0.0);
+        assert_eq!(schedule.battery[3].charge_w, 30.0);
+        assert_eq!(
+            schedule.vehicles[0].windows,
+            vec![EvChargingWindow {
+                start_hour: 1,
+                end_hour: 3,
+                energy_wh: 5700.0,
+                max_power_w: 3700.0,
+            }]
+        );
+        assert_eq!(schedule.heat_pump_boost_hours, vec![13]);
+
+        let schedule = ControlSchedule::from_results(&results, 24, 24).unwrap();
+        assert_eq!(schedule.vehicles[0].windows[0].start_hour, 30);
+        assert_eq!(schedule.battery[0].hour, 24);
+        assert!(ControlSchedule::from_results(&results, 24, 25).is_err());
+    }
+
+    #[test]
+    fn test_control_schedule_json() {
+        let schedule = ControlSchedule::from_results(&example_results(), 0, 24).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&schedule.to_json().unwrap()).unwrap();
+        assert_eq!(json["resolution_minutes"], 60);
+        assert_eq!(json["battery"][3]["charge_w"], 30.0);
+        assert_eq!(json["vehicles"][0]["windows"][0]["end_hour"], 3);
+        assert_eq!(json["heat_pump_boost_hours"][0], 13);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("schedule.json");
+        schedule.write_json(&path).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            schedule.to_json().unwrap()
+        );
+    }
+}
@@ -0,0 +1,512 @@
+use std::str::FromStr;
+
+use crate::general::calendar::{date_label, day_of_year};
+use crate::simple::solar_system_utils::SimpleOptimizationResults;
+
+/// Day(s) to plot, either fixed or found in the results
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DaySelection {
+    Day(usize),                        // Day of the modelled year (0-364)
+    Date { month: usize, day: usize }, // Calendar date, month and day starting at 1
+    Solstices,                         // Jun 21 and Dec 21
+    Equinoxes,                         // Mar 20 and Sep 22
+    MinPvDay,                          // Day with the lowest PV production
+    MaxDemandDay,                      // Day with the highest total demand
+    NotableDays,                       // All days of `find_notable_days`
+}
+
+impl FromStr for DaySelection {
+    type Err = Box<dyn std::error::Error>;
+
+    /// Parses a day index ("171"), a date ("2024-06-21" or "06-21"; the year is ignored as the
+    /// model covers one non-leap year) or a preset ("solstices", "equinoxes", "min_pv_day",
+    /// "max_demand_day", "notable_days")
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let selection = match value {
+            "solstices" => DaySelection::Solstices,
+            "equinoxes" => DaySelection::Equinoxes,
+            "min_pv_day" => DaySelection::MinPvDay,
+            "max_demand_day" => DaySelection::MaxDemandDay,
+            "notable_days" => DaySelection::NotableDays,
+            _ => {
+                let parts: Vec<&str> = value.split('-').collect();
+                let (month, day) = match parts.as_slice() {
+                    [day] => return Ok(DaySelection::Day(day.parse()?)),
+                    [month, day] | [_, month, day] => (month.parse()?, day.parse()?),
+                    _ => return Err(format!("Invalid day selection '{}'", value).into()),
+                };
+                DaySelection::Date { month, day }
+            }
+        };
+        // Catch dates that don't exist early
+        if let DaySelection::Date { month, day } = selection {
+            day_of_year(month, day)?;
+        }
+        Ok(selection)
+    }
+}
+
+impl DaySelection {
+    /// Days of the modelled year (0-364) this selection stands for
+    pub fn resolve(
+        &self,
+        results: &SimpleOptimizationResults,
+    ) -> Result<Vec<usize>, Box<dyn std::error::Error>> {
+        Ok(match *self {
+            DaySelection::Day(day) if day < 365 => vec![day],
+            DaySelection::Day(day) => {
+                return Err(format!("Day {} is out of range (0-364)", day).into());
+            }
+            DaySelection::Date { month, day } => vec![day_of_year(month, day)?],
+            DaySelection::Solstices => vec![day_of_year(6, 21)?, day_of_year(12, 21)?],
+            DaySelection::Equinoxes => vec![day_of_year(3, 20)?, day_of_year(9, 22)?],
+            DaySelection::MinPvDay => {
+                vec![extreme_day(&results.hourly_total_pv_production, |a, b| {
+                    a < b
+                })?]
+            }
+            DaySelection::MaxDemandDay => {
+                vec![extreme_day(
+                    &results.hourly_total_electricity_demand,
+                    |a, b| a > b,
+                )?]
+            }
+            DaySelection::NotableDays => find_notable_days(results)
+                .iter()
+                .map(|notable| notable.day)
+                .collect(),
+        })
+    }
+}
+
+/// Why a day is noteworthy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotableDayKind {
+    HighestGridImport,
+    HighestExport,
+    LongestBatteryEmptyStreak, // Day the longest run of hours with an empty battery starts
+    MostTypical,               // Demand and PV profile closest to the mean day (lowest RMSE)
+}
+
+/// A noteworthy day of the results with its daily totals
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotableDay {
+    pub kind: NotableDayKind,
+    pub day: usize,
+    pub date: String, // e.g. "Jun 21"
+    pub score: f64, // kWh for import and export, hours for the empty streak, RMSE in Wh for the typical day
+    pub demand_kwh: f64,
+    pub pv_production_kwh: f64,
+    pub grid_import_kwh: f64,
+    pub export_kwh: f64,
+    pub battery_empty_hours: usize,
+}
+
+/// Battery levels below this share of the maximum level count as empty
+const EMPTY_BATTERY_SHARE: f64 = 0.01;
+
+/// Hours in which the battery is empty, all false without a battery
+fn empty_battery_hours(results: &SimpleOptimizationResults) -> Vec<bool> {
+    let max_level = results
+        .hourly_battery_storage
+        .iter()
+        .copied()
+        .fold(0.0f64, f64::max);
+    results
+        .hourly_battery_storage
+        .iter()
+        .map(|&level| max_level > 0.0 && level <= EMPTY_BATTERY_SHARE * max_level)
+        .collect()
+}
+
+/// Scans the hourly results for noteworthy days: highest grid import, highest export, start
+/// of the longest streak of hours with an empty battery (only with a battery) and the most
+/// typical day. Pass `DaySelection::NotableDays` to the day plots to plot them all.
+pub fn find_notable_days(results: &SimpleOptimizationResults) -> Vec<NotableDay> {
+    let num_days = results.hourly_total_electricity_demand.len().min(365 * 24) / 24;
+    if num_days == 0 {
+        return Vec::new();
+    }
+    let hourly = |values: &[f64], t: usize| values.get(t).copied().unwrap_or(0.0);
+    let daily_sum = |values: &[f64], day: usize| -> f64 {
+        (day * 24..(day + 1) * 24).map(|t| hourly(values, t)).sum()
+    };
+
+    let empty_hours = empty_battery_hours(results);
+    let is_empty = |t: usize| empty_hours.get(t).copied().unwrap_or(false);
+
+    let notable = |kind, day: usize, score| NotableDay {
+        kind,
+        day,
+        date: date_label(day),
+        score,
+        demand_kwh: daily_sum(&results.hourly_total_electricity_demand, day) / 1000.0,
+        pv_production_kwh: daily_sum(&results.hourly_total_pv_production, day) / 1000.0,
+        grid_import_kwh: daily_sum(&results.hourly_grid_consumption, day) / 1000.0,
+        export_kwh: daily_sum(&results.hourly_overproduction, day) / 1000.0,
+        battery_empty_hours: (day * 24..(day + 1) * 24).filter(|&t| is_empty(t)).count(),
+    };
+    let day_with_max = |score: &dyn Fn(usize) -> f64| {
+        (0..num_days)
+            .map(|day| (day, score(day)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .expect("at least one day")
+    };
+
+    let mut days = Vec::with_capacity(4);
+    let (day, import) = day_with_max(&|day| daily_sum(&results.hourly_grid_consumption, day));
+    days.push(notable(
+        NotableDayKind::HighestGridImport,
+        day,
+        import / 1000.0,
+    ));
+    let (day, export) = day_with_max(&|day| daily_sum(&results.hourly_overproduction, day));
+    days.push(notable(NotableDayKind::HighestExport, day, export / 1000.0));
+
+    // Longest run of empty hours, may span several days
+    let mut longest: Option<(usize, usize)> = None; // (start hour, length)
+    let mut start = None;
+    for t in 0..=num_days * 24 {
+        match (t < num_days * 24 && is_empty(t), start) {
+            (true, None) => start = Some(t),
+            (false, Some(streak_start)) => {
+                if longest.is_none_or(|(_, length)| t - streak_start > length) {
+                    longest = Some((streak_start, t - streak_start));
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some((start_hour, length)) = longest {
+        days.push(notable(
+            NotableDayKind::LongestBatteryEmptyStreak,
+            start_hour / 24,
+            length as f64,
+        ));
+    }
+
+    // Most typical day: lowest RMSE of the demand and PV profile to the mean day
+    let profile = |day: usize| -> Vec<f64> {
+        (day * 24..(day + 1) * 24)
+            .map(|t| hourly(&results.hourly_total_electricity_demand, t))
+            .chain(
+                (day * 24..(day + 1) * 24).map(|t| hourly(&results.hourly_total_pv_production, t)),
+            )
+            .collect()
+    };
+    let profiles: Vec<Vec<f64>> = (0..num_days).map(profile).collect();
+    let mean: Vec<f64> = (0..48)
+        .map(|i| profiles.iter().map(|profile| profile[i]).sum::<f64>() / num_days as f64)
+        .collect();
+    let (day, negative_rmse) = day_with_max(&|day| {
+        let squared_error: f64 = profiles[day]
+            .iter()
+            .zip(&mean)
+            .map(|(value, mean)| (value - mean).powi(2))
+            .sum();
+        -(squared_error / 48.0).sqrt()
+    });
+    days.push(notable(NotableDayKind::MostTypical, day, -negative_rmse));
+
+    days
+}
+
+/// The 7 consecutive days with the lowest PV coverage, usually a week in December
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorstWeek {
+    pub start_day: usize,
+    pub start_date: String, // e.g. "Dec 10"
+    pub end_date: String,
+    pub demand_kwh: f64,
+    pub pv_production_kwh: f64,
+    pub grid_import_kwh: f64,
+    pub pv_coverage_percent: f64, // PV production over demand
+    pub unmet_by_pv_percent: f64, // Share of the demand above the PV production of the same hour
+    pub battery_empty_hours: usize,
+}
+
+impl WorstWeek {
+    /// Renders the week as plain text lines
+    pub fn render(&self) -> String {
+        [
+            format!(
+                "Worst PV week: {} to {} (days {} to {})",
+                self.start_date,
+                self.end_date,
+                self.start_day,
+                self.start_day + 6
+            ),
+            format!("Demand: {:.1} kWh", self.demand_kwh),
+            format!("PV production: {:.1} kWh", self.pv_production_kwh),
+            format!("Grid import: {:.1} kWh", self.grid_import_kwh),
+            format!("PV coverage: {:.1} %", self.pv_coverage_percent),
+            format!("Unmet by PV: {:.1} %", self.unmet_by_pv_percent),
+            format!("Battery empty: {} h", self.battery_empty_hours),
+        ]
+        .join("\n")
+    }
+}
+
+/// Finds the 7 consecutive days of the year with the lowest PV coverage (PV production over
+/// demand) and reports how the system copes with them. None if the results hold less than a
+/// week of hourly data. Use `plot::plot_worst_week` for the matching plot.
+pub fn find_worst_week(results: &SimpleOptimizationResults) -> Option<WorstWeek> {
+    const HOURS_PER_WEEK: usize = 168;
+    let num_hours = results.hourly_total_electricity_demand.len().min(365 * 24);
+    if num_hours < HOURS_PER_WEEK {
+        return None;
+    }
+    let hourly = |values: &[f64], t: usize| values.get(t).copied().unwrap_or(0.0);
+    let window_sum = |values: &[f64], start_hour: usize| -> f64 {
+        (start_hour..start_hour + HOURS_PER_WEEK)
+            .map(|t| hourly(values, t))
+            .sum()
+    };
+    let coverage = |start_hour: usize| {
+        let demand = window_sum(&results.hourly_total_electricity_demand, start_hour);
+        if demand > 0.0 {
+            window_sum(&results.hourly_total_pv_production, start_hour) / demand
+        } else {
+            f64::INFINITY
+        }
+    };
+
+    let start_day = (0..=(num_hours - HOURS_PER_WEEK) / 24)
+        .min_by(|&a, &b| coverage(a * 24).total_cmp(&coverage(b * 24)))?;
+    let start_hour = start_day * 24;
+    let hours = start_hour..start_hour + HOURS_PER_WEEK;
+
+    let demand = window_sum(&results.hourly_total_electricity_demand, start_hour);
+    let pv_production = window_sum(&results.hourly_total_pv_production, start_hour);
+    let unmet_by_pv: f64 = hours
+        .clone()
+        .map(|t| {
+            (hourly(&results.hourly_total_electricity_demand, t)
+                - hourly(&results.hourly_total_pv_production, t))
+            .max(0.0)
+        })
+        .sum();
+    let share = |value: f64| {
+        if demand > 0.0 {
+            value / demand * 100.0
+        } else {
+            0.0
+        }
+    };
+    let empty_hours = empty_battery_hours(results);
+
+    Some(WorstWeek {
+        start_day,
+        start_date: date_label(start_day),
+        end_date: date_label(start_day + 6),
+        demand_kwh: demand / 1000.0,
+        pv_production_kwh: pv_production / 1000.0,
+        grid_import_kwh: window_sum(&results.hourly_grid_consumption, start_hour) / 1000.0,
+        pv_coverage_percent: share(pv_production),
+        unmet_by_pv_percent: share(unmet_by_pv),
+        battery_empty_hours: hours
+            .filter(|&t| empty_hours.get(t).copied().unwrap_or(false))
+            .count(),
+    })
+}
+
+/// Parses a comma separated list of selections, e.g. "2024-06-21,2024-12-21" or
+/// "solstices,min_pv_day"
+pub fn parse_day_selections(value: &str) -> Result<Vec<DaySelection>, Box<dyn std::error::Error>> {
+    value
+        .split(',')
+        .filter(|part| !part.trim().is_empty())
+        .map(DaySelection::from_str)
+        .collect()
+}
+
+/// Resolves the selections to distinct days of the year in the order they were given
+pub fn resolve_days(
+    selections: &[DaySelection],
+    results: &SimpleOptimizationResults,
+) -> Result<Vec<usize>, Box<dyn std::error::Error>> {
+    let mut days = Vec::new();
+    for selection in selections {
+        for day in selection.resolve(results)? {
+            if !days.contains(&day) {
+                days.push(day);
+            }
+        }
+    }
+    Ok(days)
+}
+
+/// Day whose sum of the hourly values is preferred by `is_better` over all other days
+fn extreme_day(
+    hourly: &[f64],
+    is_better: fn(f64, f64) -> bool,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut best: Option<(usize, f64)> = None;
+    for (day, values) in hourly.chunks_exact(24).take(365).enumerate() {
+        let total: f64 = values.iter().sum();
+        if best.is_none_or(|(_, best_total)| is_better(total, best_total)) {
+            best = Some((day, total));
+        }
+    }
+    best.map(|(day, _)| day)
+        .ok_or_else(|| "The results contain no full day of hourly data".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_day_selections() {
+        let selections = parse_day_selections("2024-06-21, 12-21,171,solstices").unwrap();
+        assert_eq!(
+            selections,
+            vec![
+                DaySelection::Date { month: 6, day: 21 },
+                DaySelection::Date { month: 12, day: 21 },
+                DaySelection::Day(171),
+                DaySelection::Solstices,
+            ]
+        );
+        assert!(parse_day_selections("2024-02-30").is_err());
+        assert!(parse_day_selections("2024-02-29").is_err());
+        assert!(parse_day_selections("midsummer").is_err());
+    }
+
+    #[test]
+    fn test_find_notable_days() {
+        let num_hours = 10 * 24;
+        // demand 500 Wh and PV 1000 Wh at noon on every day, except a dull day 3 and a sunny day 7
+        let demand = vec![500.0; num_hours];
+        let pv: Vec<f64> = (0..num_hours)
+            .map(|t| match (t / 24, t % 24) {
+                (3, 12) => 0.0,
+                (7, 12) => 3000.0,
+                (_, 12) => 1000.0,
+                _ => 0.0,
+            })
+            .collect();
+        let grid: Vec<f64> = (0..num_hours)
+            .map(|t| if t / 24 == 3 { 500.0 } else { 0.0 })
+            .collect();
+        let export: Vec<f64> = (0..num_hours)
+            .map(|t| (pv[t] - demand[t]).max(0.0))
+            .collect();
+        // battery empty from hour 50 to 61 and from 100 to 105
+        let battery: Vec<f64> = (0..num_hours)
+            .map(|t| {
+                if (50..62).contains(&t) || (100..106).contains(&t) {
+                    0.0
+                } else {
+                    2000.0
+                }
+            })
+            .collect();
+        let results = SimpleOptimizationResults {
+            hourly_total_electricity_demand: demand,
+            hourly_total_pv_production: pv,
+            hourly_grid_consumption: grid,
+            hourly_overproduction: export,
+            hourly_battery_storage: battery,
+            ..Default::default()
+        };
+
+        let days = find_notable_days(&results);
+        let kinds: Vec<(NotableDayKind, usize)> = days.iter().map(|d| (d.kind, d.day)).collect();
+        assert_eq!(kinds[0], (NotableDayKind::HighestGridImport, 3));
+        assert_eq!(kinds[1], (NotableDayKind::HighestExport, 7));
+        assert_eq!(kinds[2], (NotableDayKind::LongestBatteryEmptyStreak, 2));
+        assert_eq!(days[2].score, 12.0);
+        assert_eq!(days[2].battery_empty_hours, 12);
+        assert_eq!(days[0].grid_import_kwh, 12.0);
+        assert_eq!(days[0].date, "Jan 4");
+        // every other day matches the mean profile best
+        assert_eq!(kinds[3].0, NotableDayKind::MostTypical);
+        assert!(![3, 7].contains(&kinds[3].1));
+
+        assert_eq!(
+            DaySelection::NotableDays.resolve(&results).unwrap().len(),
+            4
+        );
+        assert!(find_notable_days(&SimpleOptimizationResults::default()).is_empty());
+    }
+
+    #[test]
+    fn test_find_worst_week() {
+        let num_hours = 365 * 24;
+        // 1 kWh PV at noon, only 200 Wh from Dec 1 (day 334) to Dec 9
+        let pv: Vec<f64> = (0..num_hours)
+            .map(|t| match (t / 24, t % 24) {
+                (334..343, 12) => 200.0,
+                (_, 12) => 1000.0,
+                _ => 0.0,
+            })
+            .collect();
+        let demand = vec![100.0; num_hours];
+        let grid: Vec<f64> = (0..num_hours)
+            .map(|t| (demand[t] - pv[t]).max(0.0))
+            .collect();
+        let battery: Vec<f64> = (0..num_hours)
+            .map(|t| {
+                if t / 24 == 336 && t % 24 < 5 {
+                    0.0
+                } else {
+                    1000.0
+                }
+            })
+            .collect();
+        let results = SimpleOptimizationResults {
+            hourly_total_electricity_demand: demand,
+            hourly_total_pv_production: pv,
+            hourly_grid_consumption: grid,
+            hourly_battery_storage: battery,
+            ..Default::default()
+        };
+
+        let week = find_worst_week(&results).unwrap();
+        assert_eq!(week.start_day, 334);
+        assert_eq!(week.start_date, "Dec 1");
+        assert_eq!(week.end_date, "Dec 7");
+        assert!((week.demand_kwh - 16.8).abs() < 1e-9);
+        assert!((week.pv_production_kwh - 1.4).abs() < 1e-9);
+        assert!((week.pv_coverage_percent - 1.4 / 16.8 * 100.0).abs() < 1e-9);
+        // only the noon hours are covered
+        assert!((week.unmet_by_pv_percent - 23.0 / 24.0 * 100.0).abs() < 1e-9);
+        assert!((week.grid_import_kwh - 16.1).abs() < 1e-9);
+        assert_eq!(week.battery_empty_hours, 5);
+        assert!(week.render().contains("Dec 1 to Dec 7"));
+
+        let short = SimpleOptimizationResults {
+            hourly_total_electricity_demand: vec![100.0; 100],
+            ..Default::default()
+        };
+        assert!(find_worst_week(&short).is_none());
+    }
+
+    #[test]
+    fn test_resolve_days() {
+        let mut pv = vec![1.0; 365 * 24];
+        pv[40 * 24 + 12] = 0.0;
+        let mut demand = vec![1.0; 365 * 24];
+        demand[300 * 24] = 10.0;
+        let results = SimpleOptimizationResults {
+            hourly_total_pv_production: pv,
+            hourly_total_electricity_demand: demand,
+            ..Default::default()
+        };
+
+        let selections = parse_day_selections("solstices,06-21,min_pv_day,max_demand_day").unwrap();
+        assert_eq!(
+            resolve_days(&selections, &results).unwrap(),
+            vec![171, 354, 40, 300]
+        );
+        assert!(resolve_days(&[DaySelection::Day(365)], &results).is_err());
+        assert!(
+            DaySelection::MinPvDay
+                .resolve(&SimpleOptimizationResults::default())
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,351 @@
+use ems_model::building::electricity::ElectricityRate;
+use good_lp::Solver;
+
+use crate::general::units::Energy;
+use crate::simple::simple_opt_re::run_simple_opt;
+use crate::simple::solar_system_utils::{OptimizationConfig, SimpleOptimizationResults};
+
+/// Fuel of a fossil boiler
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoilerFuel {
+    Gas,
+    Oil,
+}
+
+impl BoilerFuel {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BoilerFuel::Gas => "Gas",
+            BoilerFuel::Oil => "Oil",
+        }
+    }
+}
+
+/// Gas or oil boiler the homeowner would keep instead of installing the heat pump
+#[derive(Debug, Clone, PartialEq)]
+pub struct Boiler {
+    pub fuel: BoilerFuel,
+    pub fuel_price_per_kwh: f64, // Price per kWh of fuel (oil: about 10 kWh per litre)
+    pub efficiency: f64,         // Seasonal efficiency, heat delivered per kWh of fuel
+    pub annual_fixed_cost: f64,  // Maintenance, chimney sweep and gas connection fee per year
+}
+
+impl Boiler {
+    /// Condensing gas boiler
+    pub fn gas() -> Self {
+        Self {
+            fuel: BoilerFuel::Gas,
+            fuel_price_per_kwh: 0.12,
+            efficiency: 0.9,
+            annual_fixed_cost: 250.0,
+        }
+    }
+
+    /// Oil condensing boiler
+    pub fn oil() -> Self {
+        Self {
+            fuel: BoilerFuel::Oil,
+            fuel_price_per_kwh: 0.11,
+            efficiency: 0.85,
+            annual_fixed_cost: 300.0,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.efficiency.is_nan() || self.efficiency <= 0.0 || self.efficiency > 1.2 {
+            return Err(format!(
+                "Boiler efficiency must be between 0 and 1.2, got {}",
+                self.efficiency
+            )
+            .into());
+        }
+        for (name, value) in [
+            ("fuel price", self.fuel_price_per_kwh),
+            ("fixed cost", self.annual_fixed_cost),
+        ] {
+            if value.is_nan() || value < 0.0 {
+                return Err(format!("Boiler {} must not be negative, got {}", name, value).into());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Costs of one heating option
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeatingOption {
+    pub name: String,
+    pub investment: f64, // New PV, battery, wind, solar thermal and heat pump
+    pub annual_fuel_kwh: f64,
+    pub annual_grid_kwh: f64,
+    pub annual_running_cost: f64, // Electricity, fuel and fixed boiler costs per year
+    pub annual_cost: f64,         // Running cost plus the annuity of the investment
+}
+
+/// Heat pump with PV against keeping the boiler, both supplying the same heat demand
+#[derive(Debug, Clone)]
+pub struct HeatingComparison {
+    pub annual_heat_kwh: f64,
+    pub boiler: HeatingOption,
+    pub heat_pump: HeatingOption,
+    pub additional_investment: f64, // Investment of the heat pump option minus the boiler option
+    pub annual_saving: f64,         // Annual cost of the boiler option minus the heat pump option
+    pub annual_running_saving: f64, // Running cost of the boiler option minus the heat pump option
+    pub simple_payback_years: Option<f64>, // Additional investment over the running saving; None if it doesn't pay back
+    pub boiler_results: SimpleOptimizationResults,
+    pub heat_pump_results: SimpleOptimizationResults,
+}
+
+impl HeatingComparison {
+    /// Renders both options as a plain text table
+    pub fn render(&self) -> String {
+        let mut lines = vec![format!(
+            "{:<24} {:>12} {:>12} {:>12} {:>14} {:>12}",
+            "Option", "Investment", "Fuel (kWh)", "Grid (kWh)", "Running cost", "Annual cost"
+        )];
+        for option in [&self.boiler, &self.heat_pump] {
+            lines.push(format!(
+                "{:<24} {:>12.2} {:>12.0} {:>12.0} {:>14.2} {:>12.2}",
+                option.name,
+                option.investment,
+                option.annual_fuel_kwh,
+                option.annual_grid_kwh,
+                option.annual_running_cost,
+                option.annual_cost
+            ));
+        }
+        lines.push(format!(
+            "Annual saving {:.2}, payback {}",
+            self.annual_saving,
+            match self.simple_payback_years {
+                Some(years) => format!("{:.1} years", years),
+                None => "never".to_string(),
+            }
+        ));
+        lines.join("\n")
+    }
+}
+
+/// Investment in the new components of an optimization result, including the dispatched heat
+/// pump
+fn investment(results: &SimpleOptimizationResults) -> f64 {
+    let config = &results.config;
+    let wind = config.wind_turbine.as_ref().map_or(0.0, |wind_turbine| {
+        results.wind_capacity_kw * wind_turbine.inv_wind
+    });
+    let solar_thermal = config.solar_thermal().map_or(0.0, |collector| {
+        results.solar_thermal_area_m2 * collector.inv_per_m2
+    });
+    config.currency.convert(
+        results.pv_capacity_kw * config.inv_pv
+            + results.battery_capacity_kwh * config.inv_bat
+            + wind
+            + solar_thermal
+            + config.heat_pump_investment(),
+    )
+}
+
+/// Compares installing the heat pump of `config.heat_pump_dispatch` together with PV, battery
+/// and the other components of the config against keeping `boiler`.
+///
+/// The heat pump option is the regular optimization. The boiler option buys the household
+/// electricity without new PV, battery or wind and burns fuel for the heat demand of the heat
+/// pump (space heating and domestic hot water). Annual costs are the objective of each run
+/// (annuity of the investment, grid cost minus feed-in revenue) plus fuel and fixed boiler costs.
+pub fn compare_heat_pump_with_boiler<S: Solver + Clone>(
+    config: OptimizationConfig,
+    pv_cap_w_max: f64,
+    solar_irradiance: Vec<f64>,
+    electricity_demand: Vec<f64>,
+    electricity_rate: ElectricityRate,
+    boiler: &Boiler,
+    solver: S,
+) -> Result<HeatingComparison, Box<dyn std::error::Error>> {
+    boiler.validate()?;
+    let Some(heat_pump) = &config.heat_pump_dispatch else {
+        return Err("The comparison needs a heat pump dispatch in the config".into());
+    };
+    let hot_water_wh: f64 = heat_pump
+        .domestic_hot_water
+        .as_ref()
+        .map_or(0.0, |hot_water| hot_water.hourly_demand.iter().sum());
+    let annual_heat_kwh =
+        (heat_pump.hourly_heat_demand.iter().sum::<f64>() + hot_water_wh) / 1000.0;
+
+    let boiler_config = OptimizationConfig {
+        heat_pump_dispatch: None,
+        pv_fixed: true,
+        bat_fixed: true,
+        bat_value: Energy::ZERO,
+        wind_turbine: None,
+        discrete_sizing: None,
+        ..config.clone()
+    };
+    let boiler_results = run_simple_opt(
+        boiler_config,
+        0.0,
+        solar_irradiance.clone(),
+        electricity_demand.clone(),
+        electricity_rate.clone(),
+        solver.clone(),
+    )?;
+    let heat_pump_results = run_simple_opt(
+        config.clone(),
+        pv_cap_w_max,
+        solar_irradiance,
+        electricity_demand,
+        electricity_rate,
+        solver,
+    )?;
+
+    let annual_fuel_kwh = annual_heat_kwh / boiler.efficiency;
+    let boiler_investment = investment(&boiler_results);
+    // the objectives are already converted into the currency of the config
+    let convert = |amount: f64| config.currency.convert(amount);
+    let boiler_cost = boiler_results.objective_value
+        + convert(annual_fuel_kwh * boiler.fuel_price_per_kwh + boiler.annual_fixed_cost);
+    let boiler_option = HeatingOption {
+        name: format!("{} boiler", boiler.fuel.name()),
+        investment: boiler_investment,
+        annual_fuel_kwh,
+        annual_grid_kwh: boiler_results.annual_grid_energy_kwh,
+        annual_running_cost: boiler_cost - boiler_investment * config.annuity,
+        annual_cost: boiler_cost,
+    };
+    let heat_pump_investment = investment(&heat_pump_results);
+    let heat_pump_cost =
+        heat_pump_results.objective_value + convert(config.heat_pump_investment() * config.annuity);
+    let heat_pump_option = HeatingOption {
+        name: "Heat pump + PV".to_string(),
+        investment: heat_pump_investment,
+        annual_fuel_kwh: 0.0,
+        annual_grid_kwh: heat_pump_results.annual_grid_energy_kwh,
+        annual_running_cost: heat_pump_cost - heat_pump_investment * config.annuity,
+        annual_cost: heat_pump_cost,
+    };
+
+    let additional_investment = heat_pump_option.investment - boiler_option.investment;
+    let annual_running_saving =
+        boiler_option.annual_running_cost - heat_pump_option.annual_running_cost;
+    Ok(HeatingComparison {
+        annual_heat_kwh,
+        additional_investment,
+        annual_saving: boiler_option.annual_cost - heat_pump_option.annual_cost,
+        annual_running_saving,
+        simple_payback_years: (annual_running_saving > 0.0)
+            .then(|| additional_investment.max(0.0) / annual_running_saving),
+        boiler: boiler_option,
+        heat_pump: heat_pump_option,
+        boiler_results,
+        heat_pump_results,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simple::solar_system_utils::{
+        HeatPumpDispatch, load_demand_from_csv, load_solar_radiance_from_csv,
+    };
+
+    #[test]
+    fn test_compare_heat_pump_with_boiler() {
+        // 1 kW of heat in the winter months
+        let heat_demand: Vec<f64> = (0..8760)
+            .map(|t| {
+                if !(2160..7296).contains(&t) {
+                    1000.0
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        let annual_heat_kwh = heat_demand.iter().sum::<f64>() / 1000.0;
+        let config = OptimizationConfig {
+            inv_heat_pump: 1000.0,
+            heat_pump_dispatch: Some(HeatPumpDispatch {
+                hourly_heat_demand: heat_demand,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let comparison = compare_heat_pump_with_boiler(
+            config.clone(),
+            10000.0,
+            load_solar_radiance_from_csv(),
+            load_demand_from_csv().1,
+            ElectricityRate::fixed(0.3),
+            &Boiler::gas(),
+            good_lp::clarabel,
+        )
+        .unwrap();
+
+        assert!((comparison.annual_heat_kwh - annual_heat_kwh).abs() < 1e-6);
+        assert!((comparison.boiler.annual_fuel_kwh - annual_heat_kwh / 0.9).abs() < 1e-6);
+        assert!(comparison.boiler.investment.abs() < 1e-6);
+        assert!(comparison.boiler_results.pv_capacity_kw.abs() < 1e-6);
+        // a heat pump with COP 3 at 0.30 per kWh beats gas at 0.12 per kWh
+        assert!(comparison.heat_pump.investment >= 3000.0);
+        assert!(comparison.annual_running_saving > annual_heat_kwh * (0.12 / 0.9 - 0.1));
+        assert!(comparison.simple_payback_years.unwrap() > 0.0);
+        assert!(
+            (comparison.annual_saving
+                - (comparison.boiler.annual_cost - comparison.heat_pump.annual_cost))
+                .abs()
+                < 1e-9
+        );
+        assert!(comparison.render().contains("Gas boiler"));
+    }
+
+    #[test]
+    fn test_compare_heat_pump_with_boiler_rejects_invalid_input() {
+        // both checks fail before any optimization runs
+        let compare = |config: OptimizationConfig, boiler: &Boiler| {
+            compare_heat_pump_with_boiler(
+                config,
+                10000.0,
+                vec![0.0; 8760],
+                vec![0.0; 8760],
+                ElectricityRate::fixed(0.3),
+                boiler,
+                good_lp::clarabel,
+            )
+        };
+        let with_heat_pump = OptimizationConfig {
+            heat_pump_dispatch: Some(HeatPumpDispatch::default()),
+            ..Default::default()
+        };
+
+        let err = compare(OptimizationConfig::default(), &Boiler::gas()).unwrap_err();
+        assert!(err.to_string().contains("heat pump dispatch"));
+
+        for broken in [
+            Boiler {
+                efficiency: 0.0,
+                ..Boiler::oil()
+            },
+            Boiler {
+                efficiency: 1.5,
+                ..Boiler::gas()
+            },
+            Boiler {
+                efficiency: f64::NAN,
+                ..Boiler::gas()
+            },
+            Boiler {
+                fuel_price_per_kwh: -0.1,
+                ..Boiler::gas()
+            },
+            Boiler {
+                annual_fixed_cost: f64::NAN,
+                ..Boiler::oil()
+            },
+        ] {
+            assert!(broken.validate().is_err());
+            assert!(compare(with_heat_pump.clone(), &broken).is_err());
+        }
+        assert!(Boiler::gas().validate().is_ok());
+        assert!(Boiler::oil().validate().is_ok());
+    }
+}
@@ -0,0 +1,383 @@
+use std::fmt;
+
+use crate::general::calendar::DAYS_IN_MONTH;
+use crate::general::calendar::NUM_HOURS;
+
+/// Input series the optimizer takes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputSeries {
+    SolarIrradiance,
+    ElectricityDemand,
+}
+
+impl fmt::Display for InputSeries {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputSeries::SolarIrradiance => write!(f, "Solar irradiance"),
+            InputSeries::ElectricityDemand => write!(f, "Electricity demand"),
+        }
+    }
+}
+
+/// Kind of problem found in an input series
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QualityIssueKind {
+    Missing,     // NaN or infinite values, e.g. empty cells exported as NaN
+    Negative,    // Below zero, e.g. a sensor offset at night or unmetered export
+    Flatline,    // The same value for at least `flatline_hours`, e.g. a stuck meter
+    Spike,       // Demand above `spike_factor` times its 95th percentile
+    WrongLength, // Not 8760 hourly values
+}
+
+/// Problem in a run of consecutive hours
+#[derive(Debug, Clone, PartialEq)]
+pub struct QualityIssue {
+    pub kind: QualityIssueKind,
+    pub start_hour: usize,
+    pub hours: usize,
+    pub value: f64, // First value of the run, or the length for `WrongLength`
+}
+
+impl fmt::Display for QualityIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hours = format!(
+            "hours {}-{}",
+            self.start_hour,
+            self.start_hour + self.hours.max(1) - 1
+        );
+        match self.kind {
+            QualityIssueKind::Missing => write!(f, "Missing values in {}", hours),
+            QualityIssueKind::Negative => {
+                write!(f, "Negative values in {} (first {:.2})", hours, self.value)
+            }
+            QualityIssueKind::Flatline => {
+                write!(f, "Constant value {:.2} in {}", self.value, hours)
+            }
+            QualityIssueKind::Spike => write!(f, "Spike of {:.0} in {}", self.value, hours),
+            QualityIssueKind::WrongLength => write!(
+                f,
+                "{} values instead of {} hourly values",
+                self.value, NUM_HOURS
+            ),
+        }
+    }
+}
+
+/// Thresholds of the quality check
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QualityCheckConfig {
+    pub flatline_hours: usize, // Shortest run of identical values that counts as a flatline
+    pub spike_factor: f64,     // Demand above this multiple of its 95th percentile is a spike
+}
+
+impl Default for QualityCheckConfig {
+    fn default() -> Self {
+        Self {
+            flatline_hours: 24,
+            spike_factor: 5.0,
+        }
+    }
+}
+
+/// Problems found in one input series
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputQualityReport {
+    pub series: InputSeries,
+    pub issues: Vec<QualityIssue>,
+}
+
+impl InputQualityReport {
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Hours affected by issues of the given kind
+    pub fn hours(&self, kind: QualityIssueKind) -> usize {
+        self.issues
+            .iter()
+            .filter(|issue| issue.kind == kind && kind != QualityIssueKind::WrongLength)
+            .map(|issue| issue.hours)
+            .sum()
+    }
+
+    /// Whether `repair` can fix the series. A wrong length can't be repaired.
+    pub fn is_repairable(&self) -> bool {
+        self.issues
+            .iter()
+            .all(|issue| issue.kind != QualityIssueKind::WrongLength)
+    }
+
+    /// One line per kind of issue, e.g. for logging before an optimization
+    pub fn summary(&self) -> String {
+        if self.is_clean() {
+            return format!("{}: no issues", self.series);
+        }
+        let mut parts = Vec::new();
+        for (kind, name) in [
+            (QualityIssueKind::Missing, "missing"),
+            (QualityIssueKind::Negative, "negative"),
+            (QualityIssueKind::Flatline, "flatlined"),
+            (QualityIssueKind::Spike, "spikes"),
+        ] {
+            let hours = self.hours(kind);
+            if hours > 0 {
+                parts.push(format!("{} h {}", hours, name));
+            }
+        }
+        if let Some(issue) = self
+            .issues
+            .iter()
+            .find(|issue| issue.kind == QualityIssueKind::WrongLength)
+        {
+            parts.push(issue.to_string());
+        }
+        format!("{}: {}", self.series, parts.join(", "))
+    }
+
+    /// Summary followed by every issue
+    pub fn render(&self) -> String {
+        std::iter::once(self.summary())
+            .chain(self.issues.iter().map(|issue| format!("  {}", issue)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Repairs the series the report was made for. Negative irradiance is set to zero. Missing,
+    /// negative demand, flatlined and spiking hours are filled with the mean of the same hour of
+    /// day in the same month over the unaffected hours, or of the whole year if the month has
+    /// none.
+    pub fn repair(&self, values: &[f64]) -> Result<Vec<f64>, Box<dyn std::error::Error>> {
+        if !self.is_repairable() || values.len() != NUM_HOURS {
+            return Err(format!(
+                "{} needs {} hourly values to be repaired, got {}",
+                self.series,
+                NUM_HOURS,
+                values.len()
+            )
+            .into());
+        }
+        let mut repaired = values.to_vec();
+        let mut is_gap = vec![false; NUM_HOURS];
+        for issue in &self.issues {
+            for hour in issue.start_hour..issue.start_hour + issue.hours {
+                if issue.kind == QualityIssueKind::Negative
+                    && self.series == InputSeries::SolarIrradiance
+                {
+                    repaired[hour] = 0.0;
+                } else {
+                    is_gap[hour] = true;
+                }
+            }
+        }
+
+        let month_of_hour: Vec<usize> = DAYS_IN_MONTH
+            .iter()
+            .enumerate()
+            .flat_map(|(month, days)| std::iter::repeat_n(month, days * 24))
+            .collect();
+        let mut sums = [[(0.0, 0usize); 24]; 13]; // Per month and for the whole year (index 12)
+        for hour in (0..NUM_HOURS).filter(|&hour| !is_gap[hour]) {
+            for group in [month_of_hour[hour], 12] {
+                let (sum, count) = &mut sums[group][hour % 24];
+                *sum += repaired[hour];
+                *count += 1;
+            }
+        }
+        for hour in (0..NUM_HOURS).filter(|&hour| is_gap[hour]) {
+            repaired[hour] = [month_of_hour[hour], 12]
+                .iter()
+                .map(|&group| sums[group][hour % 24])
+                .find(|&(_, count)| count > 0)
+                .map(|(sum, count)| sum / count as f64)
+                .ok_or_else(|| {
+                    format!(
+                        "{} has no valid value for hour {} of the day",
+                        self.series,
+                        hour % 24
+                    )
+                })?;
+        }
+        Ok(repaired)
+    }
+}
+
+/// Checks an input series for missing and negative values, flatlines and (for demand) spikes
+pub fn check_input_series(
+    series: InputSeries,
+    values: &[f64],
+    config: &QualityCheckConfig,
+) -> InputQualityReport {
+    let mut issues = Vec::new();
+    if values.len() != NUM_HOURS {
+        issues.push(QualityIssue {
+            kind: QualityIssueKind::WrongLength,
+            start_hour: 0,
+            hours: 0,
+            value: values.len() as f64,
+        });
+    }
+
+    let spike_limit = if series == InputSeries::ElectricityDemand {
+        let mut finite: Vec<f64> = values.iter().copied().filter(|v| v.is_finite()).collect();
+        finite.sort_by(f64::total_cmp);
+        finite
+            .get(finite.len() * 95 / 100)
+            .filter(|&&p95| p95 > 0.0)
+            .map(|p95| p95 * config.spike_factor)
+    } else {
+        None
+    };
+    let kind_of = |value: f64| {
+        if !value.is_finite() {
+            Some(QualityIssueKind::Missing)
+        } else if value < 0.0 {
+            Some(QualityIssueKind::Negative)
+        } else if spike_limit.is_some_and(|limit| value > limit) {
+            Some(QualityIssueKind::Spike)
+        } else {
+            None
+        }
+    };
+    // runs of hours with the same kind of issue
+    let mut hour = 0;
+    while hour < values.len() {
+        let Some(kind) = kind_of(values[hour]) else {
+            hour += 1;
+            continue;
+        };
+        let start_hour = hour;
+        while hour < values.len() && kind_of(values[hour]) == Some(kind) {
+            hour += 1;
+        }
+        issues.push(QualityIssue {
+            kind,
+            start_hour,
+            hours: hour - start_hour,
+            value: values[start_hour],
+        });
+    }
+
+    // flatlines of otherwise valid values
+    let mut start_hour = 0;
+    for hour in 1..=values.len() {
+        if hour < values.len() && values[hour] == values[start_hour] {
+            continue;
+        }
+        if hour - start_hour >= config.flatline_hours.max(2)
+            && kind_of(values[start_hour]).is_none()
+        {
+            issues.push(QualityIssue {
+                kind: QualityIssueKind::Flatline,
+                start_hour,
+                hours: hour - start_hour,
+                value: values[start_hour],
+            });
+        }
+        start_hour = hour;
+    }
+    issues.sort_by_key(|issue| issue.start_hour);
+
+    InputQualityReport { series, issues }
+}
+
+/// Checks the irradiance and the demand series before an optimization
+pub fn check_inputs(
+    solar_irradiance: &[f64],
+    electricity_demand: &[f64],
+    config: &QualityCheckConfig,
+) -> (InputQualityReport, InputQualityReport) {
+    (
+        check_input_series(InputSeries::SolarIrradiance, solar_irradiance, config),
+        check_input_series(InputSeries::ElectricityDemand, electricity_demand, config),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simple::solar_system_utils::{load_csv_data, load_demand_csv_data};
+
+    /// Daily demand pattern with a small wobble, so neighbouring hours always differ
+    fn demand() -> Vec<f64> {
+        (0..NUM_HOURS)
+            .map(|t| {
+                let demand =
+                    300.0 + 200.0 * ((t % 24) as f64 / 24.0 * std::f64::consts::TAU).sin().abs();
+                demand + (demand * 7.3).sin()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_check_input_series() {
+        let mut values = demand();
+        values[100] = f64::NAN;
+        values[101] = f64::NAN;
+        values[500] = -50.0;
+        values[1000] = 20000.0;
+        values[2000..2030].iter_mut().for_each(|v| *v = 250.0);
+
+        let report =
+            check_input_series(InputSeries::ElectricityDemand, &values, &Default::default());
+        let kinds: Vec<_> = report.issues.iter().map(|issue| issue.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                QualityIssueKind::Missing,
+                QualityIssueKind::Negative,
+                QualityIssueKind::Spike,
+                QualityIssueKind::Flatline
+            ]
+        );
+        assert_eq!(report.hours(QualityIssueKind::Missing), 2);
+        assert_eq!(report.issues[3].start_hour, 2000);
+        assert_eq!(report.issues[3].hours, 30);
+        assert!(report.summary().contains("2 h missing"));
+        assert!(
+            report
+                .render()
+                .contains("Spike of 20000 in hours 1000-1000")
+        );
+
+        let repaired = report.repair(&values).unwrap();
+        let repaired_report = check_input_series(
+            InputSeries::ElectricityDemand,
+            &repaired,
+            &Default::default(),
+        );
+        assert!(repaired_report.is_clean(), "{}", repaired_report.render());
+        assert!(repaired.iter().all(|v| v.is_finite() && *v >= 0.0));
+        // gaps are filled from the same hour of day
+        assert!((repaired[500] - values[500 - 24]).abs() < 10.0);
+
+        // negative irradiance is cut at zero, irradiance has no spikes
+        let mut solar = vec![0.0; NUM_HOURS];
+        for (t, value) in solar.iter_mut().enumerate() {
+            *value = (((t % 24) as f64 - 6.0) / 12.0 * std::f64::consts::PI)
+                .sin()
+                .max(0.0);
+        }
+        solar[3] = -0.01;
+        solar[12] = 100.0;
+        let report = check_input_series(InputSeries::SolarIrradiance, &solar, &Default::default());
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(report.repair(&solar).unwrap()[3], 0.0);
+
+        let short = check_input_series(
+            InputSeries::SolarIrradiance,
+            &[0.5; 100],
+            &Default::default(),
+        );
+        assert!(!short.is_repairable());
+        assert!(short.repair(&[0.5; 100]).is_err());
+    }
+
+    #[test]
+    fn test_bundled_data_is_clean() {
+        let solar = load_csv_data("data/ts_res.csv").unwrap();
+        let (_, electricity) = load_demand_csv_data("data/demand.csv").unwrap();
+        let (solar, demand) = check_inputs(&solar, &electricity, &Default::default());
+        assert!(solar.is_clean(), "{}", solar.render());
+        assert!(demand.is_clean(), "{}", demand.render());
+    }
+}
@@ -0,0 +1,310 @@
+use ems_model::building::electricity::ElectricityRate;
+use ems_model::general::location::{Country, Location};
+use good_lp::Solver;
+
+use crate::general::calendar::DAYS_IN_MONTH;
+use crate::general::calendar::NUM_HOURS;
+use crate::general::units::Energy;
+use crate::simple::simple_opt_re::run_simple_opt;
+use crate::simple::solar_system_utils::{
+    HeatingType, OptimizationConfig, SimpleOptimizationResults, load_demand_from_csv,
+    load_solar_radiance_from_csv,
+};
+
+/// PV capacity limit of a first estimate, as the capacity whose yield is this multiple of the
+/// annual consumption
+const FIRST_ESTIMATE_PV_YIELD_FACTOR: f64 = 2.0;
+
+/// Climate band of the bundled default datasets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClimateBand {
+    CentralEurope, // Germany; the bundled ts_res.csv profile
+    IberiaNorth,   // Spain and Portugal from 40° N, e.g. Madrid or Porto
+    IberiaSouth,   // Spain and Portugal south of 40° N, e.g. Seville or the Algarve
+}
+
+impl ClimateBand {
+    /// Band of a location from its country and latitude. Countries without own data get the
+    /// band of their latitude: Central Europe from 45° N.
+    pub fn of(location: &Location) -> Self {
+        let latitude = location.coordinates.latitude;
+        match location.country {
+            Country::Germany => ClimateBand::CentralEurope,
+            Country::Spain | Country::Portugal if latitude < 40.0 => ClimateBand::IberiaSouth,
+            Country::Spain | Country::Portugal => ClimateBand::IberiaNorth,
+            _ if latitude >= 45.0 => ClimateBand::CentralEurope,
+            _ if latitude >= 40.0 => ClimateBand::IberiaNorth,
+            _ => ClimateBand::IberiaSouth,
+        }
+    }
+
+    /// Typical PV yield per month in kWh/kWp
+    pub fn monthly_pv_yield(&self) -> [f64; 12] {
+        match self {
+            ClimateBand::CentralEurope => [
+                15.0, 30.0, 73.0, 102.0, 125.0, 120.0, 140.0, 115.0, 99.0, 60.0, 39.0, 30.0,
+            ],
+            ClimateBand::IberiaNorth => [
+                80.0, 95.0, 135.0, 155.0, 175.0, 190.0, 205.0, 190.0, 150.0, 115.0, 85.0, 70.0,
+            ],
+            ClimateBand::IberiaSouth => [
+                95.0, 105.0, 145.0, 160.0, 185.0, 195.0, 205.0, 195.0, 160.0, 130.0, 95.0, 85.0,
+            ],
+        }
+    }
+
+    /// Mean outdoor temperature per month in °C
+    pub fn monthly_mean_temp_c(&self) -> [f64; 12] {
+        match self {
+            ClimateBand::CentralEurope => [
+                0.5, 1.5, 5.0, 9.0, 13.5, 16.5, 18.5, 18.0, 14.0, 9.5, 4.5, 1.5,
+            ],
+            ClimateBand::IberiaNorth => [
+                6.0, 7.5, 10.5, 12.5, 16.5, 22.0, 25.5, 25.0, 20.5, 15.0, 9.5, 6.5,
+            ],
+            ClimateBand::IberiaSouth => [
+                11.0, 12.5, 15.0, 17.0, 20.5, 25.0, 28.0, 28.0, 25.0, 20.0, 15.0, 12.0,
+            ],
+        }
+    }
+
+    /// Half the typical difference between the warmest and coldest hour of a day in K
+    pub fn daily_temp_amplitude_k(&self) -> f64 {
+        match self {
+            ClimateBand::CentralEurope => 4.0,
+            ClimateBand::IberiaNorth => 7.0,
+            ClimateBand::IberiaSouth => 5.5,
+        }
+    }
+}
+
+/// Default datasets of a location for a first sizing estimate
+#[derive(Debug, Clone)]
+pub struct LocationDefaults {
+    pub band: ClimateBand,
+    pub solar_irradiance: Vec<f64>, // Normalized irradiance for 8760 hours, as ts_res.csv
+    pub hourly_outdoor_temp_c: Vec<f64>, // Outdoor temperature for 8760 hours
+    pub hourly_cop: Vec<f64>,       // Air source heat pump COP for 8760 hours
+}
+
+impl LocationDefaults {
+    /// Annual PV yield of the irradiance in kWh/kWp
+    pub fn annual_pv_yield(&self) -> f64 {
+        self.solar_irradiance.iter().sum()
+    }
+}
+
+/// Picks the bundled default irradiance, temperature and COP series for the climate band of
+/// `location`.
+///
+/// The irradiance keeps the hourly shape of the bundled `data/ts_res.csv` profile, scaled month
+/// by month to the typical yield of the band. Temperatures follow the monthly means of the band
+/// (interpolated between mid-month) with a daily cycle peaking at 15:00. The COP is the
+/// when2heat regression for air source heat pumps with the heating curve of `heating_type`.
+pub fn location_defaults(location: &Location, heating_type: &HeatingType) -> LocationDefaults {
+    let band = ClimateBand::of(location);
+    let hourly_outdoor_temp_c = hourly_temperatures(&band);
+    let hourly_cop = hourly_outdoor_temp_c
+        .iter()
+        .map(|&temp| air_source_cop(temp, heating_type))
+        .collect();
+    LocationDefaults {
+        band,
+        solar_irradiance: scaled_irradiance(&load_solar_radiance_from_csv(), &band),
+        hourly_outdoor_temp_c,
+        hourly_cop,
+    }
+}
+
+/// First sizing estimate from just a location and the annual electricity consumption.
+///
+/// Uses the defaults of the location, the bundled household profile scaled to
+/// `annual_consumption_kwh`, the default config with its fixed grid price, and a PV limit whose
+/// yield is twice the consumption.
+pub fn run_first_estimate<S: Solver>(
+    location: &Location,
+    annual_consumption_kwh: f64,
+    solver: S,
+) -> Result<SimpleOptimizationResults, Box<dyn std::error::Error>> {
+    if !(annual_consumption_kwh > 0.0 && annual_consumption_kwh.is_finite()) {
+        return Err(format!(
+            "Annual consumption must be positive, got {} kWh",
+            annual_consumption_kwh
+        )
+        .into());
+    }
+    let defaults = location_defaults(location, &HeatingType::default());
+    let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+    let pv_cap_w_max = FIRST_ESTIMATE_PV_YIELD_FACTOR * annual_consumption_kwh
+        / defaults.annual_pv_yield()
+        * 1000.0;
+
+    let config = OptimizationConfig {
+        electricity_usage: Energy::from_kwh(annual_consumption_kwh),
+        monthly_demand: None,
+        ..Default::default()
+    };
+    let electricity_rate = ElectricityRate::fixed(config.fc_grid);
+    run_simple_opt(
+        config,
+        pv_cap_w_max,
+        defaults.solar_irradiance,
+        electricity_demand,
+        electricity_rate,
+        solver,
+    )
+}
+
+/// Scales every month of `irradiance` to the monthly yield of the band
+fn scaled_irradiance(irradiance: &[f64], band: &ClimateBand) -> Vec<f64> {
+    let mut scaled = Vec::with_capacity(NUM_HOURS);
+    let mut start = 0;
+    for (days, target) in DAYS_IN_MONTH.iter().zip(band.monthly_pv_yield()) {
+        let month = &irradiance[start..start + days * 24];
+        let total: f64 = month.iter().sum();
+        if total > 0.0 {
+            scaled.extend(month.iter().map(|value| value * target / total));
+        } else {
+            scaled.extend(std::iter::repeat_n(
+                target / month.len() as f64,
+                month.len(),
+            ));
+        }
+        start += days * 24;
+    }
+    scaled
+}
+
+/// Hourly temperatures from the monthly means and the daily cycle of the band
+fn hourly_temperatures(band: &ClimateBand) -> Vec<f64> {
+    let means = band.monthly_mean_temp_c();
+    let amplitude = band.daily_temp_amplitude_k();
+    // Middle of every month in hours, the means apply there
+    let mut mid_month = [0.0; 12];
+    let mut start = 0.0;
+    for (month, days) in DAYS_IN_MONTH.iter().enumerate() {
+        mid_month[month] = start + *days as f64 * 12.0;
+        start += *days as f64 * 24.0;
+    }
+    (0..NUM_HOURS)
+        .map(|hour| {
+            let t = hour as f64 + 0.5;
+            let next = mid_month.iter().position(|mid| *mid > t).unwrap_or(12);
+            let (from, till) = match next {
+                0 | 12 => (11, 0),
+                next => (next - 1, next),
+            };
+            let from_hour = if next == 0 {
+                mid_month[11] - 8760.0
+            } else {
+                mid_month[from]
+            };
+            let till_hour = if next == 12 {
+                mid_month[0] + 8760.0
+            } else {
+                mid_month[till]
+            };
+            let share = (t - from_hour) / (till_hour - from_hour);
+            let mean = means[from] + share * (means[till] - means[from]);
+            let hour_of_day = (hour % 24) as f64;
+            mean + amplitude * (2.0 * std::f64::consts::PI * (hour_of_day - 15.0) / 24.0).cos()
+        })
+        .collect()
+}
+
+/// COP of an air source heat pump after the when2heat regression, with a supply temperature of
+/// 40 °C minus the outdoor temperature for radiators and 30 °C minus half of it for floor
+/// heating, and a temperature lift of at least 15 K
+fn air_source_cop(outdoor_temp_c: f64, heating_type: &HeatingType) -> f64 {
+    let sink_c = match heating_type {
+        HeatingType::Radiator => 40.0 - outdoor_temp_c,
+        HeatingType::Floor => 30.0 - 0.5 * outdoor_temp_c,
+    };
+    let lift = (sink_c - outdoor_temp_c).max(15.0);
+    0.85 * (6.08 - 0.09 * lift + 0.0005 * lift * lift)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ems_model::general::location::Coordinates;
+
+    fn location(country: Country, city: &str, latitude: f64, longitude: f64) -> Location {
+        Location::minimal(
+            city.to_string(),
+            country,
+            city.to_string(),
+            "00000".to_string(),
+            Coordinates::new(latitude, longitude).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_location_defaults() {
+        let berlin = location(Country::Germany, "Berlin", 52.5, 13.4);
+        let madrid = location(Country::Spain, "Madrid", 40.4, -3.7);
+        let seville = location(Country::Spain, "Seville", 37.4, -6.0);
+        let faro = location(Country::Portugal, "Faro", 37.0, -7.9);
+        assert_eq!(ClimateBand::of(&berlin), ClimateBand::CentralEurope);
+        assert_eq!(ClimateBand::of(&madrid), ClimateBand::IberiaNorth);
+        assert_eq!(ClimateBand::of(&seville), ClimateBand::IberiaSouth);
+        assert_eq!(ClimateBand::of(&faro), ClimateBand::IberiaSouth);
+        // other countries by latitude
+        let vienna = location(Country::Austria, "Vienna", 48.2, 16.4);
+        let rome = location(Country::Italy, "Rome", 41.9, 12.5);
+        let athens = location(Country::Other("GR".to_string()), "Athens", 38.0, 23.7);
+        assert_eq!(ClimateBand::of(&vienna), ClimateBand::CentralEurope);
+        assert_eq!(ClimateBand::of(&rome), ClimateBand::IberiaNorth);
+        assert_eq!(ClimateBand::of(&athens), ClimateBand::IberiaSouth);
+
+        let defaults = location_defaults(&madrid, &HeatingType::Floor);
+        assert_eq!(defaults.solar_irradiance.len(), NUM_HOURS);
+        assert_eq!(defaults.hourly_outdoor_temp_c.len(), NUM_HOURS);
+        assert_eq!(defaults.hourly_cop.len(), NUM_HOURS);
+        let expected: f64 = ClimateBand::IberiaNorth.monthly_pv_yield().iter().sum();
+        assert!((defaults.annual_pv_yield() - expected).abs() < 1e-6);
+        let january: f64 = defaults.solar_irradiance[..31 * 24].iter().sum();
+        assert!((january - 80.0).abs() < 1e-6);
+
+        // mid-January mean, daily cycle peaking at 15:00
+        let jan_16 = &defaults.hourly_outdoor_temp_c[15 * 24..16 * 24];
+        let mean = jan_16.iter().sum::<f64>() / 24.0;
+        assert!((mean - 6.0).abs() < 0.1);
+        assert!(jan_16[15] > jan_16[3]);
+        // heat pumps work better in summer and with floor heating
+        assert!(defaults.hourly_cop[4000] > defaults.hourly_cop[15 * 24]);
+        let radiator = location_defaults(&madrid, &HeatingType::Radiator);
+        assert!(defaults.hourly_cop[15 * 24] > radiator.hourly_cop[15 * 24]);
+
+        let berlin_defaults = location_defaults(&berlin, &HeatingType::Floor);
+        assert!(berlin_defaults.annual_pv_yield() < defaults.annual_pv_yield());
+        assert!(berlin_defaults.hourly_outdoor_temp_c[15 * 24] < mean);
+    }
+
+    #[test]
+    fn test_run_first_estimate() {
+        let seville = location(Country::Spain, "Seville", 37.4, -6.0);
+        let results = run_first_estimate(&seville, 4000.0, good_lp::clarabel).unwrap();
+        assert!((results.annual_electricity_demand_kwh - 4000.0).abs() < 1.0);
+        assert!(results.pv_capacity_kw > 0.0);
+        let max_kw = 2.0 * 4000.0 / 1755.0;
+        assert!(results.pv_capacity_kw <= max_kw + 1e-3);
+    }
+
+    #[test]
+    fn test_location_defaults_edge_cases() {
+        // the band limits belong to the northern band
+        let spain = location(Country::Spain, "Madrid", 40.0, -3.7);
+        let italy = location(Country::Italy, "Bologna", 45.0, 11.3);
+        let sydney = location(Country::Other("AU".to_string()), "Sydney", -33.9, 151.2);
+        assert_eq!(ClimateBand::of(&spain), ClimateBand::IberiaNorth);
+        assert_eq!(ClimateBand::of(&italy), ClimateBand::CentralEurope);
+        assert_eq!(ClimateBand::of(&sydney), ClimateBand::IberiaSouth);
+
+        let seville = location(Country::Spain, "Seville", 37.4, -6.0);
+        for consumption in [0.0, -4000.0, f64::NAN, f64::INFINITY] {
+            let err = run_first_estimate(&seville, consumption, good_lp::clarabel).unwrap_err();
+            assert!(err.to_string().contains("Annual consumption"));
+        }
+    }
+}
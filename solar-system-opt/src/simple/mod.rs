@@ -1,3 +1,25 @@
+pub mod battery_binding;
+pub mod billing;
+pub mod capacity_sweep;
+pub mod community_opt;
+pub mod comparison;
+pub mod contracted_power;
+pub mod control_schedule;
+pub mod day_selection;
+pub mod heating_comparison;
+pub mod input_quality;
+pub mod location_defaults;
+#[cfg(feature = "pdf")]
+pub mod pdf_report;
+pub mod peak_shaving;
 pub mod plot;
+pub mod portfolio_opt;
+pub mod regulatory;
+pub mod report;
+#[cfg(feature = "sqlite")]
+pub mod results_store;
 pub mod simple_opt_re;
 pub mod solar_system_utils;
+pub mod staged_plan;
+pub mod typical_year;
+pub mod weather_years;
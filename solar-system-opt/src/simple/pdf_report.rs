@@ -0,0 +1,378 @@
+use std::path::Path;
+
+use printpdf::path::PaintMode;
+use printpdf::{
+    BuiltinFont, Color, IndirectFontRef, Line, Mm, PdfDocument, PdfLayerReference, Point, Rect, Rgb,
+};
+
+use crate::general::finance::{
+    OptimizedROIResult, ROICalculationInput, calculate_annual_savings, calculate_optimized_roi,
+};
+use crate::simple::solar_system_utils::SimpleOptimizationResults;
+
+const PAGE_WIDTH: f32 = 210.0; // A4 in mm
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 20.0;
+const LINE_HEIGHT: f32 = 6.0;
+
+#[derive(Debug, Clone)]
+pub struct PdfReportOptions {
+    pub title: String,
+    pub num_years: usize, // Number of years used for ROI and the payback chart
+    pub other_yearly_cost: f64, // Yearly maintenance etc., in the currency of the cost inputs
+}
+
+impl Default for PdfReportOptions {
+    fn default() -> Self {
+        Self {
+            title: "Solar system quote".to_string(),
+            num_years: 25,
+            other_yearly_cost: 0.0,
+        }
+    }
+}
+
+/// Figures shown on the report, with money already converted into the report currency
+#[derive(Debug)]
+pub struct PdfReportData {
+    pub initial_investment: f64,
+    pub first_year_savings: f64,
+    pub cumulative_cash_flow: Vec<f64>, // Index 0 is the investment, index i the balance after year i
+    pub roi: OptimizedROIResult,
+}
+
+impl PdfReportData {
+    pub fn new(
+        results: &SimpleOptimizationResults,
+        options: &PdfReportOptions,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let input = ROICalculationInput::from(results.clone());
+        let currency = &results.config.currency;
+        let savings =
+            calculate_annual_savings(&input, options.num_years, options.other_yearly_cost);
+        let initial_investment = input.initial_investment();
+
+        let mut cumulative_cash_flow = Vec::with_capacity(savings.len() + 1);
+        let mut balance = -initial_investment;
+        cumulative_cash_flow.push(currency.convert(balance));
+        for saving in &savings {
+            balance += saving;
+            cumulative_cash_flow.push(currency.convert(balance));
+        }
+
+        Ok(Self {
+            initial_investment: currency.convert(initial_investment),
+            first_year_savings: currency.convert(savings.first().copied().unwrap_or(0.0)),
+            cumulative_cash_flow,
+            roi: calculate_optimized_roi(input, options.num_years, options.other_yearly_cost)?,
+        })
+    }
+}
+
+/// Writes a customer-ready one-page PDF with system sizing, savings, ROI, a payback chart and
+/// the assumptions the figures are based on.
+pub fn write_pdf_report(
+    results: &SimpleOptimizationResults,
+    options: &PdfReportOptions,
+    path: impl AsRef<Path>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let data = PdfReportData::new(results, options)?;
+    let bytes = render_pdf_report(results, options, &data)?;
+    std::fs::write(path, bytes)?;
+    Ok(())
+}
+
+/// Renders the report into PDF bytes
+pub fn render_pdf_report(
+    results: &SimpleOptimizationResults,
+    options: &PdfReportOptions,
+    data: &PdfReportData,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let (doc, page, layer) = PdfDocument::new(
+        options.title.as_str(),
+        Mm(PAGE_WIDTH),
+        Mm(PAGE_HEIGHT),
+        "Report",
+    );
+    let layer = doc.get_page(page).get_layer(layer);
+    let font = doc.add_builtin_font(BuiltinFont::Helvetica)?;
+    let bold = doc.add_builtin_font(BuiltinFont::HelveticaBold)?;
+    let config = &results.config;
+    let currency = &config.currency;
+
+    let mut y = PAGE_HEIGHT - MARGIN;
+    layer.use_text(options.title.as_str(), 20.0, Mm(MARGIN), Mm(y), &bold);
+    y -= 2.0 * LINE_HEIGHT;
+
+    let section = |title: &str, rows: &[(String, String)], y: &mut f32| {
+        layer.use_text(title, 13.0, Mm(MARGIN), Mm(*y), &bold);
+        *y -= LINE_HEIGHT;
+        for (label, value) in rows {
+            layer.use_text(label.as_str(), 10.0, Mm(MARGIN + 4.0), Mm(*y), &font);
+            layer.use_text(value.as_str(), 10.0, Mm(MARGIN + 80.0), Mm(*y), &font);
+            *y -= LINE_HEIGHT;
+        }
+        *y -= LINE_HEIGHT / 2.0;
+    };
+
+    let mut sizing = vec![
+        (
+            "PV capacity".to_string(),
+            format!("{:.2} kWp", results.pv_capacity_kw),
+        ),
+        (
+            "Battery capacity".to_string(),
+            format!("{:.2} kWh", results.battery_capacity_kwh),
+        ),
+    ];
+    if config.wind_turbine.is_some() {
+        sizing.push((
+            "Wind turbine".to_string(),
+            format!("{:.2} kW", results.wind_capacity_kw),
+        ));
+    }
+    if config.existing_system.is_some() {
+        sizing.push((
+            "Existing PV / battery".to_string(),
+            format!(
+                "{:.2} kWp / {:.2} kWh",
+                results.existing_pv_capacity_kw, results.existing_battery_capacity_kwh
+            ),
+        ));
+    }
+    section("System sizing", &sizing, &mut y);
+
+    section(
+        "Annual energy",
+        &[
+            (
+                "Electricity demand".to_string(),
+                format!("{:.0} kWh", results.annual_electricity_demand_kwh),
+            ),
+            (
+                "PV production".to_string(),
+                format!("{:.0} kWh", results.annual_pv_production_kwh),
+            ),
+            (
+                "Grid consumption".to_string(),
+                format!("{:.0} kWh", results.annual_grid_energy_kwh),
+            ),
+            (
+                "Autarky".to_string(),
+                format!("{:.1} %", results.autarky.percent()),
+            ),
+        ],
+        &mut y,
+    );
+
+    let payback = |payback_period: Option<f64>| match payback_period {
+        Some(years) => format!("{:.1} years", years),
+        None => format!("not within {} years", options.num_years),
+    };
+    section(
+        "Economics",
+        &[
+            (
+                "Investment".to_string(),
+                currency.format(data.initial_investment),
+            ),
+            (
+                "Savings in the first year".to_string(),
+                currency.format(data.first_year_savings),
+            ),
+            (
+                "Return on investment (nominal)".to_string(),
+                format!("{:.1} %", data.roi.roi * 100.0),
+            ),
+            (
+                "Return on investment (real)".to_string(),
+                format!("{:.1} %", data.roi.real_roi * 100.0),
+            ),
+            (
+                "Net present value (nominal)".to_string(),
+                currency.format(data.roi.net_present_value),
+            ),
+            (
+                "Net present value (real)".to_string(),
+                currency.format(data.roi.real_net_present_value),
+            ),
+            (
+                "Payback period (nominal)".to_string(),
+                payback(data.roi.payback_period),
+            ),
+            (
+                "Payback period (real)".to_string(),
+                payback(data.roi.real_payback_period),
+            ),
+        ],
+        &mut y,
+    );
+
+    layer.use_text("Cumulative cash flow", 13.0, Mm(MARGIN), Mm(y), &bold);
+    y -= LINE_HEIGHT;
+    let chart_height = 55.0;
+    draw_payback_chart(
+        &layer,
+        &font,
+        &data.cumulative_cash_flow,
+        MARGIN,
+        y - chart_height,
+        PAGE_WIDTH - 2.0 * MARGIN,
+        chart_height,
+    );
+    y -= chart_height + 2.0 * LINE_HEIGHT;
+
+    let mut assumptions = vec![
+        (
+            "PV investment".to_string(),
+            format!("{}/kWp", currency.format(currency.convert(config.inv_pv))),
+        ),
+        (
+            "Battery investment".to_string(),
+            format!("{}/kWh", currency.format(currency.convert(config.inv_bat))),
+        ),
+        (
+            "Electricity price".to_string(),
+            format!("{}/kWh", currency.format(currency.convert(config.fc_grid))),
+        ),
+        (
+            "Feed-in tariff".to_string(),
+            format!(
+                "{}/kWh",
+                currency.format(currency.convert(config.feed_in_tariff))
+            ),
+        ),
+        (
+            "Electricity price increase".to_string(),
+            format!(
+                "{:.1} % per year",
+                config.electricity_price_increase * 100.0
+            ),
+        ),
+        (
+            "Inflation".to_string(),
+            format!("{:.1} % per year", config.inflation_rate * 100.0),
+        ),
+        (
+            "Other yearly costs".to_string(),
+            currency.format(currency.convert(options.other_yearly_cost)),
+        ),
+        (
+            "Calculation period".to_string(),
+            format!("{} years", options.num_years),
+        ),
+    ];
+    if let Some(rate) = currency.conversion_rate {
+        assumptions.push((
+            "Exchange rate".to_string(),
+            format!("1 EUR = {:.4} {}", rate, currency.code),
+        ));
+    }
+    section("Assumptions", &assumptions, &mut y);
+
+    Ok(doc.save_to_bytes()?)
+}
+
+/// Bar chart of the cumulative cash flow, red while the system has not paid back yet
+fn draw_payback_chart(
+    layer: &PdfLayerReference,
+    font: &IndirectFontRef,
+    cumulative_cash_flow: &[f64],
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+) {
+    if cumulative_cash_flow.is_empty() {
+        return;
+    }
+    let max = cumulative_cash_flow.iter().copied().fold(0.0f64, f64::max);
+    let min = cumulative_cash_flow.iter().copied().fold(0.0f64, f64::min);
+    let range = (max - min).max(1e-6);
+    let to_y = |value: f64| y + ((value - min) / range) as f32 * height;
+    let zero_y = to_y(0.0);
+
+    let slot = width / cumulative_cash_flow.len() as f32;
+    for (year, &value) in cumulative_cash_flow.iter().enumerate() {
+        let color = if value >= 0.0 {
+            Rgb::new(0.2, 0.6, 0.3, None)
+        } else {
+            Rgb::new(0.8, 0.25, 0.2, None)
+        };
+        layer.set_fill_color(Color::Rgb(color));
+        let left = x + year as f32 * slot + slot * 0.15;
+        let right = left + slot * 0.7;
+        let top = to_y(value);
+        let rect = Rect::new(
+            Mm(left),
+            Mm(zero_y.min(top)),
+            Mm(right),
+            Mm(zero_y.max(top)),
+        )
+        .with_mode(PaintMode::Fill);
+        layer.add_rect(rect);
+
+        if year % 5 == 0 {
+            layer.set_fill_color(Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None)));
+            layer.use_text(year.to_string(), 7.0, Mm(left), Mm(y - 4.0), font);
+        }
+    }
+
+    layer.set_outline_color(Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None)));
+    layer.set_outline_thickness(0.5);
+    layer.add_line(Line {
+        points: vec![
+            (Point::new(Mm(x), Mm(zero_y)), false),
+            (Point::new(Mm(x + width), Mm(zero_y)), false),
+        ],
+        is_closed: false,
+    });
+    layer.set_fill_color(Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::general::units::Ratio;
+    use crate::simple::solar_system_utils::OptimizationConfig;
+
+    fn example_results() -> SimpleOptimizationResults {
+        SimpleOptimizationResults {
+            pv_capacity_kw: 5.0,
+            battery_capacity_kwh: 5.0,
+            annual_electricity_demand_kwh: 4173.44,
+            annual_pv_production_kwh: 2800.0,
+            annual_grid_energy_kwh: 1800.0,
+            autarky: Ratio::from_percent(56.9),
+            config: OptimizationConfig::default(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_pdf_report_data() {
+        let results = example_results();
+        let options = PdfReportOptions::default();
+        let data = PdfReportData::new(&results, &options).unwrap();
+
+        let config = &results.config;
+        let investment = 5.0 * config.inv_pv + 5.0 * config.inv_bat;
+        assert!((data.initial_investment - investment).abs() < 1e-9);
+        assert_eq!(data.cumulative_cash_flow.len(), options.num_years + 1);
+        assert_eq!(data.cumulative_cash_flow[0], -investment);
+        let expected_savings =
+            config.fc_grid * (config.electricity_usage.kwh() - results.annual_grid_energy_kwh);
+        assert!((data.first_year_savings - expected_savings).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_write_pdf_report() {
+        let results = example_results();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.pdf");
+        write_pdf_report(&results, &PdfReportOptions::default(), &path).unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert!(bytes.starts_with(b"%PDF"));
+    }
+}
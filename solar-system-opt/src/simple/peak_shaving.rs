@@ -0,0 +1,202 @@
+use ems_model::building::electricity::ElectricityRate;
+use good_lp::Solver;
+
+use crate::general::calendar::MONTHS;
+use crate::general::calendar::NUM_HOURS;
+use crate::simple::simple_opt_re::run_simple_opt;
+use crate::simple::solar_system_utils::{
+    DemandCharges, OptimizationConfig, SimpleOptimizationResults,
+};
+
+/// Import peak of a month without and with the peak shaving battery
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeakShavingMonth {
+    pub month: usize,          // 1-12
+    pub unshaved_peak_kw: f64, // Peak of the demand without battery
+    pub billed_peak_kw: f64,   // Peak of the grid import with the battery
+    pub charge_saving: f64,    // Saved demand charge and excess power penalty
+}
+
+/// Battery sized for peak shaving and the monthly peaks it achieves
+#[derive(Debug, Clone)]
+pub struct PeakShavingResults {
+    pub battery_capacity_kwh: f64,
+    pub months: Vec<PeakShavingMonth>,
+    pub annual_charge_saving: f64, // Demand charges and penalties saved over the year
+    pub results: SimpleOptimizationResults,
+}
+
+impl PeakShavingResults {
+    /// Renders the monthly peaks as a plain text table
+    pub fn render(&self) -> String {
+        let mut lines = vec![
+            format!(
+                "Peak shaving battery: {:.2} kWh, annual saving {:.2}",
+                self.battery_capacity_kwh, self.annual_charge_saving
+            ),
+            format!(
+                "{:<10} {:>14} {:>14} {:>10}",
+                "Month", "Unshaved (kW)", "Billed (kW)", "Saving"
+            ),
+        ];
+        for month in &self.months {
+            lines.push(format!(
+                "{:<10} {:>14.2} {:>14.2} {:>10.2}",
+                MONTHS[month.month - 1],
+                month.unshaved_peak_kw,
+                month.billed_peak_kw,
+                month.charge_saving
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Industrial mode: sizes a battery purely for peak shaving of the `electricity_demand` (e.g.
+/// a factory profile) under the `demand_charges` of the config. PV, wind and an existing
+/// system are left out, so the battery charges from the grid in hours below the monthly peak.
+pub fn run_peak_shaving<S: Solver>(
+    config: OptimizationConfig,
+    electricity_demand: Vec<f64>,
+    electricity_rate: ElectricityRate,
+    solver: S,
+) -> Result<PeakShavingResults, Box<dyn std::error::Error>> {
+    let Some(demand_charges) = config.demand_charges.clone() else {
+        return Err("Peak shaving needs demand charges in the config".into());
+    };
+    let config = OptimizationConfig {
+        pv_fixed: true,
+        existing_system: None,
+        wind_turbine: None,
+        ..config
+    };
+    let results = run_simple_opt(
+        config,
+        0.0,
+        vec![0.0; NUM_HOURS],
+        electricity_demand,
+        electricity_rate,
+        solver,
+    )?;
+
+    let unshaved = DemandCharges::monthly_peaks_kw(&results.hourly_total_electricity_demand);
+    let cost = |peak_kw: f64| {
+        let (charge, penalty) = demand_charges.monthly_cost(peak_kw);
+        results.config.currency.convert(charge + penalty)
+    };
+    let months: Vec<PeakShavingMonth> = unshaved
+        .iter()
+        .zip(&results.monthly_billed_peaks_kw)
+        .enumerate()
+        .map(
+            |(month, (&unshaved_peak_kw, &billed_peak_kw))| PeakShavingMonth {
+                month: month + 1,
+                unshaved_peak_kw,
+                billed_peak_kw,
+                charge_saving: cost(unshaved_peak_kw) - cost(billed_peak_kw),
+            },
+        )
+        .collect();
+    Ok(PeakShavingResults {
+        battery_capacity_kwh: results.battery_capacity_kwh,
+        annual_charge_saving: months.iter().map(|month| month.charge_saving).sum(),
+        months,
+        results,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simple::solar_system_utils::load_demand_from_csv;
+
+    #[test]
+    fn test_run_peak_shaving() {
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+        let config = OptimizationConfig {
+            demand_charges: Some(DemandCharges {
+                price_per_kw_month: 15.0,
+                contracted_kw: 1.0,
+                excess_price_per_kw: 30.0,
+            }),
+            ..Default::default()
+        };
+        let shaving = run_peak_shaving(
+            config,
+            electricity_demand,
+            ElectricityRate::fixed(0.3),
+            good_lp::clarabel,
+        )
+        .unwrap();
+
+        assert!(shaving.battery_capacity_kwh > 0.0);
+        assert!(shaving.results.pv_capacity_kw.abs() < 1e-6);
+        assert_eq!(shaving.months.len(), 12);
+        for month in &shaving.months {
+            assert!(month.billed_peak_kw <= month.unshaved_peak_kw + 1e-6);
+        }
+        assert!(shaving.annual_charge_saving > 0.0);
+        let charges =
+            shaving.results.annual_demand_charge + shaving.results.annual_excess_power_penalty;
+        let unshaved: f64 = shaving
+            .months
+            .iter()
+            .map(|month| {
+                let (charge, penalty) = DemandCharges {
+                    price_per_kw_month: 15.0,
+                    contracted_kw: 1.0,
+                    excess_price_per_kw: 30.0,
+                }
+                .monthly_cost(month.unshaved_peak_kw);
+                charge + penalty
+            })
+            .sum();
+        assert!((unshaved - charges - shaving.annual_charge_saving).abs() < 1e-6);
+        assert!(shaving.render().contains("Jan"));
+    }
+
+    #[test]
+    fn test_run_peak_shaving_rejects_invalid_charges() {
+        let shave = |demand_charges: Option<DemandCharges>| {
+            run_peak_shaving(
+                OptimizationConfig {
+                    demand_charges,
+                    ..Default::default()
+                },
+                vec![1000.0; NUM_HOURS],
+                ElectricityRate::fixed(0.3),
+                good_lp::clarabel,
+            )
+        };
+        let err = shave(None).unwrap_err();
+        assert!(err.to_string().contains("demand charges"));
+
+        let charges = DemandCharges {
+            price_per_kw_month: 15.0,
+            contracted_kw: 1.0,
+            excess_price_per_kw: 30.0,
+        };
+        for broken in [
+            DemandCharges {
+                price_per_kw_month: -1.0,
+                ..charges.clone()
+            },
+            DemandCharges {
+                contracted_kw: f64::NAN,
+                ..charges.clone()
+            },
+            DemandCharges {
+                excess_price_per_kw: f64::INFINITY,
+                ..charges.clone()
+            },
+        ] {
+            assert!(shave(Some(broken)).is_err());
+        }
+
+        // peaks of a short series only cover the months it reaches
+        let peaks = DemandCharges::monthly_peaks_kw(&[2000.0; 31 * 24 + 24]);
+        assert_eq!(peaks.len(), 12);
+        assert_eq!(&peaks[..2], &[2.0, 2.0]);
+        assert!(peaks[2..].iter().all(|&peak| peak == 0.0));
+    }
+}
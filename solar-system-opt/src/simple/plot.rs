@@ -1,7 +1,289 @@
+use plotters::chart::MeshStyle;
+use plotters::coord::Shift;
+use plotters::coord::ranged1d::{Ranged, ValueFormatter};
+use plotters::coord::types::RangedCoordf64;
 use plotters::prelude::*;
+use serde::Serialize;
 use std::collections::HashMap;
-
+use std::path::Path;
+use tracing::info;
+
+use crate::general::calendar::DAYS_IN_MONTH;
+use crate::simple::capacity_sweep::CapacityMap;
+use crate::simple::comparison::{ComparisonReport, KpiDelta, KpiKind};
+use crate::simple::day_selection::WorstWeek;
+#[allow(deprecated)]
 use crate::simple::solar_system_utils::OptimizationResults;
+use crate::simple::solar_system_utils::SimpleOptimizationResults;
+
+/// File format the plots are written in
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PlotFormat {
+    #[default]
+    Png,
+    Svg, // Vector output for web UIs and print documents
+}
+
+impl PlotFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            PlotFormat::Png => "png",
+            PlotFormat::Svg => "svg",
+        }
+    }
+}
+
+/// Colors used for background, text, grid lines and data series
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlotTheme {
+    pub background: RGBColor,
+    pub foreground: RGBColor, // Text, axes and legend border
+    pub grid: RGBColor,
+    pub series: [RGBColor; 5], // Data series colors in drawing order
+}
+
+impl PlotTheme {
+    pub fn light() -> Self {
+        Self {
+            background: WHITE,
+            foreground: BLACK,
+            grid: BLACK,
+            series: [RED, BLUE, GREEN, MAGENTA, RGBColor(255, 165, 0)],
+        }
+    }
+
+    pub fn dark() -> Self {
+        Self {
+            background: RGBColor(30, 30, 30),
+            foreground: RGBColor(230, 230, 230),
+            grid: RGBColor(200, 200, 200),
+            series: [
+                RGBColor(255, 99, 99),
+                RGBColor(100, 160, 255),
+                RGBColor(90, 220, 120),
+                RGBColor(230, 110, 230),
+                RGBColor(255, 185, 60),
+            ],
+        }
+    }
+}
+
+impl Default for PlotTheme {
+    fn default() -> Self {
+        Self::light()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlotOptions {
+    pub format: PlotFormat,
+    pub size: Option<(u32, u32)>, // Width and height in pixels at 96 DPI; None uses the size of each plot
+    pub dpi: u32, // Scales canvas, fonts and line widths, 96 keeps the plot size in pixels
+    pub theme: PlotTheme,
+    pub data_format: Option<PlotDataFormat>, // Also writes the plotted series next to the image
+}
+
+impl Default for PlotOptions {
+    fn default() -> Self {
+        Self {
+            format: PlotFormat::Png,
+            size: None,
+            dpi: 96,
+            theme: PlotTheme::light(),
+            data_format: None,
+        }
+    }
+}
+
+/// File format of the plot data written next to an image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlotDataFormat {
+    Json,
+    Csv,
+}
+
+impl PlotDataFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            PlotDataFormat::Json => "json",
+            PlotDataFormat::Csv => "csv",
+        }
+    }
+}
+
+/// Values along the x axis of a plot: numbers, or category names for bar plots
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum PlotAxis {
+    Values(Vec<f64>),
+    Labels(Vec<String>),
+}
+
+impl PlotAxis {
+    /// Index values 0..len, e.g. the hours of a profile
+    pub fn indices(len: usize) -> Self {
+        PlotAxis::Values((0..len).map(|i| i as f64).collect())
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            PlotAxis::Values(values) => values.len(),
+            PlotAxis::Labels(labels) => labels.len(),
+        }
+    }
+
+    fn cell(&self, i: usize) -> String {
+        match self {
+            PlotAxis::Values(values) => values.get(i).map(|v| v.to_string()).unwrap_or_default(),
+            PlotAxis::Labels(labels) => labels.get(i).map(|l| csv_field(l)).unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlotSeries {
+    pub name: String,
+    pub values: Vec<f64>,
+}
+
+/// The series of a plot as drawn, so other tools can re-render it without re-running the
+/// optimization
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlotData {
+    pub title: String,
+    pub x_label: String,
+    pub x: PlotAxis,
+    pub series: Vec<PlotSeries>,
+}
+
+impl PlotData {
+    pub fn new(title: &str, x_label: &str, x: PlotAxis) -> Self {
+        Self {
+            title: title.to_string(),
+            x_label: x_label.to_string(),
+            x,
+            series: Vec::new(),
+        }
+    }
+
+    pub fn with_series(mut self, name: &str, values: &[f64]) -> Self {
+        self.series.push(PlotSeries {
+            name: name.to_string(),
+            values: values.to_vec(),
+        });
+        self
+    }
+
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// One column for the x axis and one per series; shorter series leave their cells empty
+    pub fn to_csv(&self) -> String {
+        let rows = self
+            .series
+            .iter()
+            .map(|series| series.values.len())
+            .fold(self.x.len(), usize::max);
+        let mut csv = std::iter::once(csv_field(&self.x_label))
+            .chain(self.series.iter().map(|series| csv_field(&series.name)))
+            .collect::<Vec<_>>()
+            .join(",");
+        csv.push('\n');
+        for i in 0..rows {
+            let row: Vec<String> = std::iter::once(self.x.cell(i))
+                .chain(self.series.iter().map(|series| {
+                    series
+                        .values
+                        .get(i)
+                        .map(|v| v.to_string())
+                        .unwrap_or_default()
+                }))
+                .collect();
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+
+    /// Writes the data next to `image_filename` with the same basename and returns the path
+    pub fn write_next_to(
+        &self,
+        image_filename: &str,
+        format: PlotDataFormat,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let path = Path::new(image_filename).with_extension(format.extension());
+        let content = match format {
+            PlotDataFormat::Json => self.to_json()?,
+            PlotDataFormat::Csv => self.to_csv(),
+        };
+        std::fs::write(&path, content)?;
+        Ok(path.to_string_lossy().into_owned())
+    }
+}
+
+/// Quotes a CSV field if it contains a separator, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Writes the plot data next to the image if `options.data_format` is set
+fn export_plot_data(
+    filename: &str,
+    options: &PlotOptions,
+    data: impl FnOnce() -> PlotData,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(format) = options.data_format {
+        let path = data().write_next_to(filename, format)?;
+        info!("Plot data saved as {}", path);
+    }
+    Ok(())
+}
+
+impl PlotOptions {
+    fn scale(&self) -> f64 {
+        self.dpi as f64 / 96.0
+    }
+
+    /// Scales a length given at 96 DPI to the configured DPI
+    fn px(&self, value: u32) -> u32 {
+        ((value as f64 * self.scale()).round() as u32).max(1)
+    }
+
+    fn canvas_size(&self, default_size: (u32, u32)) -> (u32, u32) {
+        let (width, height) = self.size.unwrap_or(default_size);
+        (self.px(width), self.px(height))
+    }
+
+    fn font(&self, size: u32) -> TextStyle<'static> {
+        ("sans-serif", self.px(size))
+            .into_font()
+            .color(&self.theme.foreground)
+    }
+
+    /// Short horizontal legend line starting at `(x, y)`
+    fn legend_line(&self, x: i32, y: i32, length: u32) -> Vec<(i32, i32)> {
+        vec![(x, y), (x + self.px(length) as i32, y)]
+    }
+
+    /// Applies the theme to the axes, grid and labels of a mesh
+    fn style_mesh<X, Y, DB>(&self, mesh: &mut MeshStyle<'_, '_, X, Y, DB>)
+    where
+        X: Ranged<ValueType = f64> + ValueFormatter<f64>,
+        Y: Ranged<ValueType = f64> + ValueFormatter<f64>,
+        DB: DrawingBackend,
+    {
+        mesh.axis_style(self.theme.foreground)
+            .bold_line_style(self.theme.grid.mix(0.3))
+            .light_line_style(self.theme.grid.mix(0.1))
+            .label_style(self.font(12))
+            .axis_desc_style(self.font(14));
+    }
+}
 
 // Equivalent to plot_data1 function
 pub fn plot_data1(
@@ -11,44 +293,114 @@ pub fn plot_data1(
     y_axis: &str,
     filename: &str,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let root = BitMapBackend::new(filename, (800, 600)).into_drawing_area();
-    root.fill(&WHITE)?;
+    plot_data1_with_options(
+        data,
+        title,
+        x_axis,
+        y_axis,
+        filename,
+        &PlotOptions::default(),
+    )
+}
+
+pub fn plot_data1_with_options(
+    data: &[f64],
+    title: &str,
+    x_axis: &str,
+    y_axis: &str,
+    filename: &str,
+    options: &PlotOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let size = options.canvas_size((800, 600));
+    match options.format {
+        PlotFormat::Png => draw_data1(
+            BitMapBackend::new(filename, size).into_drawing_area(),
+            data,
+            title,
+            x_axis,
+            y_axis,
+            options,
+        )?,
+        PlotFormat::Svg => draw_data1(
+            SVGBackend::new(filename, size).into_drawing_area(),
+            data,
+            title,
+            x_axis,
+            y_axis,
+            options,
+        )?,
+    }
+    info!("Plot saved as {}", filename);
+    export_plot_data(filename, options, || {
+        PlotData::new(title, x_axis, PlotAxis::indices(data.len())).with_series(y_axis, data)
+    })?;
+    Ok(())
+}
+
+fn draw_data1<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    data: &[f64],
+    title: &str,
+    x_axis: &str,
+    y_axis: &str,
+    options: &PlotOptions,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let theme = &options.theme;
+    root.fill(&theme.background)?;
 
     let mut chart = ChartBuilder::on(&root)
-        .caption(title, ("sans-serif", 30))
-        .margin(20)
-        .x_label_area_size(40)
-        .y_label_area_size(60)
+        .caption(title, options.font(30))
+        .margin(options.px(20))
+        .x_label_area_size(options.px(40))
+        .y_label_area_size(options.px(60))
         .build_cartesian_2d(
             0f64..data.len() as f64,
             data.iter().fold(f64::INFINITY, |a, &b| a.min(b))
                 ..data.iter().fold(f64::NEG_INFINITY, |a, &b| a.max(b)),
         )?;
 
-    chart
-        .configure_mesh()
-        .x_desc(x_axis)
-        .y_desc(y_axis)
-        .draw()?;
+    let mut mesh = chart.configure_mesh();
+    options.style_mesh(&mut mesh);
+    mesh.x_desc(x_axis).y_desc(y_axis).draw()?;
 
+    let color = theme.series[0];
     chart
         .draw_series(LineSeries::new(
             data.iter().enumerate().map(|(i, &y)| (i as f64, y)),
-            &RED,
+            color.stroke_width(options.px(1)),
         ))?
         .label("Data")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 10, y)], RED));
+        .legend(move |(x, y)| PathElement::new(options.legend_line(x, y, 10), color));
 
     chart.draw_series(PointSeries::of_element(
         data.iter().enumerate().map(|(i, &y)| (i as f64, y)),
-        5,
-        &RED,
+        options.px(5),
+        &color,
         &|c, s, st| EmptyElement::at(c) + Circle::new((0, 0), s, st.filled()),
     ))?;
 
-    chart.configure_series_labels().draw()?;
+    draw_series_labels(&mut chart, options)?;
     root.present()?;
-    println!("Plot saved as {}", filename);
+    Ok(())
+}
+
+/// Draws the legend of a chart in the theme colors
+fn draw_series_labels<'a, DB: DrawingBackend + 'a>(
+    chart: &mut ChartContext<'a, DB, Cartesian2d<RangedCoordf64, RangedCoordf64>>,
+    options: &PlotOptions,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    DB::ErrorType: 'static,
+{
+    chart
+        .configure_series_labels()
+        .label_font(options.font(12))
+        .background_style(options.theme.background.mix(0.8))
+        .border_style(options.theme.foreground)
+        .draw()?;
     Ok(())
 }
 
@@ -60,8 +412,71 @@ pub fn plot_data2(
     sup_pv: &[f64],
     filename: &str,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let root = BitMapBackend::new(filename, (800, 1000)).into_drawing_area();
-    root.fill(&WHITE)?;
+    plot_data2_with_options(
+        dem_elec,
+        dem_charge,
+        sup_grid,
+        sup_pv,
+        filename,
+        &PlotOptions::default(),
+    )
+}
+
+pub fn plot_data2_with_options(
+    dem_elec: &[f64],
+    dem_charge: &[f64],
+    sup_grid: &[f64],
+    sup_pv: &[f64],
+    filename: &str,
+    options: &PlotOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let size = options.canvas_size((800, 1000));
+    match options.format {
+        PlotFormat::Png => draw_data2(
+            BitMapBackend::new(filename, size).into_drawing_area(),
+            dem_elec,
+            dem_charge,
+            sup_grid,
+            sup_pv,
+            options,
+        )?,
+        PlotFormat::Svg => draw_data2(
+            SVGBackend::new(filename, size).into_drawing_area(),
+            dem_elec,
+            dem_charge,
+            sup_grid,
+            sup_pv,
+            options,
+        )?,
+    }
+    info!("Plot saved as {}", filename);
+    export_plot_data(filename, options, || {
+        PlotData::new(
+            "Demand and Supply",
+            "Index",
+            PlotAxis::indices(dem_elec.len()),
+        )
+        .with_series("dem_elec", dem_elec)
+        .with_series("dem_charge", dem_charge)
+        .with_series("GRID", sup_grid)
+        .with_series("PV", sup_pv)
+    })?;
+    Ok(())
+}
+
+fn draw_data2<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    dem_elec: &[f64],
+    dem_charge: &[f64],
+    sup_grid: &[f64],
+    sup_pv: &[f64],
+    options: &PlotOptions,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let theme = &options.theme;
+    root.fill(&theme.background)?;
 
     let areas = root.split_evenly((2, 1));
     let upper = &areas[0];
@@ -69,10 +484,10 @@ pub fn plot_data2(
 
     // First subplot: demand comparison
     let mut chart1 = ChartBuilder::on(upper)
-        .caption("Demand Comparison", ("sans-serif", 30))
-        .margin(20)
-        .x_label_area_size(40)
-        .y_label_area_size(60)
+        .caption("Demand Comparison", options.font(30))
+        .margin(options.px(20))
+        .x_label_area_size(options.px(40))
+        .y_label_area_size(options.px(60))
         .build_cartesian_2d(
             0f64..dem_elec.len() as f64,
             0f64..dem_elec
@@ -81,34 +496,33 @@ pub fn plot_data2(
                 .fold(0f64, |a, &b| a.max(b)),
         )?;
 
-    chart1
-        .configure_mesh()
-        .x_desc("Index")
-        .y_desc("Value")
-        .draw()?;
+    let mut mesh = chart1.configure_mesh();
+    options.style_mesh(&mut mesh);
+    mesh.x_desc("Index").y_desc("Value").draw()?;
 
+    let [red, blue, green, magenta, _] = theme.series;
     chart1
         .draw_series(dem_elec.iter().enumerate().map(|(i, &y)| {
-            Rectangle::new([(i as f64 - 0.2, 0.0), (i as f64 + 0.2, y)], BLUE.filled())
+            Rectangle::new([(i as f64 - 0.2, 0.0), (i as f64 + 0.2, y)], blue.filled())
         }))?
         .label("dem_elec")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 10, y)], BLUE));
+        .legend(move |(x, y)| PathElement::new(options.legend_line(x, y, 10), blue));
 
     chart1
         .draw_series(dem_charge.iter().enumerate().map(|(i, &y)| {
-            Rectangle::new([(i as f64 + 0.2, 0.0), (i as f64 + 0.6, y)], GREEN.filled())
+            Rectangle::new([(i as f64 + 0.2, 0.0), (i as f64 + 0.6, y)], green.filled())
         }))?
         .label("dem_charge")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 10, y)], GREEN));
+        .legend(move |(x, y)| PathElement::new(options.legend_line(x, y, 10), green));
 
-    chart1.configure_series_labels().draw()?;
+    draw_series_labels(&mut chart1, options)?;
 
     // Second subplot: supply comparison
     let mut chart2 = ChartBuilder::on(lower)
-        .caption("Supply Comparison", ("sans-serif", 30))
-        .margin(20)
-        .x_label_area_size(40)
-        .y_label_area_size(60)
+        .caption("Supply Comparison", options.font(30))
+        .margin(options.px(20))
+        .x_label_area_size(options.px(40))
+        .y_label_area_size(options.px(60))
         .build_cartesian_2d(
             0f64..sup_grid.len() as f64,
             0f64..sup_grid
@@ -117,33 +531,30 @@ pub fn plot_data2(
                 .fold(0f64, |a, &b| a.max(b)),
         )?;
 
-    chart2
-        .configure_mesh()
-        .x_desc("Index")
-        .y_desc("Value")
-        .draw()?;
+    let mut mesh = chart2.configure_mesh();
+    options.style_mesh(&mut mesh);
+    mesh.x_desc("Index").y_desc("Value").draw()?;
 
     chart2
         .draw_series(sup_grid.iter().enumerate().map(|(i, &y)| {
-            Rectangle::new([(i as f64 - 0.2, 0.0), (i as f64 + 0.2, y)], RED.filled())
+            Rectangle::new([(i as f64 - 0.2, 0.0), (i as f64 + 0.2, y)], red.filled())
         }))?
         .label("GRID")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 10, y)], RED));
+        .legend(move |(x, y)| PathElement::new(options.legend_line(x, y, 10), red));
 
     chart2
         .draw_series(sup_pv.iter().enumerate().map(|(i, &y)| {
             Rectangle::new(
                 [(i as f64 + 0.2, 0.0), (i as f64 + 0.6, y)],
-                MAGENTA.filled(),
+                magenta.filled(),
             )
         }))?
         .label("PV")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 10, y)], MAGENTA));
+        .legend(move |(x, y)| PathElement::new(options.legend_line(x, y, 10), magenta));
 
-    chart2.configure_series_labels().draw()?;
+    draw_series_labels(&mut chart2, options)?;
 
     root.present()?;
-    println!("Plot saved as {}", filename);
     Ok(())
 }
 
@@ -153,20 +564,69 @@ pub fn plot_result1(
     pv_capacity: &[f64],
     filename: &str,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let root = BitMapBackend::new(filename, (800, 1200)).into_drawing_area();
-    root.fill(&WHITE)?;
+    plot_result1_with_options(results, pv_capacity, filename, &PlotOptions::default())
+}
+
+pub fn plot_result1_with_options(
+    results: &HashMap<String, Vec<f64>>,
+    pv_capacity: &[f64],
+    filename: &str,
+    options: &PlotOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let size = options.canvas_size((800, 1200));
+    match options.format {
+        PlotFormat::Png => draw_result1(
+            BitMapBackend::new(filename, size).into_drawing_area(),
+            results,
+            pv_capacity,
+            options,
+        )?,
+        PlotFormat::Svg => draw_result1(
+            SVGBackend::new(filename, size).into_drawing_area(),
+            results,
+            pv_capacity,
+            options,
+        )?,
+    }
+    info!("Plot saved as {}", filename);
+    export_plot_data(filename, options, || {
+        let data = PlotData::new(
+            "Results vs PV-Capacity",
+            "PV-Capacity [kW]",
+            PlotAxis::Values(pv_capacity.to_vec()),
+        );
+        ["PV", "GRID", "OP", "OBJEC"]
+            .iter()
+            .filter_map(|key| results.get(*key).map(|values| (key, values)))
+            .fold(data, |data, (key, values)| data.with_series(key, values))
+    })?;
+    Ok(())
+}
+
+fn draw_result1<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    results: &HashMap<String, Vec<f64>>,
+    pv_capacity: &[f64],
+    options: &PlotOptions,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let theme = &options.theme;
+    root.fill(&theme.background)?;
 
     let areas = root.split_evenly((3, 1));
     let upper = &areas[0];
     let middle = &areas[1];
     let lower = &areas[2];
+    let [red, blue, green, _, orange] = theme.series;
 
     // Plot 1: PV and GRID production
     let mut chart1 = ChartBuilder::on(upper)
-        .caption("Energy vs PV-Capacity", ("sans-serif", 25))
-        .margin(15)
-        .x_label_area_size(40)
-        .y_label_area_size(60)
+        .caption("Energy vs PV-Capacity", options.font(25))
+        .margin(options.px(15))
+        .x_label_area_size(options.px(40))
+        .y_label_area_size(options.px(60))
         .build_cartesian_2d(
             pv_capacity[0]..pv_capacity[pv_capacity.len() - 1],
             results["PV"]
@@ -179,9 +639,9 @@ pub fn plot_result1(
                     .fold(f64::NEG_INFINITY, |a, &b| a.max(b)),
         )?;
 
-    chart1
-        .configure_mesh()
-        .x_desc("PV-Capacity [kW]")
+    let mut mesh = chart1.configure_mesh();
+    options.style_mesh(&mut mesh);
+    mesh.x_desc("PV-Capacity [kW]")
         .y_desc("Energy [kWh]")
         .draw()?;
 
@@ -191,10 +651,10 @@ pub fn plot_result1(
                 .iter()
                 .zip(results["PV"].iter())
                 .map(|(&x, &y)| (x, y)),
-            &BLUE,
+            blue.stroke_width(options.px(1)),
         ))?
         .label("PV_PROD")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 10, y)], BLUE));
+        .legend(move |(x, y)| PathElement::new(options.legend_line(x, y, 10), blue));
 
     chart1
         .draw_series(LineSeries::new(
@@ -202,27 +662,27 @@ pub fn plot_result1(
                 .iter()
                 .zip(results["GRID"].iter())
                 .map(|(&x, &y)| (x, y)),
-            &RED,
+            red.stroke_width(options.px(1)),
         ))?
         .label("GRID_PROD")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 10, y)], RED));
+        .legend(move |(x, y)| PathElement::new(options.legend_line(x, y, 10), red));
 
-    chart1.configure_series_labels().draw()?;
+    draw_series_labels(&mut chart1, options)?;
 
     // Plot 2: Overproduction
     let mut chart2 = ChartBuilder::on(middle)
-        .caption("Overproduction vs PV-Capacity", ("sans-serif", 25))
-        .margin(15)
-        .x_label_area_size(40)
-        .y_label_area_size(60)
+        .caption("Overproduction vs PV-Capacity", options.font(25))
+        .margin(options.px(15))
+        .x_label_area_size(options.px(40))
+        .y_label_area_size(options.px(60))
         .build_cartesian_2d(
             pv_capacity[0]..pv_capacity[pv_capacity.len() - 1],
             0f64..results["OP"].iter().fold(0f64, |a, &b| a.max(b)),
         )?;
 
-    chart2
-        .configure_mesh()
-        .x_desc("PV-Capacity [kW]")
+    let mut mesh = chart2.configure_mesh();
+    options.style_mesh(&mut mesh);
+    mesh.x_desc("PV-Capacity [kW]")
         .y_desc("Overproduction [kWh]")
         .draw()?;
 
@@ -232,19 +692,19 @@ pub fn plot_result1(
                 .iter()
                 .zip(results["OP"].iter())
                 .map(|(&x, &y)| (x, y)),
-            &RGBColor(255, 165, 0), // Orange
+            orange.stroke_width(options.px(1)),
         ))?
         .label("OverProd")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 10, y)], RGBColor(255, 165, 0)));
+        .legend(move |(x, y)| PathElement::new(options.legend_line(x, y, 10), orange));
 
-    chart2.configure_series_labels().draw()?;
+    draw_series_labels(&mut chart2, options)?;
 
     // Plot 3: Cost
     let mut chart3 = ChartBuilder::on(lower)
-        .caption("Cost vs PV-Capacity", ("sans-serif", 25))
-        .margin(15)
-        .x_label_area_size(40)
-        .y_label_area_size(60)
+        .caption("Cost vs PV-Capacity", options.font(25))
+        .margin(options.px(15))
+        .x_label_area_size(options.px(40))
+        .y_label_area_size(options.px(60))
         .build_cartesian_2d(
             pv_capacity[0]..pv_capacity[pv_capacity.len() - 1],
             results["OBJEC"]
@@ -255,11 +715,9 @@ pub fn plot_result1(
                     .fold(f64::NEG_INFINITY, |a, &b| a.max(b)),
         )?;
 
-    chart3
-        .configure_mesh()
-        .x_desc("PV-Capacity [kW]")
-        .y_desc("Cost [€]")
-        .draw()?;
+    let mut mesh = chart3.configure_mesh();
+    options.style_mesh(&mut mesh);
+    mesh.x_desc("PV-Capacity [kW]").y_desc("Cost [€]").draw()?;
 
     chart3
         .draw_series(LineSeries::new(
@@ -267,59 +725,79 @@ pub fn plot_result1(
                 .iter()
                 .zip(results["OBJEC"].iter())
                 .map(|(&x, &y)| (x, y)),
-            &GREEN,
+            green.stroke_width(options.px(1)),
         ))?
         .label("ObRes")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 10, y)], GREEN));
+        .legend(move |(x, y)| PathElement::new(options.legend_line(x, y, 10), green));
 
-    chart3.configure_series_labels().draw()?;
+    draw_series_labels(&mut chart3, options)?;
 
     root.present()?;
-    println!("Plot saved as {}", filename);
     Ok(())
 }
 
 // Function to generate plots with actual optimization results
+#[deprecated(
+    note = "use `run_simple_opt_with_output`, `plot_seasonal_profiles` or `plot_week_profile`"
+)]
+#[allow(deprecated)]
 pub fn generate_optimization_plots(
     results: &OptimizationResults,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    generate_optimization_plots_with_options(results, &PlotOptions::default())
+}
+
+#[deprecated(
+    note = "use `run_simple_opt_with_output`, `plot_seasonal_profiles` or `plot_week_profile`"
+)]
+#[allow(deprecated)]
+pub fn generate_optimization_plots_with_options(
+    results: &OptimizationResults,
+    options: &PlotOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let extension = options.format.extension();
+
     // Plot 1: Energy flows over first 168 hours (one week)
     let hours_to_plot = 168.min(results.pv_energy.len());
 
-    plot_data1(
+    plot_data1_with_options(
         &results.pv_energy[..hours_to_plot],
         "PV Energy Production (First Week)",
         "Time (hours)",
         "Energy (kWh)",
-        "pv_energy_week.png",
+        &format!("pv_energy_week.{}", extension),
+        options,
     )?;
 
-    plot_data1(
+    plot_data1_with_options(
         &results.battery_storage[..hours_to_plot],
         "Battery Storage Level (First Week)",
         "Time (hours)",
         "Storage (kWh)",
-        "battery_storage_week.png",
+        &format!("battery_storage_week.{}", extension),
+        options,
     )?;
 
-    plot_data1(
+    plot_data1_with_options(
         &results.energy_overproduction[..hours_to_plot],
         "Energy Overproduction (First Week)",
         "Time (hours)",
         "Energy (kWh)",
-        "overproduction_week.png",
+        &format!("overproduction_week.{}", extension),
+        options,
     )?;
 
     // Plot 2: Compare demand vs supply for first 24 hours
     let daily_hours = 24.min(results.pv_energy.len());
     let electricity_demand = vec![2.0; daily_hours];
 
-    plot_data2(
+    plot_data2_with_options(
         &electricity_demand,
         &results.charging_energy[..daily_hours],
         &results.grid_energy[..daily_hours],
         &results.pv_energy[..daily_hours],
-        "daily_energy_balance.png",
+        &format!("daily_energy_balance.{}", extension),
+        options,
     )?;
 
     // Plot 3: Create a summary result plot showing the optimization outcome
@@ -337,38 +815,43 @@ pub fn generate_optimization_plots(
 
     let pv_cap_vec = vec![results.pv_capacity];
 
-    plot_result1(
+    plot_result1_with_options(
         &optimization_summary,
         &pv_cap_vec,
-        "optimization_summary.png",
+        &format!("optimization_summary.{}", extension),
+        options,
     )?;
 
-    println!("Optimization plots generated successfully!");
+    info!("Optimization plots generated successfully!");
     Ok(())
 }
 
 // Function to print optimization summary
+#[deprecated(
+    note = "`run_simple_opt_with_output` prints the summary of `SimpleOptimizationResults`"
+)]
+#[allow(deprecated)]
 pub fn print_optimization_summary(results: &OptimizationResults) {
-    println!("\n=== OPTIMIZATION RESULTS SUMMARY ===");
-    println!("Total Cost: €{:.2}", results.total_cost);
-    println!("PV Capacity: {:.2} kW", results.pv_capacity);
-    println!("Battery Capacity: {:.2} kWh", results.battery_capacity);
-    println!(
+    info!("=== OPTIMIZATION RESULTS SUMMARY ===");
+    info!("Total Cost: €{:.2}", results.total_cost);
+    info!("PV Capacity: {:.2} kW", results.pv_capacity);
+    info!("Battery Capacity: {:.2} kWh", results.battery_capacity);
+    info!(
         "Hot Water Storage Capacity: {:.2} kWh",
         results.hot_water_capacity
     );
-    println!("Grid Capacity: {:.2} kW", results.grid_capacity);
+    info!("Grid Capacity: {:.2} kW", results.grid_capacity);
 
     let annual_pv: f64 = results.pv_energy.iter().sum();
     let annual_grid: f64 = results.grid_energy.iter().sum();
     let annual_overproduction: f64 = results.energy_overproduction.iter().sum();
     let annual_charging: f64 = results.charging_energy.iter().sum();
 
-    println!("\nAnnual Energy Summary:");
-    println!("PV Production: {:.2} kWh", annual_pv);
-    println!("Grid Energy: {:.2} kWh", annual_grid);
-    println!("Overproduction: {:.2} kWh", annual_overproduction);
-    println!("EV Charging: {:.2} kWh", annual_charging);
+    info!("Annual Energy Summary:");
+    info!("PV Production: {:.2} kWh", annual_pv);
+    info!("Grid Energy: {:.2} kWh", annual_grid);
+    info!("Overproduction: {:.2} kWh", annual_overproduction);
+    info!("EV Charging: {:.2} kWh", annual_charging);
 
     let max_battery_level = results
         .battery_storage
@@ -379,18 +862,18 @@ pub fn print_optimization_summary(results: &OptimizationResults) {
         .iter()
         .fold(0.0f64, |a, &b| a.max(b));
 
-    println!("\nStorage Utilization:");
-    println!(
+    info!("Storage Utilization:");
+    info!(
         "Max Battery Level: {:.2} kWh ({:.1}% of capacity)",
         max_battery_level,
         (max_battery_level / results.battery_capacity.max(1e-6)) * 100.0
     );
-    println!(
+    info!(
         "Max Hot Water Level: {:.2} kWh ({:.1}% of capacity)",
         max_hot_water_level,
         (max_hot_water_level / results.hot_water_capacity.max(1e-6)) * 100.0
     );
-    println!("=====================================\n");
+    info!("=====================================");
 }
 
 /// Plot hourly averages for electricity demand, PV production, and grid consumption
@@ -420,6 +903,27 @@ pub fn plot_hourly_averages_with_title(
     battery_storage: &[f64],
     filename: &str,
     custom_title: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    plot_hourly_averages_with_options(
+        electricity_demand,
+        pv_production,
+        grid_consumption,
+        battery_storage,
+        filename,
+        custom_title,
+        &PlotOptions::default(),
+    )
+}
+
+/// Plot hourly data with optional custom title in the given format, size and theme
+pub fn plot_hourly_averages_with_options(
+    electricity_demand: &[f64],
+    pv_production: &[f64],
+    grid_consumption: &[f64],
+    battery_storage: &[f64],
+    filename: &str,
+    custom_title: Option<&str>,
+    options: &PlotOptions,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let data_len = electricity_demand.len();
 
@@ -484,115 +988,1625 @@ pub fn plot_hourly_averages_with_title(
     };
 
     // Create the plot
-    let root = BitMapBackend::new(filename, (1000, 600)).into_drawing_area();
-    root.fill(&WHITE)?;
+    let size = options.canvas_size((1000, 600));
+    let series = [
+        ("Electricity Demand", &hourly_demand),
+        ("PV Production", &hourly_pv),
+        ("Grid Consumption (+) / Feed-in (-)", &hourly_grid),
+        ("Battery Storage Level", &hourly_battery),
+    ];
+    match options.format {
+        PlotFormat::Png => draw_hourly_averages(
+            BitMapBackend::new(filename, size).into_drawing_area(),
+            &title,
+            &series,
+            options,
+        )?,
+        PlotFormat::Svg => draw_hourly_averages(
+            SVGBackend::new(filename, size).into_drawing_area(),
+            &title,
+            &series,
+            options,
+        )?,
+    }
+    info!("Hourly averages plot saved as {}", filename);
+    export_plot_data(filename, options, || hourly_plot_data(&title, &series))?;
+    Ok(())
+}
+
+/// Plot data of the series drawn by `draw_hourly_profiles`
+fn hourly_plot_data(title: &str, series: &[(&str, &[f64; 24])]) -> PlotData {
+    series.iter().fold(
+        PlotData::new(title, "Hour of Day", PlotAxis::indices(24)),
+        |data, (name, values)| data.with_series(name, values.as_slice()),
+    )
+}
+
+fn draw_hourly_averages<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    title: &str,
+    series: &[(&str, &[f64; 24])],
+    options: &PlotOptions,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&options.theme.background)?;
+    draw_hourly_profiles(&root, title, series, options)?;
+    root.present()?;
+    Ok(())
+}
+
+/// Draws one line with point markers per series over the 24 hours of a day
+fn draw_hourly_profiles<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    title: &str,
+    series: &[(&str, &[f64; 24])],
+    options: &PlotOptions,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let theme = &options.theme;
 
     // Find the range for y-axis
-    let min_val = hourly_demand
+    let min_val = series
         .iter()
-        .chain(hourly_pv.iter())
-        .chain(hourly_grid.iter())
-        .chain(hourly_battery.iter())
+        .flat_map(|(_, values)| values.iter())
         .fold(f64::INFINITY, |a, &b| a.min(b));
-    let max_val = hourly_demand
+    let max_val = series
         .iter()
-        .chain(hourly_pv.iter())
-        .chain(hourly_grid.iter())
-        .chain(hourly_battery.iter())
+        .flat_map(|(_, values)| values.iter())
         .fold(f64::NEG_INFINITY, |a, &b| a.max(b));
 
-    let mut chart = ChartBuilder::on(&root)
-        .caption(&title, ("sans-serif", 40))
-        .margin(20)
-        .x_label_area_size(50)
-        .y_label_area_size(80)
+    let mut chart = ChartBuilder::on(root)
+        .caption(title, options.font(40))
+        .margin(options.px(20))
+        .x_label_area_size(options.px(50))
+        .y_label_area_size(options.px(80))
         .build_cartesian_2d(0f64..23f64, (min_val * 0.9)..(max_val * 1.1))?;
 
-    chart
-        .configure_mesh()
-        .x_desc("Hour of Day")
+    let mut mesh = chart.configure_mesh();
+    options.style_mesh(&mut mesh);
+    mesh.x_desc("Hour of Day")
         .y_desc("Energy (kWh)")
         .x_label_formatter(&|x| format!("{:.0}", x))
         .draw()?;
 
-    // Draw electricity demand line
-    chart
-        .draw_series(LineSeries::new(
-            hourly_demand
+    for (index, (label, values)) in series.iter().enumerate() {
+        let color = theme.series[index % theme.series.len()];
+        let stroke_width = options.px(3);
+        chart
+            .draw_series(LineSeries::new(
+                values.iter().enumerate().map(|(i, &y)| (i as f64, y)),
+                color.stroke_width(stroke_width),
+            ))?
+            .label(*label)
+            .legend(move |(x, y)| {
+                PathElement::new(
+                    options.legend_line(x, y, 15),
+                    color.stroke_width(stroke_width),
+                )
+            });
+    }
+
+    // Add point markers for better visibility
+    for (index, (_, values)) in series.iter().enumerate() {
+        let color = theme.series[index % theme.series.len()];
+        chart.draw_series(
+            values
                 .iter()
                 .enumerate()
-                .map(|(i, &y)| (i as f64, y)),
-            RED.stroke_width(3),
-        ))?
-        .label("Electricity Demand")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 15, y)], RED.stroke_width(3)));
+                .map(|(i, &y)| Circle::new((i as f64, y), options.px(3), color.filled())),
+        )?;
+    }
 
-    // Draw PV production line
-    chart
-        .draw_series(LineSeries::new(
-            hourly_pv.iter().enumerate().map(|(i, &y)| (i as f64, y)),
-            BLUE.stroke_width(3),
-        ))?
-        .label("PV Production")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 15, y)], BLUE.stroke_width(3)));
+    draw_series_labels(&mut chart, options)?;
+    Ok(())
+}
+
+/// Hourly flows of a single day, e.g. taken from `SimpleOptimizationResults`
+#[derive(Debug, Clone, Copy)]
+pub struct DailyEnergyProfile<'a> {
+    pub electricity_demand: &'a [f64],
+    pub pv_production: &'a [f64],
+    pub grid_consumption: &'a [f64],
+    pub battery_storage: &'a [f64],
+    pub battery_in: &'a [f64],  // Charging power
+    pub battery_out: &'a [f64], // Discharging power
+}
+
+impl<'a> DailyEnergyProfile<'a> {
+    /// Takes the 24 hours of `day` (0-364) from the results, None if the day is out of range
+    pub fn from_results(results: &'a SimpleOptimizationResults, day: usize) -> Option<Self> {
+        let start_hour = day * 24;
+        let end_hour = start_hour + 24;
+        let day_slice = |values: &'a [f64]| values.get(start_hour..end_hour);
+        Some(Self {
+            electricity_demand: day_slice(&results.hourly_total_electricity_demand)?,
+            pv_production: day_slice(&results.hourly_total_pv_production)?,
+            grid_consumption: day_slice(&results.hourly_grid_consumption)?,
+            battery_storage: day_slice(&results.hourly_battery_storage)?,
+            battery_in: day_slice(&results.hourly_battery_in)?,
+            battery_out: day_slice(&results.hourly_battery_out)?,
+        })
+    }
+}
+
+/// Plot a single day with the energy profile on top and the battery charge (up) and discharge
+/// (down) power below. `power_limit` draws the C-rate limit as dashed lines.
+pub fn plot_day_with_battery_power(
+    profile: &DailyEnergyProfile,
+    filename: &str,
+    title: &str,
+    power_limit: Option<f64>,
+    options: &PlotOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let day_array = |values: &[f64], name: &str| -> Result<[f64; 24], Box<dyn std::error::Error>> {
+        values
+            .try_into()
+            .map_err(|_| format!("{} has {} values, expected 24", name, values.len()).into())
+    };
+    let demand = day_array(profile.electricity_demand, "Electricity demand")?;
+    let pv = day_array(profile.pv_production, "PV production")?;
+    let grid = day_array(profile.grid_consumption, "Grid consumption")?;
+    let storage = day_array(profile.battery_storage, "Battery storage")?;
+    let battery_in = day_array(profile.battery_in, "Battery charging")?;
+    let battery_out = day_array(profile.battery_out, "Battery discharging")?;
+
+    let series = [
+        ("Electricity Demand", &demand),
+        ("PV Production", &pv),
+        ("Grid Consumption (+) / Feed-in (-)", &grid),
+        ("Battery Storage Level", &storage),
+    ];
+    let size = options.canvas_size((1000, 900));
+    match options.format {
+        PlotFormat::Png => draw_day_with_battery_power(
+            BitMapBackend::new(filename, size).into_drawing_area(),
+            title,
+            &series,
+            (&battery_in, &battery_out),
+            power_limit,
+            options,
+        )?,
+        PlotFormat::Svg => draw_day_with_battery_power(
+            SVGBackend::new(filename, size).into_drawing_area(),
+            title,
+            &series,
+            (&battery_in, &battery_out),
+            power_limit,
+            options,
+        )?,
+    }
+    info!("Day plot with battery power saved as {}", filename);
+    export_plot_data(filename, options, || {
+        hourly_plot_data(title, &series)
+            .with_series("Battery Charging", &battery_in)
+            .with_series("Battery Discharging", &battery_out)
+    })?;
+    Ok(())
+}
 
-    // Draw grid consumption line
+fn draw_day_with_battery_power<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    title: &str,
+    series: &[(&str, &[f64; 24])],
+    (battery_in, battery_out): (&[f64; 24], &[f64; 24]),
+    power_limit: Option<f64>,
+    options: &PlotOptions,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let theme = &options.theme;
+    root.fill(&theme.background)?;
+    let (upper, lower) = root.split_vertically(root.dim_in_pixel().1 * 2 / 3);
+    draw_hourly_profiles(&upper, title, series, options)?;
+
+    // Charging is drawn upwards, discharging downwards from the zero line
+    let max_power = battery_in
+        .iter()
+        .chain(battery_out.iter())
+        .copied()
+        .chain(power_limit)
+        .fold(0.0f64, f64::max)
+        .max(1e-6)
+        * 1.1;
+    let mut chart = ChartBuilder::on(&lower)
+        .caption("Battery Charge / Discharge", options.font(25))
+        .margin(options.px(20))
+        .x_label_area_size(options.px(50))
+        .y_label_area_size(options.px(80))
+        .build_cartesian_2d(-0.5f64..23.5f64, -max_power..max_power)?;
+
+    let mut mesh = chart.configure_mesh();
+    options.style_mesh(&mut mesh);
+    mesh.x_desc("Hour of Day")
+        .y_desc("Power (W)")
+        .x_label_formatter(&|x| format!("{:.0}", x))
+        .draw()?;
+
+    let charge_color = theme.series[2];
+    let discharge_color = theme.series[0];
     chart
-        .draw_series(LineSeries::new(
-            hourly_grid.iter().enumerate().map(|(i, &y)| (i as f64, y)),
-            GREEN.stroke_width(3),
-        ))?
-        .label("Grid Consumption (+) / Feed-in (-)")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 15, y)], GREEN.stroke_width(3)));
+        .draw_series(battery_in.iter().enumerate().map(|(hour, &power)| {
+            Rectangle::new(
+                [(hour as f64 - 0.35, 0.0), (hour as f64 + 0.35, power)],
+                charge_color.filled(),
+            )
+        }))?
+        .label("Charging")
+        .legend(move |(x, y)| {
+            Rectangle::new(
+                [(x, y - 4), (x + options.px(15) as i32, y + 4)],
+                charge_color.filled(),
+            )
+        });
+    chart
+        .draw_series(battery_out.iter().enumerate().map(|(hour, &power)| {
+            Rectangle::new(
+                [(hour as f64 - 0.35, 0.0), (hour as f64 + 0.35, -power)],
+                discharge_color.filled(),
+            )
+        }))?
+        .label("Discharging")
+        .legend(move |(x, y)| {
+            Rectangle::new(
+                [(x, y - 4), (x + options.px(15) as i32, y + 4)],
+                discharge_color.filled(),
+            )
+        });
+
+    if let Some(limit) = power_limit {
+        let limit_color = theme.foreground;
+        for limit in [limit, -limit] {
+            chart.draw_series(DashedLineSeries::new(
+                [(-0.5, limit), (23.5, limit)],
+                options.px(6),
+                options.px(4),
+                limit_color.stroke_width(options.px(1)),
+            ))?;
+        }
+        chart
+            .draw_series(std::iter::empty::<PathElement<(f64, f64)>>())?
+            .label("C-rate limit")
+            .legend(move |(x, y)| PathElement::new(options.legend_line(x, y, 15), limit_color));
+    }
+
+    draw_series_labels(&mut chart, options)?;
+    root.present()?;
+    Ok(())
+}
+
+/// Hourly energy balance of a window, split into what covers the demand and where the own
+/// production goes. PV includes wind if a turbine is configured.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EnergyBalance {
+    pub start_hour: usize,
+    // Covering the demand
+    pub pv_direct_use: Vec<f64>,
+    pub battery_discharge: Vec<f64>,
+    pub grid_import: Vec<f64>, // Grid energy going to the load, without grid charging the battery
+    pub demand: Vec<f64>,
+    // Consuming the own production
+    pub battery_charge: Vec<f64>,
+    pub export: Vec<f64>,
+    pub production: Vec<f64>,
+}
+
+impl EnergyBalance {
+    /// Builds the balance for `num_hours` hours starting at `start_hour`, e.g. 24 for a day or
+    /// 168 for a week
+    pub fn from_results(
+        results: &SimpleOptimizationResults,
+        start_hour: usize,
+        num_hours: usize,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let available = results.hourly_total_electricity_demand.len();
+        if num_hours == 0 || start_hour + num_hours > available {
+            return Err(format!(
+                "Window of {} hours starting at hour {} is outside of the {} available hours",
+                num_hours, start_hour, available
+            )
+            .into());
+        }
+
+        let value = |values: &[f64], t: usize| values.get(t).copied().unwrap_or(0.0);
+        let mut balance = Self {
+            start_hour,
+            ..Default::default()
+        };
+        for t in start_hour..start_hour + num_hours {
+            let production = value(&results.hourly_total_pv_production, t)
+                + value(&results.hourly_wind_production, t);
+            let export = value(&results.hourly_overproduction, t);
+            let battery_in = value(&results.hourly_battery_in, t);
+            let own_use = production - export;
+            // The battery is charged from own production first, the rest comes from the grid
+            let charge_from_own = battery_in.min(own_use).max(0.0);
+            let grid_to_battery = battery_in - charge_from_own;
+
+            balance.pv_direct_use.push(own_use - charge_from_own);
+            balance
+                .battery_discharge
+                .push(value(&results.hourly_battery_out, t));
+            balance
+                .grid_import
+                .push(value(&results.hourly_grid_consumption, t) - grid_to_battery);
+            balance
+                .demand
+                .push(value(&results.hourly_total_electricity_demand, t));
+            balance.battery_charge.push(charge_from_own);
+            balance.export.push(export);
+            balance.production.push(production);
+        }
+        Ok(balance)
+    }
+
+    /// Largest difference between the stacked supply and the demand
+    pub fn max_imbalance(&self) -> f64 {
+        (0..self.demand.len())
+            .map(|i| {
+                (self.pv_direct_use[i] + self.battery_discharge[i] + self.grid_import[i]
+                    - self.demand[i])
+                    .abs()
+            })
+            .fold(0.0, f64::max)
+    }
+}
+
+/// Stacked-area plot of an energy balance window: PV direct use, battery discharge and grid
+/// import covering the demand on top, and direct use, battery charge and export consuming the
+/// production below. The demand and production lines show whether the balance closes.
+pub fn plot_energy_balance(
+    balance: &EnergyBalance,
+    filename: &str,
+    title: &str,
+    options: &PlotOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let size = options.canvas_size((1200, 900));
+    match options.format {
+        PlotFormat::Png => draw_energy_balance(
+            BitMapBackend::new(filename, size).into_drawing_area(),
+            balance,
+            title,
+            options,
+        )?,
+        PlotFormat::Svg => draw_energy_balance(
+            SVGBackend::new(filename, size).into_drawing_area(),
+            balance,
+            title,
+            options,
+        )?,
+    }
+    info!("Energy balance plot saved as {}", filename);
+    export_plot_data(filename, options, || {
+        let hours = (balance.start_hour..balance.start_hour + balance.demand.len())
+            .map(|hour| hour as f64)
+            .collect();
+        PlotData::new(title, "Hour", PlotAxis::Values(hours))
+            .with_series("PV Direct Use", &balance.pv_direct_use)
+            .with_series("Battery Discharge", &balance.battery_discharge)
+            .with_series("Grid Import", &balance.grid_import)
+            .with_series("Demand", &balance.demand)
+            .with_series("Battery Charge", &balance.battery_charge)
+            .with_series("Export", &balance.export)
+            .with_series("Production", &balance.production)
+    })?;
+    Ok(())
+}
+
+fn draw_energy_balance<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    balance: &EnergyBalance,
+    title: &str,
+    options: &PlotOptions,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let theme = &options.theme;
+    root.fill(&theme.background)?;
+    let root = root.titled(title, options.font(35))?;
+    let (upper, lower) = root.split_vertically(root.dim_in_pixel().1 / 2);
+    let [red, blue, green, magenta, orange] = theme.series;
+
+    draw_stacked_areas(
+        &upper,
+        "Demand Coverage",
+        balance.start_hour,
+        &[
+            ("PV Direct Use", &balance.pv_direct_use, orange),
+            ("Battery Discharge", &balance.battery_discharge, magenta),
+            ("Grid Import", &balance.grid_import, red),
+        ],
+        ("Electricity Demand", &balance.demand),
+        options,
+    )?;
+    draw_stacked_areas(
+        &lower,
+        "Use of Own Production",
+        balance.start_hour,
+        &[
+            ("PV Direct Use", &balance.pv_direct_use, orange),
+            ("Battery Charge", &balance.battery_charge, green),
+            ("Export", &balance.export, blue),
+        ],
+        ("PV Production", &balance.production),
+        options,
+    )?;
 
-    // Draw battery storage line
+    root.present()?;
+    Ok(())
+}
+
+/// Draws the bands on top of each other and the total they should add up to as a line
+fn draw_stacked_areas<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    caption: &str,
+    start_hour: usize,
+    bands: &[(&str, &Vec<f64>, RGBColor)],
+    (total_label, total): (&str, &Vec<f64>),
+    options: &PlotOptions,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let num_hours = total.len();
+    let mut lower = vec![0.0; num_hours];
+    let stacked: Vec<(Vec<f64>, Vec<f64>)> = bands
+        .iter()
+        .map(|(_, values, _)| {
+            let upper: Vec<f64> = lower
+                .iter()
+                .zip(values.iter())
+                .map(|(base, value)| base + value.max(0.0))
+                .collect();
+            let band = (lower.clone(), upper.clone());
+            lower = upper;
+            band
+        })
+        .collect();
+
+    let max_val = lower
+        .iter()
+        .chain(total.iter())
+        .fold(0.0f64, |a, &b| a.max(b))
+        .max(1e-6);
+    let x_start = start_hour as f64;
+    let x_end = (start_hour + num_hours.max(2) - 1) as f64;
+
+    let mut chart = ChartBuilder::on(area)
+        .caption(caption, options.font(25))
+        .margin(options.px(20))
+        .x_label_area_size(options.px(50))
+        .y_label_area_size(options.px(80))
+        .build_cartesian_2d(x_start..x_end, 0f64..max_val * 1.1)?;
+
+    let mut mesh = chart.configure_mesh();
+    options.style_mesh(&mut mesh);
+    mesh.x_desc("Hour of Year")
+        .y_desc("Energy (Wh)")
+        .x_label_formatter(&|x| format!("{:.0}", x))
+        .draw()?;
+
+    for ((label, _, color), (band_lower, band_upper)) in bands.iter().zip(stacked.iter()) {
+        let color = *color;
+        let points: Vec<(f64, f64)> = band_upper
+            .iter()
+            .enumerate()
+            .map(|(i, &y)| (x_start + i as f64, y))
+            .chain(
+                band_lower
+                    .iter()
+                    .enumerate()
+                    .rev()
+                    .map(|(i, &y)| (x_start + i as f64, y)),
+            )
+            .collect();
+        chart
+            .draw_series(std::iter::once(Polygon::new(
+                points,
+                color.mix(0.7).filled(),
+            )))?
+            .label(*label)
+            .legend(move |(x, y)| {
+                Rectangle::new(
+                    [(x, y - 4), (x + options.px(15) as i32, y + 4)],
+                    color.mix(0.7).filled(),
+                )
+            });
+    }
+
+    let line_color = options.theme.foreground;
     chart
         .draw_series(LineSeries::new(
-            hourly_battery
+            total
                 .iter()
                 .enumerate()
-                .map(|(i, &y)| (i as f64, y)),
-            MAGENTA.stroke_width(3),
+                .map(|(i, &y)| (x_start + i as f64, y)),
+            line_color.stroke_width(options.px(2)),
         ))?
-        .label("Battery Storage Level")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 15, y)], MAGENTA.stroke_width(3)));
+        .label(total_label)
+        .legend(move |(x, y)| {
+            PathElement::new(
+                options.legend_line(x, y, 15),
+                line_color.stroke_width(options.px(2)),
+            )
+        });
 
-    // Add point markers for better visibility
-    chart.draw_series(
-        hourly_demand
-            .iter()
-            .enumerate()
-            .map(|(i, &y)| Circle::new((i as f64, y), 3, RED.filled())),
-    )?;
+    draw_series_labels(&mut chart, options)?;
+    Ok(())
+}
 
-    chart.draw_series(
-        hourly_pv
-            .iter()
-            .enumerate()
-            .map(|(i, &y)| Circle::new((i as f64, y), 3, BLUE.filled())),
-    )?;
+/// Meteorological season (winter = Dec-Feb, spring = Mar-May, ...)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Season {
+    Winter,
+    Spring,
+    Summer,
+    Fall,
+}
+
+impl Season {
+    pub const ALL: [Season; 4] = [Season::Winter, Season::Spring, Season::Summer, Season::Fall];
+
+    /// Season of a day of the year (0-364, non-leap year)
+    pub fn from_day(day: usize) -> Self {
+        let mut remaining_days = day % 365;
+        let mut month = 0;
+        for (i, &days) in DAYS_IN_MONTH.iter().enumerate() {
+            if remaining_days < days {
+                month = i;
+                break;
+            }
+            remaining_days -= days;
+        }
+        match month {
+            2..=4 => Season::Spring,
+            5..=7 => Season::Summer,
+            8..=10 => Season::Fall,
+            _ => Season::Winter,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Season::Winter => "Winter",
+            Season::Spring => "Spring",
+            Season::Summer => "Summer",
+            Season::Fall => "Fall",
+        }
+    }
+}
+
+/// Average daily profile (24 values) of an hourly series over all days of a season
+pub fn seasonal_average_profile(values: &[f64], season: Season) -> [f64; 24] {
+    let mut profile = [0.0; 24];
+    let mut hour_counts = [0usize; 24];
+    for (hour, &value) in values.iter().enumerate() {
+        if Season::from_day(hour / 24) == season {
+            profile[hour % 24] += value;
+            hour_counts[hour % 24] += 1;
+        }
+    }
+    for (value, &count) in profile.iter_mut().zip(hour_counts.iter()) {
+        if count > 0 {
+            *value /= count as f64;
+        }
+    }
+    profile
+}
 
-    chart.draw_series(
-        hourly_grid
+/// Plot the average daily profiles of demand, PV, grid and battery level per season in a 2x2 grid
+pub fn plot_seasonal_profiles(
+    results: &SimpleOptimizationResults,
+    filename: &str,
+    options: &PlotOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let season_series = Season::ALL.map(|season| {
+        [
+            seasonal_average_profile(&results.hourly_total_electricity_demand, season),
+            seasonal_average_profile(&results.hourly_total_pv_production, season),
+            seasonal_average_profile(&results.hourly_grid_consumption, season),
+            seasonal_average_profile(&results.hourly_battery_storage, season),
+        ]
+    });
+
+    let size = options.canvas_size((1600, 1100));
+    match options.format {
+        PlotFormat::Png => draw_seasonal_profiles(
+            BitMapBackend::new(filename, size).into_drawing_area(),
+            &season_series,
+            options,
+        )?,
+        PlotFormat::Svg => draw_seasonal_profiles(
+            SVGBackend::new(filename, size).into_drawing_area(),
+            &season_series,
+            options,
+        )?,
+    }
+    info!("Seasonal profiles plot saved as {}", filename);
+    export_plot_data(filename, options, || {
+        let names = [
+            "Electricity Demand",
+            "PV Production",
+            "Grid Consumption (+) / Feed-in (-)",
+            "Battery Storage Level",
+        ];
+        let data = PlotData::new("Seasonal Profiles", "Hour of Day", PlotAxis::indices(24));
+        Season::ALL
             .iter()
-            .enumerate()
-            .map(|(i, &y)| Circle::new((i as f64, y), 3, GREEN.filled())),
-    )?;
+            .zip(season_series.iter())
+            .flat_map(|(season, profiles)| {
+                names
+                    .iter()
+                    .zip(profiles.iter())
+                    .map(move |(name, values)| (format!("{} - {}", season.name(), name), values))
+            })
+            .fold(data, |data, (name, values)| data.with_series(&name, values))
+    })?;
+    Ok(())
+}
+
+fn draw_seasonal_profiles<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    season_series: &[[[f64; 24]; 4]; 4],
+    options: &PlotOptions,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&options.theme.background)?;
+    let areas = root.split_evenly((2, 2));
+    for ((area, season), [demand, pv, grid, battery]) in
+        areas.iter().zip(Season::ALL).zip(season_series.iter())
+    {
+        let series = [
+            ("Electricity Demand", demand),
+            ("PV Production", pv),
+            ("Grid Consumption (+) / Feed-in (-)", grid),
+            ("Battery Storage Level", battery),
+        ];
+        draw_hourly_profiles(area, season.name(), &series, options)?;
+    }
+    root.present()?;
+    Ok(())
+}
 
-    chart.draw_series(
-        hourly_battery
+/// Plot demand, PV, grid and battery level over the 168 hours of the week starting at `start_day`
+pub fn plot_week_profile(
+    results: &SimpleOptimizationResults,
+    start_day: usize,
+    filename: &str,
+    options: &PlotOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let title = format!("Week Profile - Days {} to {}", start_day, start_day + 6);
+    plot_week(results, start_day, &title, filename, options)
+}
+
+/// Plot the week of `find_worst_week` like `plot_week_profile`, with its dates and PV coverage
+/// in the title
+pub fn plot_worst_week(
+    results: &SimpleOptimizationResults,
+    week: &WorstWeek,
+    filename: &str,
+    options: &PlotOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let title = format!(
+        "Worst PV Week - {} to {} (PV coverage {:.1} %, battery empty {} h)",
+        week.start_date, week.end_date, week.pv_coverage_percent, week.battery_empty_hours
+    );
+    plot_week(results, week.start_day, &title, filename, options)
+}
+
+fn plot_week(
+    results: &SimpleOptimizationResults,
+    start_day: usize,
+    title: &str,
+    filename: &str,
+    options: &PlotOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    const HOURS_PER_WEEK: usize = 168;
+    let start_hour = start_day * 24;
+    let end_hour = start_hour + HOURS_PER_WEEK;
+    let window = |values: &[f64], name: &str| -> Result<Vec<f64>, Box<dyn std::error::Error>> {
+        values
+            .get(start_hour..end_hour)
+            .map(|values| values.to_vec())
+            .ok_or_else(|| {
+                format!(
+                    "{} has {} values, the week starting at day {} needs {}",
+                    name,
+                    values.len(),
+                    start_day,
+                    end_hour
+                )
+                .into()
+            })
+    };
+    let series = [
+        (
+            "Electricity Demand",
+            window(
+                &results.hourly_total_electricity_demand,
+                "Electricity demand",
+            )?,
+        ),
+        (
+            "PV Production",
+            window(&results.hourly_total_pv_production, "PV production")?,
+        ),
+        (
+            "Grid Consumption (+) / Feed-in (-)",
+            window(&results.hourly_grid_consumption, "Grid consumption")?,
+        ),
+        (
+            "Battery Storage Level",
+            window(&results.hourly_battery_storage, "Battery storage")?,
+        ),
+    ];
+
+    let size = options.canvas_size((1400, 600));
+    match options.format {
+        PlotFormat::Png => draw_line_series(
+            BitMapBackend::new(filename, size).into_drawing_area(),
+            title,
+            "Hour of Week",
+            &series,
+            options,
+        )?,
+        PlotFormat::Svg => draw_line_series(
+            SVGBackend::new(filename, size).into_drawing_area(),
+            title,
+            "Hour of Week",
+            &series,
+            options,
+        )?,
+    }
+    info!("Week plot saved as {}", filename);
+    export_plot_data(filename, options, || {
+        line_plot_data(title, "Hour of Week", &series)
+    })?;
+    Ok(())
+}
+
+/// Duration curves: the hourly values sorted in descending order
+#[derive(Debug, Clone, Default)]
+pub struct DurationCurves {
+    pub demand: Vec<f64>,
+    pub residual_load: Vec<f64>, // Demand minus own production (PV and wind), negative while producing a surplus
+    pub export: Vec<f64>,
+}
+
+impl DurationCurves {
+    pub fn from_results(results: &SimpleOptimizationResults) -> Self {
+        let value = |values: &[f64], t: usize| values.get(t).copied().unwrap_or(0.0);
+        let residual_load: Vec<f64> = (0..results.hourly_total_electricity_demand.len())
+            .map(|t| {
+                value(&results.hourly_total_electricity_demand, t)
+                    - value(&results.hourly_total_pv_production, t)
+                    - value(&results.hourly_wind_production, t)
+            })
+            .collect();
+        Self {
+            demand: duration_curve(&results.hourly_total_electricity_demand),
+            residual_load: duration_curve(&residual_load),
+            export: duration_curve(&results.hourly_overproduction),
+        }
+    }
+}
+
+/// Sorts the values in descending order
+pub fn duration_curve(values: &[f64]) -> Vec<f64> {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| b.total_cmp(a));
+    sorted
+}
+
+/// Plot the duration curves of demand, residual load after own production and export
+pub fn plot_duration_curves(
+    curves: &DurationCurves,
+    filename: &str,
+    options: &PlotOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let series = [
+        ("Electricity Demand", curves.demand.clone()),
+        ("Residual Load after PV", curves.residual_load.clone()),
+        ("Export", curves.export.clone()),
+    ];
+    let title = "Duration Curves";
+
+    let size = options.canvas_size((1200, 700));
+    match options.format {
+        PlotFormat::Png => draw_line_series(
+            BitMapBackend::new(filename, size).into_drawing_area(),
+            title,
+            "Hours (sorted)",
+            &series,
+            options,
+        )?,
+        PlotFormat::Svg => draw_line_series(
+            SVGBackend::new(filename, size).into_drawing_area(),
+            title,
+            "Hours (sorted)",
+            &series,
+            options,
+        )?,
+    }
+    info!("Duration curves plot saved as {}", filename);
+    export_plot_data(filename, options, || {
+        line_plot_data(title, "Hours (sorted)", &series)
+    })?;
+    Ok(())
+}
+
+/// Side-by-side bar plot of two compared scenarios: annual energies on the left, coverage
+/// percentages on the right
+pub fn plot_comparison(
+    report: &ComparisonReport,
+    filename: &str,
+    options: &PlotOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let size = options.canvas_size((1600, 700));
+    match options.format {
+        PlotFormat::Png => draw_comparison(
+            BitMapBackend::new(filename, size).into_drawing_area(),
+            report,
+            options,
+        )?,
+        PlotFormat::Svg => draw_comparison(
+            SVGBackend::new(filename, size).into_drawing_area(),
+            report,
+            options,
+        )?,
+    }
+    info!("Comparison plot saved as {}", filename);
+    export_plot_data(filename, options, || {
+        let names = report
+            .kpis
             .iter()
-            .enumerate()
-            .map(|(i, &y)| Circle::new((i as f64, y), 3, MAGENTA.filled())),
+            .map(|kpi| format!("{} ({})", kpi.name, kpi.unit))
+            .collect();
+        let a: Vec<f64> = report.kpis.iter().map(|kpi| kpi.a).collect();
+        let b: Vec<f64> = report.kpis.iter().map(|kpi| kpi.b).collect();
+        PlotData::new(
+            &format!("{} vs {}", report.label_a, report.label_b),
+            "KPI",
+            PlotAxis::Labels(names),
+        )
+        .with_series(&report.label_a, &a)
+        .with_series(&report.label_b, &b)
+    })?;
+    Ok(())
+}
+
+fn draw_comparison<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    report: &ComparisonReport,
+    options: &PlotOptions,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&options.theme.background)?;
+    let root = root.titled(
+        &format!("{} vs {}", report.label_a, report.label_b),
+        options.font(35),
+    )?;
+    let (left, right) = root.split_horizontally(root.dim_in_pixel().0 * 2 / 3);
+    let energies: Vec<&KpiDelta> = report.kpis_of(KpiKind::AnnualEnergy).collect();
+    let percentages: Vec<&KpiDelta> = report.kpis_of(KpiKind::Share).collect();
+    draw_comparison_bars(
+        &left,
+        "Annual Energy",
+        "Energy (kWh)",
+        &energies,
+        report,
+        options,
     )?;
+    draw_comparison_bars(
+        &right,
+        "Coverage",
+        "Share (%)",
+        &percentages,
+        report,
+        options,
+    )?;
+    root.present()?;
+    Ok(())
+}
 
-    chart
-        .configure_series_labels()
-        .background_style(WHITE.mix(0.8))
-        .border_style(BLACK)
+/// Draws one pair of bars (scenario A and B) per KPI
+fn draw_comparison_bars<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    title: &str,
+    y_desc: &str,
+    kpis: &[&KpiDelta],
+    report: &ComparisonReport,
+    options: &PlotOptions,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let max_val = kpis
+        .iter()
+        .flat_map(|kpi| [kpi.a, kpi.b])
+        .fold(0.0f64, f64::max)
+        .max(1e-6)
+        * 1.1;
+    let names: Vec<&str> = kpis.iter().map(|kpi| kpi.name).collect();
+
+    let mut chart = ChartBuilder::on(area)
+        .caption(title, options.font(25))
+        .margin(options.px(20))
+        .x_label_area_size(options.px(60))
+        .y_label_area_size(options.px(80))
+        .build_cartesian_2d(-0.5f64..(kpis.len() as f64 - 0.5), 0f64..max_val)?;
+
+    let mut mesh = chart.configure_mesh();
+    options.style_mesh(&mut mesh);
+    mesh.y_desc(y_desc)
+        .disable_x_mesh()
+        .x_labels(kpis.len())
+        .x_label_formatter(&|x| {
+            let index = x.round();
+            if (x - index).abs() < 1e-6 && index >= 0.0 {
+                names.get(index as usize).copied().unwrap_or("").to_string()
+            } else {
+                String::new()
+            }
+        })
+        .draw()?;
+
+    let bars = [
+        (report.label_a.as_str(), -0.4, options.theme.series[1]),
+        (report.label_b.as_str(), 0.0, options.theme.series[4]),
+    ];
+    for (scenario, (label, offset, color)) in bars.into_iter().enumerate() {
+        chart
+            .draw_series(kpis.iter().enumerate().map(|(i, kpi)| {
+                let value = if scenario == 0 { kpi.a } else { kpi.b };
+                let left = i as f64 + offset;
+                Rectangle::new([(left, 0.0), (left + 0.4, value)], color.filled())
+            }))?
+            .label(label)
+            .legend(move |(x, y)| {
+                Rectangle::new(
+                    [(x, y - 4), (x + options.px(15) as i32, y + 4)],
+                    color.filled(),
+                )
+            });
+    }
+
+    draw_series_labels(&mut chart, options)?;
+    Ok(())
+}
+
+/// Draws one line per series over the series' hours, used for the week view and duration curves
+/// Plot data of the series drawn by `draw_line_series`
+fn line_plot_data(title: &str, x_desc: &str, series: &[(&str, Vec<f64>)]) -> PlotData {
+    let len = series.first().map_or(0, |(_, values)| values.len());
+    series.iter().fold(
+        PlotData::new(title, x_desc, PlotAxis::indices(len)),
+        |data, (name, values)| data.with_series(name, values),
+    )
+}
+
+fn draw_line_series<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    title: &str,
+    x_desc: &str,
+    series: &[(&str, Vec<f64>)],
+    options: &PlotOptions,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let theme = &options.theme;
+    root.fill(&theme.background)?;
+
+    let min_val = series
+        .iter()
+        .flat_map(|(_, values)| values.iter())
+        .fold(0.0f64, |a, &b| a.min(b));
+    let max_val = series
+        .iter()
+        .flat_map(|(_, values)| values.iter())
+        .fold(0.0f64, |a, &b| a.max(b))
+        .max(1e-6);
+    let max_hour = series
+        .iter()
+        .map(|(_, values)| values.len())
+        .max()
+        .unwrap_or(1)
+        .saturating_sub(1)
+        .max(1);
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption(title, options.font(35))
+        .margin(options.px(20))
+        .x_label_area_size(options.px(50))
+        .y_label_area_size(options.px(80))
+        .build_cartesian_2d(0f64..max_hour as f64, (min_val * 1.1)..(max_val * 1.1))?;
+
+    let mut mesh = chart.configure_mesh();
+    options.style_mesh(&mut mesh);
+    mesh.x_desc(x_desc)
+        .y_desc("Energy (Wh)")
+        .x_labels(8)
+        .x_label_formatter(&|x| format!("{:.0}", x))
+        .draw()?;
+
+    for (index, (label, values)) in series.iter().enumerate() {
+        let color = theme.series[index % theme.series.len()];
+        let stroke_width = options.px(2);
+        chart
+            .draw_series(LineSeries::new(
+                values.iter().enumerate().map(|(i, &y)| (i as f64, y)),
+                color.stroke_width(stroke_width),
+            ))?
+            .label(*label)
+            .legend(move |(x, y)| {
+                PathElement::new(
+                    options.legend_line(x, y, 15),
+                    color.stroke_width(stroke_width),
+                )
+            });
+    }
+
+    draw_series_labels(&mut chart, options)?;
+    root.present()?;
+    Ok(())
+}
+
+/// KPI of a `CapacityMap` drawn by `plot_capacity_map`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum CapacityMapKpi {
+    #[default]
+    Autarky,
+    SelfConsumption,
+    AnnualCost,
+}
+
+impl CapacityMapKpi {
+    fn values(self, map: &CapacityMap) -> &[Vec<f64>] {
+        match self {
+            CapacityMapKpi::Autarky => &map.autarky,
+            CapacityMapKpi::SelfConsumption => &map.self_consumption,
+            CapacityMapKpi::AnnualCost => &map.annual_cost,
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            CapacityMapKpi::Autarky => "Autarky (%)",
+            CapacityMapKpi::SelfConsumption => "Self-Consumption (%)",
+            CapacityMapKpi::AnnualCost => "Annual Cost",
+        }
+    }
+
+    /// Values the contour lines are drawn at: every 10 % for shares, 8 steps for the cost
+    fn levels(self, min: f64, max: f64) -> Vec<f64> {
+        match self {
+            CapacityMapKpi::Autarky | CapacityMapKpi::SelfConsumption => (1..10)
+                .map(|i| i as f64 * 10.0)
+                .filter(|&level| level > min && level < max)
+                .collect(),
+            CapacityMapKpi::AnnualCost => {
+                (1..8).map(|i| min + (max - min) * i as f64 / 8.0).collect()
+            }
+        }
+    }
+}
+
+/// Contour plot of one KPI over PV capacity (x) and battery capacity (y), e.g. the autarky map
+/// of a household. The colored cells show the value of every run, the lines connect equal values.
+pub fn plot_capacity_map(
+    map: &CapacityMap,
+    kpi: CapacityMapKpi,
+    filename: &str,
+    options: &PlotOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let size = options.canvas_size((1000, 800));
+    match options.format {
+        PlotFormat::Png => draw_capacity_map(
+            BitMapBackend::new(filename, size).into_drawing_area(),
+            map,
+            kpi,
+            options,
+        )?,
+        PlotFormat::Svg => draw_capacity_map(
+            SVGBackend::new(filename, size).into_drawing_area(),
+            map,
+            kpi,
+            options,
+        )?,
+    }
+    info!("Capacity map saved as {}", filename);
+    export_plot_data(filename, options, || {
+        // One column per battery capacity, one row per PV capacity
+        let data = PlotData::new(
+            kpi.title(),
+            "PV Capacity (kW)",
+            PlotAxis::Values(map.pv_capacities_kw.clone()),
+        );
+        map.battery_capacities_kwh.iter().zip(kpi.values(map)).fold(
+            data,
+            |data, (battery_kwh, row)| {
+                data.with_series(&format!("Battery {} kWh", battery_kwh), row)
+            },
+        )
+    })?;
+    Ok(())
+}
+
+fn draw_capacity_map<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    map: &CapacityMap,
+    kpi: CapacityMapKpi,
+    options: &PlotOptions,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let values = kpi.values(map);
+    let x_edges = cell_edges(&map.pv_capacities_kw);
+    let y_edges = cell_edges(&map.battery_capacities_kwh);
+    if x_edges.is_empty() || y_edges.is_empty() {
+        return Err("The capacity map is empty".into());
+    }
+    let min_val = values
+        .iter()
+        .flatten()
+        .copied()
+        .fold(f64::INFINITY, f64::min);
+    let max_val = values
+        .iter()
+        .flatten()
+        .copied()
+        .fold(f64::NEG_INFINITY, f64::max)
+        .max(min_val + 1e-6);
+
+    root.fill(&options.theme.background)?;
+    let mut chart = ChartBuilder::on(&root)
+        .caption(kpi.title(), options.font(35))
+        .margin(options.px(20))
+        .x_label_area_size(options.px(50))
+        .y_label_area_size(options.px(80))
+        .build_cartesian_2d(
+            x_edges[0]..x_edges[x_edges.len() - 1],
+            y_edges[0]..y_edges[y_edges.len() - 1],
+        )?;
+
+    let mut mesh = chart.configure_mesh();
+    options.style_mesh(&mut mesh);
+    mesh.x_desc("PV Capacity (kWp)")
+        .y_desc("Battery Capacity (kWh)")
+        .disable_mesh()
         .draw()?;
 
+    chart.draw_series(values.iter().enumerate().flat_map(|(j, row)| {
+        let (x_edges, y_edges) = (&x_edges, &y_edges);
+        row.iter().enumerate().map(move |(i, &value)| {
+            Rectangle::new(
+                [(x_edges[i], y_edges[j]), (x_edges[i + 1], y_edges[j + 1])],
+                ViridisRGB::get_color_normalized(value, min_val, max_val).filled(),
+            )
+        })
+    }))?;
+
+    let line_style = options.theme.foreground.stroke_width(options.px(2));
+    for level in kpi.levels(min_val, max_val) {
+        let segments = contour_segments(
+            &map.pv_capacities_kw,
+            &map.battery_capacities_kwh,
+            values,
+            level,
+        );
+        if let Some(&[(x, y), _]) = segments.first() {
+            chart.draw_series(std::iter::once(Text::new(
+                format!("{:.0}", level),
+                (x, y),
+                options.font(12),
+            )))?;
+        }
+        chart.draw_series(
+            segments
+                .into_iter()
+                .map(|segment| PathElement::new(segment.to_vec(), line_style)),
+        )?;
+    }
+
     root.present()?;
-    println!("Hourly averages plot saved as {}", filename);
     Ok(())
 }
+
+/// Cell borders around the grid points, half way between neighbouring points
+fn cell_edges(points: &[f64]) -> Vec<f64> {
+    let half_width = |index: usize| match points.len() {
+        0 | 1 => 0.5,
+        _ => (points[index.min(points.len() - 2) + 1] - points[index.min(points.len() - 2)]) / 2.0,
+    };
+    match points.first() {
+        None => Vec::new(),
+        Some(&first) => std::iter::once(first - half_width(0))
+            .chain(
+                points
+                    .iter()
+                    .enumerate()
+                    .map(|(index, &point)| point + half_width(index)),
+            )
+            .collect(),
+    }
+}
+
+/// Line segments where the values, given on the grid points `values[y][x]`, cross `level`
+/// (marching squares with linear interpolation along the cell edges)
+fn contour_segments(x: &[f64], y: &[f64], values: &[Vec<f64>], level: f64) -> Vec<[(f64, f64); 2]> {
+    let mut segments = Vec::new();
+    for j in 0..y.len().saturating_sub(1) {
+        for i in 0..x.len().saturating_sub(1) {
+            // Cell corners counter-clockwise, the edges connect neighbouring corners
+            let corners = [
+                (x[i], y[j], values[j][i]),
+                (x[i + 1], y[j], values[j][i + 1]),
+                (x[i + 1], y[j + 1], values[j + 1][i + 1]),
+                (x[i], y[j + 1], values[j + 1][i]),
+            ];
+            let crossings: Vec<(f64, f64)> = (0..4)
+                .filter_map(|edge| {
+                    let (x0, y0, v0) = corners[edge];
+                    let (x1, y1, v1) = corners[(edge + 1) % 4];
+                    ((v0 >= level) != (v1 >= level)).then(|| {
+                        let fraction = (level - v0) / (v1 - v0);
+                        (x0 + fraction * (x1 - x0), y0 + fraction * (y1 - y0))
+                    })
+                })
+                .collect();
+            // Two crossings, or four on a saddle cell
+            for pair in crossings.chunks_exact(2) {
+                segments.push([pair[0], pair[1]]);
+            }
+        }
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::general::units::Ratio;
+
+    #[test]
+    fn test_plot_options_scaling() {
+        let options = PlotOptions {
+            size: Some((400, 300)),
+            dpi: 192,
+            ..Default::default()
+        };
+        assert_eq!(options.canvas_size((1000, 600)), (800, 600));
+        assert_eq!(options.px(3), 6);
+        assert_eq!(PlotOptions::default().canvas_size((1000, 600)), (1000, 600));
+    }
+
+    #[test]
+    fn test_plot_hourly_averages_svg_dark() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hourly.svg");
+        let demand: Vec<f64> = (0..48).map(|h| 0.5 + (h % 24) as f64 * 0.01).collect();
+        let pv: Vec<f64> = (0..48).map(|h| ((h % 24) as f64 - 12.0).abs()).collect();
+        let zeros = vec![0.0; 48];
+        let options = PlotOptions {
+            format: PlotFormat::Svg,
+            theme: PlotTheme::dark(),
+            ..Default::default()
+        };
+
+        plot_hourly_averages_with_options(
+            &demand,
+            &pv,
+            &zeros,
+            &zeros,
+            path.to_str().unwrap(),
+            None,
+            &options,
+        )
+        .unwrap();
+
+        let svg = std::fs::read_to_string(&path).unwrap();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("#1E1E1E"));
+    }
+
+    #[test]
+    fn test_plot_day_with_battery_power() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("day.svg");
+        let results = SimpleOptimizationResults {
+            hourly_total_electricity_demand: vec![500.0; 48],
+            hourly_total_pv_production: vec![800.0; 48],
+            hourly_grid_consumption: vec![0.0; 48],
+            hourly_battery_storage: vec![1000.0; 48],
+            hourly_battery_in: vec![300.0; 48],
+            hourly_battery_out: vec![100.0; 48],
+            ..Default::default()
+        };
+        assert!(DailyEnergyProfile::from_results(&results, 2).is_none());
+        let profile = DailyEnergyProfile::from_results(&results, 1).unwrap();
+        let options = PlotOptions {
+            format: PlotFormat::Svg,
+            ..Default::default()
+        };
+
+        plot_day_with_battery_power(
+            &profile,
+            path.to_str().unwrap(),
+            "Day 1",
+            Some(1500.0),
+            &options,
+        )
+        .unwrap();
+
+        let svg = std::fs::read_to_string(&path).unwrap();
+        assert!(svg.contains("Battery Charge / Discharge"));
+        assert!(svg.contains("C-rate limit"));
+    }
+
+    #[test]
+    fn test_energy_balance() {
+        // hour 0: PV covers the load and charges the battery, hour 1: the battery and the grid
+        // cover the load while the grid also charges the battery
+        let results = SimpleOptimizationResults {
+            hourly_total_electricity_demand: vec![500.0, 600.0],
+            hourly_total_pv_production: vec![1000.0, 0.0],
+            hourly_overproduction: vec![200.0, 0.0],
+            hourly_grid_consumption: vec![0.0, 450.0],
+            hourly_battery_in: vec![300.0, 50.0],
+            hourly_battery_out: vec![0.0, 200.0],
+            ..Default::default()
+        };
+
+        let balance = EnergyBalance::from_results(&results, 0, 2).unwrap();
+        assert_eq!(balance.pv_direct_use, vec![500.0, 0.0]);
+        assert_eq!(balance.battery_charge, vec![300.0, 0.0]);
+        assert_eq!(balance.grid_import, vec![0.0, 400.0]);
+        assert_eq!(balance.battery_discharge, vec![0.0, 200.0]);
+        assert_eq!(balance.export, vec![200.0, 0.0]);
+        assert!(balance.max_imbalance() < 1e-9);
+        assert!(EnergyBalance::from_results(&results, 1, 2).is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("balance.svg");
+        let options = PlotOptions {
+            format: PlotFormat::Svg,
+            ..Default::default()
+        };
+        plot_energy_balance(&balance, path.to_str().unwrap(), "Balance", &options).unwrap();
+        let svg = std::fs::read_to_string(&path).unwrap();
+        assert!(svg.contains("Demand Coverage"));
+    }
+
+    #[test]
+    fn test_seasons() {
+        assert_eq!(Season::from_day(0), Season::Winter); // Jan 1
+        assert_eq!(Season::from_day(58), Season::Winter); // Feb 28
+        assert_eq!(Season::from_day(59), Season::Spring); // Mar 1
+        assert_eq!(Season::from_day(151), Season::Summer); // Jun 1
+        assert_eq!(Season::from_day(243), Season::Fall); // Sep 1
+        assert_eq!(Season::from_day(334), Season::Winter); // Dec 1
+
+        // 1.0 in summer, 0.0 otherwise
+        let values: Vec<f64> = (0..8760)
+            .map(|hour| {
+                if Season::from_day(hour / 24) == Season::Summer {
+                    1.0
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        assert_eq!(seasonal_average_profile(&values, Season::Summer), [1.0; 24]);
+        assert_eq!(seasonal_average_profile(&values, Season::Winter), [0.0; 24]);
+    }
+
+    #[test]
+    fn test_plot_seasonal_and_week_profiles() {
+        let dir = tempfile::tempdir().unwrap();
+        let results = SimpleOptimizationResults {
+            hourly_total_electricity_demand: vec![500.0; 8760],
+            hourly_total_pv_production: (0..8760).map(|h| (h % 24) as f64 * 10.0).collect(),
+            hourly_grid_consumption: vec![100.0; 8760],
+            hourly_battery_storage: vec![0.0; 8760],
+            ..Default::default()
+        };
+        let options = PlotOptions {
+            format: PlotFormat::Svg,
+            ..Default::default()
+        };
+
+        let seasonal = dir.path().join("seasons.svg");
+        plot_seasonal_profiles(&results, seasonal.to_str().unwrap(), &options).unwrap();
+        let svg = std::fs::read_to_string(&seasonal).unwrap();
+        assert!(svg.contains("Winter") && svg.contains("Fall"));
+
+        let week = dir.path().join("week.svg");
+        plot_week_profile(&results, 100, week.to_str().unwrap(), &options).unwrap();
+        assert!(plot_week_profile(&results, 360, week.to_str().unwrap(), &options).is_err());
+
+        let worst_week = crate::simple::day_selection::find_worst_week(&results).unwrap();
+        let path = dir.path().join("worst_week.svg");
+        plot_worst_week(&results, &worst_week, path.to_str().unwrap(), &options).unwrap();
+        assert!(
+            std::fs::read_to_string(&path)
+                .unwrap()
+                .contains("Worst PV Week - Jan 1 to Jan 7")
+        );
+    }
+
+    #[test]
+    fn test_duration_curves() {
+        assert_eq!(
+            duration_curve(&[1.0, 3.0, -2.0, 2.0]),
+            vec![3.0, 2.0, 1.0, -2.0]
+        );
+
+        let results = SimpleOptimizationResults {
+            hourly_total_electricity_demand: vec![500.0, 400.0, 300.0],
+            hourly_total_pv_production: vec![0.0, 600.0, 100.0],
+            hourly_overproduction: vec![0.0, 200.0, 0.0],
+            ..Default::default()
+        };
+        let curves = DurationCurves::from_results(&results);
+        assert_eq!(curves.demand, vec![500.0, 400.0, 300.0]);
+        assert_eq!(curves.residual_load, vec![500.0, 200.0, -200.0]);
+        assert_eq!(curves.export, vec![200.0, 0.0, 0.0]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("duration.svg");
+        let options = PlotOptions {
+            format: PlotFormat::Svg,
+            ..Default::default()
+        };
+        plot_duration_curves(&curves, path.to_str().unwrap(), &options).unwrap();
+        assert!(
+            std::fs::read_to_string(&path)
+                .unwrap()
+                .contains("Duration Curves")
+        );
+    }
+
+    #[test]
+    fn test_plot_comparison() {
+        let a = SimpleOptimizationResults {
+            annual_electricity_demand_kwh: 4000.0,
+            autarky: Ratio::from_percent(50.0),
+            ..Default::default()
+        };
+        let b = SimpleOptimizationResults {
+            annual_electricity_demand_kwh: 6000.0,
+            autarky: Ratio::from_percent(45.0),
+            ..Default::default()
+        };
+        let report = a.compare(&b).with_labels("Without EV", "With EV");
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("comparison.svg");
+        let options = PlotOptions {
+            format: PlotFormat::Svg,
+            ..Default::default()
+        };
+        plot_comparison(&report, path.to_str().unwrap(), &options).unwrap();
+        let svg = std::fs::read_to_string(&path).unwrap();
+        assert!(svg.contains("Without EV vs With EV"));
+        assert!(svg.contains("Autarky"));
+    }
+
+    #[test]
+    fn test_plot_data_export() {
+        let dir = tempfile::tempdir().unwrap();
+        let curves = DurationCurves {
+            demand: vec![3.0, 2.0, 1.0],
+            residual_load: vec![1.0, 0.0, -1.0],
+            export: vec![2.0, 0.0, 0.0],
+        };
+        let png = dir.path().join("duration.png");
+        let options = PlotOptions {
+            data_format: Some(PlotDataFormat::Csv),
+            ..Default::default()
+        };
+        plot_duration_curves(&curves, png.to_str().unwrap(), &options).unwrap();
+        let csv = std::fs::read_to_string(dir.path().join("duration.csv")).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[0],
+            "Hours (sorted),Electricity Demand,Residual Load after PV,Export"
+        );
+        assert_eq!(lines[3], "2,1,-1,0");
+        assert_eq!(lines.len(), 4);
+
+        let report = SimpleOptimizationResults::default()
+            .compare(&SimpleOptimizationResults::default())
+            .with_labels("A, base", "B");
+        let svg = dir.path().join("comparison.svg");
+        let options = PlotOptions {
+            format: PlotFormat::Svg,
+            data_format: Some(PlotDataFormat::Json),
+            ..Default::default()
+        };
+        plot_comparison(&report, svg.to_str().unwrap(), &options).unwrap();
+        let json: serde_json::Value = serde_json::from_str(
+            &std::fs::read_to_string(dir.path().join("comparison.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(json["x"].as_array().unwrap().len(), report.kpis.len());
+        assert_eq!(json["series"][0]["name"], "A, base");
+
+        // Without a data format only the image is written
+        plot_duration_curves(
+            &curves,
+            dir.path().join("plain.png").to_str().unwrap(),
+            &PlotOptions::default(),
+        )
+        .unwrap();
+        assert!(!dir.path().join("plain.csv").exists());
+        assert!(!dir.path().join("plain.json").exists());
+    }
+
+    #[test]
+    fn test_plot_data_csv_quoting() {
+        let data = PlotData::new("T", "KPI", PlotAxis::Labels(vec!["Cost (€, net)".into()]))
+            .with_series("Say \"hi\"", &[1.5, 2.0]);
+        assert_eq!(
+            data.to_csv(),
+            "KPI,\"Say \"\"hi\"\"\"\n\"Cost (€, net)\",1.5\n,2\n"
+        );
+    }
+
+    #[test]
+    fn test_contour_segments() {
+        // values grow with x, so the 15 level is a vertical line at x = 1.5
+        let x = [0.0, 1.0, 2.0];
+        let y = [0.0, 1.0];
+        let values = vec![vec![0.0, 10.0, 20.0], vec![0.0, 10.0, 20.0]];
+        let segments = contour_segments(&x, &y, &values, 15.0);
+        assert_eq!(segments.len(), 1);
+        for (x, _) in segments[0] {
+            assert!((x - 1.5).abs() < 1e-12);
+        }
+        assert!(contour_segments(&x, &y, &values, 25.0).is_empty());
+
+        assert_eq!(cell_edges(&[0.0, 2.0, 4.0]), vec![-1.0, 1.0, 3.0, 5.0]);
+        assert_eq!(cell_edges(&[5.0]), vec![4.5, 5.5]);
+        assert!(cell_edges(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_plot_capacity_map() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("autarky_map.svg");
+        let map = CapacityMap {
+            pv_capacities_kw: vec![0.0, 5.0, 10.0],
+            battery_capacities_kwh: vec![0.0, 10.0],
+            autarky: vec![vec![0.0, 30.0, 40.0], vec![0.0, 55.0, 70.0]],
+            self_consumption: vec![vec![0.0, 60.0, 35.0], vec![0.0, 90.0, 60.0]],
+            annual_cost: vec![vec![1200.0, 1000.0, 1050.0], vec![1400.0, 1100.0, 1080.0]],
+        };
+        let options = PlotOptions {
+            format: PlotFormat::Svg,
+            ..Default::default()
+        };
+        plot_capacity_map(
+            &map,
+            CapacityMapKpi::Autarky,
+            path.to_str().unwrap(),
+            &options,
+        )
+        .unwrap();
+        let svg = std::fs::read_to_string(&path).unwrap();
+        assert!(svg.contains("Autarky (%)"));
+        assert!(svg.contains("PV Capacity (kWp)"));
+        assert!(svg.contains("<polyline"));
+    }
+}
@@ -0,0 +1,280 @@
+use ems_model::building::electricity::ElectricityRate;
+use good_lp::{Expression, ProblemVariables, Solution, Solver, SolverModel, constraint};
+
+use crate::general::units::Ratio;
+use crate::simple::simple_opt_re::SiteModel;
+use crate::simple::solar_system_utils::{OptimizationConfig, SimpleOptimizationResults};
+
+/// A building of the portfolio with its own demand, irradiance and tariff
+#[derive(Debug, Clone)]
+pub struct PortfolioSite {
+    /// Name of the site, used to label the results
+    pub name: String,
+    /// Configuration of the site, as for `run_simple_opt`; its own `investment_budget` still
+    /// applies on top of the shared budget
+    pub config: OptimizationConfig,
+    /// Maximum PV capacity of the site in W
+    pub pv_cap_w_max: f64,
+    /// Hourly normalized solar irradiance for 8760 hours
+    pub solar_irradiance: Vec<f64>,
+    /// Hourly base electricity demand profile
+    pub electricity_demand: Vec<f64>,
+    /// Grid tariff of the site
+    pub electricity_rate: ElectricityRate,
+}
+
+/// Results of one site of a portfolio optimization
+#[derive(Debug, Clone)]
+pub struct PortfolioSiteResults {
+    pub name: String,
+    pub investment: f64, // Share of the shared budget, including the dispatched heat pump
+    pub annual_cost: f64, // Objective value of the site
+    pub results: SimpleOptimizationResults,
+}
+
+/// Results of a portfolio optimization, per site and aggregated over all sites
+#[derive(Debug, Clone)]
+pub struct PortfolioResults {
+    pub sites: Vec<PortfolioSiteResults>,
+    pub budget: f64,
+
+    // Aggregated KPIs
+    pub total_investment: f64,
+    pub total_annual_cost: f64,
+    pub pv_capacity_kw: f64,
+    pub battery_capacity_kwh: f64,
+    pub annual_electricity_demand_kwh: f64,
+    pub annual_grid_energy_kwh: f64,
+    pub autarky: Ratio, // Demand-weighted over all sites
+    pub optimization_duration: std::time::Duration,
+}
+
+impl PortfolioResults {
+    /// Get the results of a site by its name
+    pub fn site(&self, name: &str) -> Option<&PortfolioSiteResults> {
+        self.sites.iter().find(|site| site.name == name)
+    }
+
+    /// Renders the per-site results and the portfolio totals as a plain text table
+    pub fn render(&self) -> String {
+        let mut lines = vec![format!(
+            "{:<20} {:>12} {:>12} {:>14} {:>12} {:>10}",
+            "Site", "PV (kWp)", "Bat (kWh)", "Investment", "Annual cost", "Autarky"
+        )];
+        for site in &self.sites {
+            lines.push(format!(
+                "{:<20} {:>12.2} {:>12.2} {:>14.2} {:>12.2} {:>9.1}%",
+                site.name,
+                site.results.pv_capacity_kw,
+                site.results.battery_capacity_kwh,
+                site.investment,
+                site.annual_cost,
+                site.results.autarky.percent()
+            ));
+        }
+        lines.push(format!(
+            "{:<20} {:>12.2} {:>12.2} {:>14.2} {:>12.2} {:>9.1}%",
+            "Total",
+            self.pv_capacity_kw,
+            self.battery_capacity_kwh,
+            self.total_investment,
+            self.total_annual_cost,
+            self.autarky.percent()
+        ));
+        lines.push(format!("Budget {:.2}", self.budget));
+        lines.join("\n")
+    }
+}
+
+/// Optimizes several sites at once under a shared capital budget.
+///
+/// All sites are added to a single LP which minimizes the sum of their annual costs, so the
+/// budget goes to the sites where it saves the most. The budget covers new PV, battery, wind
+/// and solar thermal capacities of all sites plus their dispatched heat pumps, which are built
+/// in any case.
+///
+/// # Arguments
+///
+/// * `sites` - Buildings with their configs, demand, irradiance and tariffs
+/// * `budget` - Maximum total investment over all sites
+/// * `solver` - LP solver to use
+pub fn run_portfolio_opt<S: Solver>(
+    sites: Vec<PortfolioSite>,
+    budget: f64,
+    solver: S,
+) -> Result<PortfolioResults, Box<dyn std::error::Error>> {
+    if sites.is_empty() {
+        return Err("Portfolio optimization requires at least one site".into());
+    }
+    let heat_pump_investment: f64 = sites
+        .iter()
+        .map(|site| site.config.heat_pump_investment())
+        .sum();
+    if !(budget.is_finite() && budget >= heat_pump_investment) {
+        return Err(format!(
+            "Portfolio budget must cover the heat pump investment of {:.2}, got {}",
+            heat_pump_investment, budget
+        )
+        .into());
+    }
+
+    let mut vars = ProblemVariables::new();
+    let mut names = Vec::with_capacity(sites.len());
+    let mut models = Vec::with_capacity(sites.len());
+    for site in sites {
+        models.push(SiteModel::new(
+            site.config,
+            site.pv_cap_w_max,
+            site.solar_irradiance,
+            site.electricity_demand,
+            site.electricity_rate,
+            &mut vars,
+        )?);
+        names.push(site.name);
+    }
+
+    let objectives: Vec<Expression> = models.iter().map(SiteModel::objective).collect();
+    let investments: Vec<Expression> = models.iter().map(SiteModel::investment).collect();
+    let objective: Expression = objectives.iter().cloned().sum();
+    let total_investment: Expression = investments.iter().cloned().sum();
+
+    let mut model = vars.minimise(objective).using(solver);
+    for site in &models {
+        model = site.add_constraints(model).0;
+    }
+    model = model.with(constraint!(
+        total_investment <= budget - heat_pump_investment
+    ));
+
+    let start_time = std::time::Instant::now();
+    let solution = model
+        .solve()
+        .map_err(|e| format!("Portfolio optimization failed: {:?}", e))?;
+    let optimization_duration = start_time.elapsed();
+
+    let site_results: Vec<PortfolioSiteResults> = models
+        .iter()
+        .zip(names)
+        .zip(objectives.iter().zip(&investments))
+        .map(|((site, name), (objective, investment))| {
+            let annual_cost = solution.eval(objective);
+            PortfolioSiteResults {
+                name,
+                investment: solution.eval(investment) + site.config().heat_pump_investment(),
+                annual_cost,
+                results: site.results(&solution, annual_cost, Vec::new(), optimization_duration),
+            }
+        })
+        .collect();
+
+    let sum = |kpi: fn(&PortfolioSiteResults) -> f64| site_results.iter().map(kpi).sum::<f64>();
+    let annual_electricity_demand_kwh = sum(|site| site.results.annual_electricity_demand_kwh);
+    let autarky = Ratio::of(
+        sum(|site| site.results.autarky.fraction() * site.results.annual_electricity_demand_kwh),
+        annual_electricity_demand_kwh,
+    );
+    Ok(PortfolioResults {
+        budget,
+        total_investment: sum(|site| site.investment),
+        total_annual_cost: sum(|site| site.annual_cost),
+        pv_capacity_kw: sum(|site| site.results.pv_capacity_kw),
+        battery_capacity_kwh: sum(|site| site.results.battery_capacity_kwh),
+        annual_electricity_demand_kwh,
+        annual_grid_energy_kwh: sum(|site| site.results.annual_grid_energy_kwh),
+        autarky,
+        optimization_duration,
+        sites: site_results,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::general::units::Energy;
+    use crate::simple::solar_system_utils::{
+        DemandCharges, HeatPumpDispatch, load_demand_from_csv, load_solar_radiance_from_csv,
+    };
+
+    #[test]
+    fn test_run_portfolio_opt() {
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+        let site = |name: &str, rate: f64| PortfolioSite {
+            name: name.to_string(),
+            config: OptimizationConfig {
+                bat_value: Energy::ZERO,
+                ..Default::default()
+            },
+            pv_cap_w_max: 10000.0,
+            solar_irradiance: solar_irradiance.clone(),
+            electricity_demand: electricity_demand.clone(),
+            electricity_rate: ElectricityRate::fixed(rate),
+        };
+        let sites = vec![site("Expensive", 0.4), site("Cheap", 0.15)];
+
+        // a budget too small for both sites goes to the site with the expensive tariff
+        let inv_pv = sites[0].config.inv_pv;
+        let budget = 2.0 * inv_pv;
+        let results = run_portfolio_opt(sites, budget, good_lp::clarabel).unwrap();
+        assert_eq!(results.sites.len(), 2);
+        assert!(results.total_investment <= budget + 1e-3);
+        let expensive = results.site("Expensive").unwrap();
+        let cheap = results.site("Cheap").unwrap();
+        assert!(expensive.results.pv_capacity_kw > cheap.results.pv_capacity_kw);
+        assert!((expensive.investment - expensive.results.pv_capacity_kw * inv_pv).abs() < 1e-2);
+        assert!(
+            (results.total_annual_cost - expensive.annual_cost - cheap.annual_cost).abs() < 1e-6
+        );
+        assert!(
+            (results.pv_capacity_kw
+                - expensive.results.pv_capacity_kw
+                - cheap.results.pv_capacity_kw)
+                .abs()
+                < 1e-6
+        );
+        assert!(results.render().contains("Expensive"));
+    }
+
+    #[test]
+    fn test_run_portfolio_opt_rejects_invalid_input() {
+        let site = |config: OptimizationConfig| PortfolioSite {
+            name: "Site".to_string(),
+            config,
+            pv_cap_w_max: 10000.0,
+            solar_irradiance: vec![0.0; 8760],
+            electricity_demand: vec![0.0; 8760],
+            electricity_rate: ElectricityRate::fixed(0.3),
+        };
+        let err = run_portfolio_opt(Vec::new(), 1000.0, good_lp::clarabel).unwrap_err();
+        assert!(err.to_string().contains("at least one site"));
+        for budget in [-1.0, f64::NAN, f64::INFINITY] {
+            let sites = vec![site(OptimizationConfig::default())];
+            assert!(run_portfolio_opt(sites, budget, good_lp::clarabel).is_err());
+        }
+
+        // the budget has to cover the heat pumps of all sites together
+        let with_heat_pump = OptimizationConfig {
+            inv_heat_pump: 1000.0,
+            heat_pump_dispatch: Some(HeatPumpDispatch::default()),
+            ..Default::default()
+        };
+        let heat_pump_investment = with_heat_pump.heat_pump_investment();
+        assert!(heat_pump_investment > 0.0);
+        let sites = vec![site(with_heat_pump.clone()), site(with_heat_pump)];
+        let err =
+            run_portfolio_opt(sites, 1.5 * heat_pump_investment, good_lp::clarabel).unwrap_err();
+        assert!(err.to_string().contains("heat pump investment"));
+
+        // an invalid site config fails before the shared problem is solved
+        let broken = OptimizationConfig {
+            demand_charges: Some(DemandCharges {
+                price_per_kw_month: -1.0,
+                contracted_kw: 0.0,
+                excess_price_per_kw: 0.0,
+            }),
+            ..Default::default()
+        };
+        let sites = vec![site(OptimizationConfig::default()), site(broken)];
+        assert!(run_portfolio_opt(sites, 1000.0, good_lp::clarabel).is_err());
+    }
+}
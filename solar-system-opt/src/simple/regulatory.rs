@@ -0,0 +1,308 @@
+use ems_model::general::location::Country;
+
+use crate::general::currency::Currency;
+use crate::simple::solar_system_utils::OptimizationConfig;
+
+/// How exported energy is remunerated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompensationMechanism {
+    NoCompensation,         // Exports are not paid
+    FeedInTariff,           // Fixed tariff per exported kWh (Germany, EEG)
+    SimplifiedCompensation, // Monthly credit up to the import cost (Spain, RD 244/2019)
+    SurplusSale,            // Surplus sold at a market based price (Portugal, UPAC)
+}
+
+/// Typical regulatory parameters of a small residential PV system in a country and year
+///
+/// The values are defaults for a first estimate, not a replacement for the actual contract.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegulatoryPreset {
+    pub country: Country,
+    pub year: i32,
+    pub compensation: CompensationMechanism,
+    pub feed_in_tariff: f64,              // Remuneration per exported kWh
+    pub feed_in_limit_share: Option<f64>, // Export limit as share of the PV peak; None for no limit
+    pub unpaid_at_negative_prices: bool,  // Exports aren't paid in hours with negative spot prices
+    pub vat_rate_electricity: f64,        // VAT on the electricity bill (0-1)
+    pub vat_rate_investment: f64,         // VAT on PV and battery investments (0-1)
+    pub energy_price_per_kwh: f64,        // Net energy price incl. levies and taxes other than VAT
+    pub grid_fee_per_kwh: f64,            // Net network fee per imported kWh
+    pub currency: Currency,               // Currency of the prices above
+}
+
+impl RegulatoryPreset {
+    /// Preset for a system commissioned in `year` in `country`. Countries without own data get
+    /// a neutral preset matching the defaults of `OptimizationConfig`.
+    pub fn for_country(country: Country, year: i32) -> Self {
+        match country {
+            Country::Germany => Self {
+                compensation: CompensationMechanism::FeedInTariff,
+                feed_in_tariff: match year {
+                    ..=2021 => 0.069,
+                    2022 | 2023 => 0.082,
+                    2024 => 0.0811,
+                    2025 => 0.0794,
+                    _ => 0.0778,
+                },
+                // 70 % rule until 2022, 60 % without smart meter since the Solarspitzengesetz
+                feed_in_limit_share: match year {
+                    ..=2022 => Some(0.7),
+                    2023 | 2024 => None,
+                    _ => Some(0.6),
+                },
+                unpaid_at_negative_prices: year >= 2025,
+                vat_rate_electricity: 0.19,
+                vat_rate_investment: if year >= 2023 { 0.0 } else { 0.19 },
+                energy_price_per_kwh: match year {
+                    ..=2021 => 0.17,
+                    2022 => 0.25,
+                    2023 => 0.27,
+                    _ => 0.23,
+                },
+                grid_fee_per_kwh: if year >= 2024 { 0.116 } else { 0.09 },
+                currency: Currency::eur(),
+                country,
+                year,
+            },
+            Country::Spain => Self {
+                compensation: if year >= 2019 {
+                    CompensationMechanism::SimplifiedCompensation
+                } else {
+                    CompensationMechanism::NoCompensation
+                },
+                feed_in_tariff: match year {
+                    ..=2018 => 0.0,
+                    2022 => 0.11,
+                    2023 => 0.07,
+                    _ => 0.05,
+                },
+                feed_in_limit_share: None,
+                unpaid_at_negative_prices: false,
+                // temporarily reduced VAT on electricity during the energy crisis
+                vat_rate_electricity: if (2022..=2023).contains(&year) {
+                    0.10
+                } else {
+                    0.21
+                },
+                vat_rate_investment: 0.21,
+                energy_price_per_kwh: match year {
+                    2022 => 0.2,
+                    2023 => 0.14,
+                    _ => 0.12,
+                },
+                grid_fee_per_kwh: 0.045,
+                currency: Currency::eur(),
+                country,
+                year,
+            },
+            Country::Portugal => Self {
+                compensation: CompensationMechanism::SurplusSale,
+                feed_in_tariff: if year == 2022 { 0.1 } else { 0.045 },
+                feed_in_limit_share: None,
+                unpaid_at_negative_prices: false,
+                vat_rate_electricity: 0.23,
+                // reduced VAT on solar panels from 2023 to 2025
+                vat_rate_investment: if (2023..=2025).contains(&year) {
+                    0.06
+                } else {
+                    0.23
+                },
+                energy_price_per_kwh: 0.12,
+                grid_fee_per_kwh: 0.05,
+                currency: Currency::eur(),
+                country,
+                year,
+            },
+            // prices in CHF: ElCom median household tariffs, feed-in tariffs vary by utility
+            Country::Switzerland => Self {
+                compensation: CompensationMechanism::FeedInTariff,
+                feed_in_tariff: match year {
+                    ..=2022 => 0.09,
+                    2023 => 0.14,
+                    2024 => 0.12,
+                    _ => 0.09,
+                },
+                feed_in_limit_share: None,
+                unpaid_at_negative_prices: false,
+                vat_rate_electricity: if year >= 2024 { 0.081 } else { 0.077 },
+                vat_rate_investment: if year >= 2024 { 0.081 } else { 0.077 },
+                energy_price_per_kwh: match year {
+                    ..=2022 => 0.112,
+                    2023 => 0.17,
+                    2024 => 0.18,
+                    _ => 0.155,
+                },
+                grid_fee_per_kwh: if year >= 2024 { 0.12 } else { 0.099 },
+                currency: Currency::new("CHF"),
+                country,
+                year,
+            },
+            // no data yet: the defaults of OptimizationConfig, investment costs left as they are
+            _ => Self {
+                compensation: CompensationMechanism::FeedInTariff,
+                feed_in_tariff: 0.079,
+                feed_in_limit_share: None,
+                unpaid_at_negative_prices: false,
+                vat_rate_electricity: 0.2,
+                vat_rate_investment: 0.0,
+                energy_price_per_kwh: 0.17,
+                grid_fee_per_kwh: 0.08,
+                currency: Currency::eur(),
+                country,
+                year,
+            },
+        }
+    }
+
+    /// Gross import price per kWh: energy price and grid fee plus VAT
+    pub fn electricity_price(&self) -> f64 {
+        (self.energy_price_per_kwh + self.grid_fee_per_kwh) * (1.0 + self.vat_rate_electricity)
+    }
+
+    /// Applies the preset onto `config`: import price, feed-in rules and currency. The investment
+    /// costs of the config are treated as net prices and get the investment VAT added.
+    ///
+    /// The currency of the config is kept if its costs are given in the currency of the preset:
+    /// the same code, or any code with a conversion rate for euro presets. Otherwise it is
+    /// replaced by the currency of the preset, and euro investment costs of a config converting
+    /// into that currency are converted.
+    pub fn apply(&self, config: OptimizationConfig) -> OptimizationConfig {
+        let cost_currency = if config.currency.conversion_rate.is_some() {
+            "EUR"
+        } else {
+            config.currency.code.as_str()
+        };
+        let (currency, conversion) = if cost_currency == self.currency.code {
+            (config.currency.clone(), 1.0)
+        } else if config.currency.code == self.currency.code {
+            (self.currency.clone(), config.currency.convert(1.0))
+        } else {
+            (self.currency.clone(), 1.0)
+        };
+        let vat = (1.0 + self.vat_rate_investment) * conversion;
+        OptimizationConfig {
+            fc_grid: self.electricity_price(),
+            feed_in_tariff: self.feed_in_tariff,
+            feed_in_limit_share: self.feed_in_limit_share,
+            curtail_unpaid_feed_in: self.unpaid_at_negative_prices,
+            monthly_feed_in_credit_cap: self.compensation
+                == CompensationMechanism::SimplifiedCompensation,
+            inv_pv: config.inv_pv * vat,
+            inv_bat: config.inv_bat * vat,
+            currency,
+            ..config
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regulatory_presets() {
+        let germany = RegulatoryPreset::for_country(Country::Germany, 2025);
+        assert_eq!(germany.compensation, CompensationMechanism::FeedInTariff);
+        assert_eq!(germany.feed_in_limit_share, Some(0.6));
+        assert!(germany.unpaid_at_negative_prices);
+        assert_eq!(
+            RegulatoryPreset::for_country(Country::Germany, 2024).feed_in_limit_share,
+            None
+        );
+        assert_eq!(
+            RegulatoryPreset::for_country(Country::Germany, 2020).feed_in_limit_share,
+            Some(0.7)
+        );
+
+        let config = germany.apply(OptimizationConfig::default());
+        assert!((config.fc_grid - (0.23 + 0.116) * 1.19).abs() < 1e-9);
+        assert_eq!(config.feed_in_tariff, 0.0794);
+        assert_eq!(config.feed_in_limit_share, Some(0.6));
+        assert!(config.curtail_unpaid_feed_in);
+        assert!(!config.monthly_feed_in_credit_cap);
+        assert_eq!(config.inv_pv, OptimizationConfig::default().inv_pv);
+
+        let spain = RegulatoryPreset::for_country(Country::Spain, 2024);
+        let config = spain.apply(OptimizationConfig::default());
+        assert!(config.monthly_feed_in_credit_cap);
+        assert_eq!(config.feed_in_limit_share, None);
+        assert!((config.inv_pv - OptimizationConfig::default().inv_pv * 1.21).abs() < 1e-9);
+        assert_eq!(
+            RegulatoryPreset::for_country(Country::Spain, 2018).compensation,
+            CompensationMechanism::NoCompensation
+        );
+
+        let default = OptimizationConfig::default();
+        let config = RegulatoryPreset::for_country(Country::France, 2025).apply(default.clone());
+        assert!((config.fc_grid - default.fc_grid).abs() < 1e-9);
+        assert_eq!(config.feed_in_tariff, default.feed_in_tariff);
+        assert_eq!(config.inv_pv, default.inv_pv);
+
+        // a config converting euro costs into francs keeps its currency
+        let chf = Currency::with_conversion_rate("CHF", 0.95).unwrap();
+        let config = germany.apply(OptimizationConfig {
+            currency: chf.clone(),
+            ..Default::default()
+        });
+        assert_eq!(config.currency, chf);
+        assert_eq!(config.inv_pv, OptimizationConfig::default().inv_pv);
+        let config = germany.apply(OptimizationConfig {
+            currency: Currency::new("CHF"),
+            ..Default::default()
+        });
+        assert_eq!(config.currency, Currency::eur());
+
+        // Swiss prices are in francs: euro investment costs are converted with the config rate
+        let switzerland = RegulatoryPreset::for_country(Country::Switzerland, 2025);
+        assert_eq!(switzerland.currency, Currency::new("CHF"));
+        let config = switzerland.apply(OptimizationConfig::default());
+        assert_eq!(config.currency, Currency::new("CHF"));
+        assert!((config.fc_grid - (0.155 + 0.12) * 1.081).abs() < 1e-9);
+        let config = switzerland.apply(OptimizationConfig {
+            currency: chf,
+            ..Default::default()
+        });
+        assert_eq!(config.currency, Currency::new("CHF"));
+        assert!((config.inv_pv - OptimizationConfig::default().inv_pv * 1.081 * 0.95).abs() < 1e-9);
+
+        let portugal = RegulatoryPreset::for_country(Country::Portugal, 2024);
+        assert_eq!(portugal.compensation, CompensationMechanism::SurplusSale);
+        assert!(
+            !portugal
+                .apply(OptimizationConfig::default())
+                .monthly_feed_in_credit_cap
+        );
+    }
+
+    #[test]
+    fn test_regulatory_presets_outside_known_years() {
+        // years before and after the tabulated ones use the oldest and newest values
+        let old = RegulatoryPreset::for_country(Country::Germany, 1990);
+        assert_eq!(old.feed_in_tariff, 0.069);
+        assert_eq!(old.feed_in_limit_share, Some(0.7));
+        assert!(!old.unpaid_at_negative_prices);
+        assert_eq!(old.vat_rate_investment, 0.19);
+        let future = RegulatoryPreset::for_country(Country::Germany, 2100);
+        assert_eq!(future.feed_in_tariff, 0.0778);
+        assert_eq!(future.feed_in_limit_share, Some(0.6));
+        assert_eq!(future.vat_rate_investment, 0.0);
+
+        let spain = RegulatoryPreset::for_country(Country::Spain, 1990);
+        assert_eq!(spain.compensation, CompensationMechanism::NoCompensation);
+        assert_eq!(spain.feed_in_tariff, 0.0);
+        let config = spain.apply(OptimizationConfig {
+            monthly_feed_in_credit_cap: true,
+            ..Default::default()
+        });
+        assert!(!config.monthly_feed_in_credit_cap);
+        assert_eq!(config.feed_in_tariff, 0.0);
+
+        // the preset only replaces prices and feed-in rules
+        let config = future.apply(OptimizationConfig {
+            annuity: 0.2,
+            ..Default::default()
+        });
+        assert_eq!(config.annuity, 0.2);
+        assert_eq!(config.inv_pv, OptimizationConfig::default().inv_pv);
+    }
+}
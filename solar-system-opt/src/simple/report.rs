@@ -0,0 +1,412 @@
+use ems_model::general::location::Country;
+
+use crate::general::finance::OptimizedROIResult;
+use crate::simple::solar_system_utils::SimpleOptimizationResults;
+
+/// Language and number format of the rendered report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    #[default]
+    English,
+    German,
+    Spanish,
+    Portuguese,
+}
+
+impl From<&Country> for Locale {
+    fn from(country: &Country) -> Self {
+        match country {
+            Country::Germany | Country::Austria | Country::Switzerland => Locale::German,
+            Country::Spain => Locale::Spanish,
+            Country::Portugal => Locale::Portuguese,
+            // no translation yet
+            _ => Locale::English,
+        }
+    }
+}
+
+impl From<Country> for Locale {
+    fn from(country: Country) -> Self {
+        Locale::from(&country)
+    }
+}
+
+impl Locale {
+    /// Get the decimal separator used in this locale
+    pub fn decimal_separator(&self) -> char {
+        match self {
+            Locale::English => '.',
+            Locale::German | Locale::Spanish | Locale::Portuguese => ',',
+        }
+    }
+
+    /// Get the thousands separator used in this locale
+    pub fn thousands_separator(&self) -> char {
+        match self {
+            Locale::English => ',',
+            Locale::German | Locale::Spanish => '.',
+            Locale::Portuguese => ' ',
+        }
+    }
+
+    /// Formats a number with the given number of decimals, e.g. `1234.5` -> `1.234,50` in German
+    pub fn format_number(&self, value: f64, decimals: usize) -> String {
+        let formatted = format!("{:.*}", decimals, value.abs());
+        let (integer, fraction) = match formatted.split_once('.') {
+            Some((integer, fraction)) => (integer, Some(fraction)),
+            None => (formatted.as_str(), None),
+        };
+
+        let mut grouped = String::with_capacity(formatted.len() + integer.len() / 3);
+        for (i, digit) in integer.chars().enumerate() {
+            if i > 0 && (integer.len() - i) % 3 == 0 {
+                grouped.push(self.thousands_separator());
+            }
+            grouped.push(digit);
+        }
+        if let Some(fraction) = fraction {
+            grouped.push(self.decimal_separator());
+            grouped.push_str(fraction);
+        }
+
+        // Avoid printing "-0,00" for values that round to zero
+        let is_zero = formatted.chars().all(|c| c == '0' || c == '.');
+        if value.is_sign_negative() && !is_zero {
+            format!("-{}", grouped)
+        } else {
+            grouped
+        }
+    }
+
+    fn labels(&self) -> &'static ReportLabels {
+        match self {
+            Locale::English => &ENGLISH,
+            Locale::German => &GERMAN,
+            Locale::Spanish => &SPANISH,
+            Locale::Portuguese => &PORTUGUESE,
+        }
+    }
+}
+
+struct ReportLabels {
+    title: &'static str,
+    system: &'static str,
+    pv_capacity: &'static str,
+    battery_capacity: &'static str,
+    wind_capacity: &'static str,
+    energy: &'static str,
+    demand: &'static str,
+    pv_production: &'static str,
+    grid_energy: &'static str,
+    overproduction: &'static str,
+    car_charging: &'static str,
+    public_charging: &'static str,
+    autarky: &'static str,
+    autarky_without_battery: &'static str,
+    finance: &'static str,
+    roi: &'static str,
+    net_present_value: &'static str,
+    payback_period: &'static str,
+    years: &'static str,
+    no_payback: &'static str,
+    nominal: &'static str,
+    real: &'static str,
+}
+
+const ENGLISH: ReportLabels = ReportLabels {
+    title: "Solar system summary",
+    system: "System",
+    pv_capacity: "PV capacity",
+    battery_capacity: "Battery capacity",
+    wind_capacity: "Wind turbine",
+    energy: "Annual energy",
+    demand: "Electricity demand",
+    pv_production: "PV production",
+    grid_energy: "Grid consumption",
+    overproduction: "Feed-in",
+    car_charging: "EV charging",
+    public_charging: "Public charging",
+    autarky: "Autarky",
+    autarky_without_battery: "Autarky without battery",
+    finance: "Economics",
+    roi: "Return on investment",
+    net_present_value: "Net present value",
+    payback_period: "Payback period",
+    years: "years",
+    no_payback: "not within the calculation period",
+    nominal: "nominal",
+    real: "inflation-adjusted",
+};
+
+const GERMAN: ReportLabels = ReportLabels {
+    title: "Zusammenfassung Solaranlage",
+    system: "Anlage",
+    pv_capacity: "PV-Leistung",
+    battery_capacity: "Speicherkapazität",
+    wind_capacity: "Windkraftanlage",
+    energy: "Jährliche Energiebilanz",
+    demand: "Stromverbrauch",
+    pv_production: "PV-Erzeugung",
+    grid_energy: "Netzbezug",
+    overproduction: "Netzeinspeisung",
+    car_charging: "E-Auto-Ladung",
+    public_charging: "Öffentliches Laden",
+    autarky: "Autarkie",
+    autarky_without_battery: "Autarkie ohne Speicher",
+    finance: "Wirtschaftlichkeit",
+    roi: "Rendite",
+    net_present_value: "Kapitalwert",
+    payback_period: "Amortisationszeit",
+    years: "Jahre",
+    no_payback: "nicht innerhalb des Betrachtungszeitraums",
+    nominal: "nominal",
+    real: "inflationsbereinigt",
+};
+
+const SPANISH: ReportLabels = ReportLabels {
+    title: "Resumen de la instalación solar",
+    system: "Instalación",
+    pv_capacity: "Potencia fotovoltaica",
+    battery_capacity: "Capacidad de batería",
+    wind_capacity: "Aerogenerador",
+    energy: "Energía anual",
+    demand: "Consumo eléctrico",
+    pv_production: "Producción fotovoltaica",
+    grid_energy: "Consumo de red",
+    overproduction: "Excedentes vertidos",
+    car_charging: "Carga del vehículo eléctrico",
+    public_charging: "Carga pública",
+    autarky: "Autoconsumo cubierto",
+    autarky_without_battery: "Autoconsumo cubierto sin batería",
+    finance: "Rentabilidad",
+    roi: "Retorno de la inversión",
+    net_present_value: "Valor actual neto",
+    payback_period: "Periodo de amortización",
+    years: "años",
+    no_payback: "no dentro del periodo de cálculo",
+    nominal: "nominal",
+    real: "ajustado por inflación",
+};
+
+const PORTUGUESE: ReportLabels = ReportLabels {
+    title: "Resumo do sistema solar",
+    system: "Sistema",
+    pv_capacity: "Potência fotovoltaica",
+    battery_capacity: "Capacidade da bateria",
+    wind_capacity: "Turbina eólica",
+    energy: "Energia anual",
+    demand: "Consumo de eletricidade",
+    pv_production: "Produção fotovoltaica",
+    grid_energy: "Consumo da rede",
+    overproduction: "Energia injetada na rede",
+    car_charging: "Carregamento do veículo elétrico",
+    public_charging: "Carregamento público",
+    autarky: "Autossuficiência",
+    autarky_without_battery: "Autossuficiência sem bateria",
+    finance: "Rentabilidade",
+    roi: "Retorno do investimento",
+    net_present_value: "Valor atual líquido",
+    payback_period: "Período de retorno",
+    years: "anos",
+    no_payback: "não dentro do período de cálculo",
+    nominal: "nominal",
+    real: "ajustado à inflação",
+};
+
+/// Renders the KPI summary of an optimization in the given language.
+///
+/// The finance block is only included if an ROI result is passed.
+pub fn render_summary(
+    results: &SimpleOptimizationResults,
+    roi: Option<&OptimizedROIResult>,
+    locale: Locale,
+) -> String {
+    let labels = locale.labels();
+    let number = |value: f64, decimals: usize| locale.format_number(value, decimals);
+    let mut lines = Vec::new();
+
+    lines.push(format!("=== {} ===", labels.title));
+    lines.push(format!("{}:", labels.system));
+    lines.push(format!(
+        "  {}: {} kWp",
+        labels.pv_capacity,
+        number(results.pv_capacity_kw, 2)
+    ));
+    lines.push(format!(
+        "  {}: {} kWh",
+        labels.battery_capacity,
+        number(results.battery_capacity_kwh, 2)
+    ));
+    if results.config.wind_turbine.is_some() {
+        lines.push(format!(
+            "  {}: {} kW",
+            labels.wind_capacity,
+            number(results.wind_capacity_kw, 2)
+        ));
+    }
+
+    lines.push(format!("{}:", labels.energy));
+    lines.push(format!(
+        "  {}: {} kWh",
+        labels.demand,
+        number(results.annual_electricity_demand_kwh, 0)
+    ));
+    lines.push(format!(
+        "  {}: {} kWh",
+        labels.pv_production,
+        number(results.annual_pv_production_kwh, 0)
+    ));
+    lines.push(format!(
+        "  {}: {} kWh",
+        labels.grid_energy,
+        number(results.annual_grid_energy_kwh, 0)
+    ));
+    lines.push(format!(
+        "  {}: {} kWh",
+        labels.overproduction,
+        number(results.annual_overproduction_kwh, 0)
+    ));
+    if !results.config.vehicles().is_empty() {
+        lines.push(format!(
+            "  {}: {} kWh",
+            labels.car_charging,
+            number(results.annual_car_charging_kwh, 0)
+        ));
+        if results.annual_public_charging_kwh > 0.0 {
+            lines.push(format!(
+                "  {}: {} kWh",
+                labels.public_charging,
+                number(results.annual_public_charging_kwh, 0)
+            ));
+        }
+    }
+    lines.push(format!(
+        "  {}: {} %",
+        labels.autarky,
+        number(results.autarky.percent(), 1)
+    ));
+    lines.push(format!(
+        "  {}: {} %",
+        labels.autarky_without_battery,
+        number(results.autarky_without_battery.percent(), 1)
+    ));
+
+    if let Some(roi) = roi {
+        lines.push(format!("{}:", labels.finance));
+        let figures = [
+            (
+                labels.nominal,
+                roi.roi,
+                roi.net_present_value,
+                roi.payback_period,
+            ),
+            (
+                labels.real,
+                roi.real_roi,
+                roi.real_net_present_value,
+                roi.real_payback_period,
+            ),
+        ];
+        for (kind, rate, net_present_value, payback_period) in figures {
+            lines.push(format!(
+                "  {} ({}): {} %",
+                labels.roi,
+                kind,
+                number(rate * 100.0, 1)
+            ));
+            lines.push(format!(
+                "  {} ({}): {} {}",
+                labels.net_present_value,
+                kind,
+                number(net_present_value, 2),
+                roi.currency.symbol()
+            ));
+            let payback = match payback_period {
+                Some(years) => format!("{} {}", number(years, 1), labels.years),
+                None => labels.no_payback.to_string(),
+            };
+            lines.push(format!(
+                "  {} ({}): {}",
+                labels.payback_period, kind, payback
+            ));
+        }
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::general::currency::Currency;
+    use crate::general::units::Ratio;
+
+    #[test]
+    fn test_format_number() {
+        assert_eq!(
+            Locale::English.format_number(1234567.891, 2),
+            "1,234,567.89"
+        );
+        assert_eq!(Locale::German.format_number(1234567.891, 2), "1.234.567,89");
+        assert_eq!(Locale::Spanish.format_number(1234.5, 1), "1.234,5");
+        assert_eq!(Locale::Portuguese.format_number(1234.5, 1), "1 234,5");
+        assert_eq!(Locale::German.format_number(-1234.0, 0), "-1.234");
+        assert_eq!(Locale::German.format_number(999.0, 0), "999");
+        assert_eq!(Locale::German.format_number(-0.001, 2), "0,00");
+    }
+
+    #[test]
+    fn test_locale_from_country() {
+        assert_eq!(Locale::from(Country::Germany), Locale::German);
+        assert_eq!(Locale::from(&Country::Spain), Locale::Spanish);
+        assert_eq!(Locale::from(Country::Portugal), Locale::Portuguese);
+        assert_eq!(Locale::from(Country::Austria), Locale::German);
+        assert_eq!(
+            Locale::from(Country::Other("BE".to_string())),
+            Locale::English
+        );
+    }
+
+    #[test]
+    fn test_render_summary() {
+        let results = SimpleOptimizationResults {
+            pv_capacity_kw: 5.5,
+            battery_capacity_kwh: 10.0,
+            annual_electricity_demand_kwh: 4173.4,
+            annual_pv_production_kwh: 2500.0,
+            autarky: Ratio::from_percent(62.5),
+            ..Default::default()
+        };
+        let roi = OptimizedROIResult {
+            roi: 0.053,
+            net_present_value: 12345.678,
+            payback_period: Some(11.26),
+            real_roi: 0.031,
+            real_net_present_value: 9876.5,
+            real_payback_period: Some(12.4),
+            residual_value: 0.0,
+            after_tax: None,
+            currency: Currency::default(),
+        };
+
+        let german = render_summary(&results, Some(&roi), Locale::German);
+        assert!(german.contains("PV-Leistung: 5,50 kWp"));
+        assert!(german.contains("Stromverbrauch: 4.173 kWh"));
+        assert!(german.contains("Autarkie: 62,5 %"));
+        assert!(german.contains("Kapitalwert (nominal): 12.345,68 €"));
+        assert!(german.contains("Amortisationszeit (nominal): 11,3 Jahre"));
+        assert!(german.contains("Rendite (inflationsbereinigt): 3,1 %"));
+
+        let english = render_summary(&results, None, Locale::English);
+        assert!(english.contains("PV capacity: 5.50 kWp"));
+        assert!(english.contains("Electricity demand: 4,173 kWh"));
+        assert!(!english.contains("Economics"));
+
+        let roi_chf = OptimizedROIResult {
+            currency: Currency::new("CHF"),
+            ..roi
+        };
+        let german_chf = render_summary(&results, Some(&roi_chf), Locale::German);
+        assert!(german_chf.contains("Kapitalwert (nominal): 12.345,68 CHF"));
+    }
+}
@@ -0,0 +1,506 @@
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rusqlite::{Connection, params};
+
+use crate::general::units::Ratio;
+use crate::simple::plot::{
+    DurationCurves, PlotOptions, plot_duration_curves, plot_hourly_averages_with_options,
+    plot_seasonal_profiles,
+};
+use crate::simple::solar_system_utils::SimpleOptimizationResults;
+
+type Kpi = (
+    &'static str,
+    fn(&SimpleOptimizationResults) -> f64,
+    fn(&mut SimpleOptimizationResults, f64),
+);
+type HourlySeries = (
+    &'static str,
+    fn(&SimpleOptimizationResults) -> &Vec<f64>,
+    fn(&mut SimpleOptimizationResults) -> &mut Vec<f64>,
+);
+
+// Accessors of a results field, also naming the column or series after the field
+macro_rules! kpi {
+    ($field:ident) => {
+        (stringify!($field), |r| r.$field, |r, v| r.$field = v)
+    };
+}
+// Ratios are stored in %
+macro_rules! kpi_percent {
+    ($field:ident) => {
+        (
+            stringify!($field),
+            |r| r.$field.percent(),
+            |r, v| r.$field = Ratio::from_percent(v),
+        )
+    };
+}
+macro_rules! hourly {
+    ($field:ident) => {
+        (stringify!($field), |r| &r.$field, |r| &mut r.$field)
+    };
+}
+
+// Scalar results stored as columns of the runs table
+const KPIS: [Kpi; 28] = [
+    kpi!(pv_capacity_kw),
+    kpi!(grid_capacity_kw),
+    kpi!(battery_capacity_kwh),
+    kpi!(wind_capacity_kw),
+    kpi!(existing_pv_capacity_kw),
+    kpi!(existing_battery_capacity_kwh),
+    kpi!(annual_pv_production_kwh),
+    kpi!(annual_wind_production_kwh),
+    kpi!(annual_grid_energy_kwh),
+    kpi!(annual_battery_in_kwh),
+    kpi!(annual_battery_out_kwh),
+    kpi!(annual_car_charging_kwh),
+    kpi!(annual_heat_pump_kwh),
+    kpi!(annual_overproduction_kwh),
+    kpi!(annual_electricity_demand_kwh),
+    kpi!(base_demand_annual_wh),
+    kpi!(required_car_energy_kwh),
+    kpi!(annual_public_charging_kwh),
+    kpi!(annual_public_charging_cost),
+    kpi!(peak_grid_import_kw),
+    kpi!(grid_import_p99_kw),
+    kpi!(billed_grid_peak_kw),
+    kpi!(annual_capacity_fee),
+    kpi!(demand_response_battery_kwh),
+    kpi!(objective_value),
+    kpi!(pv_coverage_percent),
+    kpi_percent!(autarky),
+    kpi_percent!(autarky_without_battery),
+];
+
+// Hourly arrays stored as little-endian f64 blobs in the run_hourly table
+const HOURLY_SERIES: [HourlySeries; 13] = [
+    hourly!(hourly_pv_production),
+    hourly!(hourly_overproduction),
+    hourly!(hourly_grid_consumption),
+    hourly!(hourly_battery_storage),
+    hourly!(hourly_battery_in),
+    hourly!(hourly_battery_out),
+    hourly!(hourly_car_charging),
+    hourly!(hourly_heat_pump),
+    hourly!(hourly_heat_storage),
+    hourly!(hourly_total_pv_production),
+    hourly!(hourly_wind_production),
+    hourly!(hourly_total_electricity_demand),
+    hourly!(hourly_electricity_demand_base),
+];
+
+/// Describes a stored run: what it is called, how it is tagged and when it was computed
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunManifest {
+    pub name: String,
+    pub tags: Vec<String>, // e.g. client name or scenario, used to find runs again
+    pub created_at: i64,   // Unix timestamp in seconds
+    pub crate_version: String,
+}
+
+impl RunManifest {
+    /// Manifest for a run computed now
+    pub fn new(name: &str) -> Self {
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs() as i64)
+            .unwrap_or(0);
+        Self {
+            name: name.to_string(),
+            tags: Vec::new(),
+            created_at,
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    pub fn with_tags(mut self, tags: &[&str]) -> Self {
+        self.tags = tags.iter().map(|tag| tag.to_string()).collect();
+        self
+    }
+}
+
+/// Filter for past runs; unset fields match every run
+#[derive(Debug, Clone, Default)]
+pub struct RunQuery {
+    pub tag: Option<String>,
+    pub created_after: Option<i64>, // Unix timestamp in seconds, inclusive
+    pub created_before: Option<i64>, // Unix timestamp in seconds, exclusive
+}
+
+/// A run read back from the store.
+///
+/// `results` holds the stored KPIs and, if they were saved, the hourly arrays. The config is
+/// only kept as its debug print in `config`, so `results.config` is the default config.
+#[derive(Debug, Clone)]
+pub struct StoredRun {
+    pub id: i64,
+    pub manifest: RunManifest,
+    pub config: String,
+    pub has_hourly_data: bool,
+    pub results: SimpleOptimizationResults,
+}
+
+/// SQLite database of optimization runs
+pub struct ResultsStore {
+    connection: Connection,
+}
+
+impl ResultsStore {
+    /// Opens the database at `path`, creating it and its tables if needed
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self, Box<dyn std::error::Error>> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self, Box<dyn std::error::Error>> {
+        let kpi_columns: Vec<String> = KPIS
+            .iter()
+            .map(|(column, _, _)| format!("{} REAL NOT NULL", column))
+            .collect();
+        connection.execute_batch(&format!(
+            "PRAGMA foreign_keys = ON;
+            CREATE TABLE IF NOT EXISTS runs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                crate_version TEXT NOT NULL,
+                config TEXT NOT NULL,
+                optimization_duration_ms INTEGER NOT NULL,
+                {}
+            );
+            CREATE TABLE IF NOT EXISTS run_tags (
+                run_id INTEGER NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+                tag TEXT NOT NULL,
+                PRIMARY KEY (run_id, tag)
+            );
+            CREATE TABLE IF NOT EXISTS run_hourly (
+                run_id INTEGER NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+                series TEXT NOT NULL,
+                data BLOB NOT NULL,
+                PRIMARY KEY (run_id, series)
+            );",
+            kpi_columns.join(",\n")
+        ))?;
+        Ok(Self { connection })
+    }
+
+    /// Saves a run and returns its id. The hourly arrays are only stored if `include_hourly`
+    /// is set, they make up most of the database size.
+    pub fn save_run(
+        &mut self,
+        results: &SimpleOptimizationResults,
+        manifest: &RunManifest,
+        include_hourly: bool,
+    ) -> Result<i64, Box<dyn std::error::Error>> {
+        let transaction = self.connection.transaction()?;
+
+        let columns: Vec<&str> = KPIS.iter().map(|(column, _, _)| *column).collect();
+        let placeholders = vec!["?"; 5 + KPIS.len()].join(", ");
+        let sql = format!(
+            "INSERT INTO runs (name, created_at, crate_version, config, optimization_duration_ms, {})
+             VALUES ({})",
+            columns.join(", "),
+            placeholders
+        );
+        let mut values: Vec<Box<dyn rusqlite::ToSql>> = vec![
+            Box::new(manifest.name.clone()),
+            Box::new(manifest.created_at),
+            Box::new(manifest.crate_version.clone()),
+            Box::new(format!("{:#?}", results.config)),
+            Box::new(results.optimization_duration_ms as i64),
+        ];
+        values.extend(
+            KPIS.iter()
+                .map(|(_, get, _)| Box::new(get(results)) as Box<dyn rusqlite::ToSql>),
+        );
+        transaction.execute(&sql, rusqlite::params_from_iter(values.iter()))?;
+        let id = transaction.last_insert_rowid();
+
+        for tag in &manifest.tags {
+            transaction.execute(
+                "INSERT OR IGNORE INTO run_tags (run_id, tag) VALUES (?1, ?2)",
+                params![id, tag],
+            )?;
+        }
+
+        if include_hourly {
+            for (series, get, _) in HOURLY_SERIES.iter() {
+                let data: Vec<u8> = get(results)
+                    .iter()
+                    .flat_map(|value| value.to_le_bytes())
+                    .collect();
+                transaction.execute(
+                    "INSERT INTO run_hourly (run_id, series, data) VALUES (?1, ?2, ?3)",
+                    params![id, series, data],
+                )?;
+            }
+        }
+
+        transaction.commit()?;
+        Ok(id)
+    }
+
+    /// Loads a run including its hourly arrays
+    pub fn load_run(&self, id: i64) -> Result<StoredRun, Box<dyn std::error::Error>> {
+        let mut run = self
+            .query_runs("WHERE id = ?1", &[&id])?
+            .pop()
+            .ok_or_else(|| format!("No stored run with id {}", id))?;
+
+        let mut statement = self
+            .connection
+            .prepare("SELECT series, data FROM run_hourly WHERE run_id = ?1")?;
+        let rows = statement.query_map([id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Vec<u8>>(1)?))
+        })?;
+        for row in rows {
+            let (series, data) = row?;
+            if let Some((_, _, get_mut)) = HOURLY_SERIES.iter().find(|(name, _, _)| *name == series)
+            {
+                *get_mut(&mut run.results) = data
+                    .chunks_exact(8)
+                    .map(|bytes| f64::from_le_bytes(bytes.try_into().unwrap()))
+                    .collect();
+                run.has_hourly_data = true;
+            }
+        }
+        Ok(run)
+    }
+
+    /// Lists the runs matching the query, newest first, without their hourly arrays
+    pub fn list_runs(
+        &self,
+        query: &RunQuery,
+    ) -> Result<Vec<StoredRun>, Box<dyn std::error::Error>> {
+        let mut conditions = Vec::new();
+        let mut values: Vec<&dyn rusqlite::ToSql> = Vec::new();
+        if let Some(tag) = &query.tag {
+            conditions.push("id IN (SELECT run_id FROM run_tags WHERE tag = ?)");
+            values.push(tag);
+        }
+        if let Some(after) = &query.created_after {
+            conditions.push("created_at >= ?");
+            values.push(after);
+        }
+        if let Some(before) = &query.created_before {
+            conditions.push("created_at < ?");
+            values.push(before);
+        }
+        let filter = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+        self.query_runs(&filter, &values)
+    }
+
+    /// Deletes a run with its tags and hourly data
+    pub fn delete_run(&self, id: i64) -> Result<(), Box<dyn std::error::Error>> {
+        self.connection
+            .execute("DELETE FROM runs WHERE id = ?1", [id])?;
+        Ok(())
+    }
+
+    /// Renders the hourly average, seasonal and duration curve plots of a stored run into
+    /// `output_dir` and returns the written files
+    pub fn render_plots(
+        &self,
+        id: i64,
+        output_dir: impl AsRef<Path>,
+        options: &PlotOptions,
+    ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let run = self.load_run(id)?;
+        if !run.has_hourly_data {
+            return Err(format!("Run {} was stored without hourly data", id).into());
+        }
+        let results = &run.results;
+        let file = |name: &str| -> Result<String, Box<dyn std::error::Error>> {
+            let path = output_dir.as_ref().join(format!(
+                "run_{}_{}.{}",
+                id,
+                name,
+                options.format.extension()
+            ));
+            path.to_str()
+                .map(|path| path.to_string())
+                .ok_or_else(|| format!("Invalid output path {}", path.display()).into())
+        };
+
+        let hourly_averages = file("hourly_averages")?;
+        plot_hourly_averages_with_options(
+            &results.hourly_total_electricity_demand,
+            &results.hourly_total_pv_production,
+            &results.hourly_grid_consumption,
+            &results.hourly_battery_storage,
+            &hourly_averages,
+            Some(&run.manifest.name),
+            options,
+        )?;
+        let seasonal_profiles = file("seasonal_profiles")?;
+        plot_seasonal_profiles(results, &seasonal_profiles, options)?;
+        let duration_curves = file("duration_curves")?;
+        plot_duration_curves(
+            &DurationCurves::from_results(results),
+            &duration_curves,
+            options,
+        )?;
+        Ok(vec![hourly_averages, seasonal_profiles, duration_curves])
+    }
+
+    fn query_runs(
+        &self,
+        filter: &str,
+        values: &[&dyn rusqlite::ToSql],
+    ) -> Result<Vec<StoredRun>, Box<dyn std::error::Error>> {
+        let columns: Vec<&str> = KPIS.iter().map(|(column, _, _)| *column).collect();
+        let sql = format!(
+            "SELECT id, name, created_at, crate_version, config, optimization_duration_ms, {}
+             FROM runs {} ORDER BY created_at DESC, id DESC",
+            columns.join(", "),
+            filter
+        );
+        let mut statement = self.connection.prepare(&sql)?;
+        let rows = statement.query_map(values, |row| {
+            let mut results = SimpleOptimizationResults {
+                optimization_duration_ms: row.get::<_, i64>(5)? as u128,
+                ..Default::default()
+            };
+            for (index, (_, _, set)) in KPIS.iter().enumerate() {
+                set(&mut results, row.get(6 + index)?);
+            }
+            Ok(StoredRun {
+                id: row.get(0)?,
+                manifest: RunManifest {
+                    name: row.get(1)?,
+                    tags: Vec::new(),
+                    created_at: row.get(2)?,
+                    crate_version: row.get(3)?,
+                },
+                config: row.get(4)?,
+                has_hourly_data: false,
+                results,
+            })
+        })?;
+        let mut runs = rows.collect::<Result<Vec<_>, _>>()?;
+
+        let mut tag_statement = self
+            .connection
+            .prepare("SELECT tag FROM run_tags WHERE run_id = ?1 ORDER BY tag")?;
+        for run in &mut runs {
+            run.manifest.tags = tag_statement
+                .query_map([run.id], |row| row.get(0))?
+                .collect::<Result<Vec<String>, _>>()?;
+        }
+        Ok(runs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simple::plot::PlotFormat;
+
+    fn example_results() -> SimpleOptimizationResults {
+        SimpleOptimizationResults {
+            pv_capacity_kw: 7.5,
+            battery_capacity_kwh: 5.0,
+            autarky: Ratio::from_percent(63.2),
+            optimization_duration_ms: 1234,
+            hourly_total_electricity_demand: vec![500.0; 8760],
+            hourly_total_pv_production: (0..8760).map(|h| (h % 24) as f64 * 50.0).collect(),
+            hourly_grid_consumption: vec![100.0; 8760],
+            hourly_battery_storage: vec![1000.0; 8760],
+            hourly_overproduction: vec![0.0; 8760],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_save_and_load_run() {
+        let mut store = ResultsStore::open_in_memory().unwrap();
+        let results = example_results();
+        let manifest = RunManifest::new("Client A").with_tags(&["client-a", "ev"]);
+        let id = store.save_run(&results, &manifest, true).unwrap();
+
+        let run = store.load_run(id).unwrap();
+        assert_eq!(run.manifest, manifest);
+        assert!(run.has_hourly_data);
+        assert!(run.config.contains("inv_pv"));
+        assert_eq!(run.results.pv_capacity_kw, 7.5);
+        assert_eq!(run.results.autarky, Ratio::from_percent(63.2));
+        assert_eq!(run.results.optimization_duration_ms, 1234);
+        assert_eq!(
+            run.results.hourly_total_pv_production,
+            results.hourly_total_pv_production
+        );
+
+        assert!(store.load_run(id + 1).is_err());
+        store.delete_run(id).unwrap();
+        assert!(store.load_run(id).is_err());
+    }
+
+    #[test]
+    fn test_list_runs() {
+        let mut store = ResultsStore::open_in_memory().unwrap();
+        let results = example_results();
+        let old = RunManifest {
+            created_at: 1_000,
+            ..RunManifest::new("Old").with_tags(&["client-a"])
+        };
+        let new = RunManifest {
+            created_at: 2_000,
+            ..RunManifest::new("New").with_tags(&["client-a", "ev"])
+        };
+        store.save_run(&results, &old, false).unwrap();
+        store.save_run(&results, &new, false).unwrap();
+
+        let all = store.list_runs(&RunQuery::default()).unwrap();
+        let names: Vec<&str> = all.iter().map(|run| run.manifest.name.as_str()).collect();
+        assert_eq!(names, vec!["New", "Old"]);
+        assert!(all.iter().all(|run| !run.has_hourly_data));
+
+        let ev = RunQuery {
+            tag: Some("ev".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(store.list_runs(&ev).unwrap().len(), 1);
+        let before = RunQuery {
+            tag: Some("client-a".to_string()),
+            created_before: Some(1_500),
+            ..Default::default()
+        };
+        let runs = store.list_runs(&before).unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].manifest.name, "Old");
+    }
+
+    #[test]
+    fn test_render_plots_from_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = ResultsStore::open(dir.path().join("runs.sqlite")).unwrap();
+        let results = example_results();
+        let with_hourly = store
+            .save_run(&results, &RunManifest::new("Hourly"), true)
+            .unwrap();
+        let kpis_only = store
+            .save_run(&results, &RunManifest::new("KPIs"), false)
+            .unwrap();
+
+        let options = PlotOptions {
+            format: PlotFormat::Svg,
+            ..Default::default()
+        };
+        let files = store
+            .render_plots(with_hourly, dir.path(), &options)
+            .unwrap();
+        assert_eq!(files.len(), 3);
+        assert!(files.iter().all(|file| Path::new(file).exists()));
+        assert!(store.render_plots(kpis_only, dir.path(), &options).is_err());
+    }
+}
@@ -1,127 +1,255 @@
 use ems_model::building::electricity::ElectricityRate;
-use good_lp::{Expression, SolverModel, constraint, variable};
-use good_lp::{Solver, variables};
+use good_lp::constraint::ConstraintReference;
+use good_lp::{DualValues, Expression, SolutionWithDual, SolverModel, constraint, variable};
+use good_lp::{ProblemVariables, Solution, Solver};
+use tracing::{info, info_span, warn};
 
-use crate::general::electricity_demand::{MonthlyDemand, create_scaled_load_curve_from_csv};
-use crate::simple::plot::{plot_hourly_averages, plot_hourly_averages_with_title};
+use crate::general::calendar::{DAYS_IN_MONTH, NUM_HOURS, date_file_label, date_label};
+use crate::general::electricity_demand::{MonthlyDemand, generate_scaled_load_curve};
+use crate::general::time_series::{SeriesUnit, TimeSeries};
+use crate::general::units::{Energy, Ratio};
+use crate::simple::day_selection::{DaySelection, find_worst_week, resolve_days};
+use crate::simple::input_quality::{QualityCheckConfig, QualityIssueKind, check_inputs};
+use crate::simple::plot::{
+    DailyEnergyProfile, PlotOptions, plot_day_with_battery_power, plot_hourly_averages,
+    plot_worst_week,
+};
 use crate::simple::solar_system_utils::{
-    HeatingType, InsulationLevel, OptimizationConfig, SimpleOptimizationResults,
-    StaticSimulationConfigs, StaticSimulationResults, load_demand_from_csv,
+    ControllableLoadDimming, DemandCharges, DemandResponseEvent, DemandResponseEventResult,
+    DimmingEvent, DimmingEventResult, EquipmentFeasibility, HeatPumpDispatch, HeatPumpSchedule,
+    HeatingType, InsulationLevel, OptimalVsStaticComparison, OptimizationConfig, PhaseConfig,
+    RoofArea, SimpleOptimizationResults, StaticSimulationConfigs, StaticSimulationHourly,
+    StaticSimulationResults, VehicleChargingSchedule, load_demand_from_csv,
     load_solar_radiance_from_csv,
 };
 
-const NUM_HOURS: usize = 8760;
-
-struct OptimizationVariables<'a> {
-    e_pv: &'a [good_lp::Variable],
-    e_grid: &'a [good_lp::Variable],
-    e_o: &'a [good_lp::Variable],
-    est_battery: &'a Option<Vec<good_lp::Variable>>,
-    est_in_battery: &'a Option<Vec<good_lp::Variable>>,
-    est_out_battery: &'a Option<Vec<good_lp::Variable>>,
-    e_car_charge: &'a [good_lp::Variable],
+struct OptimizationVariables {
+    e_pv: Vec<good_lp::Variable>,
+    e_grid: Vec<good_lp::Variable>,
+    e_o: Vec<good_lp::Variable>,
+    e_curtail: Option<Vec<good_lp::Variable>>, // Curtailed surplus (only created if curtailing can pay)
+    est_battery: Option<Vec<good_lp::Variable>>,
+    est_in_battery: Option<Vec<good_lp::Variable>>,
+    est_out_battery: Option<Vec<good_lp::Variable>>,
+    e_car_charge: Vec<Vec<good_lp::Variable>>, // One series per car of `OptimizationConfig::vehicles`
+    car_charging_on: Vec<Option<Vec<good_lp::Variable>>>, // On/off binaries of cars whose charger enforces the minimum current
+    car_soc: Vec<Option<Vec<good_lp::Variable>>>, // State of charge of cars with a departure SOC
+    e_wind: Option<Vec<good_lp::Variable>>,
+    e_o_existing: Option<Vec<good_lp::Variable>>,
+    grid_peak: Vec<good_lp::Variable>, // Grid import peak of each capacity tariff billing period
+    monthly_peak: Vec<good_lp::Variable>, // Grid import peak of each month (only with demand charges)
+    excess_power: Vec<good_lp::Variable>, // Monthly peak above the contracted power (only with demand charges)
+    feed_in_credit: Vec<good_lp::Variable>, // Credited feed-in revenue of each month (only with the monthly credit cap)
+    heat_pump: Option<HeatPumpVariables>,
     cap_pv: good_lp::Variable,
     cap_grid: good_lp::Variable,
     cst_battery: Option<good_lp::Variable>,
+    cap_wind: Option<good_lp::Variable>,
+    discrete_sizing: Option<DiscreteSizingVariables>,
+}
+
+/// Module counts of the discrete sizing mode
+struct DiscreteSizingVariables {
+    pv_modules: good_lp::Variable, // Integer number of new PV modules
+    battery_modules: Option<good_lp::Variable>, // Integer number of new battery modules (only with a battery)
+    battery_built: Option<good_lp::Variable>,   // Binary, a battery has at least the smallest stack
 }
 
-/// Helper function to convert day number to a readable date string
-fn get_date_string(day: usize) -> String {
-    let months = [
-        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
-    ];
-    let days_in_month = [31, 28, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+impl OptimizationVariables {
+    /// Charging energy of all cars in hour `t`
+    fn car_charging(&self, t: usize) -> Expression {
+        self.e_car_charge
+            .iter()
+            .map(|e_car_charge| Expression::from(e_car_charge[t]))
+            .sum()
+    }
+}
 
-    let mut remaining_days = day;
-    let mut month = 0;
+/// Hourly variables of a dispatched heat pump
+struct HeatPumpVariables {
+    e_hp: Vec<good_lp::Variable>, // Electrical consumption in regular operation
+    est_heat: Vec<good_lp::Variable>, // Buffer level up to the regular capacity
+    // SG-Ready boost (only created if a boost energy is configured)
+    e_hp_boost: Option<Vec<good_lp::Variable>>, // Electrical consumption heating the boost band
+    est_heat_boost: Option<Vec<good_lp::Variable>>, // Buffer level above the regular capacity
+    q_heat_boost: Option<Vec<good_lp::Variable>>, // Heat demand covered from the boost band
+    // Building model (only created if a building is configured)
+    q_building: Option<Vec<good_lp::Variable>>, // Heat delivered to the building
+    t_indoor: Option<Vec<good_lp::Variable>>,   // Indoor temperature within the comfort band
+    // Hot water mode (only created if domestic hot water is configured)
+    e_dhw: Option<Vec<good_lp::Variable>>, // Electrical consumption in hot water mode
+    est_dhw: Option<Vec<good_lp::Variable>>, // Hot water tank level
+    dhw_on: Option<Vec<good_lp::Variable>>, // Mode binaries, 1 in hot water mode (only with binary modes)
+    // Solar thermal (only created if collectors are configured)
+    cap_solar_thermal: Option<good_lp::Variable>, // Collector area in m²
+    q_solar_thermal: Option<Vec<good_lp::Variable>>, // Collector heat fed into the tank
+}
 
-    for (i, &days) in days_in_month.iter().enumerate() {
-        if remaining_days < days {
-            month = i;
-            break;
+impl HeatPumpVariables {
+    /// Total electrical consumption of the heat pump in hour `t`, in both modes
+    fn consumption(&self, t: usize) -> Expression {
+        let mut consumption = Expression::from(self.e_hp[t]);
+        if let Some(e_hp_boost) = &self.e_hp_boost {
+            consumption += e_hp_boost[t];
         }
-        remaining_days -= days;
+        if let Some(e_dhw) = &self.e_dhw {
+            consumption += e_dhw[t];
+        }
+        consumption
     }
-
-    format!("{} {}", months[month], remaining_days + 1)
 }
 
+/// Scales the base demand profile either to the monthly totals (if given) or to the
+/// annual electricity usage. Works only on the provided profile and never reads files.
 pub fn get_scaled_electricity_demand(
     monthly_demand: Option<MonthlyDemand>,
-    electricity_usage: f64,
+    electricity_usage: Energy,
     electricity_demand: Vec<f64>,
 ) -> Result<Vec<f64>, Box<dyn std::error::Error>> {
-    let scaled_electricity_demand = if let Some(ref monthly_demand) = monthly_demand {
-        // Generate scaled load curve using monthly demand and base CSV data
-        create_scaled_load_curve_from_csv(monthly_demand, "data/demand.csv")?
+    match monthly_demand {
+        Some(ref monthly_demand) => scale_demand_to_monthly(monthly_demand, &electricity_demand),
+        None => scale_demand_to_annual(electricity_usage, &electricity_demand),
+    }
+}
+
+/// Scales an hourly base profile in Wh so that every month matches the given monthly demand (kWh)
+pub fn scale_demand_to_monthly(
+    monthly_demand: &MonthlyDemand,
+    base_profile: &[f64],
+) -> Result<Vec<f64>, Box<dyn std::error::Error>> {
+    let base_profile_kwh: Vec<f64> = base_profile.iter().map(|&demand| demand / 1000.0).collect();
+    Ok(
+        generate_scaled_load_curve(monthly_demand, &base_profile_kwh)?
             .iter()
             .map(|&demand| demand * 1000.0) // Convert from kWh to Wh to match existing scaling
-            .collect()
-    } else {
-        // Use the provided electricity_demand and scale by desired annual usage
-        electricity_demand
-            .iter()
-            .map(|&demand| demand * (electricity_usage / 4173440.0))
-            .collect()
-    };
-    Ok(scaled_electricity_demand)
+            .collect(),
+    )
+}
+
+/// Scales an hourly base profile in Wh to the desired annual usage, relative to the annual sum
+/// of the profile itself
+pub fn scale_demand_to_annual(
+    electricity_usage: Energy,
+    base_profile: &[f64],
+) -> Result<Vec<f64>, Box<dyn std::error::Error>> {
+    let base_total: f64 = base_profile.iter().sum();
+    if base_total <= 0.0 {
+        return Err(format!(
+            "Base demand profile must have a positive annual sum, got {} Wh",
+            base_total
+        )
+        .into());
+    }
+    Ok(base_profile
+        .iter()
+        .map(|&demand| demand * (electricity_usage.wh() / base_total))
+        .collect())
 }
 
 fn generate_objective(
     config: &OptimizationConfig,
-    e_grid: &[good_lp::Variable],
-    cap_pv: good_lp::Variable,
-    cap_grid: good_lp::Variable,
-    cst_battery: Option<good_lp::Variable>,
+    vars: &OptimizationVariables,
     electricity_rate_hourly: &[f64],
-    e_o: &[good_lp::Variable],
-) -> (
-    Expression,
-    good_lp::Variable,
-    good_lp::Variable,
-    Option<good_lp::Variable>,
-) {
+    feed_in_prices: &[f64],
+) -> Expression {
     // Build objective function
     let mut objective = Expression::default();
 
     if config.optimize_for_autonomy {
         // Optimize for maximum autonomy: minimize grid consumption
-        for e_grid_item in e_grid.iter().take(NUM_HOURS) {
+        for e_grid_item in vars.e_grid.iter().take(NUM_HOURS) {
             objective += *e_grid_item / 1000.0; // Minimize grid consumption
         }
     }
     // Optimize for minimum cost: include investment costs and operating costs
     // Investment costs
-    objective += cap_pv / 1000.0 * config.inv_pv * config.annuity;
-    objective += cap_grid / 1000.0 * config.inv_grid;
-    if config.bat_value > 0.0 {
-        objective += cst_battery.unwrap() / 1000.0 * config.inv_bat * config.annuity;
+    objective += vars.cap_pv / 1000.0 * config.inv_pv * config.annuity;
+    objective += vars.cap_grid / 1000.0 * config.inv_grid;
+    if let Some(cst_battery) = vars.cst_battery {
+        objective += cst_battery / 1000.0 * config.inv_bat * config.annuity;
+    }
+    if let (Some(cap_wind), Some(wind_turbine)) = (vars.cap_wind, &config.wind_turbine) {
+        objective += cap_wind / 1000.0 * wind_turbine.inv_wind * config.annuity;
+    }
+    if let (Some(cap_solar_thermal), Some(collector)) = (
+        vars.heat_pump
+            .as_ref()
+            .and_then(|heat_pump| heat_pump.cap_solar_thermal),
+        config.solar_thermal(),
+    ) {
+        objective += cap_solar_thermal * collector.inv_per_m2 * config.annuity;
     }
 
     // Operating costs and revenues (time-dependent)
-    for t in 0..NUM_HOURS {
-        objective += e_grid[t] / 1000.0 * electricity_rate_hourly[t]; // Cost of grid electricity
-        objective -= e_o[t] / 1000.0 * config.feed_in_tariff; // Revenue from feed-in
+    for (((&e_grid, &e_o), &rate), &feed_in_price) in vars
+        .e_grid
+        .iter()
+        .zip(vars.e_o.iter())
+        .zip(electricity_rate_hourly.iter())
+        .zip(feed_in_prices.iter())
+        .take(NUM_HOURS)
+    {
+        objective += e_grid / 1000.0 * rate; // Cost of grid electricity
+        if vars.feed_in_credit.is_empty() {
+            objective -= e_o / 1000.0 * feed_in_price; // Revenue from feed-in
+        }
+    }
+    // Feed-in revenue settled per month, capped at the import cost
+    for &feed_in_credit in &vars.feed_in_credit {
+        objective -= feed_in_credit;
+    }
+
+    // Network fee on the average peak of the billing periods
+    if let Some(capacity_tariff) = &config.capacity_tariff
+        && !vars.grid_peak.is_empty()
+    {
+        let num_periods = vars.grid_peak.len() as f64;
+        for &grid_peak in &vars.grid_peak {
+            objective += grid_peak / 1000.0 * capacity_tariff.price_per_kw_year / num_periods;
+        }
+    }
+
+    // Monthly maximum demand charges and penalties above the contracted power
+    if let Some(demand_charges) = &config.demand_charges {
+        for (&monthly_peak, &excess_power) in vars.monthly_peak.iter().zip(&vars.excess_power) {
+            objective += monthly_peak / 1000.0 * demand_charges.price_per_kw_month
+                + excess_power / 1000.0 * demand_charges.excess_price_per_kw;
+        }
+    }
+
+    // Feed-in of the existing array under its old contract (on top of the current tariff)
+    if let (Some(e_o_existing), Some(existing_tariff)) = (
+        &vars.e_o_existing,
+        config
+            .existing_system
+            .as_ref()
+            .and_then(|existing| existing.feed_in_tariff),
+    ) {
+        for (&e_o_existing_t, &feed_in_price) in e_o_existing
+            .iter()
+            .zip(feed_in_prices.iter())
+            .take(NUM_HOURS)
+        {
+            objective -= e_o_existing_t / 1000.0 * (existing_tariff - feed_in_price);
+        }
     }
 
-    (objective, cap_pv, cap_grid, cst_battery)
+    objective
 }
 
 /// Adds all fixed constraints that are not time dependent
-#[allow(clippy::too_many_arguments)]
 fn add_fixed_constraints<M>(
     mut model: M,
     config: &OptimizationConfig,
     pv_cap_w_max: f64,
-    cap_pv: good_lp::Variable,
-    cst_battery: Option<good_lp::Variable>,
-    est_battery: &Option<Vec<good_lp::Variable>>,
-    e_car_charge: &[good_lp::Variable],
-    car_daily_energy_required: f64,
+    vars: &OptimizationVariables,
 ) -> M
 where
     M: good_lp::SolverModel,
 {
+    let cap_pv = vars.cap_pv;
+    let pv_cap_w_max = config.pv_limit_w(pv_cap_w_max);
+
     // Fixed capacity constraints
     if config.pv_fixed {
         model = model.with(constraint!(cap_pv == pv_cap_w_max));
@@ -130,31 +258,165 @@ where
         model = model.with(constraint!(cap_pv <= pv_cap_w_max));
     }
 
-    // Battery capacity constraints (only if bat_value > 0)
-    if config.bat_value > 0.0 {
+    // Whole modules; fixed capacities are built as configured
+    if let (Some(discrete), Some(sizes)) = (&vars.discrete_sizing, &config.discrete_sizing) {
+        if !config.pv_fixed {
+            model = model.with(constraint!(
+                cap_pv == discrete.pv_modules * sizes.module_power_w
+            ));
+        }
+        if let (Some(cst_battery), Some(battery_modules), Some(battery_built)) = (
+            vars.cst_battery,
+            discrete.battery_modules,
+            discrete.battery_built,
+        ) && !config.bat_fixed
+        {
+            let max_modules = (config.bat_value.kwh() / sizes.battery_module_kwh).floor();
+            model = model.with(constraint!(
+                cst_battery == battery_modules * (sizes.battery_module_kwh * 1000.0)
+            ));
+            model = model.with(constraint!(
+                battery_modules >= battery_built * sizes.min_battery_modules as f64
+            ));
+            model = model.with(constraint!(battery_modules <= battery_built * max_modules));
+        }
+    }
+
+    // Battery capacity constraints (only if there is a battery)
+    if let Some(cst_battery) = vars.cst_battery {
         if config.bat_fixed {
-            model = model.with(constraint!(cst_battery.unwrap() == config.bat_value));
+            model = model.with(constraint!(cst_battery == config.bat_value.wh()));
         } else {
-            model = model.with(constraint!(cst_battery.unwrap() >= 0.0));
-            model = model.with(constraint!(cst_battery.unwrap() <= config.bat_value));
+            model = model.with(constraint!(cst_battery >= 0.0));
+            model = model.with(constraint!(cst_battery <= config.bat_value.wh()));
         }
 
         // Battery initialization constraint
-        if let Some(battery_vars) = est_battery {
+        if let Some(battery_vars) = &vars.est_battery {
             model = model.with(constraint!(battery_vars[0] == 0.0));
         }
     }
 
-    // Electric car total energy constraint
-    if config.electric_car_enabled {
-        // Sum of all charging must equal required daily energy * 365 days
+    // Electric car total energy constraints, cars with a departure SOC are covered by their
+    // daily deadlines instead
+    for (vehicle, e_car_charge) in config.vehicles().iter().zip(&vars.e_car_charge) {
+        if vehicle.departure_soc.is_some() {
+            continue;
+        }
+        // Sum of all charging must equal required daily energy * days charged at home
         let total_car_charging: Expression =
             e_car_charge.iter().map(|&var| Expression::from(var)).sum();
         model = model.with(constraint!(
-            total_car_charging == car_daily_energy_required * 365.0
+            total_car_charging == vehicle.daily_energy_wh() * vehicle.home_charging_days()
         ));
     }
 
+    // Wind turbine capacity constraints
+    if let (Some(cap_wind), Some(wind_turbine)) = (vars.cap_wind, &config.wind_turbine) {
+        if wind_turbine.wind_fixed {
+            model = model.with(constraint!(cap_wind == wind_turbine.cap_wind_w_max));
+        } else {
+            model = model.with(constraint!(cap_wind <= wind_turbine.cap_wind_w_max));
+        }
+    }
+
+    // Solar thermal collectors share the roof with the new PV
+    let cap_solar_thermal = vars
+        .heat_pump
+        .as_ref()
+        .and_then(|heat_pump| heat_pump.cap_solar_thermal);
+    if let (Some(cap_solar_thermal), Some(roof)) = (cap_solar_thermal, &config.roof) {
+        let free_area_m2 =
+            (roof.area_m2 - config.existing_pv_w() / 1000.0 * roof.m2_per_kwp).max(0.0);
+        model = model.with(constraint!(
+            cap_pv / 1000.0 * roof.m2_per_kwp + cap_solar_thermal <= free_area_m2
+        ));
+    }
+
+    // Investment budget, the heat pump is built in any case
+    if let Some(budget) = config.investment_budget {
+        model = model.with(constraint!(
+            investment_expression(config, vars) <= budget - config.heat_pump_investment()
+        ));
+    }
+
+    // Peaks below the minimum are billed at the minimum
+    if let Some(capacity_tariff) = &config.capacity_tariff {
+        for &grid_peak in &vars.grid_peak {
+            model = model.with(constraint!(
+                grid_peak >= capacity_tariff.min_billed_kw * 1000.0
+            ));
+        }
+    }
+
+    // Part of the monthly peak above the contracted power
+    if let Some(demand_charges) = &config.demand_charges {
+        for (&monthly_peak, &excess_power) in vars.monthly_peak.iter().zip(&vars.excess_power) {
+            model = model.with(constraint!(
+                excess_power >= monthly_peak - demand_charges.contracted_kw * 1000.0
+            ));
+        }
+    }
+
+    model
+}
+
+/// Month (0-11) of an hour of the modelled year
+fn month_of_hour(t: usize) -> usize {
+    let mut end = 0;
+    DAYS_IN_MONTH
+        .iter()
+        .position(|days| {
+            end += days * 24;
+            t < end
+        })
+        .unwrap_or(11)
+}
+
+/// Investment in new PV, battery, wind and solar thermal collectors; the dispatched heat pump
+/// is built in any case and not part of it
+fn investment_expression(config: &OptimizationConfig, vars: &OptimizationVariables) -> Expression {
+    let mut investment = vars.cap_pv / 1000.0 * config.inv_pv;
+    if let (Some(cap_solar_thermal), Some(collector)) = (
+        vars.heat_pump
+            .as_ref()
+            .and_then(|heat_pump| heat_pump.cap_solar_thermal),
+        config.solar_thermal(),
+    ) {
+        investment += cap_solar_thermal * collector.inv_per_m2;
+    }
+    if let Some(cst_battery) = vars.cst_battery {
+        investment += cst_battery / 1000.0 * config.inv_bat;
+    }
+    if let (Some(cap_wind), Some(wind_turbine)) = (vars.cap_wind, &config.wind_turbine) {
+        investment += cap_wind / 1000.0 * wind_turbine.inv_wind;
+    }
+    investment
+}
+
+/// Caps the credited feed-in revenue of every month at the import cost of that month
+fn add_feed_in_credit_constraints<M>(
+    mut model: M,
+    vars: &OptimizationVariables,
+    electricity_rate_hourly: &[f64],
+    feed_in_prices: &[f64],
+) -> M
+where
+    M: good_lp::SolverModel,
+{
+    let mut start = 0;
+    for (&feed_in_credit, days) in vars.feed_in_credit.iter().zip(DAYS_IN_MONTH) {
+        let hours = start..start + days * 24;
+        start = hours.end;
+        let mut revenue = Expression::default();
+        let mut import_cost = Expression::default();
+        for t in hours {
+            revenue += vars.e_o[t] / 1000.0 * feed_in_prices[t];
+            import_cost += vars.e_grid[t] / 1000.0 * electricity_rate_hourly[t];
+        }
+        model = model.with(constraint!(feed_in_credit <= revenue));
+        model = model.with(constraint!(feed_in_credit <= import_cost));
+    }
     model
 }
 
@@ -166,59 +428,192 @@ fn add_time_dependent_constraints<M>(
     solar_irradiance: &[f64],
     scaled_electricity_demand: &[f64],
     vars: &OptimizationVariables,
+    wind_capacity_factors: &[f64],
+    feed_in_prices: &[f64],
     storage_retention_bat: f64,
     eta_in_bat: f64,
     eta_out_bat_inv: f64,
-) -> M
+) -> (M, Vec<ConstraintReference>)
 where
     M: good_lp::SolverModel,
 {
+    let existing_pv_w = config.existing_pv_w();
+    let existing_battery_wh = config.existing_battery_wh();
+    let vehicles = config.vehicles();
+    let mut energy_balance = Vec::with_capacity(NUM_HOURS);
+    let peak_periods = config
+        .capacity_tariff
+        .as_ref()
+        .map(|capacity_tariff| capacity_tariff.periods(NUM_HOURS))
+        .unwrap_or_default();
+
     for t in 0..NUM_HOURS {
         let solar_t = solar_irradiance[t];
         let elec_demand_t = scaled_electricity_demand[t];
+        let wind_t = wind_capacity_factors.get(t).copied().unwrap_or(0.0);
+
+        // Own generation used: PV + Wind
+        let mut e_own = Expression::from(vars.e_pv[t]);
+        if let Some(e_wind) = &vars.e_wind {
+            e_own += e_wind[t];
+        }
+
+        // Loads scheduled by the optimizer: Car Charging + Heat Pump
+        let mut flexible_load = vars.car_charging(t);
+        if let Some(heat_pump) = &vars.heat_pump {
+            flexible_load += heat_pump.consumption(t);
+        }
 
         // Energy balance: PV + Grid + Battery Out = Demand + Battery In + Car Charging + Heat Pump
-        if let (Some(battery_in), Some(battery_out)) = (vars.est_in_battery, vars.est_out_battery) {
+        let balance = if let (Some(battery_in), Some(battery_out)) =
+            (&vars.est_in_battery, &vars.est_out_battery)
+        {
+            model.add_constraint(constraint!(
+                e_own.clone() + vars.e_grid[t] - elec_demand_t - battery_in[t] + battery_out[t]
+                    - flexible_load
+                    == 0.0
+            ))
+        } else {
+            // No battery: PV + Grid = Demand + Car Charging + Heat Pump
+            model.add_constraint(constraint!(
+                e_own.clone() + vars.e_grid[t] - elec_demand_t - flexible_load == 0.0
+            ))
+        };
+        energy_balance.push(balance);
+
+        // Surplus is exported or, if allowed in this hour, curtailed
+        let mut surplus = Expression::from(vars.e_o[t]);
+        if let Some(e_curtail) = &vars.e_curtail {
+            surplus += e_curtail[t];
+        }
+        if config.curtail_unpaid_feed_in && feed_in_prices[t] <= 0.0 {
+            model = model.with(constraint!(vars.e_o[t] == 0.0));
+        }
+        if let Some(share) = config.feed_in_limit_share {
+            model = model.with(constraint!(
+                vars.e_o[t] - (vars.cap_pv + existing_pv_w) * share <= 0.0
+            ));
+        }
+
+        // Overproduction constraint: surplus = potential PV - actual PV (+ same for wind)
+        if let (Some(e_wind), Some(cap_wind)) = (&vars.e_wind, vars.cap_wind) {
             model = model.with(constraint!(
-                vars.e_pv[t] + vars.e_grid[t] - elec_demand_t - battery_in[t] + battery_out[t]
-                    - vars.e_car_charge[t]
+                surplus - (vars.cap_pv + existing_pv_w) * solar_t - cap_wind * wind_t + e_own
                     == 0.0
             ));
+
+            // Wind capacity limit: actual wind <= potential wind
+            model = model.with(constraint!(cap_wind * wind_t - e_wind[t] >= 0.0));
         } else {
-            // No battery: PV + Grid = Demand + Car Charging
             model = model.with(constraint!(
-                vars.e_pv[t] + vars.e_grid[t] - elec_demand_t - vars.e_car_charge[t] == 0.0
+                surplus - (vars.cap_pv + existing_pv_w) * solar_t + vars.e_pv[t] == 0.0
             ));
         }
 
-        // Overproduction constraint: overproduction = potential PV - actual PV
+        // PV capacity limit: actual PV <= potential PV
         model = model.with(constraint!(
-            vars.e_o[t] - vars.cap_pv * solar_t + vars.e_pv[t] == 0.0
+            (vars.cap_pv + existing_pv_w) * solar_t - vars.e_pv[t] >= 0.0
         ));
 
-        // PV capacity limit: actual PV <= potential PV
-        model = model.with(constraint!(vars.cap_pv * solar_t - vars.e_pv[t] >= 0.0));
+        // Exports under the existing contract are limited by the production of the existing array
+        if let Some(e_o_existing) = &vars.e_o_existing {
+            model = model.with(constraint!(e_o_existing[t] <= vars.e_o[t]));
+            model = model.with(constraint!(e_o_existing[t] <= existing_pv_w * solar_t));
+        }
 
         // Grid capacity limit
         model = model.with(constraint!(vars.cap_grid - vars.e_grid[t] >= 0.0));
 
+        // Grid import peak of the capacity tariff billing period
+        if let Some(period) = peak_periods.iter().position(|period| period.contains(&t)) {
+            model = model.with(constraint!(vars.e_grid[t] <= vars.grid_peak[period]));
+        }
+
+        // Grid import peak of the month for the demand charges
+        if !vars.monthly_peak.is_empty() {
+            let month = month_of_hour(t);
+            model = model.with(constraint!(vars.e_grid[t] <= vars.monthly_peak[month]));
+        }
+
+        // Main fuse of the house connection
+        if let Some(grid_connection) = config
+            .grid_connection
+            .as_ref()
+            .filter(|grid_connection| grid_connection.enforce)
+        {
+            model = model.with(constraint!(vars.e_grid[t] <= grid_connection.max_power_w()));
+        }
+
+        // Mandated load reduction of a demand response event
+        if let Some(limit) = config.grid_import_limit_w(t) {
+            model = model.with(constraint!(vars.e_grid[t] <= limit));
+        }
+
+        // Per-phase limits
+        if let Some(phase_config) = &config.phase_config {
+            model = add_phase_constraints(
+                model,
+                phase_config,
+                vars,
+                t,
+                solar_t,
+                wind_t,
+                elec_demand_t,
+                existing_pv_w,
+            );
+        }
+
+        // Heat pump dispatch
+        if let (Some(heat_pump), Some(heat_pump_vars)) =
+            (&config.heat_pump_dispatch, &vars.heat_pump)
+        {
+            model = add_heat_pump_constraints(
+                model,
+                heat_pump,
+                heat_pump_vars,
+                vars,
+                t,
+                elec_demand_t,
+                solar_t,
+            );
+        }
+
+        // Controllable loads dimmed by the grid operator
+        if let Some(dimming) = config
+            .controllable_load_dimming
+            .as_ref()
+            .filter(|dimming| dimming.enforce && dimming.is_dimmed(t))
+        {
+            if let Some(heat_pump) = &vars.heat_pump {
+                let consumption = heat_pump.consumption(t);
+                model = model.with(constraint!(consumption <= dimming.min_power_w));
+            }
+            for e_car_charge in &vars.e_car_charge {
+                model = model.with(constraint!(e_car_charge[t] <= dimming.min_power_w));
+            }
+        }
+
         // Battery constraints
         #[allow(clippy::collapsible_if)]
-        if config.bat_value > 0.0 {
-            if let (Some(battery_storage), Some(battery_in), Some(battery_out)) =
-                (vars.est_battery, vars.est_in_battery, vars.est_out_battery)
-            {
-                // Battery capacity limit
+        if let Some(cst_battery) = vars.cst_battery {
+            if let (Some(battery_storage), Some(battery_in), Some(battery_out)) = (
+                &vars.est_battery,
+                &vars.est_in_battery,
+                &vars.est_out_battery,
+            ) {
+                // Battery capacity limit (new + existing capacity)
                 model = model.with(constraint!(
-                    vars.cst_battery.unwrap() - battery_storage[t] >= 0.0
+                    cst_battery + existing_battery_wh - battery_storage[t] >= 0.0
                 ));
 
                 // C-rate constraints
                 model = model.with(constraint!(
-                    config.c_rate_limit * vars.cst_battery.unwrap() - battery_in[t] >= 0.0
+                    config.c_rate_limit * (cst_battery + existing_battery_wh) - battery_in[t]
+                        >= 0.0
                 ));
                 model = model.with(constraint!(
-                    config.c_rate_limit * vars.cst_battery.unwrap() - battery_out[t] >= 0.0
+                    config.c_rate_limit * (cst_battery + existing_battery_wh) - battery_out[t]
+                        >= 0.0
                 ));
 
                 // Storage balance constraints (t >= 1)
@@ -234,65 +629,442 @@ where
             }
         }
 
-        // Electric car charging constraints
-        if config.electric_car_enabled {
-            // Determine if this is a charging hour (simplified: day = 6-18, night = 18-6)
-            let hour_of_day = t % 24;
-            let is_day_hour = (6..18).contains(&hour_of_day);
-            let can_charge = if config.car_charge_during_day {
-                is_day_hour
-            } else {
-                !is_day_hour
+        // Electric car charging constraints: no charging while a car is not plugged in at home
+        // and, with a charger, charging power between its minimum (if binary) and maximum
+        for ((vehicle, e_car_charge), charging_on) in vehicles
+            .iter()
+            .zip(&vars.e_car_charge)
+            .zip(&vars.car_charging_on)
+        {
+            if !vehicle.can_charge(t) {
+                model = model.with(constraint!(e_car_charge[t] == 0.0));
+            } else if let Some(charger) = vehicle.charger {
+                match charging_on {
+                    Some(charging_on) => {
+                        model = model.with(constraint!(
+                            e_car_charge[t] <= charger.max_power_w() * charging_on[t]
+                        ));
+                        model = model.with(constraint!(
+                            e_car_charge[t] >= charger.min_power_w() * charging_on[t]
+                        ));
+                    }
+                    None => {
+                        model = model.with(constraint!(e_car_charge[t] <= charger.max_power_w()));
+                    }
+                }
+            }
+        }
+
+        // Daily deadlines: the state of charge follows charging and driving (cyclic over the
+        // year) and has to reach the departure SOC before the car leaves
+        for ((vehicle, e_car_charge), car_soc) in
+            vehicles.iter().zip(&vars.e_car_charge).zip(&vars.car_soc)
+        {
+            let (Some(departure_soc), Some(car_soc)) = (vehicle.departure_soc, car_soc) else {
+                continue;
             };
+            let previous = (t + NUM_HOURS - 1) % NUM_HOURS;
+            model = model.with(constraint!(
+                car_soc[t] - car_soc[previous] - e_car_charge[t] == -vehicle.departure_energy_wh(t)
+            ));
+            if vehicle.departure_energy_wh(t) > 0.0 {
+                model = model.with(constraint!(
+                    car_soc[previous] >= departure_soc * vehicle.battery_size_kwh * 1000.0
+                ));
+            }
+        }
+    }
+
+    (model, energy_balance)
+}
 
-            // If car cannot charge during this hour, set charging to zero
-            if !can_charge {
-                model = model.with(constraint!(vars.e_car_charge[t] == 0.0));
+/// Adds the thermal buffer balance, power limit, blocking windows and SG-Ready boost of the
+/// heat pump for hour `t`
+fn add_heat_pump_constraints<M>(
+    mut model: M,
+    heat_pump: &HeatPumpDispatch,
+    heat_pump_vars: &HeatPumpVariables,
+    vars: &OptimizationVariables,
+    t: usize,
+    elec_demand_t: f64,
+    solar_t: f64,
+) -> M
+where
+    M: good_lp::SolverModel,
+{
+    let retention = 1.0 - heat_pump.storage_loss;
+    let cop_t = heat_pump.cop(t);
+    let est_heat = &heat_pump_vars.est_heat;
+    // Heat demand, fixed or what the building takes to stay in its comfort band
+    let heat_demand_t = match &heat_pump_vars.q_building {
+        Some(q_building) => Expression::from(q_building[t]),
+        None => Expression::from(heat_pump.hourly_heat_demand[t]),
+    };
+
+    // Buffer balance (the buffer starts empty): heat in from the heat pump, heat out to the
+    // demand that is not covered from the boost band
+    let mut heat_balance = est_heat[t] - cop_t * heat_pump_vars.e_hp[t] + heat_demand_t.clone();
+    if t > 0 {
+        heat_balance -= retention * est_heat[t - 1];
+    }
+    if let Some(q_heat_boost) = &heat_pump_vars.q_heat_boost {
+        heat_balance -= q_heat_boost[t];
+    }
+    model = model.with(constraint!(heat_balance == 0.0));
+    model = model.with(constraint!(est_heat[t] <= heat_pump.storage_capacity_wh));
+
+    // Hot water tank (starts empty like the buffer), kept above the priority level
+    if let (Some(hot_water), Some(e_dhw), Some(est_dhw)) = (
+        &heat_pump.domestic_hot_water,
+        &heat_pump_vars.e_dhw,
+        &heat_pump_vars.est_dhw,
+    ) {
+        let mut tank_balance = est_dhw[t]
+            - heat_pump.penalized_cop(hot_water.hourly_cop[t], t) * e_dhw[t]
+            + hot_water.hourly_demand[t];
+        if t > 0 {
+            tank_balance -= (1.0 - hot_water.tank_loss) * est_dhw[t - 1];
+        }
+        if let (Some(collector), Some(cap_solar_thermal), Some(q_solar_thermal)) = (
+            &hot_water.solar_thermal,
+            heat_pump_vars.cap_solar_thermal,
+            &heat_pump_vars.q_solar_thermal,
+        ) {
+            tank_balance -= q_solar_thermal[t];
+            model = model.with(constraint!(
+                q_solar_thermal[t] <= collector.yield_wh_per_m2(solar_t) * cap_solar_thermal
+            ));
+        }
+        model = model.with(constraint!(tank_balance == 0.0));
+        model = model.with(constraint!(est_dhw[t] <= hot_water.tank_capacity_wh));
+        model = model.with(constraint!(est_dhw[t] >= hot_water.priority_level_wh));
+
+        // One mode per hour: hot water with the binary on, space heating with it off
+        if let Some(dhw_on) = &heat_pump_vars.dhw_on {
+            let mut space_heating = Expression::from(heat_pump_vars.e_hp[t]);
+            if let Some(e_hp_boost) = &heat_pump_vars.e_hp_boost {
+                space_heating += e_hp_boost[t];
+            }
+            let max_power = heat_pump.max_electric_power_w;
+            model = model.with(constraint!(e_dhw[t] <= max_power * dhw_on[t]));
+            model = model.with(constraint!(
+                space_heating <= max_power - max_power * dhw_on[t]
+            ));
+        }
+    }
+
+    // Building temperature: the thermal mass keeps the heat minus the losses to the outside
+    if let (Some(building), Some(q_building), Some(t_indoor)) = (
+        &heat_pump.building,
+        &heat_pump_vars.q_building,
+        &heat_pump_vars.t_indoor,
+    ) {
+        let previous = (t + NUM_HOURS - 1) % NUM_HOURS;
+        let capacity = building.heat_capacity_wh_per_k;
+        let loss = building.heat_loss_w_per_k;
+        model = model.with(constraint!(
+            capacity * t_indoor[t] - (capacity - loss) * t_indoor[previous] - q_building[t]
+                == loss * building.hourly_outdoor_temp_c[t]
+        ));
+    }
+
+    // Weekly legionella purge, the boost band counts towards the purge level
+    if let Some(cycle) = heat_pump
+        .legionella_cycle
+        .filter(|cycle| cycle.is_purge_hour(t))
+    {
+        let mut level = Expression::from(est_heat[t]);
+        if let Some(est_heat_boost) = &heat_pump_vars.est_heat_boost {
+            level += est_heat_boost[t];
+        }
+        model = model.with(constraint!(level >= cycle.purge_level_wh));
+    }
+
+    // Electrical power limit, zero while the utility blocks the heat pump
+    let consumption = heat_pump_vars.consumption(t);
+    if heat_pump.is_blocked(t) {
+        model = model.with(constraint!(consumption == 0.0));
+    } else {
+        model = model.with(constraint!(consumption <= heat_pump.max_electric_power_w));
+    }
+
+    // SG-Ready boost: heating above the regular capacity only with own production
+    if let (Some(boost_wh), Some(e_hp_boost), Some(est_heat_boost), Some(q_heat_boost)) = (
+        heat_pump.sg_ready_boost_wh,
+        &heat_pump_vars.e_hp_boost,
+        &heat_pump_vars.est_heat_boost,
+        &heat_pump_vars.q_heat_boost,
+    ) {
+        let mut boost_balance = est_heat_boost[t] - cop_t * e_hp_boost[t] + q_heat_boost[t];
+        if t > 0 {
+            boost_balance -= retention * est_heat_boost[t - 1];
+        }
+        model = model.with(constraint!(boost_balance == 0.0));
+        model = model.with(constraint!(est_heat_boost[t] <= boost_wh));
+        model = model.with(constraint!(q_heat_boost[t] <= heat_demand_t));
+
+        // The grid supplies at most the regular loads, so the boost is covered by PV, wind or
+        // the battery
+        let mut regular_load = elec_demand_t + vars.car_charging(t) + heat_pump_vars.e_hp[t];
+        if let Some(e_dhw) = &heat_pump_vars.e_dhw {
+            regular_load += e_dhw[t];
+        }
+        if let Some(battery_in) = &vars.est_in_battery {
+            regular_load += battery_in[t];
+        }
+        model = model.with(constraint!(vars.e_grid[t] <= regular_load));
+    }
+
+    model
+}
+
+/// Adds the per-phase inverter, feed-in and import limits for hour `t`
+#[allow(clippy::too_many_arguments)]
+fn add_phase_constraints<M>(
+    mut model: M,
+    phase_config: &PhaseConfig,
+    vars: &OptimizationVariables,
+    t: usize,
+    solar_t: f64,
+    wind_t: f64,
+    elec_demand_t: f64,
+    existing_pv_w: f64,
+) -> M
+where
+    M: good_lp::SolverModel,
+{
+    let pv_shares = phase_config.pv_inverter.shares();
+    let wind_shares = phase_config.wind_inverter.shares();
+    let battery_shares = phase_config.battery_inverter.shares();
+    let car_shares = phase_config.car_charger.shares();
+
+    for phase in 0..3 {
+        // Net import on this phase (negative values are feed-in)
+        let mut net_import =
+            Expression::from(phase_config.household_load_shares[phase] * elec_demand_t);
+        net_import += car_shares[phase] * vars.car_charging(t);
+        // Heat pumps are connected to all three phases
+        if let Some(heat_pump) = &vars.heat_pump {
+            net_import += heat_pump.consumption(t) / 3.0;
+        }
+        net_import -= pv_shares[phase] * solar_t * (vars.cap_pv + existing_pv_w);
+        if let Some(cap_wind) = vars.cap_wind {
+            net_import -= wind_shares[phase] * wind_t * cap_wind;
+        }
+        if let (Some(battery_in), Some(battery_out)) = (&vars.est_in_battery, &vars.est_out_battery)
+        {
+            net_import += battery_shares[phase] * battery_in[t];
+            net_import -= battery_shares[phase] * battery_out[t];
+
+            if let Some(limit) = phase_config.max_inverter_power_per_phase_w {
+                model = model.with(constraint!(battery_shares[phase] * battery_in[t] <= limit));
+                model = model.with(constraint!(battery_shares[phase] * battery_out[t] <= limit));
             }
-        } else {
-            // If electric car is disabled, set all charging to zero
-            model = model.with(constraint!(vars.e_car_charge[t] == 0.0));
+        }
+
+        if let Some(limit) = phase_config.max_inverter_power_per_phase_w {
+            model = model.with(constraint!(
+                pv_shares[phase] * solar_t * (vars.cap_pv + existing_pv_w) <= limit
+            ));
+        }
+        if let Some(limit) = phase_config.max_feed_in_per_phase_w {
+            model = model.with(constraint!(net_import.clone() >= -limit));
+        }
+        if let Some(limit) = phase_config.max_import_per_phase_w {
+            model = model.with(constraint!(net_import <= limit));
         }
     }
 
     model
 }
 
+/// Calculates the peak import and feed-in per phase in W from the hourly results
+#[allow(clippy::too_many_arguments)]
+fn calculate_phase_peaks(
+    phase_config: &PhaseConfig,
+    electricity_demand: &[f64],
+    total_pv_production: &[f64],
+    wind_production: &[f64],
+    car_charging: &[f64],
+    heat_pump: &[f64],
+    battery_in: &[f64],
+    battery_out: &[f64],
+) -> ([f64; 3], [f64; 3]) {
+    let pv_shares = phase_config.pv_inverter.shares();
+    let wind_shares = phase_config.wind_inverter.shares();
+    let battery_shares = phase_config.battery_inverter.shares();
+    let car_shares = phase_config.car_charger.shares();
+
+    let mut peak_import = [0.0; 3];
+    let mut peak_feed_in = [0.0; 3];
+    for t in 0..electricity_demand.len() {
+        for phase in 0..3 {
+            let net_import = phase_config.household_load_shares[phase] * electricity_demand[t]
+                + car_shares[phase] * car_charging[t]
+                + heat_pump[t] / 3.0
+                + battery_shares[phase] * (battery_in[t] - battery_out[t])
+                - pv_shares[phase] * total_pv_production[t]
+                - wind_shares[phase] * wind_production[t];
+            peak_import[phase] = f64::max(peak_import[phase], net_import);
+            peak_feed_in[phase] = f64::max(peak_feed_in[phase], -net_import);
+        }
+    }
+
+    (peak_import, peak_feed_in)
+}
+
+/// Nearest-rank percentile (0-100) of the values
+fn percentile(values: &[f64], percent: f64) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let rank = (percent / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Settles a load moved by post-processing the LP schedule, with the export first and the grid
+/// second
+fn settle_load_shift(
+    original: &[f64],
+    shifted: &[f64],
+    pv_production: &mut [f64],
+    overproduction: &mut [f64],
+    grid_consumption: &mut [f64],
+) {
+    for t in 0..original.len() {
+        let shift = shifted[t] - original[t];
+        if shift > 0.0 {
+            let from_export = shift.min(overproduction[t]);
+            overproduction[t] -= from_export;
+            pv_production[t] += from_export;
+            grid_consumption[t] += shift - from_export;
+        } else {
+            let from_grid = (-shift).min(grid_consumption[t]);
+            let to_export = -shift - from_grid;
+            grid_consumption[t] -= from_grid;
+            overproduction[t] += to_export;
+            pv_production[t] -= to_export.min(pv_production[t]);
+        }
+    }
+}
+
+/// Operation of the controllable loads during a dimming event and the heat and charging the
+/// grid operator cuts above the dimmed power
+fn dimming_event_result(
+    config: &OptimizationConfig,
+    dimming: &ControllableLoadDimming,
+    event: &DimmingEvent,
+    heat_pump_hourly: &[f64],
+    vehicle_charging_hourly: &[Vec<f64>],
+) -> DimmingEventResult {
+    let excess = |power: f64| (power - dimming.min_power_w).max(0.0);
+    let hours = event.hours();
+    let unmet_heat_wh: f64 = match &config.heat_pump_dispatch {
+        Some(heat_pump) => hours
+            .clone()
+            .map(|t| excess(heat_pump_hourly[t]) * heat_pump.cop(t))
+            .sum(),
+        None => 0.0,
+    };
+    DimmingEventResult {
+        start_hour: event.start_hour,
+        duration_hours: event.duration_hours,
+        heat_pump_kwh: heat_pump_hourly[hours.clone()].iter().sum::<f64>() / 1000.0,
+        vehicle_charging_kwh: vehicle_charging_hourly
+            .iter()
+            .map(|charging| charging[hours.clone()].iter().sum::<f64>())
+            .sum::<f64>()
+            / 1000.0,
+        excess_heat_pump_kwh: heat_pump_hourly[hours.clone()]
+            .iter()
+            .map(|&power| excess(power))
+            .sum::<f64>()
+            / 1000.0,
+        excess_charging_kwh: vehicle_charging_hourly
+            .iter()
+            .flat_map(|charging| charging[hours.clone()].iter())
+            .map(|&power| excess(power))
+            .sum::<f64>()
+            / 1000.0,
+        unmet_heat_kwh: unmet_heat_wh / 1000.0,
+    }
+}
+
+/// Dispatch during a demand response event and the battery capacity needed to ride through it
+/// with the full demand. Without a grid import limit the battery covers the whole residual load.
+fn demand_response_event_result(
+    config: &OptimizationConfig,
+    event: &DemandResponseEvent,
+    total_electricity_demand: &[f64],
+    total_pv_production: &[f64],
+    wind_production: &[f64],
+    grid_consumption: &[f64],
+    battery_out_hourly: &[f64],
+) -> DemandResponseEventResult {
+    let limit = event.max_grid_import_w.unwrap_or(0.0);
+    let hours = event.hours();
+    // Battery output needed in each hour on top of the on-site production and the allowed import
+    let shortfall: Vec<f64> = hours
+        .clone()
+        .map(|t| {
+            (total_electricity_demand[t] - total_pv_production[t] - wind_production[t] - limit)
+                .max(0.0)
+        })
+        .collect();
+    // The stored energy covers the delivered energy plus the discharge losses, and the
+    // C-rate limit has to allow the largest hourly output
+    let energy_wh = shortfall.iter().sum::<f64>() / config.eta_out_bat;
+    let power_wh = shortfall.iter().copied().fold(0.0, f64::max) / config.c_rate_limit;
+    DemandResponseEventResult {
+        start_hour: event.start_hour,
+        duration_hours: event.duration_hours,
+        demand_kwh: total_electricity_demand[hours.clone()].iter().sum::<f64>() / 1000.0,
+        grid_import_kwh: grid_consumption[hours.clone()].iter().sum::<f64>() / 1000.0,
+        battery_discharge_kwh: battery_out_hourly[hours].iter().sum::<f64>() / 1000.0,
+        required_battery_kwh: energy_wh.max(power_wh) / 1000.0,
+    }
+}
+
 /// Formats the optimization solution into a SimpleOptimizationResults struct
+#[allow(clippy::too_many_arguments)]
 fn format_solution_results(
     solution: &dyn good_lp::Solution,
     config: &OptimizationConfig,
     vars: &OptimizationVariables,
+    base_demand_annual_wh: f64,
     scaled_electricity_demand: &[f64],
-    car_daily_energy_required: f64,
+    wind_capacity_factors: &[f64],
+    objective_value: f64,
+    hourly_marginal_price: Vec<f64>,
     optimization_duration: std::time::Duration,
 ) -> SimpleOptimizationResults {
     // Calculate and print results
-    let pv_sum: f64 = vars.e_pv.iter().map(|&var| solution.value(var)).sum();
-    let grid_sum: f64 = vars.e_grid.iter().map(|&var| solution.value(var)).sum();
-    let overproduction: f64 = vars.e_o.iter().map(|&var| solution.value(var)).sum();
     let total_demand: f64 = scaled_electricity_demand.iter().sum();
-    let battery_in_sum: f64 = if let Some(battery_in) = vars.est_in_battery {
+    let battery_in_sum: f64 = if let Some(battery_in) = &vars.est_in_battery {
         battery_in.iter().map(|&var| solution.value(var)).sum()
     } else {
         0.0
     };
-    let battery_out_sum: f64 = if let Some(battery_out) = vars.est_out_battery {
+    let battery_out_sum: f64 = if let Some(battery_out) = &vars.est_out_battery {
         battery_out.iter().map(|&var| solution.value(var)).sum()
     } else {
         0.0
     };
-    let car_charging_sum: f64 = vars
-        .e_car_charge
-        .iter()
-        .map(|&var| solution.value(var))
-        .sum();
+    let vehicles = config.vehicles();
 
     // Collect hourly data for struct
-    let pv_production: Vec<f64> = vars.e_pv.iter().map(|&var| solution.value(var)).collect();
-    let overproduction_hourly: Vec<f64> = vars.e_o.iter().map(|&var| solution.value(var)).collect();
-    let grid_consumption: Vec<f64> = vars.e_grid.iter().map(|&var| solution.value(var)).collect();
-    let battery_storage: Vec<f64> = if let Some(battery_storage_vars) = vars.est_battery {
+    let mut pv_production: Vec<f64> = vars.e_pv.iter().map(|&var| solution.value(var)).collect();
+    let mut overproduction_hourly: Vec<f64> =
+        vars.e_o.iter().map(|&var| solution.value(var)).collect();
+    let mut grid_consumption: Vec<f64> =
+        vars.e_grid.iter().map(|&var| solution.value(var)).collect();
+    let curtailment_hourly: Vec<f64> = match &vars.e_curtail {
+        Some(e_curtail) => e_curtail.iter().map(|&var| solution.value(var)).collect(),
+        None => vec![0.0; NUM_HOURS],
+    };
+    let battery_storage: Vec<f64> = if let Some(battery_storage_vars) = &vars.est_battery {
         battery_storage_vars
             .iter()
             .map(|&var| solution.value(var))
@@ -300,24 +1072,215 @@ fn format_solution_results(
     } else {
         vec![0.0; NUM_HOURS]
     };
-    let car_charging_hourly: Vec<f64> = vars
+    let hourly_values = |battery_vars: &Option<Vec<good_lp::Variable>>| -> Vec<f64> {
+        match battery_vars {
+            Some(battery_vars) => battery_vars
+                .iter()
+                .map(|&var| solution.value(var))
+                .collect(),
+            None => vec![0.0; NUM_HOURS],
+        }
+    };
+    let battery_in_hourly = hourly_values(&vars.est_in_battery);
+    let battery_out_hourly = hourly_values(&vars.est_out_battery);
+    let vehicle_charging_hourly: Vec<Vec<f64>> = vars
         .e_car_charge
         .iter()
-        .map(|&var| solution.value(var))
+        .map(|e_car_charge| {
+            e_car_charge
+                .iter()
+                .map(|&var| solution.value(var))
+                .collect()
+        })
         .collect();
+    let total_charging = |vehicle_charging: &[Vec<f64>]| -> Vec<f64> {
+        (0..NUM_HOURS)
+            .map(|t| vehicle_charging.iter().map(|charging| charging[t]).sum())
+            .collect()
+    };
+    let mut car_charging_hourly = total_charging(&vehicle_charging_hourly);
 
-    // Calculate total PV production (consumed + overproduction)
-    let total_pv_production: Vec<f64> = pv_production
+    // Make the charging schedules implementable by the wallboxes
+    let vehicle_schedules: Vec<VehicleChargingSchedule> = vehicles
         .iter()
-        .zip(overproduction_hourly.iter())
-        .map(|(&consumed, &over)| consumed + over)
+        .zip(&vehicle_charging_hourly)
+        .map(|(vehicle, charging)| vehicle.with_min_charging_power(charging))
         .collect();
-
-    // Combine electricity demand with car charging consumption
-    let total_electricity_demand: Vec<f64> = scaled_electricity_demand
+    let vehicle_charging_hourly: Vec<Vec<f64>> = vehicle_schedules
         .iter()
-        .zip(car_charging_hourly.iter())
-        .map(|(&demand, &charging)| demand + charging)
+        .map(|schedule| schedule.charging.clone())
+        .collect();
+    // State of charge of the scheduled charging, starting from the LP level at the end of the
+    // year as the model is cyclic
+    let vehicle_soc_hourly: Vec<Vec<f64>> = vehicles
+        .iter()
+        .zip(&vehicle_charging_hourly)
+        .zip(&vars.car_soc)
+        .map(|((vehicle, charging), car_soc)| match car_soc {
+            Some(car_soc) => vehicle.soc_levels(charging, solution.value(car_soc[NUM_HOURS - 1])),
+            None => Vec::new(),
+        })
+        .collect();
+    let scheduled_car_charging = total_charging(&vehicle_charging_hourly);
+    settle_load_shift(
+        &car_charging_hourly,
+        &scheduled_car_charging,
+        &mut pv_production,
+        &mut overproduction_hourly,
+        &mut grid_consumption,
+    );
+    car_charging_hourly = scheduled_car_charging;
+    let car_charging_sum: f64 = car_charging_hourly.iter().sum();
+
+    // Space heating consumption and buffer level, each including the SG-Ready boost
+    let (mut heat_pump_hourly, mut heat_storage_hourly): (Vec<f64>, Vec<f64>) =
+        match &vars.heat_pump {
+            Some(heat_pump) => (0..NUM_HOURS)
+                .map(|t| {
+                    let boost = |boost_vars: &Option<Vec<good_lp::Variable>>| {
+                        boost_vars
+                            .as_ref()
+                            .map(|boost_vars| solution.value(boost_vars[t]))
+                            .unwrap_or(0.0)
+                    };
+                    (
+                        solution.value(heat_pump.e_hp[t]) + boost(&heat_pump.e_hp_boost),
+                        solution.value(heat_pump.est_heat[t]) + boost(&heat_pump.est_heat_boost),
+                    )
+                })
+                .unzip(),
+            None => (vec![0.0; NUM_HOURS], vec![0.0; NUM_HOURS]),
+        };
+
+    // Heat the building took and its indoor temperature (only with a building model)
+    let (building_heat_hourly, indoor_temperature_hourly): (Vec<f64>, Vec<f64>) =
+        match &vars.heat_pump {
+            Some(HeatPumpVariables {
+                q_building: Some(q_building),
+                t_indoor: Some(t_indoor),
+                ..
+            }) => q_building
+                .iter()
+                .zip(t_indoor)
+                .map(|(&heat, &temp)| (solution.value(heat), solution.value(temp)))
+                .unzip(),
+            _ => (Vec::new(), Vec::new()),
+        };
+
+    // Make the space heating schedule implementable, against the heat the building took
+    let mut heat_pump_schedule = HeatPumpSchedule::default();
+    if let Some(heat_pump) = &config.heat_pump_dispatch {
+        let heat_pump = match heat_pump.building {
+            Some(_) => &HeatPumpDispatch {
+                hourly_heat_demand: building_heat_hourly,
+                ..heat_pump.clone()
+            },
+            None => heat_pump,
+        };
+        heat_pump_schedule = heat_pump.with_min_runtime(&heat_pump_hourly);
+        if heat_pump.min_runtime.is_some() {
+            settle_load_shift(
+                &heat_pump_hourly,
+                &heat_pump_schedule.consumption,
+                &mut pv_production,
+                &mut overproduction_hourly,
+                &mut grid_consumption,
+            );
+            heat_pump_hourly = heat_pump_schedule.consumption.clone();
+            heat_storage_hourly = heat_pump_schedule.storage.clone();
+        }
+    }
+    // Hot water mode, on top of the space heating schedule (only with domestic hot water)
+    let (hot_water_hourly, hot_water_storage_hourly): (Vec<f64>, Vec<f64>) = match &vars.heat_pump {
+        Some(HeatPumpVariables {
+            e_dhw: Some(e_dhw),
+            est_dhw: Some(est_dhw),
+            ..
+        }) => e_dhw
+            .iter()
+            .zip(est_dhw)
+            .map(|(&power, &level)| (solution.value(power), solution.value(level)))
+            .unzip(),
+        _ => (Vec::new(), Vec::new()),
+    };
+    for (heat_pump, hot_water) in heat_pump_hourly.iter_mut().zip(&hot_water_hourly) {
+        *heat_pump += hot_water;
+    }
+    let (solar_thermal_area, solar_thermal_hourly) = match &vars.heat_pump {
+        Some(HeatPumpVariables {
+            cap_solar_thermal: Some(cap_solar_thermal),
+            q_solar_thermal: Some(q_solar_thermal),
+            ..
+        }) => (
+            solution.value(*cap_solar_thermal),
+            q_solar_thermal
+                .iter()
+                .map(|&var| solution.value(var))
+                .collect(),
+        ),
+        _ => (0.0, Vec::new()),
+    };
+    let heat_pump_sum: f64 = heat_pump_hourly.iter().sum();
+    // Consumption beyond what the unpenalized COP would have needed for the same heat
+    let defrost_penalty_sum: f64 = match &config.heat_pump_dispatch {
+        Some(heat_pump) if heat_pump.defrost.is_some() => (0..NUM_HOURS)
+            .map(|t| {
+                let hot_water = hot_water_hourly.get(t).copied().unwrap_or(0.0);
+                let space_heating = heat_pump_hourly[t] - hot_water;
+                let hot_water_loss = match &heat_pump.domestic_hot_water {
+                    Some(dhw) => {
+                        1.0 - heat_pump.penalized_cop(dhw.hourly_cop[t], t) / dhw.hourly_cop[t]
+                    }
+                    None => 0.0,
+                };
+                space_heating * (1.0 - heat_pump.cop(t) / heat_pump.hourly_cop[t])
+                    + hot_water * hot_water_loss
+            })
+            .sum(),
+        _ => 0.0,
+    };
+    // Counted on the final schedule, as the minimum runtime rules may move the heating
+    let legionella_cycle_violations = match config
+        .heat_pump_dispatch
+        .as_ref()
+        .and_then(|heat_pump| heat_pump.legionella_cycle)
+    {
+        Some(cycle) => (0..NUM_HOURS)
+            .filter(|&t| cycle.is_purge_hour(t))
+            .filter(|&t| heat_storage_hourly[t] < cycle.purge_level_wh - 1e-3)
+            .count(),
+        None => 0,
+    };
+    let pv_sum: f64 = pv_production.iter().sum();
+    let grid_sum: f64 = grid_consumption.iter().sum();
+    let overproduction: f64 = overproduction_hourly.iter().sum();
+    // The heat pump consumption is part of the demand the autarky is measured against
+    let total_demand = total_demand + heat_pump_sum;
+
+    // Wind production (potential) and the part of it used on site
+    let wind_capacity = vars.cap_wind.map(|var| solution.value(var)).unwrap_or(0.0);
+    let wind_production: Vec<f64> = match vars.cap_wind {
+        Some(_) => wind_capacity_factors
+            .iter()
+            .map(|&cf| wind_capacity * cf)
+            .collect(),
+        None => vec![0.0; NUM_HOURS],
+    };
+    let wind_used: Vec<f64> = match &vars.e_wind {
+        Some(e_wind) => e_wind.iter().map(|&var| solution.value(var)).collect(),
+        None => vec![0.0; NUM_HOURS],
+    };
+
+    // Calculate total PV production (consumed + overproduction), excluding the wind surplus
+    // that also ends up in the overproduction
+    let total_pv_production: Vec<f64> = (0..NUM_HOURS)
+        .map(|t| pv_production[t] + overproduction_hourly[t] - (wind_production[t] - wind_used[t]))
+        .collect();
+    let total_pv_sum: f64 = total_pv_production.iter().sum();
+
+    // Combine electricity demand with car charging and heat pump consumption
+    let total_electricity_demand: Vec<f64> = (0..NUM_HOURS)
+        .map(|t| scaled_electricity_demand[t] + car_charging_hourly[t] + heat_pump_hourly[t])
         .collect();
 
     // Calculate autarky without battery by checking when user consumes directly from PV
@@ -326,7 +1289,7 @@ fn format_solution_results(
     let mut total_demand_without_battery = 0.0;
 
     for t in 0..NUM_HOURS {
-        let pv_prod_t = total_pv_production[t];
+        let pv_prod_t = total_pv_production[t] + wind_production[t];
         let demand_t = total_electricity_demand[t];
 
         // Direct consumption is the minimum of PV production and demand
@@ -335,44 +1298,253 @@ fn format_solution_results(
         total_demand_without_battery += demand_t;
     }
 
-    let autarky_without_battery = if total_demand_without_battery > 0.0 {
-        (direct_pv_consumption / total_demand_without_battery) * 100.0
-    } else {
+    let autarky_without_battery = Ratio::of(direct_pv_consumption, total_demand_without_battery);
+
+    let (phase_peak_import, phase_peak_feed_in) = match &config.phase_config {
+        Some(phase_config) => calculate_phase_peaks(
+            phase_config,
+            scaled_electricity_demand,
+            &total_pv_production,
+            &wind_production,
+            &car_charging_hourly,
+            &heat_pump_hourly,
+            &battery_in_hourly,
+            &battery_out_hourly,
+        ),
+        None => ([0.0; 3], [0.0; 3]),
+    };
+
+    let peak_grid_import_kw = grid_consumption.iter().copied().fold(0.0, f64::max) / 1000.0;
+    let grid_import_p99_kw = percentile(&grid_consumption, 99.0) / 1000.0;
+    // Post-processing can move load above an enforced connection limit, so count the overloads
+    // on the final schedule
+    let grid_connection_overload_hours = match &config.grid_connection {
+        Some(grid_connection) => {
+            let limit = grid_connection.max_power_w();
+            // Tolerance for solver noise at an enforced limit
+            grid_consumption
+                .iter()
+                .filter(|&&grid| grid > limit + 1e-3)
+                .count()
+        }
+        None => 0,
+    };
+
+    let dimming_events: Vec<DimmingEventResult> = config
+        .controllable_load_dimming
+        .iter()
+        .flat_map(|dimming| {
+            dimming.events.iter().map(|event| {
+                dimming_event_result(
+                    config,
+                    dimming,
+                    event,
+                    &heat_pump_hourly,
+                    &vehicle_charging_hourly,
+                )
+            })
+        })
+        .collect();
+    // Post-processing can move load above an enforced dimming, so count on the final schedule
+    let dimming_violation_hours = match &config.controllable_load_dimming {
+        Some(dimming) => (0..NUM_HOURS)
+            .filter(|&t| dimming.is_dimmed(t))
+            .filter(|&t| {
+                std::iter::once(heat_pump_hourly[t])
+                    .chain(vehicle_charging_hourly.iter().map(|charging| charging[t]))
+                    .any(|power| power > dimming.min_power_w + 1e-3)
+            })
+            .count(),
+        None => 0,
+    };
+
+    let billed_grid_peak_kw = config
+        .capacity_tariff
+        .as_ref()
+        .map(|capacity_tariff| capacity_tariff.billed_peak_kw(&grid_consumption))
+        .unwrap_or(0.0);
+    // money outputs are converted like the ROI figures, see `Currency`
+    let convert = |amount: f64| config.currency.convert(amount);
+    let annual_capacity_fee = config
+        .capacity_tariff
+        .as_ref()
+        .map(|capacity_tariff| billed_grid_peak_kw * capacity_tariff.price_per_kw_year)
+        .unwrap_or(0.0);
+
+    let (monthly_billed_peaks_kw, annual_demand_charge, annual_excess_power_penalty) =
+        match &config.demand_charges {
+            Some(demand_charges) => {
+                let peaks = DemandCharges::monthly_peaks_kw(&grid_consumption);
+                let (charge, penalty) = peaks
+                    .iter()
+                    .map(|&peak| demand_charges.monthly_cost(peak))
+                    .fold((0.0, 0.0), |(charge, penalty), (c, p)| {
+                        (charge + c, penalty + p)
+                    });
+                (peaks, charge, penalty)
+            }
+            None => (Vec::new(), 0.0, 0.0),
+        };
+
+    let demand_response_events: Vec<DemandResponseEventResult> = config
+        .demand_response_events
+        .iter()
+        .map(|event| {
+            demand_response_event_result(
+                config,
+                event,
+                &total_electricity_demand,
+                &total_pv_production,
+                &wind_production,
+                &grid_consumption,
+                &battery_out_hourly,
+            )
+        })
+        .collect();
+    let demand_response_battery_kwh = demand_response_events
+        .iter()
+        .map(|event| event.required_battery_kwh)
+        .fold(0.0, f64::max);
+
+    // Feed-in revenue lost to the monthly credit cap
+    let annual_forfeited_feed_in_credit = if vars.feed_in_credit.is_empty() {
         0.0
+    } else {
+        let revenue: f64 = vars
+            .e_o
+            .iter()
+            .zip(config.hourly_feed_in_prices())
+            .map(|(&var, price)| solution.value(var) / 1000.0 * price)
+            .sum();
+        let credited: f64 = vars
+            .feed_in_credit
+            .iter()
+            .map(|&var| solution.value(var))
+            .sum();
+        (revenue - credited).max(0.0)
+    };
+
+    // Driving energy charged away from home, bought at the public charging price
+    let (annual_public_charging_kwh, annual_public_charging_cost) = vehicles
+        .iter()
+        .filter_map(|vehicle| {
+            let public_charging = vehicle.public_charging.as_ref()?;
+            let energy_kwh =
+                vehicle.daily_energy_wh() * (365.0 - vehicle.home_charging_days()) / 1000.0;
+            Some((energy_kwh, energy_kwh * public_charging.price_per_kwh))
+        })
+        .fold(
+            (0.0, 0.0),
+            |(energy, cost), (vehicle_energy, vehicle_cost)| {
+                (energy + vehicle_energy, cost + vehicle_cost)
+            },
+        );
+
+    let pv_capacity_kw = solution.value(vars.cap_pv) / 1000.0;
+    let battery_capacity_kwh = vars
+        .cst_battery
+        .map(|var| solution.value(var))
+        .unwrap_or(0.0)
+        / 1000.0;
+    // The solver returns integers up to its tolerance
+    let module_count = |var: Option<good_lp::Variable>| {
+        var.map_or(0, |var| solution.value(var).round().max(0.0) as usize)
     };
+    let (pv_modules, battery_modules) = match &vars.discrete_sizing {
+        Some(discrete) => (
+            module_count(Some(discrete.pv_modules)),
+            module_count(discrete.battery_modules),
+        ),
+        None => (0, 0),
+    };
+    let equipment = EquipmentFeasibility::assess(
+        config,
+        pv_capacity_kw,
+        battery_capacity_kwh,
+        solar_thermal_area,
+    );
 
     SimpleOptimizationResults {
-        pv_capacity_kw: solution.value(vars.cap_pv) / 1000.0,
+        pv_capacity_kw,
         grid_capacity_kw: solution.value(vars.cap_grid) / 1000.0,
-        battery_capacity_kwh: vars
-            .cst_battery
-            .map(|var| solution.value(var))
-            .unwrap_or(0.0)
-            / 1000.0,
-        annual_pv_production_kwh: (pv_sum + overproduction) / 1000.0,
+        battery_capacity_kwh,
+        annual_pv_production_kwh: total_pv_sum / 1000.0,
         annual_grid_energy_kwh: grid_sum / 1000.0,
         annual_battery_in_kwh: battery_in_sum / 1000.0,
         annual_battery_out_kwh: battery_out_sum / 1000.0,
         annual_car_charging_kwh: car_charging_sum / 1000.0,
+        annual_heat_pump_kwh: heat_pump_sum / 1000.0,
+        annual_defrost_penalty_kwh: defrost_penalty_sum / 1000.0,
+        heat_pump_starts: heat_pump_schedule.starts,
+        heat_pump_min_runtime_violations: heat_pump_schedule.violations,
+        legionella_cycle_violations,
+        car_min_power_violations: vehicle_schedules
+            .iter()
+            .map(|schedule| schedule.violations)
+            .sum(),
         annual_overproduction_kwh: overproduction / 1000.0,
+        annual_curtailed_kwh: curtailment_hourly.iter().sum::<f64>() / 1000.0,
         annual_electricity_demand_kwh: total_demand / 1000.0,
-        required_car_energy_kwh: if config.electric_car_enabled {
-            car_daily_energy_required * 365.0 / 1000.0
-        } else {
-            0.0
-        },
+        base_demand_annual_wh,
+        required_car_energy_kwh: vehicles
+            .iter()
+            .map(|vehicle| vehicle.daily_energy_wh() * 365.0 / 1000.0)
+            .sum(),
+        annual_public_charging_kwh,
+        annual_public_charging_cost: convert(annual_public_charging_cost),
+        annual_vehicle_charging_kwh: vehicle_charging_hourly
+            .iter()
+            .map(|charging| charging.iter().sum::<f64>() / 1000.0)
+            .collect(),
         pv_coverage_percent: (pv_sum / total_demand) * 100.0,
-        autarky: (1.0 - grid_sum / total_demand) * 100.0,
+        autarky: Ratio::from_fraction(1.0 - grid_sum / total_demand),
         autarky_without_battery,
         hourly_pv_production: pv_production,
         hourly_overproduction: overproduction_hourly,
         hourly_grid_consumption: grid_consumption,
+        hourly_curtailment: curtailment_hourly,
         hourly_battery_storage: battery_storage,
+        hourly_battery_in: battery_in_hourly,
+        hourly_battery_out: battery_out_hourly,
         hourly_car_charging: car_charging_hourly,
+        hourly_vehicle_charging: vehicle_charging_hourly,
+        hourly_vehicle_soc: vehicle_soc_hourly,
+        hourly_heat_pump: heat_pump_hourly,
+        hourly_heat_storage: heat_storage_hourly,
+        hourly_heat_pump_hot_water: hot_water_hourly,
+        hourly_hot_water_storage: hot_water_storage_hourly,
+        hourly_solar_thermal_heat: solar_thermal_hourly,
+        hourly_indoor_temperature: indoor_temperature_hourly,
         hourly_total_pv_production: total_pv_production,
         hourly_total_electricity_demand: total_electricity_demand,
         hourly_electricity_demand_base: scaled_electricity_demand.to_vec(),
+        peak_grid_import_kw,
+        grid_import_p99_kw,
+        grid_connection_overload_hours,
+        dimming_events,
+        dimming_violation_hours,
+        billed_grid_peak_kw,
+        annual_capacity_fee: convert(annual_capacity_fee),
+        monthly_billed_peaks_kw,
+        annual_demand_charge: convert(annual_demand_charge),
+        annual_excess_power_penalty: convert(annual_excess_power_penalty),
+        annual_forfeited_feed_in_credit: convert(annual_forfeited_feed_in_credit),
+        demand_response_events,
+        demand_response_battery_kwh,
+        phase_peak_import_kw: phase_peak_import.map(|peak| peak / 1000.0),
+        phase_peak_feed_in_kw: phase_peak_feed_in.map(|peak| peak / 1000.0),
+        wind_capacity_kw: wind_capacity / 1000.0,
+        solar_thermal_area_m2: solar_thermal_area,
+        existing_pv_capacity_kw: config.existing_pv_w() / 1000.0,
+        existing_battery_capacity_kwh: config.existing_battery_wh() / 1000.0,
+        pv_modules,
+        battery_modules,
+        equipment,
+        annual_wind_production_kwh: wind_production.iter().sum::<f64>() / 1000.0,
+        hourly_wind_production: wind_production,
         config: config.clone(),
+        objective_value: convert(objective_value),
+        hourly_marginal_price: hourly_marginal_price.into_iter().map(convert).collect(),
         optimization_duration_ms: optimization_duration.as_millis(),
     }
 }
@@ -385,154 +1557,574 @@ pub fn run_simple_opt<S: Solver>(
     electricity_rate: ElectricityRate,
     solver: S,
 ) -> Result<SimpleOptimizationResults, Box<dyn std::error::Error>> {
-    // Use monthly demand to generate scaled load curve if available, otherwise use provided electricity_demand
-    let scaled_electricity_demand = get_scaled_electricity_demand(
-        config.monthly_demand.clone(),
-        config.electricity_usage,
+    solve_simple_opt(
+        config,
+        pv_cap_w_max,
+        solar_irradiance,
         electricity_demand,
-    )?;
+        electricity_rate,
+        solver,
+        |_, _| Vec::new(),
+    )
+}
+
+/// Like `run_simple_opt` with timestamped inputs: both series have to be hourly and cover the
+/// modelled year from January 1st, the irradiance normalized and the demand in an energy or
+/// power unit (converted to Wh).
+pub fn run_simple_opt_with_series<S: Solver>(
+    config: OptimizationConfig,
+    pv_cap_w_max: f64,
+    solar_irradiance: &TimeSeries,
+    electricity_demand: &TimeSeries,
+    electricity_rate: ElectricityRate,
+    solver: S,
+) -> Result<SimpleOptimizationResults, Box<dyn std::error::Error>> {
+    let year = TimeSeries::hourly_year(SeriesUnit::Irradiance, vec![0.0; NUM_HOURS])?;
+    if solar_irradiance.unit != SeriesUnit::Irradiance {
+        return Err(format!(
+            "Solar irradiance has to be normalized, got {:?}",
+            solar_irradiance.unit
+        )
+        .into());
+    }
+    year.check_aligned(solar_irradiance)?;
+    year.check_aligned(electricity_demand)?;
+    run_simple_opt(
+        config,
+        pv_cap_w_max,
+        solar_irradiance.values.clone(),
+        electricity_demand.wh_values()?,
+        electricity_rate,
+        solver,
+    )
+}
+
+/// Like `run_simple_opt`, but also returns the hourly marginal price of electricity in the
+/// home system (`hourly_marginal_price`), read from the duals of the energy balance.
+///
+/// Only solvers providing duals can be used, e.g. `good_lp::clarabel`.
+pub fn run_simple_opt_with_marginal_prices<S>(
+    config: OptimizationConfig,
+    pv_cap_w_max: f64,
+    solar_irradiance: Vec<f64>,
+    electricity_demand: Vec<f64>,
+    electricity_rate: ElectricityRate,
+    solver: S,
+) -> Result<SimpleOptimizationResults, Box<dyn std::error::Error>>
+where
+    S: Solver,
+    for<'a> <S::Model as SolverModel>::Solution: SolutionWithDual<'a>,
+{
+    solve_simple_opt(
+        config,
+        pv_cap_w_max,
+        solar_irradiance,
+        electricity_demand,
+        electricity_rate,
+        solver,
+        |solution, energy_balance| {
+            let duals = solution.compute_dual();
+            // Objective change per Wh of extra demand, as price per kWh
+            energy_balance
+                .iter()
+                .map(|constraint| -duals.dual(constraint.clone()) * 1000.0)
+                .collect()
+        },
+    )
+}
+
+/// One site of the model: a config with its prepared hourly series and variables. Several
+/// sites can be added to the same problem and solved together (see `portfolio_opt`).
+pub(crate) struct SiteModel {
+    config: OptimizationConfig,
+    pv_cap_w_max: f64,
+    base_demand_annual_wh: f64,
+    scaled_electricity_demand: Vec<f64>,
+    solar_irradiance: Vec<f64>, // After the PV variability of the config
+    electricity_rate_hourly: Vec<f64>,
+    feed_in_prices: Vec<f64>,
+    wind_capacity_factors: Vec<f64>,
+    vars: OptimizationVariables,
+}
+
+impl SiteModel {
+    /// Validates the config, prepares the hourly series and adds the variables of the site to
+    /// `vars`
+    pub(crate) fn new(
+        config: OptimizationConfig,
+        pv_cap_w_max: f64,
+        solar_irradiance: Vec<f64>,
+        electricity_demand: Vec<f64>,
+        electricity_rate: ElectricityRate,
+        vars: &mut ProblemVariables,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        if let Some(heat_pump) = &config.heat_pump_dispatch {
+            heat_pump.validate()?;
+        }
+        for vehicle in config.vehicles() {
+            vehicle.validate()?;
+        }
+        if let Some(grid_connection) = &config.grid_connection {
+            grid_connection.validate()?;
+        }
+        if let Some(capacity_tariff) = &config.capacity_tariff {
+            capacity_tariff.validate()?;
+        }
+        if let Some(demand_charges) = &config.demand_charges {
+            demand_charges.validate()?;
+        }
+        if let Some(dimming) = &config.controllable_load_dimming {
+            dimming.validate()?;
+        }
+        for event in &config.demand_response_events {
+            event.validate()?;
+        }
+        if let Some(roof) = &config.roof {
+            roof.validate()?;
+        }
+        if let Some(site_limits) = &config.site_limits {
+            site_limits.validate()?;
+        }
+        if let Some(sizes) = &config.discrete_sizing {
+            sizes.validate()?;
+        }
+        if let Some(budget) = config.investment_budget
+            && (budget.is_nan() || budget < config.heat_pump_investment())
+        {
+            return Err(format!(
+                "Investment budget {} does not cover the heat pump investment of {}",
+                budget,
+                config.heat_pump_investment()
+            )
+            .into());
+        }
+        if let Some(feed_in_prices) = &config.feed_in_prices
+            && (feed_in_prices.len() != NUM_HOURS || feed_in_prices.iter().any(|p| !p.is_finite()))
+        {
+            return Err(format!(
+                "Feed-in prices need {} finite hourly values, got {}",
+                NUM_HOURS,
+                feed_in_prices.len()
+            )
+            .into());
+        }
+        // NaN in the inputs would silently distort the results, other issues are only logged
+        let (solar_quality, demand_quality) = check_inputs(
+            &solar_irradiance,
+            &electricity_demand,
+            &QualityCheckConfig::default(),
+        );
+        for report in [&solar_quality, &demand_quality] {
+            if report.hours(QualityIssueKind::Missing) > 0 {
+                return Err(
+                    format!("{} (see `InputQualityReport::repair`)", report.summary()).into(),
+                );
+            }
+            if !report.is_clean() {
+                warn!("{}", report.summary());
+            }
+        }
+
+        // Use monthly demand to generate scaled load curve if available, otherwise use provided electricity_demand
+        let base_demand_annual_wh: f64 = electricity_demand.iter().sum();
+        let scaled_electricity_demand = get_scaled_electricity_demand(
+            config.monthly_demand.clone(),
+            config.electricity_usage,
+            electricity_demand,
+        )?;
+
+        // Degrade the PV series with day-to-day variability for robust sizing
+        let solar_irradiance = match &config.pv_variability {
+            Some(pv_variability) => pv_variability.apply(&solar_irradiance),
+            None => solar_irradiance,
+        };
 
-    let electricity_rate_hourly = electricity_rate.to_yearly_hourly_rates();
-    // Pre-calculate battery constants
-    let storage_retention_bat = 1.0 - config.storage_loss_bat;
-    let eta_in_bat = config.eta_in_bat;
-    let eta_out_bat_inv = 1.0 / config.eta_out_bat;
+        let mut electricity_rate_hourly = electricity_rate.to_yearly_hourly_rates();
+        config.apply_critical_peak_prices(&mut electricity_rate_hourly);
+        let feed_in_prices = config.hourly_feed_in_prices();
 
-    variables! {
-        vars:
-            cap_pv;
-            cap_grid;
+        let cap_pv = vars.add(variable());
+        let cap_grid = vars.add(variable());
+        let cst_battery: Option<good_lp::Variable> = if config.has_battery() {
+            Some(vars.add(variable().min(0.0)))
+        } else {
+            None
+        };
+        // module counts (only created for discrete sizing)
+        let discrete_sizing = config.discrete_sizing.map(|_| DiscreteSizingVariables {
+            pv_modules: vars.add(variable().integer().min(0.0)),
+            battery_modules: cst_battery.map(|_| vars.add(variable().integer().min(0.0))),
+            battery_built: cst_battery.map(|_| vars.add(variable().binary())),
+        });
+
+        // energy usage of own production
+        let mut e_pv: Vec<good_lp::Variable> = Vec::with_capacity(NUM_HOURS);
+        // energy usage of grid
+        let mut e_grid: Vec<good_lp::Variable> = Vec::with_capacity(NUM_HOURS);
+        // energy overproduction
+        let mut e_o: Vec<good_lp::Variable> = Vec::with_capacity(NUM_HOURS); // overproduction
+        // battery storage variables (only created if there is a new or existing battery)
+        let mut est_battery: Option<Vec<good_lp::Variable>> = if config.has_battery() {
+            Some(Vec::with_capacity(NUM_HOURS))
+        } else {
+            None
+        };
+        let mut est_in_battery: Option<Vec<good_lp::Variable>> = if config.has_battery() {
+            Some(Vec::with_capacity(NUM_HOURS))
+        } else {
+            None
+        };
+        let mut est_out_battery: Option<Vec<good_lp::Variable>> = if config.has_battery() {
+            Some(Vec::with_capacity(NUM_HOURS))
+        } else {
+            None
+        };
+        // electric car charging variables, one series per car
+        let e_car_charge: Vec<Vec<good_lp::Variable>> = config
+            .vehicles()
+            .iter()
+            .map(|_| {
+                (0..NUM_HOURS)
+                    .map(|_| vars.add(variable().min(0.0))) // Electric car charging energy (non-negative)
+                    .collect()
+            })
+            .collect();
+        // charging on/off variables (only created for chargers enforcing the minimum as MILP)
+        let car_charging_on: Vec<Option<Vec<good_lp::Variable>>> = config
+            .vehicles()
+            .iter()
+            .map(|vehicle| {
+                vehicle
+                    .charger
+                    .filter(|charger| charger.binary_on_off)
+                    .map(|_| {
+                        (0..NUM_HOURS)
+                            .map(|_| vars.add(variable().binary()))
+                            .collect()
+                    })
+            })
+            .collect();
+        // car state of charge variables (only created for cars with a departure SOC)
+        let car_soc: Vec<Option<Vec<good_lp::Variable>>> = config
+            .vehicles()
+            .iter()
+            .map(|vehicle| {
+                vehicle.departure_soc.map(|_| {
+                    (0..NUM_HOURS)
+                        .map(|_| {
+                            vars.add(variable().min(0.0).max(vehicle.battery_size_kwh * 1000.0))
+                        })
+                        .collect()
+                })
+            })
+            .collect();
+        // wind turbine variables (only created if a wind turbine is configured)
+        let cap_wind: Option<good_lp::Variable> = config
+            .wind_turbine
+            .as_ref()
+            .map(|_| vars.add(variable().min(0.0)));
+        let mut e_wind: Option<Vec<good_lp::Variable>> = config
+            .wind_turbine
+            .as_ref()
+            .map(|_| Vec::with_capacity(NUM_HOURS));
+        let wind_capacity_factors: Vec<f64> = config
+            .wind_turbine
+            .as_ref()
+            .map(|wind_turbine| wind_turbine.capacity_factors())
+            .unwrap_or_default();
+        // exports of the existing PV array under its old feed-in contract
+        let mut e_o_existing: Option<Vec<good_lp::Variable>> = config
+            .existing_system
+            .as_ref()
+            .filter(|existing| existing.pv_capacity_w > 0.0 && existing.feed_in_tariff.is_some())
+            .map(|_| Vec::with_capacity(NUM_HOURS));
+        // curtailed surplus (only created if curtailing pays or the export is limited in some hours,
+        // fixed to zero in the others)
+        let curtailable_hours: Vec<bool> = (0..NUM_HOURS)
+            .map(|t| {
+                feed_in_prices[t] < 0.0
+                    || electricity_rate_hourly[t] < 0.0
+                    || (config.curtail_unpaid_feed_in && feed_in_prices[t] <= 0.0)
+                    || config.feed_in_limit_share.is_some()
+            })
+            .collect();
+        let e_curtail: Option<Vec<good_lp::Variable>> =
+            curtailable_hours.contains(&true).then(|| {
+                curtailable_hours
+                    .iter()
+                    .map(|&curtailable| {
+                        let max = if curtailable { f64::INFINITY } else { 0.0 };
+                        vars.add(variable().min(0.0).max(max))
+                    })
+                    .collect()
+            });
+        // grid import peak per billing period (only created with a capacity tariff)
+        let grid_peak: Vec<good_lp::Variable> = config
+            .capacity_tariff
+            .as_ref()
+            .map(|capacity_tariff| capacity_tariff.periods(NUM_HOURS))
+            .unwrap_or_default()
+            .iter()
+            .map(|_| vars.add(variable().min(0.0)))
+            .collect();
+        // monthly import peak and its excess over the contracted power (only created with demand
+        // charges)
+        let (monthly_peak, excess_power): (Vec<good_lp::Variable>, Vec<good_lp::Variable>) =
+            if config.demand_charges.is_some() {
+                DAYS_IN_MONTH
+                    .iter()
+                    .map(|_| (vars.add(variable().min(0.0)), vars.add(variable().min(0.0))))
+                    .unzip()
+            } else {
+                (Vec::new(), Vec::new())
+            };
+        // credited feed-in revenue per month (only created with the monthly credit cap); may be
+        // negative in months where exporting costs money
+        let feed_in_credit: Vec<good_lp::Variable> = if config.monthly_feed_in_credit_cap {
+            DAYS_IN_MONTH.iter().map(|_| vars.add(variable())).collect()
+        } else {
+            Vec::new()
+        };
+
+        // heat pump variables (only created if heat pump dispatch is configured)
+        let heat_pump: Option<HeatPumpVariables> =
+            config.heat_pump_dispatch.as_ref().map(|heat_pump| {
+                let mut hourly = || -> Vec<good_lp::Variable> {
+                    (0..NUM_HOURS)
+                        .map(|_| vars.add(variable().min(0.0)))
+                        .collect()
+                };
+                let has_boost = heat_pump.sg_ready_boost_wh.is_some();
+                let has_building = heat_pump.building.is_some();
+                let has_hot_water = heat_pump.domestic_hot_water.is_some();
+                HeatPumpVariables {
+                    e_hp: hourly(),
+                    est_heat: hourly(),
+                    e_hp_boost: has_boost.then(&mut hourly),
+                    est_heat_boost: has_boost.then(&mut hourly),
+                    q_heat_boost: has_boost.then(&mut hourly),
+                    q_building: has_building.then(&mut hourly),
+                    e_dhw: has_hot_water.then(&mut hourly),
+                    est_dhw: has_hot_water.then(&mut hourly),
+                    q_solar_thermal: config.solar_thermal().map(|_| hourly()),
+                    dhw_on: heat_pump
+                        .domestic_hot_water
+                        .as_ref()
+                        .filter(|hot_water| hot_water.binary_modes)
+                        .map(|_| {
+                            (0..NUM_HOURS)
+                                .map(|_| vars.add(variable().binary()))
+                                .collect()
+                        }),
+                    cap_solar_thermal: config
+                        .solar_thermal()
+                        .map(|collector| vars.add(variable().min(0.0).max(collector.max_area_m2))),
+                    t_indoor: heat_pump.building.as_ref().map(|building| {
+                        (0..NUM_HOURS)
+                            .map(|_| {
+                                vars.add(
+                                    variable()
+                                        .min(building.min_indoor_temp_c)
+                                        .max(building.max_indoor_temp_c),
+                                )
+                            })
+                            .collect()
+                    }),
+                }
+            });
+
+        // Create variables for each hour
+        for _t in 0..NUM_HOURS {
+            e_pv.push(vars.add(variable().min(0.0))); // PV energy (non-negative)
+            e_grid.push(vars.add(variable().min(0.0))); // Grid energy (can be negative for feed-in)
+            e_o.push(vars.add(variable().min(0.0))); // Overproduction (non-negative)
+
+            // Only create battery variables if there is a battery
+            if config.has_battery() {
+                est_battery
+                    .as_mut()
+                    .unwrap()
+                    .push(vars.add(variable().min(0.0))); // Battery storage level (non-negative)
+                est_in_battery
+                    .as_mut()
+                    .unwrap()
+                    .push(vars.add(variable().min(0.0))); // Battery input energy (non-negative)
+                est_out_battery
+                    .as_mut()
+                    .unwrap()
+                    .push(vars.add(variable().min(0.0))); // Battery output energy (non-negative)
+            }
+
+            if let Some(e_wind) = e_wind.as_mut() {
+                e_wind.push(vars.add(variable().min(0.0))); // Wind energy used (non-negative)
+            }
+            if let Some(e_o_existing) = e_o_existing.as_mut() {
+                e_o_existing.push(vars.add(variable().min(0.0))); // Existing array export (non-negative)
+            }
+        }
+
+        let opt_vars = OptimizationVariables {
+            e_pv,
+            e_grid,
+            e_o,
+            e_curtail,
+            est_battery,
+            est_in_battery,
+            est_out_battery,
+            e_car_charge,
+            car_charging_on,
+            car_soc,
+            e_wind,
+            e_o_existing,
+            grid_peak,
+            monthly_peak,
+            excess_power,
+            feed_in_credit,
+            heat_pump,
+            cap_pv,
+            cap_grid,
+            cst_battery,
+            cap_wind,
+            discrete_sizing,
+        };
+
+        Ok(Self {
+            config,
+            pv_cap_w_max,
+            base_demand_annual_wh,
+            scaled_electricity_demand,
+            solar_irradiance,
+            electricity_rate_hourly,
+            feed_in_prices,
+            wind_capacity_factors,
+            vars: opt_vars,
+        })
     }
-    let cst_battery: Option<good_lp::Variable> = if config.bat_value > 0.0 {
-        Some(vars.add(variable().min(0.0)))
-    } else {
-        None
-    };
 
-    // energy usage of own production
-    let mut e_pv: Vec<good_lp::Variable> = Vec::with_capacity(NUM_HOURS);
-    // energy usage of grid
-    let mut e_grid: Vec<good_lp::Variable> = Vec::with_capacity(NUM_HOURS);
-    // energy overproduction
-    let mut e_o: Vec<good_lp::Variable> = Vec::with_capacity(NUM_HOURS); // overproduction
-    // battery storage variables (only created if bat_value > 0)
-    let mut est_battery: Option<Vec<good_lp::Variable>> = if config.bat_value > 0.0 {
-        Some(Vec::with_capacity(NUM_HOURS))
-    } else {
-        None
-    };
-    let mut est_in_battery: Option<Vec<good_lp::Variable>> = if config.bat_value > 0.0 {
-        Some(Vec::with_capacity(NUM_HOURS))
-    } else {
-        None
-    };
-    let mut est_out_battery: Option<Vec<good_lp::Variable>> = if config.bat_value > 0.0 {
-        Some(Vec::with_capacity(NUM_HOURS))
-    } else {
-        None
-    };
-    // electric car charging variables
-    let mut e_car_charge: Vec<good_lp::Variable> = Vec::with_capacity(NUM_HOURS);
-
-    // Create variables for each hour
-    for _t in 0..NUM_HOURS {
-        e_pv.push(vars.add(variable().min(0.0))); // PV energy (non-negative)
-        e_grid.push(vars.add(variable().min(0.0))); // Grid energy (can be negative for feed-in)
-        e_o.push(vars.add(variable().min(0.0))); // Overproduction (non-negative)
-
-        // Only create battery variables if bat_value > 0
-        if config.bat_value > 0.0 {
-            est_battery
-                .as_mut()
-                .unwrap()
-                .push(vars.add(variable().min(0.0))); // Battery storage level (non-negative)
-            est_in_battery
-                .as_mut()
-                .unwrap()
-                .push(vars.add(variable().min(0.0))); // Battery input energy (non-negative)
-            est_out_battery
-                .as_mut()
-                .unwrap()
-                .push(vars.add(variable().min(0.0))); // Battery output energy (non-negative)
-        }
-
-        e_car_charge.push(vars.add(variable().min(0.0))); // Electric car charging energy (non-negative)
+    pub(crate) fn config(&self) -> &OptimizationConfig {
+        &self.config
     }
 
-    // Build objective function
-    let (objective, cap_pv, cap_grid, cst_battery) = generate_objective(
-        &config,
-        &e_grid,
-        cap_pv,
-        cap_grid,
-        cst_battery,
-        &electricity_rate_hourly,
-        &e_o,
-    );
-    // Create model
-    let mut model = vars.minimise(objective).using(solver);
+    /// Annualized investment plus the grid cost minus the feed-in revenue of the site
+    pub(crate) fn objective(&self) -> Expression {
+        generate_objective(
+            &self.config,
+            &self.vars,
+            &self.electricity_rate_hourly,
+            &self.feed_in_prices,
+        )
+    }
 
-    // Calculate electric car parameters if enabled
-    let car_daily_energy_required = if config.electric_car_enabled {
-        (config.car_daily_km * config.car_efficiency_kwh_per_km * 1000.0) // Convert to Wh
-            .min(config.car_battery_size_kwh * 1000.0) // Take minimum with battery capacity
-    } else {
-        0.0
-    };
+    /// Investment in new capacities of the site, see `investment_expression`
+    pub(crate) fn investment(&self) -> Expression {
+        investment_expression(&self.config, &self.vars)
+    }
 
-    // Add fixed constraints (non-time dependent)
-    model = add_fixed_constraints(
-        model,
-        &config,
-        pv_cap_w_max,
-        cap_pv,
-        cst_battery,
-        &est_battery,
-        &e_car_charge,
-        car_daily_energy_required,
-    );
+    /// Adds the constraints of the site and returns the energy balance constraint of every hour
+    pub(crate) fn add_constraints<M>(&self, mut model: M) -> (M, Vec<ConstraintReference>)
+    where
+        M: good_lp::SolverModel,
+    {
+        // Pre-calculate battery constants
+        let storage_retention_bat = 1.0 - self.config.storage_loss_bat;
+        let eta_in_bat = self.config.eta_in_bat;
+        let eta_out_bat_inv = 1.0 / self.config.eta_out_bat;
 
-    // Create optimization variables struct
-    let opt_vars = OptimizationVariables {
-        e_pv: &e_pv,
-        e_grid: &e_grid,
-        e_o: &e_o,
-        est_battery: &est_battery,
-        est_in_battery: &est_in_battery,
-        est_out_battery: &est_out_battery,
-        e_car_charge: &e_car_charge,
-        cap_pv,
-        cap_grid,
-        cst_battery,
-    };
+        // Add fixed constraints (non-time dependent)
+        model = add_fixed_constraints(model, &self.config, self.pv_cap_w_max, &self.vars);
+        model = add_feed_in_credit_constraints(
+            model,
+            &self.vars,
+            &self.electricity_rate_hourly,
+            &self.feed_in_prices,
+        );
 
-    // Add time-dependent constraints
-    model = add_time_dependent_constraints(
-        model,
-        &config,
-        &solar_irradiance,
-        &scaled_electricity_demand,
-        &opt_vars,
-        storage_retention_bat,
-        eta_in_bat,
-        eta_out_bat_inv,
-    );
+        // Add time-dependent constraints
+        add_time_dependent_constraints(
+            model,
+            &self.config,
+            &self.solar_irradiance,
+            &self.scaled_electricity_demand,
+            &self.vars,
+            &self.wind_capacity_factors,
+            &self.feed_in_prices,
+            storage_retention_bat,
+            eta_in_bat,
+            eta_out_bat_inv,
+        )
+    }
+
+    /// Results of the site from a solution of the problem
+    pub(crate) fn results(
+        &self,
+        solution: &dyn good_lp::Solution,
+        objective_value: f64,
+        hourly_marginal_price: Vec<f64>,
+        optimization_duration: std::time::Duration,
+    ) -> SimpleOptimizationResults {
+        format_solution_results(
+            solution,
+            &self.config,
+            &self.vars,
+            self.base_demand_annual_wh,
+            &self.scaled_electricity_demand,
+            &self.wind_capacity_factors,
+            objective_value,
+            hourly_marginal_price,
+            optimization_duration,
+        )
+    }
+}
+
+/// Builds and solves the model. `marginal_prices` reads the hourly prices from the solution and
+/// the energy balance constraints.
+fn solve_simple_opt<S: Solver>(
+    config: OptimizationConfig,
+    pv_cap_w_max: f64,
+    solar_irradiance: Vec<f64>,
+    electricity_demand: Vec<f64>,
+    electricity_rate: ElectricityRate,
+    solver: S,
+    marginal_prices: impl FnOnce(
+        &mut <S::Model as SolverModel>::Solution,
+        &[ConstraintReference],
+    ) -> Vec<f64>,
+) -> Result<SimpleOptimizationResults, Box<dyn std::error::Error>> {
+    let model_build_span = info_span!("model_build").entered();
+    let mut vars = ProblemVariables::new();
+    let site = SiteModel::new(
+        config,
+        pv_cap_w_max,
+        solar_irradiance,
+        electricity_demand,
+        electricity_rate,
+        &mut vars,
+    )?;
+
+    // Build objective function
+    let objective = site.objective();
+    // Create model
+    let model = vars.minimise(objective.clone()).using(solver);
+    let (model, energy_balance) = site.add_constraints(model);
+    drop(model_build_span);
 
     // Time the optimization
     let start_time = std::time::Instant::now();
-    let opt_result = model.solve();
+    let opt_result = info_span!("solve").in_scope(|| model.solve());
     let optimization_duration = start_time.elapsed();
 
     // Solve the optimization
     match opt_result {
-        Ok(solution) => Ok(format_solution_results(
-            &solution,
-            &config,
-            &opt_vars,
-            &scaled_electricity_demand,
-            car_daily_energy_required,
-            optimization_duration,
-        )),
+        Ok(mut solution) => Ok(info_span!("post_process").in_scope(|| {
+            let objective_value = solution.eval(&objective);
+            let hourly_marginal_price = marginal_prices(&mut solution, &energy_balance);
+            site.results(
+                &solution,
+                objective_value,
+                hourly_marginal_price,
+                optimization_duration,
+            )
+        })),
         Err(e) => Err(format!("Optimization failed: {:?}", e).into()),
     }
 }
@@ -543,7 +2135,7 @@ pub fn run_simple_opt_with_output(
     pv_cap_w_max: f64,
     solar_irradiance: Vec<f64>,
     electricity_demand: Vec<f64>,
-    days_to_plot: Option<&[usize]>,
+    days_to_plot: Option<&[DaySelection]>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let results = run_simple_opt(
         config.clone(),
@@ -555,56 +2147,65 @@ pub fn run_simple_opt_with_output(
     )?;
 
     // Print results
-    println!("=== SIMPLE OPTIMIZATION RESULTS ===");
-    println!("Config: {:?}", results.config);
-    println!("PV Capacity: {:.2} kW", results.pv_capacity_kw);
-    println!("Grid Capacity: {:.2} kW", results.grid_capacity_kw);
-    println!("Battery Capacity: {:.2} kWh", results.battery_capacity_kwh);
-    println!(
+    info!("=== SIMPLE OPTIMIZATION RESULTS ===");
+    info!("Config: {:?}", results.config);
+    info!("PV Capacity: {:.2} kW", results.pv_capacity_kw);
+    info!("Grid Capacity: {:.2} kW", results.grid_capacity_kw);
+    info!("Battery Capacity: {:.2} kWh", results.battery_capacity_kwh);
+    info!(
         "Annual PV Production: {:.2} kWh",
         results.annual_pv_production_kwh
     );
-    println!(
+    info!(
         "Annual Grid Energy: {:.2} kWh",
         results.annual_grid_energy_kwh
     );
-    println!(
+    info!(
         "Annual Battery In: {:.2} kWh",
         results.annual_battery_in_kwh
     );
-    println!(
+    info!(
         "Annual Battery Out: {:.2} kWh",
         results.annual_battery_out_kwh
     );
-    if results.config.electric_car_enabled {
-        println!(
+    if !results.config.vehicles().is_empty() {
+        info!(
             "Annual Car Charging: {:.2} kWh",
             results.annual_car_charging_kwh
         );
-        println!(
+        info!(
             "Required Car Energy: {:.2} kWh",
             results.required_car_energy_kwh
         );
+        if results.annual_public_charging_kwh > 0.0 {
+            info!(
+                "Public Charging: {:.2} kWh for {:.2}",
+                results.annual_public_charging_kwh, results.annual_public_charging_cost
+            );
+        }
     }
-    println!(
+    info!(
         "Annual Overproduction: {:.2} kWh",
         results.annual_overproduction_kwh
     );
-    println!(
+    info!(
         "Annual Electricity Demand: {:.2} kWh",
         results.annual_electricity_demand_kwh
     );
-    println!("PV Coverage: {:.1}%", results.pv_coverage_percent);
-    println!("Autarky: {:.1}%", results.autarky);
-    println!(
+    info!("PV Coverage: {:.1}%", results.pv_coverage_percent);
+    info!("Autarky: {:.1}%", results.autarky.percent());
+    info!(
         "Autarky without Battery: {:.1}%",
-        results.autarky_without_battery
+        results.autarky_without_battery.percent()
     );
-    println!(
+    for issue in &results.equipment.issues {
+        warn!("{}", issue);
+    }
+    info!(
         "Optimization Duration: {} ms",
         results.optimization_duration_ms
     );
-    println!("===================================");
+    info!("===================================");
 
     // Create the hourly averages plot
     if let Err(e) = plot_hourly_averages(
@@ -614,60 +2215,65 @@ pub fn run_simple_opt_with_output(
         &results.hourly_battery_storage,
         "results/hourly_energy_profile.png",
     ) {
-        println!("Warning: Failed to create plot: {}", e);
+        warn!("Failed to create plot: {}", e);
     }
 
-    // Plot individual days if requested
-    #[allow(clippy::collapsible_if)]
-    if let Some(days) = days_to_plot {
-        if !days.is_empty() {
-            // Create results directory for individual day plots
-            if let Err(e) = std::fs::create_dir_all("results/individual_days") {
-                println!("Warning: Failed to create individual_days directory: {}", e);
-            } else {
-                const HOURS_PER_DAY: usize = 24;
+    // Deep dive into the week with the lowest PV coverage
+    if let Some(week) = find_worst_week(&results) {
+        for line in week.render().lines() {
+            info!("{}", line);
+        }
+        if let Err(e) = plot_worst_week(
+            &results,
+            &week,
+            "results/worst_week.png",
+            &PlotOptions::default(),
+        ) {
+            warn!("Failed to create worst week plot: {}", e);
+        }
+    }
 
-                for &day in days {
-                    if day >= 365 {
-                        println!("Warning: Day {} is out of range (0-364), skipping.", day);
-                        continue;
-                    }
+    // Plot individual days if requested
+    let days = resolve_days(days_to_plot.unwrap_or_default(), &results)?;
+    if !days.is_empty() {
+        // Create results directory for individual day plots
+        if let Err(e) = std::fs::create_dir_all("results/individual_days") {
+            warn!("Failed to create individual_days directory: {}", e);
+        } else {
+            for day in days {
+                // Extract data for this specific day
+                let Some(profile) = DailyEnergyProfile::from_results(&results, day) else {
+                    warn!("Day {} is out of data range, skipping.", day);
+                    continue;
+                };
 
-                    let start_hour = day * HOURS_PER_DAY;
-                    let end_hour = (start_hour + HOURS_PER_DAY)
-                        .min(results.hourly_total_electricity_demand.len());
+                // Name the plot by its date
+                let filename = format!(
+                    "results/individual_days/day_{}_energy_profile.png",
+                    date_file_label(day)
+                );
+                let title = format!("Energy Profile - {} (day {})", date_label(day), day);
 
-                    if start_hour >= results.hourly_total_electricity_demand.len() {
-                        println!("Warning: Day {} is out of data range, skipping.", day);
-                        continue;
-                    }
+                // Show the battery power next to the C-rate limit to check it visually
+                let battery_capacity_wh =
+                    (results.battery_capacity_kwh + results.existing_battery_capacity_kwh) * 1000.0;
+                let power_limit = (battery_capacity_wh > 0.0)
+                    .then_some(results.config.c_rate_limit * battery_capacity_wh);
 
-                    // Extract data for this specific day
-                    let day_demand = &results.hourly_total_electricity_demand[start_hour..end_hour];
-                    let day_pv = &results.hourly_total_pv_production[start_hour..end_hour];
-                    let day_grid = &results.hourly_grid_consumption[start_hour..end_hour];
-                    let day_battery = &results.hourly_battery_storage[start_hour..end_hour];
-
-                    // Create filename for this day
-                    let filename =
-                        format!("results/individual_days/day_{:03}_energy_profile.png", day);
-
-                    // Create custom title for this day
-                    let title = format!("Energy Profile - Day {} ({})", day, get_date_string(day));
-
-                    // Call the existing plot function with this day's data and custom title
-                    if let Err(e) = plot_hourly_averages_with_title(
-                        day_demand,
-                        day_pv,
-                        day_grid,
-                        day_battery,
-                        &filename,
-                        Some(&title),
-                    ) {
-                        println!("Warning: Failed to create plot for day {}: {}", day, e);
-                    } else {
-                        println!("Day {} energy profile plot saved as {}", day, filename);
-                    }
+                if let Err(e) = plot_day_with_battery_power(
+                    &profile,
+                    &filename,
+                    &title,
+                    power_limit,
+                    &PlotOptions::default(),
+                ) {
+                    warn!("Failed to create plot for {}: {}", date_label(day), e);
+                } else {
+                    info!(
+                        "{} energy profile plot saved as {}",
+                        date_label(day),
+                        filename
+                    );
                 }
             }
         }
@@ -676,57 +2282,66 @@ pub fn run_simple_opt_with_output(
     Ok(())
 }
 
-/// Run simple optimization with specific days to plot
+/// Example household with an electric car and a heat pump on a roof for 4 kWp
+pub fn example_scenario() -> OptimizationConfig {
+    OptimizationConfig {
+        feed_in_tariff: 0.1,
+        electricity_usage: Energy::from_kwh(5000.0),
+
+        // Electric car
+        electric_car_enabled: true,
+        car_daily_km: 10.0,
+        car_efficiency_kwh_per_km: 0.18,
+        car_battery_size_kwh: 20.0,
+        car_charge_during_day: true,
+
+        // Heat pump
+        heat_pump_enabled: true,
+        house_square_meters: 120.0,
+        insulation_level: InsulationLevel::Moderate,
+        heating_type: HeatingType::Floor,
+        monthly_temperatures: [20.0; 12],
+        inv_heat_pump: 1500.0, // Investment cost per kW
+
+        roof: Some(RoofArea {
+            area_m2: 24.0,
+            m2_per_kwp: 6.0,
+        }),
+        ..Default::default()
+    }
+}
+
+/// Run simple optimization on the bundled solar and demand data with specific days to plot.
+/// The config is used as passed, `example_scenario` provides a complete example household.
 ///
 /// # Arguments
 /// * `config` - Optimization configuration
-/// * `days_to_plot` - List of day numbers (0-364) to create individual plots for
+/// * `pv_cap_w_max` - Maximum PV capacity in W
+/// * `days_to_plot` - Days to create individual plots for, by index, date or preset
 ///
 /// # Example
 /// ```rust
-/// use solar_system_opt::simple::simple_opt_re::run_simple_opt_with_day_plots;
-/// use solar_system_opt::simple::solar_system_utils::OptimizationConfig;
+/// use solar_system_opt::simple::day_selection::parse_day_selections;
+/// use solar_system_opt::simple::simple_opt_re::{example_scenario, run_simple_opt_with_day_plots};
 ///
-/// let config = OptimizationConfig::default();
-/// let days = vec![0, 100, 200, 300]; // Plot first day of each season
-/// run_simple_opt_with_day_plots(config, &days).unwrap();
+/// let days = parse_day_selections("solstices,equinoxes,min_pv_day").unwrap();
+/// run_simple_opt_with_day_plots(example_scenario(), 10000.0, &days).unwrap();
 /// ```
 pub fn run_simple_opt_with_day_plots(
-    mut config: OptimizationConfig,
-    days_to_plot: &[usize],
+    config: OptimizationConfig,
+    pv_cap_w_max: f64,
+    days_to_plot: &[DaySelection],
 ) -> Result<(), Box<dyn std::error::Error>> {
     let solar_irradiance = load_solar_radiance_from_csv();
     let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
 
-    config.feed_in_tariff = 0.1;
-
-    // Enable electric car with example parameters
-    config.electric_car_enabled = true;
-    config.car_daily_km = 10.0;
-    config.car_efficiency_kwh_per_km = 0.18;
-    config.car_battery_size_kwh = 20.0;
-    config.car_charge_during_day = true;
-    config.electricity_usage = 5000000.0;
-
-    // Enable heat pump with example parameters
-    config.heat_pump_enabled = true;
-    config.house_square_meters = 120.0;
-    config.insulation_level = InsulationLevel::Moderate;
-    config.heating_type = HeatingType::Floor;
-    config.monthly_temperatures = [
-        20.0, 20.0, 20.0, 20.0, 20.0, 20.0, 20.0, 20.0, 20.0, 20.0, 20.0, 20.0,
-    ];
-    config.inv_heat_pump = 1500.0; // Investment cost per kW
-
     run_simple_opt_with_output(
         config,
-        4.0 * 1000.0,
+        pv_cap_w_max,
         solar_irradiance,
         electricity_demand,
         Some(days_to_plot),
-    )?;
-
-    Ok(())
+    )
 }
 
 /// Runs a static simulation of a solar PV system with battery storage over multiple years.
@@ -748,6 +2363,11 @@ pub fn run_simple_opt_with_day_plots(
 ///   - `pv_degradation`: Annual PV output degradation rate (e.g., 0.005 for 0.5% per year)
 ///   - `max_battery_charge_rate`: Maximum battery charging power in watts
 ///   - `max_battery_discharge_rate`: Maximum battery discharging power in watts
+///   - `eta_in` / `eta_out`: Charging and discharging efficiency of the battery
+///   - `initial_soc`: Battery state of charge at the start of the simulation (fraction of capacity)
+///   - `electricity_rate` / `feed_in_tariff`: Optional tariff to price grid import and export
+///   - `hourly_output_year`: Year (0-based) for which the hourly energy flows are returned
+///   - `record_final_year_soc`: Whether to return the hourly battery SOC of the last year
 ///
 /// # Returns
 ///
@@ -760,6 +2380,15 @@ pub fn run_simple_opt_with_day_plots(
 /// * `total_overproduction` - Total excess energy that couldn't be used or stored (Wh)
 /// * `total_overproduction_without_battery` - Hypothetical overproduction if battery didn't exist (Wh)
 ///
+/// In addition, the per-year values (`yearly_autarky`, `yearly_production`,
+/// `yearly_direct_consumption`, `yearly_battery_in`, `yearly_battery_out`) allow plotting the
+/// effect of degradation over the system lifetime. `final_year_battery_soc` holds the hourly
+/// battery state of charge (Wh) of the last year if `record_final_year_soc` is set.
+///
+/// Grid import and export are reported in total and per year (Wh). If an `electricity_rate`
+/// is configured, the import cost and the feed-in revenue are reported as well.
+/// `hourly` holds the hourly energy flows of `hourly_output_year`, ready for `plot_hourly_averages`.
+///
 /// # Battery Operation Logic
 ///
 /// The simulation implements a simple battery control strategy for each hour:
@@ -790,6 +2419,13 @@ pub fn run_simple_opt_with_day_plots(
 ///     pv_degradation: 0.005,
 ///     max_battery_charge_rate: 5000.0,
 ///     max_battery_discharge_rate: 5000.0,
+///     eta_in: 0.95,
+///     eta_out: 0.95,
+///     initial_soc: 0.0,
+///     record_final_year_soc: false,
+///     electricity_rate: None,
+///     feed_in_tariff: 0.079,
+///     hourly_output_year: None,
 /// };
 ///
 /// let results = run_static_simulation(
@@ -800,15 +2436,16 @@ pub fn run_simple_opt_with_day_plots(
 ///     configs,
 /// ).unwrap();
 ///
-/// println!("System autarky: {:.1}%", results.autarky * 100.0);
+/// println!("System autarky: {:.1}%", results.autarky.percent());
 /// ```
 ///
 /// # Notes
 ///
 /// * The simulation assumes 8760 hours per year (NUM_HOURS constant)
 /// * All energy values are in watt-hours (Wh) or watts (W) for consistency
-/// * Battery efficiency losses during charging/discharging are simplified (included in `battery_loss`)
-/// * The first hour of each year has simplified battery initialization logic
+/// * Charging and discharging losses are applied with `eta_in`/`eta_out`, self-discharge with `battery_loss`
+/// * The battery starts at `initial_soc` and its state of charge is carried across year boundaries
+/// * Returns an error if `configs.num_years` is zero or an efficiency is outside (0, 1]
 pub fn run_static_simulation(
     pv_cap: f64,
     mut bat_cap: f64,
@@ -816,6 +2453,15 @@ pub fn run_static_simulation(
     electricity_demand: Vec<f64>,
     configs: StaticSimulationConfigs,
 ) -> Result<StaticSimulationResults, Box<dyn std::error::Error>> {
+    if configs.num_years == 0 {
+        return Err("The static simulation needs at least one year".into());
+    }
+    for (name, eta) in [("eta_in", configs.eta_in), ("eta_out", configs.eta_out)] {
+        if !(eta > 0.0 && eta <= 1.0) {
+            return Err(format!("{} must be in (0, 1], got {}", name, eta).into());
+        }
+    }
+
     let mut solar_production = solar_irradiance
         .iter()
         .map(|&x| x * pv_cap)
@@ -826,6 +2472,18 @@ pub fn run_static_simulation(
     let mut total_battery_out = vec![0.0; configs.num_years];
     let mut total_battery_in = vec![0.0; configs.num_years];
     let mut total_production = vec![0.0; configs.num_years];
+    let mut final_year_battery_soc = None;
+    let mut hourly = None;
+    let mut soc_carryover = bat_cap * configs.initial_soc.clamp(0.0, 1.0);
+    let yearly_demand: f64 = electricity_demand.iter().sum();
+    let mut yearly_grid_import = vec![0.0; configs.num_years];
+    let mut yearly_grid_export = vec![0.0; configs.num_years];
+    let mut yearly_grid_cost = vec![0.0; configs.num_years];
+    let mut yearly_feed_in_revenue = vec![0.0; configs.num_years];
+    let electricity_rate_hourly = configs
+        .electricity_rate
+        .as_ref()
+        .map(|rate| rate.to_yearly_hourly_rates());
 
     for year in 0..configs.num_years {
         let direct_consumption = electricity_demand
@@ -843,26 +2501,25 @@ pub fn run_static_simulation(
         let mut battery_out = vec![0.0; NUM_HOURS];
         let mut battery_in = vec![0.0; NUM_HOURS];
 
-        if over_production[0] > 0.0 {
-            battery_status[0] = over_production[0]
-                .max(0.0)
-                .min(bat_cap)
-                .min(configs.max_battery_charge_rate);
-            battery_in[0] = battery_status[0];
-        }
-
-        for i in 1..NUM_HOURS {
-            let current_status = battery_status[i - 1] * (1.0 - configs.battery_loss);
+        // battery_in is the surplus taken from the PV, battery_out the energy delivered to the
+        // demand; the battery state changes by eta_in * battery_in and battery_out / eta_out
+        for i in 0..NUM_HOURS {
+            let previous_status = if i == 0 {
+                soc_carryover
+            } else {
+                battery_status[i - 1]
+            };
+            let current_status = previous_status * (1.0 - configs.battery_loss);
             if over_production[i] < 0.0 && current_status > 0.0 {
-                battery_out[i] = current_status
+                battery_out[i] = (current_status * configs.eta_out)
                     .min(-over_production[i])
                     .min(configs.max_battery_discharge_rate);
-                battery_status[i] = current_status - battery_out[i];
+                battery_status[i] = current_status - battery_out[i] / configs.eta_out;
             } else if over_production[i] > 0.0 {
                 battery_in[i] = over_production[i]
-                    .min(bat_cap - current_status)
+                    .min((bat_cap - current_status) / configs.eta_in)
                     .min(configs.max_battery_charge_rate);
-                battery_status[i] = current_status + battery_in[i];
+                battery_status[i] = current_status + battery_in[i] * configs.eta_in;
             } else {
                 battery_status[i] = current_status;
             }
@@ -874,14 +2531,55 @@ pub fn run_static_simulation(
         total_battery_out[year] = battery_out.iter().sum();
         total_battery_in[year] = battery_in.iter().sum();
         total_production[year] = solar_production.iter().sum();
+        let last_status = battery_status[NUM_HOURS - 1];
+
+        // Whatever is not covered by PV or battery is imported, unstored surplus is exported
+        let grid_import: Vec<f64> = (0..NUM_HOURS)
+            .map(|i| (electricity_demand[i] - direct_consumption[i] - battery_out[i]).max(0.0))
+            .collect();
+        let grid_export: Vec<f64> = (0..NUM_HOURS)
+            .map(|i| (over_production[i].max(0.0) - battery_in[i]).max(0.0))
+            .collect();
+        yearly_grid_import[year] = grid_import.iter().sum();
+        yearly_grid_export[year] = grid_export.iter().sum();
+        if let Some(rates) = &electricity_rate_hourly {
+            // rates are per kWh, energy is in Wh
+            yearly_grid_cost[year] = grid_import
+                .iter()
+                .zip(rates.iter())
+                .map(|(&import, &rate)| import / 1000.0 * rate)
+                .sum();
+            yearly_feed_in_revenue[year] =
+                yearly_grid_export[year] / 1000.0 * configs.feed_in_tariff;
+        }
+
+        if configs.hourly_output_year == Some(year) {
+            hourly = Some(StaticSimulationHourly {
+                year,
+                electricity_demand: electricity_demand.clone(),
+                production: solar_production.clone(),
+                direct_consumption,
+                battery_in,
+                battery_out,
+                battery_soc: battery_status.clone(),
+                grid_import,
+                grid_export,
+            });
+        }
+
+        if configs.record_final_year_soc && year + 1 == configs.num_years {
+            final_year_battery_soc = Some(battery_status);
+        }
+
+        bat_cap *= 1.0 - configs.battery_degradation;
+        // the next year starts where this one ended (within the degraded capacity)
+        soc_carryover = last_status.min(bat_cap);
+        solar_production = solar_production
+            .iter()
+            .map(|&x| x * (1.0 - configs.pv_degradation))
+            .collect::<Vec<f64>>();
+    }
 
-        bat_cap *= 1.0 - configs.battery_degradation;
-        solar_production = solar_production
-            .iter()
-            .map(|&x| x * (1.0 - configs.pv_degradation))
-            .collect::<Vec<f64>>();
-    }
-
     // Sum up all years
     let total_production_sum: f64 = total_production.iter().sum();
     let total_direct_consumption_sum: f64 = total_direct_consumption.iter().sum();
@@ -889,63 +2587,1868 @@ pub fn run_static_simulation(
     let total_battery_in_sum: f64 = total_battery_in.iter().sum();
     let total_overproduction_without_battery: f64 = total_over_production.iter().sum();
 
-    // Calculate total demand (constant across years, so just multiply by num_years)
-    let total_demand: f64 = electricity_demand.iter().sum::<f64>() * configs.num_years as f64;
+    // Calculate total demand (constant across years, so just multiply by num_years)
+    let total_demand: f64 = electricity_demand.iter().sum::<f64>() * configs.num_years as f64;
+
+    // Calculate autarky: percentage of demand met by own production (direct + from battery)
+    let autarky = Ratio::of(
+        total_direct_consumption_sum + total_battery_out_sum,
+        total_demand,
+    );
+
+    // Calculate what overproduction would be without battery:
+    // This is the production that exceeds demand at each hour, without battery storage
+    let total_overproduction = total_overproduction_without_battery - total_battery_in_sum;
+
+    let yearly_autarky = total_direct_consumption
+        .iter()
+        .zip(total_battery_out.iter())
+        .map(|(&direct, &out)| Ratio::of(direct + out, yearly_demand))
+        .collect();
+
+    Ok(StaticSimulationResults {
+        autarky,
+        total_production: total_production_sum,
+        total_direct_consumption: total_direct_consumption_sum,
+        total_battery_out: total_battery_out_sum,
+        total_battery_in: total_battery_in_sum,
+        total_overproduction,
+        total_overproduction_without_battery,
+        yearly_autarky,
+        yearly_production: total_production,
+        yearly_direct_consumption: total_direct_consumption,
+        yearly_battery_in: total_battery_in,
+        yearly_battery_out: total_battery_out,
+        final_year_battery_soc,
+        total_grid_import: yearly_grid_import.iter().sum(),
+        total_grid_export: yearly_grid_export.iter().sum(),
+        yearly_grid_import,
+        yearly_grid_export,
+        total_grid_cost: electricity_rate_hourly
+            .as_ref()
+            .map(|_| yearly_grid_cost.iter().sum()),
+        total_feed_in_revenue: electricity_rate_hourly
+            .as_ref()
+            .map(|_| yearly_feed_in_revenue.iter().sum()),
+        yearly_grid_cost: electricity_rate_hourly.as_ref().map(|_| yearly_grid_cost),
+        yearly_feed_in_revenue: electricity_rate_hourly
+            .as_ref()
+            .map(|_| yearly_feed_in_revenue),
+        hourly,
+    })
+}
+
+/// Runs the static simulation with the capacities chosen by [`run_simple_opt`] and
+/// contrasts the year-1 LP optimum with the lifetime KPIs under degradation.
+///
+/// The LP assumes perfect foresight and a single year, so its autarky is an upper bound.
+/// Feeding the same capacities into [`run_static_simulation`] shows what a rule-based
+/// battery achieves over `configs.num_years` years.
+///
+/// # Arguments
+///
+/// * `results` - Results of [`run_simple_opt`] (capacities in kW/kWh, hourly data in Wh)
+/// * `solar_irradiance` - The same hourly irradiance that was used for the optimization
+/// * `configs` - Static simulation settings (years, degradation, charge rates in W)
+///
+/// # Notes
+///
+/// * Capacities include an existing system declared in the config
+/// * The demand includes the car charging profile chosen by the optimizer
+/// * Wind generation is not part of the static simulation
+pub fn compare_optimal_with_static_simulation(
+    results: &SimpleOptimizationResults,
+    solar_irradiance: Vec<f64>,
+    configs: StaticSimulationConfigs,
+) -> Result<OptimalVsStaticComparison, Box<dyn std::error::Error>> {
+    if results.hourly_total_electricity_demand.len() != NUM_HOURS {
+        return Err(format!(
+            "Expected {} hours of demand in the optimization results, got {}",
+            NUM_HOURS,
+            results.hourly_total_electricity_demand.len()
+        )
+        .into());
+    }
+
+    // kW/kWh -> W/Wh to match the hourly data
+    let pv_capacity_kw = results.pv_capacity_kw + results.existing_pv_capacity_kw;
+    let battery_capacity_kwh = results.battery_capacity_kwh + results.existing_battery_capacity_kwh;
+    let num_years = configs.num_years;
+
+    let static_results = run_static_simulation(
+        pv_capacity_kw * 1000.0,
+        battery_capacity_kwh * 1000.0,
+        solar_irradiance,
+        results.hourly_total_electricity_demand.clone(),
+        configs,
+    )?;
+
+    let annual_demand_wh: f64 = results.hourly_total_electricity_demand.iter().sum();
+    let lifetime_own_consumption_wh =
+        static_results.total_direct_consumption + static_results.total_battery_out;
+    let lifetime_autarky = static_results.autarky;
+
+    Ok(OptimalVsStaticComparison {
+        pv_capacity_kw,
+        battery_capacity_kwh,
+        num_years,
+        optimal_autarky: results.autarky,
+        optimal_annual_pv_production_kwh: results.annual_pv_production_kwh,
+        optimal_annual_grid_energy_kwh: results.annual_grid_energy_kwh,
+        lifetime_autarky,
+        lifetime_total_pv_production_kwh: static_results.total_production / 1000.0,
+        lifetime_avg_annual_pv_production_kwh: static_results.total_production
+            / num_years as f64
+            / 1000.0,
+        lifetime_avg_annual_grid_energy_kwh: (annual_demand_wh
+            - lifetime_own_consumption_wh / num_years as f64)
+            / 1000.0,
+        autarky_gap: results.autarky - lifetime_autarky,
+        static_results,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::general::currency::Currency;
+    use crate::simple::capacity_sweep::ProductSizes;
+    use crate::simple::solar_system_utils::{
+        BlockingWindow, BuildingThermalModel, CapacityTariff, Charger, ChargingWindow,
+        DefrostPenalty, DomesticHotWater, ElectricVehicle, ExistingSystem, GridConnection,
+        HeatingZone, LegionellaCycle, MinRuntime, PeakBilling, PhaseAssignment, PublicCharging,
+        PublicChargingSplit, PvVariabilityConfig, SiteLimits, SolarThermalCollector,
+        WindTurbineConfig,
+    };
+
+    #[test]
+    fn test_run_simple_opt() {
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let electricity_demand = load_demand_from_csv();
+        let config = OptimizationConfig {
+            feed_in_tariff: 0.0,
+            fc_grid: 0.15,
+            electricity_usage: Energy::from_kwh(8000.0),
+            bat_value: Energy::ZERO,
+            ..Default::default()
+        };
+
+        let results = run_simple_opt(
+            config.clone(),
+            100000.0,
+            solar_irradiance,
+            electricity_demand.1,
+            ElectricityRate::fixed(0.1),
+            good_lp::scip,
+        )
+        .unwrap();
+        println!(
+            "Simple optimization took: {} ms",
+            results.optimization_duration_ms
+        );
+        println!("Results: {:?}", results.annual_overproduction_kwh);
+        assert!(
+            results.annual_grid_energy_kwh + results.annual_pv_production_kwh
+                - results.annual_overproduction_kwh
+                - config.electricity_usage.kwh()
+                < 100.0
+        );
+        // demand is scaled relative to the profile's own annual sum (4_173_443.464 Wh)
+        assert!((results.pv_capacity_kw - 1.8513563155033534).abs() < 1e-8);
+        assert!((results.base_demand_annual_wh - 4_173_443.464).abs() < 1e-3);
+        assert_eq!(results.battery_capacity_kwh, 0.0);
+    }
+
+    #[test]
+    fn test_run_simple_opt_single_phase_inverter_limit() {
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+        let config = OptimizationConfig {
+            bat_value: Energy::ZERO,
+            phase_config: Some(PhaseConfig {
+                pv_inverter: PhaseAssignment::L1,
+                max_inverter_power_per_phase_w: Some(4600.0),
+                ..PhaseConfig::germany()
+            }),
+            ..Default::default()
+        };
+
+        let results = run_simple_opt(
+            config,
+            20000.0,
+            solar_irradiance.clone(),
+            electricity_demand,
+            ElectricityRate::fixed(0.3),
+            good_lp::clarabel,
+        )
+        .unwrap();
+
+        let max_irradiance = solar_irradiance.iter().fold(0.0f64, |a, &b| a.max(b));
+        assert!(results.pv_capacity_kw * max_irradiance <= 4.6 + 1e-3);
+        assert!(results.phase_peak_feed_in_kw[0] <= 4.6 + 1e-3);
+        // nothing is connected to L2 and L3 except the household load
+        assert_eq!(results.phase_peak_feed_in_kw[1], 0.0);
+        assert_eq!(results.phase_peak_feed_in_kw[2], 0.0);
+    }
+
+    #[test]
+    fn test_run_simple_opt_with_wind_turbine() {
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+        let wind_turbine = WindTurbineConfig {
+            hourly_wind_speed: vec![8.0; NUM_HOURS],
+            inv_wind: 2000.0,
+            ..Default::default()
+        };
+        // 8 m/s lies halfway between the 7 and 9 m/s points of the default power curve
+        assert!((wind_turbine.power_fraction(8.0) - 0.44).abs() < 1e-9);
+        assert_eq!(wind_turbine.power_fraction(30.0), 0.0);
+
+        let config = OptimizationConfig {
+            bat_value: Energy::ZERO,
+            wind_turbine: Some(wind_turbine),
+            ..Default::default()
+        };
+
+        let results = run_simple_opt(
+            config,
+            20000.0,
+            solar_irradiance,
+            electricity_demand,
+            ElectricityRate::fixed(0.3),
+            good_lp::clarabel,
+        )
+        .unwrap();
+
+        // every kWh of wind is worth more than its annualized cost, so the turbine is maxed out
+        assert!((results.wind_capacity_kw - 5.0).abs() < 1e-2);
+        let expected_wind_kwh = 5.0 * 0.44 * NUM_HOURS as f64;
+        assert!((results.annual_wind_production_kwh - expected_wind_kwh).abs() < 5.0);
+        assert_eq!(results.hourly_wind_production.len(), NUM_HOURS);
+        // constant wind covers almost all of the load, only rare peaks are drawn from the grid
+        assert!(results.autarky.percent() > 99.0);
+    }
+
+    #[test]
+    fn test_run_simple_opt_brownfield_expansion() {
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+        let config = OptimizationConfig {
+            bat_value: Energy::ZERO,
+            existing_system: Some(ExistingSystem {
+                pv_capacity_w: 3000.0,
+                battery_capacity_wh: 5000.0,
+                feed_in_tariff: Some(0.25),
+            }),
+            ..Default::default()
+        };
+
+        let results = run_simple_opt(
+            config,
+            20000.0,
+            solar_irradiance.clone(),
+            electricity_demand,
+            ElectricityRate::fixed(0.3),
+            good_lp::clarabel,
+        )
+        .unwrap();
+
+        assert_eq!(results.existing_pv_capacity_kw, 3.0);
+        assert_eq!(results.existing_battery_capacity_kwh, 5.0);
+        // no new battery may be added, but the existing one is used
+        assert!(results.battery_capacity_kwh.abs() < 1e-3);
+        assert!(results.annual_battery_out_kwh > 100.0);
+        // hourly charge/discharge power adds up to the annual totals and respects the C-rate
+        let battery_out_kwh = results.hourly_battery_out.iter().sum::<f64>() / 1000.0;
+        assert!((battery_out_kwh - results.annual_battery_out_kwh).abs() < 1e-6);
+        let c_rate_limit_w = results.config.c_rate_limit * 5000.0;
+        assert!(
+            results
+                .hourly_battery_in
+                .iter()
+                .chain(results.hourly_battery_out.iter())
+                .all(|&power| power <= c_rate_limit_w + 1e-3)
+        );
+        // the existing array always produces, on top of whatever is added
+        let existing_production_kwh = 3.0 * solar_irradiance.iter().sum::<f64>();
+        let added_production_kwh = results.pv_capacity_kw * solar_irradiance.iter().sum::<f64>();
+        assert!(
+            (results.annual_pv_production_kwh - existing_production_kwh - added_production_kwh)
+                .abs()
+                < 1.0
+        );
+    }
+
+    #[test]
+    fn test_run_simple_opt_public_charging() {
+        let fraction = |fraction| PublicCharging {
+            split: PublicChargingSplit::Fraction(fraction),
+            price_per_kwh: 0.5,
+        };
+        assert!(fraction(1.5).validate().is_err());
+        let config = OptimizationConfig {
+            electric_car_enabled: true,
+            public_charging: Some(fraction(0.25)),
+            ..Default::default()
+        };
+        assert!((config.vehicles()[0].home_charging_days() - 273.75).abs() < 1e-9);
+
+        // the car is away on two days of every week
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+        let public_charging = PublicCharging {
+            split: PublicChargingSplit::AwayDays([true, true, false, false, false, false, false]),
+            price_per_kwh: 0.5,
+        };
+        let config = OptimizationConfig {
+            bat_value: Energy::ZERO,
+            electric_car_enabled: true,
+            public_charging: Some(public_charging.clone()),
+            ..Default::default()
+        };
+        assert_eq!(config.vehicles()[0].home_charging_days(), 260.0);
+
+        let results = run_simple_opt(
+            config,
+            10000.0,
+            solar_irradiance,
+            electricity_demand,
+            ElectricityRate::fixed(0.3),
+            good_lp::clarabel,
+        )
+        .unwrap();
+
+        // 50 km at 0.2 kWh/km per day
+        assert!((results.required_car_energy_kwh - 3650.0).abs() < 1e-6);
+        assert!((results.annual_car_charging_kwh - 2600.0).abs() < 1e-3);
+        assert!((results.annual_public_charging_kwh - 1050.0).abs() < 1e-6);
+        assert!((results.annual_public_charging_cost - 525.0).abs() < 1e-6);
+        for t in 0..NUM_HOURS {
+            if public_charging.is_away(t / 24) {
+                assert!(results.hourly_car_charging[t].abs() < 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn test_run_simple_opt_multiple_vehicles() {
+        let overnight = ChargingWindow {
+            start_hour: 18,
+            end_hour: 7,
+        };
+        assert!(overnight.contains(23) && overnight.contains(6) && !overnight.contains(7));
+        assert!(ChargingWindow::DAY.contains(6) && !ChargingWindow::DAY.contains(18));
+
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+        let second_car = ElectricVehicle {
+            daily_km: 30.0,
+            efficiency_kwh_per_km: 0.18,
+            battery_size_kwh: 40.0,
+            availability: overnight,
+            public_charging: None,
+            charger: None,
+            departure_soc: None,
+        };
+        let config = OptimizationConfig {
+            bat_value: Energy::ZERO,
+            electric_car_enabled: true,
+            electric_vehicles: vec![second_car.clone()],
+            ..Default::default()
+        };
+        assert_eq!(config.vehicles().len(), 2);
+
+        let results = run_simple_opt(
+            config,
+            10000.0,
+            solar_irradiance,
+            electricity_demand,
+            ElectricityRate::fixed(0.3),
+            good_lp::clarabel,
+        )
+        .unwrap();
+
+        assert_eq!(results.annual_vehicle_charging_kwh.len(), 2);
+        assert!((results.annual_vehicle_charging_kwh[0] - 3650.0).abs() < 1e-3);
+        assert!((results.annual_vehicle_charging_kwh[1] - 1971.0).abs() < 1e-3);
+        assert!((results.required_car_energy_kwh - 5621.0).abs() < 1e-6);
+        for t in 0..NUM_HOURS {
+            if !second_car.can_charge(t) {
+                assert!(results.hourly_vehicle_charging[1][t].abs() < 1e-3);
+            }
+            let total =
+                results.hourly_vehicle_charging[0][t] + results.hourly_vehicle_charging[1][t];
+            assert!((results.hourly_car_charging[t] - total).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_charger_min_charging_power() {
+        assert!((Charger::KW_11.max_power_w() - 11040.0).abs() < 1e-9);
+        assert!((Charger::KW_11.min_power_w() - 4140.0).abs() < 1e-9);
+        assert!((Charger::KW_3_7.min_power_w() - 1380.0).abs() < 1e-9);
+        assert!(Charger::KW_22.validate().is_ok());
+        let two_phases = Charger {
+            phases: 2,
+            ..Charger::KW_11
+        };
+        assert!(two_phases.validate().is_err());
+        let min_above_max = Charger {
+            min_current_a: 20.0,
+            ..Charger::KW_11
+        };
+        assert!(min_above_max.validate().is_err());
+
+        let vehicle = ElectricVehicle {
+            charger: Some(Charger::KW_11),
+            ..Default::default()
+        };
+        let mut charging = vec![0.0; 72];
+        charging[8] = 1000.0;
+        charging[9] = 1000.0;
+        charging[10] = 1000.0;
+        charging[12] = 6000.0;
+        charging[30] = 500.0; // less than one hour at minimum power
+        charging[60] = 5000.0;
+        let schedule = vehicle.with_min_charging_power(&charging);
+
+        // 9 kWh fit into two hours at 4.14-11.04 kW
+        assert!((schedule.charging[12] - 4860.0).abs() < 1e-9);
+        assert!((schedule.charging[8] - 4140.0).abs() < 1e-9);
+        assert_eq!(schedule.charging[9], 0.0);
+        assert_eq!(schedule.charging[10], 0.0);
+        // the small session is charged with the next one
+        assert_eq!(schedule.charging[30], 0.0);
+        assert!((schedule.charging[60] - 5500.0).abs() < 1e-9);
+        assert_eq!(schedule.violations, 0);
+        let total = |charging: &[f64]| charging.iter().sum::<f64>();
+        assert!((total(&schedule.charging) - total(&charging)).abs() < 1e-9);
+
+        // in the last session it stays below the minimum
+        let schedule = vehicle.with_min_charging_power(&charging[..48]);
+        assert_eq!(schedule.charging[30], 500.0);
+        assert_eq!(schedule.violations, 1);
+
+        // without a charger the schedule is kept
+        let schedule = ElectricVehicle::default().with_min_charging_power(&charging);
+        assert_eq!(schedule.charging, charging);
+        assert_eq!(schedule.violations, 0);
+    }
+
+    fn assert_charger_limits(results: &SimpleOptimizationResults, charger: &Charger) {
+        assert!((results.annual_car_charging_kwh - 3650.0).abs() < 1e-3);
+        for &charging in &results.hourly_car_charging {
+            assert!(charging <= charger.max_power_w() + 1e-3);
+            assert!(charging < 1.0 || charging >= charger.min_power_w() - 1.0);
+        }
+        assert_eq!(results.car_min_power_violations, 0);
+    }
+
+    #[test]
+    fn test_run_simple_opt_with_charger() {
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+        let config = OptimizationConfig {
+            bat_value: Energy::ZERO,
+            electric_car_enabled: true,
+            car_charger: Some(Charger::KW_3_7),
+            ..Default::default()
+        };
+
+        let results = run_simple_opt(
+            config,
+            10000.0,
+            solar_irradiance,
+            electricity_demand,
+            ElectricityRate::fixed(0.3),
+            good_lp::clarabel,
+        )
+        .unwrap();
+
+        assert_charger_limits(&results, &Charger::KW_3_7);
+        let balance: f64 = (0..NUM_HOURS)
+            .map(|t| {
+                results.hourly_pv_production[t] + results.hourly_grid_consumption[t]
+                    - results.hourly_total_electricity_demand[t]
+            })
+            .sum();
+        assert!(balance.abs() < 1.0);
+    }
+
+    #[test]
+    fn test_run_simple_opt_with_grid_connection() {
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+        let grid_connection = GridConnection {
+            phases: 1,
+            fuse_current_a: 16.0,
+            enforce: true,
+            ..Default::default()
+        };
+        let config = OptimizationConfig {
+            bat_value: Energy::ZERO,
+            electric_car_enabled: true,
+            grid_connection: Some(grid_connection.clone()),
+            ..Default::default()
+        };
+
+        let results = run_simple_opt(
+            config,
+            10000.0,
+            solar_irradiance,
+            electricity_demand,
+            ElectricityRate::fixed(0.3),
+            good_lp::clarabel,
+        )
+        .unwrap();
+
+        let limit_kw = grid_connection.max_power_w() / 1000.0;
+        assert!(results.peak_grid_import_kw <= limit_kw + 1e-3);
+        assert!(results.peak_grid_import_kw > 0.0);
+        assert!(results.grid_import_p99_kw <= results.peak_grid_import_kw);
+        assert_eq!(results.grid_connection_overload_hours, 0);
+
+        assert!(
+            GridConnection {
+                phases: 2,
+                ..Default::default()
+            }
+            .validate()
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_run_simple_opt_with_roof_and_budget() {
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+        let roof = RoofArea {
+            area_m2: 30.0,
+            m2_per_kwp: 6.0,
+        };
+        let config = OptimizationConfig {
+            bat_value: Energy::ZERO,
+            roof: Some(roof),
+            ..Default::default()
+        };
+
+        // PV pays off up to the limit, so the roof (5 kWp) is filled
+        let results = run_simple_opt(
+            config.clone(),
+            20000.0,
+            solar_irradiance.clone(),
+            electricity_demand.clone(),
+            ElectricityRate::fixed(0.3),
+            good_lp::clarabel,
+        )
+        .unwrap();
+        assert!((results.pv_capacity_kw - 5.0).abs() < 1e-2);
+
+        let budget_config = OptimizationConfig {
+            roof: None,
+            investment_budget: Some(1500.0),
+            ..config.clone()
+        };
+        let results = run_simple_opt(
+            budget_config.clone(),
+            20000.0,
+            solar_irradiance,
+            electricity_demand,
+            ElectricityRate::fixed(0.3),
+            good_lp::clarabel,
+        )
+        .unwrap();
+        let investment = results.pv_capacity_kw * budget_config.inv_pv;
+        assert!(investment <= 1500.0 + 1e-3);
+        assert!(investment > 1450.0);
+
+        // an existing array takes its share of the roof
+        let existing_config = OptimizationConfig {
+            existing_system: Some(ExistingSystem {
+                pv_capacity_w: 2000.0,
+                battery_capacity_wh: 0.0,
+                feed_in_tariff: None,
+            }),
+            ..config
+        };
+        assert!((existing_config.pv_limit_w(20000.0) - 3000.0).abs() < 1e-9);
+        assert_eq!(existing_config.pv_limit_w(1000.0), 1000.0);
+        assert!(
+            RoofArea {
+                area_m2: 30.0,
+                m2_per_kwp: 0.0
+            }
+            .validate()
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_run_simple_opt_with_capacity_tariff() {
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+        let capacity_tariff = CapacityTariff {
+            price_per_kw_year: 60.0,
+            billing: PeakBilling::MonthlyAverage,
+            min_billed_kw: 0.0,
+        };
+        let run = |capacity_tariff: Option<CapacityTariff>| {
+            let config = OptimizationConfig {
+                bat_value: Energy::ZERO,
+                electric_car_enabled: true,
+                capacity_tariff,
+                ..Default::default()
+            };
+            run_simple_opt(
+                config,
+                10000.0,
+                solar_irradiance.clone(),
+                electricity_demand.clone(),
+                ElectricityRate::fixed(0.3),
+                good_lp::clarabel,
+            )
+            .unwrap()
+        };
+
+        let energy_only = run(None);
+        let with_fee = run(Some(capacity_tariff.clone()));
+
+        let unmanaged_peak_kw =
+            capacity_tariff.billed_peak_kw(&energy_only.hourly_grid_consumption);
+        assert!(with_fee.billed_grid_peak_kw < unmanaged_peak_kw);
+        assert!((with_fee.annual_capacity_fee - with_fee.billed_grid_peak_kw * 60.0).abs() < 1e-6);
+        assert_eq!(energy_only.annual_capacity_fee, 0.0);
+
+        // with a conversion rate the euro costs are reported in the target currency
+        let config = OptimizationConfig {
+            bat_value: Energy::ZERO,
+            electric_car_enabled: true,
+            capacity_tariff: Some(capacity_tariff),
+            currency: Currency::with_conversion_rate("GBP", 0.85).unwrap(),
+            ..Default::default()
+        };
+        let converted = run_simple_opt(
+            config,
+            10000.0,
+            solar_irradiance.clone(),
+            electricity_demand.clone(),
+            ElectricityRate::fixed(0.3),
+            good_lp::clarabel,
+        )
+        .unwrap();
+        assert!(
+            (converted.annual_capacity_fee - with_fee.annual_capacity_fee * 0.85).abs()
+                < 1e-3 * with_fee.annual_capacity_fee
+        );
+        assert!(
+            (converted.objective_value - with_fee.objective_value * 0.85).abs()
+                < 1e-3 * with_fee.objective_value.abs()
+        );
+    }
+
+    #[test]
+    fn test_run_simple_opt_with_demand_response_events() {
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+        // no import on a January evening, critical peak price on the next one
+        let load_reduction = DemandResponseEvent::load_reduction(10, 17, 4, 0.0);
+        let critical_peak = DemandResponseEvent::critical_peak(11, 17, 4, 2.0);
+        let config = OptimizationConfig {
+            demand_response_events: vec![load_reduction.clone(), critical_peak.clone()],
+            ..Default::default()
+        };
+        assert_eq!(config.grid_import_limit_w(10 * 24 + 18), Some(0.0));
+        assert_eq!(config.grid_import_limit_w(11 * 24 + 18), None);
+
+        let results = run_simple_opt(
+            config,
+            10000.0,
+            solar_irradiance,
+            electricity_demand,
+            ElectricityRate::fixed(0.3),
+            good_lp::clarabel,
+        )
+        .unwrap();
+
+        for t in load_reduction.hours() {
+            assert!(results.hourly_grid_consumption[t] <= 1e-3);
+        }
+        let events = &results.demand_response_events;
+        assert_eq!(events.len(), 2);
+        assert!(events[0].grid_import_kwh <= 1e-3);
+        assert!(events[0].battery_discharge_kwh > 0.0);
+        assert!(events[0].required_battery_kwh > 0.0);
+        assert!(results.battery_capacity_kwh >= events[0].required_battery_kwh - 1e-3);
+        assert!(
+            (results.demand_response_battery_kwh
+                - events[0]
+                    .required_battery_kwh
+                    .max(events[1].required_battery_kwh))
+            .abs()
+                < 1e-9
+        );
+
+        // events must lie within the year
+        assert!(
+            DemandResponseEvent::load_reduction(364, 22, 4, 0.0)
+                .validate()
+                .is_err()
+        );
+        assert!(
+            DemandResponseEvent::load_reduction(0, 17, 4, -1.0)
+                .validate()
+                .is_err()
+        );
+        assert!(critical_peak.validate().is_ok());
+    }
+
+    #[test]
+    fn test_run_simple_opt_with_monthly_feed_in_credit_cap() {
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+        let run = |monthly_feed_in_credit_cap: bool| {
+            let config = OptimizationConfig {
+                feed_in_tariff: 0.2,
+                monthly_feed_in_credit_cap,
+                ..Default::default()
+            };
+            run_simple_opt(
+                config,
+                20000.0,
+                solar_irradiance.clone(),
+                electricity_demand.clone(),
+                ElectricityRate::fixed(0.3),
+                good_lp::clarabel,
+            )
+            .unwrap()
+        };
+
+        let uncapped = run(false);
+        let capped = run(true);
+        assert!(capped.pv_capacity_kw < uncapped.pv_capacity_kw);
+        assert_eq!(uncapped.annual_forfeited_feed_in_credit, 0.0);
+        assert!(capped.annual_forfeited_feed_in_credit >= 0.0);
+    }
+
+    #[test]
+    fn test_run_simple_opt_with_negative_prices() {
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+        // feed-in is unpaid from 11:00 to 14:00 and costs money at 13:00, import pays at 13:00
+        let feed_in_prices: Vec<f64> = (0..NUM_HOURS)
+            .map(|t| match t % 24 {
+                11 | 12 => 0.0,
+                13 => -0.05,
+                _ => 0.08,
+            })
+            .collect();
+        let purchase_rates: Vec<f64> = (0..NUM_HOURS)
+            .map(|t| if t % 24 == 13 { -0.1 } else { 0.3 })
+            .collect();
+        let config = OptimizationConfig {
+            bat_value: Energy::ZERO,
+            pv_fixed: true,
+            feed_in_prices: Some(feed_in_prices.clone()),
+            curtail_unpaid_feed_in: true,
+            ..Default::default()
+        };
+
+        let results = run_simple_opt(
+            config,
+            10000.0,
+            solar_irradiance,
+            electricity_demand,
+            ElectricityRate::hourly(purchase_rates),
+            good_lp::clarabel,
+        )
+        .unwrap();
+
+        for (t, &feed_in_price) in feed_in_prices.iter().enumerate() {
+            if feed_in_price <= 0.0 {
+                assert!(results.hourly_overproduction[t] < 1e-3);
+            } else {
+                assert!(results.hourly_curtailment[t].abs() < 1e-3);
+            }
+            // the negative purchase price makes importing cheaper than own PV
+            if t % 24 == 13 {
+                assert!(results.hourly_pv_production[t] < 1.0);
+            }
+        }
+        assert!(results.annual_curtailed_kwh > 0.0);
+        let surplus: f64 = results.hourly_overproduction.iter().sum::<f64>()
+            + results.hourly_curtailment.iter().sum::<f64>();
+        assert!(surplus / 1000.0 > results.annual_overproduction_kwh);
+
+        let config = OptimizationConfig {
+            feed_in_prices: Some(vec![0.08; 24]),
+            ..Default::default()
+        };
+        assert!(
+            run_simple_opt(
+                config,
+                10000.0,
+                load_solar_radiance_from_csv(),
+                load_demand_from_csv().1,
+                ElectricityRate::fixed(0.3),
+                good_lp::clarabel,
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_run_simple_opt_with_feed_in_limit() {
+        let config = OptimizationConfig {
+            bat_value: Energy::ZERO,
+            pv_fixed: true,
+            feed_in_limit_share: Some(0.6),
+            ..Default::default()
+        };
+
+        let results = run_simple_opt(
+            config,
+            10000.0,
+            load_solar_radiance_from_csv(),
+            load_demand_from_csv().1,
+            ElectricityRate::fixed(0.3),
+            good_lp::clarabel,
+        )
+        .unwrap();
+
+        assert!((results.pv_capacity_kw - 10.0).abs() < 1e-6);
+        for &export in &results.hourly_overproduction {
+            assert!(export <= 6000.0 + 1e-3);
+        }
+        assert!(results.annual_curtailed_kwh > 0.0);
+    }
+
+    #[test]
+    fn test_run_simple_opt_with_series() {
+        let solar = TimeSeries::hourly_year(SeriesUnit::Irradiance, load_solar_radiance_from_csv())
+            .unwrap();
+        let demand_wh = load_demand_from_csv().1;
+        let demand = TimeSeries::hourly_year(
+            SeriesUnit::KWh,
+            demand_wh.iter().map(|wh| wh / 1000.0).collect(),
+        )
+        .unwrap();
+        let config = OptimizationConfig {
+            bat_value: Energy::ZERO,
+            pv_fixed: true,
+            ..Default::default()
+        };
+        let results = run_simple_opt_with_series(
+            config.clone(),
+            5000.0,
+            &solar,
+            &demand,
+            ElectricityRate::fixed(0.3),
+            good_lp::clarabel,
+        )
+        .unwrap();
+        let expected = run_simple_opt(
+            config.clone(),
+            5000.0,
+            load_solar_radiance_from_csv(),
+            demand_wh,
+            ElectricityRate::fixed(0.3),
+            good_lp::clarabel,
+        )
+        .unwrap();
+        assert!((results.annual_grid_energy_kwh - expected.annual_grid_energy_kwh).abs() < 1e-3);
+
+        // the demand of a year starting a day late doesn't line up with the irradiance
+        let shifted = TimeSeries {
+            start: crate::general::time_series::Timestamp::from_hour(24),
+            ..demand.clone()
+        };
+        let result = run_simple_opt_with_series(
+            config.clone(),
+            5000.0,
+            &solar,
+            &shifted,
+            ElectricityRate::fixed(0.3),
+            good_lp::clarabel,
+        );
+        assert!(result.unwrap_err().to_string().contains("not aligned"));
+        assert!(
+            run_simple_opt_with_series(
+                config,
+                5000.0,
+                &demand,
+                &demand,
+                ElectricityRate::fixed(0.3),
+                good_lp::clarabel,
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_run_simple_opt_rejects_missing_inputs() {
+        let mut demand = load_demand_from_csv().1;
+        demand[42] = f64::NAN;
+        let result = run_simple_opt(
+            OptimizationConfig::default(),
+            10000.0,
+            load_solar_radiance_from_csv(),
+            demand,
+            ElectricityRate::fixed(0.3),
+            good_lp::clarabel,
+        );
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("Electricity demand: 1 h missing")
+        );
+    }
+
+    #[test]
+    fn test_run_simple_opt_with_marginal_prices() {
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+        let config = OptimizationConfig {
+            bat_value: Energy::ZERO,
+            ..Default::default()
+        };
+
+        let results = run_simple_opt_with_marginal_prices(
+            config.clone(),
+            10000.0,
+            solar_irradiance,
+            electricity_demand,
+            ElectricityRate::fixed(0.3),
+            good_lp::clarabel,
+        )
+        .unwrap();
+
+        // Without battery and flexible loads the objective is investment, grid cost and feed-in
+        let expected_objective = results.pv_capacity_kw * config.inv_pv * config.annuity
+            + results.annual_grid_energy_kwh * 0.3
+            - results.annual_overproduction_kwh * config.feed_in_tariff;
+        assert!((results.objective_value - expected_objective).abs() < 1.0);
+
+        // Extra demand is bought from the grid, or costs the feed-in revenue in export hours
+        assert_eq!(results.hourly_marginal_price.len(), NUM_HOURS);
+        for t in 0..NUM_HOURS {
+            if results.hourly_pv_production[t] < 1.0 {
+                assert!((results.hourly_marginal_price[t] - 0.3).abs() < 1e-3);
+            } else if results.hourly_overproduction[t] > 1.0 {
+                assert!((results.hourly_marginal_price[t] - config.feed_in_tariff).abs() < 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn test_demand_charges_monthly_cost() {
+        let mut grid_import = vec![500.0; NUM_HOURS];
+        grid_import[10] = 4000.0; // January
+        grid_import[31 * 24 + 5] = 2000.0; // February
+        let peaks = DemandCharges::monthly_peaks_kw(&grid_import);
+        assert_eq!(peaks.len(), 12);
+        assert_eq!(&peaks[..3], &[4.0, 2.0, 0.5]);
+        assert_eq!(month_of_hour(31 * 24 + 5), 1);
+        assert_eq!(month_of_hour(NUM_HOURS - 1), 11);
+
+        let demand_charges = DemandCharges {
+            price_per_kw_month: 10.0,
+            contracted_kw: 3.0,
+            excess_price_per_kw: 20.0,
+        };
+        assert_eq!(demand_charges.monthly_cost(4.0), (40.0, 20.0));
+        assert_eq!(demand_charges.monthly_cost(2.0), (20.0, 0.0));
+        assert!(
+            DemandCharges {
+                contracted_kw: -1.0,
+                ..demand_charges
+            }
+            .validate()
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_capacity_tariff_billed_peak() {
+        let mut grid_import = vec![500.0; NUM_HOURS];
+        grid_import[10] = 4000.0; // January
+        grid_import[31 * 24 + 5] = 2000.0; // February
+        let tariff = CapacityTariff {
+            price_per_kw_year: 50.0,
+            billing: PeakBilling::AnnualPeak,
+            min_billed_kw: 0.0,
+        };
+        assert_eq!(tariff.periods(NUM_HOURS).len(), 1);
+        assert!((tariff.billed_peak_kw(&grid_import) - 4.0).abs() < 1e-9);
+
+        let tariff = CapacityTariff {
+            billing: PeakBilling::MonthlyAverage,
+            min_billed_kw: 1.0,
+            ..tariff
+        };
+        let periods = tariff.periods(NUM_HOURS);
+        assert_eq!(periods.len(), 12);
+        assert_eq!(periods[11].end, NUM_HOURS);
+        // 4 kW, 2 kW and ten months at the 1 kW minimum
+        assert!((tariff.billed_peak_kw(&grid_import) - 16.0 / 12.0).abs() < 1e-9);
+        assert!(
+            CapacityTariff {
+                price_per_kw_year: -1.0,
+                ..tariff
+            }
+            .validate()
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_percentile() {
+        let values: Vec<f64> = (1..=100).rev().map(f64::from).collect();
+        assert_eq!(percentile(&values, 99.0), 99.0);
+        assert_eq!(percentile(&values, 100.0), 100.0);
+        assert_eq!(percentile(&values, 0.0), 1.0);
+        assert_eq!(percentile(&[], 99.0), 0.0);
+    }
+
+    #[test]
+    fn test_run_simple_opt_with_departure_soc() {
+        use ems_model::building::electricity::{HourRange, RateTier, WeekdayType};
+
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+        // cheap night tariff from 0:00 to 6:00, the car is plugged in from 18:00 to 7:00
+        let hours = |from, till| {
+            vec![
+                HourRange::new(from, till, WeekdayType::Weekday),
+                HourRange::new(from, till, WeekdayType::Weekend),
+            ]
+        };
+        let tariff = ElectricityRate::tiered(vec![
+            RateTier::new("Night".to_string(), 0.15, hours(0, 6)),
+            RateTier::new("Day".to_string(), 0.35, hours(6, 0)),
+        ]);
+        let car = ElectricVehicle {
+            availability: ChargingWindow {
+                start_hour: 18,
+                end_hour: 7,
+            },
+            departure_soc: Some(0.8),
+            ..Default::default()
+        };
+        assert!(
+            ElectricVehicle {
+                departure_soc: Some(1.2),
+                ..Default::default()
+            }
+            .validate()
+            .is_err()
+        );
+        let config = OptimizationConfig {
+            bat_value: Energy::ZERO,
+            electric_vehicles: vec![car.clone()],
+            ..Default::default()
+        };
+
+        let results = run_simple_opt(
+            config,
+            10000.0,
+            solar_irradiance,
+            electricity_demand,
+            tariff,
+            good_lp::clarabel,
+        )
+        .unwrap();
+
+        assert!((results.annual_car_charging_kwh - 3650.0).abs() < 1e-3);
+        let soc = &results.hourly_vehicle_soc[0];
+        assert_eq!(soc.len(), NUM_HOURS);
+        for t in 1..NUM_HOURS {
+            assert!(soc[t] >= -1e-3 && soc[t] <= 50000.0 + 1e-3);
+            if car.departure_energy_wh(t) > 0.0 {
+                assert!(soc[t - 1] >= 0.8 * 50000.0 - 1e-3);
+            }
+        }
+        // the car is charged in the cheap night hours or from the morning PV before it leaves
+        let morning_charging: f64 = (0..NUM_HOURS)
+            .filter(|t| t % 24 < 7)
+            .map(|t| results.hourly_car_charging[t])
+            .sum();
+        assert!(morning_charging / 1000.0 > 0.99 * results.annual_car_charging_kwh);
+    }
+
+    #[test]
+    fn test_run_simple_opt_with_binary_charger() {
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+        let charger = Charger {
+            binary_on_off: true,
+            ..Charger::KW_11
+        };
+        let config = OptimizationConfig {
+            bat_value: Energy::ZERO,
+            electric_car_enabled: true,
+            car_charger: Some(charger),
+            ..Default::default()
+        };
+
+        // the on/off variables need a solver with integer support
+        let results = run_simple_opt(
+            config,
+            10000.0,
+            solar_irradiance,
+            electricity_demand,
+            ElectricityRate::fixed(0.3),
+            good_lp::scip,
+        )
+        .unwrap();
+
+        assert_charger_limits(&results, &charger);
+    }
+
+    #[test]
+    fn test_run_simple_opt_with_discrete_sizing() {
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+        let sizes = ProductSizes::default();
+        let config = OptimizationConfig {
+            bat_value: Energy::from_kwh(9.2),
+            discrete_sizing: Some(sizes),
+            ..Default::default()
+        };
+
+        // the module counts need a solver with integer support
+        let results = run_simple_opt(
+            config,
+            7430.0,
+            solar_irradiance,
+            electricity_demand,
+            ElectricityRate::fixed(0.3),
+            good_lp::scip,
+        )
+        .unwrap();
+
+        let pv_kw = results.pv_modules as f64 * sizes.module_power_w / 1000.0;
+        assert!((results.pv_capacity_kw - pv_kw).abs() < 1e-6);
+        assert!(results.pv_capacity_kw <= 7.43 + 1e-6);
+        let battery_kwh = results.battery_modules as f64 * sizes.battery_module_kwh;
+        assert!((results.battery_capacity_kwh - battery_kwh).abs() < 1e-6);
+        assert!(
+            results.battery_modules == 0 || results.battery_modules >= sizes.min_battery_modules
+        );
+        // 9.2 kWh hold three modules at most
+        assert!(results.battery_modules <= 3);
+    }
+
+    #[test]
+    fn test_heat_pump_dispatch_validation() {
+        let window = |start_hour, duration_hours| BlockingWindow {
+            start_hour,
+            duration_hours,
+        };
+        let heat_pump = HeatPumpDispatch {
+            blocking_windows: vec![window(23, 2), window(11, 2), window(17, 1)],
+            ..Default::default()
+        };
+        assert!(heat_pump.validate().is_ok());
+        // windows may cross midnight
+        assert!(heat_pump.is_blocked(23) && heat_pump.is_blocked(24) && !heat_pump.is_blocked(25));
+        assert!(heat_pump.is_blocked(24 * 100 + 12) && !heat_pump.is_blocked(13));
+
+        let too_many = HeatPumpDispatch {
+            blocking_windows: vec![window(0, 1); 4],
+            ..Default::default()
+        };
+        assert!(too_many.validate().is_err());
+        let too_long = HeatPumpDispatch {
+            blocking_windows: vec![window(12, 3)],
+            ..Default::default()
+        };
+        assert!(too_long.validate().is_err());
+        let short_series = HeatPumpDispatch {
+            hourly_cop: vec![3.0; 24],
+            ..Default::default()
+        };
+        assert!(short_series.validate().is_err());
+    }
+
+    #[test]
+    fn test_heat_pump_min_runtime() {
+        // one hour on, three hours off, the buffer is empty again before each start
+        let consumption: Vec<f64> = (0..NUM_HOURS)
+            .map(|t| if t % 4 == 0 { 1000.0 } else { 0.0 })
+            .collect();
+        let heat_pump = HeatPumpDispatch {
+            hourly_heat_demand: vec![750.0; NUM_HOURS],
+            hourly_cop: vec![3.0; NUM_HOURS],
+            storage_loss: 0.0,
+            blocking_windows: vec![BlockingWindow {
+                start_hour: 2,
+                duration_hours: 2,
+            }],
+            min_runtime: Some(MinRuntime {
+                min_on_hours: 3,
+                min_off_hours: 2,
+            }),
+            ..Default::default()
+        };
+
+        let unchanged = HeatPumpDispatch {
+            min_runtime: None,
+            ..heat_pump.clone()
+        }
+        .with_min_runtime(&consumption);
+        assert_eq!(unchanged.consumption, consumption);
+        assert_eq!(unchanged.starts, NUM_HOURS / 4);
+        assert_eq!(unchanged.violations, 0);
+
+        let schedule = heat_pump.with_min_runtime(&consumption);
+        assert!(schedule.starts < unchanged.starts);
+        let heat = |consumption: &[f64]| consumption.iter().sum::<f64>() * 3.0;
+        assert!((heat(&schedule.consumption) - heat(&consumption)).abs() < 1e-3);
+        for t in 0..NUM_HOURS {
+            if heat_pump.is_blocked(t) {
+                assert_eq!(schedule.consumption[t], 0.0);
+            }
+            assert!(schedule.consumption[t] <= heat_pump.max_electric_power_w + 1e-6);
+            assert!(schedule.storage[t] >= -1e-6);
+            assert!(schedule.storage[t] <= heat_pump.storage_capacity_wh + 1e-6);
+        }
+        // the run right before each blocking window can't be stretched to three hours
+        assert!(schedule.violations > 0);
+        assert!(schedule.violations <= NUM_HOURS / 24);
+    }
+
+    #[test]
+    fn test_run_simple_opt_heat_pump_blocking_and_sg_ready() {
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+        let heat_pump = HeatPumpDispatch {
+            hourly_heat_demand: vec![900.0; NUM_HOURS],
+            hourly_cop: vec![3.0; NUM_HOURS],
+            storage_capacity_wh: 6000.0,
+            blocking_windows: vec![
+                BlockingWindow {
+                    start_hour: 11,
+                    duration_hours: 2,
+                },
+                BlockingWindow {
+                    start_hour: 18,
+                    duration_hours: 2,
+                },
+            ],
+            sg_ready_boost_wh: Some(10000.0),
+            min_runtime: Some(MinRuntime {
+                min_on_hours: 3,
+                min_off_hours: 2,
+            }),
+            ..Default::default()
+        };
+        let config = OptimizationConfig {
+            bat_value: Energy::ZERO,
+            heat_pump_dispatch: Some(heat_pump.clone()),
+            ..Default::default()
+        };
+
+        let results = run_simple_opt(
+            config,
+            10000.0,
+            solar_irradiance,
+            electricity_demand,
+            ElectricityRate::fixed(0.3),
+            good_lp::clarabel,
+        )
+        .unwrap();
+
+        let max_storage_wh = heat_pump.storage_capacity_wh + 10000.0;
+        for t in 0..NUM_HOURS {
+            if heat_pump.is_blocked(t) {
+                assert!(results.hourly_heat_pump[t].abs() < 1e-3);
+            }
+            assert!(results.hourly_heat_pump[t] <= heat_pump.max_electric_power_w + 1e-3);
+            assert!(results.hourly_heat_storage[t] <= max_storage_wh + 1e-3);
+        }
+        // the boost band above the regular capacity is filled with PV surplus
+        assert!(
+            results
+                .hourly_heat_storage
+                .iter()
+                .any(|&level| level > heat_pump.storage_capacity_wh + 1.0)
+        );
+        // the buffer starts empty, the heat demand is covered at a COP of 3 plus buffer losses
+        let heat_kwh = 0.9 * NUM_HOURS as f64;
+        assert!(results.annual_heat_pump_kwh >= heat_kwh / 3.0 - 1.0);
+        assert!(results.annual_heat_pump_kwh < heat_kwh / 3.0 * 1.2);
+        let heat_pump_kwh = results.hourly_heat_pump.iter().sum::<f64>() / 1000.0;
+        assert!((heat_pump_kwh - results.annual_heat_pump_kwh).abs() < 1e-6);
+        // the post-processed schedule keeps the energy balance of every hour
+        for t in 0..NUM_HOURS {
+            let supply = results.hourly_pv_production[t]
+                + results.hourly_grid_consumption[t]
+                + results.hourly_battery_out[t];
+            let load = results.hourly_electricity_demand_base[t]
+                + results.hourly_car_charging[t]
+                + results.hourly_heat_pump[t]
+                + results.hourly_battery_in[t];
+            assert!((supply - load).abs() < 1e-2, "hour {t}: {supply} vs {load}");
+        }
+        assert!(results.heat_pump_starts > 0);
+        assert!(results.heat_pump_min_runtime_violations < results.heat_pump_starts);
+    }
+
+    #[test]
+    fn test_run_simple_opt_with_controllable_load_dimming() {
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+        let heat_pump = HeatPumpDispatch {
+            hourly_heat_demand: vec![900.0; NUM_HOURS],
+            hourly_cop: vec![3.0; NUM_HOURS],
+            storage_capacity_wh: 6000.0,
+            ..Default::default()
+        };
+        let dimming = ControllableLoadDimming {
+            min_power_w: 100.0,
+            events: vec![DimmingEvent::on(20, 17, 3)],
+            enforce: true,
+        };
+        let config = OptimizationConfig {
+            bat_value: Energy::ZERO,
+            electric_car_enabled: true,
+            heat_pump_dispatch: Some(heat_pump.clone()),
+            controllable_load_dimming: Some(dimming.clone()),
+            ..Default::default()
+        };
+
+        let results = run_simple_opt(
+            config.clone(),
+            10000.0,
+            solar_irradiance,
+            electricity_demand,
+            ElectricityRate::fixed(0.3),
+            good_lp::clarabel,
+        )
+        .unwrap();
+
+        // the buffer is preheated, so the heat demand is met at the dimmed power
+        for t in dimming.events[0].hours() {
+            assert!(results.hourly_heat_pump[t] <= 100.0 + 1e-3);
+            assert!(results.hourly_car_charging[t] <= 100.0 + 1e-3);
+        }
+        assert_eq!(results.dimming_violation_hours, 0);
+        assert_eq!(results.dimming_events.len(), 1);
+        assert!(results.dimming_events[0].unmet_heat_kwh < 1e-6);
+
+        // without planning, the grid operator cuts everything above the dimmed power
+        let heat_pump_hourly = vec![5000.0; NUM_HOURS];
+        let charging_hourly = vec![vec![11000.0; NUM_HOURS]];
+        let dimming = ControllableLoadDimming {
+            min_power_w: 4200.0,
+            ..dimming
+        };
+        let event = dimming_event_result(
+            &config,
+            &dimming,
+            &dimming.events[0],
+            &heat_pump_hourly,
+            &charging_hourly,
+        );
+        assert!((event.excess_heat_pump_kwh - 2.4).abs() < 1e-9);
+        assert!((event.excess_charging_kwh - 20.4).abs() < 1e-9);
+        assert!((event.unmet_heat_kwh - 7.2).abs() < 1e-9);
+
+        assert!(
+            ControllableLoadDimming {
+                events: vec![DimmingEvent::on(364, 23, 2)],
+                ..Default::default()
+            }
+            .validate()
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_run_simple_opt_with_legionella_cycle() {
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+        // purge on Sunday evenings, when there is no PV to heat the tank for free
+        let cycle = LegionellaCycle {
+            purge_level_wh: 8000.0,
+            weekday: 6,
+            hour: 20,
+        };
+        let heat_pump = HeatPumpDispatch {
+            hourly_heat_demand: vec![300.0; NUM_HOURS],
+            hourly_cop: vec![3.0; NUM_HOURS],
+            legionella_cycle: Some(cycle),
+            ..Default::default()
+        };
+        let config = OptimizationConfig {
+            bat_value: Energy::ZERO,
+            heat_pump_dispatch: Some(heat_pump.clone()),
+            ..Default::default()
+        };
+
+        let results = run_simple_opt(
+            config,
+            10000.0,
+            solar_irradiance,
+            electricity_demand,
+            ElectricityRate::fixed(0.3),
+            good_lp::clarabel,
+        )
+        .unwrap();
+
+        let purge_hours: Vec<usize> = (0..NUM_HOURS).filter(|&t| cycle.is_purge_hour(t)).collect();
+        assert_eq!(purge_hours.len(), 52);
+        assert_eq!(purge_hours[0], 6 * 24 + 20);
+        for t in purge_hours {
+            assert!(results.hourly_heat_storage[t] >= cycle.purge_level_wh - 1e-3);
+        }
+        assert_eq!(results.legionella_cycle_violations, 0);
+
+        // the purge level has to fit into the buffer
+        let too_high = HeatPumpDispatch {
+            legionella_cycle: Some(LegionellaCycle {
+                purge_level_wh: 12000.0,
+                ..cycle
+            }),
+            ..heat_pump
+        };
+        assert!(too_high.validate().is_err());
+    }
+
+    #[test]
+    fn test_run_simple_opt_with_building_thermal_mass() {
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+        let building = BuildingThermalModel {
+            hourly_outdoor_temp_c: vec![0.0; NUM_HOURS],
+            ..Default::default()
+        };
+        let heat_pump = HeatPumpDispatch {
+            hourly_cop: vec![3.0; NUM_HOURS],
+            max_electric_power_w: 5000.0,
+            storage_capacity_wh: 1000.0,
+            building: Some(building.clone()),
+            ..Default::default()
+        };
+        let config = OptimizationConfig {
+            bat_value: Energy::ZERO,
+            heat_pump_dispatch: Some(heat_pump),
+            ..Default::default()
+        };
+        // expensive evenings
+        let rates: Vec<f64> = (0..NUM_HOURS)
+            .map(|t| {
+                if (17..21).contains(&(t % 24)) {
+                    0.6
+                } else {
+                    0.2
+                }
+            })
+            .collect();
+
+        let results = run_simple_opt(
+            config,
+            10000.0,
+            solar_irradiance,
+            electricity_demand,
+            ElectricityRate::hourly(rates),
+            good_lp::clarabel,
+        )
+        .unwrap();
+
+        let temperatures = &results.hourly_indoor_temperature;
+        assert_eq!(temperatures.len(), NUM_HOURS);
+        assert!(temperatures.iter().all(|&temp| {
+            temp >= building.min_indoor_temp_c - 1e-3 && temp <= building.max_indoor_temp_c + 1e-3
+        }));
+        // the house is pre-heated before the evening and coasts through it
+        let coasting_days = (0..365)
+            .filter(|day| temperatures[day * 24 + 16] > temperatures[day * 24 + 20] + 0.5)
+            .count();
+        assert!(coasting_days > 300);
+        // the losses at 0 °C outdoors are covered over the year
+        let min_heat_kwh =
+            building.heat_loss_w_per_k * building.min_indoor_temp_c * NUM_HOURS as f64 / 1000.0;
+        assert!(results.annual_heat_pump_kwh * 3.0 >= min_heat_kwh - 1.0);
+
+        assert!(
+            BuildingThermalModel {
+                heat_capacity_wh_per_k: 100.0,
+                ..building
+            }
+            .validate()
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_zoned_heat_demand() {
+        let zones = vec![
+            HeatingZone::new("Living", 40.0, 21.0),
+            HeatingZone::new("Bedrooms", 60.0, 18.0),
+        ];
+        let config = OptimizationConfig {
+            heating_zones: zones.clone(),
+            ..Default::default()
+        };
+        let zoned = config.heat_demand();
+        assert_eq!(zoned.len(), NUM_HOURS);
+        // January (8 °C outdoors) at the moderate 1.8 W/m²K: 40 m² at 13 K plus 60 m² at 10 K
+        let expected_kwh = 1.8 * (40.0 * 13.0 + 60.0 * 10.0) / 1000.0;
+        assert!((zoned[0] - expected_kwh).abs() < 1e-9);
+        // in September (22 °C outdoors) only zones below the outdoor temperature stay cold
+        assert_eq!(zoned[6000], 0.0);
+
+        // cooler bedrooms need less heat than the whole house at 21 °C
+        let whole_house = OptimizationConfig {
+            monthly_temperatures: [21.0; 12],
+            ..Default::default()
+        }
+        .heat_demand();
+        assert!(zoned.iter().sum::<f64>() < whole_house.iter().sum::<f64>());
+
+        assert!(HeatingZone::new("Attic", 0.0, 18.0).validate().is_err());
+        assert!(zones.iter().all(|zone| zone.validate().is_ok()));
+    }
+
+    fn hot_water_heat_pump(binary_modes: bool) -> HeatPumpDispatch {
+        // showers in the morning and the evening
+        let hot_water = DomesticHotWater {
+            hourly_demand: (0..NUM_HOURS)
+                .map(|t| {
+                    if matches!(t % 24, 7 | 19) {
+                        3000.0
+                    } else {
+                        0.0
+                    }
+                })
+                .collect(),
+            binary_modes,
+            ..Default::default()
+        };
+        HeatPumpDispatch {
+            hourly_heat_demand: vec![900.0; NUM_HOURS],
+            hourly_cop: vec![3.5; NUM_HOURS],
+            domestic_hot_water: Some(hot_water),
+            ..Default::default()
+        }
+    }
 
-    // Calculate autarky: percentage of demand met by own production (direct + from battery)
-    let autarky = (total_direct_consumption_sum + total_battery_out_sum) / total_demand;
+    #[test]
+    fn test_run_simple_opt_with_domestic_hot_water() {
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+        let heat_pump = hot_water_heat_pump(false);
+        let hot_water = heat_pump.domestic_hot_water.clone().unwrap();
+        let config = OptimizationConfig {
+            bat_value: Energy::ZERO,
+            heat_pump_dispatch: Some(heat_pump.clone()),
+            ..Default::default()
+        };
 
-    // Calculate what overproduction would be without battery:
-    // This is the production that exceeds demand at each hour, without battery storage
-    let total_overproduction = total_overproduction_without_battery - total_battery_in_sum;
+        let results = run_simple_opt(
+            config,
+            10000.0,
+            solar_irradiance,
+            electricity_demand,
+            ElectricityRate::fixed(0.3),
+            good_lp::clarabel,
+        )
+        .unwrap();
 
-    Ok(StaticSimulationResults {
-        autarky,
-        total_production: total_production_sum,
-        total_direct_consumption: total_direct_consumption_sum,
-        total_battery_out: total_battery_out_sum,
-        total_battery_in: total_battery_in_sum,
-        total_overproduction,
-        total_overproduction_without_battery,
-    })
-}
+        assert_eq!(results.hourly_heat_pump_hot_water.len(), NUM_HOURS);
+        for t in 0..NUM_HOURS {
+            // both modes share the compressor
+            assert!(results.hourly_heat_pump[t] <= heat_pump.max_electric_power_w + 1e-3);
+            assert!(results.hourly_heat_pump_hot_water[t] <= results.hourly_heat_pump[t] + 1e-6);
+            assert!(results.hourly_hot_water_storage[t] >= hot_water.priority_level_wh - 1e-3);
+        }
+        // hot water at a COP of 2.5 on top of space heating at 3.5
+        let hot_water_kwh = results.hourly_heat_pump_hot_water.iter().sum::<f64>() / 1000.0;
+        assert!(hot_water_kwh >= 6.0 * 365.0 / 2.5 - 1.0);
+        assert!(results.annual_heat_pump_kwh >= hot_water_kwh + 0.9 * NUM_HOURS as f64 / 3.5 - 1.0);
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        let too_high = DomesticHotWater {
+            priority_level_wh: 9000.0,
+            ..hot_water
+        };
+        assert!(too_high.validate().is_err());
+    }
 
     #[test]
-    fn test_run_simple_opt() {
+    fn test_run_simple_opt_with_exclusive_hot_water_mode() {
         let solar_irradiance = load_solar_radiance_from_csv();
-        let electricity_demand = load_demand_from_csv();
-        let mut config = OptimizationConfig::default();
-        config.feed_in_tariff = 0.0;
-        config.fc_grid = 0.15;
-        config.electricity_usage = 8000000.0;
-        config.bat_value = 0.0;
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+        let config = OptimizationConfig {
+            bat_value: Energy::ZERO,
+            heat_pump_dispatch: Some(hot_water_heat_pump(true)),
+            ..Default::default()
+        };
 
         let results = run_simple_opt(
-            config.clone(),
-            100000.0,
+            config,
+            10000.0,
             solar_irradiance,
-            electricity_demand.1,
-            ElectricityRate::fixed(0.1),
+            electricity_demand,
+            ElectricityRate::fixed(0.3),
             good_lp::scip,
         )
         .unwrap();
-        println!(
-            "Simple optimization took: {} ms",
-            results.optimization_duration_ms
+
+        // every hour runs either hot water or space heating
+        for t in 0..NUM_HOURS {
+            let hot_water = results.hourly_heat_pump_hot_water[t];
+            let space_heating = results.hourly_heat_pump[t] - hot_water;
+            assert!(hot_water < 1e-3 || space_heating < 1e-3, "hour {t}");
+        }
+    }
+
+    #[test]
+    fn test_run_simple_opt_with_solar_thermal() {
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+        let collector = SolarThermalCollector {
+            inv_per_m2: 50.0,
+            ..Default::default()
+        };
+        let mut heat_pump = hot_water_heat_pump(false);
+        heat_pump.domestic_hot_water.as_mut().unwrap().solar_thermal = Some(collector);
+        let roof = RoofArea {
+            area_m2: 55.0,
+            m2_per_kwp: 5.0,
+        };
+        let config = OptimizationConfig {
+            bat_value: Energy::ZERO,
+            heat_pump_dispatch: Some(heat_pump),
+            roof: Some(roof),
+            ..Default::default()
+        };
+
+        let results = run_simple_opt(
+            config,
+            10000.0,
+            solar_irradiance.clone(),
+            electricity_demand,
+            ElectricityRate::fixed(0.3),
+            good_lp::clarabel,
+        )
+        .unwrap();
+
+        assert!(results.solar_thermal_area_m2 > 0.1);
+        assert!(results.solar_thermal_area_m2 <= collector.max_area_m2 + 1e-6);
+        // collectors and PV share the roof
+        assert!(
+            results.pv_capacity_kw * roof.m2_per_kwp + results.solar_thermal_area_m2
+                <= roof.area_m2 + 1e-3
         );
-        println!("Results: {:?}", results.annual_overproduction_kwh);
+        assert_eq!(results.hourly_solar_thermal_heat.len(), NUM_HOURS);
+        for (&heat, &solar) in results
+            .hourly_solar_thermal_heat
+            .iter()
+            .zip(&solar_irradiance)
+        {
+            assert!(
+                heat <= collector.yield_wh_per_m2(solar) * results.solar_thermal_area_m2 + 1e-3
+            );
+        }
+        // the collectors take over part of the hot water
+        let solar_heat_kwh = results.hourly_solar_thermal_heat.iter().sum::<f64>() / 1000.0;
+        let hot_water_kwh = results.hourly_heat_pump_hot_water.iter().sum::<f64>() / 1000.0;
+        assert!(solar_heat_kwh > 100.0);
+        assert!(hot_water_kwh < 6.0 * 365.0 / 2.5);
+
+        // no yield in the dark or once the losses outweigh the irradiance
+        assert_eq!(collector.yield_wh_per_m2(0.0), 0.0);
+        assert_eq!(collector.yield_wh_per_m2(0.1), 0.0);
         assert!(
-            results.annual_grid_energy_kwh + results.annual_pv_production_kwh
-                - results.annual_overproduction_kwh
-                - config.electricity_usage
-                < 100.0
+            SolarThermalCollector {
+                eta0: 1.2,
+                ..collector
+            }
+            .validate()
+            .is_err()
         );
-        assert_eq!(results.pv_capacity_kw, 1.8513578521489689);
-        assert_eq!(results.battery_capacity_kwh, 0.0);
+    }
+
+    #[test]
+    fn test_run_simple_opt_with_defrost_penalty() {
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+        // -4 °C in January and 20 °C in July
+        let outdoor_temp: Vec<f64> = (0..NUM_HOURS)
+            .map(|t| 8.0 - 12.0 * (2.0 * std::f64::consts::PI * t as f64 / NUM_HOURS as f64).cos())
+            .collect();
+        let defrost = DefrostPenalty {
+            hourly_outdoor_temp_c: outdoor_temp,
+            ..Default::default()
+        };
+        let heat_pump = HeatPumpDispatch {
+            hourly_heat_demand: vec![900.0; NUM_HOURS],
+            hourly_cop: vec![3.0; NUM_HOURS],
+            defrost: Some(defrost.clone()),
+            ..Default::default()
+        };
+        let config = OptimizationConfig {
+            bat_value: Energy::ZERO,
+            heat_pump_dispatch: Some(heat_pump.clone()),
+            ..Default::default()
+        };
+
+        let results = run_simple_opt(
+            config,
+            10000.0,
+            solar_irradiance,
+            electricity_demand,
+            ElectricityRate::fixed(0.3),
+            good_lp::clarabel,
+        )
+        .unwrap();
+
+        // the heat demand is covered at the penalized COP
+        let penalized_kwh: f64 = (0..NUM_HOURS).map(|t| 0.9 / heat_pump.cop(t)).sum();
+        assert!(results.annual_heat_pump_kwh >= penalized_kwh - 1.0);
+        assert!(penalized_kwh > 0.9 * NUM_HOURS as f64 / 3.0 + 50.0);
+        assert!(results.annual_defrost_penalty_kwh > 50.0);
+        assert!(results.annual_defrost_penalty_kwh < results.annual_heat_pump_kwh);
+
+        // the most frosting at the peak, the cold penalty far below it
+        assert!((defrost.cop_factor_at(2.0) - 0.85).abs() < 1e-9);
+        assert!((defrost.cop_factor_at(20.0) - 1.0).abs() < 1e-9);
+        assert!((defrost.cop_factor_at(-17.0) - 0.8).abs() < 1e-9);
+        // the backup heater keeps the COP at 1
+        let severe = DefrostPenalty {
+            cold_penalty_per_k: 0.5,
+            hourly_outdoor_temp_c: vec![-20.0; NUM_HOURS],
+            ..defrost.clone()
+        };
+        assert_eq!(severe.apply(3.0, 0), 1.0);
+        assert!(
+            DefrostPenalty {
+                max_defrost_penalty: 1.0,
+                ..defrost
+            }
+            .validate()
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_equipment_feasibility() {
+        let config = OptimizationConfig {
+            heat_pump_dispatch: Some(HeatPumpDispatch {
+                max_electric_power_w: 6000.0,
+                ..Default::default()
+            }),
+            roof: Some(RoofArea {
+                area_m2: 50.0,
+                m2_per_kwp: 5.0,
+            }),
+            ..Default::default()
+        };
+        let equipment = EquipmentFeasibility::assess(&config, 8.0, 10.0, 4.0);
+        assert!((equipment.battery_volume_l - 100.0).abs() < 1e-9);
+        assert!((equipment.battery_weight_kg - 120.0).abs() < 1e-9);
+        assert!((equipment.roof_area_used_m2 - 44.0).abs() < 1e-9);
+        // twice the power of the reference unit, 3 dB more
+        let sound_power_db = equipment.heat_pump_sound_power_db.unwrap();
+        assert!((sound_power_db - 63.0).abs() < 0.1);
+        assert!(equipment.is_feasible());
+
+        let config = OptimizationConfig {
+            site_limits: Some(SiteLimits {
+                max_sound_power_db: Some(60.0),
+                max_battery_volume_l: Some(150.0),
+                max_battery_weight_kg: Some(100.0),
+            }),
+            ..config
+        };
+        let equipment = EquipmentFeasibility::assess(&config, 10.0, 10.0, 4.0);
+        // roof, sound power and battery weight are exceeded, the volume fits
+        assert_eq!(equipment.issues.len(), 3);
+        assert!(!equipment.is_feasible());
+
+        let invalid = SiteLimits {
+            max_sound_power_db: Some(-1.0),
+            ..Default::default()
+        };
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn test_get_scaled_electricity_demand_uses_provided_profile() {
+        let monthly_demand = MonthlyDemand {
+            january: 400.0,
+            february: 350.0,
+            march: 330.0,
+            april: 300.0,
+            may: 280.0,
+            june: 260.0,
+            july: 250.0,
+            august: 260.0,
+            september: 290.0,
+            october: 320.0,
+            november: 360.0,
+            december: 400.0,
+        };
+        // a profile that doesn't exist on disk: doubled demand in the evenings
+        let base_profile: Vec<f64> = (0..NUM_HOURS)
+            .map(|t| if t % 24 >= 18 { 800.0 } else { 400.0 })
+            .collect();
+
+        let scaled =
+            get_scaled_electricity_demand(Some(monthly_demand), Energy::ZERO, base_profile.clone())
+                .unwrap();
+
+        assert_eq!(scaled.len(), NUM_HOURS);
+        let january_wh: f64 = scaled[..744].iter().sum();
+        assert!((january_wh - 400_000.0).abs() < 1e-6);
+        // the shape of the provided profile is kept
+        assert!((scaled[18] / scaled[0] - 2.0).abs() < 1e-9);
+
+        let too_short = get_scaled_electricity_demand(
+            Some(MonthlyDemand {
+                january: 1.0,
+                february: 1.0,
+                march: 1.0,
+                april: 1.0,
+                may: 1.0,
+                june: 1.0,
+                july: 1.0,
+                august: 1.0,
+                september: 1.0,
+                october: 1.0,
+                november: 1.0,
+                december: 1.0,
+            }),
+            Energy::ZERO,
+            vec![1.0; 24],
+        );
+        assert!(too_short.is_err());
+    }
+
+    #[test]
+    fn test_pv_variability() {
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let pv_variability = PvVariabilityConfig::default();
+
+        let degraded = pv_variability.apply(&solar_irradiance);
+        assert_eq!(degraded.len(), NUM_HOURS);
+        // deterministic for the same seed
+        assert_eq!(degraded, pv_variability.apply(&solar_irradiance));
+
+        // mean 1 and the configured standard deviation (within sampling noise)
+        let factors = pv_variability.daily_factors(365);
+        let mean = factors.iter().sum::<f64>() / 365.0;
+        let std_dev = (factors.iter().map(|f| (f - mean).powi(2)).sum::<f64>() / 365.0).sqrt();
+        assert!((mean - 1.0).abs() < 0.05);
+        assert!((std_dev - pv_variability.forecast_error).abs() < 0.05);
+
+        let no_error = PvVariabilityConfig {
+            forecast_error: 0.0,
+            ..Default::default()
+        };
+        assert_eq!(no_error.apply(&solar_irradiance), solar_irradiance);
     }
 
     #[test]
@@ -994,17 +4497,19 @@ mod tests {
             results.total_overproduction_without_battery,
             3131082.7258256325
         );
-        assert_eq!(results.autarky, 0.28378918601937186);
+        assert_eq!(results.autarky.fraction(), 0.28378918601937186);
     }
 
     #[test]
     fn test_run_static_simulation_with_battery() {
-        // Test static simulation with battery storage
+        // Test static simulation with a lossless battery storage
         let solar_irradiance = load_solar_radiance_from_csv();
         let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
 
         let configs = StaticSimulationConfigs {
             num_years: 2,
+            eta_in: 1.0,
+            eta_out: 1.0,
             ..Default::default()
         };
 
@@ -1031,6 +4536,274 @@ mod tests {
             results.total_overproduction_without_battery,
             15_695_301.960250005
         );
-        assert_eq!(results.autarky, 0.8581880054056307);
+        assert_eq!(results.autarky.fraction(), 0.8581880054056307);
+    }
+
+    #[test]
+    fn test_run_static_simulation_with_battery_efficiency() {
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+
+        let configs = StaticSimulationConfigs {
+            num_years: 2,
+            ..Default::default()
+        };
+
+        let results = run_static_simulation(
+            10000.0,
+            13500.0,
+            solar_irradiance,
+            electricity_demand,
+            configs,
+        )
+        .unwrap();
+
+        assert_eq!(results.total_production, 18_903_241.455000013);
+        assert_eq!(results.total_direct_consumption, 3_207_939.49475001);
+        assert_eq!(results.total_battery_out, 3_864_157.478303951);
+        assert_eq!(results.total_battery_in, 4_288_631.797200787);
+        assert_eq!(results.total_overproduction, 11_406_670.163049217);
+        assert_eq!(results.autarky.fraction(), 0.8472736044057695);
+        // charging and discharging losses (0.95 * 0.95) show up in the round trip
+        assert!(results.total_battery_out < 0.95 * 0.95 * results.total_battery_in);
+    }
+
+    #[test]
+    fn test_run_static_simulation_initial_soc() {
+        // no PV: the battery can only deliver what it starts with
+        let configs = StaticSimulationConfigs {
+            num_years: 2,
+            initial_soc: 0.5,
+            battery_loss: 0.0,
+            battery_degradation: 0.0,
+            ..Default::default()
+        };
+
+        let results = run_static_simulation(
+            0.0,
+            10000.0,
+            vec![0.0; NUM_HOURS],
+            vec![100.0; NUM_HOURS],
+            configs,
+        )
+        .unwrap();
+
+        let expected_out = 5000.0 * 0.95;
+        assert!((results.yearly_battery_out[0] - expected_out).abs() < 1e-6);
+        // the empty battery is carried over into the second year instead of being reset
+        assert!(results.yearly_battery_out[1].abs() < 1e-6);
+        assert_eq!(results.yearly_battery_in, vec![0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_run_static_simulation_grid_exchange() {
+        // 1 kW PV producing 500 Wh during 12 hours per day, constant 250 Wh demand
+        let solar_irradiance: Vec<f64> = (0..NUM_HOURS)
+            .map(|t| {
+                if (6..18).contains(&(t % 24)) {
+                    0.5
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        let configs = StaticSimulationConfigs {
+            num_years: 2,
+            pv_degradation: 0.0,
+            electricity_rate: Some(ElectricityRate::fixed(0.3)),
+            feed_in_tariff: 0.1,
+            ..Default::default()
+        };
+
+        let results = run_static_simulation(
+            1000.0,
+            0.0,
+            solar_irradiance,
+            vec![250.0; NUM_HOURS],
+            configs,
+        )
+        .unwrap();
+
+        let yearly_import = 250.0 * 12.0 * 365.0;
+        let yearly_export = 250.0 * 12.0 * 365.0;
+        assert_eq!(results.yearly_grid_import, vec![yearly_import; 2]);
+        assert_eq!(results.yearly_grid_export, vec![yearly_export; 2]);
+        assert_eq!(results.total_grid_import, 2.0 * yearly_import);
+        assert_eq!(results.total_grid_export, results.total_overproduction);
+        assert!(
+            (results.total_grid_cost.unwrap() - 2.0 * yearly_import / 1000.0 * 0.3).abs() < 1e-6
+        );
+        assert!(
+            (results.total_feed_in_revenue.unwrap() - 2.0 * yearly_export / 1000.0 * 0.1).abs()
+                < 1e-6
+        );
+        assert_eq!(results.yearly_grid_cost.unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_run_static_simulation_hourly_output() {
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+
+        let configs = StaticSimulationConfigs {
+            num_years: 3,
+            hourly_output_year: Some(1),
+            ..Default::default()
+        };
+
+        let results = run_static_simulation(
+            10000.0,
+            13500.0,
+            solar_irradiance,
+            electricity_demand,
+            configs,
+        )
+        .unwrap();
+
+        let hourly = results.hourly.unwrap();
+        assert_eq!(hourly.year, 1);
+        assert_eq!(hourly.battery_soc.len(), NUM_HOURS);
+        let production: f64 = hourly.production.iter().sum();
+        assert!((production - results.yearly_production[1]).abs() < 1e-6);
+        let grid_import: f64 = hourly.grid_import.iter().sum();
+        assert!((grid_import - results.yearly_grid_import[1]).abs() < 1e-6);
+
+        // every hour the demand is covered by PV, battery or grid
+        for t in 0..NUM_HOURS {
+            let covered =
+                hourly.direct_consumption[t] + hourly.battery_out[t] + hourly.grid_import[t];
+            assert!((covered - hourly.electricity_demand[t]).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_run_static_simulation_yearly_values() {
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+
+        let configs = StaticSimulationConfigs {
+            num_years: 3,
+            record_final_year_soc: true,
+            ..Default::default()
+        };
+        let battery_capacity = 13500.0;
+
+        let results = run_static_simulation(
+            10000.0,
+            battery_capacity,
+            solar_irradiance,
+            electricity_demand,
+            configs.clone(),
+        )
+        .unwrap();
+
+        assert_eq!(results.yearly_autarky.len(), 3);
+        assert_eq!(results.yearly_production.len(), 3);
+        let production_sum: f64 = results.yearly_production.iter().sum();
+        assert!((production_sum - results.total_production).abs() < 1e-6);
+        let battery_out_sum: f64 = results.yearly_battery_out.iter().sum();
+        assert!((battery_out_sum - results.total_battery_out).abs() < 1e-6);
+
+        // degradation reduces the production every year
+        assert!(results.yearly_production[1] < results.yearly_production[0]);
+        assert!(results.yearly_production[2] < results.yearly_production[1]);
+        let mean_autarky: f64 = results
+            .yearly_autarky
+            .iter()
+            .map(|autarky| autarky.fraction())
+            .sum::<f64>()
+            / 3.0;
+        assert!((mean_autarky - results.autarky.fraction()).abs() < 1e-9);
+
+        // SOC of the last year stays within the degraded battery capacity
+        let soc = results.final_year_battery_soc.unwrap();
+        assert_eq!(soc.len(), NUM_HOURS);
+        let degraded_capacity = battery_capacity * (1.0 - configs.battery_degradation).powi(2);
+        assert!(soc.iter().all(|&e| e <= degraded_capacity + 1e-6));
+    }
+
+    #[test]
+    fn test_compare_optimal_with_static_simulation() {
+        // 0.5 W/W irradiance during 12 hours per day, constant 500 Wh demand
+        let solar_irradiance: Vec<f64> = (0..NUM_HOURS)
+            .map(|t| {
+                if (6..18).contains(&(t % 24)) {
+                    0.5
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        let results = SimpleOptimizationResults {
+            pv_capacity_kw: 1.0,
+            existing_pv_capacity_kw: 1.0,
+            battery_capacity_kwh: 0.0,
+            autarky: Ratio::from_percent(60.0),
+            annual_pv_production_kwh: 2.0 * 0.5 * 12.0 * 365.0,
+            hourly_total_electricity_demand: vec![500.0; NUM_HOURS],
+            ..Default::default()
+        };
+        let configs = StaticSimulationConfigs {
+            num_years: 2,
+            pv_degradation: 0.0,
+            ..Default::default()
+        };
+
+        let comparison =
+            compare_optimal_with_static_simulation(&results, solar_irradiance, configs).unwrap();
+
+        assert_eq!(comparison.pv_capacity_kw, 2.0);
+        assert_eq!(comparison.num_years, 2);
+        // without a battery the demand is covered during the 12 sunny hours only
+        assert!((comparison.lifetime_autarky.percent() - 50.0).abs() < 1e-9);
+        assert!((comparison.autarky_gap.percent() - 10.0).abs() < 1e-9);
+        assert!((comparison.lifetime_avg_annual_pv_production_kwh - 4380.0).abs() < 1e-6);
+        assert!((comparison.lifetime_avg_annual_grid_energy_kwh - 2190.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_compare_optimal_with_static_simulation_requires_hourly_demand() {
+        let results = SimpleOptimizationResults::default();
+        let comparison = compare_optimal_with_static_simulation(
+            &results,
+            vec![0.0; NUM_HOURS],
+            StaticSimulationConfigs::default(),
+        );
+        assert!(comparison.is_err());
+    }
+
+    #[test]
+    fn test_compare_optimal_with_static_simulation_requires_years() {
+        let results = SimpleOptimizationResults {
+            hourly_total_electricity_demand: vec![500.0; NUM_HOURS],
+            ..Default::default()
+        };
+        let configs = StaticSimulationConfigs {
+            num_years: 0,
+            ..Default::default()
+        };
+        let comparison =
+            compare_optimal_with_static_simulation(&results, vec![0.0; NUM_HOURS], configs);
+        assert!(comparison.is_err());
+    }
+
+    #[test]
+    fn test_run_static_simulation_rejects_invalid_efficiencies() {
+        for (eta_in, eta_out) in [(0.95, 0.0), (1.2, 0.95), (f64::NAN, 0.95)] {
+            let configs = StaticSimulationConfigs {
+                num_years: 1,
+                eta_in,
+                eta_out,
+                ..Default::default()
+            };
+            let results = run_static_simulation(
+                1000.0,
+                1000.0,
+                vec![0.5; NUM_HOURS],
+                vec![500.0; NUM_HOURS],
+                configs,
+            );
+            assert!(results.is_err());
+        }
     }
 }
@@ -3,11 +3,19 @@ use std::io::{BufRead, BufReader};
 use std::path::Path;
 use std::sync::{LazyLock, Mutex};
 
+use ems_model::building::electricity::ElectricityRate;
 use ems_model::building::insulation::{
     BuildingTypeEnum, YearCategoryESEnum, YearCategoryESMapping,
 };
+use tracing::{info, warn};
 
+use crate::general::calendar::DAYS_IN_MONTH;
+use crate::general::currency::Currency;
 use crate::general::electricity_demand::MonthlyDemand;
+use crate::general::finance::{BusinessTax, ResidualValue};
+use crate::general::resampling::{Quantity, StandardDayProfile, to_hourly};
+use crate::general::units::{Energy, Ratio};
+use crate::simple::capacity_sweep::ProductSizes;
 
 #[derive(Debug, Clone)]
 pub struct StaticSimulationConfigs {
@@ -17,151 +25,2133 @@ pub struct StaticSimulationConfigs {
     pub battery_loss: f64,
     pub max_battery_charge_rate: f64,
     pub max_battery_discharge_rate: f64,
+    pub eta_in: f64, // Battery charging efficiency (same meaning as eta_in_bat in the LP)
+    pub eta_out: f64, // Battery discharging efficiency (same meaning as eta_out_bat in the LP)
+    pub initial_soc: f64, // Battery state of charge at the start as fraction of capacity (0-1)
+    pub record_final_year_soc: bool, // if true, the hourly SOC of the last year is returned
+    pub electricity_rate: Option<ElectricityRate>, // Prices grid import; None skips cost/revenue
+    pub feed_in_tariff: f64, // Feed-in tariff per kWh
+    pub hourly_output_year: Option<usize>, // Year (0-based) for which the hourly arrays are returned
+}
+
+impl Default for StaticSimulationConfigs {
+    fn default() -> Self {
+        Self {
+            pv_degradation: 0.005,
+            battery_degradation: 0.03,
+            num_years: 25,
+            battery_loss: 0.00005,
+            max_battery_charge_rate: 2000.0,
+            max_battery_discharge_rate: 2000.0,
+            eta_in: 0.95,
+            eta_out: 0.95,
+            initial_soc: 0.0,
+            record_final_year_soc: false,
+            electricity_rate: None,
+            feed_in_tariff: 0.079,
+            hourly_output_year: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StaticSimulationResults {
+    pub autarky: Ratio,
+    pub total_production: f64,
+    pub total_direct_consumption: f64,
+    pub total_battery_out: f64,
+    pub total_battery_in: f64,
+    pub total_overproduction: f64,
+    pub total_overproduction_without_battery: f64,
+
+    // Per-year values (index 0 is the first year)
+    pub yearly_autarky: Vec<Ratio>,
+    pub yearly_production: Vec<f64>,
+    pub yearly_direct_consumption: Vec<f64>,
+    pub yearly_battery_in: Vec<f64>,
+    pub yearly_battery_out: Vec<f64>,
+
+    // Hourly battery SOC of the last simulated year (only if requested)
+    pub final_year_battery_soc: Option<Vec<f64>>,
+
+    // Grid exchange in Wh
+    pub total_grid_import: f64,
+    pub total_grid_export: f64,
+    pub yearly_grid_import: Vec<f64>,
+    pub yearly_grid_export: Vec<f64>,
+
+    // Grid cost and feed-in revenue (only if an electricity rate is configured)
+    pub total_grid_cost: Option<f64>,
+    pub total_feed_in_revenue: Option<f64>,
+    pub yearly_grid_cost: Option<Vec<f64>>,
+    pub yearly_feed_in_revenue: Option<Vec<f64>>,
+
+    // Hourly arrays of the selected year (only if requested)
+    pub hourly: Option<StaticSimulationHourly>,
+}
+
+/// Hourly energy flows of one simulated year in Wh, compatible with `plot_hourly_averages`
+#[derive(Debug, Clone)]
+pub struct StaticSimulationHourly {
+    pub year: usize,
+    pub electricity_demand: Vec<f64>,
+    pub production: Vec<f64>,
+    pub direct_consumption: Vec<f64>,
+    pub battery_in: Vec<f64>,
+    pub battery_out: Vec<f64>,
+    pub battery_soc: Vec<f64>,
+    pub grid_import: Vec<f64>,
+    pub grid_export: Vec<f64>,
+}
+
+/// Year-1 KPIs of the LP optimum next to the lifetime KPIs of the static simulation
+/// for the same capacities
+#[derive(Debug, Clone)]
+pub struct OptimalVsStaticComparison {
+    // Capacities used for both (incl. existing system)
+    pub pv_capacity_kw: f64,
+    pub battery_capacity_kwh: f64,
+    pub num_years: usize,
+
+    // Year 1, perfect foresight (LP)
+    pub optimal_autarky: Ratio,
+    pub optimal_annual_pv_production_kwh: f64,
+    pub optimal_annual_grid_energy_kwh: f64,
+
+    // Lifetime, rule-based dispatch with degradation (static simulation)
+    pub lifetime_autarky: Ratio,
+    pub lifetime_total_pv_production_kwh: f64,
+    pub lifetime_avg_annual_pv_production_kwh: f64,
+    pub lifetime_avg_annual_grid_energy_kwh: f64,
+
+    pub autarky_gap: Ratio, // optimal - lifetime autarky
+    pub static_results: StaticSimulationResults,
+}
+
+/// Configuration struct holding all optimization parameters
+#[derive(Debug, Clone)]
+pub struct OptimizationConfig {
+    // Investment costs
+    pub inv_pv: f64,        // Investment cost for PV per kW
+    pub inv_bat: f64,       // Investment cost for battery per kWh
+    pub inv_hwat: f64,      // Investment cost for hot water storage per kWh
+    pub inv_grid: f64,      // Investment cost for grid connection per kW
+    pub inv_heat_pump: f64, // Investment cost for heat pump per kW
+
+    // Economic parameters
+    pub annuity: f64,                                     // Annuity factor
+    pub fc_grid: f64,                                     // Fuel cost for grid electricity per kWh
+    pub feed_in_tariff: f64,                              // Feed-in tariff per kWh
+    pub capacity_tariff: Option<CapacityTariff>, // Network fee per kW of the grid import peak; None bills energy only
+    pub demand_charges: Option<DemandCharges>, // Industrial monthly maximum demand charges and contracted power; None for none
+    pub feed_in_prices: Option<Vec<f64>>, // Hourly feed-in price per kWh (dynamic export tariff, may be negative); None pays feed_in_tariff in every hour
+    pub curtail_unpaid_feed_in: bool, // Curtail instead of exporting in hours with a feed-in price <= 0
+    pub feed_in_limit_share: Option<f64>, // Export limit as share of the installed PV peak (e.g. 0.6 under the German Solarspitzengesetz); None for no limit
+    pub monthly_feed_in_credit_cap: bool, // Credit the feed-in of a month only up to its import cost (Spanish simplified compensation)
+    pub demand_response_events: Vec<DemandResponseEvent>, // Critical peak prices and mandated load reductions; empty for none
+
+    // System parameters
+    pub hwat_enabled: bool,                    // Flag for hot water system
+    pub storage_loss_bat: f64,                 // Battery hourly loss rate
+    pub storage_loss_hwat: f64,                // Hot water storage hourly loss rate
+    pub eta_in_bat: f64,                       // Battery charging efficiency
+    pub eta_out_bat: f64,                      // Battery discharging efficiency
+    pub eta_in_hwat: f64,                      // Hot water storage efficiency
+    pub eta_out_hwat: f64,                     // Hot water discharge efficiency
+    pub c_rate_limit: f64, // C-rate limit for battery (fraction of capacity per hour)
+    pub electricity_usage: Energy, // Annual electricity usage (normalizes timeseries to this total)
+    pub monthly_demand: Option<MonthlyDemand>, // Monthly demand in kWh
+
+    // Electric car parameters
+    pub electric_car_enabled: bool,     // Flag for electric car
+    pub car_daily_km: f64,              // Daily kilometers driven
+    pub car_efficiency_kwh_per_km: f64, // Car efficiency in kWh per km
+    pub car_battery_size_kwh: f64,      // Car battery size in kWh
+    pub car_charge_during_day: bool,    // Whether car charges during day (true) or night (false)
+    pub public_charging: Option<PublicCharging>, // Charging away from home; None charges everything at home
+    pub car_charger: Option<Charger>, // Wallbox limits; None charges without power limits
+    pub car_departure_soc: Option<f64>, // State of charge (0-1) required when leaving each day; None only requires the annual energy
+    pub electric_vehicles: Vec<ElectricVehicle>, // Further cars, each with its own charging variables
+
+    // Heat pump parameters
+    pub heat_pump_enabled: bool,           // Flag for heat pump system
+    pub house_square_meters: f64,          // House size in square meters
+    pub insulation_level: InsulationLevel, // Insulation quality
+    pub heating_type: HeatingType,         // Floor or radiator heating
+    pub monthly_temperatures: [f64; 12],   // Desired temperature for each month (°C)
+    pub heating_zones: Vec<HeatingZone>, // Zones with their own setpoints; replace house size and monthly temperatures if not empty
+    pub heat_pump_dispatch: Option<HeatPumpDispatch>, // Heat pump and buffer dispatched by the optimizer; None leaves heating out of the model
+
+    // Building configuration parameters
+    pub building_type: BuildingTypeEnum, // Building type (SingleFamily, Terraced, MultiFamily, Apartment)
+    pub construction_period: YearCategoryESEnum, // Construction period (Before1900, Between1901and1936, etc.)
+    pub insulation_standard: InsulationLevel,    // Insulation standard (Poor, Moderate, Good)
+
+    // Optimization loop parameters
+    pub bat_value: Energy, // Maximum battery capacity, built exactly with bat_fixed
+    pub pv_capacity_min: f64, // Minimum PV capacity to test
+    pub pv_capacity_max: f64, // Maximum PV capacity to test
+    pub pv_capacity_step: f64, // Step size for PV capacity testing
+    pub bat_capacity_min: f64, // Minimum battery capacity to test in kWh
+    pub bat_capacity_max: f64, // Maximum battery capacity to test in kWh
+    pub bat_capacity_step: f64, // Step size for battery capacity testing in kWh
+    pub pv_fixed: bool,    // if true, pv capacity cannot be changed by optimization
+    pub bat_fixed: bool,   // if true, battery capacity cannot be changed by optimization
+    pub discrete_sizing: Option<ProductSizes>, // Size new PV and battery in whole modules (MILP, needs SCIP); None sizes them continuously
+    pub electricity_price_increase: f64,       // Nominal electricity price increase per year
+    pub inflation_rate: f64, // General inflation per year for the real (inflation-adjusted) returns
+    pub residual_value: ResidualValue, // Value of PV and battery at the end of the ROI period
+    pub business_tax: Option<BusinessTax>, // Corporate tax and depreciation of commercial operators; None for households
+
+    // Optimization mode
+    pub optimize_for_autonomy: bool, // if true, optimize for maximum autonomy instead of minimum cost
+
+    // Electrical connection
+    pub phase_config: Option<PhaseConfig>, // Per-phase limits; None models a single balanced connection
+    pub grid_connection: Option<GridConnection>, // Main fuse of the house connection; None skips the fuse check
+    pub controllable_load_dimming: Option<ControllableLoadDimming>, // Dimming of heat pump and wallboxes by the grid operator (§14a EnWG); None for no dimming
+
+    // Installation limits
+    pub roof: Option<RoofArea>, // Usable roof area limiting the PV capacity; None leaves the limit to pv_cap_w_max
+    pub investment_budget: Option<f64>, // Maximum investment in new PV, battery, wind and the dispatched heat pump; None for no budget
+    pub site_limits: Option<SiteLimits>, // Sound and space limits the recommendation is checked against; None only reports the estimates
+
+    // Additional generation
+    pub wind_turbine: Option<WindTurbineConfig>, // Small wind turbine; None disables wind generation
+
+    // Already installed system (brownfield expansion)
+    pub existing_system: Option<ExistingSystem>, // None assumes a greenfield build
+
+    // Robust sizing
+    pub pv_variability: Option<PvVariabilityConfig>, // Day-to-day PV noise applied when sizing; None uses the series as is
+
+    // Currency of all costs in this config and of the money outputs
+    pub currency: Currency,
+}
+
+impl Default for OptimizationConfig {
+    fn default() -> Self {
+        Self {
+            inv_pv: 465.0,
+            inv_bat: 200.0,
+            inv_hwat: 60.0,
+            inv_grid: 0.0,
+            inv_heat_pump: 0.0,
+
+            // Economic parameters
+            annuity: 0.1,
+            fc_grid: 0.30,
+            feed_in_tariff: 0.079,
+            capacity_tariff: None,
+            demand_charges: None,
+            feed_in_prices: None,
+            curtail_unpaid_feed_in: false,
+            feed_in_limit_share: None,
+            monthly_feed_in_credit_cap: false,
+            demand_response_events: Vec::new(),
+
+            // System parameters
+            hwat_enabled: true,
+            storage_loss_bat: 0.001,
+            storage_loss_hwat: 0.01,
+            eta_in_bat: 0.95,
+            eta_out_bat: 0.95,
+            eta_in_hwat: 0.90,
+            eta_out_hwat: 0.90,
+            c_rate_limit: 0.3,
+            electricity_usage: Energy::from_kwh(4173.44),
+            monthly_demand: None,
+
+            // Electric car parameters
+            electric_car_enabled: false,
+            car_daily_km: 50.0,             // 50 km per day default
+            car_efficiency_kwh_per_km: 0.2, // 0.2 kWh per km default
+            car_battery_size_kwh: 50.0,     // 50 kWh battery default
+            car_charge_during_day: true,    // Default to daytime charging
+            public_charging: None,
+            car_charger: None,
+            car_departure_soc: None,
+            electric_vehicles: Vec::new(),
+
+            // Heat pump parameters
+            heat_pump_enabled: false,
+            house_square_meters: 100.0,
+            insulation_level: InsulationLevel::Moderate,
+            heating_type: HeatingType::Floor,
+            monthly_temperatures: [20.0; 12],
+            heating_zones: Vec::new(),
+            heat_pump_dispatch: None,
+
+            // Building configuration parameters
+            building_type: BuildingTypeEnum::SingleFamily,
+            construction_period: YearCategoryESEnum::Before1900,
+            insulation_standard: InsulationLevel::Moderate,
+
+            // Optimization loop parameters
+            bat_value: Energy::from_kwh(20.0),
+            pv_capacity_min: 0.0,
+            pv_capacity_max: 2.0,
+            pv_capacity_step: 0.5,
+            bat_capacity_min: 0.0,
+            bat_capacity_max: 20.0,
+            bat_capacity_step: 5.0,
+            pv_fixed: false,
+            bat_fixed: false,
+            discrete_sizing: None,
+            electricity_price_increase: 0.0,
+            inflation_rate: 0.0,
+            residual_value: ResidualValue::Zero,
+            business_tax: None,
+
+            // Optimization mode
+            optimize_for_autonomy: false,
+
+            // Electrical connection
+            phase_config: None,
+            grid_connection: None,
+            controllable_load_dimming: None,
+
+            // Installation limits
+            roof: None,
+            investment_budget: None,
+            site_limits: None,
+
+            // Additional generation
+            wind_turbine: None,
+
+            // Already installed system (brownfield expansion)
+            existing_system: None,
+
+            // Robust sizing
+            pv_variability: None,
+
+            // Currency
+            currency: Currency::default(),
+        }
+    }
+}
+
+impl OptimizationConfig {
+    /// Feed-in price per kWh of every hour of the year
+    pub fn hourly_feed_in_prices(&self) -> Vec<f64> {
+        match &self.feed_in_prices {
+            Some(prices) => prices.clone(),
+            None => vec![self.feed_in_tariff; 8760],
+        }
+    }
+
+    /// Hourly heat demand in kWh of the heating zones, or of the whole house at the monthly
+    /// temperatures without zones
+    pub fn heat_demand(&self) -> Vec<f64> {
+        if self.heating_zones.is_empty() {
+            calculate_heat_demand(
+                self.house_square_meters,
+                &self.insulation_level,
+                &self.monthly_temperatures,
+            )
+        } else {
+            calculate_zoned_heat_demand(&self.heating_zones, &self.insulation_level)
+        }
+    }
+
+    /// Capacity of the already installed PV array in W
+    pub fn existing_pv_w(&self) -> f64 {
+        self.existing_system
+            .as_ref()
+            .map(|existing| existing.pv_capacity_w)
+            .unwrap_or(0.0)
+    }
+
+    /// Capacity of the already installed battery in Wh
+    pub fn existing_battery_wh(&self) -> f64 {
+        self.existing_system
+            .as_ref()
+            .map(|existing| existing.battery_capacity_wh)
+            .unwrap_or(0.0)
+    }
+
+    /// All cars charged at home: the car described by the `car_*` fields (if enabled) followed
+    /// by `electric_vehicles`
+    pub fn vehicles(&self) -> Vec<ElectricVehicle> {
+        let mut vehicles = Vec::with_capacity(self.electric_vehicles.len() + 1);
+        if self.electric_car_enabled {
+            vehicles.push(ElectricVehicle {
+                daily_km: self.car_daily_km,
+                efficiency_kwh_per_km: self.car_efficiency_kwh_per_km,
+                battery_size_kwh: self.car_battery_size_kwh,
+                availability: if self.car_charge_during_day {
+                    ChargingWindow::DAY
+                } else {
+                    ChargingWindow::NIGHT
+                },
+                public_charging: self.public_charging.clone(),
+                charger: self.car_charger,
+                departure_soc: self.car_departure_soc,
+            });
+        }
+        vehicles.extend(self.electric_vehicles.iter().cloned());
+        vehicles
+    }
+
+    /// Grid import limit in W of hour `t`, the lowest of all load reduction events covering it
+    pub fn grid_import_limit_w(&self, t: usize) -> Option<f64> {
+        self.demand_response_events
+            .iter()
+            .filter(|event| event.hours().contains(&t))
+            .filter_map(|event| event.max_grid_import_w)
+            .reduce(f64::min)
+    }
+
+    /// Replaces the import prices of the critical peak events in an hourly rate series
+    pub fn apply_critical_peak_prices(&self, electricity_rate_hourly: &mut [f64]) {
+        for event in &self.demand_response_events {
+            if let Some(price) = event.price_per_kwh {
+                for rate in electricity_rate_hourly
+                    .iter_mut()
+                    .take(event.hours().end)
+                    .skip(event.start_hour)
+                {
+                    *rate = price;
+                }
+            }
+        }
+    }
+
+    /// Whether the model contains a battery, either a new or an already installed one
+    pub fn has_battery(&self) -> bool {
+        self.bat_value > Energy::ZERO || self.existing_battery_wh() > 0.0
+    }
+
+    /// Upper limit of the new PV capacity in W: `pv_cap_w_max`, reduced to the roof area left
+    /// next to an already installed array
+    pub fn pv_limit_w(&self, pv_cap_w_max: f64) -> f64 {
+        match &self.roof {
+            Some(roof) => pv_cap_w_max.min((roof.max_pv_w() - self.existing_pv_w()).max(0.0)),
+            None => pv_cap_w_max,
+        }
+    }
+
+    /// Solar thermal collectors of the hot water tank, if any
+    pub fn solar_thermal(&self) -> Option<&SolarThermalCollector> {
+        self.heat_pump_dispatch
+            .as_ref()?
+            .domestic_hot_water
+            .as_ref()?
+            .solar_thermal
+            .as_ref()
+    }
+
+    /// Investment in the dispatched heat pump, which is built in any case
+    pub fn heat_pump_investment(&self) -> f64 {
+        self.heat_pump_dispatch
+            .as_ref()
+            .map(|heat_pump| heat_pump.max_electric_power_w / 1000.0 * self.inv_heat_pump)
+            .unwrap_or(0.0)
+    }
+}
+
+/// Roof area available for PV
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RoofArea {
+    pub area_m2: f64,    // Usable area, without edges, windows and shaded parts
+    pub m2_per_kwp: f64, // Module area per kWp (about 5 to 7 m² for current modules)
+}
+
+impl RoofArea {
+    pub fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if !(self.area_m2 >= 0.0 && self.m2_per_kwp > 0.0) {
+            return Err(format!(
+                "Roof area must not be negative and the area per kWp must be positive, got {} m² and {} m²/kWp",
+                self.area_m2, self.m2_per_kwp
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// PV capacity fitting on the roof in W
+    pub fn max_pv_w(&self) -> f64 {
+        self.area_m2 / self.m2_per_kwp * 1000.0
+    }
+}
+
+/// Site limits the recommended equipment is checked against after the optimization
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SiteLimits {
+    pub max_sound_power_db: Option<f64>, // Sound power limit of the heat pump outdoor unit in dB(A)
+    pub max_battery_volume_l: Option<f64>, // Space available for the new battery
+    pub max_battery_weight_kg: Option<f64>, // Load the wall or floor can carry
+}
+
+impl SiteLimits {
+    pub fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        for limit in [
+            self.max_sound_power_db,
+            self.max_battery_volume_l,
+            self.max_battery_weight_kg,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if !(limit.is_finite() && limit > 0.0) {
+                return Err(format!("Site limits must be positive, got {:?}", self).into());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Practical feasibility of the recommended capacities, from typical equipment sizes
+///
+/// The estimates are rough: the battery assumes wall-mounted lithium iron phosphate units, the
+/// heat pump its datasheet sound power or a typical unit of its size.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct EquipmentFeasibility {
+    pub battery_volume_l: f64,                 // New battery
+    pub battery_weight_kg: f64,                // New battery
+    pub heat_pump_sound_power_db: Option<f64>, // Outdoor unit (only set with a dispatched heat pump)
+    pub roof_area_used_m2: f64,                // New and existing PV plus solar thermal collectors
+    pub roof_area_m2: Option<f64>,             // Usable roof area (only set with a roof)
+    pub issues: Vec<String>,                   // Site limits the recommendation exceeds
+}
+
+impl EquipmentFeasibility {
+    pub const BATTERY_LITERS_PER_KWH: f64 = 10.0;
+    pub const BATTERY_KG_PER_KWH: f64 = 12.0;
+    // Module area per kWp without a roof config
+    pub const M2_PER_KWP: f64 = 5.5;
+
+    /// Annotates the recommended capacities with their space and sound estimates and checks
+    /// them against the roof and the site limits of the config
+    pub fn assess(
+        config: &OptimizationConfig,
+        pv_capacity_kw: f64,
+        battery_capacity_kwh: f64,
+        solar_thermal_area_m2: f64,
+    ) -> Self {
+        let m2_per_kwp = config.roof.map_or(Self::M2_PER_KWP, |roof| roof.m2_per_kwp);
+        let pv_kw = pv_capacity_kw + config.existing_pv_w() / 1000.0;
+        let mut feasibility = Self {
+            battery_volume_l: battery_capacity_kwh * Self::BATTERY_LITERS_PER_KWH,
+            battery_weight_kg: battery_capacity_kwh * Self::BATTERY_KG_PER_KWH,
+            heat_pump_sound_power_db: config
+                .heat_pump_dispatch
+                .as_ref()
+                .map(HeatPumpDispatch::outdoor_sound_power_db),
+            roof_area_used_m2: pv_kw * m2_per_kwp + solar_thermal_area_m2,
+            roof_area_m2: config.roof.map(|roof| roof.area_m2),
+            issues: Vec::new(),
+        };
+
+        if let Some(area_m2) = feasibility.roof_area_m2
+            && feasibility.roof_area_used_m2 > area_m2 + 1e-3
+        {
+            feasibility.issues.push(format!(
+                "Equipment needs {:.1} m² of roof, only {:.1} m² are usable",
+                feasibility.roof_area_used_m2, area_m2
+            ));
+        }
+        let limits = config.site_limits.unwrap_or_default();
+        if let (Some(sound_power_db), Some(limit)) = (
+            feasibility.heat_pump_sound_power_db,
+            limits.max_sound_power_db,
+        ) && sound_power_db > limit
+        {
+            feasibility.issues.push(format!(
+                "Heat pump outdoor unit has a sound power of {:.1} dB(A), the limit is {:.1} dB(A)",
+                sound_power_db, limit
+            ));
+        }
+        if let Some(limit) = limits.max_battery_volume_l
+            && feasibility.battery_volume_l > limit
+        {
+            feasibility.issues.push(format!(
+                "Battery needs about {:.0} l of space, {:.0} l are available",
+                feasibility.battery_volume_l, limit
+            ));
+        }
+        if let Some(limit) = limits.max_battery_weight_kg
+            && feasibility.battery_weight_kg > limit
+        {
+            feasibility.issues.push(format!(
+                "Battery weighs about {:.0} kg, the site carries {:.0} kg",
+                feasibility.battery_weight_kg, limit
+            ));
+        }
+        feasibility
+    }
+
+    /// Whether the recommendation fits all site limits
+    pub fn is_feasible(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Forecast error / cloud variability applied to the PV series before sizing.
+///
+/// Every day of the series is multiplied with a random factor with mean 1 and standard
+/// deviation `forecast_error` (truncated at 0), so a battery is not tuned to the exact
+/// sequence of sunny and cloudy days of one historical year. The noise is deterministic
+/// for a given `seed`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PvVariabilityConfig {
+    pub forecast_error: f64, // Standard deviation of the daily PV factor (e.g. 0.2 for 20%)
+    pub seed: u64,           // Seed of the pseudo random generator
+}
+
+impl Default for PvVariabilityConfig {
+    fn default() -> Self {
+        Self {
+            forecast_error: 0.2,
+            seed: 42,
+        }
+    }
+}
+
+impl PvVariabilityConfig {
+    /// Daily factors for the given number of days
+    pub fn daily_factors(&self, num_days: usize) -> Vec<f64> {
+        let mut state = self.seed;
+        (0..num_days)
+            .map(|_| {
+                // splitmix64, mapped to a uniform distribution with unit variance
+                state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+                let mut z = state;
+                z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+                z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+                z ^= z >> 31;
+                let uniform = (z >> 11) as f64 / (1u64 << 53) as f64;
+                let noise = (uniform * 2.0 - 1.0) * 3.0_f64.sqrt();
+                (1.0 + self.forecast_error * noise).max(0.0)
+            })
+            .collect()
+    }
+
+    /// Applies the daily factors to an hourly PV series
+    pub fn apply(&self, solar_irradiance: &[f64]) -> Vec<f64> {
+        let factors = self.daily_factors(solar_irradiance.len().div_ceil(24));
+        solar_irradiance
+            .iter()
+            .enumerate()
+            .map(|(t, &value)| value * factors[t / 24])
+            .collect()
+    }
+}
+
+/// Already installed PV array and battery. They have no investment cost and a fixed
+/// capacity; `pv_capacity_w_max`/`bat_value` then describe the capacity that may be added.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ExistingSystem {
+    pub pv_capacity_w: f64,          // Installed PV capacity in W
+    pub battery_capacity_wh: f64,    // Installed battery capacity in Wh
+    pub feed_in_tariff: Option<f64>, // Feed-in tariff of the existing contract per kWh; None uses the current tariff
+}
+
+/// Small wind turbine as additional generation asset
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindTurbineConfig {
+    pub hourly_wind_speed: Vec<f64>, // Wind speed at hub height in m/s for 8760 hours
+    pub power_curve: Vec<(f64, f64)>, // (wind speed in m/s, output as fraction of rated power), sorted by wind speed
+    pub inv_wind: f64,                // Investment cost per kW rated power
+    pub cap_wind_w_max: f64,          // Maximum rated power in W
+    pub wind_fixed: bool,             // if true, wind capacity cannot be changed by optimization
+}
+
+impl Default for WindTurbineConfig {
+    fn default() -> Self {
+        Self {
+            hourly_wind_speed: vec![0.0; 8760],
+            // Generic small turbine: cut-in 3 m/s, rated at 12 m/s, cut-out 25 m/s
+            power_curve: vec![
+                (0.0, 0.0),
+                (3.0, 0.0),
+                (5.0, 0.1),
+                (7.0, 0.3),
+                (9.0, 0.58),
+                (11.0, 0.87),
+                (12.0, 1.0),
+                (25.0, 1.0),
+                (25.01, 0.0),
+            ],
+            inv_wind: 3000.0,
+            cap_wind_w_max: 5000.0,
+            wind_fixed: false,
+        }
+    }
+}
+
+impl WindTurbineConfig {
+    /// Output of the turbine as fraction of the rated power at the given wind speed,
+    /// linearly interpolated between the points of the power curve
+    pub fn power_fraction(&self, wind_speed: f64) -> f64 {
+        let (Some(first), Some(last)) = (self.power_curve.first(), self.power_curve.last()) else {
+            return 0.0;
+        };
+        if wind_speed <= first.0 {
+            return first.1;
+        }
+        if wind_speed >= last.0 {
+            return last.1;
+        }
+
+        for window in self.power_curve.windows(2) {
+            let ((v0, p0), (v1, p1)) = (window[0], window[1]);
+            if wind_speed <= v1 {
+                if v1 <= v0 {
+                    return p1;
+                }
+                return p0 + (p1 - p0) * (wind_speed - v0) / (v1 - v0);
+            }
+        }
+        last.1
+    }
+
+    /// Hourly capacity factors (output per W of rated power) for the configured wind speeds
+    pub fn capacity_factors(&self) -> Vec<f64> {
+        self.hourly_wind_speed
+            .iter()
+            .map(|&wind_speed| self.power_fraction(wind_speed))
+            .collect()
+    }
+}
+
+/// Hours of the day in which a car is plugged in at home
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChargingWindow {
+    pub start_hour: usize, // Hour of day (0-23) the car arrives
+    pub end_hour: usize, // Hour of day the car leaves, before `start_hour` for windows crossing midnight
+}
+
+impl ChargingWindow {
+    /// Simplified daytime charging (6-18), e.g. at home during the day
+    pub const DAY: Self = Self {
+        start_hour: 6,
+        end_hour: 18,
+    };
+    /// Simplified overnight charging (18-6)
+    pub const NIGHT: Self = Self {
+        start_hour: 18,
+        end_hour: 6,
+    };
+
+    /// Whether the car is plugged in in the given hour of the day, equal start and end mean
+    /// always plugged in
+    pub fn contains(&self, hour_of_day: usize) -> bool {
+        if self.start_hour < self.end_hour {
+            (self.start_hour..self.end_hour).contains(&hour_of_day)
+        } else {
+            hour_of_day >= self.start_hour || hour_of_day < self.end_hour
+        }
+    }
+}
+
+/// Electric car charged at home
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElectricVehicle {
+    pub daily_km: f64,                           // Daily kilometers driven
+    pub efficiency_kwh_per_km: f64,              // Car efficiency in kWh per km
+    pub battery_size_kwh: f64,                   // Car battery size in kWh
+    pub availability: ChargingWindow,            // Hours the car is plugged in at home
+    pub public_charging: Option<PublicCharging>, // Charging away from home; None charges everything at home
+    pub charger: Option<Charger>, // Wallbox limits; None charges without power limits
+    pub departure_soc: Option<f64>, // State of charge (0-1) required when leaving each day; None only requires the annual energy
+}
+
+impl Default for ElectricVehicle {
+    fn default() -> Self {
+        Self {
+            daily_km: 50.0,
+            efficiency_kwh_per_km: 0.2,
+            battery_size_kwh: 50.0,
+            availability: ChargingWindow::DAY,
+            public_charging: None,
+            charger: None,
+            departure_soc: None,
+        }
+    }
+}
+
+impl ElectricVehicle {
+    // Charging below this counts as idle, the LP leaves tiny values in idle hours
+    const ON_THRESHOLD_WH: f64 = 1.0;
+
+    pub fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(public_charging) = &self.public_charging {
+            public_charging.validate()?;
+        }
+        if let Some(charger) = &self.charger {
+            charger.validate()?;
+        }
+        if let Some(departure_soc) = self.departure_soc
+            && !(0.0..=1.0).contains(&departure_soc)
+        {
+            return Err(format!("Departure SOC must be 0-1, got {}", departure_soc).into());
+        }
+        Ok(())
+    }
+
+    /// Driving energy per day in Wh, limited to one full battery
+    pub fn daily_energy_wh(&self) -> f64 {
+        (self.daily_km * self.efficiency_kwh_per_km * 1000.0).min(self.battery_size_kwh * 1000.0)
+    }
+
+    /// Number of days worth of driving energy that is charged at home in a year
+    pub fn home_charging_days(&self) -> f64 {
+        match &self.public_charging {
+            Some(public_charging) => (0..365).map(|day| public_charging.home_share(day)).sum(),
+            None => 365.0,
+        }
+    }
+
+    /// Whether the car is plugged in at home in the given hour of the year
+    pub fn can_charge(&self, hour: usize) -> bool {
+        let is_away = self
+            .public_charging
+            .as_ref()
+            .is_some_and(|public_charging| public_charging.is_away(hour / 24));
+        self.availability.contains(hour % 24) && !is_away
+    }
+
+    /// Driving energy in Wh that leaves the battery in the given hour of the year. The car
+    /// leaves at the end of its charging window and uses the part of the daily energy that is
+    /// charged at home.
+    pub fn departure_energy_wh(&self, hour: usize) -> f64 {
+        if hour % 24 != self.availability.end_hour {
+            return 0.0;
+        }
+        let home_share = self
+            .public_charging
+            .as_ref()
+            .map(|public_charging| public_charging.home_share(hour / 24))
+            .unwrap_or(1.0);
+        self.daily_energy_wh() * home_share
+    }
+
+    /// State of charge in Wh for a charging schedule, starting from `initial_wh` before the
+    /// first hour
+    pub fn soc_levels(&self, charging: &[f64], initial_wh: f64) -> Vec<f64> {
+        let mut level = initial_wh;
+        charging
+            .iter()
+            .enumerate()
+            .map(|(t, &charging)| {
+                level += charging - self.departure_energy_wh(t);
+                level
+            })
+            .collect()
+    }
+
+    /// Turns a charging schedule into one the wallbox can follow: within every plug-in session,
+    /// charging below the minimum power of the charger is moved into fewer hours.
+    ///
+    /// This is a heuristic on top of the LP: a session keeps its energy and the hours with the
+    /// most charging in the LP. Sessions with less than one hour at minimum power hand their
+    /// energy to the next session, only in the last one it is counted in `violations`. With a
+    /// departure SOC the energy stays in its session so every departure is still met. The moved
+    /// energy may end up in hours that draw from the grid.
+    pub fn with_min_charging_power(&self, charging: &[f64]) -> VehicleChargingSchedule {
+        let mut charging = charging.to_vec();
+        let Some(charger) = self.charger else {
+            return VehicleChargingSchedule {
+                charging,
+                violations: 0,
+            };
+        };
+        let (min_power, max_power) = (charger.min_power_w(), charger.max_power_w());
+        let is_too_low = |energy: f64| energy > Self::ON_THRESHOLD_WH && energy < min_power - 1.0;
+
+        let sessions = self.sessions(charging.len());
+        let mut carried = 0.0;
+        for (index, &(start, end)) in sessions.iter().enumerate() {
+            let energy = carried + charging[start..end].iter().sum::<f64>();
+            if carried <= 0.0
+                && !charging[start..end]
+                    .iter()
+                    .any(|&energy| is_too_low(energy))
+            {
+                continue;
+            }
+            if energy < min_power - 1.0
+                && index + 1 < sessions.len()
+                && self.departure_soc.is_none()
+            {
+                carried = energy;
+                charging[start..end].fill(0.0);
+                continue;
+            }
+            carried = 0.0;
+
+            let active = (start..end)
+                .filter(|&t| charging[t] > Self::ON_THRESHOLD_WH)
+                .count();
+            let mut by_charging: Vec<usize> = (start..end).collect();
+            by_charging.sort_by(|&a, &b| charging[b].total_cmp(&charging[a]));
+
+            // As many hours as the LP used, but only as many as the energy fills at minimum power
+            let hours = active
+                .min((energy / min_power).floor() as usize)
+                .max((energy / max_power).ceil() as usize)
+                .clamp(1, end - start);
+            let kept = &by_charging[..hours];
+            let mut scheduled = vec![0.0; end - start];
+            for &t in kept {
+                scheduled[t - start] = charging[t].clamp(min_power, max_power);
+            }
+
+            // Settle the difference to the session energy, raising the hours with the most
+            // charging first and lowering the ones with the least
+            let mut difference = energy - scheduled.iter().sum::<f64>();
+            for &t in kept {
+                let step = difference.min(max_power - scheduled[t - start]).max(0.0);
+                scheduled[t - start] += step;
+                difference -= step;
+            }
+            for &t in kept.iter().rev() {
+                let step = (-difference).min(scheduled[t - start] - min_power).max(0.0);
+                scheduled[t - start] -= step;
+                difference += step;
+            }
+            // Energy that doesn't fill all hours at minimum power (e.g. less than one hour) is
+            // taken from the hour with the least charging
+            scheduled[kept[hours - 1] - start] += difference.min(0.0);
+            charging[start..end].copy_from_slice(&scheduled);
+        }
+
+        VehicleChargingSchedule {
+            violations: charging
+                .iter()
+                .filter(|&&energy| is_too_low(energy))
+                .count(),
+            charging,
+        }
+    }
+
+    /// Plug-in sessions at home as half-open hour ranges
+    fn sessions(&self, num_hours: usize) -> Vec<(usize, usize)> {
+        let mut sessions = Vec::new();
+        let mut session_start = None;
+        for t in 0..num_hours {
+            match (self.can_charge(t), session_start) {
+                (true, None) => session_start = Some(t),
+                (false, Some(start)) => {
+                    sessions.push((start, t));
+                    session_start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(start) = session_start {
+            sessions.push((start, num_hours));
+        }
+        sessions
+    }
+}
+
+/// Car charging schedule after applying the minimum charging power of the wallbox
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct VehicleChargingSchedule {
+    pub charging: Vec<f64>, // Charging energy in Wh per hour
+    pub violations: usize,  // Hours still charging below the minimum power
+}
+
+/// Wallbox a car is charged with
+///
+/// Charging is limited to the rated current and, while active, can't drop below the minimum
+/// current of the charging standard (6 A per phase, IEC 61851). With `binary_on_off` the LP
+/// enforces the minimum exactly with on/off variables, which turns it into a MILP that needs a
+/// solver with integer support such as SCIP. Otherwise the LP only limits the power and the
+/// minimum is applied afterwards, see `ElectricVehicle::with_min_charging_power`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Charger {
+    pub phases: usize,       // Phases used for charging, 1 or 3
+    pub max_current_a: f64,  // Current limit per phase in A
+    pub min_current_a: f64,  // Minimum current per phase in A while charging
+    pub voltage_v: f64,      // Phase voltage in V
+    pub binary_on_off: bool, // Enforce the minimum current with binary variables (MILP)
+}
+
+impl Charger {
+    /// Single-phase wallbox with 16 A (3.7 kW)
+    pub const KW_3_7: Self = Self {
+        phases: 1,
+        max_current_a: 16.0,
+        min_current_a: 6.0,
+        voltage_v: 230.0,
+        binary_on_off: false,
+    };
+    /// Three-phase wallbox with 16 A (11 kW)
+    pub const KW_11: Self = Self {
+        phases: 3,
+        max_current_a: 16.0,
+        min_current_a: 6.0,
+        voltage_v: 230.0,
+        binary_on_off: false,
+    };
+    /// Three-phase wallbox with 32 A (22 kW)
+    pub const KW_22: Self = Self {
+        phases: 3,
+        max_current_a: 32.0,
+        min_current_a: 6.0,
+        voltage_v: 230.0,
+        binary_on_off: false,
+    };
+
+    pub fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.phases != 1 && self.phases != 3 {
+            return Err(format!("Charger must use 1 or 3 phases, got {}", self.phases).into());
+        }
+        let valid = self.min_current_a > 0.0
+            && self.min_current_a <= self.max_current_a
+            && self.voltage_v > 0.0;
+        if !valid {
+            return Err(format!(
+                "Charger needs a positive voltage and 0 < min current <= max current, got {:?}",
+                self
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Maximum charging power in W
+    pub fn max_power_w(&self) -> f64 {
+        self.phases as f64 * self.max_current_a * self.voltage_v
+    }
+
+    /// Minimum charging power in W while charging
+    pub fn min_power_w(&self) -> f64 {
+        self.phases as f64 * self.min_current_a * self.voltage_v
+    }
+}
+
+/// Part of the driving energy that is charged away from home, e.g. at work or at fast chargers.
+/// Public charging is bought at its own price and never covered by the home PV system.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PublicCharging {
+    pub split: PublicChargingSplit,
+    pub price_per_kwh: f64, // Public charging price per kWh
+}
+
+/// How the driving energy is split between home and public charging
+#[derive(Debug, Clone, PartialEq)]
+pub enum PublicChargingSplit {
+    Fraction(f64),       // Share of the daily energy charged publicly (0-1)
+    AwayDays([bool; 7]), // Days the car only charges publicly, indexed by day of the year modulo 7
+}
+
+impl PublicCharging {
+    pub fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if let PublicChargingSplit::Fraction(fraction) = self.split
+            && !(0.0..=1.0).contains(&fraction)
+        {
+            return Err(format!("Public charging fraction must be 0-1, got {}", fraction).into());
+        }
+        if self.price_per_kwh.is_nan() || self.price_per_kwh < 0.0 {
+            return Err(format!(
+                "Public charging price must not be negative, got {}",
+                self.price_per_kwh
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Whether the car is away and can't charge at home on the given day of the year
+    pub fn is_away(&self, day: usize) -> bool {
+        match &self.split {
+            PublicChargingSplit::Fraction(_) => false,
+            PublicChargingSplit::AwayDays(days) => days[day % 7],
+        }
+    }
+
+    /// Share of the daily driving energy charged at home on the given day of the year
+    pub fn home_share(&self, day: usize) -> f64 {
+        match &self.split {
+            PublicChargingSplit::Fraction(fraction) => 1.0 - fraction,
+            PublicChargingSplit::AwayDays(_) if self.is_away(day) => 0.0,
+            PublicChargingSplit::AwayDays(_) => 1.0,
+        }
+    }
+}
+
+/// Daily window in which the utility blocks the heat pump (e.g. German heat pump tariffs)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockingWindow {
+    pub start_hour: usize, // Hour of day (0-23) the block starts, windows may cross midnight
+    pub duration_hours: usize, // Length of the block in hours
+}
+
+/// Heat pump dispatched by the optimizer against a thermal buffer storage
+///
+/// The heat pump has to cover the hourly heat demand from the buffer. Blocking windows force
+/// the electrical power to zero, so the buffer has to bridge them. With an SG-Ready boost the
+/// buffer may be heated above its normal capacity, but only with own production: the grid never
+/// supplies more than the regular loads in that hour.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeatPumpDispatch {
+    pub hourly_heat_demand: Vec<f64>, // Thermal demand in Wh for 8760 hours
+    pub hourly_cop: Vec<f64>,         // Coefficient of performance for 8760 hours
+    pub max_electric_power_w: f64,    // Electrical power limit of the heat pump in W
+    pub storage_capacity_wh: f64,     // Usable thermal energy of the buffer in Wh
+    pub storage_loss: f64,            // Hourly loss rate of the buffer
+    pub blocking_windows: Vec<BlockingWindow>, // Utility blocking windows, applied every day
+    pub sg_ready_boost_wh: Option<f64>, // Extra buffer energy in Wh usable in boost mode; None disables SG-Ready boost
+    pub min_runtime: Option<MinRuntime>, // Anti-cycling rules applied to the optimized schedule; None keeps it as is
+    pub legionella_cycle: Option<LegionellaCycle>, // Weekly purge of a hot water buffer; None for space heating buffers
+    pub building: Option<BuildingThermalModel>, // Heated building replacing `hourly_heat_demand`; None keeps the fixed demand
+    pub domestic_hot_water: Option<DomesticHotWater>, // Hot water mode of the heat pump; None for space heating only
+    pub defrost: Option<DefrostPenalty>, // Defrost and cold-climate losses on top of `hourly_cop`; None takes it as is
+    pub sound_power_db: Option<f64>, // Outdoor unit sound power in dB(A) from the datasheet; None estimates it from the size
+}
+
+/// Domestic hot water produced by the heat pump in a second mode, at the lower COP of the
+/// higher flow temperature
+///
+/// The compressor heats either the space heating buffer or the hot water tank. The LP shares
+/// each hour between the two modes; with `binary_modes` it runs one mode per hour, which turns
+/// it into a MILP that needs a solver with integer support such as SCIP. Hot water has
+/// priority: the tank never drops below `priority_level_wh`, the level at which the controller
+/// switches to hot water.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DomesticHotWater {
+    pub hourly_demand: Vec<f64>, // Hot water heat demand in Wh for 8760 hours
+    pub hourly_cop: Vec<f64>,    // COP in hot water mode for 8760 hours
+    pub tank_capacity_wh: f64,   // Usable thermal energy of the tank in Wh
+    pub tank_loss: f64,          // Hourly loss rate of the tank
+    pub priority_level_wh: f64,  // Tank level kept for hot water priority
+    pub binary_modes: bool,      // Run one mode per hour with binary variables (MILP)
+    pub solar_thermal: Option<SolarThermalCollector>, // Collectors heating the tank; None for heat pump only
+}
+
+/// Solar thermal collectors heating the hot water tank, sized by the optimizer
+///
+/// The yield per m² follows the collector efficiency curve
+/// `eta0 * G - a1 * dT - a2 * dT²` with the irradiance `G` from the PV series (capacity factor
+/// times 1000 W/m²) and a constant difference `dT` between collector and ambient temperature.
+/// Heat the tank can't take is dumped (stagnation). With a roof area the collectors and the
+/// PV modules share the roof.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SolarThermalCollector {
+    pub eta0: f64,             // Optical efficiency
+    pub a1: f64,               // Linear heat loss coefficient in W/m²K
+    pub a2: f64,               // Quadratic heat loss coefficient in W/m²K²
+    pub mean_temp_diff_k: f64, // Collector mean temperature above ambient
+    pub max_area_m2: f64,      // Largest collector area to consider
+    pub inv_per_m2: f64,       // Investment cost per m², annualized like PV
+}
+
+impl Default for SolarThermalCollector {
+    fn default() -> Self {
+        // Flat plate collector
+        Self {
+            eta0: 0.8,
+            a1: 3.5,
+            a2: 0.015,
+            mean_temp_diff_k: 30.0,
+            max_area_m2: 10.0,
+            inv_per_m2: 500.0,
+        }
+    }
+}
+
+impl SolarThermalCollector {
+    pub fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let values = [
+            self.eta0,
+            self.a1,
+            self.a2,
+            self.mean_temp_diff_k,
+            self.max_area_m2,
+            self.inv_per_m2,
+        ];
+        if values
+            .iter()
+            .any(|value| !value.is_finite() || *value < 0.0)
+            || self.eta0 > 1.0
+        {
+            return Err(format!(
+                "Solar thermal collector needs non-negative values and an optical efficiency of at most 1, got {:?}",
+                self
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Heat yield in Wh per m² of collector in an hour with the given PV capacity factor
+    pub fn yield_wh_per_m2(&self, solar_capacity_factor: f64) -> f64 {
+        let irradiance_w_per_m2 = solar_capacity_factor * 1000.0;
+        let dt = self.mean_temp_diff_k;
+        (self.eta0 * irradiance_w_per_m2 - self.a1 * dt - self.a2 * dt * dt).max(0.0)
+    }
+}
+
+impl Default for DomesticHotWater {
+    fn default() -> Self {
+        Self {
+            hourly_demand: vec![0.0; 8760],
+            hourly_cop: vec![2.5; 8760],
+            tank_capacity_wh: 8000.0,
+            tank_loss: 0.01,
+            priority_level_wh: 2000.0,
+            binary_modes: false,
+            solar_thermal: None,
+        }
+    }
+}
+
+impl DomesticHotWater {
+    pub fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.hourly_demand.len() != 8760 || self.hourly_cop.len() != 8760 {
+            return Err(format!(
+                "Hot water needs 8760 hourly values, got {} demand and {} COP values",
+                self.hourly_demand.len(),
+                self.hourly_cop.len()
+            )
+            .into());
+        }
+        if self
+            .hourly_cop
+            .iter()
+            .any(|&cop| cop.is_nan() || cop <= 0.0)
+        {
+            return Err("Hot water COP must be positive in every hour".into());
+        }
+        if self.priority_level_wh.is_nan()
+            || self.priority_level_wh < 0.0
+            || self.priority_level_wh > self.tank_capacity_wh
+        {
+            return Err(format!(
+                "Hot water priority level must be between 0 and the tank capacity of {} Wh, got {} Wh",
+                self.tank_capacity_wh, self.priority_level_wh
+            )
+            .into());
+        }
+        if let Some(solar_thermal) = &self.solar_thermal {
+            solar_thermal.validate()?;
+        }
+        Ok(())
+    }
+}
+
+/// Building heated by the heat pump, as a single RC node: the structure and the indoor air
+/// store heat (capacitance) and lose it to the outside (resistance)
+///
+/// The heat demand follows from the losses, and the indoor temperature may move within the
+/// comfort band, so the optimizer can pre-heat with PV surplus and coast through expensive
+/// hours. In hourly steps: `C * (T[t] - T[t-1]) = Q[t] - H * (T[t-1] - T_out[t])`, cyclic over
+/// the year.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuildingThermalModel {
+    pub heat_loss_w_per_k: f64, // Transmission and ventilation losses (H = 1/R)
+    pub heat_capacity_wh_per_k: f64, // Effective thermal mass (C)
+    pub hourly_outdoor_temp_c: Vec<f64>, // Outdoor temperature for 8760 hours
+    pub min_indoor_temp_c: f64,
+    pub max_indoor_temp_c: f64,
+}
+
+impl Default for BuildingThermalModel {
+    fn default() -> Self {
+        Self {
+            heat_loss_w_per_k: 150.0,
+            heat_capacity_wh_per_k: 10000.0,
+            hourly_outdoor_temp_c: vec![5.0; 8760],
+            min_indoor_temp_c: 20.0,
+            max_indoor_temp_c: 23.0,
+        }
+    }
+}
+
+impl BuildingThermalModel {
+    pub fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.hourly_outdoor_temp_c.len() != 8760
+            || self
+                .hourly_outdoor_temp_c
+                .iter()
+                .any(|temp| !temp.is_finite())
+        {
+            return Err(format!(
+                "Building needs 8760 finite hourly outdoor temperatures, got {}",
+                self.hourly_outdoor_temp_c.len()
+            )
+            .into());
+        }
+        // The hourly steps are only stable if an hour loses less than the stored heat
+        if self.heat_loss_w_per_k.is_nan()
+            || self.heat_loss_w_per_k <= 0.0
+            || self.heat_loss_w_per_k >= self.heat_capacity_wh_per_k
+        {
+            return Err(format!(
+                "Building heat loss must be positive and below the heat capacity per hour, got {} W/K and {} Wh/K",
+                self.heat_loss_w_per_k, self.heat_capacity_wh_per_k
+            )
+            .into());
+        }
+        if self.min_indoor_temp_c.is_nan()
+            || self.max_indoor_temp_c.is_nan()
+            || self.min_indoor_temp_c > self.max_indoor_temp_c
+        {
+            return Err(format!(
+                "Indoor temperature band {} to {} °C is empty",
+                self.min_indoor_temp_c, self.max_indoor_temp_c
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Time constant of the building in hours (R * C)
+    pub fn time_constant_hours(&self) -> f64 {
+        self.heat_capacity_wh_per_k / self.heat_loss_w_per_k
+    }
+}
+
+/// COP losses of an air source heat pump that a COP series from the temperature lift misses
+///
+/// Around freezing, humid air frosts the evaporator and the defrost cycles cost up to
+/// `max_defrost_penalty` of the COP at `peak_frost_temp_c`, fading out `frost_band_k` above
+/// and below. In colder air the heat pump loses another `cold_penalty_per_k` of the COP per K
+/// below `cold_limit_c`. The COP never drops below 1, the backup heater takes over there.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DefrostPenalty {
+    pub hourly_outdoor_temp_c: Vec<f64>, // Outdoor temperature for 8760 hours
+    pub max_defrost_penalty: f64,        // Share of the COP lost at the peak, 0.1-0.2 are typical
+    pub peak_frost_temp_c: f64,          // Outdoor temperature with the most frosting
+    pub frost_band_k: f64,               // Distance from the peak where defrosting stops
+    pub cold_limit_c: f64,               // Below this the COP drops further
+    pub cold_penalty_per_k: f64,         // Share of the COP lost per K below the cold limit
+}
+
+impl Default for DefrostPenalty {
+    fn default() -> Self {
+        Self {
+            hourly_outdoor_temp_c: vec![5.0; 8760],
+            max_defrost_penalty: 0.15,
+            peak_frost_temp_c: 2.0,
+            frost_band_k: 7.0,
+            cold_limit_c: -7.0,
+            cold_penalty_per_k: 0.02,
+        }
+    }
+}
+
+impl DefrostPenalty {
+    pub fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.hourly_outdoor_temp_c.len() != 8760
+            || self
+                .hourly_outdoor_temp_c
+                .iter()
+                .any(|temp| !temp.is_finite())
+        {
+            return Err(format!(
+                "Defrost penalty needs 8760 finite hourly outdoor temperatures, got {}",
+                self.hourly_outdoor_temp_c.len()
+            )
+            .into());
+        }
+        if !((0.0..1.0).contains(&self.max_defrost_penalty)
+            && self.frost_band_k > 0.0
+            && self.peak_frost_temp_c.is_finite()
+            && self.cold_limit_c.is_finite()
+            && self.cold_penalty_per_k >= 0.0)
+        {
+            return Err(format!(
+                "Defrost penalty must be below 1 with a positive frost band and a non-negative cold penalty, got {} at {} ± {} K and {} per K below {} °C",
+                self.max_defrost_penalty,
+                self.peak_frost_temp_c,
+                self.frost_band_k,
+                self.cold_penalty_per_k,
+                self.cold_limit_c
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Share of the COP left in hour `t`, before the backup heater limit
+    pub fn cop_factor(&self, t: usize) -> f64 {
+        self.cop_factor_at(self.hourly_outdoor_temp_c[t])
+    }
+
+    /// Share of the COP left at an outdoor temperature, before the backup heater limit
+    pub fn cop_factor_at(&self, temp: f64) -> f64 {
+        let frosting = (1.0 - (temp - self.peak_frost_temp_c).abs() / self.frost_band_k).max(0.0);
+        let cold = (self.cold_limit_c - temp).max(0.0);
+        (1.0 - self.max_defrost_penalty * frosting - self.cold_penalty_per_k * cold).max(0.0)
+    }
+
+    /// COP in hour `t` after the penalty, never below 1 (or the COP itself if that is lower)
+    pub fn apply(&self, cop: f64, t: usize) -> f64 {
+        (cop * self.cop_factor(t)).max(cop.min(1.0))
+    }
+}
+
+/// Weekly thermal disinfection of a hot water buffer
+///
+/// The buffer has to hold `purge_level_wh`, the energy equivalent of heating the tank to the
+/// purge temperature (e.g. 60 °C), at the purge hour of every week. The hour is fixed to keep
+/// the model linear.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LegionellaCycle {
+    pub purge_level_wh: f64,
+    pub weekday: usize, // Day of the week, 0 = Monday (the modelled year starts on a Monday)
+    pub hour: usize,    // Hour of the day (0-23)
+}
+
+impl LegionellaCycle {
+    /// Whether hour `t` of the year is a purge hour
+    pub fn is_purge_hour(&self, t: usize) -> bool {
+        t % (7 * 24) == self.weekday * 24 + self.hour
+    }
+}
+
+/// Minimum on and off durations of the heat pump compressor
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MinRuntime {
+    pub min_on_hours: usize,
+    pub min_off_hours: usize,
+}
+
+/// Heat pump schedule after applying the minimum runtime rules
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct HeatPumpSchedule {
+    pub consumption: Vec<f64>, // Electrical consumption in Wh per hour
+    pub storage: Vec<f64>,     // Thermal buffer level in Wh
+    pub starts: usize,         // Number of times the heat pump switches on
+    pub violations: usize,     // On runs and off gaps that are still shorter than the minimum
+}
+
+impl Default for HeatPumpDispatch {
+    fn default() -> Self {
+        Self {
+            hourly_heat_demand: vec![0.0; 8760],
+            hourly_cop: vec![3.0; 8760],
+            max_electric_power_w: 3000.0,
+            storage_capacity_wh: 10000.0,
+            storage_loss: 0.01,
+            blocking_windows: Vec::new(),
+            sg_ready_boost_wh: None,
+            min_runtime: None,
+            legionella_cycle: None,
+            building: None,
+            domestic_hot_water: None,
+            defrost: None,
+            sound_power_db: None,
+        }
+    }
+}
+
+impl HeatPumpDispatch {
+    /// Network operators may block a heat pump at most three times a day for two hours each
+    pub const MAX_BLOCKING_WINDOWS: usize = 3;
+    pub const MAX_BLOCKING_HOURS: usize = 2;
+    // Consumption below this counts as off, the LP leaves tiny values in idle hours
+    const ON_THRESHOLD_WH: f64 = 1.0;
+
+    /// Heat pump covering the given heat demand with the COP series of the configured heating type
+    pub fn from_heat_demand(
+        hourly_heat_demand: Vec<f64>,
+        heating_type: &HeatingType,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self {
+            hourly_cop: load_cop_data_from_csv(heating_type)?,
+            hourly_heat_demand,
+            ..Default::default()
+        })
+    }
+
+    /// Checks the series lengths, the COP values and the blocking windows
+    pub fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.hourly_heat_demand.len() != 8760 || self.hourly_cop.len() != 8760 {
+            return Err(format!(
+                "Heat pump needs 8760 hourly values, got {} heat demand and {} COP values",
+                self.hourly_heat_demand.len(),
+                self.hourly_cop.len()
+            )
+            .into());
+        }
+        if self
+            .hourly_cop
+            .iter()
+            .any(|&cop| cop.is_nan() || cop <= 0.0)
+        {
+            return Err("Heat pump COP must be positive in every hour".into());
+        }
+        if self.blocking_windows.len() > Self::MAX_BLOCKING_WINDOWS {
+            return Err(format!(
+                "At most {} blocking windows per day are allowed, got {}",
+                Self::MAX_BLOCKING_WINDOWS,
+                self.blocking_windows.len()
+            )
+            .into());
+        }
+        for window in &self.blocking_windows {
+            if window.start_hour >= 24
+                || window.duration_hours == 0
+                || window.duration_hours > Self::MAX_BLOCKING_HOURS
+            {
+                return Err(format!(
+                    "Blocking window must start at hour 0-23 and last 1-{} hours, got {:?}",
+                    Self::MAX_BLOCKING_HOURS,
+                    window
+                )
+                .into());
+            }
+        }
+        if let Some(building) = &self.building {
+            building.validate()?;
+        }
+        if let Some(hot_water) = &self.domestic_hot_water {
+            hot_water.validate()?;
+        }
+        if let Some(defrost) = &self.defrost {
+            defrost.validate()?;
+        }
+        if let Some(sound_power_db) = self.sound_power_db
+            && !(sound_power_db.is_finite() && sound_power_db > 0.0)
+        {
+            return Err(format!(
+                "Heat pump sound power must be positive, got {} dB(A)",
+                sound_power_db
+            )
+            .into());
+        }
+        if let Some(cycle) = &self.legionella_cycle {
+            let max_level = self.storage_capacity_wh + self.sg_ready_boost_wh.unwrap_or(0.0);
+            if cycle.weekday >= 7 || cycle.hour >= 24 {
+                return Err(format!(
+                    "Legionella cycle must be on weekday 0-6 at hour 0-23, got {:?}",
+                    cycle
+                )
+                .into());
+            }
+            if cycle.purge_level_wh.is_nan()
+                || cycle.purge_level_wh < 0.0
+                || cycle.purge_level_wh > max_level
+            {
+                return Err(format!(
+                    "Legionella purge level must be between 0 and the buffer capacity of {} Wh, got {} Wh",
+                    max_level, cycle.purge_level_wh
+                )
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Sound power of the outdoor unit in dB(A), estimated for units of about 60 dB(A) at 3 kW
+    /// electrical power if the datasheet value is missing
+    pub fn outdoor_sound_power_db(&self) -> f64 {
+        self.sound_power_db
+            .unwrap_or_else(|| 60.0 + 10.0 * (self.max_electric_power_w / 3000.0).log10())
+    }
+
+    /// Space heating COP in hour `t`, including the defrost penalty
+    pub fn cop(&self, t: usize) -> f64 {
+        self.penalized_cop(self.hourly_cop[t], t)
+    }
+
+    /// Applies the defrost penalty of the outdoor unit to a COP of hour `t`, e.g. the hot water
+    /// COP
+    pub fn penalized_cop(&self, cop: f64, t: usize) -> f64 {
+        match &self.defrost {
+            Some(defrost) => defrost.apply(cop, t),
+            None => cop,
+        }
+    }
+
+    /// Whether the heat pump is blocked in the given hour of the year
+    pub fn is_blocked(&self, hour: usize) -> bool {
+        let hour_of_day = hour % 24;
+        self.blocking_windows
+            .iter()
+            .any(|window| (hour_of_day + 24 - window.start_hour) % 24 < window.duration_hours)
+    }
+
+    /// Thermal buffer level for an electrical consumption schedule, starting empty
+    pub fn storage_levels(&self, consumption: &[f64]) -> Vec<f64> {
+        let retention = 1.0 - self.storage_loss;
+        let mut level = 0.0;
+        consumption
+            .iter()
+            .enumerate()
+            .map(|(t, &consumption)| {
+                level = level * retention + self.cop(t) * consumption - self.hourly_heat_demand[t];
+                level
+            })
+            .collect()
+    }
+
+    /// Turns a consumption schedule into one a controller can follow by merging on runs and
+    /// off gaps that are shorter than the configured minimum.
+    ///
+    /// This is a heuristic on top of the LP: a merged block delivers the same heat spread
+    /// evenly over its hours, and a merge is only kept if the buffer stays within its limits,
+    /// the power limit holds and no blocking window is touched. Runs that can't be merged are
+    /// counted in `violations`. The boost energy may end up in hours that draw from the grid.
+    pub fn with_min_runtime(&self, consumption: &[f64]) -> HeatPumpSchedule {
+        const MAX_PASSES: usize = 20;
+        let mut consumption = consumption.to_vec();
+        let Some(min_runtime) = self.min_runtime else {
+            return self.schedule(consumption, None);
+        };
+        let mut levels = self.storage_levels(&consumption);
+        // The LP may leave the buffer slightly below zero, merges must not make it worse
+        let min_level = levels.iter().copied().fold(0.0f64, f64::min);
+
+        for _ in 0..MAX_PASSES {
+            let runs = self.on_runs(&consumption);
+            let mut changed = false;
+
+            // Fill off gaps that are too short by merging the surrounding runs
+            for pair in runs.windows(2) {
+                let (start, gap_start, gap_end, end) = (pair[0].0, pair[0].1, pair[1].0, pair[1].1);
+                if gap_end - gap_start < min_runtime.min_off_hours
+                    && self.try_merge(&mut consumption, &mut levels, start, end, min_level)
+                {
+                    changed = true;
+                }
+            }
+
+            // Stretch on runs that are too short, forwards if possible, otherwise backwards
+            let runs = self.on_runs(&consumption);
+            for (index, &(start, end)) in runs.iter().enumerate() {
+                if end - start >= min_runtime.min_on_hours {
+                    continue;
+                }
+                let mut forward_end = (start + min_runtime.min_on_hours).min(consumption.len());
+                if let Some(&(next_start, next_end)) = runs.get(index + 1)
+                    && next_start < forward_end + min_runtime.min_off_hours
+                {
+                    forward_end = forward_end.max(next_end);
+                }
+                let mut backward_start = end.saturating_sub(min_runtime.min_on_hours);
+                if let Some(&(previous_start, previous_end)) =
+                    index.checked_sub(1).and_then(|previous| runs.get(previous))
+                    && previous_end + min_runtime.min_off_hours > backward_start
+                {
+                    backward_start = backward_start.min(previous_start);
+                }
+                if (forward_end > end
+                    && self.try_merge(&mut consumption, &mut levels, start, forward_end, min_level))
+                    || (backward_start < start
+                        && self.try_merge(
+                            &mut consumption,
+                            &mut levels,
+                            backward_start,
+                            end,
+                            min_level,
+                        ))
+                {
+                    changed = true;
+                }
+            }
+
+            if !changed {
+                break;
+            }
+        }
+
+        self.schedule(consumption, Some(min_runtime))
+    }
+
+    /// On runs of the heat pump as half-open hour ranges
+    fn on_runs(&self, consumption: &[f64]) -> Vec<(usize, usize)> {
+        let mut runs = Vec::new();
+        let mut run_start = None;
+        for (t, &consumption) in consumption.iter().enumerate() {
+            match (consumption > Self::ON_THRESHOLD_WH, run_start) {
+                (true, None) => run_start = Some(t),
+                (false, Some(start)) => {
+                    runs.push((start, t));
+                    run_start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(start) = run_start {
+            runs.push((start, consumption.len()));
+        }
+        runs
+    }
+
+    /// Spreads the heat of the hours `start..end` evenly over all of them if the buffer stays
+    /// between `min_level` and its capacity, returns whether the schedule was changed
+    fn try_merge(
+        &self,
+        consumption: &mut [f64],
+        levels: &mut [f64],
+        start: usize,
+        end: usize,
+        min_level: f64,
+    ) -> bool {
+        if end <= start || (start..end).any(|t| self.is_blocked(t)) {
+            return false;
+        }
+        let heat: f64 = (start..end).map(|t| self.cop(t) * consumption[t]).sum();
+        let heat_per_hour = heat / (end - start) as f64;
+        let merged: Vec<f64> = (start..end).map(|t| heat_per_hour / self.cop(t)).collect();
+        if merged
+            .iter()
+            .any(|&power| power > self.max_electric_power_w + 1e-6)
+        {
+            return false;
+        }
+
+        // Simulate the buffer from the block on until the difference to the current levels
+        // has decayed
+        let retention = 1.0 - self.storage_loss;
+        let max_level = self.storage_capacity_wh + self.sg_ready_boost_wh.unwrap_or(0.0);
+        let within_limits = |level: f64| level >= min_level - 1e-6 && level <= max_level + 1e-6;
+        let mut new_levels = Vec::new();
+        let mut level = start.checked_sub(1).map(|t| levels[t]).unwrap_or(0.0);
+        for t in start..levels.len() {
+            let power = if t < end {
+                merged[t - start]
+            } else {
+                consumption[t]
+            };
+            level = level * retention + self.cop(t) * power - self.hourly_heat_demand[t];
+            if !within_limits(level) {
+                return false;
+            }
+            new_levels.push(level);
+            if t >= end && (level - levels[t]).abs() < 1e-9 {
+                break;
+            }
+        }
+
+        consumption[start..end].copy_from_slice(&merged);
+        levels[start..start + new_levels.len()].copy_from_slice(&new_levels);
+        true
+    }
+
+    fn schedule(&self, consumption: Vec<f64>, min_runtime: Option<MinRuntime>) -> HeatPumpSchedule {
+        let runs = self.on_runs(&consumption);
+        let violations = match min_runtime {
+            Some(min_runtime) => {
+                let short_runs = runs
+                    .iter()
+                    .filter(|(start, end)| end - start < min_runtime.min_on_hours)
+                    .count();
+                let short_gaps = runs
+                    .windows(2)
+                    .filter(|pair| pair[1].0 - pair[0].1 < min_runtime.min_off_hours)
+                    .count();
+                short_runs + short_gaps
+            }
+            None => 0,
+        };
+        HeatPumpSchedule {
+            storage: self.storage_levels(&consumption),
+            starts: runs.len(),
+            violations,
+            consumption,
+        }
+    }
 }
 
-impl Default for StaticSimulationConfigs {
+/// Phase(s) a device is connected to
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PhaseAssignment {
+    L1,
+    L2,
+    L3,
+    #[default]
+    ThreePhase,
+}
+
+impl PhaseAssignment {
+    /// Share of the device power on each of the three phases
+    pub fn shares(&self) -> [f64; 3] {
+        match self {
+            PhaseAssignment::L1 => [1.0, 0.0, 0.0],
+            PhaseAssignment::L2 => [0.0, 1.0, 0.0],
+            PhaseAssignment::L3 => [0.0, 0.0, 1.0],
+            PhaseAssignment::ThreePhase => [1.0 / 3.0; 3],
+        }
+    }
+}
+
+/// Per-phase model of the grid connection
+///
+/// With a phase config the optimizer checks the net power flow on every phase
+/// instead of only the sum over all phases, so single-phase inverters and
+/// unbalanced loads can't exceed the per-phase limits.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhaseConfig {
+    pub household_load_shares: [f64; 3], // Share of the base household demand on L1, L2, L3
+    pub pv_inverter: PhaseAssignment,    // Phase(s) of the PV inverter
+    pub wind_inverter: PhaseAssignment,  // Phase(s) of the wind turbine inverter
+    pub battery_inverter: PhaseAssignment, // Phase(s) of the battery inverter
+    pub car_charger: PhaseAssignment,    // Phase(s) of the wallbox
+    pub max_inverter_power_per_phase_w: Option<f64>, // AC limit of the inverters per phase in W
+    pub max_feed_in_per_phase_w: Option<f64>, // Feed-in limit per phase in W
+    pub max_import_per_phase_w: Option<f64>, // Import limit (fuse) per phase in W
+}
+
+impl Default for PhaseConfig {
     fn default() -> Self {
         Self {
-            pv_degradation: 0.005,
-            battery_degradation: 0.03,
-            num_years: 25,
-            battery_loss: 0.00005,
-            max_battery_charge_rate: 2000.0,
-            max_battery_discharge_rate: 2000.0,
+            household_load_shares: [1.0 / 3.0; 3],
+            pv_inverter: PhaseAssignment::ThreePhase,
+            wind_inverter: PhaseAssignment::ThreePhase,
+            battery_inverter: PhaseAssignment::ThreePhase,
+            car_charger: PhaseAssignment::ThreePhase,
+            max_inverter_power_per_phase_w: None,
+            max_feed_in_per_phase_w: None,
+            max_import_per_phase_w: None,
         }
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct StaticSimulationResults {
-    pub autarky: f64,
-    pub total_production: f64,
-    pub total_direct_consumption: f64,
-    pub total_battery_out: f64,
-    pub total_battery_in: f64,
-    pub total_overproduction: f64,
-    pub total_overproduction_without_battery: f64,
+impl PhaseConfig {
+    /// German grid connection rules: single-phase feed-in is limited to 4.6 kW
+    /// (VDE-AR-N 4105 unbalance limit)
+    pub fn germany() -> Self {
+        Self {
+            max_feed_in_per_phase_w: Some(4600.0),
+            ..Default::default()
+        }
+    }
 }
 
-/// Configuration struct holding all optimization parameters
-#[derive(Debug, Clone)]
-pub struct OptimizationConfig {
-    // Investment costs
-    pub inv_pv: f64,        // Investment cost for PV per kW
-    pub inv_bat: f64,       // Investment cost for battery per kWh
-    pub inv_hwat: f64,      // Investment cost for hot water storage per kWh
-    pub inv_grid: f64,      // Investment cost for grid connection per kW
-    pub inv_heat_pump: f64, // Investment cost for heat pump per kW
+/// Main fuse of the house connection
+///
+/// The grid import is checked against the connection power in the hourly results. Hourly
+/// averages hide short peaks, so the check is a lower bound of the real fuse load.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridConnection {
+    pub phases: usize,       // Number of phases (1 or 3)
+    pub fuse_current_a: f64, // Rated current of the main fuse per phase
+    pub voltage_v: f64,      // Phase voltage
+    pub enforce: bool,       // Limit the grid import to the connection power in the optimization
+}
 
-    // Economic parameters
-    pub annuity: f64,        // Annuity factor
-    pub fc_grid: f64,        // Fuel cost for grid electricity per kWh
-    pub feed_in_tariff: f64, // Feed-in tariff per kWh
+impl Default for GridConnection {
+    fn default() -> Self {
+        Self {
+            phases: 3,
+            fuse_current_a: 25.0,
+            voltage_v: 230.0,
+            enforce: false,
+        }
+    }
+}
 
-    // System parameters
-    pub hwat_enabled: bool,                    // Flag for hot water system
-    pub storage_loss_bat: f64,                 // Battery hourly loss rate
-    pub storage_loss_hwat: f64,                // Hot water storage hourly loss rate
-    pub eta_in_bat: f64,                       // Battery charging efficiency
-    pub eta_out_bat: f64,                      // Battery discharging efficiency
-    pub eta_in_hwat: f64,                      // Hot water storage efficiency
-    pub eta_out_hwat: f64,                     // Hot water discharge efficiency
-    pub c_rate_limit: f64, // C-rate limit for battery (fraction of capacity per hour)
-    pub electricity_usage: f64, // Annual electricity usage in kWh (normalizes timeseries to this total)
-    pub monthly_demand: Option<MonthlyDemand>, // Monthly demand in kWh
+impl GridConnection {
+    pub fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.phases != 1 && self.phases != 3 {
+            return Err(format!(
+                "Grid connection must have 1 or 3 phases, got {}",
+                self.phases
+            )
+            .into());
+        }
+        if self.fuse_current_a <= 0.0 || self.voltage_v <= 0.0 {
+            return Err(format!(
+                "Grid connection fuse current and voltage must be positive, got {} A and {} V",
+                self.fuse_current_a, self.voltage_v
+            )
+            .into());
+        }
+        Ok(())
+    }
 
-    // Electric car parameters
-    pub electric_car_enabled: bool,     // Flag for electric car
-    pub car_daily_km: f64,              // Daily kilometers driven
-    pub car_efficiency_kwh_per_km: f64, // Car efficiency in kWh per km
-    pub car_battery_size_kwh: f64,      // Car battery size in kWh
-    pub car_charge_during_day: bool,    // Whether car charges during day (true) or night (false)
+    /// Power the connection can carry in W
+    pub fn max_power_w(&self) -> f64 {
+        self.phases as f64 * self.fuse_current_a * self.voltage_v
+    }
+}
 
-    // Heat pump parameters
-    pub heat_pump_enabled: bool,           // Flag for heat pump system
-    pub house_square_meters: f64,          // House size in square meters
-    pub insulation_level: InsulationLevel, // Insulation quality
-    pub heating_type: HeatingType,         // Floor or radiator heating
-    pub monthly_temperatures: [f64; 12],   // Desired temperature for each month (°C)
+/// Dimming of controllable loads by the grid operator during grid stress, as in §14a EnWG:
+/// the heat pump and every wallbox keep at least `min_power_w` during a dimming event.
+///
+/// The dispatch is checked against the dimmed power in the hourly results. With `enforce` the
+/// optimizer plans around the events, e.g. by preheating the buffer and charging earlier.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ControllableLoadDimming {
+    pub min_power_w: f64, // Power each controllable load may still draw during an event
+    pub events: Vec<DimmingEvent>,
+    pub enforce: bool, // Limit the controllable loads to the dimmed power in the optimization
+}
 
-    // Building configuration parameters
-    pub building_type: BuildingTypeEnum, // Building type (SingleFamily, Terraced, MultiFamily, Apartment)
-    pub construction_period: YearCategoryESEnum, // Construction period (Before1900, Between1901and1936, etc.)
-    pub insulation_standard: InsulationLevel,    // Insulation standard (Poor, Moderate, Good)
+/// Hours in which the grid operator dims the controllable loads
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DimmingEvent {
+    pub start_hour: usize, // Hour of the year (0-8759) the event starts
+    pub duration_hours: usize,
+}
 
-    // Optimization loop parameters
-    pub bat_value: f64,        // Fixed battery capacity for optimization loop
-    pub pv_capacity_min: f64,  // Minimum PV capacity to test
-    pub pv_capacity_max: f64,  // Maximum PV capacity to test
-    pub pv_capacity_step: f64, // Step size for PV capacity testing
-    pub pv_fixed: bool,        // if true, pv capacity cannot be changed by optimization
-    pub bat_fixed: bool,       // if true, battery capacity cannot be changed by optimization
-    pub electricity_price_increase: f64, // Electricity price increase per year
+impl Default for ControllableLoadDimming {
+    fn default() -> Self {
+        Self {
+            min_power_w: 4200.0,
+            events: Vec::new(),
+            enforce: true,
+        }
+    }
+}
 
-    // Optimization mode
-    pub optimize_for_autonomy: bool, // if true, optimize for maximum autonomy instead of minimum cost
+impl ControllableLoadDimming {
+    pub fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.min_power_w.is_finite() || self.min_power_w < 0.0 {
+            return Err(format!(
+                "Dimmed power must not be negative, got {} W",
+                self.min_power_w
+            )
+            .into());
+        }
+        for event in &self.events {
+            if event.duration_hours == 0 || event.hours().end > 8760 {
+                return Err(format!(
+                    "Dimming event of {} hours starting at hour {} must lie within the year",
+                    event.duration_hours, event.start_hour
+                )
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the loads are dimmed in hour `t`
+    pub fn is_dimmed(&self, t: usize) -> bool {
+        self.events.iter().any(|event| event.hours().contains(&t))
+    }
 }
 
-impl Default for OptimizationConfig {
-    fn default() -> Self {
+impl DimmingEvent {
+    /// Dimming on `day` (0-364) from `start_hour` (0-23) for `duration_hours`
+    pub fn on(day: usize, start_hour: usize, duration_hours: usize) -> Self {
         Self {
-            inv_pv: 465.0,
-            inv_bat: 200.0,
-            inv_hwat: 60.0,
-            inv_grid: 0.0,
-            inv_heat_pump: 0.0,
+            start_hour: day * 24 + start_hour,
+            duration_hours,
+        }
+    }
 
-            // Economic parameters
-            annuity: 0.1,
-            fc_grid: 0.30,
-            feed_in_tariff: 0.079,
+    /// Hours of the year covered by the event
+    pub fn hours(&self) -> std::ops::Range<usize> {
+        self.start_hour..self.start_hour + self.duration_hours
+    }
+}
 
-            // System parameters
-            hwat_enabled: true,
-            storage_loss_bat: 0.001,
-            storage_loss_hwat: 0.01,
-            eta_in_bat: 0.95,
-            eta_out_bat: 0.95,
-            eta_in_hwat: 0.90,
-            eta_out_hwat: 0.90,
-            c_rate_limit: 0.3,
-            electricity_usage: 4173440.0, // Default: normalized annual electricity usage in Wh
-            monthly_demand: None,
+/// How the controllable loads were operated during a dimming event
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DimmingEventResult {
+    pub start_hour: usize,
+    pub duration_hours: usize,
+    pub heat_pump_kwh: f64,
+    pub vehicle_charging_kwh: f64,
+    pub excess_heat_pump_kwh: f64, // Heat pump consumption above the dimmed power, cut by the grid operator
+    pub excess_charging_kwh: f64,  // Charging above the dimmed power, cut by the grid operator
+    pub unmet_heat_kwh: f64, // Heat lost with the cut heat pump consumption, the comfort impact of the event
+}
 
-            // Electric car parameters
-            electric_car_enabled: false,
-            car_daily_km: 50.0,             // 50 km per day default
-            car_efficiency_kwh_per_km: 0.2, // 0.2 kWh per km default
-            car_battery_size_kwh: 50.0,     // 50 kWh battery default
-            car_charge_during_day: true,    // Default to daytime charging
+/// Capacity-priced network tariff: an annual fee per kW of the billed grid import peak
+///
+/// Tariffs billing 15-minute peaks are approximated with the hourly averages of the model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapacityTariff {
+    pub price_per_kw_year: f64, // Annual fee per kW of the billed peak
+    pub billing: PeakBilling,
+    pub min_billed_kw: f64, // Lower peaks are billed at this value (e.g. 2.5 kW in Flanders)
+}
 
-            // Heat pump parameters
-            heat_pump_enabled: false,
-            house_square_meters: 100.0,
-            insulation_level: InsulationLevel::Moderate,
-            heating_type: HeatingType::Floor,
-            monthly_temperatures: [20.0; 12],
+/// How the billed peak is formed from the grid import
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PeakBilling {
+    AnnualPeak,     // Highest import of the year
+    MonthlyAverage, // Average of the monthly import peaks
+}
 
-            // Building configuration parameters
-            building_type: BuildingTypeEnum::SingleFamily,
-            construction_period: YearCategoryESEnum::Before1900,
-            insulation_standard: InsulationLevel::Moderate,
+impl CapacityTariff {
+    pub fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.price_per_kw_year.is_nan() || self.price_per_kw_year < 0.0 {
+            return Err(format!(
+                "Capacity tariff price must not be negative, got {}",
+                self.price_per_kw_year
+            )
+            .into());
+        }
+        if self.min_billed_kw.is_nan() || self.min_billed_kw < 0.0 {
+            return Err(format!(
+                "Minimum billed peak must not be negative, got {}",
+                self.min_billed_kw
+            )
+            .into());
+        }
+        Ok(())
+    }
 
-            // Optimization loop parameters
-            bat_value: 20000.0,
-            pv_capacity_min: 0.0,
-            pv_capacity_max: 2.0,
-            pv_capacity_step: 0.5,
-            pv_fixed: false,
-            bat_fixed: false,
-            electricity_price_increase: 0.0,
+    /// Hours of each billing period within the first `num_hours` hours of the year
+    pub fn periods(&self, num_hours: usize) -> Vec<std::ops::Range<usize>> {
+        match self.billing {
+            PeakBilling::AnnualPeak => std::iter::once(0..num_hours).collect(),
+            PeakBilling::MonthlyAverage => {
+                let mut start = 0;
+                DAYS_IN_MONTH
+                    .iter()
+                    .map(|days| {
+                        let end = (start + days * 24).min(num_hours);
+                        let period = start..end;
+                        start = end;
+                        period
+                    })
+                    .filter(|period| !period.is_empty())
+                    .collect()
+            }
+        }
+    }
 
-            // Optimization mode
-            optimize_for_autonomy: false,
+    /// Billed peak in kW of an hourly grid import series in W
+    pub fn billed_peak_kw(&self, grid_import: &[f64]) -> f64 {
+        let periods = self.periods(grid_import.len());
+        if periods.is_empty() {
+            return self.min_billed_kw;
+        }
+        let peak_sum: f64 = periods
+            .iter()
+            .map(|period| {
+                let peak = grid_import[period.clone()]
+                    .iter()
+                    .copied()
+                    .fold(0.0, f64::max);
+                (peak / 1000.0).max(self.min_billed_kw)
+            })
+            .sum();
+        peak_sum / periods.len() as f64
+    }
+}
+
+/// Industrial grid contract billing the maximum demand of every month, with a penalty for the
+/// part of the monthly peak above the contracted power. Peaks are the hourly averages.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DemandCharges {
+    pub price_per_kw_month: f64,  // Charge per kW of the monthly import peak
+    pub contracted_kw: f64,       // Contracted grid power
+    pub excess_price_per_kw: f64, // Penalty per kW of the monthly peak above the contracted power
+}
+
+impl DemandCharges {
+    pub fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        for (name, value) in [
+            ("demand charge", self.price_per_kw_month),
+            ("contracted power", self.contracted_kw),
+            ("excess power penalty", self.excess_price_per_kw),
+        ] {
+            if !(value >= 0.0 && value.is_finite()) {
+                return Err(format!("The {} must not be negative, got {}", name, value).into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Demand charge and excess power penalty of a month with a peak of `peak_kw`
+    pub fn monthly_cost(&self, peak_kw: f64) -> (f64, f64) {
+        (
+            peak_kw * self.price_per_kw_month,
+            (peak_kw - self.contracted_kw).max(0.0) * self.excess_price_per_kw,
+        )
+    }
+
+    /// Import peak of every month in kW of an hourly grid import series in W
+    pub fn monthly_peaks_kw(grid_import: &[f64]) -> Vec<f64> {
+        let mut start = 0;
+        DAYS_IN_MONTH
+            .iter()
+            .map(|days| {
+                let end = (start + days * 24).min(grid_import.len());
+                let peak = grid_import[start..end].iter().copied().fold(0.0, f64::max);
+                start = end;
+                peak / 1000.0
+            })
+            .collect()
+    }
+}
+
+/// Demand response event: hours with a critical peak price and/or a mandated limit on the grid
+/// import, e.g. announced by the grid operator a day ahead
+#[derive(Debug, Clone, PartialEq)]
+pub struct DemandResponseEvent {
+    pub start_hour: usize, // Hour of the year (0-8759) the event starts
+    pub duration_hours: usize,
+    pub price_per_kwh: Option<f64>, // Import price during the event; None keeps the electricity rate
+    pub max_grid_import_w: Option<f64>, // Mandated grid import limit during the event; None leaves the import free
+}
+
+impl DemandResponseEvent {
+    /// Critical peak pricing on `day` (0-364) from `start_hour` (0-23) for `duration_hours`
+    pub fn critical_peak(
+        day: usize,
+        start_hour: usize,
+        duration_hours: usize,
+        price_per_kwh: f64,
+    ) -> Self {
+        Self {
+            start_hour: day * 24 + start_hour,
+            duration_hours,
+            price_per_kwh: Some(price_per_kwh),
+            max_grid_import_w: None,
+        }
+    }
+
+    /// Mandated load reduction on `day` (0-364) from `start_hour` (0-23) for `duration_hours`
+    pub fn load_reduction(
+        day: usize,
+        start_hour: usize,
+        duration_hours: usize,
+        max_grid_import_w: f64,
+    ) -> Self {
+        Self {
+            start_hour: day * 24 + start_hour,
+            duration_hours,
+            price_per_kwh: None,
+            max_grid_import_w: Some(max_grid_import_w),
+        }
+    }
+
+    /// Hours of the year covered by the event
+    pub fn hours(&self) -> std::ops::Range<usize> {
+        self.start_hour..self.start_hour + self.duration_hours
+    }
+
+    pub fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.duration_hours == 0 || self.hours().end > 8760 {
+            return Err(format!(
+                "Demand response event of {} hours starting at hour {} must lie within the year",
+                self.duration_hours, self.start_hour
+            )
+            .into());
+        }
+        if let Some(price) = self.price_per_kwh
+            && !price.is_finite()
+        {
+            return Err(format!("Critical peak price must be finite, got {}", price).into());
+        }
+        if let Some(limit) = self.max_grid_import_w
+            && (limit.is_nan() || limit < 0.0)
+        {
+            return Err(format!(
+                "Grid import limit of a load reduction must not be negative, got {} W",
+                limit
+            )
+            .into());
         }
+        Ok(())
     }
 }
 
+/// How the dispatch rode through a demand response event
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DemandResponseEventResult {
+    pub start_hour: usize,
+    pub duration_hours: usize,
+    pub demand_kwh: f64, // Demand during the event, including car charging and the heat pump
+    pub grid_import_kwh: f64,
+    pub battery_discharge_kwh: f64,
+    pub required_battery_kwh: f64, // Battery capacity covering the event without import above the limit (without any import for price-only events)
+}
+
 type DemandData = LazyLock<Mutex<Option<(Vec<f64>, Vec<f64>)>>>;
 
 // PERFORMANCE OPTIMIZATION: Cache loaded data to avoid repeated file I/O
@@ -186,23 +2176,23 @@ pub fn load_solar_radiance_from_csv() -> Vec<f64> {
     let data = match load_csv_data(csv_path) {
         Ok(data) => {
             if data.len() >= 8760 {
-                println!(
+                info!(
                     "Successfully loaded {} solar radiance values from {}",
                     data.len(),
                     csv_path
                 );
                 data[..8760].to_vec() // Take first 8760 hours for annual simulation
             } else {
-                println!(
-                    "Warning: CSV file has only {} values, expected 8760. Using default values.",
+                warn!(
+                    "CSV file has only {} values, expected 8760. Using default values.",
                     data.len()
                 );
                 get_default_solar_radiance()
             }
         }
         Err(e) => {
-            println!(
-                "Warning: Could not load solar radiance from {}: {}. Using default values.",
+            warn!(
+                "Could not load solar radiance from {}: {}. Using default values.",
                 csv_path, e
             );
             get_default_solar_radiance()
@@ -253,7 +2243,12 @@ pub fn load_csv_data(file_path: &str) -> Result<Vec<f64>, Box<dyn std::error::Er
         }
     }
 
-    Ok(solar_data)
+    // 15-minute values are averaged, daily values are spread over the daylight hours
+    Ok(to_hourly(
+        solar_data,
+        Quantity::Rate,
+        &StandardDayProfile::Daylight,
+    ))
 }
 
 /// Get default solar radiance values (fallback)
@@ -278,23 +2273,23 @@ pub fn load_demand_from_csv() -> (Vec<f64>, Vec<f64>) {
     let data = match load_demand_csv_data(csv_path) {
         Ok((hot_water, electricity)) => {
             if hot_water.len() >= 8760 && electricity.len() >= 8760 {
-                println!(
+                info!(
                     "Successfully loaded {} demand values from {}",
                     hot_water.len(),
                     csv_path
                 );
                 (hot_water[..8760].to_vec(), electricity[..8760].to_vec()) // Take first 8760 hours for annual simulation
             } else {
-                println!(
-                    "Warning: CSV file has only {} values, expected 8760. Using default values.",
+                warn!(
+                    "CSV file has only {} values, expected 8760. Using default values.",
                     hot_water.len().min(electricity.len())
                 );
                 get_default_demand()
             }
         }
         Err(e) => {
-            println!(
-                "Warning: Could not load demand data from {}: {}. Using default values.",
+            warn!(
+                "Could not load demand data from {}: {}. Using default values.",
                 csv_path, e
             );
             get_default_demand()
@@ -364,7 +2359,18 @@ pub fn load_demand_csv_data(
         }
     }
 
-    Ok((hot_water_data, electricity_data))
+    Ok((
+        to_hourly(
+            hot_water_data,
+            Quantity::Energy,
+            &StandardDayProfile::Household,
+        ),
+        to_hourly(
+            electricity_data,
+            Quantity::Energy,
+            &StandardDayProfile::Household,
+        ),
+    ))
 }
 
 /// Get default demand values (fallback)
@@ -372,7 +2378,9 @@ fn get_default_demand() -> (Vec<f64>, Vec<f64>) {
     (vec![1.0; 8760], vec![2.0; 8760]) // (hot_water_demand, electricity_demand)
 }
 
-// Struct to hold optimization results for plotting
+/// Results of the former hot water model, which was merged into `run_simple_opt`.
+/// Nothing produces these anymore.
+#[deprecated(note = "use `SimpleOptimizationResults` from `run_simple_opt`")]
 #[derive(Debug, Clone)]
 pub struct OptimizationResults {
     pub pv_energy: Vec<f64>,
@@ -400,35 +2408,93 @@ pub struct SimpleOptimizationResults {
     pub pv_capacity_kw: f64,
     pub grid_capacity_kw: f64,
     pub battery_capacity_kwh: f64,
+    pub wind_capacity_kw: f64,
+    pub solar_thermal_area_m2: f64, // Optimized collector area (only set with solar thermal)
+    pub existing_pv_capacity_kw: f64, // Already installed, not part of pv_capacity_kw
+    pub existing_battery_capacity_kwh: f64, // Already installed, not part of battery_capacity_kwh
+    pub pv_modules: usize,          // New PV modules (only set with discrete sizing)
+    pub battery_modules: usize,     // New battery modules (only set with discrete sizing)
+    pub equipment: EquipmentFeasibility, // Space and sound estimates of the recommendation, checked against the site limits
 
     // Annual totals
     pub annual_pv_production_kwh: f64,
+    pub annual_wind_production_kwh: f64,
     pub annual_grid_energy_kwh: f64,
     pub annual_battery_in_kwh: f64,
     pub annual_battery_out_kwh: f64,
     pub annual_car_charging_kwh: f64,
+    pub annual_heat_pump_kwh: f64,
+    pub annual_defrost_penalty_kwh: f64, // Heat pump consumption caused by the defrost penalty
     pub annual_overproduction_kwh: f64,
+    pub annual_curtailed_kwh: f64, // PV surplus neither used nor exported
     pub annual_electricity_demand_kwh: f64,
+    pub base_demand_annual_wh: f64, // Annual sum of the provided demand profile before scaling
     pub required_car_energy_kwh: f64,
+    pub annual_public_charging_kwh: f64, // Part of the required car energy charged away from home
+    pub annual_public_charging_cost: f64, // Cost of public charging in the config currency
+    pub annual_vehicle_charging_kwh: Vec<f64>, // Home charging per car, in the order of `OptimizationConfig::vehicles`
 
     // Coverage metrics
     pub pv_coverage_percent: f64,
-    pub autarky: f64,
-    pub autarky_without_battery: f64,
+    pub autarky: Ratio,
+    pub autarky_without_battery: Ratio,
 
     // Hourly data for plotting
     pub hourly_pv_production: Vec<f64>,
-    pub hourly_overproduction: Vec<f64>,
+    pub hourly_overproduction: Vec<f64>, // Exported surplus
+    pub hourly_curtailment: Vec<f64>, // Curtailed surplus, only non-zero in hours where exporting doesn't pay
     pub hourly_grid_consumption: Vec<f64>,
     pub hourly_battery_storage: Vec<f64>,
-    pub hourly_car_charging: Vec<f64>,
+    pub hourly_battery_in: Vec<f64>,            // Charging power
+    pub hourly_battery_out: Vec<f64>,           // Discharging power
+    pub hourly_car_charging: Vec<f64>,          // Sum over all cars
+    pub hourly_vehicle_charging: Vec<Vec<f64>>, // Per car, in the order of `OptimizationConfig::vehicles`
+    pub hourly_vehicle_soc: Vec<Vec<f64>>, // State of charge in Wh per car, empty for cars without a departure SOC
+    pub hourly_heat_pump: Vec<f64>,        // Electrical consumption of the heat pump
+    pub hourly_heat_storage: Vec<f64>, // Thermal buffer level, including the SG-Ready boost energy
+    pub hourly_heat_pump_hot_water: Vec<f64>, // Part of `hourly_heat_pump` in hot water mode (only set with domestic hot water)
+    pub hourly_hot_water_storage: Vec<f64>, // Hot water tank level (only set with domestic hot water)
+    pub hourly_solar_thermal_heat: Vec<f64>, // Collector heat fed into the tank (only set with solar thermal)
+    pub hourly_indoor_temperature: Vec<f64>, // Indoor temperature in °C (only set with a building model)
     pub hourly_total_pv_production: Vec<f64>,
+    pub hourly_wind_production: Vec<f64>,
     pub hourly_total_electricity_demand: Vec<f64>,
     pub hourly_electricity_demand_base: Vec<f64>,
 
     // Configuration used
     pub config: OptimizationConfig,
 
+    // Heat pump switching (only set when heat pump dispatch is used)
+    pub heat_pump_starts: usize,
+    pub heat_pump_min_runtime_violations: usize, // Runs and gaps still shorter than the minimum runtime
+    pub legionella_cycle_violations: usize, // Purge hours the buffer misses the purge level (only set with a legionella cycle)
+    pub car_min_power_violations: usize, // Hours in which a car still charges below the wallbox minimum
+
+    // Grid import peaks, from the hourly averages
+    pub peak_grid_import_kw: f64,
+    pub grid_import_p99_kw: f64, // Exceeded in 1 % of the hours (about 88 hours a year)
+    pub grid_connection_overload_hours: usize, // Hours above the grid connection power (only set with a grid connection)
+    pub dimming_events: Vec<DimmingEventResult>, // Operation during the dimming events (only set with controllable load dimming)
+    pub dimming_violation_hours: usize, // Event hours in which a controllable load exceeds the dimmed power
+    pub billed_grid_peak_kw: f64, // Peak billed by the capacity tariff (only set with a capacity tariff)
+    pub annual_capacity_fee: f64, // Capacity tariff fee in the config currency
+    pub monthly_billed_peaks_kw: Vec<f64>, // Import peak of every month (only set with demand charges)
+    pub annual_demand_charge: f64,         // Monthly maximum demand charges in the config currency
+    pub annual_excess_power_penalty: f64,  // Penalties for monthly peaks above the contracted power
+    pub annual_forfeited_feed_in_credit: f64, // Feed-in revenue above the monthly import cost (only set with `monthly_feed_in_credit_cap`)
+
+    // Demand response events (only set with events in the config)
+    pub demand_response_events: Vec<DemandResponseEventResult>,
+    pub demand_response_battery_kwh: f64, // Battery capacity riding through every event, the largest requirement of all events
+
+    // Per-phase peaks (only set when a phase config is used)
+    pub phase_peak_import_kw: [f64; 3],
+    pub phase_peak_feed_in_kw: [f64; 3],
+
+    // Solver outputs
+    pub objective_value: f64, // Objective of the solved model in the config currency, before post-processing of the schedule
+    pub hourly_marginal_price: Vec<f64>, // Price of one more kWh of demand per hour (dual of the energy balance); empty unless run with marginal prices
+
     // Timing information
     pub optimization_duration_ms: u128,
 }
@@ -490,6 +2556,8 @@ pub fn load_cop_data_from_csv(
         }
     }
 
+    let cop_data = to_hourly(cop_data, Quantity::Rate, &StandardDayProfile::Flat);
+
     // Cache the result
     {
         let mut cache = COP_DATA_CACHE.lock().unwrap();
@@ -532,7 +2600,11 @@ pub fn load_heat_demand_profile_from_csv(
         }
     }
 
-    Ok(heat_demand_data)
+    Ok(to_hourly(
+        heat_demand_data,
+        Quantity::Energy,
+        &StandardDayProfile::Flat,
+    ))
 }
 
 /// Get annual heating demand per m² based on building characteristics
@@ -639,6 +2711,53 @@ pub fn calculate_heat_pump_electricity_consumption(
     Ok(electricity_consumption)
 }
 
+/// Part of the house heated to its own setpoint, e.g. the living room at 21 °C and the
+/// bedrooms at 18 °C
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeatingZone {
+    pub name: String,
+    pub area_m2: f64,
+    pub target_temp_c: f64, // Setpoint of the zone all year
+}
+
+impl HeatingZone {
+    pub fn new(name: &str, area_m2: f64, target_temp_c: f64) -> Self {
+        Self {
+            name: name.to_string(),
+            area_m2,
+            target_temp_c,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.area_m2.is_finite() || self.area_m2 <= 0.0 || !self.target_temp_c.is_finite() {
+            return Err(format!(
+                "Heating zone {} needs a positive area and a finite setpoint, got {} m² at {} °C",
+                self.name, self.area_m2, self.target_temp_c
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// Hourly heat demand in kWh of heating zones, the sum of each zone heated to its setpoint.
+/// Heat flowing between the zones is neglected.
+pub fn calculate_zoned_heat_demand(
+    zones: &[HeatingZone],
+    insulation_level: &InsulationLevel,
+) -> Vec<f64> {
+    let mut heat_demand = vec![0.0; 8760];
+    for zone in zones {
+        let zone_demand =
+            calculate_heat_demand(zone.area_m2, insulation_level, &[zone.target_temp_c; 12]);
+        for (total, demand) in heat_demand.iter_mut().zip(zone_demand) {
+            *total += demand;
+        }
+    }
+    heat_demand
+}
+
 /// Calculate hourly heat demand based on house characteristics and desired temperatures
 pub fn calculate_heat_demand(
     house_square_meters: f64,
@@ -0,0 +1,491 @@
+use crate::general::calendar::NUM_HOURS;
+use crate::general::finance::{
+    AfterTaxCashFlows, BusinessTax, ResidualValue, calculate_after_tax_cash_flows,
+    internal_rate_of_return, net_present_value, real_values,
+};
+use crate::general::units::Ratio;
+use crate::simple::simple_opt_re::run_static_simulation;
+use crate::simple::solar_system_utils::{ElectricVehicle, StaticSimulationConfigs};
+
+/// Asset added in a stage of a plan
+#[derive(Debug, Clone, PartialEq)]
+pub enum StagedAsset {
+    Pv {
+        capacity_kw: f64,
+    },
+    Battery {
+        capacity_kwh: f64,
+    },
+    ElectricVehicle {
+        vehicle: ElectricVehicle,
+        investment: f64,
+    }, // Charged evenly over its plug-in window; investment e.g. for the wallbox
+}
+
+impl StagedAsset {
+    /// Hourly charging demand of a car in Wh for one year
+    fn vehicle_demand(vehicle: &ElectricVehicle) -> Vec<f64> {
+        let window_hours = (0..24)
+            .filter(|&hour| vehicle.availability.contains(hour))
+            .count()
+            .max(1);
+        let hourly_wh =
+            vehicle.daily_km * vehicle.efficiency_kwh_per_km * 1000.0 / window_hours as f64;
+        (0..NUM_HOURS)
+            .map(|t| {
+                if window_hours == 24 || vehicle.availability.contains(t % 24) {
+                    hourly_wh
+                } else {
+                    0.0
+                }
+            })
+            .collect()
+    }
+}
+
+/// Asset becoming active at the start of a year of the plan (0 for the first year)
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlanStage {
+    pub year: usize,
+    pub asset: StagedAsset,
+}
+
+/// Investments spread over the years of the multi-year simulation, e.g. PV now, a battery in
+/// year 3 and an electric car in year 5
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StagedPlan {
+    pub stages: Vec<PlanStage>,
+}
+
+impl StagedPlan {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_stage(mut self, year: usize, asset: StagedAsset) -> Self {
+        self.stages.push(PlanStage { year, asset });
+        self
+    }
+
+    /// The same plan with PV and batteries built in the first year; cars keep their years
+    pub fn all_at_once(&self) -> Self {
+        Self {
+            stages: self
+                .stages
+                .iter()
+                .map(|stage| PlanStage {
+                    year: match stage.asset {
+                        StagedAsset::ElectricVehicle { .. } => stage.year,
+                        _ => 0,
+                    },
+                    asset: stage.asset.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Investment costs and discounting of a staged plan
+#[derive(Debug, Clone, PartialEq)]
+pub struct StagedPlanFinance {
+    pub inv_pv: f64,                       // Investment cost for PV per kW
+    pub inv_bat: f64,                      // Investment cost for battery per kWh
+    pub discount_rate: f64,                // Nominal discount rate of the net present value
+    pub electricity_price_increase: f64,   // Nominal electricity price increase per year
+    pub inflation_rate: f64,               // General inflation per year for the real returns
+    pub residual_value: ResidualValue, // Value of each stage's investment at the end of the plan
+    pub business_tax: Option<BusinessTax>, // Corporate tax and depreciation; None for households
+}
+
+/// Yearly energy, cash flows and returns of a staged plan
+#[derive(Debug, Clone, PartialEq)]
+pub struct StagedPlanResults {
+    pub yearly_investment: Vec<f64>,
+    pub yearly_savings: Vec<f64>, // Grid cost saved against buying all electricity, plus feed-in revenue
+    pub yearly_cash_flow: Vec<f64>, // Savings minus investment, the last year with the residual value
+    pub residual_value: f64,        // Value of all stages at the end of the plan
+    pub yearly_grid_import_kwh: Vec<f64>,
+    pub yearly_autarky: Vec<Ratio>,
+    pub net_present_value: f64, // Nominal cash flows at the nominal discount rate
+    pub internal_rate_of_return: Option<f64>, // Nominal; None if the cash flows never change their sign
+    pub real_internal_rate_of_return: Option<f64>, // Of the cash flows deflated by the inflation rate
+    pub after_tax: Option<AfterTaxCashFlows>,      // Only with a business tax
+}
+
+/// Staged plan next to building its PV and batteries at once
+#[derive(Debug, Clone, PartialEq)]
+pub struct StagedPlanComparison {
+    pub staged: StagedPlanResults,
+    pub all_at_once: StagedPlanResults,
+    pub npv_difference: f64, // Net present value of the staged plan minus all at once
+}
+
+/// Active PV capacity in W and battery capacity in Wh at the start of `year`, each stage
+/// degraded by its age, and the charging demand of the active cars
+fn active_assets(
+    plan: &StagedPlan,
+    year: usize,
+    configs: &StaticSimulationConfigs,
+) -> (f64, f64, Vec<f64>) {
+    let mut pv_w = 0.0;
+    let mut battery_wh = 0.0;
+    let mut vehicle_demand = vec![0.0; NUM_HOURS];
+    for stage in plan.stages.iter().filter(|stage| stage.year <= year) {
+        let age = (year - stage.year) as i32;
+        match &stage.asset {
+            StagedAsset::Pv { capacity_kw } => {
+                pv_w += capacity_kw * 1000.0 * (1.0 - configs.pv_degradation).powi(age)
+            }
+            StagedAsset::Battery { capacity_kwh } => {
+                battery_wh += capacity_kwh * 1000.0 * (1.0 - configs.battery_degradation).powi(age)
+            }
+            StagedAsset::ElectricVehicle { vehicle, .. } => {
+                for (total, demand) in vehicle_demand
+                    .iter_mut()
+                    .zip(StagedAsset::vehicle_demand(vehicle))
+                {
+                    *total += demand;
+                }
+            }
+        }
+    }
+    (pv_w, battery_wh, vehicle_demand)
+}
+
+/// Simulates a staged plan over `configs.num_years` years with the static simulation and
+/// computes its cash flows, net present value and internal rate of return.
+///
+/// The years between two stages are simulated together, starting with an empty battery. The
+/// savings are the grid cost of the household and the active cars without PV and battery minus
+/// the grid cost with them, plus the feed-in revenue; the grid costs rise by the electricity
+/// price increase. `configs.electricity_rate` has to be set and both series have to cover
+/// 8760 hours.
+pub fn simulate_staged_plan(
+    plan: &StagedPlan,
+    solar_irradiance: &[f64],
+    electricity_demand: &[f64],
+    configs: &StaticSimulationConfigs,
+    finance: &StagedPlanFinance,
+) -> Result<StagedPlanResults, Box<dyn std::error::Error>> {
+    if configs.electricity_rate.is_none() {
+        return Err("A staged plan needs an electricity rate in the simulation configs".into());
+    }
+    for (name, series) in [
+        ("solar irradiance", solar_irradiance),
+        ("electricity demand", electricity_demand),
+    ] {
+        if series.len() != NUM_HOURS {
+            return Err(format!(
+                "Expected {} hours of {}, got {}",
+                NUM_HOURS,
+                name,
+                series.len()
+            )
+            .into());
+        }
+    }
+    if let Some(stage) = plan
+        .stages
+        .iter()
+        .find(|stage| stage.year >= configs.num_years)
+    {
+        return Err(format!(
+            "Stage in year {} lies outside the {} simulated years",
+            stage.year, configs.num_years
+        )
+        .into());
+    }
+
+    let num_years = configs.num_years;
+    let mut yearly_investment = vec![0.0; num_years];
+    let mut residual_value = 0.0;
+    for stage in &plan.stages {
+        let investment = match &stage.asset {
+            StagedAsset::Pv { capacity_kw } => capacity_kw * finance.inv_pv,
+            StagedAsset::Battery { capacity_kwh } => capacity_kwh * finance.inv_bat,
+            StagedAsset::ElectricVehicle { investment, .. } => *investment,
+        };
+        yearly_investment[stage.year] += investment;
+        residual_value += finance
+            .residual_value
+            .value(investment, (num_years - stage.year) as f64);
+    }
+
+    // years in which the active assets change
+    let mut starts: Vec<usize> = plan.stages.iter().map(|stage| stage.year).collect();
+    starts.push(0);
+    starts.sort_unstable();
+    starts.dedup();
+
+    let mut yearly_savings = Vec::with_capacity(num_years);
+    let mut yearly_grid_import_kwh = Vec::with_capacity(num_years);
+    let mut yearly_autarky = Vec::with_capacity(num_years);
+    for (index, &start) in starts.iter().enumerate() {
+        let end = starts.get(index + 1).copied().unwrap_or(num_years);
+        let (pv_w, battery_wh, vehicle_demand) = active_assets(plan, start, configs);
+        let demand: Vec<f64> = electricity_demand
+            .iter()
+            .zip(&vehicle_demand)
+            .map(|(household, vehicle)| household + vehicle)
+            .collect();
+        let segment_configs = StaticSimulationConfigs {
+            num_years: end - start,
+            initial_soc: 0.0,
+            record_final_year_soc: false,
+            hourly_output_year: None,
+            ..configs.clone()
+        };
+        let with_assets = run_static_simulation(
+            pv_w,
+            battery_wh,
+            solar_irradiance.to_vec(),
+            demand.clone(),
+            segment_configs.clone(),
+        )?;
+        let without_assets =
+            run_static_simulation(0.0, 0.0, solar_irradiance.to_vec(), demand, segment_configs)?;
+
+        let costs = with_assets.yearly_grid_cost.unwrap_or_default();
+        let base_costs = without_assets.yearly_grid_cost.unwrap_or_default();
+        let revenues = with_assets.yearly_feed_in_revenue.unwrap_or_default();
+        for offset in 0..end - start {
+            let escalation =
+                (1.0 + finance.electricity_price_increase).powi((start + offset) as i32);
+            yearly_savings
+                .push((base_costs[offset] - costs[offset]) * escalation + revenues[offset]);
+            yearly_grid_import_kwh.push(with_assets.yearly_grid_import[offset] / 1000.0);
+            yearly_autarky.push(with_assets.yearly_autarky[offset]);
+        }
+    }
+
+    let mut yearly_cash_flow: Vec<f64> = yearly_savings
+        .iter()
+        .zip(&yearly_investment)
+        .map(|(savings, investment)| savings - investment)
+        .collect();
+    if let Some(last) = yearly_cash_flow.last_mut() {
+        *last += residual_value;
+    }
+    let after_tax = finance.business_tax.as_ref().map(|tax| {
+        calculate_after_tax_cash_flows(
+            &yearly_savings,
+            &yearly_investment,
+            residual_value,
+            tax,
+            finance.discount_rate,
+        )
+    });
+    Ok(StagedPlanResults {
+        after_tax,
+        net_present_value: net_present_value(&yearly_cash_flow, finance.discount_rate),
+        internal_rate_of_return: internal_rate_of_return(&yearly_cash_flow),
+        real_internal_rate_of_return: internal_rate_of_return(&real_values(
+            &yearly_cash_flow,
+            finance.inflation_rate,
+        )),
+        yearly_investment,
+        yearly_savings,
+        yearly_cash_flow,
+        residual_value,
+        yearly_grid_import_kwh,
+        yearly_autarky,
+    })
+}
+
+/// Simulates a staged plan and the same plan with all PV and batteries built in the first year
+pub fn compare_staged_with_all_at_once(
+    plan: &StagedPlan,
+    solar_irradiance: &[f64],
+    electricity_demand: &[f64],
+    configs: &StaticSimulationConfigs,
+    finance: &StagedPlanFinance,
+) -> Result<StagedPlanComparison, Box<dyn std::error::Error>> {
+    let staged =
+        simulate_staged_plan(plan, solar_irradiance, electricity_demand, configs, finance)?;
+    let all_at_once = simulate_staged_plan(
+        &plan.all_at_once(),
+        solar_irradiance,
+        electricity_demand,
+        configs,
+        finance,
+    )?;
+    Ok(StagedPlanComparison {
+        npv_difference: staged.net_present_value - all_at_once.net_present_value,
+        staged,
+        all_at_once,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::general::finance::DepreciationSchedule;
+    use crate::simple::solar_system_utils::{
+        ChargingWindow, load_demand_from_csv, load_solar_radiance_from_csv,
+    };
+    use ems_model::building::electricity::ElectricityRate;
+
+    #[test]
+    fn test_staged_plan() {
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+        let configs = StaticSimulationConfigs {
+            num_years: 10,
+            electricity_rate: Some(ElectricityRate::fixed(0.3)),
+            ..Default::default()
+        };
+        let finance = StagedPlanFinance {
+            inv_pv: 1000.0,
+            inv_bat: 500.0,
+            discount_rate: 0.03,
+            electricity_price_increase: 0.02,
+            inflation_rate: 0.02,
+            residual_value: ResidualValue::StraightLine {
+                lifetime_years: 20.0,
+            },
+            business_tax: None,
+        };
+        let vehicle = ElectricVehicle {
+            daily_km: 40.0,
+            efficiency_kwh_per_km: 0.2,
+            availability: ChargingWindow::DAY,
+            ..Default::default()
+        };
+        let plan = StagedPlan::new()
+            .with_stage(0, StagedAsset::Pv { capacity_kw: 5.0 })
+            .with_stage(3, StagedAsset::Battery { capacity_kwh: 5.0 })
+            .with_stage(
+                5,
+                StagedAsset::ElectricVehicle {
+                    vehicle,
+                    investment: 1000.0,
+                },
+            );
+
+        let results = simulate_staged_plan(
+            &plan,
+            &solar_irradiance,
+            &electricity_demand,
+            &configs,
+            &finance,
+        )
+        .unwrap();
+        assert_eq!(results.yearly_investment[0], 5000.0);
+        assert_eq!(results.yearly_investment[3], 2500.0);
+        assert_eq!(results.yearly_investment[5], 1000.0);
+        assert_eq!(results.yearly_savings.len(), 10);
+        // PV at half, battery at 65 % and the wallbox at 75 % of its lifetime
+        assert!((results.residual_value - (2500.0 + 2500.0 * 0.65 + 1000.0 * 0.75)).abs() < 1e-9);
+        assert!(
+            (results.yearly_cash_flow[9] - results.yearly_savings[9] - results.residual_value)
+                .abs()
+                < 1e-9
+        );
+        // the battery raises the autarky, the car's daytime charging uses more PV
+        assert!(results.yearly_autarky[3] > results.yearly_autarky[2]);
+        assert!(results.yearly_savings[5] > results.yearly_savings[4]);
+        assert!(results.yearly_grid_import_kwh[5] > results.yearly_grid_import_kwh[4]);
+        let npv = net_present_value(&results.yearly_cash_flow, 0.03);
+        assert!((results.net_present_value - npv).abs() < 1e-9);
+        if let (Some(nominal), Some(real)) = (
+            results.internal_rate_of_return,
+            results.real_internal_rate_of_return,
+        ) {
+            assert!((1.0 + real - (1.0 + nominal) / 1.02).abs() < 1e-6);
+        }
+
+        let comparison = compare_staged_with_all_at_once(
+            &plan,
+            &solar_irradiance,
+            &electricity_demand,
+            &configs,
+            &finance,
+        )
+        .unwrap();
+        assert_eq!(comparison.staged, results);
+        assert_eq!(comparison.all_at_once.yearly_investment[0], 7500.0);
+        assert_eq!(comparison.all_at_once.yearly_investment[5], 1000.0);
+        assert!(
+            (comparison.npv_difference
+                - (results.net_present_value - comparison.all_at_once.net_present_value))
+                .abs()
+                < 1e-9
+        );
+
+        let business = StagedPlanFinance {
+            business_tax: Some(BusinessTax {
+                corporate_tax_rate: 0.3,
+                depreciation: DepreciationSchedule::StraightLine { years: 20 },
+            }),
+            ..finance.clone()
+        };
+        let after_tax = simulate_staged_plan(
+            &plan,
+            &solar_irradiance,
+            &electricity_demand,
+            &configs,
+            &business,
+        )
+        .unwrap()
+        .after_tax
+        .unwrap();
+        // over the whole plan the investments are written off against the savings
+        let taxable: f64 = after_tax.yearly_taxable_income.iter().sum();
+        let profit = results.yearly_savings.iter().sum::<f64>() + results.residual_value
+            - results.yearly_investment.iter().sum::<f64>();
+        assert!((taxable - profit).abs() < 1e-6);
+        assert!(results.after_tax.is_none());
+    }
+
+    #[test]
+    fn test_staged_plan_edge_cases() {
+        let irradiance = vec![0.5; NUM_HOURS];
+        let demand = vec![500.0; NUM_HOURS];
+        let configs = StaticSimulationConfigs {
+            num_years: 3,
+            electricity_rate: Some(ElectricityRate::fixed(0.3)),
+            ..Default::default()
+        };
+        let finance = StagedPlanFinance {
+            inv_pv: 1000.0,
+            inv_bat: 500.0,
+            discount_rate: 0.03,
+            electricity_price_increase: 0.0,
+            inflation_rate: 0.0,
+            residual_value: ResidualValue::Zero,
+            business_tax: None,
+        };
+        let pv = StagedPlan::new().with_stage(0, StagedAsset::Pv { capacity_kw: 1.0 });
+        let simulate = |plan: &StagedPlan,
+                        irradiance: &[f64],
+                        demand: &[f64],
+                        configs: &StaticSimulationConfigs| {
+            simulate_staged_plan(plan, irradiance, demand, configs, &finance)
+        };
+
+        // an empty plan saves nothing and never pays back
+        let empty = simulate(&StagedPlan::new(), &irradiance, &demand, &configs).unwrap();
+        assert_eq!(empty.yearly_savings, vec![0.0; 3]);
+        assert_eq!(empty.net_present_value, 0.0);
+        assert_eq!(empty.internal_rate_of_return, None);
+        assert_eq!(StagedPlan::new().all_at_once(), StagedPlan::new());
+
+        let too_late = StagedPlan::new().with_stage(3, StagedAsset::Pv { capacity_kw: 1.0 });
+        let err = simulate(&too_late, &irradiance, &demand, &configs).unwrap_err();
+        assert!(err.to_string().contains("year 3"));
+        let no_rate = StaticSimulationConfigs {
+            electricity_rate: None,
+            ..configs.clone()
+        };
+        assert!(simulate(&pv, &irradiance, &demand, &no_rate).is_err());
+        let no_years = StaticSimulationConfigs {
+            num_years: 0,
+            ..configs.clone()
+        };
+        assert!(simulate(&StagedPlan::new(), &irradiance, &demand, &no_years).is_err());
+        let err = simulate(&pv, &irradiance[..24], &demand, &configs).unwrap_err();
+        assert!(err.to_string().contains("solar irradiance"));
+        let err = simulate(&pv, &irradiance, &[], &configs).unwrap_err();
+        assert!(err.to_string().contains("electricity demand"));
+    }
+}
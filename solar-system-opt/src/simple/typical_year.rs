@@ -0,0 +1,343 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+
+use crate::general::calendar::NUM_HOURS;
+use crate::general::calendar::{DAYS_IN_MONTH, MONTHS};
+use crate::general::resampling::{Quantity, StandardDayProfile, to_hourly};
+use crate::simple::weather_years::WeatherYear;
+
+/// Hourly irradiance and outdoor temperature of one measured year
+#[derive(Debug, Clone, PartialEq)]
+pub struct HourlyWeather {
+    pub label: String,
+    pub solar_irradiance: Vec<f64>, // 8760 hourly values, same scale as ts_res.csv
+    pub temperature_c: Vec<f64>,    // 8760 hourly outdoor temperatures in °C
+}
+
+impl HourlyWeather {
+    /// Loads a year from a CSV file in the "Time,Solar,Temperature" format written by `to_csv`.
+    /// 15-minute and daily rows are brought to hourly resolution first (see `to_hourly`). Rows
+    /// beyond 8760 hours (e.g. February 29th at the end of a leap year) are ignored.
+    pub fn from_csv(label: &str, file_path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let reader = BufReader::new(File::open(file_path)?);
+        let mut solar_irradiance = Vec::with_capacity(NUM_HOURS);
+        let mut temperature_c = Vec::with_capacity(NUM_HOURS);
+        for (line_num, line) in reader.lines().enumerate().skip(1) {
+            let line = line?;
+            let parts: Vec<&str> = line.split(',').collect();
+            if parts.len() < 3 {
+                return Err(
+                    format!("Invalid CSV format on line {}: '{}'", line_num + 1, line).into(),
+                );
+            }
+            let value = |index: usize| {
+                parts[index].trim().parse::<f64>().map_err(|_| {
+                    format!(
+                        "Could not parse value on line {}: '{}'",
+                        line_num + 1,
+                        parts[index]
+                    )
+                })
+            };
+            solar_irradiance.push(value(1)?);
+            temperature_c.push(value(2)?);
+        }
+        let weather = Self {
+            label: label.to_string(),
+            solar_irradiance: to_hourly(
+                solar_irradiance,
+                Quantity::Rate,
+                &StandardDayProfile::Daylight,
+            ),
+            temperature_c: to_hourly(temperature_c, Quantity::Rate, &StandardDayProfile::Flat),
+        };
+        weather.validate()?;
+        Ok(Self {
+            solar_irradiance: weather.solar_irradiance[..NUM_HOURS].to_vec(),
+            temperature_c: weather.temperature_c[..NUM_HOURS].to_vec(),
+            ..weather
+        })
+    }
+
+    /// Writes the year as "Time,Solar,Temperature" with the hour starting at 1 like
+    /// `data/ts_res.csv`, so `load_csv_data` and `WeatherYear::from_csv` read its irradiance
+    pub fn to_csv(&self, file_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let mut writer = BufWriter::new(File::create(file_path)?);
+        writeln!(writer, "Time,Solar,Temperature")?;
+        for (hour, (solar, temperature)) in self
+            .solar_irradiance
+            .iter()
+            .zip(&self.temperature_c)
+            .enumerate()
+        {
+            writeln!(writer, "{},{:.4},{:.2}", hour + 1, solar, temperature)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Irradiance only, e.g. for the weather year sensitivity analysis
+    pub fn to_weather_year(&self) -> WeatherYear {
+        WeatherYear {
+            label: self.label.clone(),
+            solar_irradiance: self.solar_irradiance.clone(),
+        }
+    }
+
+    fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        for (name, len) in [
+            ("irradiance", self.solar_irradiance.len()),
+            ("temperature", self.temperature_c.len()),
+        ] {
+            if len < NUM_HOURS {
+                return Err(format!(
+                    "Weather year '{}' has only {} {} values, expected {}",
+                    self.label, len, name, NUM_HOURS
+                )
+                .into());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Weights of the daily indices in the month selection. The defaults follow the Sandia
+/// weights for irradiance and dry bulb temperature, scaled to sum to one without dew point
+/// and wind.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TypicalYearWeights {
+    pub daily_irradiance: f64,
+    pub mean_temperature: f64,
+    pub max_temperature: f64,
+    pub min_temperature: f64,
+}
+
+impl Default for TypicalYearWeights {
+    fn default() -> Self {
+        Self {
+            daily_irradiance: 0.5,
+            mean_temperature: 0.2,
+            max_temperature: 0.15,
+            min_temperature: 0.15,
+        }
+    }
+}
+
+/// Typical year assembled from the most typical calendar month of several years
+#[derive(Debug, Clone)]
+pub struct TypicalYear {
+    pub weather: HourlyWeather,
+    pub selected_years: Vec<String>, // Label of the year each month was taken from, January first
+    pub weighted_statistics: Vec<f64>, // Weighted Finkelstein-Schafer statistic of the chosen months
+}
+
+impl TypicalYear {
+    /// Lists the source year of every month
+    pub fn render(&self) -> String {
+        MONTHS
+            .iter()
+            .zip(&self.selected_years)
+            .zip(&self.weighted_statistics)
+            .map(|((month, year), statistic)| format!("{:<4}{:<10} {:.4}", month, year, statistic))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Daily indices of one month of one year: irradiance sum, mean, max and min temperature
+fn daily_indices(weather: &HourlyWeather, first_day: usize, days: usize) -> [Vec<f64>; 4] {
+    let mut indices: [Vec<f64>; 4] = Default::default();
+    for day in first_day..first_day + days {
+        let hours = day * 24..(day + 1) * 24;
+        let temperatures = &weather.temperature_c[hours.clone()];
+        indices[0].push(weather.solar_irradiance[hours].iter().sum());
+        indices[1].push(temperatures.iter().sum::<f64>() / 24.0);
+        indices[2].push(temperatures.iter().copied().fold(f64::MIN, f64::max));
+        indices[3].push(temperatures.iter().copied().fold(f64::MAX, f64::min));
+    }
+    indices
+}
+
+/// Empirical cumulative distribution of `sorted` at `value`
+fn cdf(sorted: &[f64], value: f64) -> f64 {
+    sorted.partition_point(|&x| x <= value) as f64 / sorted.len() as f64
+}
+
+/// Finkelstein-Schafer statistic: mean absolute difference between the distribution of the
+/// candidate days and the long-term distribution, evaluated at the candidate days
+fn finkelstein_schafer(candidate: &[f64], long_term_sorted: &[f64]) -> f64 {
+    let mut sorted = candidate.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    sorted
+        .iter()
+        .map(|&value| (cdf(&sorted, value) - cdf(long_term_sorted, value)).abs())
+        .sum::<f64>()
+        / sorted.len() as f64
+}
+
+/// Builds a typical meteorological year with the Sandia method: for every calendar month the
+/// year whose daily irradiance and temperature distributions are closest to the long-term
+/// distributions of that month (lowest weighted Finkelstein-Schafer statistic) is chosen, and
+/// the twelve months are concatenated. The persistence check and the smoothing of month
+/// boundaries of the full method are left out.
+pub fn build_typical_year(
+    years: &[HourlyWeather],
+    weights: &TypicalYearWeights,
+) -> Result<TypicalYear, Box<dyn std::error::Error>> {
+    if years.is_empty() {
+        return Err("A typical year needs at least one year of data".into());
+    }
+    for year in years {
+        year.validate()?;
+    }
+    let weights = [
+        weights.daily_irradiance,
+        weights.mean_temperature,
+        weights.max_temperature,
+        weights.min_temperature,
+    ];
+
+    let mut solar_irradiance = Vec::with_capacity(NUM_HOURS);
+    let mut temperature_c = Vec::with_capacity(NUM_HOURS);
+    let mut selected_years = Vec::with_capacity(12);
+    let mut weighted_statistics = Vec::with_capacity(12);
+    let mut first_day = 0;
+    for days in DAYS_IN_MONTH {
+        let candidates: Vec<[Vec<f64>; 4]> = years
+            .iter()
+            .map(|year| daily_indices(year, first_day, days))
+            .collect();
+        let long_term: Vec<Vec<f64>> = (0..4)
+            .map(|index| {
+                let mut values: Vec<f64> = candidates
+                    .iter()
+                    .flat_map(|candidate| candidate[index].iter().copied())
+                    .collect();
+                values.sort_by(f64::total_cmp);
+                values
+            })
+            .collect();
+        let (best, statistic) = candidates
+            .iter()
+            .map(|candidate| {
+                (0..4)
+                    .map(|index| {
+                        weights[index] * finkelstein_schafer(&candidate[index], &long_term[index])
+                    })
+                    .sum::<f64>()
+            })
+            .enumerate()
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap_or((0, 0.0));
+
+        let hours = first_day * 24..(first_day + days) * 24;
+        solar_irradiance.extend_from_slice(&years[best].solar_irradiance[hours.clone()]);
+        temperature_c.extend_from_slice(&years[best].temperature_c[hours]);
+        selected_years.push(years[best].label.clone());
+        weighted_statistics.push(statistic);
+        first_day += days;
+    }
+
+    Ok(TypicalYear {
+        weather: HourlyWeather {
+            label: "TMY".to_string(),
+            solar_irradiance,
+            temperature_c,
+        },
+        selected_years,
+        weighted_statistics,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Year with irradiance and temperature scaled around a common daily pattern
+    fn year(label: &str, scale: f64, offset_c: f64) -> HourlyWeather {
+        let solar_irradiance = (0..NUM_HOURS)
+            .map(|t| {
+                let hour = (t % 24) as f64;
+                // day to day variation, so the daily distributions aren't degenerate
+                let day = (t / 24) as f64;
+                scale
+                    * (1.0 + 0.3 * (day * 0.7).sin())
+                    * (0.1 * (hour - 6.0)).max(0.0)
+                    * f64::from(hour < 18.0)
+            })
+            .collect();
+        let temperature_c = (0..NUM_HOURS)
+            .map(|t| {
+                10.0 + offset_c
+                    + 5.0 * ((t % 24) as f64 / 24.0 * std::f64::consts::TAU).sin()
+                    + ((t / 24) as f64 * 1.3).cos()
+            })
+            .collect();
+        HourlyWeather {
+            label: label.to_string(),
+            solar_irradiance,
+            temperature_c,
+        }
+    }
+
+    #[test]
+    fn test_build_typical_year() {
+        let years = vec![
+            year("2019", 0.6, -3.0),
+            year("2020", 1.0, 0.0),
+            year("2021", 1.4, 3.0),
+        ];
+        let tmy = build_typical_year(&years, &TypicalYearWeights::default()).unwrap();
+        assert_eq!(tmy.weather.solar_irradiance.len(), NUM_HOURS);
+        assert_eq!(tmy.weather.temperature_c.len(), NUM_HOURS);
+        // the middle year is the most typical one in every month
+        assert!(tmy.selected_years.iter().all(|label| label == "2020"));
+        assert_eq!(tmy.weather.solar_irradiance, years[1].solar_irradiance);
+        assert!(tmy.render().starts_with("Jan 2020"));
+
+        // months are taken from different years if the typical one changes
+        let mut mixed = years.clone();
+        // January of 2020 becomes the sunniest and warmest, leaving 2021 in the middle
+        mixed[1].solar_irradiance[..31 * 24]
+            .iter_mut()
+            .for_each(|irradiance| *irradiance *= 2.0);
+        mixed[1].temperature_c[..31 * 24]
+            .iter_mut()
+            .for_each(|temperature| *temperature += 10.0);
+        let tmy = build_typical_year(&mixed, &TypicalYearWeights::default()).unwrap();
+        assert_eq!(tmy.selected_years[0], "2021");
+        assert!(tmy.selected_years[1..].iter().all(|label| label == "2020"));
+        assert_eq!(
+            tmy.weather.temperature_c[..31 * 24],
+            mixed[2].temperature_c[..31 * 24]
+        );
+        assert_eq!(
+            tmy.weather.temperature_c[31 * 24..],
+            mixed[1].temperature_c[31 * 24..]
+        );
+
+        assert!(build_typical_year(&[], &TypicalYearWeights::default()).is_err());
+        let short = HourlyWeather {
+            temperature_c: vec![0.0; 100],
+            ..year("short", 1.0, 0.0)
+        };
+        assert!(build_typical_year(&[short], &TypicalYearWeights::default()).is_err());
+    }
+
+    #[test]
+    fn test_typical_year_csv_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tmy.csv");
+        let path = path.to_str().unwrap();
+        let weather = year("2020", 1.0, 0.0);
+        weather.to_csv(path).unwrap();
+
+        let loaded = HourlyWeather::from_csv("2020", path).unwrap();
+        assert_eq!(loaded.solar_irradiance.len(), NUM_HOURS);
+        assert!((loaded.solar_irradiance[12] - weather.solar_irradiance[12]).abs() < 1e-4);
+        assert!((loaded.temperature_c[15] - weather.temperature_c[15]).abs() < 1e-2);
+        let irradiance = WeatherYear::from_csv("2020", path).unwrap();
+        assert_eq!(irradiance.solar_irradiance, loaded.solar_irradiance);
+        assert_eq!(weather.to_weather_year().label, "2020");
+    }
+}
@@ -0,0 +1,299 @@
+use ems_model::building::electricity::ElectricityRate;
+use good_lp::Solver;
+
+use crate::general::calendar::NUM_HOURS;
+use crate::general::finance::{ROICalculationInput, calculate_optimized_roi};
+use crate::general::units::{Energy, Ratio};
+use crate::simple::simple_opt_re::run_simple_opt;
+use crate::simple::solar_system_utils::{
+    OptimizationConfig, SimpleOptimizationResults, load_csv_data,
+};
+
+/// Hourly irradiance of one weather year (e.g. 2015 or a TMY)
+#[derive(Debug, Clone)]
+pub struct WeatherYear {
+    pub label: String,
+    pub solar_irradiance: Vec<f64>, // 8760 hourly values, same scale as ts_res.csv
+}
+
+impl WeatherYear {
+    /// Loads a weather year from a CSV file in the same "Time,Solar" format as `data/ts_res.csv`
+    pub fn from_csv(label: &str, file_path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let data = load_csv_data(file_path)?;
+        if data.len() < NUM_HOURS {
+            return Err(format!(
+                "Weather year '{}' has only {} values in {}, expected {}",
+                label,
+                data.len(),
+                file_path,
+                NUM_HOURS
+            )
+            .into());
+        }
+        Ok(Self {
+            label: label.to_string(),
+            solar_irradiance: data[..NUM_HOURS].to_vec(),
+        })
+    }
+}
+
+/// How the system is sized across the weather years
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SizingStrategy {
+    /// Size the system separately for every weather year
+    PerYear,
+    /// Size once against the hour-wise average of all years, then evaluate that system in every year
+    #[default]
+    TypicalYear,
+}
+
+#[derive(Debug, Clone)]
+pub struct WeatherYearSensitivityConfig {
+    pub strategy: SizingStrategy,
+    pub roi_years: usize,       // Number of years used for the ROI calculation
+    pub other_yearly_cost: f64, // Yearly maintenance etc. used for the ROI calculation
+}
+
+impl Default for WeatherYearSensitivityConfig {
+    fn default() -> Self {
+        Self {
+            strategy: SizingStrategy::TypicalYear,
+            roi_years: 25,
+            other_yearly_cost: 0.0,
+        }
+    }
+}
+
+/// Minimum, maximum and mean of a KPI across the weather years
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct KpiSpread {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+}
+
+impl KpiSpread {
+    pub fn from_values(values: &[f64]) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+        Self {
+            min: values.iter().copied().fold(f64::INFINITY, f64::min),
+            max: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            mean: values.iter().sum::<f64>() / values.len() as f64,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WeatherYearResult {
+    pub label: String,
+    pub pv_capacity_kw: f64,
+    pub battery_capacity_kwh: f64,
+    pub autarky: Ratio,
+    pub roi: f64,
+    pub results: SimpleOptimizationResults,
+}
+
+#[derive(Debug, Clone)]
+pub struct WeatherYearSensitivityResults {
+    pub strategy: SizingStrategy,
+    pub years: Vec<WeatherYearResult>,
+    pub autarky_spread: KpiSpread, // in %
+    pub roi_spread: KpiSpread,
+    // System sized against the typical year (only for SizingStrategy::TypicalYear)
+    pub typical_year_sizing: Option<SimpleOptimizationResults>,
+}
+
+/// Builds a typical year as the hour-wise mean of all weather years
+pub fn average_weather_years(
+    weather_years: &[WeatherYear],
+) -> Result<Vec<f64>, Box<dyn std::error::Error>> {
+    if weather_years.is_empty() {
+        return Err("At least one weather year is required".into());
+    }
+    if let Some(year) = weather_years
+        .iter()
+        .find(|year| year.solar_irradiance.len() != NUM_HOURS)
+    {
+        return Err(format!(
+            "Weather year '{}' has {} values, expected {}",
+            year.label,
+            year.solar_irradiance.len(),
+            NUM_HOURS
+        )
+        .into());
+    }
+
+    let num_years = weather_years.len() as f64;
+    Ok((0..NUM_HOURS)
+        .map(|t| {
+            weather_years
+                .iter()
+                .map(|year| year.solar_irradiance[t])
+                .sum::<f64>()
+                / num_years
+        })
+        .collect())
+}
+
+/// Runs the optimization against several weather years and reports the spread of autarky and ROI.
+///
+/// With [`SizingStrategy::PerYear`] every year gets its own optimal system. With
+/// [`SizingStrategy::TypicalYear`] the system is sized against the averaged year and then
+/// re-run with fixed capacities in every weather year, which shows how a single installed
+/// system performs in good and bad years.
+pub fn run_weather_year_sensitivity<S: Solver + Clone>(
+    config: OptimizationConfig,
+    pv_cap_w_max: f64,
+    weather_years: &[WeatherYear],
+    electricity_demand: Vec<f64>,
+    electricity_rate: ElectricityRate,
+    sensitivity_config: WeatherYearSensitivityConfig,
+    solver: S,
+) -> Result<WeatherYearSensitivityResults, Box<dyn std::error::Error>> {
+    let typical_year = average_weather_years(weather_years)?;
+
+    let (year_config, year_pv_cap_w_max, typical_year_sizing) = match sensitivity_config.strategy {
+        SizingStrategy::PerYear => (config.clone(), pv_cap_w_max, None),
+        SizingStrategy::TypicalYear => {
+            let sizing = run_simple_opt(
+                config.clone(),
+                pv_cap_w_max,
+                typical_year,
+                electricity_demand.clone(),
+                electricity_rate.clone(),
+                solver.clone(),
+            )?;
+            let fixed_config = OptimizationConfig {
+                pv_fixed: true,
+                bat_fixed: true,
+                bat_value: Energy::from_kwh(sizing.battery_capacity_kwh),
+                ..config.clone()
+            };
+            (fixed_config, sizing.pv_capacity_kw * 1000.0, Some(sizing))
+        }
+    };
+
+    let mut years = Vec::with_capacity(weather_years.len());
+    for weather_year in weather_years {
+        let results = run_simple_opt(
+            year_config.clone(),
+            year_pv_cap_w_max,
+            weather_year.solar_irradiance.clone(),
+            electricity_demand.clone(),
+            electricity_rate.clone(),
+            solver.clone(),
+        )
+        .map_err(|e| {
+            format!(
+                "Optimization for weather year '{}' failed: {}",
+                weather_year.label, e
+            )
+        })?;
+
+        let roi = calculate_optimized_roi(
+            ROICalculationInput::from(results.clone()),
+            sensitivity_config.roi_years,
+            sensitivity_config.other_yearly_cost,
+        )?
+        .roi;
+
+        years.push(WeatherYearResult {
+            label: weather_year.label.clone(),
+            pv_capacity_kw: results.pv_capacity_kw,
+            battery_capacity_kwh: results.battery_capacity_kwh,
+            autarky: results.autarky,
+            roi,
+            results,
+        });
+    }
+
+    let autarky: Vec<f64> = years.iter().map(|year| year.autarky.percent()).collect();
+    let roi: Vec<f64> = years.iter().map(|year| year.roi).collect();
+
+    Ok(WeatherYearSensitivityResults {
+        strategy: sensitivity_config.strategy,
+        autarky_spread: KpiSpread::from_values(&autarky),
+        roi_spread: KpiSpread::from_values(&roi),
+        years,
+        typical_year_sizing,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simple::solar_system_utils::{load_demand_from_csv, load_solar_radiance_from_csv};
+
+    #[test]
+    fn test_average_weather_years() {
+        let years = vec![
+            WeatherYear {
+                label: "a".to_string(),
+                solar_irradiance: vec![0.2; NUM_HOURS],
+            },
+            WeatherYear {
+                label: "b".to_string(),
+                solar_irradiance: vec![0.4; NUM_HOURS],
+            },
+        ];
+        let typical_year = average_weather_years(&years).unwrap();
+        assert!(typical_year.iter().all(|&v| (v - 0.3).abs() < 1e-12));
+
+        assert!(average_weather_years(&[]).is_err());
+        let short_year = WeatherYear {
+            label: "short".to_string(),
+            solar_irradiance: vec![0.0; 10],
+        };
+        assert!(average_weather_years(&[short_year]).is_err());
+    }
+
+    #[test]
+    fn test_run_weather_year_sensitivity_typical_year() {
+        let solar_irradiance = load_solar_radiance_from_csv();
+        let (_hot_water_demand, electricity_demand) = load_demand_from_csv();
+        let weather_years = vec![
+            WeatherYear {
+                label: "dull".to_string(),
+                solar_irradiance: solar_irradiance.iter().map(|&v| v * 0.9).collect(),
+            },
+            WeatherYear {
+                label: "sunny".to_string(),
+                solar_irradiance: solar_irradiance.iter().map(|&v| v * 1.1).collect(),
+            },
+        ];
+        let config = OptimizationConfig {
+            bat_value: Energy::ZERO,
+            ..Default::default()
+        };
+
+        let results = run_weather_year_sensitivity(
+            config,
+            20000.0,
+            &weather_years,
+            electricity_demand,
+            ElectricityRate::fixed(0.3),
+            WeatherYearSensitivityConfig::default(),
+            good_lp::clarabel,
+        )
+        .unwrap();
+
+        let sizing = results.typical_year_sizing.unwrap();
+        assert_eq!(results.years.len(), 2);
+        // the same system is evaluated in both years
+        for year in &results.years {
+            assert!((year.pv_capacity_kw - sizing.pv_capacity_kw).abs() < 1e-3);
+        }
+        assert!(results.years[1].autarky > results.years[0].autarky);
+        assert_eq!(
+            results.autarky_spread.min,
+            results.years[0].autarky.percent()
+        );
+        assert_eq!(
+            results.autarky_spread.max,
+            results.years[1].autarky.percent()
+        );
+        assert!(results.roi_spread.max >= results.roi_spread.min);
+    }
+}